@@ -9,19 +9,30 @@ extern crate rust_bitcoin_coin_selection;
 use bitcoin::consensus::encode::Error as EncodeError;
 use bitcoin::secp256k1::rand::thread_rng;
 use bitcoin::secp256k1::{PublicKey, SecretKey};
+use bitcoin::util::psbt::PartiallySignedTransaction;
 use bitcoin::{
     consensus::Decodable, network::constants::Network, Amount, PrivateKey, Script, Transaction,
     Txid,
 };
 use bitcoin::{Address, OutPoint, TxOut};
+use bitcoincore_rpc::jsonrpc::serde_json;
 use bitcoincore_rpc::{json, Auth, Client, RpcApi};
 use bitcoincore_rpc_json::AddressType;
 use dlc_manager::error::Error as ManagerError;
 use dlc_manager::{Blockchain, Utxo, Wallet};
 use rust_bitcoin_coin_selection::select_coins;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+/// The code returned by Bitcoin Core for unknown RPC methods.
+const RPC_METHOD_NOT_FOUND: i32 = -32601;
 
 pub struct BitcoinCoreProvider {
     pub client: Client,
+    // Transactions found in the blockchain spending watched outpoints, so
+    // that blocks are only scanned once for each of them.
+    spending_txs: Mutex<HashMap<OutPoint, Transaction>>,
 }
 
 #[derive(Debug)]
@@ -92,7 +103,89 @@ impl BitcoinCoreProvider {
         };
         let auth = Auth::UserPass(rpc_user, rpc_password);
         let client = Client::new(&rpc_url, auth)?;
-        Ok(BitcoinCoreProvider { client })
+        Ok(BitcoinCoreProvider::from_client(client))
+    }
+
+    /// Creates a provider using the given RPC client.
+    pub fn from_client(client: Client) -> Self {
+        BitcoinCoreProvider {
+            client,
+            spending_txs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the id of the mempool transaction spending the given outpoint
+    /// if any, using `gettxspendingprevout` on nodes supporting it (Bitcoin
+    /// Core 24.0 and later) and scanning the mempool otherwise.
+    fn get_mempool_spending_txid(&self, outpoint: &OutPoint) -> Result<Option<Txid>, ManagerError> {
+        let args = [serde_json::json!([{
+            "txid": outpoint.txid.to_string(),
+            "vout": outpoint.vout,
+        }])];
+        match self
+            .client
+            .call::<serde_json::Value>("gettxspendingprevout", &args)
+        {
+            Ok(res) => {
+                return res
+                    .get(0)
+                    .and_then(|x| x.get("spendingtxid"))
+                    .and_then(|x| x.as_str())
+                    .map(|x| Txid::from_str(x).map_err(|_| ManagerError::BlockchainError))
+                    .transpose();
+            }
+            Err(bitcoincore_rpc::Error::JsonRpc(bitcoincore_rpc::jsonrpc::Error::Rpc(e)))
+                if e.code == RPC_METHOD_NOT_FOUND => {}
+            Err(e) => return Err(rpc_err_to_manager_err(e)),
+        }
+
+        for txid in self
+            .client
+            .get_raw_mempool()
+            .map_err(rpc_err_to_manager_err)?
+        {
+            let tx = self
+                .client
+                .get_raw_transaction(&txid, None)
+                .map_err(rpc_err_to_manager_err)?;
+            if tx.input.iter().any(|x| &x.previous_output == outpoint) {
+                return Ok(Some(txid));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the transaction spending the given outpoint in the blocks
+    /// following the one including it, looking from the tip.
+    fn get_block_spending_transaction(
+        &self,
+        outpoint: &OutPoint,
+    ) -> Result<Option<Transaction>, ManagerError> {
+        let start_height = match self.get_tx_block_height(&outpoint.txid)? {
+            Some(height) => height,
+            None => return Ok(None),
+        };
+        let tip_height = self.get_block_height()?;
+        for height in (start_height..=tip_height).rev() {
+            let block_hash = self
+                .client
+                .get_block_hash(height)
+                .map_err(rpc_err_to_manager_err)?;
+            let block = self
+                .client
+                .get_block(&block_hash)
+                .map_err(rpc_err_to_manager_err)?;
+            if let Some(tx) = block
+                .txdata
+                .into_iter()
+                .find(|tx| tx.input.iter().any(|x| &x.previous_output == outpoint))
+            {
+                return Ok(Some(tx));
+            }
+        }
+
+        Ok(None)
     }
 }
 
@@ -152,32 +245,49 @@ impl Wallet for BitcoinCoreProvider {
         Ok(pk.key)
     }
 
-    fn sign_tx_input(
+    fn sign_psbt_input(
         &self,
-        tx: &mut Transaction,
+        psbt: &mut PartiallySignedTransaction,
         input_index: usize,
-        tx_out: &TxOut,
-        redeem_script: Option<Script>,
     ) -> Result<(), ManagerError> {
+        let tx = &psbt.global.unsigned_tx;
         let outpoint = &tx.input[input_index].previous_output;
+        let psbt_input = &psbt.inputs[input_index];
+        let tx_out = match (&psbt_input.witness_utxo, &psbt_input.non_witness_utxo) {
+            (Some(tx_out), _) => tx_out.clone(),
+            (None, Some(prev_tx)) => prev_tx
+                .output
+                .get(outpoint.vout as usize)
+                .ok_or(Error::InvalidState)?
+                .clone(),
+            (None, None) => return Err(Error::InvalidState.into()),
+        };
 
+        // Bitcoin Core expects the witness script in place of the redeem
+        // script for P2WSH outputs.
         let input = json::SignRawTransactionInput {
             txid: outpoint.txid,
             vout: outpoint.vout,
             script_pub_key: tx_out.script_pubkey.clone(),
-            redeem_script,
+            redeem_script: psbt_input
+                .witness_script
+                .clone()
+                .or_else(|| psbt_input.redeem_script.clone()),
             amount: Some(Amount::from_sat(tx_out.value)),
         };
 
         let sign_result = self
             .client
-            .sign_raw_transaction_with_wallet(&*tx, Some(&[input]), None)
+            .sign_raw_transaction_with_wallet(tx, Some(&[input]), None)
             .map_err(rpc_err_to_manager_err)?;
         let signed_tx =
             Transaction::consensus_decode(&*sign_result.hex).map_err(enc_err_to_manager_err)?;
 
-        tx.input[input_index].script_sig = signed_tx.input[input_index].script_sig.clone();
-        tx.input[input_index].witness = signed_tx.input[input_index].witness.clone();
+        let signed_input = &signed_tx.input[input_index];
+        if !signed_input.script_sig.is_empty() {
+            psbt.inputs[input_index].final_script_sig = Some(signed_input.script_sig.clone());
+        }
+        psbt.inputs[input_index].final_script_witness = Some(signed_input.witness.clone());
 
         Ok(())
     }
@@ -206,6 +316,8 @@ impl Wallet for BitcoinCoreProvider {
                     },
                     address: x.address.as_ref().ok_or(Error::InvalidState)?.clone(),
                     redeem_script: x.redeem_script.as_ref().unwrap_or(&Script::new()).clone(),
+                    witness_script: x.witness_script.clone(),
+                    max_witness_len: None,
                 }))
             })
             .collect::<Result<Vec<UtxoWrap>, Error>>()?;
@@ -262,6 +374,13 @@ impl Wallet for BitcoinCoreProvider {
             },
         }
     }
+
+    fn unlock_utxos(&self, outpoints: &[OutPoint]) -> Result<(), ManagerError> {
+        self.client
+            .unlock_unspent(outpoints)
+            .map_err(rpc_err_to_manager_err)?;
+        Ok(())
+    }
 }
 
 impl Blockchain for BitcoinCoreProvider {
@@ -289,4 +408,89 @@ impl Blockchain for BitcoinCoreProvider {
 
         Ok(network)
     }
+
+    fn get_block_height(&self) -> Result<u64, ManagerError> {
+        self.client
+            .get_block_count()
+            .map_err(rpc_err_to_manager_err)
+    }
+
+    fn get_median_time_past(&self) -> Result<u64, ManagerError> {
+        Ok(self
+            .client
+            .get_blockchain_info()
+            .map_err(rpc_err_to_manager_err)?
+            .median_time)
+    }
+
+    fn is_in_mempool(&self, txid: &Txid) -> Result<bool, ManagerError> {
+        let mempool = self
+            .client
+            .get_raw_mempool()
+            .map_err(rpc_err_to_manager_err)?;
+        Ok(mempool.contains(txid))
+    }
+
+    fn get_tx_block_height(&self, txid: &Txid) -> Result<Option<u64>, ManagerError> {
+        if self.get_transaction_confirmations(txid)? == 0 {
+            return Ok(None);
+        }
+
+        let block_hash = match self
+            .client
+            .get_transaction(txid, None)
+            .map_err(rpc_err_to_manager_err)?
+            .info
+            .blockhash
+        {
+            Some(block_hash) => block_hash,
+            None => return Ok(None),
+        };
+        let header_info = self
+            .client
+            .get_block_header_info(&block_hash)
+            .map_err(rpc_err_to_manager_err)?;
+
+        Ok(Some(header_info.height as u64))
+    }
+
+    fn get_spending_transaction(
+        &self,
+        outpoint: &OutPoint,
+    ) -> Result<Option<Transaction>, ManagerError> {
+        // Outputs that are unspent, including by mempool transactions, are
+        // reported by gettxout without having to look for their spender.
+        let unspent = self
+            .client
+            .get_tx_out(&outpoint.txid, outpoint.vout, Some(true))
+            .map_err(rpc_err_to_manager_err)?;
+        if unspent.is_some() {
+            // The spending transaction might have been reorged out.
+            self.spending_txs.lock().unwrap().remove(outpoint);
+            return Ok(None);
+        }
+
+        if let Some(tx) = self.spending_txs.lock().unwrap().get(outpoint) {
+            return Ok(Some(tx.clone()));
+        }
+
+        if let Some(txid) = self.get_mempool_spending_txid(outpoint)? {
+            let tx = self
+                .client
+                .get_raw_transaction(&txid, None)
+                .map_err(rpc_err_to_manager_err)?;
+            return Ok(Some(tx));
+        }
+
+        // Only confirmed spends are cached, as mempool transactions can be
+        // replaced.
+        let tx = self.get_block_spending_transaction(outpoint)?;
+        if let Some(tx) = &tx {
+            self.spending_txs
+                .lock()
+                .unwrap()
+                .insert(*outpoint, tx.clone());
+        }
+        Ok(tx)
+    }
 }
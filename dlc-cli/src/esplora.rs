@@ -0,0 +1,229 @@
+//! Blockchain backend using the HTTP API of an
+//! [Esplora](https://github.com/Blockstream/esplora/blob/master/API.md)
+//! server, so that the chain can be monitored without running a full node.
+
+use bitcoin::consensus::encode::{deserialize, serialize_hex};
+use bitcoin::hashes::hex::FromHex;
+use bitcoin::network::constants::Network;
+use bitcoin::{OutPoint, Transaction, Txid};
+use dlc_manager::error::Error;
+use dlc_manager::Blockchain;
+use reqwest::blocking::{Client, Response};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::str::FromStr;
+
+#[derive(Deserialize)]
+struct TxStatus {
+    confirmed: bool,
+    block_height: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct OutputStatus {
+    spent: bool,
+    txid: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BlockInfo {
+    mediantime: u64,
+}
+
+/// Implementation of the [`Blockchain`] trait querying an Esplora server.
+pub struct EsploraBlockchain {
+    client: Client,
+    url: String,
+    network: Network,
+}
+
+impl EsploraBlockchain {
+    /// Creates a backend querying the Esplora API at the given url (e.g.
+    /// `https://blockstream.info/testnet/api`) for the given network.
+    pub fn new(url: &str, network: Network) -> Self {
+        EsploraBlockchain {
+            client: Client::new(),
+            url: url.trim_end_matches('/').to_string(),
+            network,
+        }
+    }
+
+    /// Returns the response to a GET request on the given path, or `None` if
+    /// the requested resource was not found.
+    fn get(&self, path: &str) -> Result<Option<Response>, Error> {
+        let response = self
+            .client
+            .get(format!("{}{}", self.url, path))
+            .send()
+            .map_err(to_io_error)?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            s if s.is_success() => Ok(Some(response)),
+            _ => Err(Error::BlockchainError),
+        }
+    }
+
+    fn get_text(&self, path: &str) -> Result<Option<String>, Error> {
+        match self.get(path)? {
+            Some(response) => Ok(Some(response.text().map_err(to_io_error)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<Option<T>, Error> {
+        match self.get(path)? {
+            Some(response) => Ok(Some(response.json().map_err(to_io_error)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn get_tx_status(&self, txid: &Txid) -> Result<Option<TxStatus>, Error> {
+        self.get_json(&format!("/tx/{}/status", txid))
+    }
+}
+
+impl Blockchain for EsploraBlockchain {
+    fn send_transaction(&self, transaction: &Transaction) -> Result<(), Error> {
+        let response = self
+            .client
+            .post(format!("{}/tx", self.url))
+            .body(serialize_hex(transaction))
+            .send()
+            .map_err(to_io_error)?;
+        if !response.status().is_success() {
+            return Err(Error::BlockchainError);
+        }
+        Ok(())
+    }
+
+    fn get_network(&self) -> Result<Network, Error> {
+        Ok(self.network)
+    }
+
+    fn get_block_height(&self) -> Result<u64, Error> {
+        self.get_text("/blocks/tip/height")?
+            .and_then(|x| x.trim().parse().ok())
+            .ok_or(Error::BlockchainError)
+    }
+
+    fn get_median_time_past(&self) -> Result<u64, Error> {
+        let tip_hash = self
+            .get_text("/blocks/tip/hash")?
+            .ok_or(Error::BlockchainError)?;
+        let block: BlockInfo = self
+            .get_json(&format!("/block/{}", tip_hash.trim()))?
+            .ok_or(Error::BlockchainError)?;
+        Ok(block.mediantime)
+    }
+
+    fn is_in_mempool(&self, txid: &Txid) -> Result<bool, Error> {
+        Ok(self.get_tx_status(txid)?.map_or(false, |x| !x.confirmed))
+    }
+
+    fn get_tx_block_height(&self, txid: &Txid) -> Result<Option<u64>, Error> {
+        Ok(self
+            .get_tx_status(txid)?
+            .filter(|x| x.confirmed)
+            .and_then(|x| x.block_height))
+    }
+
+    fn get_spending_transaction(&self, outpoint: &OutPoint) -> Result<Option<Transaction>, Error> {
+        let status: Option<OutputStatus> =
+            self.get_json(&format!("/tx/{}/outspend/{}", outpoint.txid, outpoint.vout))?;
+        let txid = match status {
+            Some(OutputStatus {
+                spent: true,
+                txid: Some(txid),
+            }) => Txid::from_str(&txid).map_err(|_| Error::BlockchainError)?,
+            _ => return Ok(None),
+        };
+        let hex = self
+            .get_text(&format!("/tx/{}/hex", txid))?
+            .ok_or(Error::BlockchainError)?;
+        let tx = Vec::<u8>::from_hex(hex.trim())
+            .ok()
+            .and_then(|x| deserialize(&x).ok())
+            .ok_or(Error::BlockchainError)?;
+        Ok(Some(tx))
+    }
+}
+
+fn to_io_error(e: reqwest::Error) -> Error {
+    Error::IOError(std::io::Error::new(std::io::ErrorKind::Other, e))
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate mockito;
+    use self::mockito::mock;
+    use super::*;
+
+    const TXID: &str = "4d5cc0e1e1f0f5c2d0bb1c1c4e8b5d3a2c4b1f0e9d8c7b6a5f4e3d2c1b0a9f8e";
+
+    fn get_blockchain() -> EsploraBlockchain {
+        EsploraBlockchain::new(&mockito::server_url(), Network::Regtest)
+    }
+
+    #[test]
+    fn get_block_height_test() {
+        let _m = mock("GET", "/blocks/tip/height").with_body("2100").create();
+
+        assert_eq!(2100, get_blockchain().get_block_height().unwrap());
+    }
+
+    #[test]
+    fn get_median_time_past_test() {
+        let _hash = mock("GET", "/blocks/tip/hash")
+            .with_body("0000000000000000000aa")
+            .create();
+        let _block = mock("GET", "/block/0000000000000000000aa")
+            .with_body(r#"{"id":"0000000000000000000aa","height":2100,"mediantime":1650000000}"#)
+            .create();
+
+        assert_eq!(1650000000, get_blockchain().get_median_time_past().unwrap());
+    }
+
+    #[test]
+    fn tx_status_test() {
+        let txid = Txid::from_str(TXID).unwrap();
+        let path = format!("/tx/{}/status", TXID);
+        let blockchain = get_blockchain();
+
+        {
+            let _m = mock("GET", path.as_str())
+                .with_body(r#"{"confirmed":true,"block_height":2000}"#)
+                .create();
+            assert!(!blockchain.is_in_mempool(&txid).unwrap());
+            assert_eq!(Some(2000), blockchain.get_tx_block_height(&txid).unwrap());
+        }
+        {
+            let _m = mock("GET", path.as_str())
+                .with_body(r#"{"confirmed":false}"#)
+                .create();
+            assert!(blockchain.is_in_mempool(&txid).unwrap());
+            assert_eq!(None, blockchain.get_tx_block_height(&txid).unwrap());
+        }
+        {
+            let _m = mock("GET", path.as_str()).with_status(404).create();
+            assert!(!blockchain.is_in_mempool(&txid).unwrap());
+            assert_eq!(None, blockchain.get_tx_block_height(&txid).unwrap());
+        }
+    }
+
+    #[test]
+    fn unspent_output_test() {
+        let outpoint = OutPoint {
+            txid: Txid::from_str(TXID).unwrap(),
+            vout: 1,
+        };
+        let _m = mock("GET", format!("/tx/{}/outspend/1", TXID).as_str())
+            .with_body(r#"{"spent":false}"#)
+            .create();
+
+        assert!(get_blockchain()
+            .get_spending_transaction(&outpoint)
+            .unwrap()
+            .is_none());
+    }
+}
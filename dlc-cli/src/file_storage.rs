@@ -0,0 +1,569 @@
+//! Storage of contracts in plain files, one per contract, which can be
+//! inspected and backed up without any tool. Contracts are stored under their
+//! id once accepted, and under their temporary id before, prefixed with the
+//! index of their state in [`STATES`]. The history of each contract is
+//! appended to a file named after its temporary id. Contracts and history
+//! entries can be encrypted with a [`StorageEncryptionKey`], the file names
+//! being left in clear.
+
+use bitcoin::hashes::hex::{FromHex, ToHex};
+use dlc_manager::contract::accepted_contract::AcceptedContract;
+use dlc_manager::contract::offered_contract::OfferedContract;
+use dlc_manager::contract::ser::Serializable;
+use dlc_manager::contract::signed_contract::SignedContract;
+use dlc_manager::contract::{
+    ClosedContract, Contract, ContractHistoryEntry, ContractState, FailedAcceptContract,
+    FailedSignContract,
+};
+use dlc_manager::{error::Error, ContractId, Storage, TemporaryContractId};
+use dlc_sled_storage_provider::StorageEncryptionKey;
+use std::fs::{self, OpenOptions};
+use std::io::{Cursor, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// The states of the contracts, whose index prefixes their serialization.
+/// New states must be appended to keep existing files readable.
+const STATES: [ContractState; 10] = [
+    ContractState::Offered,
+    ContractState::Accepted,
+    ContractState::Signed,
+    ContractState::Confirmed,
+    ContractState::Closed,
+    ContractState::FailedAccept,
+    ContractState::FailedSign,
+    ContractState::Refunded,
+    ContractState::Renewed,
+    ContractState::RefundedDueToNoAttestation,
+];
+
+const TMP_EXTENSION: &str = "tmp";
+/// Name of the file in which encrypted storages keep [`KEY_CHECK_VALUE`]
+/// encrypted with their key, so that opening them with a wrong key, or
+/// without key, is detected before reading any contract.
+const KEY_CHECK_FILE: &str = "key_check";
+const KEY_CHECK_VALUE: &[u8] = b"dlc-cli/file_storage";
+
+/// Implementation of the [`Storage`] trait keeping contracts in files.
+pub struct FileStorage {
+    contracts_dir: PathBuf,
+    history_dir: PathBuf,
+    encryption_key: Option<StorageEncryptionKey>,
+}
+
+impl FileStorage {
+    /// Creates a storage keeping its files in the given directory, creating
+    /// it if needed.
+    pub fn new(path: &str) -> Result<Self, Error> {
+        Self::open(path, None)
+    }
+
+    /// Creates a storage encrypting the contract and history files with the
+    /// given key. The storage must always be opened with the same key,
+    /// opening it with another key or opening an unencrypted storage that
+    /// already contains contracts returns an error.
+    pub fn new_encrypted(path: &str, encryption_key: StorageEncryptionKey) -> Result<Self, Error> {
+        Self::open(path, Some(encryption_key))
+    }
+
+    fn open(path: &str, encryption_key: Option<StorageEncryptionKey>) -> Result<Self, Error> {
+        let contracts_dir = Path::new(path).join("contracts");
+        let history_dir = Path::new(path).join("history");
+        fs::create_dir_all(&contracts_dir)?;
+        fs::create_dir_all(&history_dir)?;
+        let storage = FileStorage {
+            contracts_dir,
+            history_dir,
+            encryption_key,
+        };
+        storage.check_encryption_key(&Path::new(path).join(KEY_CHECK_FILE))?;
+        Ok(storage)
+    }
+
+    /// Checks that the storage is opened with the key it was encrypted with,
+    /// or without key if it is not encrypted. The check value is written when
+    /// an empty storage is first opened with a key.
+    fn check_encryption_key(&self, key_check_path: &Path) -> Result<(), Error> {
+        let key_check = match fs::read(key_check_path) {
+            Ok(buf) => Some(buf),
+            Err(e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        match (&self.encryption_key, key_check) {
+            (None, None) => Ok(()),
+            (None, Some(_)) => Err(Error::StorageError("The storage is encrypted".to_string())),
+            (Some(encryption_key), Some(key_check)) => {
+                match encryption_key.decrypt(KEY_CHECK_VALUE, &key_check) {
+                    Ok(value) if value == KEY_CHECK_VALUE => Ok(()),
+                    _ => Err(Error::StorageError("Invalid encryption key".to_string())),
+                }
+            }
+            (Some(encryption_key), None) => {
+                if fs::read_dir(&self.contracts_dir)?.next().is_some()
+                    || fs::read_dir(&self.history_dir)?.next().is_some()
+                {
+                    return Err(Error::StorageError(
+                        "The storage is not encrypted".to_string(),
+                    ));
+                }
+                fs::write(
+                    key_check_path,
+                    encryption_key.encrypt(KEY_CHECK_VALUE, KEY_CHECK_VALUE)?,
+                )?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Encrypts the given value if an encryption key was provided, binding it
+    /// to the key under which it is stored so that files cannot be swapped.
+    fn encrypt(&self, key: &[u8], value: Vec<u8>) -> Result<Vec<u8>, Error> {
+        match &self.encryption_key {
+            Some(encryption_key) => encryption_key.encrypt(key, &value),
+            None => Ok(value),
+        }
+    }
+
+    /// Decrypts a value produced by [`FileStorage::encrypt`] for the given key.
+    fn decrypt(&self, key: &[u8], value: Vec<u8>) -> Result<Vec<u8>, Error> {
+        match &self.encryption_key {
+            Some(encryption_key) => encryption_key.decrypt(key, &value),
+            None => Ok(value),
+        }
+    }
+
+    fn read_contract(&self, key: &[u8]) -> Result<Option<Contract>, Error> {
+        match fs::read(self.contracts_dir.join(key.to_hex())) {
+            Ok(buf) => Ok(Some(deserialize_contract(&self.decrypt(key, buf)?)?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Reads the contract stored in the given file, whose name is the hex
+    /// encoding of the key of the contract.
+    fn read_contract_file(&self, path: &Path) -> Result<Contract, Error> {
+        let key = path
+            .file_name()
+            .and_then(|x| x.to_str())
+            .and_then(|x| Vec::<u8>::from_hex(x).ok())
+            .ok_or_else(|| Error::StorageError(format!("Invalid contract file {:?}", path)))?;
+        deserialize_contract(&self.decrypt(&key, fs::read(path)?)?)
+    }
+
+    /// Writes the contract to a temporary file first and renames it, so that
+    /// an interrupted write does not corrupt the previous version.
+    fn write_contract(&self, key: &[u8], contract: &Contract) -> Result<(), Error> {
+        let path = self.contracts_dir.join(key.to_hex());
+        let tmp_path = path.with_extension(TMP_EXTENSION);
+        fs::write(&tmp_path, self.encrypt(key, serialize_contract(contract)?)?)?;
+        fs::rename(tmp_path, path)?;
+        Ok(())
+    }
+
+    fn remove_contract(&self, key: &[u8]) -> Result<(), Error> {
+        match fs::remove_file(self.contracts_dir.join(key.to_hex())) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn get_contracts_in_state(&self, state: ContractState) -> Result<Vec<Contract>, Error> {
+        Ok(self
+            .get_contracts()?
+            .into_iter()
+            .filter(|x| x.get_state() == state)
+            .collect())
+    }
+}
+
+impl Storage for FileStorage {
+    fn get_contract(&self, id: &ContractId) -> Result<Option<Contract>, Error> {
+        self.read_contract(id.as_ref())
+    }
+
+    fn get_contract_by_temporary_id(
+        &self,
+        temporary_id: &TemporaryContractId,
+    ) -> Result<Option<Contract>, Error> {
+        self.read_contract(temporary_id.as_ref())
+    }
+
+    fn get_contracts(&self) -> Result<Vec<Contract>, Error> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(&self.contracts_dir)? {
+            let path = entry?.path();
+            if path.extension().map_or(true, |x| x != TMP_EXTENSION) {
+                paths.push(path);
+            }
+        }
+        // Sorted so that contracts are always returned in the same order, as
+        // required for paginating them by state.
+        paths.sort();
+        paths.iter().map(|x| self.read_contract_file(x)).collect()
+    }
+
+    fn create_contract(&mut self, contract: &OfferedContract) -> Result<(), Error> {
+        self.write_contract(contract.id.as_ref(), &Contract::Offered(contract.clone()))
+    }
+
+    fn delete_contract(&mut self, id: &ContractId) -> Result<(), Error> {
+        self.remove_contract(id.as_ref())
+    }
+
+    fn delete_contract_by_temporary_id(
+        &mut self,
+        temporary_id: &TemporaryContractId,
+    ) -> Result<(), Error> {
+        self.remove_contract(temporary_id.as_ref())
+    }
+
+    fn update_contract(&mut self, contract: &Contract) -> Result<(), Error> {
+        match contract.get_id() {
+            Some(id) => {
+                self.write_contract(id.as_ref(), contract)?;
+                // Once accepted, contracts are only stored under their id.
+                self.remove_contract(contract.get_temporary_id().as_ref())
+            }
+            None => self.write_contract(contract.get_temporary_id().as_ref(), contract),
+        }
+    }
+
+    fn get_contract_offers(&self) -> Result<Vec<OfferedContract>, Error> {
+        Ok(self
+            .get_contracts_in_state(ContractState::Offered)?
+            .into_iter()
+            .filter_map(|x| match x {
+                Contract::Offered(o) => Some(o),
+                _ => None,
+            })
+            .collect())
+    }
+
+    fn get_signed_contracts(&self) -> Result<Vec<SignedContract>, Error> {
+        Ok(self
+            .get_contracts_in_state(ContractState::Signed)?
+            .into_iter()
+            .filter_map(|x| match x {
+                Contract::Signed(s) => Some(s),
+                _ => None,
+            })
+            .collect())
+    }
+
+    fn get_confirmed_contracts(&self) -> Result<Vec<SignedContract>, Error> {
+        Ok(self
+            .get_contracts_in_state(ContractState::Confirmed)?
+            .into_iter()
+            .filter_map(|x| match x {
+                Contract::Confirmed(s) => Some(s),
+                _ => None,
+            })
+            .collect())
+    }
+
+    fn add_contract_history_entry(
+        &mut self,
+        temporary_id: &TemporaryContractId,
+        entry: &ContractHistoryEntry,
+    ) -> Result<(), Error> {
+        let serialized = self.encrypt(temporary_id.as_ref(), entry.serialize()?)?;
+        // Entries are prefixed by their length so that they can be read back
+        // from the file in which they are appended.
+        let mut buf = (serialized.len() as u32).to_be_bytes().to_vec();
+        buf.extend(serialized);
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.history_dir.join(temporary_id.as_ref().to_hex()))?
+            .write_all(&buf)?;
+        Ok(())
+    }
+
+    fn get_contract_history(
+        &self,
+        temporary_id: &TemporaryContractId,
+    ) -> Result<Vec<ContractHistoryEntry>, Error> {
+        let buf = match fs::read(self.history_dir.join(temporary_id.as_ref().to_hex())) {
+            Ok(buf) => buf,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut cursor = Cursor::new(&buf);
+        let mut entries = Vec::new();
+        while (cursor.position() as usize) < buf.len() {
+            let mut len = [0u8; 4];
+            cursor.read_exact(&mut len)?;
+            let mut serialized = vec![0u8; u32::from_be_bytes(len) as usize];
+            cursor.read_exact(&mut serialized)?;
+            let serialized = self.decrypt(temporary_id.as_ref(), serialized)?;
+            entries.push(
+                ContractHistoryEntry::deserialize(&mut Cursor::new(&serialized))
+                    .map_err(to_storage_error)?,
+            );
+        }
+        Ok(entries)
+    }
+}
+
+fn to_storage_error<T: std::fmt::Debug>(e: T) -> Error {
+    Error::StorageError(format!("{:?}", e))
+}
+
+fn serialize_contract(contract: &Contract) -> Result<Vec<u8>, Error> {
+    let serialized = match contract {
+        Contract::Offered(o) => o.serialize(),
+        Contract::Accepted(o) => o.serialize(),
+        Contract::Signed(o)
+        | Contract::Confirmed(o)
+        | Contract::Refunded(o)
+        | Contract::RefundedDueToNoAttestation(o)
+        | Contract::Renewed(o) => o.serialize(),
+        Contract::FailedAccept(c) => c.serialize(),
+        Contract::FailedSign(c) => c.serialize(),
+        Contract::Closed(c) => c.serialize(),
+    }?;
+    let state = STATES
+        .iter()
+        .position(|x| *x == contract.get_state())
+        .expect("all states to be listed");
+    let mut res = Vec::with_capacity(serialized.len() + 1);
+    res.push(state as u8);
+    res.extend(serialized);
+    Ok(res)
+}
+
+fn deserialize_contract(buf: &[u8]) -> Result<Contract, Error> {
+    let (state, serialized) = match buf.split_first() {
+        Some((state, serialized)) => (*state as usize, serialized),
+        None => return Err(Error::StorageError("Empty contract file".to_string())),
+    };
+    let state = STATES
+        .get(state)
+        .ok_or_else(|| Error::StorageError(format!("Unknown contract state {}", state)))?;
+    let mut cursor = Cursor::new(serialized);
+    let contract = match state {
+        ContractState::Offered => {
+            Contract::Offered(OfferedContract::deserialize(&mut cursor).map_err(to_storage_error)?)
+        }
+        ContractState::Accepted => Contract::Accepted(
+            AcceptedContract::deserialize(&mut cursor).map_err(to_storage_error)?,
+        ),
+        ContractState::Signed => {
+            Contract::Signed(SignedContract::deserialize(&mut cursor).map_err(to_storage_error)?)
+        }
+        ContractState::Confirmed => {
+            Contract::Confirmed(SignedContract::deserialize(&mut cursor).map_err(to_storage_error)?)
+        }
+        ContractState::Closed => {
+            Contract::Closed(ClosedContract::deserialize(&mut cursor).map_err(to_storage_error)?)
+        }
+        ContractState::FailedAccept => Contract::FailedAccept(
+            FailedAcceptContract::deserialize(&mut cursor).map_err(to_storage_error)?,
+        ),
+        ContractState::FailedSign => Contract::FailedSign(
+            FailedSignContract::deserialize(&mut cursor).map_err(to_storage_error)?,
+        ),
+        ContractState::Refunded => {
+            Contract::Refunded(SignedContract::deserialize(&mut cursor).map_err(to_storage_error)?)
+        }
+        ContractState::Renewed => {
+            Contract::Renewed(SignedContract::deserialize(&mut cursor).map_err(to_storage_error)?)
+        }
+        ContractState::RefundedDueToNoAttestation => Contract::RefundedDueToNoAttestation(
+            SignedContract::deserialize(&mut cursor).map_err(to_storage_error)?,
+        ),
+    };
+    Ok(contract)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_storage(name: &str) -> (String, FileStorage) {
+        let path = format!("test_files/file_storage/{}", name);
+        let _ = fs::remove_dir_all(&path);
+        let storage = FileStorage::new(&path).expect("Error creating storage");
+        (path, storage)
+    }
+
+    fn deserialize<T: Serializable>(serialized: &[u8]) -> T {
+        T::deserialize(&mut Cursor::new(serialized)).unwrap()
+    }
+
+    #[test]
+    fn contract_lifecycle_test() {
+        let (path, mut storage) = get_storage("contract_lifecycle_test");
+        let offered: OfferedContract = deserialize(include_bytes!(
+            "../../dlc-sled-storage-provider/test_files/Offered"
+        ));
+        let accepted: AcceptedContract = deserialize(include_bytes!(
+            "../../dlc-sled-storage-provider/test_files/Accepted"
+        ));
+        let temporary_id = offered.id;
+        let contract_id = accepted.get_contract_id();
+
+        storage
+            .create_contract(&offered)
+            .expect("Error creating contract");
+        assert_eq!(1, storage.get_contract_offers().unwrap().len());
+        match storage.get_contract_by_temporary_id(&temporary_id).unwrap() {
+            Some(Contract::Offered(o)) => {
+                assert_eq!(offered.serialize().unwrap(), o.serialize().unwrap())
+            }
+            _ => panic!("Expected offered contract"),
+        }
+
+        storage
+            .update_contract(&Contract::Accepted(accepted))
+            .expect("Error updating contract");
+        assert!(storage.get_contract_offers().unwrap().is_empty());
+        assert!(matches!(
+            storage.get_contract(&contract_id).unwrap(),
+            Some(Contract::Accepted(_))
+        ));
+        assert_eq!(1, storage.get_contracts().unwrap().len());
+
+        storage
+            .delete_contract(&contract_id)
+            .expect("Error deleting contract");
+        assert!(storage.get_contracts().unwrap().is_empty());
+
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn contracts_are_filtered_by_state_test() {
+        let (path, mut storage) = get_storage("contracts_are_filtered_by_state_test");
+        let signed: SignedContract = deserialize(include_bytes!(
+            "../../dlc-sled-storage-provider/test_files/Signed"
+        ));
+        let confirmed: SignedContract = deserialize(include_bytes!(
+            "../../dlc-sled-storage-provider/test_files/Confirmed"
+        ));
+
+        storage
+            .update_contract(&Contract::Signed(signed))
+            .expect("Error updating contract");
+        storage
+            .update_contract(&Contract::Confirmed(confirmed))
+            .expect("Error updating contract");
+
+        assert_eq!(1, storage.get_signed_contracts().unwrap().len());
+        assert_eq!(1, storage.get_confirmed_contracts().unwrap().len());
+        assert_eq!(
+            1,
+            storage
+                .count_contracts_by_state(ContractState::Confirmed)
+                .unwrap()
+        );
+
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn history_is_returned_in_insertion_order_test() {
+        let (path, mut storage) = get_storage("history_is_returned_in_insertion_order_test");
+        let temporary_id = TemporaryContractId([1u8; 32]);
+        let entries: Vec<_> = (0..3)
+            .map(|i| ContractHistoryEntry {
+                timestamp: i,
+                state: format!("State{}", i),
+                txid: None,
+                message_hash: Some([i as u8; 32]),
+            })
+            .collect();
+
+        assert!(storage
+            .get_contract_history(&temporary_id)
+            .unwrap()
+            .is_empty());
+        for entry in &entries {
+            storage
+                .add_contract_history_entry(&temporary_id, entry)
+                .expect("Error adding history entry");
+        }
+
+        assert_eq!(
+            entries,
+            storage.get_contract_history(&temporary_id).unwrap()
+        );
+
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn encrypted_storage_test() {
+        let path = "test_files/file_storage/encrypted_storage_test";
+        let _ = fs::remove_dir_all(path);
+        let encryption_key = StorageEncryptionKey::from_seed(&[1u8; 32]);
+        let mut storage = FileStorage::new_encrypted(path, encryption_key.clone())
+            .expect("Error creating storage");
+        let offered: OfferedContract = deserialize(include_bytes!(
+            "../../dlc-sled-storage-provider/test_files/Offered"
+        ));
+        let temporary_id = offered.id;
+        let entry = ContractHistoryEntry {
+            timestamp: 1,
+            state: "Offered".to_string(),
+            txid: None,
+            message_hash: None,
+        };
+
+        storage
+            .create_contract(&offered)
+            .expect("Error creating contract");
+        storage
+            .add_contract_history_entry(&temporary_id, &entry)
+            .expect("Error adding history entry");
+
+        // The contract details are not readable from the file.
+        let plain = serialize_contract(&Contract::Offered(offered.clone())).unwrap();
+        let file = fs::read(
+            Path::new(path)
+                .join("contracts")
+                .join(temporary_id.as_ref().to_hex()),
+        )
+        .unwrap();
+        assert!(!file.windows(plain.len()).any(|x| x == plain.as_slice()));
+        assert!(deserialize_contract(&file).is_err());
+
+        let storage =
+            FileStorage::new_encrypted(path, encryption_key).expect("Error opening storage");
+        match storage.get_contract_by_temporary_id(&temporary_id).unwrap() {
+            Some(Contract::Offered(o)) => {
+                assert_eq!(offered.serialize().unwrap(), o.serialize().unwrap())
+            }
+            _ => panic!("Expected offered contract"),
+        }
+        assert_eq!(1, storage.get_contracts().unwrap().len());
+        assert_eq!(
+            vec![entry],
+            storage.get_contract_history(&temporary_id).unwrap()
+        );
+
+        assert!(
+            FileStorage::new_encrypted(path, StorageEncryptionKey::from_seed(&[2u8; 32])).is_err()
+        );
+        assert!(FileStorage::new(path).is_err());
+
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn unencrypted_storage_cannot_be_opened_with_key_test() {
+        let (path, mut storage) = get_storage("unencrypted_storage_cannot_be_opened_with_key_test");
+        let offered: OfferedContract = deserialize(include_bytes!(
+            "../../dlc-sled-storage-provider/test_files/Offered"
+        ));
+        storage
+            .create_contract(&offered)
+            .expect("Error creating contract");
+
+        assert!(
+            FileStorage::new_encrypted(&path, StorageEncryptionKey::from_seed(&[1u8; 32])).is_err()
+        );
+
+        fs::remove_dir_all(path).unwrap();
+    }
+}
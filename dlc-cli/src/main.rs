@@ -0,0 +1,382 @@
+//! Command line tool wrapping the DLC manager, exchanging DLC messages with the
+//! counter party through files instead of a network connection. Each
+//! invocation processes a single step of the contract lifecycle, which makes
+//! it usable both as a reference integration and to test interoperability
+//! with other implementations.
+
+mod esplora;
+mod file_storage;
+
+use bitcoin::secp256k1::rand::thread_rng;
+use bitcoin::secp256k1::{PublicKey, Secp256k1, SecretKey};
+use bitcoin_rpc_provider::BitcoinCoreProvider;
+use dlc_manager::contract::contract_input::ContractInput;
+use dlc_manager::contract::ContractState;
+use dlc_manager::{Blockchain, ContractId, Oracle, Storage, SystemTimeProvider};
+use dlc_messages::oracle_msgs::OracleAttestation;
+use dlc_messages::Message;
+use dlc_sled_storage_provider::{SledStorageProvider, StorageEncryptionKey};
+use esplora::EsploraBlockchain;
+use file_storage::FileStorage;
+use lightning::ln::wire::Type;
+use lightning::util::ser::Writeable;
+use p2pd_oracle_client::P2PDOracleClient;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::Cursor;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Salt used to derive the storage encryption key from the passphrase.
+const STORAGE_SALT: &[u8] = b"dlc-cli/storage";
+
+type DlcManager = dlc_manager::manager::Manager<
+    Arc<BitcoinCoreProvider>,
+    Arc<dyn Blockchain>,
+    Box<dyn Storage>,
+    Box<P2PDOracleClient>,
+    Arc<SystemTimeProvider>,
+>;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BitcoindInfo {
+    rpc_username: String,
+    rpc_password: String,
+    rpc_port: u16,
+    rpc_host: String,
+    wallet: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OracleConfig {
+    host: String,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum StorageType {
+    Sled,
+    File,
+}
+
+impl Default for StorageType {
+    fn default() -> Self {
+        StorageType::Sled
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Configuration {
+    bitcoin_info: BitcoindInfo,
+    /// The url of the Esplora API to monitor the blockchain with instead of
+    /// bitcoind, which is then only used as wallet.
+    esplora_url: Option<String>,
+    storage_dir_path: String,
+    #[serde(default)]
+    storage_type: StorageType,
+    /// The passphrase from which the key encrypting the stored contracts is
+    /// derived, contracts being stored in clear if not set.
+    storage_passphrase: Option<String>,
+    oracle_config: OracleConfig,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() < 2 {
+        usage();
+        std::process::exit(1);
+    }
+
+    if let Err(e) = run(&args[0], &args[1], &args[2..]) {
+        eprintln!("ERROR: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn usage() {
+    println!("Usage: dlc-cli <config_path> <command> [arguments]");
+    println!();
+    println!("Commands:");
+    println!("  pubkey");
+    println!("  offer <contract_input_json> <counter_party_pubkey> <offer_output_path>");
+    println!("  accept <offer_path> <counter_party_pubkey> <accept_output_path>");
+    println!("  sign <accept_path> <counter_party_pubkey> <sign_output_path>");
+    println!("  finalize <sign_path> <counter_party_pubkey>");
+    println!("  list");
+    println!("  check");
+    println!("  close <contract_id> [attestation_json]");
+}
+
+fn run(config_path: &str, command: &str, args: &[String]) -> Result<(), String> {
+    let config_file = fs::read_to_string(config_path).map_err(|e| e.to_string())?;
+    let config: Configuration = serde_yaml::from_str(&config_file).map_err(|e| e.to_string())?;
+    fs::create_dir_all(&config.storage_dir_path).map_err(|e| e.to_string())?;
+
+    if command == "pubkey" {
+        expect_args(args, 0)?;
+        println!("{}", get_public_key(&config.storage_dir_path)?);
+        return Ok(());
+    }
+
+    let dlc_manager = create_manager(config)?;
+
+    match command {
+        "offer" => {
+            expect_args(args, 3)?;
+            let contract_input_str = fs::read_to_string(&args[0]).map_err(|e| e.to_string())?;
+            let contract_input: ContractInput =
+                serde_json::from_str(&contract_input_str).map_err(|e| e.to_string())?;
+            let offer = dlc_manager
+                .send_offer(&contract_input, parse_public_key(&args[1])?)
+                .map_err(|e| e.to_string())?;
+            let temporary_id = offer
+                .get_temporary_contract_id()
+                .map_err(|e| e.to_string())?;
+            println!("Offered contract: {}", temporary_id);
+            write_message(&args[2], &Message::Offer(offer))
+        }
+        "accept" => {
+            expect_args(args, 3)?;
+            let offer = match read_message(&args[0])? {
+                Message::Offer(offer) => offer,
+                _ => return Err("Expected an offer message.".to_string()),
+            };
+            let temporary_id = offer
+                .get_temporary_contract_id()
+                .map_err(|e| e.to_string())?;
+            dlc_manager
+                .on_dlc_message(&Message::Offer(offer), parse_public_key(&args[1])?)
+                .map_err(|e| e.to_string())?;
+            let (contract_id, _, accept) = dlc_manager
+                .accept_contract_offer(&temporary_id)
+                .map_err(|e| e.to_string())?;
+            println!("Accepted contract: {}", contract_id);
+            write_message(&args[2], &Message::Accept(accept))
+        }
+        "sign" => {
+            expect_args(args, 3)?;
+            let accept = match read_message(&args[0])? {
+                Message::Accept(accept) => accept,
+                _ => return Err("Expected an accept message.".to_string()),
+            };
+            let sign = dlc_manager
+                .on_dlc_message(&Message::Accept(accept), parse_public_key(&args[1])?)
+                .map_err(|e| e.to_string())?;
+            match sign {
+                Some(sign @ Message::Sign(_)) => write_message(&args[2], &sign),
+                _ => Err("Could not sign contract.".to_string()),
+            }
+        }
+        "finalize" => {
+            expect_args(args, 2)?;
+            let sign = match read_message(&args[0])? {
+                Message::Sign(sign) => sign,
+                _ => return Err("Expected a sign message.".to_string()),
+            };
+            dlc_manager
+                .on_dlc_message(&Message::Sign(sign), parse_public_key(&args[1])?)
+                .map_err(|e| e.to_string())?;
+            println!("Fund transaction broadcast.");
+            Ok(())
+        }
+        "list" => {
+            expect_args(args, 0)?;
+            print_contracts(&dlc_manager)
+        }
+        "check" => {
+            expect_args(args, 0)?;
+            dlc_manager.periodic_check().map_err(|e| e.to_string())?;
+            print_contracts(&dlc_manager)
+        }
+        "close" => {
+            if args.is_empty() || args.len() > 2 {
+                return Err(format!("Expected 1 or 2 arguments but got {}.", args.len()));
+            }
+            let contract_id =
+                ContractId::from_str(&args[0]).map_err(|_| "Invalid contract id.".to_string())?;
+            close_contract(&dlc_manager, &contract_id, args.get(1))
+        }
+        _ => {
+            usage();
+            Err(format!("Unknown command `{}`.", command))
+        }
+    }
+}
+
+fn create_manager(config: Configuration) -> Result<DlcManager, String> {
+    let bitcoind_provider = Arc::new(
+        BitcoinCoreProvider::new(
+            config.bitcoin_info.rpc_host,
+            config.bitcoin_info.rpc_port,
+            config.bitcoin_info.wallet,
+            config.bitcoin_info.rpc_username,
+            config.bitcoin_info.rpc_password,
+        )
+        .map_err(|e| e.to_string())?,
+    );
+
+    let oracle = P2PDOracleClient::new(&config.oracle_config.host).map_err(|e| e.to_string())?;
+    let mut oracles = HashMap::new();
+    oracles.insert(oracle.get_public_key(), Box::new(oracle));
+
+    let db_path = format!("{}/db", config.storage_dir_path);
+    let encryption_key = config
+        .storage_passphrase
+        .as_ref()
+        .map(|x| StorageEncryptionKey::from_passphrase(x, STORAGE_SALT));
+    let storage: Box<dyn Storage> = match (config.storage_type, encryption_key) {
+        (StorageType::Sled, None) => {
+            Box::new(SledStorageProvider::new(&db_path).map_err(|e| e.to_string())?)
+        }
+        (StorageType::Sled, Some(key)) => {
+            Box::new(SledStorageProvider::new_encrypted(&db_path, key).map_err(|e| e.to_string())?)
+        }
+        (StorageType::File, None) => {
+            Box::new(FileStorage::new(&db_path).map_err(|e| e.to_string())?)
+        }
+        (StorageType::File, Some(key)) => {
+            Box::new(FileStorage::new_encrypted(&db_path, key).map_err(|e| e.to_string())?)
+        }
+    };
+
+    let blockchain: Arc<dyn Blockchain> = match &config.esplora_url {
+        Some(url) => {
+            let network = bitcoind_provider.get_network().map_err(|e| e.to_string())?;
+            Arc::new(EsploraBlockchain::new(url, network))
+        }
+        None => bitcoind_provider.clone(),
+    };
+
+    Ok(dlc_manager::manager::Manager::new(
+        bitcoind_provider,
+        blockchain,
+        storage,
+        oracles,
+        Arc::new(SystemTimeProvider {}),
+    ))
+}
+
+/// Closes the confirmed contract with the given id, using the attestation
+/// read from the given file if provided, or the one of the oracle otherwise.
+fn close_contract(
+    dlc_manager: &DlcManager,
+    contract_id: &ContractId,
+    attestation_path: Option<&String>,
+) -> Result<(), String> {
+    if let Some(path) = attestation_path {
+        let attestation_str = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let attestation: OracleAttestation =
+            serde_json::from_str(&attestation_str).map_err(|e| e.to_string())?;
+        dlc_manager
+            .provide_attestation(contract_id, attestation)
+            .map_err(|e| e.to_string())?;
+    }
+    dlc_manager.periodic_check().map_err(|e| e.to_string())?;
+    let contract = dlc_manager
+        .get_store()
+        .get_contract(contract_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Unknown contract id.".to_string())?;
+    match contract.get_state() {
+        ContractState::Closed
+        | ContractState::Refunded
+        | ContractState::RefundedDueToNoAttestation => {
+            println!("{} contract: {}", contract.get_state_name(), contract_id);
+            Ok(())
+        }
+        ContractState::Signed => {
+            Err("The fund transaction of the contract is not confirmed yet.".to_string())
+        }
+        ContractState::Confirmed => Err(
+            "The contract has not matured yet or the oracle attestation is not available."
+                .to_string(),
+        ),
+        _ => Err(format!(
+            "Cannot close a contract in {} state.",
+            contract.get_state_name()
+        )),
+    }
+}
+
+/// Returns the public key identifying this node, generating its secret key if
+/// needed. The secret key is only used as an identifier and stored in
+/// plaintext.
+fn get_public_key(storage_dir_path: &str) -> Result<PublicKey, String> {
+    let sk_path = format!("{}/secret_key", storage_dir_path);
+    let sk = if fs::metadata(&sk_path).is_ok() {
+        let sk_str = fs::read_to_string(&sk_path).map_err(|e| e.to_string())?;
+        SecretKey::from_str(sk_str.trim()).map_err(|e| e.to_string())?
+    } else {
+        let sk = SecretKey::new(&mut thread_rng());
+        fs::write(&sk_path, sk.to_string()).map_err(|e| e.to_string())?;
+        sk
+    };
+    Ok(PublicKey::from_secret_key(&Secp256k1::new(), &sk))
+}
+
+fn print_contracts(dlc_manager: &DlcManager) -> Result<(), String> {
+    let contracts = dlc_manager
+        .get_store()
+        .get_contracts()
+        .map_err(|e| e.to_string())?;
+    for contract in contracts {
+        let id = match contract.get_id() {
+            Some(id) => id.to_string(),
+            None => contract.get_temporary_id().to_string(),
+        };
+        println!("{} contract: {}", contract.get_state_name(), id);
+    }
+    Ok(())
+}
+
+fn expect_args(args: &[String], nb_args: usize) -> Result<(), String> {
+    if args.len() != nb_args {
+        return Err(format!(
+            "Expected {} arguments but got {}.",
+            nb_args,
+            args.len()
+        ));
+    }
+    Ok(())
+}
+
+fn parse_public_key(public_key: &str) -> Result<PublicKey, String> {
+    PublicKey::from_str(public_key).map_err(|e| format!("Invalid public key: {}", e))
+}
+
+/// Writes the message to the given path, prefixed with its type as in the
+/// wire encoding used by peers.
+fn write_message(path: &str, msg: &Message) -> Result<(), String> {
+    let mut buf = msg.type_id().to_be_bytes().to_vec();
+    buf.extend(msg.encode());
+    fs::write(path, buf).map_err(|e| e.to_string())
+}
+
+fn read_message(path: &str) -> Result<Message, String> {
+    let buf = fs::read(path).map_err(|e| e.to_string())?;
+    dlc_messages::read_message(&mut Cursor::new(buf))
+        .map_err(|e| format!("Error decoding message: {:?}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_configuration_test() {
+        let config: Configuration =
+            serde_yaml::from_str(include_str!("../examples/alice.yml")).unwrap();
+        assert_eq!(StorageType::Sled, config.storage_type);
+        assert!(config.esplora_url.is_none());
+
+        let config: Configuration =
+            serde_yaml::from_str(include_str!("../examples/bob_esplora.yml")).unwrap();
+        assert_eq!(StorageType::File, config.storage_type);
+        assert_eq!(Some("http://localhost:3000"), config.esplora_url.as_deref());
+    }
+}
@@ -0,0 +1,220 @@
+//! # dlc-ffi
+//! C compatible bindings to the DLC libraries, from which headers can be
+//! generated using cbindgen and wrapped for Kotlin or Swift, so that mobile
+//! wallets can parse messages, validate contracts, compute payouts and run a
+//! [`manager::DlcManager`] without reimplementing the protocol.
+//!
+//! Functions take and return NUL terminated UTF-8 strings. Returned strings
+//! are JSON objects containing either a `result` or an `error` field, and must
+//! be freed using [`dlc_string_free`].
+
+#![crate_name = "dlc_ffi"]
+// Coding conventions
+#![deny(non_upper_case_globals)]
+#![deny(non_camel_case_types)]
+#![deny(non_snake_case)]
+#![deny(unused_mut)]
+#![deny(dead_code)]
+#![deny(unused_imports)]
+#![deny(missing_docs)]
+
+extern crate bitcoin;
+extern crate dlc;
+extern crate dlc_manager;
+extern crate dlc_messages;
+extern crate dlc_sled_storage_provider;
+extern crate lightning;
+extern crate secp256k1_zkp;
+extern crate serde;
+extern crate serde_json;
+
+pub mod manager;
+
+use dlc_manager::contract::descriptor_parser;
+use dlc_manager::contract::ContractDescriptor;
+use dlc_messages::Message;
+use serde_json::{json, Value};
+use std::ffi::{CStr, CString};
+use std::io::Cursor;
+use std::os::raw::c_char;
+
+/// Decodes a DLC message, given as its wire encoding prefixed by its two bytes
+/// type, and returns it as JSON with its type.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn dlc_message_to_json(data: *const u8, len: usize) -> *mut c_char {
+    if data.is_null() {
+        return to_c_string(Err("Null message.".to_string()));
+    }
+    let data = std::slice::from_raw_parts(data, len);
+    to_c_string(message_to_json(data))
+}
+
+/// Parses a contract descriptor written in the format of the
+/// [`descriptor_parser`] module, validating it against the given total
+/// collateral, and returns it as JSON.
+///
+/// # Safety
+///
+/// `descriptor` must be a valid NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn dlc_parse_contract_descriptor(
+    descriptor: *const c_char,
+    total_collateral: u64,
+) -> *mut c_char {
+    let res = from_c_str(descriptor).and_then(|descriptor| {
+        let descriptor = descriptor_parser::parse_contract_descriptor(descriptor, total_collateral)
+            .map_err(|e| e.to_string())?;
+        serde_json::to_value(&descriptor).map_err(|e| e.to_string())
+    });
+    to_c_string(res)
+}
+
+/// Returns the payouts of the parties for a contract descriptor given as JSON,
+/// as returned by [`dlc_parse_contract_descriptor`].
+///
+/// # Safety
+///
+/// `descriptor_json` must be a valid NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn dlc_get_payouts(
+    descriptor_json: *const c_char,
+    total_collateral: u64,
+) -> *mut c_char {
+    let res = from_c_str(descriptor_json).and_then(|descriptor_json| {
+        let descriptor: ContractDescriptor =
+            serde_json::from_str(descriptor_json).map_err(|e| e.to_string())?;
+        get_payouts(&descriptor, total_collateral)
+    });
+    to_c_string(res)
+}
+
+/// Frees a string returned by one of the functions of this library.
+///
+/// # Safety
+///
+/// `s` must have been returned by this library and not freed already.
+#[no_mangle]
+pub unsafe extern "C" fn dlc_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+fn message_to_json(data: &[u8]) -> Result<Value, String> {
+    let message = dlc_messages::read_message(&mut Cursor::new(data))
+        .map_err(|e| format!("Invalid message: {:?}", e))?;
+    let (msg_type, msg) = match &message {
+        Message::Offer(o) => ("offer", serde_json::to_value(o)),
+        Message::Accept(a) => ("accept", serde_json::to_value(a)),
+        Message::Sign(s) => ("sign", serde_json::to_value(s)),
+        Message::RenewOffer(r) => ("renewOffer", serde_json::to_value(r)),
+        Message::Reestablish(r) => ("reestablish", serde_json::to_value(r)),
+    };
+    Ok(json!({ "type": msg_type, "message": msg.map_err(|e| e.to_string())? }))
+}
+
+fn get_payouts(descriptor: &ContractDescriptor, total_collateral: u64) -> Result<Value, String> {
+    let payouts = match descriptor {
+        ContractDescriptor::Enum(e) => e
+            .outcome_payouts
+            .iter()
+            .map(|x| {
+                json!({
+                    "outcome": x.outcome,
+                    "offer": x.payout.offer,
+                    "accept": x.payout.accept,
+                })
+            })
+            .collect(),
+        ContractDescriptor::Numerical(n) => n
+            .get_range_payouts(total_collateral)
+            .map_err(|e| e.to_string())?
+            .iter()
+            .map(|x| {
+                json!({
+                    "start": x.start,
+                    "count": x.count,
+                    "offer": x.payout.offer,
+                    "accept": x.payout.accept,
+                })
+            })
+            .collect(),
+        ContractDescriptor::Hybrid(h) => h
+            .get_range_payouts(total_collateral)
+            .map_err(|e| e.to_string())?
+            .iter()
+            .zip(h.branches.iter())
+            .flat_map(|(range_payouts, branch)| {
+                range_payouts.iter().map(move |x| {
+                    json!({
+                        "outcome": branch.outcome,
+                        "start": x.start,
+                        "count": x.count,
+                        "offer": x.payout.offer,
+                        "accept": x.payout.accept,
+                    })
+                })
+            })
+            .collect(),
+    };
+    Ok(Value::Array(payouts))
+}
+
+unsafe fn from_c_str<'a>(s: *const c_char) -> Result<&'a str, String> {
+    if s.is_null() {
+        return Err("Null string.".to_string());
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| "Invalid UTF-8 string.".to_string())
+}
+
+fn to_c_string(res: Result<Value, String>) -> *mut c_char {
+    let json = match res {
+        Ok(result) => json!({ "result": result }),
+        Err(error) => json!({ "error": error }),
+    };
+    // Serialized JSON escapes control characters so cannot contain NUL bytes.
+    CString::new(json.to_string())
+        .expect("JSON to not contain NUL bytes")
+        .into_raw()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_payouts_from_parsed_descriptor_test() {
+        let input = CString::new("enum\noutcome a pays 100\noutcome b pays 0").unwrap();
+        unsafe {
+            let parsed = dlc_parse_contract_descriptor(input.as_ptr(), 100);
+            let parsed_json: Value =
+                serde_json::from_str(CStr::from_ptr(parsed).to_str().unwrap()).unwrap();
+            let descriptor = CString::new(parsed_json["result"].to_string()).unwrap();
+            let payouts = dlc_get_payouts(descriptor.as_ptr(), 100);
+            let payouts_json: Value =
+                serde_json::from_str(CStr::from_ptr(payouts).to_str().unwrap()).unwrap();
+
+            assert_eq!(100, payouts_json["result"][1]["accept"]);
+            dlc_string_free(parsed);
+            dlc_string_free(payouts);
+        }
+    }
+
+    #[test]
+    fn invalid_message_returns_error_test() {
+        let data = [0u8, 1, 2];
+        unsafe {
+            let res = dlc_message_to_json(data.as_ptr(), data.len());
+            let res_json: Value =
+                serde_json::from_str(CStr::from_ptr(res).to_str().unwrap()).unwrap();
+            assert!(res_json["error"].is_string());
+            dlc_string_free(res);
+        }
+    }
+}
@@ -0,0 +1,761 @@
+//! Callback driven [`Manager`](dlc_manager::manager::Manager), for which the wallet, blockchain and oracles
+//! are provided by the host application through a single callback, contracts
+//! being persisted using the sled storage provider.
+//!
+//! The callback receives the name of a method and its parameters as a JSON
+//! object, and must return a JSON object containing either a `result` or an
+//! `error` field, using the same convention as the functions of this library.
+//! Transactions, PSBTs and oracle messages are exchanged as hex strings of
+//! their consensus (respectively wire) encoding, keys as hex strings.
+//!
+//! | Method | Parameters | Result |
+//! |---|---|---|
+//! | `getNewAddress` | | address |
+//! | `getNewSecretKey` | | secret key |
+//! | `getSecretKeyForPubkey` | `pubkey` | secret key |
+//! | `signPsbtInput` | `psbt`, `inputIndex` | signed PSBT |
+//! | `getUtxosForAmount` | `amount`, `feeRate`, `lockUtxos` | array of UTXOs |
+//! | `importAddress` | `address` | |
+//! | `getTransaction` | `txid` | transaction |
+//! | `getTransactionConfirmations` | `txid` | number of confirmations |
+//! | `unlockUtxos` | `outpoints` (array of `txid` and `vout`) | |
+//! | `sendTransaction` | `transaction` | |
+//! | `getBlockHeight` | | height |
+//! | `getMedianTimePast` | | time |
+//! | `isInMempool` | `txid` | boolean |
+//! | `getTxBlockHeight` | `txid` | height or null |
+//! | `getSpendingTransaction` | `txid`, `vout` | transaction or null |
+//! | `getAnnouncement` | `oraclePublicKey`, `eventId` | announcement |
+//! | `getAttestation` | `oraclePublicKey`, `eventId` | attestation |
+//!
+//! UTXOs are objects with `txid`, `vout`, `value`, `scriptPubkey`, `address`,
+//! and optional `redeemScript`, `witnessScript` and `maxWitnessLen` fields.
+
+use crate::{from_c_str, to_c_string};
+use bitcoin::consensus::encode::{deserialize, serialize_hex, Decodable};
+use bitcoin::hashes::hex::{FromHex, ToHex};
+use bitcoin::network::constants::Network;
+use bitcoin::util::psbt::PartiallySignedTransaction;
+use bitcoin::{Address, OutPoint, Script, Transaction, TxOut, Txid};
+use dlc_manager::contract::contract_input::ContractInput;
+use dlc_manager::error::Error;
+use dlc_manager::{
+    Blockchain, Oracle, Storage, SystemTimeProvider, TemporaryContractId, Utxo, Wallet,
+};
+use dlc_messages::oracle_msgs::{OracleAnnouncement, OracleAttestation};
+use dlc_messages::Message;
+use dlc_sled_storage_provider::SledStorageProvider;
+use lightning::ln::wire::Type;
+use lightning::util::ser::{Readable, Writeable};
+use secp256k1_zkp::schnorrsig::PublicKey as SchnorrPublicKey;
+use secp256k1_zkp::{PublicKey, SecretKey};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::io::Cursor;
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Callback through which the host application provides the wallet,
+/// blockchain and oracle functionalities to a [`DlcManager`].
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DlcCallbacks {
+    /// Opaque pointer passed back to the callbacks.
+    pub context: *mut c_void,
+    /// Executes the given method with the given JSON parameters, and returns
+    /// a NUL terminated JSON object containing either a `result` or an
+    /// `error` field. May be invoked from any thread calling the manager.
+    pub call: extern "C" fn(
+        context: *mut c_void,
+        method: *const c_char,
+        params: *const c_char,
+    ) -> *mut c_char,
+    /// Frees a string returned by `call`.
+    pub free: extern "C" fn(context: *mut c_void, s: *mut c_char),
+}
+
+struct HostCallbacks {
+    callbacks: DlcCallbacks,
+    network: Network,
+}
+
+// The host application guarantees that its callbacks can be invoked from any
+// thread, as documented on `DlcCallbacks::call`.
+unsafe impl Send for HostCallbacks {}
+unsafe impl Sync for HostCallbacks {}
+
+impl HostCallbacks {
+    fn call(&self, method: &str, params: Value) -> Result<Value, String> {
+        let c_method = CString::new(method).expect("method names to not contain NUL bytes");
+        let c_params = CString::new(params.to_string()).expect("JSON to not contain NUL bytes");
+        let res =
+            (self.callbacks.call)(self.callbacks.context, c_method.as_ptr(), c_params.as_ptr());
+        if res.is_null() {
+            return Err(format!("No response to {}.", method));
+        }
+        let response = unsafe { CStr::from_ptr(res) }
+            .to_str()
+            .map_err(|_| "Invalid UTF-8 string.".to_string())
+            .and_then(|x| serde_json::from_str::<Value>(x).map_err(|e| e.to_string()));
+        (self.callbacks.free)(self.callbacks.context, res);
+        let mut response = response?;
+        match response.get("error") {
+            Some(Value::String(e)) => Err(e.clone()),
+            Some(e) => Err(e.to_string()),
+            None => Ok(response["result"].take()),
+        }
+    }
+
+    fn call_str(&self, method: &str, params: Value) -> Result<String, String> {
+        match self.call(method, params)? {
+            Value::String(s) => Ok(s),
+            _ => Err(format!("Expected a string in response to {}.", method)),
+        }
+    }
+
+    fn call_u64(&self, method: &str, params: Value) -> Result<u64, String> {
+        self.call(method, params)?
+            .as_u64()
+            .ok_or_else(|| format!("Expected a number in response to {}.", method))
+    }
+
+    fn call_decodable<T: Decodable>(&self, method: &str, params: Value) -> Result<T, String> {
+        decode_hex(&self.call_str(method, params)?)
+    }
+
+    fn call_optional_decodable<T: Decodable>(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<Option<T>, String> {
+        match self.call(method, params)? {
+            Value::Null => Ok(None),
+            Value::String(s) => decode_hex(&s).map(Some),
+            _ => Err(format!("Expected a string in response to {}.", method)),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HostUtxo {
+    txid: String,
+    vout: u32,
+    value: u64,
+    script_pubkey: String,
+    address: String,
+    redeem_script: Option<String>,
+    witness_script: Option<String>,
+    max_witness_len: Option<usize>,
+}
+
+impl HostUtxo {
+    fn into_utxo(self) -> Result<Utxo, String> {
+        let to_script = |s: &str| Vec::<u8>::from_hex(s).map(Script::from);
+        let invalid = |name: &str| format!("Invalid UTXO {}.", name);
+        Ok(Utxo {
+            tx_out: TxOut {
+                value: self.value,
+                script_pubkey: to_script(&self.script_pubkey)
+                    .map_err(|_| invalid("script pubkey"))?,
+            },
+            outpoint: OutPoint {
+                txid: Txid::from_str(&self.txid).map_err(|_| invalid("txid"))?,
+                vout: self.vout,
+            },
+            address: Address::from_str(&self.address).map_err(|_| invalid("address"))?,
+            redeem_script: match self.redeem_script {
+                Some(s) => to_script(&s).map_err(|_| invalid("redeem script"))?,
+                None => Script::new(),
+            },
+            witness_script: match self.witness_script {
+                Some(s) => Some(to_script(&s).map_err(|_| invalid("witness script"))?),
+                None => None,
+            },
+            max_witness_len: self.max_witness_len,
+        })
+    }
+}
+
+impl Wallet for HostCallbacks {
+    fn get_new_address(&self) -> Result<Address, Error> {
+        let address = self
+            .call_str("getNewAddress", json!({}))
+            .map_err(to_wallet_error)?;
+        Address::from_str(&address).map_err(|e| Error::WalletError(Box::new(e)))
+    }
+
+    fn get_new_secret_key(&self) -> Result<SecretKey, Error> {
+        let sk = self
+            .call_str("getNewSecretKey", json!({}))
+            .map_err(to_wallet_error)?;
+        SecretKey::from_str(&sk).map_err(|e| Error::WalletError(Box::new(e)))
+    }
+
+    fn get_secret_key_for_pubkey(&self, pubkey: &PublicKey) -> Result<SecretKey, Error> {
+        let sk = self
+            .call_str(
+                "getSecretKeyForPubkey",
+                json!({ "pubkey": pubkey.to_string() }),
+            )
+            .map_err(to_wallet_error)?;
+        SecretKey::from_str(&sk).map_err(|e| Error::WalletError(Box::new(e)))
+    }
+
+    fn sign_psbt_input(
+        &self,
+        psbt: &mut PartiallySignedTransaction,
+        input_index: usize,
+    ) -> Result<(), Error> {
+        *psbt = self
+            .call_decodable(
+                "signPsbtInput",
+                json!({ "psbt": serialize_hex(psbt), "inputIndex": input_index }),
+            )
+            .map_err(to_wallet_error)?;
+        Ok(())
+    }
+
+    fn get_utxos_for_amount(
+        &self,
+        amount: u64,
+        fee_rate: Option<u64>,
+        lock_utxos: bool,
+    ) -> Result<Vec<Utxo>, Error> {
+        let utxos = self
+            .call(
+                "getUtxosForAmount",
+                json!({ "amount": amount, "feeRate": fee_rate, "lockUtxos": lock_utxos }),
+            )
+            .map_err(to_wallet_error)?;
+        let utxos: Vec<HostUtxo> =
+            serde_json::from_value(utxos).map_err(|e| Error::WalletError(Box::new(e)))?;
+        utxos
+            .into_iter()
+            .map(HostUtxo::into_utxo)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(to_wallet_error)
+    }
+
+    fn import_address(&self, address: &Address) -> Result<(), Error> {
+        self.call("importAddress", json!({ "address": address.to_string() }))
+            .map_err(to_wallet_error)?;
+        Ok(())
+    }
+
+    fn get_transaction(&self, tx_id: &Txid) -> Result<Transaction, Error> {
+        self.call_decodable("getTransaction", json!({ "txid": tx_id.to_string() }))
+            .map_err(to_wallet_error)
+    }
+
+    fn get_transaction_confirmations(&self, tx_id: &Txid) -> Result<u32, Error> {
+        let confirmations = self
+            .call_u64(
+                "getTransactionConfirmations",
+                json!({ "txid": tx_id.to_string() }),
+            )
+            .map_err(to_wallet_error)?;
+        Ok(confirmations as u32)
+    }
+
+    fn unlock_utxos(&self, outpoints: &[OutPoint]) -> Result<(), Error> {
+        let outpoints: Vec<Value> = outpoints
+            .iter()
+            .map(|x| json!({ "txid": x.txid.to_string(), "vout": x.vout }))
+            .collect();
+        self.call("unlockUtxos", json!({ "outpoints": outpoints }))
+            .map_err(to_wallet_error)?;
+        Ok(())
+    }
+}
+
+impl Blockchain for HostCallbacks {
+    fn send_transaction(&self, transaction: &Transaction) -> Result<(), Error> {
+        self.call(
+            "sendTransaction",
+            json!({ "transaction": serialize_hex(transaction) }),
+        )
+        .map_err(|_| Error::BlockchainError)?;
+        Ok(())
+    }
+
+    fn get_network(&self) -> Result<Network, Error> {
+        Ok(self.network)
+    }
+
+    fn get_block_height(&self) -> Result<u64, Error> {
+        self.call_u64("getBlockHeight", json!({}))
+            .map_err(|_| Error::BlockchainError)
+    }
+
+    fn get_median_time_past(&self) -> Result<u64, Error> {
+        self.call_u64("getMedianTimePast", json!({}))
+            .map_err(|_| Error::BlockchainError)
+    }
+
+    fn is_in_mempool(&self, txid: &Txid) -> Result<bool, Error> {
+        self.call("isInMempool", json!({ "txid": txid.to_string() }))
+            .ok()
+            .and_then(|x| x.as_bool())
+            .ok_or(Error::BlockchainError)
+    }
+
+    fn get_tx_block_height(&self, txid: &Txid) -> Result<Option<u64>, Error> {
+        match self.call("getTxBlockHeight", json!({ "txid": txid.to_string() })) {
+            Ok(Value::Null) => Ok(None),
+            Ok(height) => height.as_u64().map(Some).ok_or(Error::BlockchainError),
+            Err(_) => Err(Error::BlockchainError),
+        }
+    }
+
+    fn get_spending_transaction(&self, outpoint: &OutPoint) -> Result<Option<Transaction>, Error> {
+        self.call_optional_decodable(
+            "getSpendingTransaction",
+            json!({ "txid": outpoint.txid.to_string(), "vout": outpoint.vout }),
+        )
+        .map_err(|_| Error::BlockchainError)
+    }
+}
+
+struct HostOracle {
+    public_key: SchnorrPublicKey,
+    host: Arc<HostCallbacks>,
+}
+
+impl HostOracle {
+    fn get_oracle_message<T: Readable>(&self, method: &str, event_id: &str) -> Result<T, Error> {
+        let params = json!({
+            "oraclePublicKey": self.public_key.to_string(),
+            "eventId": event_id,
+        });
+        let buf = self
+            .host
+            .call_str(method, params)
+            .and_then(|x| Vec::<u8>::from_hex(&x).map_err(|e| e.to_string()))
+            .map_err(Error::OracleError)?;
+        Readable::read(&mut Cursor::new(buf))
+            .map_err(|e| Error::OracleError(format!("Invalid oracle message: {:?}", e)))
+    }
+}
+
+impl Oracle for HostOracle {
+    fn get_public_key(&self) -> SchnorrPublicKey {
+        self.public_key
+    }
+
+    fn get_announcement(&self, event_id: &str) -> Result<OracleAnnouncement, Error> {
+        self.get_oracle_message("getAnnouncement", event_id)
+    }
+
+    fn get_attestation(&self, event_id: &str) -> Result<OracleAttestation, Error> {
+        self.get_oracle_message("getAttestation", event_id)
+    }
+}
+
+type FfiManager = dlc_manager::manager::Manager<
+    Arc<HostCallbacks>,
+    Arc<HostCallbacks>,
+    Box<SledStorageProvider>,
+    Arc<HostOracle>,
+    Arc<SystemTimeProvider>,
+>;
+
+/// A DLC manager using the callbacks of the host application, created with
+/// [`dlc_manager_new`].
+pub struct DlcManager {
+    manager: FfiManager,
+}
+
+/// Creates a manager persisting contracts in a sled database at
+/// `storage_path`, for the given network (e.g. `regtest`) and the oracles
+/// whose public keys are given as a JSON array of hex strings. Returns null on
+/// failure, setting `error` (if not null) to a JSON object containing the
+/// error, to be freed using [`dlc_string_free`](crate::dlc_string_free).
+///
+/// # Safety
+///
+/// `storage_path`, `network` and `oracle_public_keys` must be valid NUL
+/// terminated strings, and the callbacks must remain valid until the manager
+/// is freed with [`dlc_manager_free`].
+#[no_mangle]
+pub unsafe extern "C" fn dlc_manager_new(
+    storage_path: *const c_char,
+    network: *const c_char,
+    oracle_public_keys: *const c_char,
+    callbacks: DlcCallbacks,
+    error: *mut *mut c_char,
+) -> *mut DlcManager {
+    let res = create_manager(storage_path, network, oracle_public_keys, callbacks);
+    match res {
+        Ok(manager) => Box::into_raw(Box::new(manager)),
+        Err(e) => {
+            if !error.is_null() {
+                *error = to_c_string(Err(e));
+            }
+            ptr::null_mut()
+        }
+    }
+}
+
+unsafe fn create_manager(
+    storage_path: *const c_char,
+    network: *const c_char,
+    oracle_public_keys: *const c_char,
+    callbacks: DlcCallbacks,
+) -> Result<DlcManager, String> {
+    let network = Network::from_str(from_c_str(network)?).map_err(|e| e.to_string())?;
+    let oracle_public_keys: Vec<String> =
+        serde_json::from_str(from_c_str(oracle_public_keys)?).map_err(|e| e.to_string())?;
+    let oracle_public_keys = oracle_public_keys
+        .iter()
+        .map(|x| {
+            SchnorrPublicKey::from_str(x).map_err(|_| format!("Invalid oracle public key {}.", x))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let store = SledStorageProvider::new(from_c_str(storage_path)?).map_err(|e| e.to_string())?;
+    let host = Arc::new(HostCallbacks { callbacks, network });
+    let oracles = oracle_public_keys
+        .into_iter()
+        .map(|public_key| {
+            let oracle = HostOracle {
+                public_key,
+                host: host.clone(),
+            };
+            (public_key, Arc::new(oracle))
+        })
+        .collect::<HashMap<_, _>>();
+    let manager = dlc_manager::manager::Manager::new(
+        host.clone(),
+        host,
+        Box::new(store),
+        oracles,
+        Arc::new(SystemTimeProvider {}),
+    );
+    Ok(DlcManager { manager })
+}
+
+/// Frees a manager created with [`dlc_manager_new`].
+///
+/// # Safety
+///
+/// `manager` must have been returned by [`dlc_manager_new`] and not freed
+/// already.
+#[no_mangle]
+pub unsafe extern "C" fn dlc_manager_free(manager: *mut DlcManager) {
+    if !manager.is_null() {
+        drop(Box::from_raw(manager));
+    }
+}
+
+/// Offers the contract given as JSON to the counter party with the given
+/// public key, returning the temporary contract id and the offer message to
+/// send to the counter party.
+///
+/// # Safety
+///
+/// `manager` must be a valid manager, `contract_input` and `counter_party`
+/// valid NUL terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn dlc_manager_send_offer(
+    manager: *const DlcManager,
+    contract_input: *const c_char,
+    counter_party: *const c_char,
+) -> *mut c_char {
+    let res = get_manager(manager).and_then(|manager| {
+        let contract_input: ContractInput =
+            serde_json::from_str(from_c_str(contract_input)?).map_err(|e| e.to_string())?;
+        let counter_party = parse_public_key(from_c_str(counter_party)?)?;
+        let offer = manager
+            .send_offer(&contract_input, counter_party)
+            .map_err(|e| e.to_string())?;
+        let temporary_id = offer
+            .get_temporary_contract_id()
+            .map_err(|e| e.to_string())?;
+        Ok(json!({
+            "temporaryContractId": temporary_id.to_string(),
+            "message": encode_message(&Message::Offer(offer)),
+        }))
+    });
+    to_c_string(res)
+}
+
+/// Accepts the offer with the given temporary contract id, returning the
+/// contract id and the accept message to send to the counter party.
+///
+/// # Safety
+///
+/// `manager` must be a valid manager and `temporary_id` a valid NUL
+/// terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn dlc_manager_accept_offer(
+    manager: *const DlcManager,
+    temporary_id: *const c_char,
+) -> *mut c_char {
+    let res = get_manager(manager).and_then(|manager| {
+        let temporary_id = TemporaryContractId::from_str(from_c_str(temporary_id)?)
+            .map_err(|_| "Invalid contract id.".to_string())?;
+        let (contract_id, counter_party, accept) = manager
+            .accept_contract_offer(&temporary_id)
+            .map_err(|e| e.to_string())?;
+        Ok(json!({
+            "contractId": contract_id.to_string(),
+            "counterParty": counter_party.to_string(),
+            "message": encode_message(&Message::Accept(accept)),
+        }))
+    });
+    to_c_string(res)
+}
+
+/// Processes a message received from the counter party with the given public
+/// key, given as its wire encoding prefixed by its type, and returns the
+/// message to reply with, if any.
+///
+/// # Safety
+///
+/// `manager` must be a valid manager, `data` must point to `len` readable
+/// bytes and `counter_party` must be a valid NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn dlc_manager_on_dlc_message(
+    manager: *const DlcManager,
+    data: *const u8,
+    len: usize,
+    counter_party: *const c_char,
+) -> *mut c_char {
+    let res = get_manager(manager).and_then(|manager| {
+        if data.is_null() {
+            return Err("Null message.".to_string());
+        }
+        let data = std::slice::from_raw_parts(data, len);
+        let counter_party = parse_public_key(from_c_str(counter_party)?)?;
+        let message = manager
+            .decode_dlc_message(data, &counter_party)
+            .map_err(|e| e.to_string())?;
+        let reply = manager
+            .on_dlc_message(&message, counter_party)
+            .map_err(|e| e.to_string())?;
+        Ok(json!({ "message": reply.map(|x| encode_message(&x)) }))
+    });
+    to_c_string(res)
+}
+
+/// Updates the state of the contracts according to the blockchain and the
+/// oracles, broadcasting closing transactions when possible. Meant to be
+/// called periodically by the host application.
+///
+/// # Safety
+///
+/// `manager` must be a valid manager.
+#[no_mangle]
+pub unsafe extern "C" fn dlc_manager_periodic_check(manager: *const DlcManager) -> *mut c_char {
+    let res = get_manager(manager).and_then(|manager| {
+        manager.periodic_check().map_err(|e| e.to_string())?;
+        Ok(Value::Null)
+    });
+    to_c_string(res)
+}
+
+/// Returns the contracts of the manager with their state.
+///
+/// # Safety
+///
+/// `manager` must be a valid manager.
+#[no_mangle]
+pub unsafe extern "C" fn dlc_manager_get_contracts(manager: *const DlcManager) -> *mut c_char {
+    let res = get_manager(manager).and_then(|manager| {
+        let contracts = manager
+            .get_store()
+            .get_contracts()
+            .map_err(|e| e.to_string())?;
+        Ok(Value::Array(
+            contracts
+                .iter()
+                .map(|c| {
+                    json!({
+                        "temporaryContractId": c.get_temporary_id().to_string(),
+                        "contractId": c.get_id().map(|x| x.to_string()),
+                        "counterParty": c.get_offered_contract().counter_party.to_string(),
+                        "state": c.get_state_name(),
+                    })
+                })
+                .collect(),
+        ))
+    });
+    to_c_string(res)
+}
+
+unsafe fn get_manager<'a>(manager: *const DlcManager) -> Result<&'a FfiManager, String> {
+    manager
+        .as_ref()
+        .map(|x| &x.manager)
+        .ok_or_else(|| "Null manager.".to_string())
+}
+
+fn to_wallet_error(e: String) -> Error {
+    Error::WalletError(e.into())
+}
+
+fn parse_public_key(public_key: &str) -> Result<PublicKey, String> {
+    PublicKey::from_str(public_key).map_err(|e| format!("Invalid public key: {}", e))
+}
+
+fn decode_hex<T: Decodable>(s: &str) -> Result<T, String> {
+    let buf = Vec::<u8>::from_hex(s).map_err(|e| e.to_string())?;
+    deserialize(&buf).map_err(|e| e.to_string())
+}
+
+/// Returns the hex of the wire encoding of the given message prefixed by its
+/// type, as accepted by [`dlc_manager_on_dlc_message`].
+fn encode_message(msg: &Message) -> String {
+    let mut buf = msg.type_id().to_be_bytes().to_vec();
+    buf.extend(msg.encode());
+    buf.to_hex()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dlc_string_free;
+
+    extern "C" fn test_call(
+        _context: *mut c_void,
+        method: *const c_char,
+        params: *const c_char,
+    ) -> *mut c_char {
+        let method = unsafe { CStr::from_ptr(method) }.to_str().unwrap();
+        let params: Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(params) }.to_str().unwrap()).unwrap();
+        let response = match method {
+            "getBlockHeight" => json!({ "result": 150 }),
+            "isInMempool" => json!({ "result": params["txid"].is_string() }),
+            "getTxBlockHeight" => json!({ "result": null }),
+            "getUtxosForAmount" => json!({ "result": [{
+                "txid": "4d5cc0e1e1f0f5c2d0bb1c1c4e8b5d3a2c4b1f0e9d8c7b6a5f4e3d2c1b0a9f8e",
+                "vout": 1,
+                "value": params["amount"],
+                "scriptPubkey": "0014a1b2c3d4e5f60718293a4b5c6d7e8f9012345678",
+                "address": "bcrt1q5xev84897cr3s2f6fdwx6l50jqfrg4ncncxfsx",
+            }]}),
+            _ => json!({ "error": format!("Unsupported method {}.", method) }),
+        };
+        CString::new(response.to_string()).unwrap().into_raw()
+    }
+
+    extern "C" fn test_free(_context: *mut c_void, s: *mut c_char) {
+        unsafe { drop(CString::from_raw(s)) };
+    }
+
+    fn get_host() -> HostCallbacks {
+        HostCallbacks {
+            callbacks: DlcCallbacks {
+                context: ptr::null_mut(),
+                call: test_call,
+                free: test_free,
+            },
+            network: Network::Regtest,
+        }
+    }
+
+    unsafe fn to_json(s: *mut c_char) -> Value {
+        let res = serde_json::from_str(CStr::from_ptr(s).to_str().unwrap()).unwrap();
+        dlc_string_free(s);
+        res
+    }
+
+    #[test]
+    fn host_results_are_decoded_test() {
+        let host = get_host();
+        let txid =
+            Txid::from_str("4d5cc0e1e1f0f5c2d0bb1c1c4e8b5d3a2c4b1f0e9d8c7b6a5f4e3d2c1b0a9f8e")
+                .unwrap();
+
+        assert_eq!(150, host.get_block_height().unwrap());
+        assert!(host.is_in_mempool(&txid).unwrap());
+        assert_eq!(None, host.get_tx_block_height(&txid).unwrap());
+        let utxos = host.get_utxos_for_amount(1000, None, true).unwrap();
+        assert_eq!(1, utxos.len());
+        assert_eq!(1000, utxos[0].tx_out.value);
+        assert_eq!(txid, utxos[0].outpoint.txid);
+        assert_eq!(Script::new(), utxos[0].redeem_script);
+    }
+
+    #[test]
+    fn host_errors_are_returned_test() {
+        let host = get_host();
+
+        match host.get_new_address() {
+            Err(Error::WalletError(e)) => {
+                assert_eq!("Unsupported method getNewAddress.", e.to_string())
+            }
+            _ => panic!("Expected a wallet error."),
+        }
+        assert!(matches!(
+            host.get_median_time_past(),
+            Err(Error::BlockchainError)
+        ));
+    }
+
+    #[test]
+    fn manager_lifecycle_test() {
+        let path = CString::new("test_files/manager_lifecycle_test").unwrap();
+        let network = CString::new("regtest").unwrap();
+        let oracles =
+            CString::new("[\"79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798\"]")
+                .unwrap();
+        let mut error = ptr::null_mut();
+        unsafe {
+            let manager = dlc_manager_new(
+                path.as_ptr(),
+                network.as_ptr(),
+                oracles.as_ptr(),
+                get_host().callbacks,
+                &mut error,
+            );
+            assert!(!manager.is_null());
+            assert!(error.is_null());
+
+            let contracts = to_json(dlc_manager_get_contracts(manager));
+            assert_eq!(json!({ "result": [] }), contracts);
+            let check = to_json(dlc_manager_periodic_check(manager));
+            assert_eq!(json!({ "result": null }), check);
+
+            let data = [0u8, 1, 2];
+            let counter_party =
+                CString::new("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+                    .unwrap();
+            let res = to_json(dlc_manager_on_dlc_message(
+                manager,
+                data.as_ptr(),
+                data.len(),
+                counter_party.as_ptr(),
+            ));
+            assert!(res["error"].is_string());
+
+            dlc_manager_free(manager);
+        }
+        std::fs::remove_dir_all("test_files/manager_lifecycle_test").unwrap();
+    }
+
+    #[test]
+    fn invalid_oracle_key_returns_error_test() {
+        let path = CString::new("test_files/invalid_oracle_key_test").unwrap();
+        let network = CString::new("regtest").unwrap();
+        let oracles = CString::new("[\"abcd\"]").unwrap();
+        let mut error = ptr::null_mut();
+        unsafe {
+            let manager = dlc_manager_new(
+                path.as_ptr(),
+                network.as_ptr(),
+                oracles.as_ptr(),
+                get_host().callbacks,
+                &mut error,
+            );
+            assert!(manager.is_null());
+            assert!(to_json(error)["error"].is_string());
+        }
+        assert!(std::fs::metadata("test_files/invalid_oracle_key_test").is_err());
+    }
+}
@@ -2,7 +2,7 @@ use bitcoin::hashes::Hash;
 use bitcoin::OutPoint;
 use bitcoin::Script;
 use bitcoin::WPubkeyHash;
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
 use dlc::create_dlc_transactions;
 use dlc::DlcTransactions;
 use dlc::PartyParams;
@@ -23,6 +23,7 @@ use dlc_messages::oracle_msgs::DigitDecompositionEventDescriptor;
 use dlc_messages::oracle_msgs::EventDescriptor;
 use dlc_messages::oracle_msgs::OracleAnnouncement;
 use dlc_messages::oracle_msgs::OracleEvent;
+use dlc_trie::multi_oracle::CoverageStrategy;
 use secp256k1_zkp::{
     global::SECP256K1,
     rand::thread_rng,
@@ -38,33 +39,72 @@ const FLOOR: u64 = 45000;
 const CAP: u64 = 55000;
 /// The rounding modulus to use (1 means no rounding is done).
 const ROUNDING_MOD: u64 = 1;
-/// The number of digits used to represent outcome values.
-const NB_DIGITS: usize = 17;
 /// The minimum difference between oracle supported for the contract (as a power of 2).
 const MIN_SUPPORT_EXP: usize = 7;
 /// The maximum difference between oracle supported for the contract (as a power of 2).
 const MAX_ERROR_EXP: usize = 8;
-/// Whether to allow difference in oracle's attestation values.
-const USE_DIFF_PARAMS: bool = true;
-/// The number of oracles used for the contract.
-const NB_ORACLES: usize = 3;
-/// The number of oracles required to be in agreement to close the contract.
-const THRESHOLD: usize = 2;
 /// The ID of the event.
 const EVENT_ID: &str = "Test";
 /// The total collateral value locked in the contract.
 const TOTAL_COLLATERAL: u64 = 200000000;
 
-fn max_value() -> u32 {
-    BASE.pow(NB_DIGITS as u32) - 1
+/// The oracle setup of a benchmarked contract.
+struct ContractSize {
+    /// The name of the setup used as benchmark parameter.
+    name: &'static str,
+    /// The number of digits used to represent outcome values.
+    nb_digits: usize,
+    /// The number of oracles used for the contract.
+    nb_oracles: usize,
+    /// The number of oracles required to be in agreement to close the contract.
+    threshold: usize,
+    /// Whether to allow difference in oracle's attestation values.
+    use_diff_params: bool,
 }
 
-fn create_contract_descriptor() -> ContractDescriptor {
-    let difference_params = if USE_DIFF_PARAMS {
+/// Representative contract setups, from a single oracle to a multi oracle
+/// contract allowing differences between the attested values.
+const CONTRACT_SIZES: [ContractSize; 4] = [
+    ContractSize {
+        name: "1-of-1 20 digits",
+        nb_digits: 20,
+        nb_oracles: 1,
+        threshold: 1,
+        use_diff_params: false,
+    },
+    ContractSize {
+        name: "2-of-3 17 digits",
+        nb_digits: 17,
+        nb_oracles: 3,
+        threshold: 2,
+        use_diff_params: false,
+    },
+    ContractSize {
+        name: "2-of-3 17 digits with difference",
+        nb_digits: 17,
+        nb_oracles: 3,
+        threshold: 2,
+        use_diff_params: true,
+    },
+    ContractSize {
+        name: "3-of-5 17 digits with difference",
+        nb_digits: 17,
+        nb_oracles: 5,
+        threshold: 3,
+        use_diff_params: true,
+    },
+];
+
+fn max_value(nb_digits: usize) -> u32 {
+    BASE.pow(nb_digits as u32) - 1
+}
+
+fn create_contract_descriptor(size: &ContractSize) -> ContractDescriptor {
+    let difference_params = if size.use_diff_params {
         Some(DifferenceParams {
             max_error_exp: MAX_ERROR_EXP,
             min_support_exp: MIN_SUPPORT_EXP,
-            maximize_coverage: false,
+            coverage_strategy: CoverageStrategy::MinimizeCetCount,
         })
     } else {
         None
@@ -109,7 +149,7 @@ fn create_contract_descriptor() -> ContractDescriptor {
                         extra_precision: 0,
                     },
                     PayoutPoint {
-                        event_outcome: max_value() as u64,
+                        event_outcome: max_value(size.nb_digits) as u64,
                         outcome_payout: TOTAL_COLLATERAL,
                         extra_precision: 0,
                     },
@@ -126,10 +166,14 @@ fn create_contract_descriptor() -> ContractDescriptor {
         },
         info: NumericalEventInfo {
             base: BASE as usize,
-            nb_digits: NB_DIGITS as usize,
+            nb_digits: size.nb_digits,
             unit: "sats/sec".to_owned(),
         },
         difference_params,
+        additional_payout_functions: Vec::new(),
+        exact_evaluation: false,
+        max_value: None,
+        range_payouts_cache: Default::default(),
     })
 }
 
@@ -145,8 +189,8 @@ fn get_p2wpkh_script_pubkey() -> Script {
     Script::new_v0_wpkh(&WPubkeyHash::hash(&get_pubkey().serialize()))
 }
 
-fn create_oracle_announcements() -> Vec<OracleAnnouncement> {
-    (0..NB_ORACLES).map(|_| {
+fn create_oracle_announcements(size: &ContractSize) -> Vec<OracleAnnouncement> {
+    (0..size.nb_oracles).map(|_| {
             OracleAnnouncement {
             announcement_signature: Signature::from_str("859833d34b9cbd7c0a898693a289af434c74ad1d65e15c67d1b1d3bf74d9ee85cbd5258da5e91815da9989185c8bc9b026ce6f6598c1b2fb127c1bb1a6bef74a").unwrap(),
             oracle_public_key: get_schnorr_pubkey(),
@@ -156,21 +200,21 @@ fn create_oracle_announcements() -> Vec<OracleAnnouncement> {
                 is_signed: false,
                 unit: "sats/sec".to_owned(),
                 precision: 0,
-                nb_digits: NB_DIGITS as u16,
+                nb_digits: size.nb_digits as u16,
             }),
-                oracle_nonces: (0..NB_DIGITS).map(|_| get_schnorr_pubkey()).collect(),
+                oracle_nonces: (0..size.nb_digits).map(|_| get_schnorr_pubkey()).collect(),
                 event_maturity_epoch: 1234567,
                 event_id: EVENT_ID.to_string(),
         }}}).collect()
 }
 
-fn create_contract_info() -> ContractInfo {
-    let contract_descriptor = create_contract_descriptor();
-    let oracle_announcements = create_oracle_announcements();
+fn create_contract_info(size: &ContractSize) -> ContractInfo {
+    let contract_descriptor = create_contract_descriptor(size);
+    let oracle_announcements = create_oracle_announcements(size);
     ContractInfo {
         contract_descriptor,
         oracle_announcements,
-        threshold: THRESHOLD,
+        threshold: size.threshold,
     }
 }
 
@@ -223,68 +267,76 @@ fn offer_seckey() -> SecretKey {
 
 /// Benchmark to measure the adaptor signature creation time.
 pub fn sign_bench(c: &mut Criterion) {
-    let contract_info = create_contract_info();
-    let dlc_transactions = create_transactions(&contract_info.get_payouts(200000000));
-    let fund_output_value = dlc_transactions.get_fund_output().value;
+    let mut group = c.benchmark_group("sign");
+    for size in &CONTRACT_SIZES {
+        let contract_info = create_contract_info(size);
+        let dlc_transactions = create_transactions(&contract_info.get_payouts(200000000).unwrap());
+        let fund_output_value = dlc_transactions.get_fund_output().value;
 
-    let seckey = accept_seckey();
-    c.bench_function("sign", |b| {
-        b.iter(|| {
-            black_box(
-                contract_info
-                    .get_adaptor_info(
-                        SECP256K1,
-                        TOTAL_COLLATERAL,
-                        &seckey,
-                        &dlc_transactions.funding_script_pubkey,
-                        fund_output_value,
-                        &dlc_transactions.cets,
-                        0,
-                    )
-                    .unwrap(),
-            )
+        let seckey = accept_seckey();
+        group.bench_function(BenchmarkId::from_parameter(size.name), |b| {
+            b.iter(|| {
+                black_box(
+                    contract_info
+                        .get_adaptor_info(
+                            SECP256K1,
+                            TOTAL_COLLATERAL,
+                            &seckey,
+                            &dlc_transactions.funding_script_pubkey,
+                            fund_output_value,
+                            &dlc_transactions.cets,
+                            0,
+                        )
+                        .unwrap(),
+                )
+            });
         });
-    });
+    }
+    group.finish();
 }
 
 /// Benchmark to measure the adaptor signature verification time.
 pub fn verify_bench(c: &mut Criterion) {
-    let contract_info = create_contract_info();
-    let dlc_transactions = create_transactions(&contract_info.get_payouts(200000000));
-    let fund_output_value = dlc_transactions.get_fund_output().value;
+    let mut group = c.benchmark_group("verify");
+    for size in &CONTRACT_SIZES {
+        let contract_info = create_contract_info(size);
+        let dlc_transactions = create_transactions(&contract_info.get_payouts(200000000).unwrap());
+        let fund_output_value = dlc_transactions.get_fund_output().value;
 
-    let seckey = accept_seckey();
-    let pubkey = secp256k1_zkp::PublicKey::from_secret_key(SECP256K1, &seckey);
-    let adaptor_info = contract_info
-        .get_adaptor_info(
-            SECP256K1,
-            TOTAL_COLLATERAL,
-            &seckey,
-            &dlc_transactions.funding_script_pubkey,
-            fund_output_value,
-            &dlc_transactions.cets,
-            0,
-        )
-        .unwrap();
-    let adaptor_signatures = &adaptor_info.1;
-    c.bench_function("verify", |b| {
-        b.iter(|| {
-            black_box(
-                contract_info
-                    .verify_adaptor_info(
-                        SECP256K1,
-                        &pubkey,
-                        &dlc_transactions.funding_script_pubkey,
-                        fund_output_value,
-                        &dlc_transactions.cets,
-                        adaptor_signatures,
-                        0,
-                        &adaptor_info.0,
-                    )
-                    .unwrap(),
-            );
+        let seckey = accept_seckey();
+        let pubkey = secp256k1_zkp::PublicKey::from_secret_key(SECP256K1, &seckey);
+        let adaptor_info = contract_info
+            .get_adaptor_info(
+                SECP256K1,
+                TOTAL_COLLATERAL,
+                &seckey,
+                &dlc_transactions.funding_script_pubkey,
+                fund_output_value,
+                &dlc_transactions.cets,
+                0,
+            )
+            .unwrap();
+        let adaptor_signatures = &adaptor_info.1;
+        group.bench_function(BenchmarkId::from_parameter(size.name), |b| {
+            b.iter(|| {
+                black_box(
+                    contract_info
+                        .verify_adaptor_info(
+                            SECP256K1,
+                            &pubkey,
+                            &dlc_transactions.funding_script_pubkey,
+                            fund_output_value,
+                            &dlc_transactions.cets,
+                            adaptor_signatures,
+                            0,
+                            &adaptor_info.0,
+                        )
+                        .unwrap(),
+                );
+            });
         });
-    });
+    }
+    group.finish();
 }
 
 criterion_group! {
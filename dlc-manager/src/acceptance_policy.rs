@@ -0,0 +1,256 @@
+//! #Acceptance policy evaluated on received offers, so that nodes accepting
+//! contracts programmatically can reject or set aside offers whose terms they
+//! are not willing to enter into.
+
+use crate::contract::{offered_contract::OfferedContract, ContractDescriptor};
+use secp256k1_zkp::schnorrsig::PublicKey as SchnorrPublicKey;
+
+/// The type of the descriptor of a contract.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DescriptorType {
+    /// Descriptor of an enumeration outcome contract.
+    Enum,
+    /// Descriptor of a numerical outcome contract.
+    Numerical,
+    /// Descriptor of a contract where an enumerated outcome selects the
+    /// payout function of a numerical outcome.
+    Hybrid,
+}
+
+impl From<&ContractDescriptor> for DescriptorType {
+    fn from(descriptor: &ContractDescriptor) -> DescriptorType {
+        match descriptor {
+            ContractDescriptor::Enum(_) => DescriptorType::Enum,
+            ContractDescriptor::Numerical(_) => DescriptorType::Numerical,
+            ContractDescriptor::Hybrid(_) => DescriptorType::Hybrid,
+        }
+    }
+}
+
+/// What to do with a received offer that does not satisfy the acceptance
+/// policy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PolicyViolationAction {
+    /// Reject the offer without storing it. This is the default.
+    Reject,
+    /// Store the offer so that it can be reviewed manually before being
+    /// accepted.
+    Review,
+}
+
+impl Default for PolicyViolationAction {
+    fn default() -> Self {
+        PolicyViolationAction::Reject
+    }
+}
+
+/// A term of an offer that does not satisfy the acceptance policy.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PolicyViolation {
+    /// The collateral of the local party is above the maximum.
+    CollateralTooHigh(u64),
+    /// The total collateral of the local party in open contracts would be
+    /// above the maximum if the offer was accepted.
+    TotalCollateralAtRiskTooHigh(u64),
+    /// The fee rate is below the minimum.
+    FeeRateTooLow(u64),
+    /// The contract relies on an oracle that is not allowed.
+    OracleNotAllowed(SchnorrPublicKey),
+    /// The maturity of the contract is outside of the allowed window.
+    MaturityOutOfWindow(u32),
+    /// The contract uses a type of descriptor that is not allowed.
+    DescriptorTypeNotAllowed(DescriptorType),
+}
+
+/// Terms that received offers must satisfy. Fields set to `None` do not
+/// constrain offers.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AcceptancePolicy {
+    /// The maximum collateral of the local party in the offered contract.
+    pub max_collateral: Option<u64>,
+    /// The maximum sum of the collaterals of the local party in all open
+    /// contracts, including the offered one.
+    pub max_total_collateral_at_risk: Option<u64>,
+    /// The minimum fee rate, in satoshis per virtual byte.
+    pub min_fee_rate_per_vb: Option<u64>,
+    /// The public keys of the oracles that offered contracts can rely on.
+    pub allowed_oracles: Option<Vec<SchnorrPublicKey>>,
+    /// The minimum delay in seconds between the reception of the offer and
+    /// the maturity of the contract.
+    pub min_maturity_delay: Option<u64>,
+    /// The maximum delay in seconds between the reception of the offer and
+    /// the maturity of the contract.
+    pub max_maturity_delay: Option<u64>,
+    /// The types of descriptors that offered contracts can use.
+    pub allowed_descriptor_types: Option<Vec<DescriptorType>>,
+    /// What to do with offers that do not satisfy the policy.
+    pub on_violation: PolicyViolationAction,
+}
+
+impl AcceptancePolicy {
+    /// Returns the terms of the given offer that do not satisfy the policy,
+    /// `now` being the current unix time. The total collateral at risk depends
+    /// on the other contracts of the local party and is checked by the
+    /// [`crate::manager::Manager`].
+    pub fn evaluate(&self, offered_contract: &OfferedContract, now: u64) -> Vec<PolicyViolation> {
+        let mut violations = Vec::new();
+
+        let collateral =
+            offered_contract.total_collateral - offered_contract.offer_params.collateral;
+        if self.max_collateral.map_or(false, |max| collateral > max) {
+            violations.push(PolicyViolation::CollateralTooHigh(collateral));
+        }
+
+        let fee_rate = offered_contract.fee_rate_per_vb;
+        if self.min_fee_rate_per_vb.map_or(false, |min| fee_rate < min) {
+            violations.push(PolicyViolation::FeeRateTooLow(fee_rate));
+        }
+
+        let maturity = offered_contract.contract_maturity_bound;
+        let maturity_delay = (maturity as u64).saturating_sub(now);
+        if self
+            .min_maturity_delay
+            .map_or(false, |min| maturity_delay < min)
+            || self
+                .max_maturity_delay
+                .map_or(false, |max| maturity_delay > max)
+        {
+            violations.push(PolicyViolation::MaturityOutOfWindow(maturity));
+        }
+
+        for contract_info in &offered_contract.contract_info {
+            if let Some(allowed_oracles) = &self.allowed_oracles {
+                for announcement in &contract_info.oracle_announcements {
+                    let violation =
+                        PolicyViolation::OracleNotAllowed(announcement.oracle_public_key);
+                    if !allowed_oracles.contains(&announcement.oracle_public_key)
+                        && !violations.contains(&violation)
+                    {
+                        violations.push(violation);
+                    }
+                }
+            }
+
+            if let Some(allowed_descriptor_types) = &self.allowed_descriptor_types {
+                let descriptor_type = (&contract_info.contract_descriptor).into();
+                let violation = PolicyViolation::DescriptorTypeNotAllowed(descriptor_type);
+                if !allowed_descriptor_types.contains(&descriptor_type)
+                    && !violations.contains(&violation)
+                {
+                    violations.push(violation);
+                }
+            }
+        }
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::ser::Serializable;
+    use std::io::Cursor;
+
+    const NOW: u64 = 1_000_000;
+
+    fn get_offered_contract() -> OfferedContract {
+        let mut offered_contract = OfferedContract::deserialize(&mut Cursor::new(
+            &include_bytes!("../../dlc-sled-storage-provider/test_files/Offered")[..],
+        ))
+        .unwrap();
+        offered_contract.total_collateral = offered_contract.offer_params.collateral + 1000;
+        offered_contract.fee_rate_per_vb = 2;
+        offered_contract.contract_maturity_bound = NOW as u32 + 100;
+        offered_contract
+    }
+
+    #[test]
+    fn default_policy_accepts_offers_test() {
+        assert!(AcceptancePolicy::default()
+            .evaluate(&get_offered_contract(), NOW)
+            .is_empty());
+    }
+
+    #[test]
+    fn collateral_and_fee_rate_limits_test() {
+        let offered_contract = get_offered_contract();
+        let mut policy = AcceptancePolicy {
+            max_collateral: Some(999),
+            min_fee_rate_per_vb: Some(3),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            vec![
+                PolicyViolation::CollateralTooHigh(1000),
+                PolicyViolation::FeeRateTooLow(2)
+            ],
+            policy.evaluate(&offered_contract, NOW)
+        );
+
+        policy.max_collateral = Some(1000);
+        policy.min_fee_rate_per_vb = Some(2);
+        assert!(policy.evaluate(&offered_contract, NOW).is_empty());
+    }
+
+    #[test]
+    fn maturity_window_test() {
+        let offered_contract = get_offered_contract();
+        let maturity = offered_contract.contract_maturity_bound;
+        let violation = vec![PolicyViolation::MaturityOutOfWindow(maturity)];
+        let mut policy = AcceptancePolicy {
+            min_maturity_delay: Some(101),
+            ..Default::default()
+        };
+
+        assert_eq!(violation, policy.evaluate(&offered_contract, NOW));
+
+        policy.min_maturity_delay = Some(100);
+        policy.max_maturity_delay = Some(100);
+        assert!(policy.evaluate(&offered_contract, NOW).is_empty());
+
+        policy.max_maturity_delay = Some(99);
+        assert_eq!(violation, policy.evaluate(&offered_contract, NOW));
+
+        // Offers received after their maturity have a delay of zero.
+        policy.min_maturity_delay = Some(1);
+        policy.max_maturity_delay = None;
+        assert_eq!(
+            violation,
+            policy.evaluate(&offered_contract, maturity as u64 + 10)
+        );
+    }
+
+    #[test]
+    fn oracles_and_descriptor_types_test() {
+        let offered_contract = get_offered_contract();
+        let mut oracles: Vec<SchnorrPublicKey> = Vec::new();
+        for contract_info in &offered_contract.contract_info {
+            for announcement in &contract_info.oracle_announcements {
+                if !oracles.contains(&announcement.oracle_public_key) {
+                    oracles.push(announcement.oracle_public_key);
+                }
+            }
+        }
+        let descriptor_type: DescriptorType =
+            (&offered_contract.contract_info[0].contract_descriptor).into();
+        let mut policy = AcceptancePolicy {
+            allowed_oracles: Some(Vec::new()),
+            allowed_descriptor_types: Some(Vec::new()),
+            ..Default::default()
+        };
+
+        let violations = policy.evaluate(&offered_contract, NOW);
+        // Each violation is only reported once.
+        assert_eq!(oracles.len() + 1, violations.len());
+        for oracle in &oracles {
+            assert!(violations.contains(&PolicyViolation::OracleNotAllowed(*oracle)));
+        }
+        assert!(violations.contains(&PolicyViolation::DescriptorTypeNotAllowed(descriptor_type)));
+
+        policy.allowed_oracles = Some(oracles);
+        policy.allowed_descriptor_types = Some(vec![descriptor_type]);
+        assert!(policy.evaluate(&offered_contract, NOW).is_empty());
+    }
+}
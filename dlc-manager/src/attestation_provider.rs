@@ -0,0 +1,172 @@
+//! #Sources of oracle attestations used by the Manager in addition to its
+//! oracles.
+
+use crate::error::Error;
+use dlc_messages::oracle_msgs::OracleAttestation;
+use secp256k1_zkp::schnorrsig::PublicKey as SchnorrPublicKey;
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Provides attestations from a given transport (e.g. an HTTP oracle
+/// endpoint, a Nostr relay or attestations provided manually). Attestations
+/// returned by a provider are verified by the [`crate::manager::Manager`]
+/// against the announcement of the contract before being used, so providers
+/// do not need to be trusted.
+pub trait AttestationProvider {
+    /// Returns the name of the provider, used for logging.
+    fn get_name(&self) -> String;
+    /// Returns the attestation of the given oracle for the event with the
+    /// given id, or `None` if the provider does not have it (yet).
+    fn get_attestation(
+        &self,
+        oracle_public_key: &SchnorrPublicKey,
+        event_id: &str,
+    ) -> Result<Option<OracleAttestation>, Error>;
+}
+
+/// An [`AttestationProvider`] registered on the manager, together with the
+/// time after which a request to it is abandoned.
+pub(crate) struct AttestationSource {
+    pub(crate) provider: Arc<dyn AttestationProvider + Send + Sync>,
+    pub(crate) timeout: Duration,
+}
+
+impl AttestationSource {
+    /// Requests an attestation from the provider on a separate thread, so that
+    /// a provider that does not answer within the timeout of the source does
+    /// not block the processing of other contracts.
+    pub(crate) fn get_attestation(
+        &self,
+        oracle_public_key: &SchnorrPublicKey,
+        event_id: &str,
+    ) -> Result<Option<OracleAttestation>, Error> {
+        let (sender, receiver) = mpsc::channel();
+        let provider = Arc::clone(&self.provider);
+        let oracle_public_key = *oracle_public_key;
+        let event_id = event_id.to_string();
+        std::thread::spawn(move || {
+            // The receiver is dropped if the request timed out.
+            let _ = sender.send(provider.get_attestation(&oracle_public_key, &event_id));
+        });
+        receiver.recv_timeout(self.timeout).map_err(|_| {
+            Error::OracleError(format!(
+                "{} did not provide an attestation within {:?}",
+                self.provider.get_name(),
+                self.timeout
+            ))
+        })?
+    }
+}
+
+/// An [`AttestationProvider`] returning the attestations that were added to
+/// it, enabling attestations obtained out of band (e.g. through an API of the
+/// application) to be used to close contracts.
+#[derive(Default)]
+pub struct ManualAttestationProvider {
+    attestations: Mutex<HashMap<(SchnorrPublicKey, String), OracleAttestation>>,
+}
+
+impl ManualAttestationProvider {
+    /// Creates a provider without any attestation.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an attestation for the event with the given id, replacing any
+    /// attestation previously added for the same oracle and event.
+    pub fn add_attestation(&self, event_id: &str, attestation: OracleAttestation) {
+        self.attestations.lock().unwrap().insert(
+            (attestation.oracle_public_key, event_id.to_string()),
+            attestation,
+        );
+    }
+}
+
+impl AttestationProvider for ManualAttestationProvider {
+    fn get_name(&self) -> String {
+        "manual attestation provider".to_string()
+    }
+
+    fn get_attestation(
+        &self,
+        oracle_public_key: &SchnorrPublicKey,
+        event_id: &str,
+    ) -> Result<Option<OracleAttestation>, Error> {
+        Ok(self
+            .attestations
+            .lock()
+            .unwrap()
+            .get(&(*oracle_public_key, event_id.to_string()))
+            .cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1_zkp::rand::thread_rng;
+    use secp256k1_zkp::schnorrsig::KeyPair;
+    use secp256k1_zkp::Secp256k1;
+
+    struct SlowProvider {}
+
+    impl AttestationProvider for SlowProvider {
+        fn get_name(&self) -> String {
+            "slow provider".to_string()
+        }
+
+        fn get_attestation(
+            &self,
+            _: &SchnorrPublicKey,
+            _: &str,
+        ) -> Result<Option<OracleAttestation>, Error> {
+            std::thread::sleep(Duration::from_secs(1));
+            Ok(None)
+        }
+    }
+
+    fn get_oracle_public_key() -> SchnorrPublicKey {
+        let secp = Secp256k1::new();
+        let key_pair = KeyPair::new(&secp, &mut thread_rng());
+        SchnorrPublicKey::from_keypair(&secp, &key_pair)
+    }
+
+    #[test]
+    fn manual_provider_returns_added_attestation_test() {
+        let oracle_public_key = get_oracle_public_key();
+        let attestation = OracleAttestation {
+            oracle_public_key,
+            signatures: Vec::new(),
+            outcomes: Vec::new(),
+        };
+        let provider = ManualAttestationProvider::new();
+        provider.add_attestation("event", attestation.clone());
+        let source = AttestationSource {
+            provider: Arc::new(provider),
+            timeout: Duration::from_secs(1),
+        };
+
+        assert_eq!(
+            Some(attestation),
+            source.get_attestation(&oracle_public_key, "event").unwrap()
+        );
+        assert_eq!(
+            None,
+            source.get_attestation(&oracle_public_key, "other").unwrap()
+        );
+    }
+
+    #[test]
+    fn slow_provider_times_out_test() {
+        let source = AttestationSource {
+            provider: Arc::new(SlowProvider {}),
+            timeout: Duration::from_millis(10),
+        };
+
+        assert!(source
+            .get_attestation(&get_oracle_public_key(), "event")
+            .is_err());
+    }
+}
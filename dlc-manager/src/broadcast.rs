@@ -0,0 +1,93 @@
+//! #Interception of the transactions broadcast by the Manager.
+
+use crate::ContractId;
+use bitcoin::Transaction;
+use std::fmt;
+
+/// The maximum number of attempts made to broadcast a transaction through the
+/// pending broadcast queue before giving up on it.
+pub const MAX_BROADCAST_ATTEMPTS: u32 = 10;
+
+/// The kind of transaction of a contract to be broadcast.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BroadcastKind {
+    /// The fund transaction of the contract.
+    Fund,
+    /// A CET of the contract.
+    Cet,
+    /// The refund transaction of the contract.
+    Refund,
+}
+
+impl fmt::Display for BroadcastKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BroadcastKind::Fund => write!(f, "Fund transaction"),
+            BroadcastKind::Cet => write!(f, "CET"),
+            BroadcastKind::Refund => write!(f, "Refund transaction"),
+        }
+    }
+}
+
+/// A transaction that the manager wants to broadcast, stored in the pending
+/// broadcast queue while it is delayed or after a failed attempt.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PendingBroadcast {
+    /// The id of the contract the transaction belongs to.
+    pub contract_id: ContractId,
+    /// The kind of the transaction.
+    pub kind: BroadcastKind,
+    /// The transaction to broadcast.
+    pub transaction: Transaction,
+    /// The unix time before which the transaction is not broadcast.
+    pub not_before: u64,
+    /// The number of attempts made to broadcast the transaction.
+    pub nb_attempts: u32,
+}
+
+/// The decision of a [`BroadcastInterceptor`] about a transaction.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BroadcastDecision {
+    /// Broadcast the transaction as is.
+    Approve,
+    /// Put the transaction in the pending broadcast queue for the given
+    /// number of seconds, after which the interceptor is asked again.
+    Delay(u64),
+    /// Broadcast the given transaction instead. As the other transactions of
+    /// the contract depend on it, it must have the same txid as the original
+    /// one and can thus only differ by its witnesses.
+    Replace(Transaction),
+    /// Broadcast the transaction followed by the given child transaction,
+    /// which must spend one of its outputs, e.g. an anchor output to bump the
+    /// fee of the transaction through CPFP.
+    Cpfp(Transaction),
+}
+
+/// Intercepts the transactions that the manager wants to broadcast, giving the
+/// application control over when and how they are broadcast. Transactions that
+/// are delayed or that fail to be broadcast are kept in the pending broadcast
+/// queue of the [`crate::Storage`] and retried by
+/// [`crate::manager::Manager::periodic_check`].
+pub trait BroadcastInterceptor {
+    /// Called before each attempt to broadcast the given transaction.
+    fn on_broadcast(&self, pending: &PendingBroadcast) -> BroadcastDecision;
+}
+
+/// Returns the number of seconds to wait before retrying to broadcast a
+/// transaction after the given number of failed attempts.
+pub(crate) fn get_retry_delay(nb_attempts: u32) -> u64 {
+    60u64 << nb_attempts.min(6).saturating_sub(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_delay_test() {
+        assert_eq!(60, get_retry_delay(1));
+        assert_eq!(120, get_retry_delay(2));
+        assert_eq!(1920, get_retry_delay(6));
+        assert_eq!(1920, get_retry_delay(MAX_BROADCAST_ATTEMPTS));
+    }
+}
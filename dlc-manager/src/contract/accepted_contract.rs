@@ -2,9 +2,91 @@
 
 use super::offered_contract::OfferedContract;
 use super::{AdaptorInfo, FundingInputInfo};
+use crate::contract_tool;
+use crate::error::Error;
+use crate::ContractId;
 use dlc::{DlcTransactions, PartyParams};
+use dlc_messages::FundingInputProofs;
 use secp256k1_zkp::EcdsaAdaptorSignature;
-use secp256k1_zkp::Signature;
+use secp256k1_zkp::{All, Secp256k1, SecretKey, Signature};
+
+/// The acceptance of a received offer for which the funding inputs were
+/// selected and the transactions created, but for which the adaptor
+/// signatures were not computed yet. It does not depend on the
+/// [`crate::manager::Manager`], so that the computation, which is expensive
+/// for numerical contracts, can be carried out on another thread before
+/// committing the resulting [`AcceptedContract`] with
+/// [`crate::manager::Manager::commit_accepted_contract`].
+pub struct PendingAccept {
+    pub(crate) offered_contract: OfferedContract,
+    pub(crate) accept_params: PartyParams,
+    pub(crate) fund_secret_key: SecretKey,
+    pub(crate) funding_inputs: Vec<FundingInputInfo>,
+    pub(crate) funding_input_proofs: Option<FundingInputProofs>,
+    pub(crate) dlc_transactions: DlcTransactions,
+}
+
+impl PendingAccept {
+    /// Returns the offered contract being accepted.
+    pub fn get_offered_contract(&self) -> &OfferedContract {
+        &self.offered_contract
+    }
+
+    /// Computes the adaptor and refund signatures of the accepting party,
+    /// returning the accepted contract.
+    pub fn compute(self, secp: &Secp256k1<All>) -> Result<AcceptedContract, Error> {
+        let PendingAccept {
+            offered_contract,
+            accept_params,
+            fund_secret_key,
+            funding_inputs,
+            funding_input_proofs,
+            dlc_transactions,
+        } = self;
+
+        let fund_output_value = dlc_transactions.get_fund_output().value;
+
+        let (adaptor_infos, adaptor_sigs, cets) = contract_tool::sign_cets(
+            secp,
+            &offered_contract,
+            &accept_params,
+            &dlc_transactions,
+            &fund_secret_key,
+        )?;
+
+        let DlcTransactions {
+            fund,
+            refund,
+            funding_script_pubkey,
+            ..
+        } = dlc_transactions;
+
+        let accept_refund_signature = dlc::util::get_raw_sig_for_tx_input(
+            secp,
+            &refund,
+            0,
+            &funding_script_pubkey,
+            fund_output_value,
+            &fund_secret_key,
+        );
+
+        Ok(AcceptedContract {
+            offered_contract,
+            accept_params,
+            funding_inputs,
+            funding_input_proofs,
+            adaptor_infos,
+            adaptor_signatures: Some(adaptor_sigs),
+            accept_refund_signature,
+            dlc_transactions: DlcTransactions {
+                fund,
+                cets,
+                refund,
+                funding_script_pubkey,
+            },
+        })
+    }
+}
 
 /// An AcceptedContract represents a contract in the accepted state.
 #[derive(Clone)]
@@ -15,11 +97,16 @@ pub struct AcceptedContract {
     pub accept_params: PartyParams,
     /// The funding inputs provided by the accepting party.
     pub funding_inputs: Vec<FundingInputInfo>,
+    /// The proofs that the accepting party controls the outputs spent by its
+    /// funding inputs.
+    pub funding_input_proofs: Option<FundingInputProofs>,
     /// The adaptor information for the contract storing information about
     /// the relation between adaptor signatures and outcomes.
     pub adaptor_infos: Vec<AdaptorInfo>,
-    /// The adaptor signatures of the accepting party. Note that the accepting
-    /// party does not keep them thus an option is used.
+    /// The adaptor signatures of the accepting party. They are kept by the
+    /// accepting party to be able to recover the adaptor secret from a CET
+    /// broadcast by the offering party, but may be missing for contracts
+    /// created with earlier versions.
     pub adaptor_signatures: Option<Vec<EcdsaAdaptorSignature>>,
     /// The signature for the refund transaction from the accepting party.
     pub accept_refund_signature: Signature,
@@ -30,39 +117,30 @@ pub struct AcceptedContract {
 impl AcceptedContract {
     /// Returns the contract id for the contract computed as specified here:
     /// https://github.com/discreetlogcontracts/dlcspecs/blob/master/Protocol.md#requirements-2
-    pub fn get_contract_id(&self) -> [u8; 32] {
-        let fund_output_index = self.dlc_transactions.get_fund_output_index();
-        let contract_id_vec: Vec<_> = self
-            .dlc_transactions
-            .fund
-            .txid()
-            .as_ref()
-            .iter()
-            .zip(
-                std::iter::repeat(&(0_u8))
-                    .take(28)
-                    .chain((fund_output_index as u32).to_be_bytes().iter()),
-            )
-            .zip(self.offered_contract.id.iter())
-            .map(|((x, y), z)| x ^ y ^ z)
-            .collect();
-
-        let mut contract_id = [0u8; 32];
-
-        contract_id[..32].clone_from_slice(&contract_id_vec[..32]);
-
-        contract_id
+    pub fn get_contract_id(&self) -> ContractId {
+        dlc_messages::compute_contract_id(
+            self.dlc_transactions.fund.txid(),
+            self.dlc_transactions.get_fund_output_index() as u16,
+            self.offered_contract.id,
+        )
     }
 
     /// Utility function to get the contract id as a string.
     pub fn get_contract_id_string(&self) -> String {
-        let mut string_id = String::with_capacity(32 * 2 + 2);
-        string_id.push_str("0x");
-        let id = self.get_contract_id();
-        for i in &id {
-            string_id.push_str(&std::format!("{:02x}", i));
-        }
+        crate::utils::get_contract_id_string(&self.get_contract_id())
+    }
 
-        string_id
+    /// Returns the fee paid by the fund transaction, that is the input amount
+    /// of both parties minus the value of its outputs.
+    pub fn get_fund_tx_fee(&self) -> u64 {
+        let output_value: u64 = self
+            .dlc_transactions
+            .fund
+            .output
+            .iter()
+            .map(|x| x.value)
+            .sum();
+        (self.offered_contract.offer_params.input_amount + self.accept_params.input_amount)
+            .saturating_sub(output_value)
     }
 }
@@ -4,15 +4,18 @@ use super::utils::get_majority_combination;
 use super::AdaptorInfo;
 use super::ContractDescriptor;
 use crate::error::Error;
-use bitcoin::{Script, Transaction};
-use dlc::{OracleInfo, Payout};
-use dlc_messages::oracle_msgs::{EventDescriptor, OracleAnnouncement};
+use bitcoin::{Script, Transaction, Txid};
+use dlc::{OracleInfo, Payout, RangePayout};
+use dlc_messages::oracle_msgs::{EventDescriptor, OracleAnnouncement, OracleAttestation};
 use dlc_trie::combination_iterator::CombinationIterator;
-use dlc_trie::{DlcTrie, RangeInfo};
+use dlc_trie::multi_oracle::CoverageStrategy;
+use dlc_trie::multi_oracle_trie::MultiOracleTrie;
+use dlc_trie::{get_cet_from_slice, DlcTrie, RangeInfo};
+use secp256k1_zkp::schnorrsig::Signature as SchnorrSignature;
 use secp256k1_zkp::{
-    bitcoin_hashes::sha256, All, EcdsaAdaptorSignature, Message, PublicKey, Secp256k1, SecretKey,
-    Verification,
+    All, EcdsaAdaptorSignature, PublicKey, Secp256k1, SecretKey, Signature, Verification,
 };
+use std::cmp::Ordering;
 
 pub(super) type OracleIndexAndPrefixLength = Vec<(usize, usize)>;
 
@@ -33,12 +36,246 @@ pub struct ContractInfo {
     pub threshold: usize,
 }
 
+/// A range of outcomes of a numerical or hybrid contract together with the CET
+/// enforcing its payout and the adaptor signatures completing it, as returned
+/// by [`ContractInfo::get_range_payout_cets`].
+#[derive(Debug)]
+pub struct RangePayoutCet {
+    /// The index of the branch of a hybrid contract the range belongs to,
+    /// `None` for numerical contracts.
+    pub branch_index: Option<usize>,
+    /// The range of outcomes and its payout.
+    pub range_payout: RangePayout,
+    /// The index of the CET among the given CETs.
+    pub cet_index: usize,
+    /// The id of the CET.
+    pub cet_txid: Txid,
+    /// The indexes of the adaptor signatures of the CET, one per combination
+    /// of oracle digit prefixes covering the range.
+    pub adaptor_indexes: Vec<usize>,
+}
+
 impl ContractInfo {
     /// Get the payouts associated with the contract.
-    pub fn get_payouts(&self, total_collateral: u64) -> Vec<Payout> {
+    pub fn get_payouts(&self, total_collateral: u64) -> Result<Vec<Payout>, Error> {
         match &self.contract_descriptor {
-            ContractDescriptor::Enum(e) => e.get_payouts(),
+            ContractDescriptor::Enum(e) => Ok(e.get_payouts()),
             ContractDescriptor::Numerical(n) => n.get_payouts(total_collateral),
+            ContractDescriptor::Hybrid(h) => h.get_payouts(total_collateral),
+        }
+    }
+
+    /// Returns an upper bound on the number of adaptor signatures required by
+    /// the contract, computed without building them so that the cost of
+    /// accepting an offer can be assessed beforehand. For numerical contracts,
+    /// each range of outcomes with the same payout is covered by at most
+    /// `2 * (base - 1) * nb_digits` digit prefixes, each of them combined with
+    /// at most `3^(threshold - 1)` prefixes of the other oracles when outcome
+    /// differences are allowed between them.
+    pub fn get_max_nb_adaptor_signatures(&self, total_collateral: u64) -> Result<usize, Error> {
+        let nb_per_combination = match &self.contract_descriptor {
+            ContractDescriptor::Enum(e) => e.outcome_payouts.len(),
+            ContractDescriptor::Numerical(n) => {
+                let nb_prefixes = n
+                    .get_range_payouts(total_collateral)?
+                    .len()
+                    .saturating_mul(2 * n.info.base.saturating_sub(1) * n.info.nb_digits);
+                match n.difference_params {
+                    Some(_) => nb_prefixes.saturating_mul(
+                        3usize.saturating_pow(self.threshold.saturating_sub(1) as u32),
+                    ),
+                    None => nb_prefixes,
+                }
+            }
+            ContractDescriptor::Hybrid(h) => h
+                .get_range_payouts(total_collateral)?
+                .iter()
+                .map(|x| x.len())
+                .sum::<usize>()
+                .saturating_mul(2 * h.info.base.saturating_sub(1) * h.info.nb_digits),
+        };
+
+        let nb_oracles = self.oracle_announcements.len();
+        let nb_combinations = (0..self.threshold.min(nb_oracles)).fold(1usize, |acc, i| {
+            acc.saturating_mul(nb_oracles - i) / (i + 1)
+        });
+
+        Ok(nb_per_combination.saturating_mul(nb_combinations))
+    }
+
+    /// Returns each range of outcomes of a numerical or hybrid contract with
+    /// the CET and the indexes of the adaptor signatures enforcing its payout,
+    /// so that the step function enforced by a signed contract can be checked
+    /// against its payout curve. `cets` must be the CETs of this contract
+    /// information, in order.
+    pub fn get_range_payout_cets(
+        &self,
+        adaptor_info: &AdaptorInfo,
+        total_collateral: u64,
+        cets: &[Transaction],
+    ) -> Result<Vec<RangePayoutCet>, Error> {
+        let (range_payouts, range_infos): (Vec<(Option<usize>, RangePayout)>, Vec<RangeInfo>) =
+            match (&self.contract_descriptor, adaptor_info) {
+                (ContractDescriptor::Numerical(n), AdaptorInfo::Numerical(trie)) => (
+                    n.get_range_payouts(total_collateral)?
+                        .into_iter()
+                        .map(|x| (None, x))
+                        .collect(),
+                    trie.get_range_infos(),
+                ),
+                (ContractDescriptor::Numerical(n), AdaptorInfo::NumericalWithDifference(trie)) => (
+                    n.get_range_payouts(total_collateral)?
+                        .into_iter()
+                        .map(|x| (None, x))
+                        .collect(),
+                    trie.get_range_infos(),
+                ),
+                (ContractDescriptor::Numerical(n), AdaptorInfo::CompactNumerical(trie)) => (
+                    n.get_range_payouts(total_collateral)?
+                        .into_iter()
+                        .map(|x| (None, x))
+                        .collect(),
+                    trie.get_range_infos(),
+                ),
+                (ContractDescriptor::Hybrid(h), AdaptorInfo::Hybrid(tries)) => (
+                    h.get_range_payouts(total_collateral)?
+                        .into_iter()
+                        .enumerate()
+                        .flat_map(|(i, x)| x.into_iter().map(move |y| (Some(i), y)))
+                        .collect(),
+                    tries
+                        .iter()
+                        .flat_map(|(cet_offset, trie)| {
+                            trie.get_range_infos().into_iter().map(move |x| RangeInfo {
+                                cet_index: cet_offset + x.cet_index,
+                                adaptor_index: x.adaptor_index,
+                            })
+                        })
+                        .collect(),
+                ),
+                _ => {
+                    return Err(Error::InvalidParameters(
+                        "Only numerical and hybrid contracts have range payouts.".to_string(),
+                    ))
+                }
+            };
+
+        if range_payouts.len() != cets.len() {
+            return Err(Error::InvalidParameters(
+                "The number of CETs does not match the number of range payouts.".to_string(),
+            ));
+        }
+
+        let mut adaptor_indexes = vec![Vec::new(); cets.len()];
+        for range_info in range_infos {
+            adaptor_indexes
+                .get_mut(range_info.cet_index)
+                .ok_or_else(|| {
+                    Error::InvalidParameters("Invalid CET index in adaptor info.".to_string())
+                })?
+                .push(range_info.adaptor_index);
+        }
+
+        Ok(range_payouts
+            .into_iter()
+            .zip(cets.iter())
+            .zip(adaptor_indexes.into_iter())
+            .enumerate()
+            .map(
+                |(cet_index, (((branch_index, range_payout), cet), adaptor_indexes))| {
+                    RangePayoutCet {
+                        branch_index,
+                        range_payout,
+                        cet_index,
+                        cet_txid: cet.txid(),
+                        adaptor_indexes,
+                    }
+                },
+            )
+            .collect())
+    }
+
+    /// Returns the payout of each of the given outcomes as enforced by the
+    /// CETs of the contract: payout functions are evaluated and rounded
+    /// according to the rounding intervals, and the result is bounded by the
+    /// total collateral. Payout outputs under the dust limit, which are omitted
+    /// from CETs, are not removed. Only numerical contracts are supported, and
+    /// an error is returned for outcomes that are not covered by any CET.
+    pub fn simulate(
+        &self,
+        outcomes: impl Iterator<Item = u64>,
+        total_collateral: u64,
+    ) -> Result<Vec<(u64, Payout)>, Error> {
+        let range_payouts = match &self.contract_descriptor {
+            ContractDescriptor::Numerical(n) => n.get_range_payouts(total_collateral)?,
+            ContractDescriptor::Enum(_) | ContractDescriptor::Hybrid(_) => {
+                return Err(Error::InvalidParameters(
+                    "Simulation is only supported for numerical contracts.".to_string(),
+                ))
+            }
+        };
+
+        outcomes
+            .map(|outcome| {
+                let index = range_payouts
+                    .binary_search_by(|x| {
+                        if ((x.start + x.count) as u64) <= outcome {
+                            Ordering::Less
+                        } else if x.start as u64 > outcome {
+                            Ordering::Greater
+                        } else {
+                            Ordering::Equal
+                        }
+                    })
+                    .map_err(|_| {
+                        Error::InvalidParameters(format!(
+                            "Outcome {} is not covered by the contract.",
+                            outcome
+                        ))
+                    })?;
+                Ok((outcome, range_payouts[index].payout.clone()))
+            })
+            .collect()
+    }
+
+    /// Checks that the payouts of the contract are within the total collateral.
+    /// Only contracts whose payouts are given by the sum of multiple payout
+    /// functions are checked, as the payouts of other ones are bounded by
+    /// construction. For numerical contracts, also checks that the oracle
+    /// events decompose outcomes as expected by the contract, that the
+    /// payout functions cover the outcomes up to the maximum value of the
+    /// contract if any without extending past the largest outcome the oracles
+    /// can attest, and that their difference parameters can be exchanged
+    /// in offer messages. Hybrid contracts are also checked against the events
+    /// selecting their branches.
+    pub fn validate(&self, total_collateral: u64) -> Result<(), Error> {
+        match &self.contract_descriptor {
+            ContractDescriptor::Numerical(n) => {
+                if let Some(params) = &n.difference_params {
+                    if !params
+                        .coverage_strategy
+                        .is_valid_for(params.max_error_exp, params.min_support_exp)
+                    {
+                        return Err(Error::InvalidParameters(
+                            "Minimum support must be lower than maximum error.".to_string(),
+                        ));
+                    }
+                    if let CoverageStrategy::Custom { .. } = params.coverage_strategy {
+                        return Err(Error::InvalidParameters(
+                            "Custom coverage strategies cannot be indicated in offers.".to_string(),
+                        ));
+                    }
+                }
+                n.validate_outcome_bounds(&self.oracle_announcements)?;
+                if !n.additional_payout_functions.is_empty() {
+                    n.get_range_payouts(total_collateral)?;
+                }
+                Ok(())
+            }
+            ContractDescriptor::Enum(e) => e.validate(&self.oracle_announcements, total_collateral),
+            ContractDescriptor::Hybrid(h) => {
+                h.validate(&self.oracle_announcements, total_collateral)
+            }
         }
     }
 
@@ -77,7 +314,7 @@ impl ContractInfo {
                 fund_privkey,
                 funding_script_pubkey,
                 fund_output_value,
-                cets,
+                &|i| get_cet_from_slice(cets, i),
                 &self.precompute_points(secp)?,
             )?),
             AdaptorInfo::NumericalWithDifference(trie) => Ok(trie.sign(
@@ -85,9 +322,29 @@ impl ContractInfo {
                 fund_privkey,
                 funding_script_pubkey,
                 fund_output_value,
-                cets,
+                &|i| get_cet_from_slice(cets, i),
                 &self.precompute_points(secp)?,
             )?),
+            AdaptorInfo::CompactNumerical(trie) => Ok(trie.sign(
+                secp,
+                fund_privkey,
+                funding_script_pubkey,
+                fund_output_value,
+                &|i| get_cet_from_slice(cets, i),
+                &self.precompute_points(secp)?,
+            )?),
+            AdaptorInfo::Hybrid(tries) => match &self.contract_descriptor {
+                ContractDescriptor::Hybrid(h) => h.get_adaptor_signatures(
+                    secp,
+                    tries,
+                    fund_privkey,
+                    funding_script_pubkey,
+                    fund_output_value,
+                    &self.precompute_points(secp)?,
+                    cets,
+                ),
+                _ => unreachable!(),
+            },
         }
     }
 
@@ -129,6 +386,18 @@ impl ContractInfo {
                 adaptor_sigs,
                 adaptor_sig_start,
             )?),
+            ContractDescriptor::Hybrid(h) => h.verify_and_get_adaptor_info(
+                secp,
+                total_collateral,
+                fund_pubkey,
+                funding_script_pubkey,
+                fund_output_value,
+                self.threshold,
+                &self.precompute_points(secp)?,
+                cets,
+                adaptor_sigs,
+                adaptor_sig_start,
+            ),
         }
     }
 
@@ -166,25 +435,28 @@ impl ContractInfo {
                 let (s_outcomes, actual_combination) = get_majority_combination(outcomes)?;
                 let digits_outcome = get_digits_outcome(&s_outcomes)?;
 
-                let res = n
-                    .digit_trie
-                    .look_up(&digits_outcome)
-                    .ok_or(crate::error::Error::InvalidState)?;
-
+                Ok(Some(self.look_up_trie(
+                    n,
+                    &digits_outcome,
+                    actual_combination,
+                )?))
+            }
+            AdaptorInfo::CompactNumerical(trie) => {
+                let (s_outcomes, actual_combination) = get_majority_combination(outcomes)?;
+                let digits_outcome = get_digits_outcome(&s_outcomes)?;
                 let sufficient_combination: Vec<_> = actual_combination
                     .into_iter()
                     .take(self.threshold)
                     .collect();
-                let position =
-                    CombinationIterator::new(self.oracle_announcements.len(), self.threshold)
-                        .get_index_for_combination(&sufficient_combination)
-                        .ok_or(crate::error::Error::InvalidState)?;
+                let (path_len, range_info) = trie
+                    .look_up(&digits_outcome, &sufficient_combination)
+                    .ok_or(Error::InvalidState)?;
                 Ok(Some((
                     sufficient_combination
                         .iter()
-                        .map(|x| (*x, res[0].path.len()))
+                        .map(|x| (*x, path_len))
                         .collect(),
-                    res[0].value[position].clone(),
+                    range_info,
                 )))
             }
             AdaptorInfo::NumericalWithDifference(n) => {
@@ -202,9 +474,63 @@ impl ContractInfo {
                     res.value.clone(),
                 )))
             }
+            // The outcomes of each oracle are expected to start with the
+            // outcome of the selector event, followed by the digits of the
+            // numerical event.
+            AdaptorInfo::Hybrid(tries) => match &self.contract_descriptor {
+                ContractDescriptor::Hybrid(h) => {
+                    let (s_outcomes, actual_combination) = get_majority_combination(outcomes)?;
+                    let (selector_outcome, digits) = s_outcomes
+                        .split_first()
+                        .ok_or(crate::error::Error::InvalidState)?;
+                    let (cet_offset, trie) = match h.get_branch_index(selector_outcome) {
+                        Some(index) => &tries[index],
+                        None => return Ok(None),
+                    };
+                    let digits_outcome = get_digits_outcome(digits)?;
+
+                    let (sig_infos, range_info) =
+                        self.look_up_trie(trie, &digits_outcome, actual_combination)?;
+                    Ok(Some((
+                        sig_infos.into_iter().map(|(x, y)| (x, y + 1)).collect(),
+                        RangeInfo {
+                            cet_index: cet_offset + range_info.cet_index,
+                            adaptor_index: range_info.adaptor_index,
+                        },
+                    )))
+                }
+                _ => unreachable!(),
+            },
         }
     }
 
+    fn look_up_trie(
+        &self,
+        trie: &MultiOracleTrie,
+        digits_outcome: &[usize],
+        actual_combination: Vec<usize>,
+    ) -> Result<(OracleIndexAndPrefixLength, RangeInfo), Error> {
+        let res = trie
+            .digit_trie
+            .look_up(digits_outcome)
+            .ok_or(Error::InvalidState)?;
+
+        let sufficient_combination: Vec<_> = actual_combination
+            .into_iter()
+            .take(self.threshold)
+            .collect();
+        let position = CombinationIterator::new(self.oracle_announcements.len(), self.threshold)
+            .get_index_for_combination(&sufficient_combination)
+            .ok_or(Error::InvalidState)?;
+        Ok((
+            sufficient_combination
+                .iter()
+                .map(|x| (*x, res[0].path.len()))
+                .collect(),
+            res[0].value[position].clone(),
+        ))
+    }
+
     /// Verifies the given adaptor signatures are valid with respect to the given
     /// adaptor info.
     pub fn verify_adaptor_info(
@@ -239,7 +565,7 @@ impl ContractInfo {
                     funding_script_pubkey,
                     fund_output_value,
                     adaptor_sigs,
-                    cets,
+                    &|i| get_cet_from_slice(cets, i),
                     &self.precompute_points(secp)?,
                 )?),
                 AdaptorInfo::NumericalWithDifference(trie) => Ok(trie.verify(
@@ -248,11 +574,221 @@ impl ContractInfo {
                     funding_script_pubkey,
                     fund_output_value,
                     adaptor_sigs,
-                    cets,
+                    &|i| get_cet_from_slice(cets, i),
                     &self.precompute_points(secp)?,
                 )?),
+                AdaptorInfo::CompactNumerical(trie) => Ok(trie.verify(
+                    secp,
+                    fund_pubkey,
+                    funding_script_pubkey,
+                    fund_output_value,
+                    adaptor_sigs,
+                    &|i| get_cet_from_slice(cets, i),
+                    &self.precompute_points(secp)?,
+                )?),
+                AdaptorInfo::Hybrid(_) => unreachable!(),
+            },
+            ContractDescriptor::Hybrid(h) => match adaptor_info {
+                AdaptorInfo::Hybrid(tries) => h.verify_adaptor_info(
+                    secp,
+                    tries,
+                    fund_pubkey,
+                    funding_script_pubkey,
+                    fund_output_value,
+                    &self.precompute_points(secp)?,
+                    cets,
+                    adaptor_sigs,
+                    adaptor_sig_start,
+                ),
+                _ => unreachable!(),
+            },
+        }
+    }
+
+    /// Recovers the adaptor secret used to decrypt one of the given adaptor
+    /// signatures from the signatures contained in the witness of the given
+    /// published CET. Returns `None` if none of the signatures of the CET was
+    /// decrypted from an adaptor signature related to it.
+    pub fn recover_adaptor_secret(
+        &self,
+        secp: &Secp256k1<All>,
+        adaptor_info: &AdaptorInfo,
+        adaptor_sigs: &[EcdsaAdaptorSignature],
+        cet_index: usize,
+        cet: &Transaction,
+    ) -> Result<Option<SecretKey>, Error> {
+        Ok(self
+            .recover_adaptor_secret_and_index(secp, adaptor_info, adaptor_sigs, cet_index, cet)?
+            .map(|(_, secret)| secret))
+    }
+
+    /// Reconstructs the oracle attestation that was used to decrypt one of the
+    /// given adaptor signatures from the signatures of the given published CET.
+    /// Returns `None` if none of the signatures of the CET was decrypted from
+    /// an adaptor signature related to it. As the adaptor secret is the sum of
+    /// the oracle signatures it was computed from, the attestation can only be
+    /// reconstructed if the adaptor point is made of a single oracle signature
+    /// (e.g. for enumerated outcome contracts using a single oracle), an error
+    /// is returned otherwise.
+    pub fn recover_attestation_from_cet(
+        &self,
+        secp: &Secp256k1<All>,
+        published_tx: &Transaction,
+        adaptor_info: &AdaptorInfo,
+        adaptor_sigs: &[EcdsaAdaptorSignature],
+        cet_index: usize,
+    ) -> Result<Option<OracleAttestation>, Error> {
+        let (adaptor_index, secret) = match self.recover_adaptor_secret_and_index(
+            secp,
+            adaptor_info,
+            adaptor_sigs,
+            cet_index,
+            published_tx,
+        )? {
+            Some(res) => res,
+            None => return Ok(None),
+        };
+
+        let oracle_outcomes: Vec<(usize, Vec<String>)> = match adaptor_info {
+            AdaptorInfo::Enum => match &self.contract_descriptor {
+                ContractDescriptor::Enum(e) => {
+                    let nb_combinations =
+                        CombinationIterator::new(self.oracle_announcements.len(), self.threshold)
+                            .count();
+                    let outcome = &e
+                        .outcome_payouts
+                        .get(adaptor_index / nb_combinations)
+                        .ok_or(Error::InvalidState)?
+                        .outcome;
+                    CombinationIterator::new(self.oracle_announcements.len(), self.threshold)
+                        .nth(adaptor_index % nb_combinations)
+                        .ok_or(Error::InvalidState)?
+                        .into_iter()
+                        .map(|i| (i, vec![outcome.clone()]))
+                        .collect()
+                }
+                _ => unreachable!(),
+            },
+            AdaptorInfo::Numerical(trie) => trie
+                .get_oracle_paths_for_adaptor_index(adaptor_index)
+                .ok_or(Error::InvalidState)?
+                .into_iter()
+                .map(|(i, path)| (i, path.iter().map(|x| x.to_string()).collect()))
+                .collect(),
+            AdaptorInfo::NumericalWithDifference(trie) => trie
+                .get_oracle_paths_for_adaptor_index(adaptor_index)
+                .ok_or(Error::InvalidState)?
+                .into_iter()
+                .map(|(i, path)| (i, path.iter().map(|x| x.to_string()).collect()))
+                .collect(),
+            AdaptorInfo::CompactNumerical(trie) => trie
+                .get_oracle_paths_for_adaptor_index(adaptor_index)
+                .ok_or(Error::InvalidState)?
+                .into_iter()
+                .map(|(i, path)| (i, path.iter().map(|x| x.to_string()).collect()))
+                .collect(),
+            // Adaptor secrets of hybrid contracts always combine the
+            // signatures of both events.
+            AdaptorInfo::Hybrid(_) => Vec::new(),
+        };
+
+        let (oracle_index, outcome) = match oracle_outcomes.as_slice() {
+            [(oracle_index, outcomes)] if outcomes.len() == 1 => (*oracle_index, &outcomes[0]),
+            _ => {
+                return Err(Error::InvalidParameters(
+                    "Cannot split adaptor secret into oracle signatures.".to_string(),
+                ))
+            }
+        };
+
+        let announcement = &self.oracle_announcements[oracle_index];
+        let nonce = announcement
+            .oracle_event
+            .oracle_nonces
+            .get(0)
+            .ok_or(Error::InvalidState)?;
+        let mut sig_bytes = Vec::with_capacity(64);
+        sig_bytes.extend_from_slice(&nonce.serialize());
+        sig_bytes.extend_from_slice(&secret[..]);
+        let signature = SchnorrSignature::from_slice(&sig_bytes).map_err(dlc::Error::from)?;
+
+        let msg = dlc::get_outcome_message(outcome);
+        secp.schnorrsig_verify(&signature, &msg, &announcement.oracle_public_key)
+            .map_err(dlc::Error::from)?;
+
+        Ok(Some(OracleAttestation {
+            oracle_public_key: announcement.oracle_public_key,
+            signatures: vec![signature],
+            outcomes: vec![outcome.clone()],
+        }))
+    }
+
+    fn recover_adaptor_secret_and_index(
+        &self,
+        secp: &Secp256k1<All>,
+        adaptor_info: &AdaptorInfo,
+        adaptor_sigs: &[EcdsaAdaptorSignature],
+        cet_index: usize,
+        cet: &Transaction,
+    ) -> Result<Option<(usize, SecretKey)>, Error> {
+        let adaptor_points = match adaptor_info {
+            AdaptorInfo::Enum => match &self.contract_descriptor {
+                ContractDescriptor::Enum(e) => e.get_adaptor_points_for_cet(
+                    secp,
+                    &self.get_oracle_infos(),
+                    self.threshold,
+                    cet_index,
+                )?,
+                _ => unreachable!(),
+            },
+            AdaptorInfo::Numerical(trie) => {
+                trie.get_adaptor_points_for_cet(cet_index, &self.precompute_points(secp)?)?
+            }
+            AdaptorInfo::NumericalWithDifference(trie) => {
+                trie.get_adaptor_points_for_cet(cet_index, &self.precompute_points(secp)?)?
+            }
+            AdaptorInfo::CompactNumerical(trie) => {
+                trie.get_adaptor_points_for_cet(cet_index, &self.precompute_points(secp)?)?
+            }
+            AdaptorInfo::Hybrid(tries) => match &self.contract_descriptor {
+                ContractDescriptor::Hybrid(h) => h.get_adaptor_points_for_cet(
+                    secp,
+                    tries,
+                    &self.precompute_points(secp)?,
+                    cet_index,
+                )?,
+                _ => unreachable!(),
             },
+        };
+
+        // Witness elements of the funding output input are DER signatures
+        // followed by the sighash type, the multisig script is skipped as it
+        // does not parse.
+        let signatures: Vec<Signature> = match cet.input.get(0) {
+            Some(input) => input
+                .witness
+                .iter()
+                .filter(|x| !x.is_empty())
+                .filter_map(|x| Signature::from_der(&x[..x.len() - 1]).ok())
+                .collect(),
+            None => return Ok(None),
+        };
+
+        for (adaptor_index, adaptor_point) in adaptor_points {
+            let adaptor_sig = match adaptor_sigs.get(adaptor_index) {
+                Some(adaptor_sig) => adaptor_sig,
+                None => continue,
+            };
+            for sig in &signatures {
+                if let Ok(secret) = adaptor_sig.recover(secp, sig, &adaptor_point) {
+                    if PublicKey::from_secret_key(secp, &secret) == adaptor_point {
+                        return Ok(Some((adaptor_index, secret)));
+                    }
+                }
+            }
         }
+
+        Ok(None)
     }
 
     /// Generate the adaptor info and adaptor signatures for the contract.
@@ -290,6 +826,17 @@ impl ContractInfo {
                 cets,
                 adaptor_index_start,
             )?),
+            ContractDescriptor::Hybrid(h) => h.get_adaptor_info(
+                secp,
+                total_collateral,
+                fund_priv_key,
+                funding_script_pubkey,
+                fund_output_value,
+                self.threshold,
+                &self.precompute_points(secp)?,
+                cets,
+                adaptor_index_start,
+            ),
         }
     }
 
@@ -311,15 +858,15 @@ impl ContractInfo {
                                 "Number of digits and nonces must be equal".to_string(),
                             ));
                         }
+                        let digit_msgs = dlc::get_outcome_messages(
+                            &(0..base).map(|j| j.to_string()).collect::<Vec<_>>(),
+                        );
                         let mut d_points = Vec::with_capacity(nb_digits);
                         for nonce in nonces {
                             let mut points = Vec::with_capacity(base);
-                            for j in 0..base {
-                                let msg = Message::from_hashed_data::<sha256::Hash>(
-                                    j.to_string().as_bytes(),
-                                );
+                            for msg in &digit_msgs {
                                 let sig_point = dlc::secp_utils::schnorrsig_compute_sig_point(
-                                    secp, pubkey, nonce, &msg,
+                                    secp, pubkey, nonce, msg,
                                 )?;
                                 points.push(sig_point);
                             }
@@ -335,3 +882,85 @@ impl ContractInfo {
             .collect::<Result<Vec<Vec<Vec<PublicKey>>>, Error>>()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::descriptor_parser::parse_contract_descriptor;
+    use crate::contract::enum_descriptor::EnumDescriptor;
+
+    fn get_contract_info(input: &str) -> ContractInfo {
+        ContractInfo {
+            contract_descriptor: parse_contract_descriptor(input, 1000).unwrap(),
+            oracle_announcements: Vec::new(),
+            threshold: 1,
+        }
+    }
+
+    #[test]
+    fn simulate_applies_rounding_and_collateral_bounds() {
+        let contract_info = get_contract_info(
+            "numerical base 2 digits 10\nfrom 0 pay 0\nflat to 100\nlinear to 200 pay 1000\nflat to max\nround from 0 to 100",
+        );
+        let payouts: Vec<_> = contract_info
+            .simulate(vec![0, 100, 130, 150, 1023].into_iter(), 1000)
+            .unwrap()
+            .into_iter()
+            .map(|(outcome, payout)| (outcome, payout.offer, payout.accept))
+            .collect();
+
+        assert_eq!(
+            vec![
+                (0, 0, 1000),
+                (100, 0, 1000),
+                (130, 300, 700),
+                (150, 500, 500),
+                (1023, 1000, 0)
+            ],
+            payouts
+        );
+    }
+
+    #[test]
+    fn simulate_matches_range_payouts() {
+        let contract_info = get_contract_info(
+            "numerical base 2 digits 10\nfrom 0 pay 0\nlinear to 1023 pay 1000\nround from 0 to 50",
+        );
+        let range_payouts = match &contract_info.contract_descriptor {
+            ContractDescriptor::Numerical(n) => n.get_range_payouts(1000).unwrap(),
+            _ => unreachable!(),
+        };
+        let simulated = contract_info.simulate(0..1024, 1000).unwrap();
+
+        assert_eq!(1024, simulated.len());
+        for range_payout in range_payouts {
+            for outcome in range_payout.start..range_payout.start + range_payout.count {
+                assert_eq!(
+                    (outcome as u64, range_payout.payout.clone()),
+                    simulated[outcome]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn simulate_rejects_outcome_not_covered() {
+        let contract_info =
+            get_contract_info("numerical base 2 digits 10\nfrom 0 pay 0\nflat to max");
+        assert!(contract_info
+            .simulate(vec![1023, 1024].into_iter(), 1000)
+            .is_err());
+    }
+
+    #[test]
+    fn simulate_rejects_enum_contract() {
+        let contract_info = ContractInfo {
+            contract_descriptor: ContractDescriptor::Enum(EnumDescriptor {
+                outcome_payouts: Vec::new(),
+            }),
+            oracle_announcements: Vec::new(),
+            threshold: 1,
+        };
+        assert!(contract_info.simulate(vec![0].into_iter(), 1000).is_err());
+    }
+}
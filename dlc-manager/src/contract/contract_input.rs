@@ -1,6 +1,13 @@
 //! #ContractInput
 
+use super::contract_info::ContractInfo;
 use super::ContractDescriptor;
+use crate::error::Error;
+use crate::manager::REFUND_DELAY;
+use crate::TimeSource;
+use bitcoin::Script;
+use dlc::FeeSplit;
+use dlc_messages::oracle_msgs::OracleAnnouncement;
 use secp256k1_zkp::schnorrsig::PublicKey as SchnorrPublicKey;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -23,6 +30,43 @@ pub struct OracleInput {
     pub threshold: u16,
 }
 
+/// Information about a premium to be paid through the fund transaction.
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct PremiumInput {
+    /// The amount of the premium.
+    pub amount: u64,
+    /// Whether the premium is paid by the offering party to the accepting
+    /// party (otherwise it is paid by the accepting party to the offering
+    /// party).
+    pub paid_by_offer: bool,
+}
+
+/// Policy applied to the change output of a party in the fund transaction.
+/// The change of all the inputs contributed by a party is always merged into a
+/// single output. As change outputs whose value is lower than
+/// [`dlc::DUST_LIMIT`] are discarded by both parties, the policies are
+/// enforced by rejecting funding inputs leaving a change above it.
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub enum ChangePolicy {
+    /// No change output is created, the funding inputs being rejected if the
+    /// input value in excess of the collateral and fees is not lower than
+    /// [`dlc::DUST_LIMIT`]. The excess is added to the fees.
+    NoChange,
+    /// The funding inputs are rejected if they leave a change value lower
+    /// than the given amount but not lower than [`dlc::DUST_LIMIT`].
+    MinChangeValue(u64),
+}
+
 /// Represents the contract specifications.
 #[cfg_attr(
     feature = "serde",
@@ -54,4 +98,397 @@ pub struct ContractInput {
     /// The set of contract that make up the DLC (a single DLC can be based
     /// on multiple contracts).
     pub contract_infos: Vec<ContractInputInfo>,
+    /// The script pubkey to which the payouts of the offering party should be
+    /// sent. Must be of a standard type (P2PKH, P2SH, P2WPKH, P2WSH or P2TR).
+    /// If not provided, a new address is obtained from the wallet.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub payout_script_pubkey: Option<Script>,
+    /// Whether `payout_script_pubkey` belongs to a destination that is not
+    /// controlled by the wallet (e.g. cold storage), in which case it is not
+    /// imported for watching. Ignored if `payout_script_pubkey` is not set.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub is_payout_external: bool,
+    /// An optional premium paid by one party to the other within the fund
+    /// transaction, enabling option style contracts where one of the parties
+    /// contributes no collateral.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub premium: Option<PremiumInput>,
+    /// How the fees of the fund and CET transactions are split between the
+    /// parties. If not set, each party pays for its own inputs and outputs
+    /// and half of the common transaction fees.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub fee_split: Option<FeeSplit>,
+    /// The policy to apply to the change output of the offering party. If not
+    /// set, a change output is created unless its value is below the dust
+    /// limit.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub change_policy: Option<ChangePolicy>,
+    /// The number of confirmations of the fund transaction required before
+    /// considering the contract as confirmed. Defaults to
+    /// [`crate::manager::NB_CONFIRMATIONS`] if not set. Zero makes the
+    /// contract confirmed as soon as its fund transaction is broadcast, which
+    /// must be allowed by [`crate::manager::PeerPolicy::allow_zero_conf`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub nb_confirmations: Option<u32>,
+    /// The source of time used to evaluate the maturity of the contract and
+    /// its refund locktime.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub time_source: TimeSource,
+    /// The locktime of the refund transaction. Must be a block height when
+    /// `time_source` is [`TimeSource::BlockHeight`], in which case it is
+    /// required, and a unix timestamp after the maturity time otherwise.
+    /// Defaults to the maturity time plus [`REFUND_DELAY`] if not set.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub refund_locktime: Option<u32>,
+    /// Whether the payouts of the numerical outcomes of the contract are
+    /// computed using integer arithmetic instead of floating point, avoiding
+    /// discrepancies between the payouts computed by the parties. Signaled to
+    /// the counter party in the offer and applied to all the contract infos.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub exact_payouts: bool,
+}
+
+/// The value below which a locktime is interpreted as a block height rather
+/// than as a unix timestamp.
+pub(crate) const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+
+impl ContractInput {
+    /// Checks that the contract input is consistent with the announcements of
+    /// the events it refers to, returning an error listing all the problems
+    /// found, if any. Checked are the collaterals, the oracle thresholds, the
+    /// maturity of the contract against the one of the events and the refund
+    /// locktime, as well as the contract descriptors against the events.
+    pub fn validate(&self, announcements: &[OracleAnnouncement]) -> Result<(), Error> {
+        let mut problems = Vec::new();
+
+        let total_collateral = self
+            .offer_collateral
+            .checked_add(self.accept_collateral)
+            .unwrap_or_else(|| {
+                problems.push("Total collateral overflows.".to_string());
+                0
+            });
+        if self.offer_collateral == 0 && self.accept_collateral == 0 {
+            problems.push("Total collateral must be positive.".to_string());
+        }
+        if self.maturity_time < LOCKTIME_THRESHOLD {
+            problems.push(format!(
+                "Maturity time {} is not a unix timestamp.",
+                self.maturity_time
+            ));
+        }
+        let is_block_height = self.time_source == TimeSource::BlockHeight;
+        match self.refund_locktime {
+            Some(locktime) if is_block_height => {
+                if locktime >= LOCKTIME_THRESHOLD {
+                    problems.push(format!(
+                        "Refund locktime {} is not a block height.",
+                        locktime
+                    ));
+                }
+            }
+            Some(locktime) => {
+                if locktime <= self.maturity_time {
+                    problems.push(format!(
+                        "Refund locktime {} is not after maturity time {}.",
+                        locktime, self.maturity_time
+                    ));
+                }
+            }
+            None if is_block_height => {
+                problems.push("Refund locktime is required with block height time.".to_string());
+            }
+            None => {
+                if self.maturity_time.checked_add(REFUND_DELAY).is_none() {
+                    problems.push(format!(
+                        "Refund locktime overflows for maturity time {}.",
+                        self.maturity_time
+                    ));
+                }
+            }
+        }
+        if self.contract_infos.is_empty() {
+            problems.push("At least one contract is required.".to_string());
+        }
+
+        for (i, info) in self.contract_infos.iter().enumerate() {
+            let oracles = &info.oracles;
+            if oracles.threshold == 0 || oracles.threshold as usize > oracles.public_keys.len() {
+                problems.push(format!(
+                    "Contract {}: threshold {} is invalid for {} oracles.",
+                    i,
+                    oracles.threshold,
+                    oracles.public_keys.len()
+                ));
+            }
+
+            let mut oracle_announcements = Vec::new();
+            for public_key in &oracles.public_keys {
+                let announcement = announcements.iter().find(|x| {
+                    x.oracle_public_key == *public_key && x.get_event_id() == oracles.event_id
+                });
+                let announcement = match announcement {
+                    Some(announcement) => announcement,
+                    None => {
+                        problems.push(format!(
+                            "Contract {}: no announcement of event {} by oracle {}.",
+                            i, oracles.event_id, public_key
+                        ));
+                        continue;
+                    }
+                };
+                let event_maturity = announcement.get_event_maturity_epoch();
+                if event_maturity > self.maturity_time {
+                    problems.push(format!(
+                        "Contract {}: maturity {} is before event maturity {} of oracle {}.",
+                        i, self.maturity_time, event_maturity, public_key
+                    ));
+                }
+                oracle_announcements.push(announcement.clone());
+            }
+
+            let mut contract_descriptor = info.contract_descriptor.clone();
+            if let ContractDescriptor::Hybrid(h) = &mut contract_descriptor {
+                h.selector_announcements.clear();
+                for public_key in &oracles.public_keys {
+                    let announcement = announcements.iter().find(|x| {
+                        x.oracle_public_key == *public_key
+                            && x.get_event_id() == h.selector_event_id
+                    });
+                    match announcement {
+                        Some(announcement) => {
+                            let event_maturity = announcement.get_event_maturity_epoch();
+                            if event_maturity > self.maturity_time {
+                                problems.push(format!(
+                                    "Contract {}: selector event maturity {} is after {}.",
+                                    i, event_maturity, self.maturity_time
+                                ));
+                            }
+                            h.selector_announcements.push(announcement.clone());
+                        }
+                        None => problems.push(format!(
+                            "Contract {}: no announcement of selector event {} by oracle {}.",
+                            i, h.selector_event_id, public_key
+                        )),
+                    }
+                }
+            }
+
+            if oracle_announcements.len() < oracles.public_keys.len() || total_collateral == 0 {
+                continue;
+            }
+            let contract_info = ContractInfo {
+                contract_descriptor,
+                oracle_announcements,
+                threshold: oracles.threshold as usize,
+            };
+            if let Err(e) = contract_info.validate(total_collateral) {
+                let problem = match e {
+                    Error::InvalidParameters(s) => s,
+                    e => e.to_string(),
+                };
+                problems.push(format!("Contract {}: {}", i, problem));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidParameters(problems.join(" ")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::enum_descriptor::EnumDescriptor;
+    use dlc::{EnumerationPayout, Payout};
+    use dlc_messages::oracle_msgs::{EnumEventDescriptor, EventDescriptor, OracleEvent};
+    use secp256k1_zkp::schnorrsig::KeyPair;
+    use secp256k1_zkp::{Message, Secp256k1};
+
+    const MATURITY: u32 = 1_700_000_000;
+
+    fn get_announcement(seed: u8, event_id: &str, maturity: u32) -> OracleAnnouncement {
+        let secp = Secp256k1::new();
+        let key_pair = KeyPair::from_seckey_slice(&secp, &[seed; 32]).unwrap();
+        let public_key = SchnorrPublicKey::from_keypair(&secp, &key_pair);
+        OracleAnnouncement {
+            announcement_signature: secp
+                .schnorrsig_sign(&Message::from_slice(&[1u8; 32]).unwrap(), &key_pair),
+            oracle_public_key: public_key,
+            oracle_event: OracleEvent {
+                oracle_nonces: vec![public_key],
+                event_maturity_epoch: maturity,
+                event_descriptor: EventDescriptor::EnumEvent(EnumEventDescriptor {
+                    outcomes: vec!["a".to_string(), "b".to_string()],
+                }),
+                event_id: event_id.to_string(),
+            },
+        }
+    }
+
+    fn get_input(announcements: &[OracleAnnouncement], threshold: u16) -> ContractInput {
+        let outcome_payouts = vec![
+            EnumerationPayout {
+                outcome: "a".to_string(),
+                payout: Payout {
+                    offer: 200,
+                    accept: 0,
+                },
+            },
+            EnumerationPayout {
+                outcome: "b".to_string(),
+                payout: Payout {
+                    offer: 0,
+                    accept: 200,
+                },
+            },
+        ];
+        ContractInput {
+            offer_collateral: 100,
+            accept_collateral: 100,
+            maturity_time: MATURITY,
+            fee_rate: 2,
+            contract_infos: vec![ContractInputInfo {
+                contract_descriptor: ContractDescriptor::Enum(EnumDescriptor { outcome_payouts }),
+                oracles: OracleInput {
+                    public_keys: announcements.iter().map(|x| x.oracle_public_key).collect(),
+                    event_id: "event".to_string(),
+                    threshold,
+                },
+            }],
+            payout_script_pubkey: None,
+            is_payout_external: false,
+            premium: None,
+            fee_split: None,
+            change_policy: None,
+            nb_confirmations: None,
+            time_source: TimeSource::WallClock,
+            refund_locktime: None,
+            exact_payouts: false,
+        }
+    }
+
+    fn get_problems(input: &ContractInput, announcements: &[OracleAnnouncement]) -> String {
+        match input.validate(announcements) {
+            Err(Error::InvalidParameters(s)) => s,
+            e => panic!("Unexpected validation result {:?}", e),
+        }
+    }
+
+    #[test]
+    fn valid_input_is_accepted() {
+        let announcements = vec![
+            get_announcement(1, "event", MATURITY),
+            get_announcement(2, "event", MATURITY - 10),
+        ];
+        let input = get_input(&announcements, 2);
+
+        input.validate(&announcements).unwrap();
+    }
+
+    #[test]
+    fn all_problems_are_reported() {
+        let announcements = vec![get_announcement(1, "event", MATURITY)];
+        let mut input = get_input(&announcements, 2);
+        input.offer_collateral = 0;
+        input.accept_collateral = 0;
+        input.refund_locktime = Some(MATURITY);
+
+        let problems = get_problems(&input, &announcements);
+
+        assert!(problems.contains("Total collateral must be positive."));
+        assert!(problems.contains(&format!(
+            "Refund locktime {} is not after maturity time {}.",
+            MATURITY, MATURITY
+        )));
+        assert!(problems.contains("Contract 0: threshold 2 is invalid for 1 oracles."));
+    }
+
+    #[test]
+    fn zero_threshold_is_rejected() {
+        let announcements = vec![get_announcement(1, "event", MATURITY)];
+        let input = get_input(&announcements, 0);
+
+        assert!(get_problems(&input, &announcements).contains("threshold 0 is invalid"));
+    }
+
+    #[test]
+    fn missing_announcement_is_rejected() {
+        let announcements = vec![
+            get_announcement(1, "event", MATURITY),
+            get_announcement(2, "other_event", MATURITY),
+        ];
+        let input = get_input(&announcements, 1);
+
+        let problems = get_problems(&input, &announcements);
+
+        assert!(problems.contains(&format!(
+            "Contract 0: no announcement of event event by oracle {}.",
+            announcements[1].oracle_public_key
+        )));
+    }
+
+    #[test]
+    fn event_maturing_after_contract_is_rejected() {
+        let announcements = vec![get_announcement(1, "event", MATURITY + 1)];
+        let input = get_input(&announcements, 1);
+
+        assert!(get_problems(&input, &announcements).contains(&format!(
+            "Contract 0: maturity {} is before event maturity {}",
+            MATURITY,
+            MATURITY + 1
+        )));
+    }
+
+    #[test]
+    fn maturity_time_must_be_a_timestamp() {
+        let announcements = vec![get_announcement(1, "event", 0)];
+        let mut input = get_input(&announcements, 1);
+        input.maturity_time = 700_000;
+
+        assert!(get_problems(&input, &announcements)
+            .contains("Maturity time 700000 is not a unix timestamp."));
+    }
+
+    #[test]
+    fn block_height_refund_locktime_is_checked() {
+        let announcements = vec![get_announcement(1, "event", MATURITY)];
+        let mut input = get_input(&announcements, 1);
+        input.time_source = TimeSource::BlockHeight;
+
+        assert!(get_problems(&input, &announcements)
+            .contains("Refund locktime is required with block height time."));
+
+        input.refund_locktime = Some(MATURITY + 1);
+        assert!(get_problems(&input, &announcements).contains(&format!(
+            "Refund locktime {} is not a block height.",
+            MATURITY + 1
+        )));
+
+        input.refund_locktime = Some(800_000);
+        input.validate(&announcements).unwrap();
+    }
+
+    #[test]
+    fn invalid_descriptor_is_rejected() {
+        let announcements = vec![get_announcement(1, "event", MATURITY)];
+        let mut input = get_input(&announcements, 1);
+        input.offer_collateral = 50;
+
+        assert!(get_problems(&input, &announcements).starts_with("Contract 0: "));
+    }
+
+    #[test]
+    fn no_contract_info_is_rejected() {
+        let mut input = get_input(&[], 1);
+        input.contract_infos.clear();
+
+        assert_eq!(
+            "At least one contract is required.",
+            get_problems(&input, &[])
+        );
+    }
 }
@@ -0,0 +1,446 @@
+//! #DescriptorParser
+//!
+//! Parser for a small line based language describing contract descriptors, so
+//! that contracts can be defined in configuration files or on the command
+//! line. Each line holds one statement and `#` starts a comment. Payouts are
+//! the ones of the offer party, in satoshis.
+//!
+//! An enumeration contract lists its outcomes:
+//!
+//! ```text
+//! enum
+//! outcome win pays 100000
+//! outcome lose pays 0
+//! ```
+//!
+//! A numerical contract gives the event information followed by the pieces of
+//! its payout function, starting at outcome 0 and ending at `max`, the largest
+//! outcome that the oracles can attest to. It is `base^digits - 1` unless a
+//! lower value is given with `max <max_value>` at the end of the first line:
+//!
+//! ```text
+//! numerical base 2 digits 20 unit sats
+//! from 0 pay 0
+//! flat to 20000
+//! linear to 30000 pay 100000
+//! flat to max
+//! round from 0 to 1000
+//! difference max_error 8 min_support 6 maximize_coverage
+//! ```
+//!
+//! `round` and `difference` statements are optional. When no `round`
+//! statement is given, payouts are not rounded.
+
+use super::enum_descriptor::EnumDescriptor;
+use super::numerical_descriptor::{DifferenceParams, NumericalDescriptor, NumericalEventInfo};
+use super::ContractDescriptor;
+use crate::error::Error;
+use crate::payout_curve::{
+    PayoutFunction, PayoutFunctionPiece, PayoutPoint, PolynomialPayoutCurvePiece, RoundingInterval,
+    RoundingIntervals,
+};
+use dlc::{EnumerationPayout, Payout};
+use dlc_trie::multi_oracle::CoverageStrategy;
+
+/// Parses the given description into a [`ContractDescriptor`], checking that
+/// it is valid for a contract with the given total collateral.
+pub fn parse_contract_descriptor(
+    input: &str,
+    total_collateral: u64,
+) -> Result<ContractDescriptor, Error> {
+    let mut statements = input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let line = line.split('#').next().unwrap_or("");
+            (i + 1, line.split_whitespace().collect::<Vec<_>>())
+        })
+        .filter(|(_, words)| !words.is_empty());
+
+    let (line_nb, header) = statements
+        .next()
+        .ok_or_else(|| Error::InvalidParameters("Empty contract descriptor.".to_string()))?;
+    let statements: Vec<_> = statements.collect();
+
+    match header[0] {
+        "enum" => {
+            expect_len(line_nb, &header, 1)?;
+            parse_enum_descriptor(&statements, total_collateral)
+        }
+        "numerical" => parse_numerical_descriptor(line_nb, &header, &statements, total_collateral),
+        _ => Err(parse_error(
+            line_nb,
+            "expected `enum` or `numerical` descriptor type",
+        )),
+    }
+}
+
+fn parse_enum_descriptor(
+    statements: &[(usize, Vec<&str>)],
+    total_collateral: u64,
+) -> Result<ContractDescriptor, Error> {
+    let mut outcome_payouts: Vec<EnumerationPayout> = Vec::new();
+
+    for (line_nb, words) in statements {
+        let line_nb = *line_nb;
+        match words.as_slice() {
+            ["outcome", outcome, "pays", payout] => {
+                if outcome_payouts.iter().any(|x| x.outcome == *outcome) {
+                    return Err(parse_error(
+                        line_nb,
+                        &format!("duplicate outcome `{}`", outcome),
+                    ));
+                }
+                let offer = parse_payout(line_nb, payout, total_collateral)?;
+                outcome_payouts.push(EnumerationPayout {
+                    outcome: outcome.to_string(),
+                    payout: Payout {
+                        offer,
+                        accept: total_collateral - offer,
+                    },
+                });
+            }
+            _ => {
+                return Err(parse_error(
+                    line_nb,
+                    "expected `outcome <outcome> pays <payout>`",
+                ))
+            }
+        }
+    }
+
+    if outcome_payouts.len() < 2 {
+        return Err(Error::InvalidParameters(
+            "Enum descriptor must have at least two outcomes.".to_string(),
+        ));
+    }
+
+    Ok(ContractDescriptor::Enum(EnumDescriptor { outcome_payouts }))
+}
+
+fn parse_numerical_descriptor(
+    header_line_nb: usize,
+    header: &[&str],
+    statements: &[(usize, Vec<&str>)],
+    total_collateral: u64,
+) -> Result<ContractDescriptor, Error> {
+    let header_error = || {
+        parse_error(
+            header_line_nb,
+            "expected `numerical base <base> digits <nb_digits> [unit <unit>] [max <max_value>]`",
+        )
+    };
+    let (info, max_value) = match header {
+        ["numerical", "base", base, "digits", nb_digits, rest @ ..] => {
+            let (unit, max_value) = match rest {
+                [] => ("", None),
+                ["unit", unit] => (*unit, None),
+                ["max", max_value] => ("", Some(*max_value)),
+                ["unit", unit, "max", max_value] => (*unit, Some(*max_value)),
+                _ => return Err(header_error()),
+            };
+            let info = NumericalEventInfo {
+                base: parse_number(header_line_nb, base)?,
+                nb_digits: parse_number(header_line_nb, nb_digits)?,
+                unit: unit.to_string(),
+            };
+            (info, max_value)
+        }
+        _ => return Err(header_error()),
+    };
+
+    if info.base < 2 || info.nb_digits == 0 {
+        return Err(parse_error(
+            header_line_nb,
+            "base must be at least 2 and number of digits at least 1",
+        ));
+    }
+
+    let max_value = match max_value {
+        Some(max_value) => {
+            let max_value = parse_number(header_line_nb, max_value)?;
+            if max_value > info.get_max_outcome() {
+                return Err(parse_error(
+                    header_line_nb,
+                    &format!(
+                        "max value is above maximum outcome {}",
+                        info.get_max_outcome()
+                    ),
+                ));
+            }
+            Some(max_value)
+        }
+        None => None,
+    };
+    let max_outcome = max_value.unwrap_or_else(|| info.get_max_outcome());
+
+    let mut cur_point: Option<PayoutPoint> = None;
+    let mut pieces = Vec::new();
+    let mut intervals: Vec<RoundingInterval> = Vec::new();
+    let mut difference_params = None;
+
+    for (line_nb, words) in statements {
+        let line_nb = *line_nb;
+        let parse_outcome = |outcome: &str| {
+            if outcome == "max" {
+                Ok(max_outcome)
+            } else {
+                let outcome: u64 = parse_number(line_nb, outcome)?;
+                if outcome > max_outcome {
+                    Err(parse_error(
+                        line_nb,
+                        &format!("outcome {} is above maximum {}", outcome, max_outcome),
+                    ))
+                } else {
+                    Ok(outcome)
+                }
+            }
+        };
+
+        match words.as_slice() {
+            ["from", outcome, "pay", payout] => {
+                if cur_point.is_some() {
+                    return Err(parse_error(line_nb, "duplicate `from` statement"));
+                }
+                cur_point = Some(get_point(
+                    parse_outcome(outcome)?,
+                    parse_payout(line_nb, payout, total_collateral)?,
+                ));
+            }
+            ["flat", "to", _] | ["linear", "to", _, "pay", _] => {
+                let start = cur_point
+                    .take()
+                    .ok_or_else(|| parse_error(line_nb, "expected `from` statement first"))?;
+                let end_outcome = parse_outcome(words[2])?;
+                let end_payout = if words[0] == "flat" {
+                    start.outcome_payout
+                } else {
+                    parse_payout(line_nb, words[4], total_collateral)?
+                };
+                let end = get_point(end_outcome, end_payout);
+                let piece = PolynomialPayoutCurvePiece::new(vec![start, end.clone()])
+                    .map_err(|_| parse_error(line_nb, "outcomes must be increasing"))?;
+                pieces.push(PayoutFunctionPiece::PolynomialPayoutCurvePiece(piece));
+                cur_point = Some(end);
+            }
+            ["round", "from", outcome, "to", rounding_mod] => {
+                let begin_interval = parse_outcome(outcome)?;
+                let rounding_mod = parse_number(line_nb, rounding_mod)?;
+                if rounding_mod == 0 {
+                    return Err(parse_error(line_nb, "rounding modulus cannot be 0"));
+                }
+                if intervals
+                    .last()
+                    .map_or(begin_interval != 0, |x| x.begin_interval >= begin_interval)
+                {
+                    return Err(parse_error(
+                        line_nb,
+                        "rounding intervals must start at 0 and be increasing",
+                    ));
+                }
+                intervals.push(RoundingInterval {
+                    begin_interval,
+                    rounding_mod,
+                });
+            }
+            ["difference", "max_error", max_error, "min_support", min_support, rest @ ..] => {
+                if difference_params.is_some() {
+                    return Err(parse_error(line_nb, "duplicate `difference` statement"));
+                }
+                let coverage_strategy = match rest {
+                    [] => CoverageStrategy::MinimizeCetCount,
+                    ["maximize_coverage"] => CoverageStrategy::MaximizeSupport,
+                    _ => return Err(parse_error(line_nb, "expected `maximize_coverage`")),
+                };
+                let params = DifferenceParams {
+                    max_error_exp: parse_number(line_nb, max_error)?,
+                    min_support_exp: parse_number(line_nb, min_support)?,
+                    coverage_strategy,
+                };
+                if params.min_support_exp >= params.max_error_exp {
+                    return Err(parse_error(
+                        line_nb,
+                        "minimum support must be lower than maximum error",
+                    ));
+                }
+                difference_params = Some(params);
+            }
+            _ => return Err(parse_error(line_nb, "unknown statement")),
+        }
+    }
+
+    match (pieces.first(), &cur_point) {
+        (Some(PayoutFunctionPiece::PolynomialPayoutCurvePiece(first)), Some(last))
+            if first.payout_points[0].event_outcome == 0 && last.event_outcome == max_outcome => {}
+        _ => {
+            return Err(Error::InvalidParameters(
+                "Payout function must cover all outcomes from 0 to max.".to_string(),
+            ))
+        }
+    }
+
+    if intervals.is_empty() {
+        intervals.push(RoundingInterval {
+            begin_interval: 0,
+            rounding_mod: 1,
+        });
+    }
+
+    let descriptor = NumericalDescriptor {
+        payout_function: PayoutFunction::new(pieces)?,
+        rounding_intervals: RoundingIntervals { intervals },
+        info,
+        difference_params,
+        additional_payout_functions: Vec::new(),
+        exact_evaluation: false,
+        max_value,
+        range_payouts_cache: Default::default(),
+    };
+    descriptor.get_range_payouts(total_collateral)?;
+
+    Ok(ContractDescriptor::Numerical(descriptor))
+}
+
+fn get_point(event_outcome: u64, outcome_payout: u64) -> PayoutPoint {
+    PayoutPoint {
+        event_outcome,
+        outcome_payout,
+        extra_precision: 0,
+    }
+}
+
+fn parse_payout(line_nb: usize, payout: &str, total_collateral: u64) -> Result<u64, Error> {
+    let payout = parse_number(line_nb, payout)?;
+    if payout > total_collateral {
+        return Err(parse_error(
+            line_nb,
+            &format!(
+                "payout {} is above total collateral {}",
+                payout, total_collateral
+            ),
+        ));
+    }
+    Ok(payout)
+}
+
+fn parse_number<T: std::str::FromStr>(line_nb: usize, value: &str) -> Result<T, Error> {
+    value
+        .parse()
+        .map_err(|_| parse_error(line_nb, &format!("invalid number `{}`", value)))
+}
+
+fn expect_len(line_nb: usize, words: &[&str], len: usize) -> Result<(), Error> {
+    if words.len() != len {
+        return Err(parse_error(line_nb, "unexpected trailing words"));
+    }
+    Ok(())
+}
+
+fn parse_error(line_nb: usize, message: &str) -> Error {
+    Error::InvalidParameters(format!(
+        "Invalid contract descriptor at line {}: {}.",
+        line_nb, message
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_enum_descriptor_test() {
+        let input = "enum\noutcome win pays 100000 # offer party wins\n\noutcome lose pays 0";
+        let descriptor = match parse_contract_descriptor(input, 100000).unwrap() {
+            ContractDescriptor::Enum(e) => e,
+            _ => panic!("Expected enum descriptor"),
+        };
+
+        assert_eq!(2, descriptor.outcome_payouts.len());
+        assert_eq!("lose", descriptor.outcome_payouts[1].outcome);
+        assert_eq!(100000, descriptor.outcome_payouts[1].payout.accept);
+    }
+
+    #[test]
+    fn parse_numerical_descriptor_test() {
+        let input = "numerical base 2 digits 20 unit sats
+            from 0 pay 0
+            flat to 20000
+            linear to 30000 pay 100000
+            flat to max
+            round from 0 to 1000
+            difference max_error 8 min_support 6 maximize_coverage";
+        let descriptor = match parse_contract_descriptor(input, 100000).unwrap() {
+            ContractDescriptor::Numerical(n) => n,
+            _ => panic!("Expected numerical descriptor"),
+        };
+
+        assert_eq!(3, descriptor.payout_function.payout_function_pieces.len());
+        assert_eq!(
+            1000,
+            descriptor.rounding_intervals.intervals[0].rounding_mod
+        );
+        assert_eq!(20, descriptor.info.nb_digits);
+        assert_eq!(
+            CoverageStrategy::MaximizeSupport,
+            descriptor.difference_params.unwrap().coverage_strategy
+        );
+    }
+
+    #[test]
+    fn parse_bounded_numerical_descriptor_test() {
+        let input = "numerical base 2 digits 64 unit sats max 100000
+            from 0 pay 0
+            linear to 50000 pay 100
+            flat to max";
+        let descriptor = match parse_contract_descriptor(input, 100).unwrap() {
+            ContractDescriptor::Numerical(n) => n,
+            _ => panic!("Expected numerical descriptor"),
+        };
+
+        assert_eq!(Some(100000), descriptor.max_value);
+        assert_eq!(100000, descriptor.payout_function.get_last_outcome());
+        assert_eq!("sats", descriptor.info.unit);
+    }
+
+    #[test]
+    fn parse_numerical_descriptor_with_max_digits_test() {
+        let input = "numerical base 2 digits 64\nfrom 0 pay 0\nflat to max";
+        let descriptor = match parse_contract_descriptor(input, 100).unwrap() {
+            ContractDescriptor::Numerical(n) => n,
+            _ => panic!("Expected numerical descriptor"),
+        };
+
+        assert_eq!(None, descriptor.max_value);
+        assert_eq!(u64::MAX, descriptor.payout_function.get_last_outcome());
+    }
+
+    #[test]
+    fn parse_invalid_descriptors_test() {
+        let invalid_inputs = [
+            "",
+            "enum\noutcome a pays 1",
+            "enum\noutcome a pays 1\noutcome a pays 2",
+            "enum\noutcome a pays 200\noutcome b pays 0",
+            "numerical base 2 digits 4\nfrom 0 pay 0\nflat to 10",
+            "numerical base 2 digits 4\nfrom 0 pay 0\nlinear to 16 pay 100",
+            "numerical base 2 digits 4\nfrom 1 pay 0\nflat to max",
+            "numerical base 2 digits 4\nfrom 0 pay 0\nflat to 8\nflat to 4\nflat to max",
+            "numerical base 2 digits 4\nfrom 0 pay 0\nflat to max\nround from 2 to 10",
+            "numerical base 2 digits 4\nfrom 0 pay 0\nflat to max\nlinear",
+            "numerical base 2 digits 4\nfrom 0 pay 0\nflat to max\ndifference max_error 2 min_support 3",
+            "numerical base 2 digits 4\nfrom 0 pay 0\nflat to max\ndifference max_error 2 min_support 2",
+            "numerical base 2 digits 4 max 16\nfrom 0 pay 0\nflat to max",
+            "numerical base 2 digits 4 max 10\nfrom 0 pay 0\nflat to 15",
+            "numerical base 2 digits 4 max\nfrom 0 pay 0\nflat to max",
+            "numerical base 2 digits 4 unit\nfrom 0 pay 0\nflat to max",
+        ];
+
+        for input in &invalid_inputs {
+            assert!(
+                parse_contract_descriptor(input, 100).is_err(),
+                "Expected error for {}",
+                input
+            );
+        }
+    }
+}
@@ -7,6 +7,7 @@ use crate::error::Error;
 use bitcoin::{Script, Transaction};
 use dlc::OracleInfo;
 use dlc::{EnumerationPayout, Payout};
+use dlc_messages::oracle_msgs::{EventDescriptor, OracleAnnouncement};
 use dlc_trie::{combination_iterator::CombinationIterator, RangeInfo};
 use secp256k1_zkp::{
     All, EcdsaAdaptorSignature, Message, PublicKey, Secp256k1, SecretKey, Verification,
@@ -26,7 +27,118 @@ pub struct EnumDescriptor {
     pub outcome_payouts: Vec<EnumerationPayout>,
 }
 
+/// Builds an [`EnumDescriptor`] from the payout of the offer party for each
+/// outcome, checking the outcomes against the ones of the oracle events on
+/// which the contract is based so that mismatches are detected when creating
+/// the contract rather than when signing it.
+#[derive(Clone, Debug, Default)]
+pub struct EnumDescriptorBuilder {
+    offer_payouts: Vec<(String, u64)>,
+}
+
+impl EnumDescriptorBuilder {
+    /// Creates a builder without any outcome.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the payout of the offer party for the given outcome, the accept
+    /// party receiving the remainder of the total collateral.
+    pub fn outcome(mut self, outcome: &str, offer_payout: u64) -> Self {
+        self.offer_payouts.push((outcome.to_string(), offer_payout));
+        self
+    }
+
+    /// Builds the descriptor for the given total collateral, checking that
+    /// its outcomes are exactly the ones of the enumerated events of the given
+    /// announcements.
+    pub fn build(
+        &self,
+        oracle_announcements: &[OracleAnnouncement],
+        total_collateral: u64,
+    ) -> Result<EnumDescriptor, Error> {
+        let outcome_payouts = self
+            .offer_payouts
+            .iter()
+            .map(|(outcome, offer_payout)| {
+                let accept = total_collateral.checked_sub(*offer_payout).ok_or_else(|| {
+                    Error::InvalidParameters(format!(
+                        "Payout for outcome {} exceeds the total collateral.",
+                        outcome
+                    ))
+                })?;
+                Ok(EnumerationPayout {
+                    outcome: outcome.clone(),
+                    payout: Payout {
+                        offer: *offer_payout,
+                        accept,
+                    },
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let descriptor = EnumDescriptor { outcome_payouts };
+        descriptor.validate(oracle_announcements, total_collateral)?;
+        Ok(descriptor)
+    }
+}
+
 impl EnumDescriptor {
+    /// Checks that the outcomes of the descriptor are exactly the ones of the
+    /// enumerated events of the given announcements, each appearing once, and
+    /// that the payouts do not exceed the total collateral.
+    pub fn validate(
+        &self,
+        oracle_announcements: &[OracleAnnouncement],
+        total_collateral: u64,
+    ) -> Result<(), Error> {
+        let mut outcomes: Vec<_> = self
+            .outcome_payouts
+            .iter()
+            .map(|x| dlc::normalize_outcome(&x.outcome))
+            .collect();
+        outcomes.sort();
+        if outcomes.windows(2).any(|x| x[0] == x[1]) {
+            return Err(Error::InvalidParameters(
+                "Duplicate outcome in enum descriptor.".to_string(),
+            ));
+        }
+
+        for announcement in oracle_announcements {
+            let mut event_outcomes: Vec<_> = match &announcement.oracle_event.event_descriptor {
+                EventDescriptor::EnumEvent(e) => e
+                    .outcomes
+                    .iter()
+                    .map(|x| dlc::normalize_outcome(x))
+                    .collect(),
+                _ => {
+                    return Err(Error::InvalidParameters(
+                        "Oracle event is not an enumerated event.".to_string(),
+                    ))
+                }
+            };
+            event_outcomes.sort();
+            if event_outcomes != outcomes {
+                return Err(Error::InvalidParameters(format!(
+                    "Outcomes of the contract do not match the outcomes of event {}.",
+                    announcement.oracle_event.event_id
+                )));
+            }
+        }
+
+        if self.outcome_payouts.iter().any(|x| {
+            x.payout
+                .offer
+                .checked_add(x.payout.accept)
+                .map_or(true, |total| total > total_collateral)
+        }) {
+            return Err(Error::InvalidParameters(
+                "Outcome payouts exceed the total collateral.".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
     /// Returns the set of payouts.
     pub fn get_payouts(&self) -> Vec<Payout> {
         self.outcome_payouts
@@ -64,7 +176,7 @@ impl EnumDescriptor {
         let pos = self
             .outcome_payouts
             .iter()
-            .position(|x| x.outcome == outcome)
+            .position(|x| dlc::normalize_outcome(&x.outcome) == dlc::normalize_outcome(&outcome))
             .ok_or_else(|| {
                 crate::error::Error::InvalidParameters(format!(
                     "Outcome {} not found in the set of possible outcomes",
@@ -212,6 +324,30 @@ impl EnumDescriptor {
         Ok(adaptor_sigs)
     }
 
+    /// Returns the adaptor index and adaptor point of each adaptor signature
+    /// related to the CET at the given index.
+    pub fn get_adaptor_points_for_cet<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        oracle_infos: &[OracleInfo],
+        threshold: usize,
+        cet_index: usize,
+    ) -> Result<Vec<(usize, PublicKey)>, Error> {
+        let mut adaptor_points = Vec::new();
+        let mut adaptor_index = 0;
+        let mut callback = |adaptor_point: &PublicKey, i: usize| -> Result<(), dlc::Error> {
+            if i == cet_index {
+                adaptor_points.push((adaptor_index, *adaptor_point));
+            }
+            adaptor_index += 1;
+            Ok(())
+        };
+
+        self.iter_outcomes(secp, oracle_infos, threshold, &mut callback)?;
+
+        Ok(adaptor_points)
+    }
+
     fn iter_outcomes<C: Verification, F>(
         &self,
         secp: &Secp256k1<C>,
@@ -226,9 +362,7 @@ impl EnumDescriptor {
             .outcome_payouts
             .iter()
             .map(|x| {
-                let message = vec![Message::from_hashed_data::<
-                    secp256k1_zkp::bitcoin_hashes::sha256::Hash,
-                >(x.outcome.as_bytes())];
+                let message = vec![dlc::get_outcome_message(&x.outcome)];
                 std::iter::repeat(message).take(threshold).collect()
             })
             .collect();
@@ -260,3 +394,125 @@ impl EnumDescriptor {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dlc_messages::oracle_msgs::{
+        DigitDecompositionEventDescriptor, EnumEventDescriptor, OracleEvent,
+    };
+    use secp256k1_zkp::schnorrsig::{KeyPair, PublicKey as SchnorrPublicKey};
+
+    fn get_announcement(event_descriptor: EventDescriptor) -> OracleAnnouncement {
+        let secp = Secp256k1::new();
+        let key_pair = KeyPair::from_seckey_slice(&secp, &[1u8; 32]).unwrap();
+        let public_key = SchnorrPublicKey::from_keypair(&secp, &key_pair);
+        OracleAnnouncement {
+            announcement_signature: secp
+                .schnorrsig_sign(&Message::from_slice(&[1u8; 32]).unwrap(), &key_pair),
+            oracle_public_key: public_key,
+            oracle_event: OracleEvent {
+                oracle_nonces: vec![public_key],
+                event_maturity_epoch: 0,
+                event_descriptor,
+                event_id: "event".to_string(),
+            },
+        }
+    }
+
+    fn get_enum_announcement(outcomes: &[&str]) -> OracleAnnouncement {
+        get_announcement(EventDescriptor::EnumEvent(EnumEventDescriptor {
+            outcomes: outcomes.iter().map(|x| x.to_string()).collect(),
+        }))
+    }
+
+    #[test]
+    fn builder_computes_accept_payouts() {
+        let descriptor = EnumDescriptorBuilder::new()
+            .outcome("a", 100)
+            .outcome("b", 0)
+            .outcome("c", 60)
+            .build(&[get_enum_announcement(&["c", "b", "a"])], 100)
+            .unwrap();
+
+        let payouts: Vec<_> = descriptor
+            .outcome_payouts
+            .iter()
+            .map(|x| (x.outcome.as_str(), x.payout.offer, x.payout.accept))
+            .collect();
+        assert_eq!(vec![("a", 100, 0), ("b", 0, 100), ("c", 60, 40)], payouts);
+    }
+
+    #[test]
+    fn builder_rejects_payout_above_total_collateral() {
+        assert!(EnumDescriptorBuilder::new()
+            .outcome("a", 101)
+            .outcome("b", 0)
+            .build(&[get_enum_announcement(&["a", "b"])], 100)
+            .is_err());
+    }
+
+    #[test]
+    fn builder_rejects_mismatched_outcomes() {
+        let builder = EnumDescriptorBuilder::new()
+            .outcome("a", 100)
+            .outcome("b", 0);
+        // Missing outcome.
+        assert!(builder
+            .build(&[get_enum_announcement(&["a", "b", "c"])], 100)
+            .is_err());
+        // Unknown outcome.
+        assert!(builder
+            .build(&[get_enum_announcement(&["a"])], 100)
+            .is_err());
+        // Mismatch with one of the announcements only.
+        assert!(builder
+            .build(
+                &[
+                    get_enum_announcement(&["a", "b"]),
+                    get_enum_announcement(&["a", "c"])
+                ],
+                100
+            )
+            .is_err());
+        // Duplicate outcome.
+        assert!(builder
+            .clone()
+            .outcome("a", 50)
+            .build(&[get_enum_announcement(&["a", "b"])], 100)
+            .is_err());
+    }
+
+    #[test]
+    fn builder_rejects_numerical_event() {
+        let announcement = get_announcement(EventDescriptor::DigitDecompositionEvent(
+            DigitDecompositionEventDescriptor {
+                base: 2,
+                is_signed: false,
+                unit: String::new(),
+                precision: 0,
+                nb_digits: 10,
+            },
+        ));
+        assert!(EnumDescriptorBuilder::new()
+            .outcome("a", 100)
+            .build(&[announcement], 100)
+            .is_err());
+    }
+
+    #[test]
+    fn validate_rejects_payouts_above_total_collateral() {
+        let descriptor = EnumDescriptor {
+            outcome_payouts: vec![EnumerationPayout {
+                outcome: "a".to_string(),
+                payout: Payout {
+                    offer: 60,
+                    accept: 60,
+                },
+            }],
+        };
+        let announcements = [get_enum_announcement(&["a"])];
+        assert!(descriptor.validate(&announcements, 100).is_err());
+        descriptor.validate(&announcements, 120).unwrap();
+    }
+}
@@ -0,0 +1,400 @@
+//! #HybridDescriptor
+
+use super::numerical_descriptor::{NumericalDescriptor, NumericalEventInfo};
+use super::AdaptorInfo;
+use crate::error::Error;
+use crate::payout_curve::{PayoutFunction, RoundingIntervals};
+use bitcoin::{Script, Transaction};
+use dlc::{Payout, RangePayout};
+use dlc_messages::oracle_msgs::{EventDescriptor, OracleAnnouncement};
+use dlc_trie::multi_oracle_trie::MultiOracleTrie;
+use dlc_trie::{get_cet_from_slice, DlcTrie};
+use secp256k1_zkp::{All, EcdsaAdaptorSignature, PublicKey, Secp256k1, SecretKey, Verification};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The payouts of a hybrid contract when the selector event has a given
+/// outcome.
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct HybridBranch {
+    /// The outcome of the selector event for which the branch applies.
+    pub outcome: String,
+    /// The function representing the set of payouts of the branch.
+    pub payout_function: PayoutFunction,
+    /// Rounding intervals applied to the payouts of the branch.
+    pub rounding_intervals: RoundingIntervals,
+}
+
+/// A descriptor for a contract whose payouts are given by one of several
+/// payout functions of the outcome of a numerical event, the function being
+/// selected by the outcome of an enumerated event attested by the same
+/// oracles (e.g. to settle a contract on the price of an asset differently
+/// when the exchange providing the price halted trading).
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct HybridDescriptor {
+    /// The id of the enumerated event selecting the branch of the contract.
+    pub selector_event_id: String,
+    /// The announcements of the selector event, in the same order as the
+    /// oracle announcements of the contract. They are retrieved from the
+    /// oracles when offering a contract so can be left empty.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub selector_announcements: Vec<OracleAnnouncement>,
+    /// The branches of the contract, one for each outcome of the selector
+    /// event.
+    pub branches: Vec<HybridBranch>,
+    /// Information about the numerical event.
+    pub info: NumericalEventInfo,
+    /// Whether the payouts of the branches are computed using integer
+    /// arithmetic, as for [`NumericalDescriptor::exact_evaluation`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub exact_evaluation: bool,
+}
+
+impl HybridDescriptor {
+    /// Returns the set of RangePayout of each branch, in the order of the
+    /// branches.
+    pub fn get_range_payouts(&self, total_collateral: u64) -> Result<Vec<Vec<RangePayout>>, Error> {
+        self.branches
+            .iter()
+            .map(|x| {
+                self.get_branch_descriptor(x)
+                    .get_range_payouts(total_collateral)
+            })
+            .collect()
+    }
+
+    /// Returns the payouts of all the branches, the CETs of a branch following
+    /// the ones of the previous branches.
+    pub fn get_payouts(&self, total_collateral: u64) -> Result<Vec<Payout>, Error> {
+        Ok(self
+            .get_range_payouts(total_collateral)?
+            .into_iter()
+            .flatten()
+            .map(|x| x.payout)
+            .collect())
+    }
+
+    /// Returns the index of the branch applying for the given outcome of the
+    /// selector event, if any.
+    pub fn get_branch_index(&self, outcome: &str) -> Option<usize> {
+        let outcome = dlc::normalize_outcome(outcome);
+        self.branches
+            .iter()
+            .position(|x| dlc::normalize_outcome(&x.outcome) == outcome)
+    }
+
+    /// Checks that the selector events are enumerated events of the oracles of
+    /// the contract whose outcomes are the ones of the branches, that the
+    /// numerical events decompose outcomes as expected and that the payouts of
+    /// each branch depend on the outcome of the numerical event, as adaptor
+    /// points are made of the signatures of both events.
+    pub fn validate(
+        &self,
+        oracle_announcements: &[OracleAnnouncement],
+        total_collateral: u64,
+    ) -> Result<(), Error> {
+        if self.branches.is_empty() {
+            return Err(Error::InvalidParameters(
+                "Hybrid descriptor must have at least one branch.".to_string(),
+            ));
+        }
+
+        let mut outcomes: Vec<_> = self
+            .branches
+            .iter()
+            .map(|x| dlc::normalize_outcome(&x.outcome))
+            .collect();
+        outcomes.sort();
+        if outcomes.windows(2).any(|x| x[0] == x[1]) {
+            return Err(Error::InvalidParameters(
+                "Duplicate outcome in hybrid descriptor.".to_string(),
+            ));
+        }
+
+        if self.selector_announcements.len() != oracle_announcements.len()
+            || self
+                .selector_announcements
+                .iter()
+                .zip(oracle_announcements.iter())
+                .any(|(x, y)| x.oracle_public_key != y.oracle_public_key)
+        {
+            return Err(Error::InvalidParameters(
+                "Selector announcements do not match the oracle announcements.".to_string(),
+            ));
+        }
+
+        for announcement in &self.selector_announcements {
+            let mut event_outcomes: Vec<_> = match &announcement.oracle_event.event_descriptor {
+                EventDescriptor::EnumEvent(e) => e
+                    .outcomes
+                    .iter()
+                    .map(|x| dlc::normalize_outcome(x))
+                    .collect(),
+                _ => {
+                    return Err(Error::InvalidParameters(
+                        "Selector event is not an enumerated event.".to_string(),
+                    ))
+                }
+            };
+            event_outcomes.sort();
+            if event_outcomes != outcomes {
+                return Err(Error::InvalidParameters(format!(
+                    "Branches of the contract do not match the outcomes of event {}.",
+                    announcement.oracle_event.event_id
+                )));
+            }
+        }
+
+        let max_outcome = self.info.validate_announcements(oracle_announcements)?;
+        for (branch, range_payouts) in self
+            .branches
+            .iter()
+            .zip(self.get_range_payouts(total_collateral)?)
+        {
+            if branch.payout_function.get_last_outcome() > max_outcome {
+                return Err(Error::InvalidParameters(format!(
+                    "Payout function defined for outcomes above the maximum outcome {}.",
+                    max_outcome
+                )));
+            }
+            if range_payouts.len() < 2 {
+                return Err(Error::InvalidParameters(format!(
+                    "Payouts of branch {} do not depend on the numerical outcome.",
+                    branch.outcome
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Generate the set of adaptor signatures and the adaptor info.
+    pub fn get_adaptor_info(
+        &self,
+        secp: &Secp256k1<All>,
+        total_collateral: u64,
+        fund_priv_key: &SecretKey,
+        funding_script_pubkey: &Script,
+        fund_output_value: u64,
+        threshold: usize,
+        precomputed_points: &[Vec<Vec<PublicKey>>],
+        cets: &[Transaction],
+        adaptor_index_start: usize,
+    ) -> Result<(AdaptorInfo, Vec<EcdsaAdaptorSignature>), Error> {
+        let branch_points = self.get_branch_points(secp, precomputed_points)?;
+        let mut tries = Vec::with_capacity(self.branches.len());
+        let mut adaptor_sigs = Vec::new();
+        let mut cet_offset = 0;
+        for (range_payouts, points) in self
+            .get_range_payouts(total_collateral)?
+            .iter()
+            .zip(branch_points.iter())
+        {
+            let mut trie =
+                MultiOracleTrie::new(self.info.base, points.len(), threshold, self.info.nb_digits);
+            let adaptor_index = adaptor_index_start + adaptor_sigs.len();
+            let sigs = trie.generate_sign(
+                secp,
+                fund_priv_key,
+                funding_script_pubkey,
+                fund_output_value,
+                range_payouts,
+                &|i| get_cet_from_slice(cets, cet_offset + i),
+                points,
+                adaptor_index,
+            )?;
+            adaptor_sigs.extend(sigs);
+            tries.push((cet_offset, trie));
+            cet_offset += range_payouts.len();
+        }
+
+        Ok((AdaptorInfo::Hybrid(tries), adaptor_sigs))
+    }
+
+    /// Verify the given set of adaptor signatures and generate the adaptor info.
+    pub fn verify_and_get_adaptor_info(
+        &self,
+        secp: &Secp256k1<All>,
+        total_collateral: u64,
+        fund_pubkey: &PublicKey,
+        funding_script_pubkey: &Script,
+        fund_output_value: u64,
+        threshold: usize,
+        precomputed_points: &[Vec<Vec<PublicKey>>],
+        cets: &[Transaction],
+        adaptor_sigs: &[EcdsaAdaptorSignature],
+        adaptor_index_start: usize,
+    ) -> Result<(AdaptorInfo, usize), Error> {
+        let branch_points = self.get_branch_points(secp, precomputed_points)?;
+        let mut tries = Vec::with_capacity(self.branches.len());
+        let mut adaptor_index = adaptor_index_start;
+        let mut cet_offset = 0;
+        for (range_payouts, points) in self
+            .get_range_payouts(total_collateral)?
+            .iter()
+            .zip(branch_points.iter())
+        {
+            let mut trie =
+                MultiOracleTrie::new(self.info.base, points.len(), threshold, self.info.nb_digits);
+            adaptor_index = trie.generate_verify(
+                secp,
+                fund_pubkey,
+                funding_script_pubkey,
+                fund_output_value,
+                range_payouts,
+                &|i| get_cet_from_slice(cets, cet_offset + i),
+                points,
+                adaptor_sigs,
+                adaptor_index,
+            )?;
+            tries.push((cet_offset, trie));
+            cet_offset += range_payouts.len();
+        }
+
+        Ok((AdaptorInfo::Hybrid(tries), adaptor_index))
+    }
+
+    /// Generate the adaptor signatures of the contract from the tries of its
+    /// branches.
+    pub fn get_adaptor_signatures(
+        &self,
+        secp: &Secp256k1<All>,
+        tries: &[(usize, MultiOracleTrie)],
+        fund_priv_key: &SecretKey,
+        funding_script_pubkey: &Script,
+        fund_output_value: u64,
+        precomputed_points: &[Vec<Vec<PublicKey>>],
+        cets: &[Transaction],
+    ) -> Result<Vec<EcdsaAdaptorSignature>, Error> {
+        let branch_points = self.get_branch_points(secp, precomputed_points)?;
+        let mut adaptor_sigs = Vec::new();
+        for ((cet_offset, trie), points) in tries.iter().zip(branch_points.iter()) {
+            adaptor_sigs.extend(trie.sign(
+                secp,
+                fund_priv_key,
+                funding_script_pubkey,
+                fund_output_value,
+                &|i| get_cet_from_slice(cets, cet_offset + i),
+                points,
+            )?);
+        }
+
+        Ok(adaptor_sigs)
+    }
+
+    /// Verifies the given adaptor signatures against the tries of the branches
+    /// of the contract, returning the index following the last verified one.
+    pub fn verify_adaptor_info(
+        &self,
+        secp: &Secp256k1<All>,
+        tries: &[(usize, MultiOracleTrie)],
+        fund_pubkey: &PublicKey,
+        funding_script_pubkey: &Script,
+        fund_output_value: u64,
+        precomputed_points: &[Vec<Vec<PublicKey>>],
+        cets: &[Transaction],
+        adaptor_sigs: &[EcdsaAdaptorSignature],
+        adaptor_sig_start: usize,
+    ) -> Result<usize, Error> {
+        let branch_points = self.get_branch_points(secp, precomputed_points)?;
+        let mut adaptor_index = adaptor_sig_start;
+        for ((cet_offset, trie), points) in tries.iter().zip(branch_points.iter()) {
+            adaptor_index = trie.verify(
+                secp,
+                fund_pubkey,
+                funding_script_pubkey,
+                fund_output_value,
+                adaptor_sigs,
+                &|i| get_cet_from_slice(cets, cet_offset + i),
+                points,
+            )?;
+        }
+
+        Ok(adaptor_index)
+    }
+
+    /// Returns the adaptor index and adaptor point of each adaptor signature
+    /// related to the CET at the given index.
+    pub fn get_adaptor_points_for_cet<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        tries: &[(usize, MultiOracleTrie)],
+        precomputed_points: &[Vec<Vec<PublicKey>>],
+        cet_index: usize,
+    ) -> Result<Vec<(usize, PublicKey)>, Error> {
+        let branch_points = self.get_branch_points(secp, precomputed_points)?;
+        match tries
+            .iter()
+            .zip(branch_points.iter())
+            .rev()
+            .find(|((cet_offset, _), _)| *cet_offset <= cet_index)
+        {
+            Some(((cet_offset, trie), points)) => {
+                Ok(trie.get_adaptor_points_for_cet(cet_index - cet_offset, points)?)
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+
+    // Adaptor points of a branch are obtained by adding the signature point of
+    // the branch outcome for the selector event of each oracle to the points of
+    // the first digit of its numerical event. As every digit prefix contains
+    // the first digit, closing the contract requires both attestations.
+    fn get_branch_points<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        precomputed_points: &[Vec<Vec<PublicKey>>],
+    ) -> Result<Vec<Vec<Vec<Vec<PublicKey>>>>, Error> {
+        self.branches
+            .iter()
+            .map(|branch| {
+                let msg = dlc::get_outcome_message(&branch.outcome);
+                self.selector_announcements
+                    .iter()
+                    .zip(precomputed_points.iter())
+                    .map(|(announcement, points)| {
+                        let nonce = announcement
+                            .oracle_event
+                            .oracle_nonces
+                            .get(0)
+                            .ok_or(Error::InvalidState)?;
+                        let sig_point = dlc::secp_utils::schnorrsig_compute_sig_point(
+                            secp,
+                            &announcement.oracle_public_key,
+                            nonce,
+                            &msg,
+                        )?;
+                        let mut points = points.clone();
+                        for point in points.get_mut(0).ok_or(Error::InvalidState)? {
+                            *point = point.combine(&sig_point).map_err(dlc::Error::from)?;
+                        }
+                        Ok(points)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn get_branch_descriptor(&self, branch: &HybridBranch) -> NumericalDescriptor {
+        NumericalDescriptor {
+            payout_function: branch.payout_function.clone(),
+            rounding_intervals: branch.rounding_intervals.clone(),
+            info: self.info.clone(),
+            difference_params: None,
+            additional_payout_functions: Vec::new(),
+            exact_evaluation: self.exact_evaluation,
+            max_value: None,
+            range_payouts_cache: Default::default(),
+        }
+    }
+}
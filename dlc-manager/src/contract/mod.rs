@@ -1,10 +1,15 @@
 //! Module containing structures and functions related to contracts.
 
-use crate::ContractId;
-use bitcoin::Address;
-use dlc_messages::{oracle_msgs::OracleAttestation, AcceptDlc, FundingInput, SignDlc};
+use crate::{ContractId, TemporaryContractId};
+use accepted_contract::AcceptedContract;
+use bitcoin::{Address, Script, Transaction, Txid};
+use dlc_messages::oracle_msgs::{EventDescriptor, OracleAnnouncement, OracleAttestation};
+use dlc_messages::{AcceptDlc, FundingInput, SignDlc};
+use dlc_trie::compact_multi_oracle_trie::CompactMultiOracleTrie;
 use dlc_trie::multi_oracle_trie::MultiOracleTrie;
 use dlc_trie::multi_oracle_trie_with_diff::MultiOracleTrieWithDiff;
+use offered_contract::OfferedContract;
+use secp256k1_zkp::{PublicKey, SecretKey};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use signed_contract::SignedContract;
@@ -12,7 +17,9 @@ use signed_contract::SignedContract;
 pub mod accepted_contract;
 pub mod contract_info;
 pub mod contract_input;
+pub mod descriptor_parser;
 pub mod enum_descriptor;
+pub mod hybrid_descriptor;
 pub mod numerical_descriptor;
 pub mod offered_contract;
 pub mod ser;
@@ -34,55 +41,179 @@ pub enum Contract {
     Closed(ClosedContract),
     /// A contract whose refund transaction was broadcast.
     Refunded(signed_contract::SignedContract),
+    /// A contract whose refund transaction was broadcast by the local party
+    /// because its refund locktime passed without the oracles attesting to
+    /// its outcome.
+    RefundedDueToNoAttestation(signed_contract::SignedContract),
     /// A contract that failed when verifying information from an accept message.
     FailedAccept(FailedAcceptContract),
     /// A contract that failed when verifying information from a sign message.
     FailedSign(FailedSignContract),
+    /// A contract whose fund output was spent by the fund transaction of a
+    /// renewed contract.
+    Renewed(signed_contract::SignedContract),
+}
+
+/// The state of a contract, without the data associated with it. Used to query
+/// contracts in a given state from the storage.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ContractState {
+    /// See [`Contract::Offered`].
+    Offered,
+    /// See [`Contract::Accepted`].
+    Accepted,
+    /// See [`Contract::Signed`].
+    Signed,
+    /// See [`Contract::Confirmed`].
+    Confirmed,
+    /// See [`Contract::Closed`].
+    Closed,
+    /// See [`Contract::Refunded`].
+    Refunded,
+    /// See [`Contract::RefundedDueToNoAttestation`].
+    RefundedDueToNoAttestation,
+    /// See [`Contract::FailedAccept`].
+    FailedAccept,
+    /// See [`Contract::FailedSign`].
+    FailedSign,
+    /// See [`Contract::Renewed`].
+    Renewed,
+}
+
+impl ContractState {
+    /// All the possible contract states.
+    pub const ALL: [ContractState; 10] = [
+        ContractState::Offered,
+        ContractState::Accepted,
+        ContractState::Signed,
+        ContractState::Confirmed,
+        ContractState::Closed,
+        ContractState::Refunded,
+        ContractState::RefundedDueToNoAttestation,
+        ContractState::FailedAccept,
+        ContractState::FailedSign,
+        ContractState::Renewed,
+    ];
+
+    /// Returns a human readable name for the state.
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            ContractState::Offered => "offered",
+            ContractState::Accepted => "accepted",
+            ContractState::Signed => "signed",
+            ContractState::Confirmed => "confirmed",
+            ContractState::Closed => "closed",
+            ContractState::Refunded => "refunded",
+            ContractState::RefundedDueToNoAttestation => "refunded due to no attestation",
+            ContractState::FailedAccept => "failed accept",
+            ContractState::FailedSign => "failed sign",
+            ContractState::Renewed => "renewed",
+        }
+    }
 }
 
 impl std::fmt::Debug for Contract {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let state = match self {
-            Contract::Offered(_) => "offered",
-            Contract::Accepted(_) => "accepted",
-            Contract::Signed(_) => "signed",
-            Contract::Confirmed(_) => "confirmed",
-            Contract::Closed(_) => "closed",
-            Contract::Refunded(_) => "refunded",
-            Contract::FailedAccept(_) => "failed accept",
-            Contract::FailedSign(_) => "failed sign",
-        };
-        f.debug_struct("Contract").field("state", &state).finish()
+        f.debug_struct("Contract")
+            .field("state", &self.get_state_name())
+            .finish()
     }
 }
 
 impl Contract {
-    /// Get the id of a contract. Returns the temporary contract id for offered
-    /// and failed accept contracts.
-    pub fn get_id(&self) -> ContractId {
+    /// Returns the state of the contract.
+    pub fn get_state(&self) -> ContractState {
         match self {
-            Contract::Offered(o) => o.id,
-            Contract::Accepted(o) => o.get_contract_id(),
-            Contract::Signed(o) | Contract::Confirmed(o) | Contract::Refunded(o) => {
-                o.accepted_contract.get_contract_id()
-            }
-            Contract::FailedAccept(c) => c.offered_contract.id,
-            Contract::FailedSign(c) => c.accepted_contract.get_contract_id(),
-            Contract::Closed(c) => c.signed_contract.accepted_contract.get_contract_id(),
+            Contract::Offered(_) => ContractState::Offered,
+            Contract::Accepted(_) => ContractState::Accepted,
+            Contract::Signed(_) => ContractState::Signed,
+            Contract::Confirmed(_) => ContractState::Confirmed,
+            Contract::Closed(_) => ContractState::Closed,
+            Contract::Refunded(_) => ContractState::Refunded,
+            Contract::RefundedDueToNoAttestation(_) => ContractState::RefundedDueToNoAttestation,
+            Contract::FailedAccept(_) => ContractState::FailedAccept,
+            Contract::FailedSign(_) => ContractState::FailedSign,
+            Contract::Renewed(_) => ContractState::Renewed,
+        }
+    }
+
+    /// Returns a human readable name for the state of the contract.
+    pub fn get_state_name(&self) -> &'static str {
+        self.get_state().get_name()
+    }
+
+    /// Get the id of a contract. Returns `None` for offered and failed accept
+    /// contracts which are only identified by their temporary id.
+    pub fn get_id(&self) -> Option<ContractId> {
+        match self {
+            Contract::Offered(_) | Contract::FailedAccept(_) => None,
+            Contract::Accepted(o) => Some(o.get_contract_id()),
+            Contract::Signed(o)
+            | Contract::Confirmed(o)
+            | Contract::Refunded(o)
+            | Contract::RefundedDueToNoAttestation(o)
+            | Contract::Renewed(o) => Some(o.accepted_contract.get_contract_id()),
+            Contract::FailedSign(c) => Some(c.accepted_contract.get_contract_id()),
+            Contract::Closed(c) => Some(c.signed_contract.accepted_contract.get_contract_id()),
         }
     }
 
+    /// Returns whether the contract uses zero-conf funding, in which case it
+    /// is considered confirmed while its fund transaction can still be
+    /// invalidated by a double spend of the funding inputs.
+    pub fn is_zero_conf(&self) -> bool {
+        self.get_offered_contract().nb_confirmations == 0
+    }
+
     /// Returns the temporary contract id of a contract.
-    pub fn get_temporary_id(&self) -> ContractId {
+    pub fn get_temporary_id(&self) -> TemporaryContractId {
+        self.get_offered_contract().id
+    }
+
+    /// Returns the offered contract from which the contract was created.
+    pub fn get_offered_contract(&self) -> &OfferedContract {
         match self {
-            Contract::Offered(o) => o.id,
-            Contract::Accepted(o) => o.offered_contract.id,
-            Contract::Signed(o) | Contract::Confirmed(o) | Contract::Refunded(o) => {
-                o.accepted_contract.offered_contract.id
-            }
-            Contract::FailedAccept(c) => c.offered_contract.id,
-            Contract::FailedSign(c) => c.accepted_contract.offered_contract.id,
-            Contract::Closed(c) => c.signed_contract.accepted_contract.offered_contract.id,
+            Contract::Offered(o) => o,
+            Contract::Accepted(o) => &o.offered_contract,
+            Contract::Signed(o)
+            | Contract::Confirmed(o)
+            | Contract::Refunded(o)
+            | Contract::RefundedDueToNoAttestation(o)
+            | Contract::Renewed(o) => &o.accepted_contract.offered_contract,
+            Contract::FailedAccept(c) => &c.offered_contract,
+            Contract::FailedSign(c) => &c.accepted_contract.offered_contract,
+            Contract::Closed(c) => &c.signed_contract.accepted_contract.offered_contract,
+        }
+    }
+
+    /// Returns the accepted contract from which the contract was created.
+    /// Returns `None` for offered and failed accept contracts.
+    pub fn get_accepted_contract(&self) -> Option<&AcceptedContract> {
+        match self {
+            Contract::Offered(_) | Contract::FailedAccept(_) => None,
+            Contract::Accepted(a) => Some(a),
+            Contract::Signed(o)
+            | Contract::Confirmed(o)
+            | Contract::Refunded(o)
+            | Contract::RefundedDueToNoAttestation(o)
+            | Contract::Renewed(o) => Some(&o.accepted_contract),
+            Contract::FailedSign(c) => Some(&c.accepted_contract),
+            Contract::Closed(c) => Some(&c.signed_contract.accepted_contract),
+        }
+    }
+
+    /// Mutable version of [`Contract::get_accepted_contract`].
+    pub fn get_accepted_contract_mut(&mut self) -> Option<&mut AcceptedContract> {
+        match self {
+            Contract::Offered(_) | Contract::FailedAccept(_) => None,
+            Contract::Accepted(a) => Some(a),
+            Contract::Signed(o)
+            | Contract::Confirmed(o)
+            | Contract::Refunded(o)
+            | Contract::RefundedDueToNoAttestation(o)
+            | Contract::Renewed(o) => Some(&mut o.accepted_contract),
+            Contract::FailedSign(c) => Some(&mut c.accepted_contract),
+            Contract::Closed(c) => Some(&mut c.signed_contract.accepted_contract),
         }
     }
 }
@@ -99,6 +230,9 @@ pub struct FundingInputInfo {
     pub funding_input: FundingInput,
     /// The address corresponding to the input if it belongs to us.
     pub address: Option<Address>,
+    /// The witness script of the output spent by the input, if it is a P2WSH
+    /// (or P2SH wrapped P2WSH) output belonging to us.
+    pub witness_script: Option<Script>,
 }
 
 /// Information about a contract that failed while verifying an accept message.
@@ -128,10 +262,260 @@ pub struct FailedSignContract {
 pub struct ClosedContract {
     /// The signed contract that was closed.
     pub signed_contract: SignedContract,
-    /// The attestations that were used to decrypt the broadcast CET.
+    /// The attestations that were used to decrypt the broadcast CET. Empty if
+    /// the CET was broadcast by the counter party.
     pub attestations: Vec<OracleAttestation>,
     /// The index of the CET that was broadcast.
     pub cet_index: usize,
+    /// The amount paid to the local party by the broadcast CET.
+    pub own_payout: u64,
+    /// The adaptor secret recovered from the CET if it was broadcast by the
+    /// counter party using one of the local party's adaptor signatures.
+    pub adaptor_secret: Option<SecretKey>,
+    /// The CET signed by the local party, stored before being broadcast so
+    /// that it can be broadcast again if it gets evicted from the mempool.
+    /// `None` if the CET was broadcast by the counter party.
+    pub signed_cet: Option<Transaction>,
+}
+
+/// A compact record of a closed or refunded contract, kept in place of the
+/// full contract once it is archived so that its outcome remains available
+/// without storing its transactions and adaptor information.
+#[derive(Clone, Debug)]
+pub struct ArchivedContract {
+    /// The id of the contract.
+    pub id: ContractId,
+    /// The temporary id of the contract, under which its history is stored.
+    pub temporary_id: TemporaryContractId,
+    /// The public key of the counter party of the contract.
+    pub counter_party: PublicKey,
+    /// Whether the local party was the offering party.
+    pub is_offer_party: bool,
+    /// The state of the contract when it was archived.
+    pub state: ContractState,
+    /// The collateral put in the contract by the local party.
+    pub own_collateral: u64,
+    /// The total collateral of the contract.
+    pub total_collateral: u64,
+    /// The id of the fund transaction.
+    pub fund_txid: Txid,
+    /// The id of the CET or refund transaction that closed the contract.
+    pub closing_txid: Txid,
+    /// The amount paid to the local party by the closing transaction.
+    pub own_payout: u64,
+    /// The attestations that were used to close the contract, if any.
+    pub attestations: Vec<OracleAttestation>,
+    /// The amount paid to the counter party by the closing transaction.
+    pub counter_payout: u64,
+    /// The fee paid by the fund transaction.
+    pub fund_tx_fee: u64,
+    /// The fee paid by the closing transaction.
+    pub closing_tx_fee: u64,
+    /// The outcomes of the attestations that were used to close the contract,
+    /// for the attestations whose event is part of the contract.
+    pub attested_outcomes: Vec<AttestedOutcome>,
+}
+
+/// The outcome attested by an oracle for an event of a contract.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AttestedOutcome {
+    /// The outcome of an enumeration event.
+    Enum(String),
+    /// The value attested for a digit decomposition event, in the unit of the
+    /// event before applying its precision.
+    Numerical(i64),
+}
+
+impl AttestedOutcome {
+    /// Returns the outcome attested by the given attestation for the event of
+    /// the given announcement, or `None` if the attested outcomes are not
+    /// valid for it.
+    pub fn from_attestation(
+        attestation: &OracleAttestation,
+        announcement: &OracleAnnouncement,
+    ) -> Option<AttestedOutcome> {
+        match &announcement.oracle_event.event_descriptor {
+            EventDescriptor::EnumEvent(_) => attestation
+                .outcomes
+                .first()
+                .map(|x| AttestedOutcome::Enum(x.clone())),
+            EventDescriptor::DigitDecompositionEvent(d) => {
+                let (is_negative, digits) = if d.is_signed {
+                    (
+                        attestation.outcomes.first()? == "-",
+                        &attestation.outcomes[1..],
+                    )
+                } else {
+                    (false, &attestation.outcomes[..])
+                };
+                let mut value: i64 = 0;
+                for digit in digits {
+                    value = value
+                        .checked_mul(d.base as i64)?
+                        .checked_add(digit.parse().ok()?)?;
+                }
+                Some(AttestedOutcome::Numerical(if is_negative {
+                    -value
+                } else {
+                    value
+                }))
+            }
+        }
+    }
+}
+
+/// The accounting information of a closed or refunded contract.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClosedContractReport {
+    /// The id of the contract.
+    pub contract_id: ContractId,
+    /// The final state of the contract.
+    pub state: ContractState,
+    /// Whether the local party was the offering party.
+    pub is_offer_party: bool,
+    /// The collateral put in the contract by the local party.
+    pub own_collateral: u64,
+    /// The total collateral of the contract.
+    pub total_collateral: u64,
+    /// The amount paid to the local party by the closing transaction.
+    pub own_payout: u64,
+    /// The amount paid to the counter party by the closing transaction.
+    pub counter_payout: u64,
+    /// The fee paid by the fund transaction.
+    pub fund_tx_fee: u64,
+    /// The fee paid by the closing transaction.
+    pub closing_tx_fee: u64,
+    /// The outcomes attested by the oracles whose attestations were used to
+    /// close the contract. Empty for refunded contracts and contracts closed
+    /// by the counter party.
+    pub attested_outcomes: Vec<AttestedOutcome>,
+}
+
+impl ClosedContractReport {
+    /// Returns the profit, or loss if negative, of the local party, that is
+    /// its payout minus its collateral. Premiums and transaction fees are not
+    /// included.
+    pub fn get_pnl(&self) -> i64 {
+        self.own_payout as i64 - self.own_collateral as i64
+    }
+}
+
+impl From<&ArchivedContract> for ClosedContractReport {
+    fn from(archived: &ArchivedContract) -> ClosedContractReport {
+        ClosedContractReport {
+            contract_id: archived.id,
+            state: archived.state,
+            is_offer_party: archived.is_offer_party,
+            own_collateral: archived.own_collateral,
+            total_collateral: archived.total_collateral,
+            own_payout: archived.own_payout,
+            counter_payout: archived.counter_payout,
+            fund_tx_fee: archived.fund_tx_fee,
+            closing_tx_fee: archived.closing_tx_fee,
+            attested_outcomes: archived.attested_outcomes.clone(),
+        }
+    }
+}
+
+impl ArchivedContract {
+    /// Creates the archival record of the given contract. Returns `None` if
+    /// the contract is not closed or refunded.
+    pub fn from_contract(contract: &Contract) -> Option<ArchivedContract> {
+        let (signed_contract, closing_tx, own_payout, attestations) = match contract {
+            Contract::Closed(c) => (
+                &c.signed_contract,
+                &c.signed_contract.accepted_contract.dlc_transactions.cets[c.cet_index],
+                Some(c.own_payout),
+                c.attestations.clone(),
+            ),
+            Contract::Refunded(c) | Contract::RefundedDueToNoAttestation(c) => (
+                c,
+                &c.accepted_contract.dlc_transactions.refund,
+                None,
+                Vec::new(),
+            ),
+            _ => return None,
+        };
+        let accepted_contract = &signed_contract.accepted_contract;
+        let offered_contract = &accepted_contract.offered_contract;
+        let dlc_transactions = &accepted_contract.dlc_transactions;
+        let (own_params, counter_params) = if offered_contract.is_offer_party {
+            (
+                &offered_contract.offer_params,
+                &accepted_contract.accept_params,
+            )
+        } else {
+            (
+                &accepted_contract.accept_params,
+                &offered_contract.offer_params,
+            )
+        };
+        let own_collateral = own_params.collateral;
+        let counter_payout = match own_payout {
+            Some(_) => closing_tx
+                .output
+                .iter()
+                .filter(|x| x.script_pubkey == counter_params.payout_script_pubkey)
+                .map(|x| x.value)
+                .sum(),
+            None => counter_params.collateral,
+        };
+        let closing_tx_fee = dlc_transactions
+            .get_fund_output()
+            .value
+            .saturating_sub(closing_tx.output.iter().map(|x| x.value).sum());
+        let announcements: Vec<&OracleAnnouncement> = offered_contract
+            .contract_info
+            .iter()
+            .flat_map(|x| x.oracle_announcements.iter())
+            .collect();
+        let attested_outcomes = attestations
+            .iter()
+            .filter_map(|attestation| {
+                announcements
+                    .iter()
+                    .filter(|x| x.oracle_public_key == attestation.oracle_public_key)
+                    .find_map(|x| AttestedOutcome::from_attestation(attestation, x))
+            })
+            .collect();
+
+        Some(ArchivedContract {
+            id: accepted_contract.get_contract_id(),
+            temporary_id: offered_contract.id,
+            counter_party: offered_contract.counter_party,
+            is_offer_party: offered_contract.is_offer_party,
+            state: contract.get_state(),
+            own_collateral,
+            total_collateral: offered_contract.total_collateral,
+            fund_txid: dlc_transactions.fund.txid(),
+            closing_txid: closing_tx.txid(),
+            // The refund transaction gives back their collateral to both
+            // parties.
+            own_payout: own_payout.unwrap_or(own_collateral),
+            attestations,
+            counter_payout,
+            fund_tx_fee: accepted_contract.get_fund_tx_fee(),
+            closing_tx_fee,
+            attested_outcomes,
+        })
+    }
+}
+
+/// A record of a contract moving to a new state, kept to provide an audit
+/// trail of the contract lifecycle.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContractHistoryEntry {
+    /// The unix time at which the transition happened.
+    pub timestamp: u64,
+    /// The name of the state the contract transitioned to.
+    pub state: String,
+    /// The id of the transaction related to the transition if any (fund
+    /// transaction when signed or confirmed, CET when closed, refund
+    /// transaction when refunded).
+    pub txid: Option<Txid>,
+    /// The sha256 hash of the message that triggered or was generated by the
+    /// transition if any.
+    pub message_hash: Option<[u8; 32]>,
 }
 
 /// Information about the adaptor signatures and the CET for which they are
@@ -145,6 +529,13 @@ pub enum AdaptorInfo {
     /// For numerical outcome DLC where oracles are allowed to diverge to some
     /// extent in the outcome value, a trie of trie is used to store the information.
     NumericalWithDifference(MultiOracleTrieWithDiff),
+    /// For hybrid DLC, a trie is used for each branch, stored along with the
+    /// index of the first CET of the branch.
+    Hybrid(Vec<(usize, MultiOracleTrie)>),
+    /// For numerical outcome DLC with multiple oracles required to attest to
+    /// the exact same outcome, a trie storing a single entry per path from
+    /// which the adaptor signature index of each oracle combination is derived.
+    CompactNumerical(CompactMultiOracleTrie),
 }
 
 /// The descriptor of a contract.
@@ -159,14 +550,35 @@ pub enum ContractDescriptor {
     Enum(enum_descriptor::EnumDescriptor),
     /// Case for numerical outcome DLC.
     Numerical(numerical_descriptor::NumericalDescriptor),
+    /// Case for DLC where an enumerated outcome selects the payout function of
+    /// a numerical outcome.
+    Hybrid(hybrid_descriptor::HybridDescriptor),
 }
 
 impl ContractDescriptor {
     /// Get the parameters on allowed divergence between oracle if any.
     pub fn get_oracle_params(&self) -> Option<numerical_descriptor::DifferenceParams> {
         match self {
-            ContractDescriptor::Enum(_) => None,
+            ContractDescriptor::Enum(_) | ContractDescriptor::Hybrid(_) => None,
             ContractDescriptor::Numerical(n) => n.difference_params.clone(),
         }
     }
+
+    /// Returns whether the payouts are computed using integer arithmetic,
+    /// which is never the case for enumerated outcomes.
+    pub fn is_exact_evaluation(&self) -> bool {
+        match self {
+            ContractDescriptor::Enum(_) => false,
+            ContractDescriptor::Numerical(n) => n.exact_evaluation,
+            ContractDescriptor::Hybrid(h) => h.exact_evaluation,
+        }
+    }
+
+    pub(crate) fn set_exact_evaluation(&mut self, exact_evaluation: bool) {
+        match self {
+            ContractDescriptor::Enum(_) => {}
+            ContractDescriptor::Numerical(n) => n.exact_evaluation = exact_evaluation,
+            ContractDescriptor::Hybrid(h) => h.exact_evaluation = exact_evaluation,
+        }
+    }
 }
@@ -5,12 +5,17 @@ use crate::error::Error;
 use crate::payout_curve::{PayoutFunction, RoundingIntervals};
 use bitcoin::{Script, Transaction};
 use dlc::{Payout, RangePayout};
+use dlc_messages::oracle_msgs::{EventDescriptor, OracleAnnouncement};
+use dlc_trie::compact_multi_oracle_trie::CompactMultiOracleTrie;
+use dlc_trie::multi_oracle::CoverageStrategy;
 use dlc_trie::multi_oracle_trie::MultiOracleTrie;
 use dlc_trie::multi_oracle_trie_with_diff::MultiOracleTrieWithDiff;
-use dlc_trie::DlcTrie;
+use dlc_trie::{get_cet_from_slice, DlcTrie};
 use secp256k1_zkp::{All, EcdsaAdaptorSignature, PublicKey, Secp256k1, SecretKey};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Mutex;
 
 /// Information about the base, number of digits and unit of a numerical event.
 #[derive(Clone, Debug)]
@@ -28,6 +33,39 @@ pub struct NumericalEventInfo {
     pub unit: String,
 }
 
+impl NumericalEventInfo {
+    /// Returns the largest outcome that can be represented with the base and
+    /// number of digits of the event, `u64::MAX` if it does not fit in a u64.
+    pub fn get_max_outcome(&self) -> u64 {
+        let nb_outcomes = (self.base as u128)
+            .checked_pow(self.nb_digits as u32)
+            .unwrap_or(u128::MAX);
+        nb_outcomes.saturating_sub(1).min(u64::MAX as u128) as u64
+    }
+
+    /// Checks that the given oracle events decompose outcomes as described by
+    /// the event information, and returns the largest outcome they can attest.
+    pub(super) fn validate_announcements(
+        &self,
+        oracle_announcements: &[OracleAnnouncement],
+    ) -> Result<u64, Error> {
+        for announcement in oracle_announcements {
+            match &announcement.oracle_event.event_descriptor {
+                EventDescriptor::DigitDecompositionEvent(d)
+                    if d.base as usize == self.base && d.nb_digits as usize == self.nb_digits => {}
+                _ => {
+                    return Err(Error::InvalidParameters(
+                        "Oracle event does not match the contract outcome decomposition."
+                            .to_string(),
+                    ))
+                }
+            }
+        }
+
+        Ok(self.get_max_outcome())
+    }
+}
+
 /// Information about the allowed deviation in outcome value between the oracles.
 #[derive(Clone, Debug)]
 #[cfg_attr(
@@ -42,9 +80,9 @@ pub struct DifferenceParams {
     /// The minimum error deviation under which the contract should be guaranteed
     /// to be closeable.
     pub min_support_exp: usize,
-    /// Whether to maximize the coverage of the [min;max] interval to increase
-    /// the probability of the contract being closeable within it.
-    pub maximize_coverage: bool,
+    /// How much of the [min;max] interval to cover, trading off the
+    /// probability of the contract being closeable within it.
+    pub coverage_strategy: CoverageStrategy,
 }
 
 #[derive(Clone, Debug)]
@@ -66,23 +104,181 @@ pub struct NumericalDescriptor {
     /// If None, a quorum of oracle needs to sign the same value for the contract
     /// to be closeable.
     pub difference_params: Option<DifferenceParams>,
+    /// Additional payout functions defined over the same outcomes, whose
+    /// payouts are added to the ones of `payout_function` before rounding
+    /// (e.g. to build a spread from two option legs).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub additional_payout_functions: Vec<PayoutFunction>,
+    /// Whether the payouts are computed using integer arithmetic (see
+    /// [`PayoutFunction::to_range_payouts_exact`]) rather than floating point.
+    /// Set for all the descriptors of a contract from
+    /// [`crate::contract::contract_input::ContractInput::exact_payouts`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub exact_evaluation: bool,
+    /// The largest outcome that the oracles can attest, when lower than the
+    /// largest outcome that can be represented with the number of digits of
+    /// the event. The range payouts, and thus the CETs and the paths of the
+    /// tries, are truncated so as not to cover the outcomes above it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub max_value: Option<u64>,
+    /// The range payouts last computed for the descriptor.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub range_payouts_cache: RangePayoutsCache,
+}
+
+/// Cache of the range payouts of a [`NumericalDescriptor`], which are computed
+/// by evaluating its payout functions at every outcome and are needed multiple
+/// times while setting up a contract. Cached range payouts are only returned
+/// for the payout functions, rounding intervals and total collateral they were
+/// computed with. The cache is not serialized, and clones start empty.
+#[derive(Default)]
+pub struct RangePayoutsCache(Mutex<Option<CachedRangePayouts>>);
+
+struct CachedRangePayouts {
+    total_collateral: u64,
+    payout_functions: Vec<PayoutFunction>,
+    rounding_intervals: RoundingIntervals,
+    exact_evaluation: bool,
+    max_value: Option<u64>,
+    range_payouts: Vec<RangePayout>,
+}
+
+impl CachedRangePayouts {
+    fn is_for(&self, descriptor: &NumericalDescriptor, total_collateral: u64) -> bool {
+        self.total_collateral == total_collateral
+            && self.exact_evaluation == descriptor.exact_evaluation
+            && self.max_value == descriptor.max_value
+            && self.rounding_intervals == descriptor.rounding_intervals
+            && self
+                .payout_functions
+                .iter()
+                .eq(descriptor.get_payout_functions())
+    }
+}
+
+impl Clone for RangePayoutsCache {
+    fn clone(&self) -> Self {
+        RangePayoutsCache::default()
+    }
+}
+
+impl fmt::Debug for RangePayoutsCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RangePayoutsCache").finish()
+    }
 }
 
 impl NumericalDescriptor {
     /// Returns the set of RangePayout for the descriptor generated from the
-    /// payout function.
-    pub fn get_range_payouts(&self, total_collateral: u64) -> Vec<RangePayout> {
-        self.payout_function
-            .to_range_payouts(total_collateral, &self.rounding_intervals)
+    /// payout function, summed with the additional payout functions if any.
+    /// The payout functions are truncated at the maximum value if any, so
+    /// that no outcome above it is covered. The range payouts are cached, and
+    /// only computed again when the descriptor or total collateral change.
+    pub fn get_range_payouts(&self, total_collateral: u64) -> Result<Vec<RangePayout>, Error> {
+        let mut cache = self.range_payouts_cache.0.lock().unwrap();
+        if let Some(cached) = cache.as_ref().filter(|x| x.is_for(self, total_collateral)) {
+            return Ok(cached.range_payouts.clone());
+        }
+
+        let range_payouts = self.compute_range_payouts(total_collateral)?;
+        *cache = Some(CachedRangePayouts {
+            total_collateral,
+            payout_functions: self.get_payout_functions().cloned().collect(),
+            rounding_intervals: self.rounding_intervals.clone(),
+            exact_evaluation: self.exact_evaluation,
+            max_value: self.max_value,
+            range_payouts: range_payouts.clone(),
+        });
+        Ok(range_payouts)
+    }
+
+    fn compute_range_payouts(&self, total_collateral: u64) -> Result<Vec<RangePayout>, Error> {
+        let truncated: Vec<PayoutFunction>;
+        let payout_functions: Vec<&PayoutFunction> = match self.max_value {
+            Some(max_value) => {
+                truncated = self
+                    .get_payout_functions()
+                    .map(|x| x.truncated(max_value))
+                    .collect();
+                truncated.iter().collect()
+            }
+            None => self.get_payout_functions().collect(),
+        };
+
+        if payout_functions.len() == 1 {
+            if self.exact_evaluation {
+                return payout_functions[0]
+                    .to_range_payouts_exact(total_collateral, &self.rounding_intervals);
+            }
+            return Ok(
+                payout_functions[0].to_range_payouts(total_collateral, &self.rounding_intervals)
+            );
+        }
+
+        if self.exact_evaluation {
+            return PayoutFunction::sum_to_range_payouts_exact(
+                &payout_functions,
+                total_collateral,
+                &self.rounding_intervals,
+            );
+        }
+        PayoutFunction::sum_to_range_payouts(
+            &payout_functions,
+            total_collateral,
+            &self.rounding_intervals,
+        )
+    }
+
+    fn get_payout_functions(&self) -> impl Iterator<Item = &PayoutFunction> {
+        std::iter::once(&self.payout_function).chain(self.additional_payout_functions.iter())
+    }
+
+    /// Checks that the oracle events decompose outcomes as described by the
+    /// event information, that the maximum value, if any, can be attested by
+    /// the oracles, and that the payout functions cover the outcomes up to it
+    /// without extending past the largest outcome the oracles can attest.
+    pub(super) fn validate_outcome_bounds(
+        &self,
+        oracle_announcements: &[OracleAnnouncement],
+    ) -> Result<(), Error> {
+        let max_outcome = self.info.validate_announcements(oracle_announcements)?;
+        if let Some(max_value) = self.max_value {
+            if max_value > max_outcome {
+                return Err(Error::InvalidParameters(format!(
+                    "Maximum value {} is above the maximum outcome {}.",
+                    max_value, max_outcome
+                )));
+            }
+            if self
+                .get_payout_functions()
+                .any(|x| x.get_last_outcome() < max_value)
+            {
+                return Err(Error::InvalidParameters(format!(
+                    "Payout function does not cover the outcomes up to the maximum value {}.",
+                    max_value
+                )));
+            }
+        }
+        if self
+            .get_payout_functions()
+            .any(|x| x.get_last_outcome() > max_outcome)
+        {
+            return Err(Error::InvalidParameters(format!(
+                "Payout function defined for outcomes above the maximum outcome {}.",
+                max_outcome
+            )));
+        }
+        Ok(())
     }
 
     /// Returns the set of payouts for the descriptor generated from the payout
     /// function.
-    pub fn get_payouts(&self, total_collateral: u64) -> Vec<Payout> {
-        self.get_range_payouts(total_collateral)
+    pub fn get_payouts(&self, total_collateral: u64) -> Result<Vec<Payout>, Error> {
+        Ok(self
+            .get_range_payouts(total_collateral)?
             .iter()
             .map(|x| x.payout.clone())
-            .collect()
+            .collect())
     }
 
     /// Verify the given set of adaptor signatures and generate the adaptor info.
@@ -108,20 +304,43 @@ impl NumericalDescriptor {
                     self.info.nb_digits,
                     params.min_support_exp,
                     params.max_error_exp,
+                    params.coverage_strategy,
                 );
                 let index = multi_trie.generate_verify(
                     secp,
                     fund_pubkey,
                     funding_script_pubkey,
                     fund_output_value,
-                    &self.get_range_payouts(total_collateral),
-                    cets,
+                    &self.get_range_payouts(total_collateral)?,
+                    &|i| get_cet_from_slice(cets, i),
                     precomputed_points,
                     adaptor_pairs,
                     adaptor_index_start,
                 )?;
                 Ok((AdaptorInfo::NumericalWithDifference(multi_trie), index))
             }
+            // With multiple oracles, storing the adaptor signature index of
+            // each combination of oracles for every path is avoided.
+            None if precomputed_points.len() > 1 => {
+                let mut trie = CompactMultiOracleTrie::new(
+                    self.info.base,
+                    precomputed_points.len(),
+                    threshold,
+                    self.info.nb_digits,
+                );
+                let index = trie.generate_verify(
+                    secp,
+                    fund_pubkey,
+                    funding_script_pubkey,
+                    fund_output_value,
+                    &self.get_range_payouts(total_collateral)?,
+                    &|i| get_cet_from_slice(cets, i),
+                    precomputed_points,
+                    adaptor_pairs,
+                    adaptor_index_start,
+                )?;
+                Ok((AdaptorInfo::CompactNumerical(trie), index))
+            }
             None => {
                 let mut trie = MultiOracleTrie::new(
                     self.info.base,
@@ -134,8 +353,8 @@ impl NumericalDescriptor {
                     fund_pubkey,
                     funding_script_pubkey,
                     fund_output_value,
-                    &self.get_range_payouts(total_collateral),
-                    cets,
+                    &self.get_range_payouts(total_collateral)?,
+                    &|i| get_cet_from_slice(cets, i),
                     precomputed_points,
                     adaptor_pairs,
                     adaptor_index_start,
@@ -167,14 +386,15 @@ impl NumericalDescriptor {
                     self.info.nb_digits,
                     params.min_support_exp,
                     params.max_error_exp,
+                    params.coverage_strategy,
                 );
                 let adaptor_pairs = multi_trie.generate_sign(
                     secp,
                     fund_priv_key,
                     funding_script_pubkey,
                     fund_output_value,
-                    &self.get_range_payouts(total_collateral),
-                    cets,
+                    &self.get_range_payouts(total_collateral)?,
+                    &|i| get_cet_from_slice(cets, i),
                     precomputed_points,
                     adaptor_index_start,
                 )?;
@@ -183,6 +403,25 @@ impl NumericalDescriptor {
                     adaptor_pairs,
                 ))
             }
+            None if precomputed_points.len() > 1 => {
+                let mut trie = CompactMultiOracleTrie::new(
+                    self.info.base,
+                    precomputed_points.len(),
+                    threshold,
+                    self.info.nb_digits,
+                );
+                let sigs = trie.generate_sign(
+                    secp,
+                    fund_priv_key,
+                    funding_script_pubkey,
+                    fund_output_value,
+                    &self.get_range_payouts(total_collateral)?,
+                    &|i| get_cet_from_slice(cets, i),
+                    precomputed_points,
+                    adaptor_index_start,
+                )?;
+                Ok((AdaptorInfo::CompactNumerical(trie), sigs))
+            }
 
             None => {
                 let mut trie = MultiOracleTrie::new(
@@ -196,8 +435,8 @@ impl NumericalDescriptor {
                     fund_priv_key,
                     funding_script_pubkey,
                     fund_output_value,
-                    &self.get_range_payouts(total_collateral),
-                    cets,
+                    &self.get_range_payouts(total_collateral)?,
+                    &|i| get_cet_from_slice(cets, i),
                     precomputed_points,
                     adaptor_index_start,
                 )?;
@@ -206,3 +445,196 @@ impl NumericalDescriptor {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::descriptor_parser::parse_contract_descriptor;
+    use crate::contract::ContractDescriptor;
+    use dlc_messages::oracle_msgs::{DigitDecompositionEventDescriptor, OracleEvent};
+    use secp256k1_zkp::schnorrsig::{KeyPair, PublicKey as SchnorrPublicKey};
+    use secp256k1_zkp::Message;
+
+    fn get_announcement(base: u64, nb_digits: u16) -> OracleAnnouncement {
+        let secp = Secp256k1::new();
+        let key_pair = KeyPair::from_seckey_slice(&secp, &[1u8; 32]).unwrap();
+        let public_key = SchnorrPublicKey::from_keypair(&secp, &key_pair);
+        OracleAnnouncement {
+            announcement_signature: secp
+                .schnorrsig_sign(&Message::from_slice(&[1u8; 32]).unwrap(), &key_pair),
+            oracle_public_key: public_key,
+            oracle_event: OracleEvent {
+                oracle_nonces: vec![public_key; nb_digits as usize],
+                event_maturity_epoch: 0,
+                event_descriptor: EventDescriptor::DigitDecompositionEvent(
+                    DigitDecompositionEventDescriptor {
+                        base,
+                        is_signed: false,
+                        unit: String::new(),
+                        precision: 0,
+                        nb_digits,
+                    },
+                ),
+                event_id: "event".to_string(),
+            },
+        }
+    }
+
+    fn get_descriptor(input: &str) -> NumericalDescriptor {
+        match parse_contract_descriptor(input, 1000).unwrap() {
+            ContractDescriptor::Numerical(n) => n,
+            _ => panic!("Expected a numerical descriptor"),
+        }
+    }
+
+    fn get_event_info(base: usize, nb_digits: usize) -> NumericalEventInfo {
+        NumericalEventInfo {
+            base,
+            nb_digits,
+            unit: String::new(),
+        }
+    }
+
+    #[test]
+    fn max_outcome_test() {
+        assert_eq!(1023, get_event_info(2, 10).get_max_outcome());
+        assert_eq!(999_999, get_event_info(10, 6).get_max_outcome());
+        assert_eq!(u64::MAX, get_event_info(2, 64).get_max_outcome());
+        assert_eq!(u64::MAX, get_event_info(16, 16).get_max_outcome());
+        assert_eq!(u64::MAX, get_event_info(10, 20).get_max_outcome());
+        assert_eq!(u64::MAX, get_event_info(2, 200).get_max_outcome());
+    }
+
+    #[test]
+    fn range_payouts_are_truncated_at_max_value() {
+        let mut descriptor = get_descriptor(
+            "numerical base 2 digits 10\nfrom 0 pay 0\nflat to 100\nlinear to 200 pay 1000\nflat to max\nround from 0 to 100",
+        );
+        let range_payouts = descriptor.get_range_payouts(1000).unwrap();
+        assert_eq!(1024, range_payouts.iter().map(|x| x.count).sum::<usize>());
+
+        descriptor.max_value = Some(150);
+        let truncated = descriptor.get_range_payouts(1000).unwrap();
+        let last = truncated.last().unwrap();
+        assert_eq!(151, last.start + last.count);
+        assert_eq!(151, truncated.iter().map(|x| x.count).sum::<usize>());
+        assert!(truncated.len() < range_payouts.len());
+        for (truncated, full) in truncated.iter().zip(range_payouts.iter()) {
+            assert_eq!(full.start, truncated.start);
+            assert_eq!(full.payout, truncated.payout);
+        }
+    }
+
+    #[test]
+    fn range_payouts_are_unchanged_by_max_value_after_last_outcome() {
+        let mut descriptor = get_descriptor(
+            "numerical base 2 digits 10\nfrom 0 pay 0\nlinear to 100 pay 1000\nflat to max",
+        );
+        let range_payouts = descriptor.get_range_payouts(1000).unwrap();
+        descriptor.max_value = Some(1023);
+        assert_eq!(range_payouts, descriptor.get_range_payouts(1000).unwrap());
+    }
+
+    #[test]
+    fn announcements_with_64_binary_digits_are_valid() {
+        assert_eq!(
+            u64::MAX,
+            get_event_info(2, 64)
+                .validate_announcements(&[get_announcement(2, 64)])
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn announcements_with_other_decomposition_are_rejected() {
+        assert!(get_event_info(2, 10)
+            .validate_announcements(&[get_announcement(2, 12)])
+            .is_err());
+        assert!(get_event_info(2, 10)
+            .validate_announcements(&[get_announcement(10, 10)])
+            .is_err());
+    }
+
+    #[test]
+    fn bounded_descriptor_outcome_bounds_are_valid() {
+        let descriptor = get_descriptor(
+            "numerical base 2 digits 64 max 1000\nfrom 0 pay 0\nlinear to 500 pay 1000\nflat to max",
+        );
+        descriptor
+            .validate_outcome_bounds(&[get_announcement(2, 64)])
+            .unwrap();
+    }
+
+    #[test]
+    fn max_value_above_max_outcome_is_rejected() {
+        let mut descriptor =
+            get_descriptor("numerical base 2 digits 10\nfrom 0 pay 0\nflat to max");
+        descriptor.max_value = Some(1024);
+        assert!(descriptor
+            .validate_outcome_bounds(&[get_announcement(2, 10)])
+            .is_err());
+    }
+
+    #[test]
+    fn payout_function_not_covering_max_value_is_rejected() {
+        let mut descriptor =
+            get_descriptor("numerical base 2 digits 10 max 500\nfrom 0 pay 0\nflat to max");
+        descriptor
+            .validate_outcome_bounds(&[get_announcement(2, 10)])
+            .unwrap();
+        descriptor.max_value = Some(600);
+        assert!(descriptor
+            .validate_outcome_bounds(&[get_announcement(2, 10)])
+            .is_err());
+    }
+
+    #[test]
+    fn payout_function_above_max_outcome_is_rejected() {
+        let mut descriptor =
+            get_descriptor("numerical base 2 digits 10\nfrom 0 pay 0\nflat to max");
+        descriptor.info.nb_digits = 9;
+        assert!(descriptor
+            .validate_outcome_bounds(&[get_announcement(2, 9)])
+            .is_err());
+    }
+
+    #[test]
+    fn range_payouts_are_cached() {
+        let descriptor = get_descriptor(
+            "numerical base 2 digits 10\nfrom 0 pay 0\nlinear to 100 pay 1000\nflat to max",
+        );
+        assert!(descriptor.range_payouts_cache.0.lock().unwrap().is_none());
+        let range_payouts = descriptor.get_range_payouts(1000).unwrap();
+        assert!(descriptor.range_payouts_cache.0.lock().unwrap().is_some());
+        assert_eq!(range_payouts, descriptor.get_range_payouts(1000).unwrap());
+        assert!(descriptor
+            .clone()
+            .range_payouts_cache
+            .0
+            .lock()
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn range_payouts_are_computed_again_when_descriptor_changes() {
+        let mut descriptor = get_descriptor(
+            "numerical base 2 digits 10\nfrom 0 pay 0\nlinear to 100 pay 1000\nflat to max",
+        );
+        let range_payouts = descriptor.get_range_payouts(1000).unwrap();
+
+        let with_other_collateral = descriptor.get_range_payouts(2000).unwrap();
+        assert_eq!(1000, with_other_collateral.last().unwrap().payout.accept);
+
+        descriptor.rounding_intervals.intervals[0].rounding_mod = 100;
+        let rounded = descriptor.get_range_payouts(1000).unwrap();
+        assert!(rounded.len() < range_payouts.len());
+
+        descriptor.payout_function =
+            get_descriptor("numerical base 2 digits 10\nfrom 0 pay 1000\nflat to max")
+                .payout_function;
+        let flat = descriptor.get_range_payouts(1000).unwrap();
+        assert_eq!(1, flat.len());
+        assert_eq!(1000, flat[0].payout.offer);
+    }
+}
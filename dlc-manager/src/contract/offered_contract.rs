@@ -2,9 +2,26 @@
 
 use super::contract_info::ContractInfo;
 use super::FundingInputInfo;
-use dlc::PartyParams;
+use crate::{ContractId, TemporaryContractId, TimeSource};
+use dlc::{FeeSplit, PartyParams, PremiumInfo};
+use dlc_messages::FundingInputProofs;
 use secp256k1_zkp::PublicKey;
 
+/// Information about the renewal of an existing contract into a new one.
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct RenewalInfo {
+    /// The id of the contract whose fund output is spent by the renewal.
+    pub contract_id: ContractId,
+    /// The part of the value of the renewed contract fund output that belongs
+    /// to the party that did not propose the renewal.
+    pub counter_payout: u64,
+}
+
 /// Contains information about a contract that was offered.
 #[derive(Clone)]
 #[cfg_attr(
@@ -14,9 +31,23 @@ use secp256k1_zkp::PublicKey;
 )]
 pub struct OfferedContract {
     /// The temporary id of the contract.
-    pub id: [u8; 32],
+    pub id: TemporaryContractId,
     /// Indicated whether the contract was proposed or received.
     pub is_offer_party: bool,
+    /// Whether the local party's payout script pubkey belongs to a
+    /// destination that is not controlled by the wallet (e.g. cold storage).
+    pub is_payout_external: bool,
+    /// Information about the premium paid within the fund transaction, if any.
+    pub premium: Option<PremiumInfo>,
+    /// Information about the contract being renewed if the fund transaction of
+    /// this contract spends the fund output of an existing one.
+    pub renewal: Option<RenewalInfo>,
+    /// How the fees of the fund and CET transactions are split between the
+    /// parties, if different from the default half/half split.
+    pub fee_split: Option<FeeSplit>,
+    /// The number of confirmations of the fund transaction required before
+    /// considering the contract as confirmed.
+    pub nb_confirmations: u32,
     /// The set of contract information that are used to generate CET and
     /// adaptor signatures.
     pub contract_info: Vec<ContractInfo>,
@@ -36,4 +67,12 @@ pub struct OfferedContract {
     pub contract_maturity_bound: u32,
     /// The time at which the contract becomes refundable.
     pub contract_timeout: u32,
+    /// The source of time used to evaluate the maturity of the contract and
+    /// its refund locktime.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub time_source: TimeSource,
+    /// The proofs that the offering party controls the outputs spent by its
+    /// funding inputs, kept so that the offer message can be rebuilt.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub funding_input_proofs: Option<FundingInputProofs>,
 }
@@ -1,35 +1,65 @@
 //! Serialization trait implementations for various data structures enabling them
 //! to be converted to byte arrays.
 
+use crate::broadcast::{BroadcastKind, PendingBroadcast};
 use crate::contract::accepted_contract::AcceptedContract;
 use crate::contract::contract_info::ContractInfo;
 use crate::contract::enum_descriptor::EnumDescriptor;
+use crate::contract::hybrid_descriptor::{HybridBranch, HybridDescriptor};
 use crate::contract::numerical_descriptor::{
     DifferenceParams, NumericalDescriptor, NumericalEventInfo,
 };
-use crate::contract::offered_contract::OfferedContract;
+use crate::contract::offered_contract::{OfferedContract, RenewalInfo};
 use crate::contract::signed_contract::SignedContract;
 use crate::contract::AdaptorInfo;
 use crate::contract::{
-    ClosedContract, ContractDescriptor, FailedAcceptContract, FailedSignContract, FundingInputInfo,
+    ArchivedContract, AttestedOutcome, ClosedContract, ContractDescriptor, ContractHistoryEntry,
+    ContractState, FailedAcceptContract, FailedSignContract, FundingInputInfo,
 };
+use crate::manager::{get_own_payout, NB_CONFIRMATIONS};
 use crate::payout_curve::{
     HyperbolaPayoutCurvePiece, PayoutFunction, PayoutFunctionPiece, PayoutPoint,
     PolynomialPayoutCurvePiece, RoundingInterval, RoundingIntervals,
 };
+use crate::TimeSource;
 use dlc::DlcTransactions;
 use dlc_messages::ser_impls::{
-    read_ecdsa_adaptor_signatures, read_option_cb, read_usize, read_vec_cb,
-    write_ecdsa_adaptor_signatures, write_option_cb, write_usize, write_vec_cb,
+    read_ecdsa_adaptor_signatures, read_option, read_option_cb, read_string, read_usize, read_vec,
+    read_vec_cb, write_ecdsa_adaptor_signatures, write_option, write_option_cb, write_usize,
+    write_vec, write_vec_cb,
 };
+use dlc_messages::{AcceptDlc, SignDlc};
+use dlc_trie::compact_multi_oracle_trie::{CompactMultiOracleTrie, CompactMultiOracleTrieDump};
 use dlc_trie::digit_trie::{DigitNodeData, DigitTrieDump};
+use dlc_trie::multi_oracle::CoverageStrategy;
 use dlc_trie::multi_oracle_trie::{MultiOracleTrie, MultiOracleTrieDump};
 use dlc_trie::multi_oracle_trie_with_diff::{MultiOracleTrieWithDiff, MultiOracleTrieWithDiffDump};
-use dlc_trie::multi_trie::{MultiTrieDump, MultiTrieNodeData, TrieNodeInfo};
+use dlc_trie::multi_trie::{MultiTrieDump, MultiTrieNodeData, OraclePairExps, TrieNodeInfo};
 use dlc_trie::RangeInfo;
 use lightning::ln::msgs::DecodeError;
 use lightning::util::ser::{Readable, Writeable, Writer};
-use std::io::Read;
+use std::io::{Cursor, Read};
+
+/// Byte preceding the version of the serialization of types that were first
+/// serialized without one. Their unversioned serialization cannot start with
+/// it, as it starts with an enum variant id or with a BigSize length that is
+/// lower than 2^32.
+const VERSION_MARKER: u8 = 0xFF;
+/// The current serialization version of [`ContractDescriptor`]. Version 2
+/// added the exact evaluation flag of numerical and hybrid descriptors, and
+/// version 3 the maximum value of numerical descriptors.
+const CONTRACT_DESCRIPTOR_VERSION: u8 = 3;
+/// The current serialization version of [`PayoutFunction`].
+const PAYOUT_FUNCTION_VERSION: u8 = 1;
+/// The current serialization version of [`AdaptorInfo`].
+const ADAPTOR_INFO_VERSION: u8 = 1;
+
+/// The current serialization version of the contract records kept by
+/// storages: [`OfferedContract`], [`AcceptedContract`], [`SignedContract`],
+/// [`ClosedContract`], [`FailedAcceptContract`] and [`FailedSignContract`].
+/// Version 0 is the serialization of the records written before versions were
+/// introduced.
+pub const CONTRACT_VERSION: u8 = 1;
 
 /// Trait used to de/serialize an object to/from a vector of bytes.
 pub trait Serializable
@@ -57,6 +87,54 @@ where
     }
 }
 
+/// Trait used to deserialize contract records written with a previous version
+/// of their serialization. The version of the records is not part of their
+/// serialization, so storages must keep track of the version with which they
+/// were written.
+pub trait VersionedSerializable: Serializable {
+    /// Deserialize an object serialized with the given version, objects
+    /// serialized using [`Serializable::serialize`] having version
+    /// [`CONTRACT_VERSION`].
+    fn deserialize_version<R: Read>(reader: &mut R, version: u8) -> Result<Self, DecodeError>;
+}
+
+macro_rules! impl_versioned_serializable {
+    ($st: ident, $read_v0: ident) => {
+        impl VersionedSerializable for $st {
+            fn deserialize_version<R: Read>(
+                reader: &mut R,
+                version: u8,
+            ) -> Result<Self, DecodeError> {
+                match version {
+                    0 => $read_v0(reader),
+                    CONTRACT_VERSION => Readable::read(reader),
+                    _ => Err(DecodeError::UnknownVersion),
+                }
+            }
+        }
+    };
+}
+
+impl_versioned_serializable!(OfferedContract, read_offered_contract_v0);
+impl_versioned_serializable!(AcceptedContract, read_accepted_contract_v0);
+impl_versioned_serializable!(SignedContract, read_signed_contract_v0);
+impl_versioned_serializable!(ClosedContract, read_closed_contract_v0);
+impl_versioned_serializable!(FailedAcceptContract, read_failed_accept_contract_v0);
+impl_versioned_serializable!(FailedSignContract, read_failed_sign_contract_v0);
+
+/// Serialize the adaptor information of a contract, enabling storages to keep
+/// it separately from the contract.
+pub fn serialize_adaptor_infos(adaptor_infos: &[AdaptorInfo]) -> Result<Vec<u8>, ::std::io::Error> {
+    let mut buffer = Vec::new();
+    write_vec(adaptor_infos, &mut buffer)?;
+    Ok(buffer)
+}
+
+/// Deserialize adaptor information serialized using [`serialize_adaptor_infos`].
+pub fn deserialize_adaptor_infos<R: Read>(reader: &mut R) -> Result<Vec<AdaptorInfo>, DecodeError> {
+    read_vec(reader)
+}
+
 impl_dlc_writeable!(PayoutPoint, { (event_outcome, writeable), (outcome_payout, writeable), (extra_precision, writeable) });
 impl_dlc_writeable_enum!(
     PayoutFunctionPiece,
@@ -64,12 +142,10 @@ impl_dlc_writeable_enum!(
     (1, HyperbolaPayoutCurvePiece);;
 );
 impl_dlc_writeable!(RoundingInterval, { (begin_interval, writeable), (rounding_mod, writeable) });
-impl_dlc_writeable!(PayoutFunction, { (payout_function_pieces, vec) });
-impl_dlc_writeable!(NumericalDescriptor, { (payout_function, writeable), (rounding_intervals, writeable), (info, writeable), (difference_params, option) });
 impl_dlc_writeable!(PolynomialPayoutCurvePiece, { (payout_points, vec) });
 impl_dlc_writeable!(RoundingIntervals, { (intervals, vec) });
 impl_dlc_writeable!(NumericalEventInfo, { (base, usize), (nb_digits, usize), (unit, string) });
-impl_dlc_writeable!(DifferenceParams, { (max_error_exp, usize), (min_support_exp, usize), (maximize_coverage, writeable) });
+impl_dlc_writeable!(DifferenceParams, { (max_error_exp, usize), (min_support_exp, usize), (coverage_strategy, {cb_writeable, write_coverage_strategy, read_coverage_strategy}) });
 impl_dlc_writeable!(HyperbolaPayoutCurvePiece, {
     (left_end_point, writeable),
     (right_end_point, writeable),
@@ -81,9 +157,11 @@ impl_dlc_writeable!(HyperbolaPayoutCurvePiece, {
     (c, float),
     (d, float)
 });
-impl_dlc_writeable_enum!(ContractDescriptor, (0, Enum), (1, Numerical);;);
+impl_dlc_writeable_enum!(TimeSource,;; (0, WallClock), (1, MedianTimePast), (2, BlockHeight));
+impl_dlc_writeable!(HybridBranch, { (outcome, string), (payout_function, writeable), (rounding_intervals, writeable) });
+impl_dlc_writeable!(HybridDescriptor, { (selector_event_id, string), (selector_announcements, vec), (branches, vec), (info, writeable), (exact_evaluation, writeable) });
 impl_dlc_writeable!(ContractInfo, { (contract_descriptor, writeable), (oracle_announcements, vec), (threshold, usize)});
-impl_dlc_writeable!(FundingInputInfo, { (funding_input, writeable), (address, {option_cb, dlc_messages::ser_impls::write_address, dlc_messages::ser_impls::read_address}) });
+impl_dlc_writeable!(FundingInputInfo, { (funding_input, writeable), (address, {option_cb, dlc_messages::ser_impls::write_address, dlc_messages::ser_impls::read_address}), (witness_script, option) });
 impl_dlc_writeable!(EnumDescriptor, {
     (
         outcome_payouts,
@@ -93,6 +171,11 @@ impl_dlc_writeable!(EnumDescriptor, {
 impl_dlc_writeable!(OfferedContract, {
     (id, writeable),
     (is_offer_party, writeable),
+    (is_payout_external, writeable),
+    (premium, { option_cb, dlc_messages::ser_impls::premium_info::write, dlc_messages::ser_impls::premium_info::read }),
+    (renewal, option),
+    (fee_split, { option_cb, dlc_messages::ser_impls::fee_split::write, dlc_messages::ser_impls::fee_split::read }),
+    (nb_confirmations, writeable),
     (contract_info, vec),
     (offer_params, { cb_writeable, dlc_messages::ser_impls::party_params::write, dlc_messages::ser_impls::party_params::read }),
     (total_collateral, writeable),
@@ -101,10 +184,11 @@ impl_dlc_writeable!(OfferedContract, {
     (fee_rate_per_vb, writeable),
     (contract_maturity_bound, writeable),
     (contract_timeout, writeable),
-    (counter_party, writeable)
+    (time_source, writeable),
+    (counter_party, writeable),
+    (funding_input_proofs, option)
 });
 impl_dlc_writeable_external!(RangeInfo, range_info, { (cet_index, usize), (adaptor_index, usize)});
-impl_dlc_writeable_enum!(AdaptorInfo,; (0, Numerical, write_multi_oracle_trie, read_multi_oracle_trie), (1, NumericalWithDifference, write_multi_oracle_trie_with_diff, read_multi_oracle_trie_with_diff); (2, Enum));
 impl_dlc_writeable_external!(
     DlcTransactions, dlc_transactions,
     { (fund, writeable),
@@ -114,6 +198,7 @@ impl_dlc_writeable_external!(
 );
 impl_dlc_writeable!(AcceptedContract, {
     (offered_contract, writeable),
+    (funding_input_proofs, option),
     (accept_params, { cb_writeable, dlc_messages::ser_impls::party_params::write, dlc_messages::ser_impls::party_params::read }),
     (funding_inputs, vec),
     (adaptor_infos, vec),
@@ -125,30 +210,456 @@ impl_dlc_writeable!(SignedContract, {
     (accepted_contract, writeable),
     (adaptor_signatures, {option_cb, write_ecdsa_adaptor_signatures, read_ecdsa_adaptor_signatures }),
     (offer_refund_signature, writeable),
-    (funding_signatures, writeable)
+    (funding_signatures, writeable),
+    (signed_fund_tx, option)
 });
 impl_dlc_writeable!(ClosedContract, {
     (signed_contract, writeable),
     (attestations, vec),
-    (cet_index, usize)
+    (cet_index, usize),
+    (own_payout, writeable),
+    (adaptor_secret, option),
+    (signed_cet, option)
+});
+impl_dlc_writeable!(FailedAcceptContract, {(offered_contract, writeable), (accept_message, {cb_writeable, write_message, read_message}), (error_message, string)});
+impl_dlc_writeable!(FailedSignContract, {(accepted_contract, writeable), (sign_message, {cb_writeable, write_message, read_message}), (error_message, string)});
+impl_dlc_writeable!(RenewalInfo, {(contract_id, writeable), (counter_payout, writeable)});
+impl_dlc_writeable_enum!(
+    ContractState,;;
+    (0, Offered),
+    (1, Accepted),
+    (2, Signed),
+    (3, Confirmed),
+    (4, Closed),
+    (5, Refunded),
+    (6, RefundedDueToNoAttestation),
+    (7, FailedAccept),
+    (8, FailedSign),
+    (9, Renewed)
+);
+impl_dlc_writeable!(ArchivedContract, {
+    (id, writeable),
+    (temporary_id, writeable),
+    (counter_party, writeable),
+    (is_offer_party, writeable),
+    (state, writeable),
+    (own_collateral, writeable),
+    (total_collateral, writeable),
+    (fund_txid, writeable),
+    (closing_txid, writeable),
+    (own_payout, writeable),
+    (attestations, vec),
+    (counter_payout, writeable),
+    (fund_tx_fee, writeable),
+    (closing_tx_fee, writeable),
+    (attested_outcomes, vec)
 });
-impl_dlc_writeable!(FailedAcceptContract, {(offered_contract, writeable), (accept_message, writeable), (error_message, string)});
-impl_dlc_writeable!(FailedSignContract, {(accepted_contract, writeable), (sign_message, writeable), (error_message, string)});
+impl_dlc_writeable_enum!(AttestedOutcome,; (0, Enum, dlc_messages::ser_impls::write_string, dlc_messages::ser_impls::read_string), (1, Numerical, write_i64, read_i64););
+impl_dlc_writeable!(ContractHistoryEntry, {(timestamp, writeable), (state, string), (txid, option), (message_hash, option)});
+impl_dlc_writeable_enum!(BroadcastKind,;; (0, Fund), (1, Cet), (2, Refund));
+impl_dlc_writeable!(PendingBroadcast, {(contract_id, writeable), (kind, writeable), (transaction, writeable), (not_before, writeable), (nb_attempts, writeable)});
 
 impl_dlc_writeable_external!(DigitTrieDump<Vec<RangeInfo> >, digit_trie_dump_vec_range, { (node_data, {vec_cb, write_digit_node_data_vec_range, read_digit_node_data_vec_range}), (root, {option_cb, write_usize, read_usize}), (base, usize)});
 impl_dlc_writeable_external!(DigitTrieDump<RangeInfo>, digit_trie_dump_range, { (node_data, {vec_cb, write_digit_node_data_range, read_digit_node_data_range}), (root, {option_cb, write_usize, read_usize}), (base, usize)});
 impl_dlc_writeable_external!(DigitTrieDump<Vec<TrieNodeInfo> >, digit_trie_dump_trie, { (node_data, {vec_cb, write_digit_node_data_trie, read_digit_node_data_trie}), (root, {option_cb, write_usize, read_usize}), (base, usize)});
 impl_dlc_writeable_external!(MultiOracleTrieDump, multi_oracle_trie_dump, { (digit_trie_dump, {cb_writeable, digit_trie_dump_vec_range::write, digit_trie_dump_vec_range::read}), (nb_oracles, usize), (threshold, usize), (nb_digits, usize) });
+impl_dlc_writeable_external!(CompactMultiOracleTrieDump, compact_multi_oracle_trie_dump, { (digit_trie_dump, {cb_writeable, digit_trie_dump_range::write, digit_trie_dump_range::read}), (nb_oracles, usize), (threshold, usize), (nb_digits, usize) });
 impl_dlc_writeable_external_enum!(
     MultiTrieNodeData<RangeInfo>,
     multi_trie_node_data,
     (0, Leaf, digit_trie_dump_range),
     (1, Node, digit_trie_dump_trie)
 );
-impl_dlc_writeable_external!(MultiTrieDump<RangeInfo>, multi_trie_dump, { (node_data, {vec_cb, multi_trie_node_data::write, multi_trie_node_data::read}), (base, usize), (nb_tries, usize), (nb_required, usize), (min_support_exp, usize), (max_error_exp, usize), (nb_digits, usize), (maximize_coverage, writeable) });
+impl_dlc_writeable_external!(MultiTrieDump<RangeInfo>, multi_trie_dump, { (node_data, {vec_cb, multi_trie_node_data::write, multi_trie_node_data::read}), (base, usize), (nb_tries, usize), (nb_required, usize), (min_support_exp, usize), (max_error_exp, usize), (nb_digits, usize), (coverage_strategy, {cb_writeable, write_coverage_strategy, read_coverage_strategy}), (pair_exps, {vec_cb, oracle_pair_exps::write, oracle_pair_exps::read}) });
+impl_dlc_writeable_external!(OraclePairExps, oracle_pair_exps, { (first_index, usize), (second_index, usize), (min_support_exp, usize), (max_error_exp, usize) });
 impl_dlc_writeable_external!(MultiOracleTrieWithDiffDump, multi_oracle_trie_with_diff_dump, { (multi_trie_dump, {cb_writeable, multi_trie_dump::write, multi_trie_dump::read}), (base, usize), (nb_digits, usize) });
 impl_dlc_writeable_external!(TrieNodeInfo, trie_node_info, { (trie_index, usize), (store_index, usize) });
 
+impl Writeable for ContractDescriptor {
+    fn write<W: Writer>(&self, w: &mut W) -> Result<(), ::std::io::Error> {
+        write_version(CONTRACT_DESCRIPTOR_VERSION, w)?;
+        match self {
+            ContractDescriptor::Enum(e) => {
+                0u8.write(w)?;
+                e.write(w)
+            }
+            ContractDescriptor::Numerical(n) => {
+                1u8.write(w)?;
+                n.write(w)
+            }
+            ContractDescriptor::Hybrid(h) => {
+                2u8.write(w)?;
+                h.write(w)
+            }
+        }
+    }
+}
+
+impl Readable for ContractDescriptor {
+    fn read<R: Read>(reader: &mut R) -> Result<ContractDescriptor, DecodeError> {
+        // Unversioned descriptors were written before hybrid descriptors and
+        // summed payout functions were introduced.
+        let (version, id) = read_version(reader, CONTRACT_DESCRIPTOR_VERSION)?;
+        match (id, version) {
+            (0, _) => Ok(ContractDescriptor::Enum(Readable::read(reader)?)),
+            (1, 0) => Ok(ContractDescriptor::Numerical(read_numerical_descriptor_v0(
+                reader,
+            )?)),
+            (1, 1) => Ok(ContractDescriptor::Numerical(read_numerical_descriptor_v1(
+                reader,
+            )?)),
+            (1, 2) => Ok(ContractDescriptor::Numerical(read_numerical_descriptor_v2(
+                reader,
+            )?)),
+            (1, _) => Ok(ContractDescriptor::Numerical(Readable::read(reader)?)),
+            (2, 1) => Ok(ContractDescriptor::Hybrid(read_hybrid_descriptor_v1(
+                reader,
+            )?)),
+            (2, 2..=CONTRACT_DESCRIPTOR_VERSION) => {
+                Ok(ContractDescriptor::Hybrid(Readable::read(reader)?))
+            }
+            _ => Err(DecodeError::UnknownRequiredFeature),
+        }
+    }
+}
+
+impl Writeable for PayoutFunction {
+    fn write<W: Writer>(&self, w: &mut W) -> Result<(), ::std::io::Error> {
+        write_version(PAYOUT_FUNCTION_VERSION, w)?;
+        write_vec(&self.payout_function_pieces, w)
+    }
+}
+
+impl Readable for PayoutFunction {
+    fn read<R: Read>(reader: &mut R) -> Result<PayoutFunction, DecodeError> {
+        // Unversioned payout functions have the same layout as version 1 ones,
+        // the byte read after the version being the start of the length of
+        // their pieces.
+        let (_, first) = read_version(reader, PAYOUT_FUNCTION_VERSION)?;
+        let mut reader = Cursor::new([first]).chain(reader);
+        Ok(PayoutFunction {
+            payout_function_pieces: read_vec(&mut reader)?,
+        })
+    }
+}
+
+impl Writeable for AdaptorInfo {
+    fn write<W: Writer>(&self, w: &mut W) -> Result<(), ::std::io::Error> {
+        write_version(ADAPTOR_INFO_VERSION, w)?;
+        match self {
+            AdaptorInfo::Numerical(trie) => {
+                0u8.write(w)?;
+                write_multi_oracle_trie(trie, w)
+            }
+            AdaptorInfo::NumericalWithDifference(trie) => {
+                1u8.write(w)?;
+                write_multi_oracle_trie_with_diff(trie, w)
+            }
+            AdaptorInfo::Enum => 2u8.write(w),
+            AdaptorInfo::Hybrid(tries) => {
+                3u8.write(w)?;
+                write_hybrid_tries(tries, w)
+            }
+            AdaptorInfo::CompactNumerical(trie) => {
+                4u8.write(w)?;
+                write_compact_multi_oracle_trie(trie, w)
+            }
+        }
+    }
+}
+
+impl Readable for AdaptorInfo {
+    fn read<R: Read>(reader: &mut R) -> Result<AdaptorInfo, DecodeError> {
+        // Unversioned adaptor information was written before difference
+        // parameters specific to pairs of oracles were introduced.
+        let (version, id) = read_version(reader, ADAPTOR_INFO_VERSION)?;
+        match id {
+            1 if version == 0 => Ok(AdaptorInfo::NumericalWithDifference(
+                read_multi_oracle_trie_with_diff_v0(reader)?,
+            )),
+            0 => Ok(AdaptorInfo::Numerical(read_multi_oracle_trie(reader)?)),
+            1 => Ok(AdaptorInfo::NumericalWithDifference(
+                read_multi_oracle_trie_with_diff(reader)?,
+            )),
+            2 => Ok(AdaptorInfo::Enum),
+            3 => Ok(AdaptorInfo::Hybrid(read_hybrid_tries(reader)?)),
+            4 => Ok(AdaptorInfo::CompactNumerical(
+                read_compact_multi_oracle_trie(reader)?,
+            )),
+            _ => Err(DecodeError::UnknownRequiredFeature),
+        }
+    }
+}
+
+impl Writeable for NumericalDescriptor {
+    fn write<W: Writer>(&self, w: &mut W) -> Result<(), ::std::io::Error> {
+        self.payout_function.write(w)?;
+        self.rounding_intervals.write(w)?;
+        self.info.write(w)?;
+        write_option(&self.difference_params, w)?;
+        write_vec(&self.additional_payout_functions, w)?;
+        self.exact_evaluation.write(w)?;
+        write_option(&self.max_value, w)
+    }
+}
+
+impl Readable for NumericalDescriptor {
+    fn read<R: Read>(reader: &mut R) -> Result<NumericalDescriptor, DecodeError> {
+        Ok(NumericalDescriptor {
+            payout_function: Readable::read(reader)?,
+            rounding_intervals: Readable::read(reader)?,
+            info: Readable::read(reader)?,
+            difference_params: read_option(reader)?,
+            additional_payout_functions: read_vec(reader)?,
+            exact_evaluation: Readable::read(reader)?,
+            max_value: read_option(reader)?,
+            range_payouts_cache: Default::default(),
+        })
+    }
+}
+
+fn read_numerical_descriptor_v0<R: Read>(
+    reader: &mut R,
+) -> Result<NumericalDescriptor, DecodeError> {
+    Ok(NumericalDescriptor {
+        payout_function: Readable::read(reader)?,
+        rounding_intervals: Readable::read(reader)?,
+        info: Readable::read(reader)?,
+        difference_params: read_option(reader)?,
+        additional_payout_functions: Vec::new(),
+        exact_evaluation: false,
+        max_value: None,
+        range_payouts_cache: Default::default(),
+    })
+}
+
+// Descriptors written before version 2 are evaluated using floating point
+// arithmetic.
+fn read_numerical_descriptor_v1<R: Read>(
+    reader: &mut R,
+) -> Result<NumericalDescriptor, DecodeError> {
+    Ok(NumericalDescriptor {
+        payout_function: Readable::read(reader)?,
+        rounding_intervals: Readable::read(reader)?,
+        info: Readable::read(reader)?,
+        difference_params: read_option(reader)?,
+        additional_payout_functions: read_vec(reader)?,
+        exact_evaluation: false,
+        max_value: None,
+        range_payouts_cache: Default::default(),
+    })
+}
+
+// Descriptors written before version 3 cover all the outcomes that can be
+// represented with the number of digits of their event.
+fn read_numerical_descriptor_v2<R: Read>(
+    reader: &mut R,
+) -> Result<NumericalDescriptor, DecodeError> {
+    Ok(NumericalDescriptor {
+        payout_function: Readable::read(reader)?,
+        rounding_intervals: Readable::read(reader)?,
+        info: Readable::read(reader)?,
+        difference_params: read_option(reader)?,
+        additional_payout_functions: read_vec(reader)?,
+        exact_evaluation: Readable::read(reader)?,
+        max_value: None,
+        range_payouts_cache: Default::default(),
+    })
+}
+
+fn read_hybrid_descriptor_v1<R: Read>(reader: &mut R) -> Result<HybridDescriptor, DecodeError> {
+    Ok(HybridDescriptor {
+        selector_event_id: read_string(reader)?,
+        selector_announcements: read_vec(reader)?,
+        branches: read_vec(reader)?,
+        info: Readable::read(reader)?,
+        exact_evaluation: false,
+    })
+}
+
+fn read_multi_oracle_trie_with_diff_v0<R: Read>(
+    reader: &mut R,
+) -> Result<MultiOracleTrieWithDiff, DecodeError> {
+    let multi_trie_dump = MultiTrieDump {
+        node_data: read_vec_cb(reader, &multi_trie_node_data::read)?,
+        base: read_usize(reader)?,
+        nb_tries: read_usize(reader)?,
+        nb_required: read_usize(reader)?,
+        min_support_exp: read_usize(reader)?,
+        max_error_exp: read_usize(reader)?,
+        nb_digits: read_usize(reader)?,
+        coverage_strategy: read_coverage_strategy(reader)?,
+        pair_exps: Vec::new(),
+    };
+    Ok(MultiOracleTrieWithDiff::from_dump(
+        MultiOracleTrieWithDiffDump {
+            multi_trie_dump,
+            base: read_usize(reader)?,
+            nb_digits: read_usize(reader)?,
+        },
+    ))
+}
+
+// The records of version 0 do not contain the fields added since then, which
+// are set to the values matching the behavior of the versions that wrote them.
+fn read_offered_contract_v0<R: Read>(reader: &mut R) -> Result<OfferedContract, DecodeError> {
+    Ok(OfferedContract {
+        id: Readable::read(reader)?,
+        is_offer_party: Readable::read(reader)?,
+        is_payout_external: false,
+        premium: None,
+        renewal: None,
+        fee_split: None,
+        nb_confirmations: NB_CONFIRMATIONS,
+        contract_info: read_vec(reader)?,
+        offer_params: dlc_messages::ser_impls::party_params::read(reader)?,
+        total_collateral: Readable::read(reader)?,
+        funding_inputs_info: read_vec_cb(reader, &read_funding_input_info_v0)?,
+        fund_output_serial_id: Readable::read(reader)?,
+        fee_rate_per_vb: Readable::read(reader)?,
+        contract_maturity_bound: Readable::read(reader)?,
+        contract_timeout: Readable::read(reader)?,
+        time_source: TimeSource::default(),
+        counter_party: Readable::read(reader)?,
+        funding_input_proofs: None,
+    })
+}
+
+fn read_funding_input_info_v0<R: Read>(reader: &mut R) -> Result<FundingInputInfo, DecodeError> {
+    Ok(FundingInputInfo {
+        funding_input: Readable::read(reader)?,
+        address: read_option_cb(reader, &dlc_messages::ser_impls::read_address)?,
+        witness_script: None,
+    })
+}
+
+fn read_accepted_contract_v0<R: Read>(reader: &mut R) -> Result<AcceptedContract, DecodeError> {
+    Ok(AcceptedContract {
+        offered_contract: read_offered_contract_v0(reader)?,
+        funding_input_proofs: None,
+        accept_params: dlc_messages::ser_impls::party_params::read(reader)?,
+        funding_inputs: read_vec_cb(reader, &read_funding_input_info_v0)?,
+        adaptor_infos: read_vec(reader)?,
+        adaptor_signatures: read_option_cb(reader, &read_ecdsa_adaptor_signatures)?,
+        accept_refund_signature: Readable::read(reader)?,
+        dlc_transactions: dlc_transactions::read(reader)?,
+    })
+}
+
+fn read_signed_contract_v0<R: Read>(reader: &mut R) -> Result<SignedContract, DecodeError> {
+    Ok(SignedContract {
+        accepted_contract: read_accepted_contract_v0(reader)?,
+        adaptor_signatures: read_option_cb(reader, &read_ecdsa_adaptor_signatures)?,
+        offer_refund_signature: Readable::read(reader)?,
+        funding_signatures: Readable::read(reader)?,
+        signed_fund_tx: None,
+    })
+}
+
+fn read_closed_contract_v0<R: Read>(reader: &mut R) -> Result<ClosedContract, DecodeError> {
+    let signed_contract = read_signed_contract_v0(reader)?;
+    let attestations = read_vec(reader)?;
+    let cet_index = read_usize(reader)?;
+    let own_payout = match signed_contract
+        .accepted_contract
+        .dlc_transactions
+        .cets
+        .get(cet_index)
+    {
+        Some(cet) => get_own_payout(&signed_contract, cet),
+        None => return Err(DecodeError::InvalidValue),
+    };
+    Ok(ClosedContract {
+        signed_contract,
+        attestations,
+        cet_index,
+        own_payout,
+        adaptor_secret: None,
+        signed_cet: None,
+    })
+}
+
+fn read_failed_accept_contract_v0<R: Read>(
+    reader: &mut R,
+) -> Result<FailedAcceptContract, DecodeError> {
+    Ok(FailedAcceptContract {
+        offered_contract: read_offered_contract_v0(reader)?,
+        accept_message: AcceptDlc {
+            temporary_contract_id: Readable::read(reader)?,
+            accept_collateral: Readable::read(reader)?,
+            funding_pubkey: Readable::read(reader)?,
+            payout_spk: Readable::read(reader)?,
+            payout_serial_id: Readable::read(reader)?,
+            funding_inputs: read_vec(reader)?,
+            change_spk: Readable::read(reader)?,
+            change_serial_id: Readable::read(reader)?,
+            cet_adaptor_signatures: Readable::read(reader)?,
+            refund_signature: Readable::read(reader)?,
+            negotiation_fields: read_option(reader)?,
+            funding_input_proofs: None,
+            extra_tlvs: Vec::new(),
+        },
+        error_message: read_string(reader)?,
+    })
+}
+
+fn read_failed_sign_contract_v0<R: Read>(
+    reader: &mut R,
+) -> Result<FailedSignContract, DecodeError> {
+    Ok(FailedSignContract {
+        accepted_contract: read_accepted_contract_v0(reader)?,
+        sign_message: SignDlc {
+            contract_id: Readable::read(reader)?,
+            cet_adaptor_signatures: Readable::read(reader)?,
+            refund_signature: Readable::read(reader)?,
+            funding_signatures: Readable::read(reader)?,
+            extra_tlvs: Vec::new(),
+        },
+        error_message: read_string(reader)?,
+    })
+}
+
+fn write_version<W: Writer>(version: u8, w: &mut W) -> Result<(), ::std::io::Error> {
+    VERSION_MARKER.write(w)?;
+    version.write(w)
+}
+
+/// Reads a version written with [`write_version`] and the byte following it.
+/// Serializations written before versions were introduced are reported as
+/// version 0, the returned byte then being their first one. Versions newer
+/// than `current_version` cannot be read.
+fn read_version<R: Read>(reader: &mut R, current_version: u8) -> Result<(u8, u8), DecodeError> {
+    let first: u8 = Readable::read(reader)?;
+    if first != VERSION_MARKER {
+        return Ok((0, first));
+    }
+    let version: u8 = Readable::read(reader)?;
+    if version == 0 || version > current_version {
+        return Err(DecodeError::UnknownVersion);
+    }
+    Ok((version, Readable::read(reader)?))
+}
+
+// Messages end with a TLV stream read until the end of the input, so they are
+// prefixed with their length when followed by other fields.
+fn write_message<T: Writeable, W: Writer>(
+    message: &T,
+    writer: &mut W,
+) -> Result<(), ::std::io::Error> {
+    (message.serialized_length() as u64).write(writer)?;
+    message.write(writer)
+}
+
+fn read_message<T: Readable, R: Read>(reader: &mut R) -> Result<T, DecodeError> {
+    let len: u64 = Readable::read(reader)?;
+    let mut message_reader = reader.take(len);
+    let message = Readable::read(&mut message_reader)?;
+    if message_reader.limit() != 0 {
+        return Err(DecodeError::InvalidValue);
+    }
+    Ok(message)
+}
+
 fn write_digit_node_data_trie<W: Writer>(
     input: &DigitNodeData<Vec<TrieNodeInfo>>,
     writer: &mut W,
@@ -253,6 +764,75 @@ fn read_multi_oracle_trie<R: Read>(reader: &mut R) -> Result<MultiOracleTrie, De
     Ok(MultiOracleTrie::from_dump(dump))
 }
 
+fn write_i64<W: Writer>(i: &i64, w: &mut W) -> Result<(), ::std::io::Error> {
+    (*i as u64).write(w)
+}
+
+fn read_i64<R: Read>(reader: &mut R) -> Result<i64, DecodeError> {
+    let i: u64 = Readable::read(reader)?;
+    Ok(i as i64)
+}
+
+// Strategies are written as a boolean indicating whether the support is
+// maximized when possible, to remain readable from earlier versions.
+fn write_coverage_strategy<W: Writer>(
+    strategy: &CoverageStrategy,
+    w: &mut W,
+) -> Result<(), ::std::io::Error> {
+    match strategy {
+        CoverageStrategy::MinimizeCetCount => 0u8.write(w),
+        CoverageStrategy::MaximizeSupport => 1u8.write(w),
+        CoverageStrategy::Custom { support_exp } => {
+            2u8.write(w)?;
+            write_usize(support_exp, w)
+        }
+    }
+}
+
+fn read_coverage_strategy<R: Read>(reader: &mut R) -> Result<CoverageStrategy, DecodeError> {
+    let id: u8 = Readable::read(reader)?;
+    match id {
+        0 => Ok(CoverageStrategy::MinimizeCetCount),
+        1 => Ok(CoverageStrategy::MaximizeSupport),
+        2 => Ok(CoverageStrategy::Custom {
+            support_exp: read_usize(reader)?,
+        }),
+        _ => Err(DecodeError::UnknownRequiredFeature),
+    }
+}
+
+fn write_compact_multi_oracle_trie<W: Writer>(
+    trie: &CompactMultiOracleTrie,
+    w: &mut W,
+) -> Result<(), ::std::io::Error> {
+    compact_multi_oracle_trie_dump::write(&trie.dump(), w)
+}
+
+fn read_compact_multi_oracle_trie<R: Read>(
+    reader: &mut R,
+) -> Result<CompactMultiOracleTrie, DecodeError> {
+    let dump = compact_multi_oracle_trie_dump::read(reader)?;
+    Ok(CompactMultiOracleTrie::from_dump(dump))
+}
+
+fn write_hybrid_tries<W: Writer>(
+    tries: &[(usize, MultiOracleTrie)],
+    w: &mut W,
+) -> Result<(), ::std::io::Error> {
+    write_vec_cb(tries, w, &|(cet_offset, trie), w| {
+        write_usize(cet_offset, w)?;
+        write_multi_oracle_trie(trie, w)
+    })
+}
+
+fn read_hybrid_tries<R: Read>(
+    reader: &mut R,
+) -> Result<Vec<(usize, MultiOracleTrie)>, DecodeError> {
+    read_vec_cb(reader, &|reader| {
+        Ok((read_usize(reader)?, read_multi_oracle_trie(reader)?))
+    })
+}
+
 fn write_multi_oracle_trie_with_diff<W: Writer>(
     trie: &MultiOracleTrieWithDiff,
     w: &mut W,
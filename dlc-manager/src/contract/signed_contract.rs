@@ -1,6 +1,9 @@
 //! #SignedContract
 
 use super::accepted_contract::AcceptedContract;
+use bitcoin::consensus::encode::serialize_hex;
+use bitcoin::util::psbt::PartiallySignedTransaction;
+use bitcoin::{Script, Transaction};
 use dlc_messages::FundingSignatures;
 use secp256k1_zkp::EcdsaAdaptorSignature;
 use secp256k1_zkp::Signature;
@@ -10,10 +13,66 @@ use secp256k1_zkp::Signature;
 pub struct SignedContract {
     /// The accepted contract that was signed.
     pub accepted_contract: AcceptedContract,
-    /// The adaptor signatures of the offering party (None if offering party).
+    /// The adaptor signatures of the offering party (None if offering party
+    /// and the contract was created with an earlier version).
     pub adaptor_signatures: Option<Vec<EcdsaAdaptorSignature>>,
     /// The refund signature of the offering party.
     pub offer_refund_signature: Signature,
     /// The signatures for the funding inputs of the offering party.
     pub funding_signatures: FundingSignatures,
+    /// The fund transaction with the witnesses of all its inputs. Only the
+    /// accepting party, which completes the fund transaction upon receiving
+    /// the sign message, has it.
+    pub signed_fund_tx: Option<Transaction>,
+}
+
+/// The fully signed fund and refund transactions of a contract, for parties
+/// broadcasting them through their own infrastructure.
+#[derive(Clone, Debug)]
+pub struct SignedFunding {
+    /// The fund transaction with the witnesses of all its inputs.
+    pub fund: Transaction,
+    /// The refund transaction signed by both parties.
+    pub refund: Transaction,
+}
+
+impl SignedFunding {
+    /// Returns the hex encoded fund transaction.
+    pub fn get_fund_hex(&self) -> String {
+        serialize_hex(&self.fund)
+    }
+
+    /// Returns the hex encoded refund transaction.
+    pub fn get_refund_hex(&self) -> String {
+        serialize_hex(&self.refund)
+    }
+
+    /// Returns the fund transaction as a PSBT whose inputs are finalized.
+    pub fn get_fund_psbt(&self) -> PartiallySignedTransaction {
+        get_finalized_psbt(&self.fund)
+    }
+
+    /// Returns the refund transaction as a PSBT whose input is finalized.
+    pub fn get_refund_psbt(&self) -> PartiallySignedTransaction {
+        get_finalized_psbt(&self.refund)
+    }
+}
+
+fn get_finalized_psbt(tx: &Transaction) -> PartiallySignedTransaction {
+    let mut unsigned_tx = tx.clone();
+    for input in unsigned_tx.input.iter_mut() {
+        input.script_sig = Script::new();
+        input.witness = Vec::new();
+    }
+    let mut psbt = PartiallySignedTransaction::from_unsigned_tx(unsigned_tx)
+        .expect("to have inputs without script sig nor witness");
+    for (psbt_input, input) in psbt.inputs.iter_mut().zip(tx.input.iter()) {
+        if !input.script_sig.is_empty() {
+            psbt_input.final_script_sig = Some(input.script_sig.clone());
+        }
+        if !input.witness.is_empty() {
+            psbt_input.final_script_witness = Some(input.witness.clone());
+        }
+    }
+    psbt
 }
@@ -6,14 +6,16 @@ pub(crate) fn get_majority_combination(
 
     for outcome in outcomes {
         let index = outcome.0;
-        let outcome_value = outcome.1;
+        let outcome_value: Vec<String> = outcome
+            .1
+            .iter()
+            .map(|x| dlc::normalize_outcome(x).into_owned())
+            .collect();
 
-        if let Some(index_set) = hash_set.get_mut(outcome_value) {
-            index_set.push(index);
-        } else {
-            let index_set = vec![index];
-            hash_set.insert(outcome_value.to_vec(), index_set);
-        }
+        hash_set
+            .entry(outcome_value)
+            .or_insert_with(Vec::new)
+            .push(index);
     }
 
     if hash_set.is_empty() {
@@ -0,0 +1,194 @@
+//! # Contract tool
+//! Functions generating and verifying the adaptor signatures of the CETs of a
+//! contract independently of the [`crate::manager::Manager`], so that they
+//! can be produced by a signer that holds the fund secret key but does not
+//! take part in the exchange of messages.
+
+use crate::contract::contract_info::ContractInfo;
+use crate::contract::offered_contract::OfferedContract;
+use crate::contract::AdaptorInfo;
+use crate::error::Error;
+use bitcoin::Transaction;
+use dlc::{DlcTransactions, PartyParams};
+use secp256k1_zkp::{All, EcdsaAdaptorSignature, PublicKey, Secp256k1, SecretKey};
+
+/// Generates the adaptor information and the adaptor signatures of the CETs
+/// of all the contract information of the given offered contract, as done by
+/// the accepting party. The given transactions are the ones created for the
+/// first contract information, the CETs of all contract information being
+/// returned in the order of the signatures.
+pub fn sign_cets(
+    secp: &Secp256k1<All>,
+    offered_contract: &OfferedContract,
+    accept_params: &PartyParams,
+    dlc_transactions: &DlcTransactions,
+    fund_secret_key: &SecretKey,
+) -> Result<
+    (
+        Vec<AdaptorInfo>,
+        Vec<EcdsaAdaptorSignature>,
+        Vec<Transaction>,
+    ),
+    Error,
+> {
+    let total_collateral = offered_contract.total_collateral;
+    let fund_output_value = dlc_transactions.get_fund_output().value;
+    let mut adaptor_infos = Vec::new();
+    let mut adaptor_sigs = Vec::new();
+    let mut cets = Vec::new();
+
+    for (i, contract_info) in offered_contract.contract_info.iter().enumerate() {
+        let tmp_cets = get_contract_info_cets(
+            offered_contract,
+            accept_params,
+            dlc_transactions,
+            contract_info,
+            i,
+        )?;
+
+        let (adaptor_info, adaptor_sig) = contract_info.get_adaptor_info(
+            secp,
+            total_collateral,
+            fund_secret_key,
+            &dlc_transactions.funding_script_pubkey,
+            fund_output_value,
+            &tmp_cets,
+            adaptor_sigs.len(),
+        )?;
+
+        cets.extend(tmp_cets);
+        adaptor_infos.push(adaptor_info);
+        adaptor_sigs.extend(adaptor_sig);
+    }
+
+    Ok((adaptor_infos, adaptor_sigs, cets))
+}
+
+/// Verifies the adaptor signatures of the accepting party for the CETs of all
+/// the contract information of the given offered contract, as done by the
+/// offering party. Returns the adaptor information generated in the process
+/// along with the CETs of all contract information.
+pub fn verify_cets(
+    secp: &Secp256k1<All>,
+    offered_contract: &OfferedContract,
+    accept_params: &PartyParams,
+    dlc_transactions: &DlcTransactions,
+    adaptor_signatures: &[EcdsaAdaptorSignature],
+) -> Result<(Vec<AdaptorInfo>, Vec<Transaction>), Error> {
+    let fund_output_value = dlc_transactions.get_fund_output().value;
+    let mut adaptor_infos = Vec::new();
+    let mut adaptor_index = 0;
+    let mut cets = Vec::new();
+
+    for (i, contract_info) in offered_contract.contract_info.iter().enumerate() {
+        let tmp_cets = get_contract_info_cets(
+            offered_contract,
+            accept_params,
+            dlc_transactions,
+            contract_info,
+            i,
+        )?;
+
+        let (adaptor_info, tmp_adaptor_index) = contract_info.verify_and_get_adaptor_info(
+            secp,
+            offered_contract.total_collateral,
+            &accept_params.fund_pubkey,
+            &dlc_transactions.funding_script_pubkey,
+            fund_output_value,
+            &tmp_cets,
+            adaptor_signatures,
+            adaptor_index,
+        )?;
+
+        adaptor_index = tmp_adaptor_index;
+        cets.extend(tmp_cets);
+        adaptor_infos.push(adaptor_info);
+    }
+
+    Ok((adaptor_infos, cets))
+}
+
+/// Generates the adaptor signatures of the CETs of a contract whose adaptor
+/// information is already known, as done by the offering party after having
+/// verified the signatures of the accepting party. The given transactions
+/// must contain the CETs of all contract information.
+pub fn sign_cets_with_adaptor_infos(
+    secp: &Secp256k1<All>,
+    contract_infos: &[ContractInfo],
+    adaptor_infos: &[AdaptorInfo],
+    dlc_transactions: &DlcTransactions,
+    fund_secret_key: &SecretKey,
+) -> Result<Vec<EcdsaAdaptorSignature>, Error> {
+    let fund_output_value = dlc_transactions.get_fund_output().value;
+    let mut adaptor_sigs = Vec::new();
+
+    for (contract_info, adaptor_info) in contract_infos.iter().zip(adaptor_infos.iter()) {
+        adaptor_sigs.extend(contract_info.get_adaptor_signatures(
+            secp,
+            adaptor_info,
+            fund_secret_key,
+            &dlc_transactions.funding_script_pubkey,
+            fund_output_value,
+            &dlc_transactions.cets,
+        )?);
+    }
+
+    Ok(adaptor_sigs)
+}
+
+/// Verifies the adaptor signatures of the offering party for the CETs of a
+/// contract whose adaptor information is already known, as done by the
+/// accepting party. The given transactions must contain the CETs of all
+/// contract information.
+pub fn verify_cets_with_adaptor_infos(
+    secp: &Secp256k1<All>,
+    contract_infos: &[ContractInfo],
+    adaptor_infos: &[AdaptorInfo],
+    dlc_transactions: &DlcTransactions,
+    fund_pubkey: &PublicKey,
+    adaptor_signatures: &[EcdsaAdaptorSignature],
+) -> Result<(), Error> {
+    let fund_output_value = dlc_transactions.get_fund_output().value;
+    let mut adaptor_sig_start = 0;
+
+    for (adaptor_info, contract_info) in adaptor_infos.iter().zip(contract_infos.iter()) {
+        adaptor_sig_start = contract_info.verify_adaptor_info(
+            secp,
+            fund_pubkey,
+            &dlc_transactions.funding_script_pubkey,
+            fund_output_value,
+            &dlc_transactions.cets,
+            adaptor_signatures,
+            adaptor_sig_start,
+            adaptor_info,
+        )?;
+    }
+
+    Ok(())
+}
+
+// The CETs of the first contract information are the ones created with the
+// fund transaction, the others need to be created.
+fn get_contract_info_cets(
+    offered_contract: &OfferedContract,
+    accept_params: &PartyParams,
+    dlc_transactions: &DlcTransactions,
+    contract_info: &ContractInfo,
+    index: usize,
+) -> Result<Vec<Transaction>, Error> {
+    if index == 0 {
+        return Ok(dlc_transactions.cets.clone());
+    }
+
+    let payouts = contract_info.get_payouts(offered_contract.total_collateral)?;
+
+    Ok(dlc::create_cets(
+        &dlc_transactions.cets[0].input[0],
+        &offered_contract.offer_params.payout_script_pubkey,
+        offered_contract.offer_params.payout_serial_id,
+        &accept_params.payout_script_pubkey,
+        accept_params.payout_serial_id,
+        &payouts,
+        0,
+    ))
+}
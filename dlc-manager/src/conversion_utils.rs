@@ -1,34 +1,41 @@
 use crate::contract::{
     accepted_contract::AcceptedContract,
     contract_info::ContractInfo,
+    contract_input::LOCKTIME_THRESHOLD,
     enum_descriptor::EnumDescriptor,
+    hybrid_descriptor::{HybridBranch, HybridDescriptor},
     numerical_descriptor::{DifferenceParams, NumericalDescriptor, NumericalEventInfo},
     offered_contract::OfferedContract,
     signed_contract::SignedContract,
     ContractDescriptor, FundingInputInfo,
 };
+use crate::manager::NB_CONFIRMATIONS;
 use crate::payout_curve::{
     HyperbolaPayoutCurvePiece, PayoutFunction, PayoutFunctionPiece, PayoutPoint,
     PolynomialPayoutCurvePiece, RoundingInterval, RoundingIntervals,
 };
+use crate::TimeSource;
 use bitcoin::{consensus::encode::Decodable, OutPoint, Transaction};
-use dlc::{EnumerationPayout, PartyParams, Payout, TxInputInfo};
+use dlc::{EnumerationPayout, FeeSplit, PartyParams, Payout, TxInputInfo};
 use dlc_messages::contract_msgs::{
-    ContractDescriptor as SerContractDescriptor, ContractInfo as SerContractInfo,
-    ContractInfoInner, ContractOutcome, DisjointContractInfo, EnumeratedContractDescriptor,
+    BoundedNumericOutcomeContractDescriptor, ContractDescriptor as SerContractDescriptor,
+    ContractInfo as SerContractInfo, ContractInfoInner, ContractOutcome, DisjointContractInfo,
+    EnumeratedContractDescriptor, HybridOutcomeBranch, HybridOutcomeContractDescriptor,
     HyperbolaPayoutCurvePiece as SerHyperbolaPayoutCurvePiece, NumericOutcomeContractDescriptor,
     PayoutCurvePiece as SerPayoutCurvePiece, PayoutFunction as SerPayoutFunction,
     PayoutFunctionPiece as SerPayoutFunctionPiece, PayoutPoint as SerPayoutPoint,
     PolynomialPayoutCurvePiece as SerPolynomialPayoutCurvePiece,
     RoundingInterval as SerRoundingInterval, RoundingIntervals as SerRoundingIntervals,
-    SingleContractInfo,
+    SingleContractInfo, SummedNumericOutcomeContractDescriptor,
 };
 use dlc_messages::oracle_msgs::{
     EventDescriptor, MultiOracleInfo, OracleInfo as SerOracleInfo, OracleParams, SingleOracleInfo,
 };
 use dlc_messages::{
     AcceptDlc, CetAdaptorSignature, CetAdaptorSignatures, FundingInput, OfferDlc, SignDlc,
+    EXACT_PAYOUTS_FLAG,
 };
+use dlc_trie::multi_oracle::CoverageStrategy;
 use secp256k1_zkp::PublicKey;
 use std::error;
 use std::fmt;
@@ -73,7 +80,15 @@ impl From<&OfferedContract> for OfferDlc {
     fn from(offered_contract: &OfferedContract) -> OfferDlc {
         OfferDlc {
             protocol_version: PROTOCOL_VERSION,
-            contract_flags: 0,
+            contract_flags: if offered_contract
+                .contract_info
+                .iter()
+                .any(|x| x.contract_descriptor.is_exact_evaluation())
+            {
+                EXACT_PAYOUTS_FLAG
+            } else {
+                0
+            },
             chain_hash: BITCOIN_CHAINHASH,
             contract_info: offered_contract.into(),
             funding_pubkey: offered_contract.offer_params.fund_pubkey,
@@ -91,6 +106,18 @@ impl From<&OfferedContract> for OfferDlc {
             contract_timeout: offered_contract.contract_timeout,
             fee_rate_per_vb: offered_contract.fee_rate_per_vb,
             fund_output_serial_id: offered_contract.fund_output_serial_id,
+            premium: offered_contract.premium.as_ref().map(|x| x.into()),
+            offer_fee_share: offered_contract
+                .fee_split
+                .as_ref()
+                .and_then(|x| x.get_offer_share().ok()),
+            nb_confirmations: if offered_contract.nb_confirmations == NB_CONFIRMATIONS {
+                None
+            } else {
+                Some(offered_contract.nb_confirmations)
+            },
+            funding_input_proofs: offered_contract.funding_input_proofs.clone(),
+            extra_tlvs: Vec::new(),
         }
     }
 }
@@ -124,7 +151,9 @@ pub fn get_tx_input_infos(
 }
 
 impl OfferedContract {
-    pub(crate) fn try_from_offer_dlc(
+    /// Creates an [`OfferedContract`] from a received offer message, checking
+    /// that its contract information is consistent.
+    pub fn try_from_offer_dlc(
         offer_dlc: &OfferDlc,
         counter_party: PublicKey,
     ) -> Result<OfferedContract, Error> {
@@ -133,8 +162,17 @@ impl OfferedContract {
         let (inputs, input_amount) = get_tx_input_infos(&offer_dlc.funding_inputs)?;
 
         Ok(OfferedContract {
-            id: offer_dlc.get_hash().unwrap(),
+            id: offer_dlc.get_temporary_contract_id().unwrap(),
             is_offer_party: false,
+            is_payout_external: false,
+            premium: offer_dlc.premium.as_ref().map(|x| x.into()),
+            renewal: None,
+            fee_split: offer_dlc
+                .offer_fee_share
+                .map(FeeSplit::from_offer_share)
+                .transpose()
+                .map_err(|_| Error::InvalidParameters)?,
+            nb_confirmations: offer_dlc.nb_confirmations.unwrap_or(NB_CONFIRMATIONS),
             contract_info,
             offer_params: PartyParams {
                 fund_pubkey: offer_dlc.funding_pubkey,
@@ -148,17 +186,26 @@ impl OfferedContract {
             },
             contract_maturity_bound: offer_dlc.contract_maturity_bound,
             contract_timeout: offer_dlc.contract_timeout,
+            // The refund locktime of received offers is evaluated as consensus
+            // does when it is a block height.
+            time_source: if offer_dlc.contract_timeout < LOCKTIME_THRESHOLD {
+                TimeSource::BlockHeight
+            } else {
+                TimeSource::default()
+            },
             fee_rate_per_vb: offer_dlc.fee_rate_per_vb,
             fund_output_serial_id: offer_dlc.fund_output_serial_id,
             funding_inputs_info: offer_dlc.funding_inputs.iter().map(|x| x.into()).collect(),
             total_collateral: offer_dlc.contract_info.get_total_collateral(),
             counter_party,
+            funding_input_proofs: offer_dlc.funding_input_proofs.clone(),
         })
     }
 }
 
 fn get_contract_info_and_announcements(offer_dlc: &OfferDlc) -> Result<Vec<ContractInfo>, Error> {
     let mut contract_infos = Vec::new();
+    let exact_evaluation = offer_dlc.contract_flags & EXACT_PAYOUTS_FLAG != 0;
     let (total_collateral, inner_contract_infos) = match &offer_dlc.contract_info {
         SerContractInfo::SingleContractInfo(single) => {
             (single.total_collateral, vec![single.contract_info.clone()])
@@ -195,7 +242,44 @@ fn get_contract_info_and_announcements(offer_dlc: &OfferDlc) -> Result<Vec<Contr
 
                 (descriptor, announcements, threshold)
             }
-            SerContractDescriptor::NumericOutcomeContractDescriptor(numeric) => {
+            SerContractDescriptor::NumericOutcomeContractDescriptor(_)
+            | SerContractDescriptor::SummedNumericOutcomeContractDescriptor(_)
+            | SerContractDescriptor::BoundedNumericOutcomeContractDescriptor(_) => {
+                let (payout_function, additional_payout_functions, rounding_intervals, max_value): (
+                    PayoutFunction,
+                    Vec<PayoutFunction>,
+                    RoundingIntervals,
+                    Option<u64>,
+                ) = match &contract_info.contract_descriptor {
+                    SerContractDescriptor::SummedNumericOutcomeContractDescriptor(summed) => {
+                        let mut payout_functions =
+                            summed.payout_functions.iter().map(PayoutFunction::from);
+                        (
+                            payout_functions.next().ok_or(Error::InvalidParameters)?,
+                            payout_functions.collect(),
+                            (&summed.rounding_intervals).into(),
+                            None,
+                        )
+                    }
+                    SerContractDescriptor::BoundedNumericOutcomeContractDescriptor(bounded) => {
+                        let mut payout_functions =
+                            bounded.payout_functions.iter().map(PayoutFunction::from);
+                        (
+                            payout_functions.next().ok_or(Error::InvalidParameters)?,
+                            payout_functions.collect(),
+                            (&bounded.rounding_intervals).into(),
+                            Some(bounded.max_value),
+                        )
+                    }
+                    SerContractDescriptor::NumericOutcomeContractDescriptor(numeric) => (
+                        (&numeric.payout_function).into(),
+                        Vec::new(),
+                        (&numeric.rounding_intervals).into(),
+                        None,
+                    ),
+                    SerContractDescriptor::EnumeratedContractDescriptor(_)
+                    | SerContractDescriptor::HybridOutcomeContractDescriptor(_) => unreachable!(),
+                };
                 let threshold;
                 let mut difference_params: Option<DifferenceParams> = None;
                 let announcements = match contract_info.oracle_info {
@@ -209,7 +293,7 @@ fn get_contract_info_and_announcements(offer_dlc: &OfferDlc) -> Result<Vec<Contr
                             difference_params = Some(DifferenceParams {
                                 max_error_exp: params.max_error_exp as usize,
                                 min_support_exp: params.min_fail_exp as usize,
-                                maximize_coverage: params.maximize_coverage,
+                                coverage_strategy: get_coverage_strategy(params.maximize_coverage),
                             })
                         }
                         multi.oracle_announcements.clone()
@@ -227,10 +311,60 @@ fn get_contract_info_and_announcements(offer_dlc: &OfferDlc) -> Result<Vec<Contr
                     },
                 };
                 let descriptor = ContractDescriptor::Numerical(NumericalDescriptor {
-                    payout_function: (&numeric.payout_function).into(),
-                    rounding_intervals: (&numeric.rounding_intervals).into(),
+                    payout_function,
+                    rounding_intervals,
                     info,
                     difference_params,
+                    additional_payout_functions,
+                    exact_evaluation,
+                    max_value,
+                    range_payouts_cache: Default::default(),
+                });
+                (descriptor, announcements, threshold)
+            }
+            SerContractDescriptor::HybridOutcomeContractDescriptor(hybrid) => {
+                let (announcements, threshold) = match contract_info.oracle_info {
+                    SerOracleInfo::Single(single) => (vec![single.oracle_announcement], 1),
+                    SerOracleInfo::Multi(multi) if multi.oracle_params.is_none() => {
+                        (multi.oracle_announcements, multi.threshold)
+                    }
+                    // Outcome differences between oracles are not supported
+                    // for hybrid contracts.
+                    SerOracleInfo::Multi(_) => return Err(Error::InvalidParameters),
+                };
+                let info = match announcements
+                    .get(0)
+                    .map(|x| &x.oracle_event.event_descriptor)
+                {
+                    Some(EventDescriptor::DigitDecompositionEvent(d)) => NumericalEventInfo {
+                        base: d.base as usize,
+                        nb_digits: d.nb_digits as usize,
+                        unit: d.unit.clone(),
+                    },
+                    _ => return Err(Error::InvalidParameters),
+                };
+                let selector_event_id = hybrid
+                    .selector_announcements
+                    .get(0)
+                    .ok_or(Error::InvalidParameters)?
+                    .oracle_event
+                    .event_id
+                    .clone();
+                let branches = hybrid
+                    .branches
+                    .iter()
+                    .map(|x| HybridBranch {
+                        outcome: x.outcome.clone(),
+                        payout_function: (&x.descriptor.payout_function).into(),
+                        rounding_intervals: (&x.descriptor.rounding_intervals).into(),
+                    })
+                    .collect();
+                let descriptor = ContractDescriptor::Hybrid(HybridDescriptor {
+                    selector_event_id,
+                    selector_announcements: hybrid.selector_announcements,
+                    branches,
+                    info,
+                    exact_evaluation,
                 });
                 (descriptor, announcements, threshold)
             }
@@ -289,7 +423,8 @@ impl From<&OfferedContract> for Vec<SerOracleInfo> {
                             oracle_params: Some(OracleParams {
                                 max_error_exp: params.max_error_exp as u16,
                                 min_fail_exp: params.min_support_exp as u16,
-                                maximize_coverage: params.maximize_coverage,
+                                maximize_coverage: params.coverage_strategy
+                                    == CoverageStrategy::MaximizeSupport,
                             }),
                         }));
                         continue;
@@ -331,15 +466,73 @@ impl From<&NumericalDescriptor> for NumericOutcomeContractDescriptor {
     }
 }
 
+impl From<&NumericalDescriptor> for SummedNumericOutcomeContractDescriptor {
+    fn from(num_descriptor: &NumericalDescriptor) -> SummedNumericOutcomeContractDescriptor {
+        SummedNumericOutcomeContractDescriptor {
+            num_digits: num_descriptor.info.nb_digits as u16,
+            payout_functions: std::iter::once(&num_descriptor.payout_function)
+                .chain(num_descriptor.additional_payout_functions.iter())
+                .map(|x| x.into())
+                .collect(),
+            rounding_intervals: (&num_descriptor.rounding_intervals).into(),
+        }
+    }
+}
+
+impl From<&NumericalDescriptor> for BoundedNumericOutcomeContractDescriptor {
+    fn from(num_descriptor: &NumericalDescriptor) -> BoundedNumericOutcomeContractDescriptor {
+        BoundedNumericOutcomeContractDescriptor {
+            num_digits: num_descriptor.info.nb_digits as u16,
+            max_value: num_descriptor
+                .max_value
+                .unwrap_or_else(|| num_descriptor.info.get_max_outcome()),
+            payout_functions: std::iter::once(&num_descriptor.payout_function)
+                .chain(num_descriptor.additional_payout_functions.iter())
+                .map(|x| x.into())
+                .collect(),
+            rounding_intervals: (&num_descriptor.rounding_intervals).into(),
+        }
+    }
+}
+
+impl From<&HybridDescriptor> for HybridOutcomeContractDescriptor {
+    fn from(hybrid_descriptor: &HybridDescriptor) -> HybridOutcomeContractDescriptor {
+        HybridOutcomeContractDescriptor {
+            selector_announcements: hybrid_descriptor.selector_announcements.clone(),
+            branches: hybrid_descriptor
+                .branches
+                .iter()
+                .map(|x| HybridOutcomeBranch {
+                    outcome: x.outcome.clone(),
+                    descriptor: NumericOutcomeContractDescriptor {
+                        num_digits: hybrid_descriptor.info.nb_digits as u16,
+                        payout_function: (&x.payout_function).into(),
+                        rounding_intervals: (&x.rounding_intervals).into(),
+                    },
+                })
+                .collect(),
+        }
+    }
+}
+
 impl From<&ContractDescriptor> for SerContractDescriptor {
     fn from(descriptor: &ContractDescriptor) -> SerContractDescriptor {
         match descriptor {
             ContractDescriptor::Enum(e) => {
                 SerContractDescriptor::EnumeratedContractDescriptor(e.into())
             }
-            ContractDescriptor::Numerical(n) => {
+            ContractDescriptor::Numerical(n) if n.max_value.is_some() => {
+                SerContractDescriptor::BoundedNumericOutcomeContractDescriptor(n.into())
+            }
+            ContractDescriptor::Numerical(n) if n.additional_payout_functions.is_empty() => {
                 SerContractDescriptor::NumericOutcomeContractDescriptor(n.into())
             }
+            ContractDescriptor::Numerical(n) => {
+                SerContractDescriptor::SummedNumericOutcomeContractDescriptor(n.into())
+            }
+            ContractDescriptor::Hybrid(h) => {
+                SerContractDescriptor::HybridOutcomeContractDescriptor(h.into())
+            }
         }
     }
 }
@@ -561,6 +754,8 @@ impl From<&AcceptedContract> for AcceptDlc {
             },
             refund_signature: contract.accept_refund_signature,
             negotiation_fields: None,
+            funding_input_proofs: contract.funding_input_proofs.clone(),
+            extra_tlvs: Vec::new(),
         }
     }
 }
@@ -582,6 +777,7 @@ impl From<&SignedContract> for SignDlc {
             },
             refund_signature: contract.offer_refund_signature,
             funding_signatures: contract.funding_signatures.clone(),
+            extra_tlvs: Vec::new(),
         }
     }
 }
@@ -597,6 +793,7 @@ impl From<&FundingInput> for FundingInputInfo {
         FundingInputInfo {
             funding_input: input.clone(),
             address: None,
+            witness_script: None,
         }
     }
 }
@@ -606,7 +803,7 @@ impl From<&DifferenceParams> for OracleParams {
         OracleParams {
             max_error_exp: input.max_error_exp as u16,
             min_fail_exp: input.min_support_exp as u16,
-            maximize_coverage: input.maximize_coverage,
+            maximize_coverage: input.coverage_strategy == CoverageStrategy::MaximizeSupport,
         }
     }
 }
@@ -616,11 +813,21 @@ impl From<&OracleParams> for DifferenceParams {
         DifferenceParams {
             max_error_exp: input.max_error_exp as usize,
             min_support_exp: input.min_fail_exp as usize,
-            maximize_coverage: input.maximize_coverage,
+            coverage_strategy: get_coverage_strategy(input.maximize_coverage),
         }
     }
 }
 
+/// Offer messages only indicate whether the coverage is maximized, so custom
+/// coverage strategies cannot be exchanged with the counter party.
+fn get_coverage_strategy(maximize_coverage: bool) -> CoverageStrategy {
+    if maximize_coverage {
+        CoverageStrategy::MaximizeSupport
+    } else {
+        CoverageStrategy::MinimizeCetCount
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -677,4 +884,21 @@ mod tests {
         let res: PayoutFunction = (&ser_payout_function).into();
         assert_eq!(payout_function, res);
     }
+
+    #[test]
+    fn bounded_numerical_descriptor_conversion() {
+        let descriptor = crate::contract::descriptor_parser::parse_contract_descriptor(
+            "numerical base 2 digits 64 max 1000\nfrom 0 pay 0\nlinear to max pay 100",
+            100,
+        )
+        .unwrap();
+        match SerContractDescriptor::from(&descriptor) {
+            SerContractDescriptor::BoundedNumericOutcomeContractDescriptor(bounded) => {
+                assert_eq!(64, bounded.num_digits);
+                assert_eq!(1000, bounded.max_value);
+                assert_eq!(1, bounded.payout_functions.len());
+            }
+            _ => panic!("Expected a bounded numerical descriptor"),
+        }
+    }
 }
@@ -23,6 +23,14 @@ pub enum Error {
     OracleError(String),
     /// An error occurred in the DLC library.
     DlcError(dlc::Error),
+    /// A message, address or input is for a different network than the one
+    /// of the blockchain component.
+    NetworkMismatch {
+        /// The network of the blockchain component.
+        expected: bitcoin::network::constants::Network,
+        /// The network that was encountered.
+        actual: bitcoin::network::constants::Network,
+    },
 }
 
 impl fmt::Display for Error {
@@ -37,6 +45,14 @@ impl fmt::Display for Error {
             Error::StorageError(ref s) => write!(f, "Storage error {}", s),
             Error::DlcError(ref e) => write!(f, "Dlc error {}", e),
             Error::OracleError(ref s) => write!(f, "Oracle error {}", s),
+            Error::NetworkMismatch {
+                ref expected,
+                ref actual,
+            } => write!(
+                f,
+                "Network mismatch, expected {} but got {}",
+                expected, actual
+            ),
         }
     }
 }
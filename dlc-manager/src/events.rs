@@ -0,0 +1,59 @@
+//! #Events emitted by the Manager that callers may need to act upon.
+
+use crate::{ContractId, TemporaryContractId};
+use dlc_messages::oracle_msgs::OracleEquivocationProof;
+
+/// Events emitted by the [`crate::manager::Manager`] during its periodic
+/// checks or when processing messages.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// The refund locktime of a contract passed without the oracles having
+    /// attested to its outcome, so the refund transaction was broadcast. This
+    /// usually means that an oracle disappeared, which users may need to be
+    /// made aware of.
+    ContractRefundedDueToNoAttestation {
+        /// The id of the refunded contract.
+        contract_id: ContractId,
+    },
+    /// Attestations signing different outcomes of an event using the same
+    /// nonce were received for an oracle, revealing its private key. The
+    /// proof can be published and the oracle should no longer be trusted.
+    OracleEquivocation {
+        /// The proof that the oracle equivocated.
+        proof: OracleEquivocationProof,
+    },
+    /// The fund transaction of a contract considered confirmed with zero-conf
+    /// funding is neither in the blockchain nor in the mempool anymore, e.g.
+    /// because the counter party double spent one of its inputs, so the
+    /// contract was moved back to the signed state.
+    ZeroConfFundingReverted {
+        /// The id of the contract.
+        contract_id: ContractId,
+    },
+    /// A signed or confirmed contract will mature within the window set with
+    /// [`crate::manager::Manager::set_maturity_notification_window`], giving
+    /// a chance to fetch the attestations it will need in advance. Reported
+    /// once per contract and manager instance.
+    MaturityApproaching {
+        /// The id of the contract.
+        contract_id: ContractId,
+        /// The maturity time of the contract.
+        maturity_time: u32,
+    },
+    /// An offer spending some of the inputs of a pending offer from the same
+    /// counter party was received, without only replacing an oracle
+    /// announcement of it. The offer was stored in addition to the pending
+    /// one, at most one of which can be funded.
+    ConflictingOfferReceived {
+        /// The temporary id of the received offer.
+        temporary_id: TemporaryContractId,
+        /// The temporary id of the pending offer.
+        conflicting_temporary_id: TemporaryContractId,
+    },
+}
+
+/// Receives the events emitted by the manager.
+pub trait EventHandler {
+    /// Called when the given event occurs.
+    fn handle_event(&self, event: Event);
+}
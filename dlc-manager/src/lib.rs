@@ -23,23 +23,37 @@ extern crate log;
 #[cfg(feature = "fuzztarget")]
 extern crate rand_chacha;
 extern crate secp256k1_zkp;
+#[cfg(feature = "tracing")]
+extern crate tracing;
 
+pub mod acceptance_policy;
+pub mod attestation_provider;
+pub mod broadcast;
 pub mod contract;
+pub mod contract_tool;
 mod conversion_utils;
 pub mod error;
+pub mod events;
 pub mod manager;
+pub mod metrics;
 pub mod payout_curve;
+pub mod reporting;
+pub mod test_vectors;
 mod utils;
 
+use bitcoin::util::psbt::PartiallySignedTransaction;
 use bitcoin::{Address, OutPoint, Script, Transaction, TxOut, Txid};
-use contract::{offered_contract::OfferedContract, signed_contract::SignedContract, Contract};
+use broadcast::{BroadcastKind, PendingBroadcast};
+use contract::{
+    offered_contract::OfferedContract, signed_contract::SignedContract, AdaptorInfo,
+    ArchivedContract, Contract, ContractHistoryEntry, ContractState,
+};
 use dlc_messages::oracle_msgs::{OracleAnnouncement, OracleAttestation};
+pub use dlc_messages::{ContractId, TemporaryContractId};
 use error::Error;
 use secp256k1_zkp::schnorrsig::PublicKey as SchnorrPublicKey;
 use secp256k1_zkp::{PublicKey, SecretKey};
-
-/// Type alias for a contract id.
-pub type ContractId = [u8; 32];
+use std::sync::Arc;
 
 /// Time trait to provide current unix time. Mainly defined to facilitate testing.
 pub trait Time {
@@ -59,6 +73,33 @@ impl Time for SystemTimeProvider {
     }
 }
 
+/// The source of time against which the maturity of the events of a contract
+/// and the locktime of its refund transaction are evaluated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub enum TimeSource {
+    /// The time provided by the [`Time`] implementation of the manager.
+    WallClock,
+    /// The median time past of the tip of the blockchain, against which
+    /// consensus evaluates time based locktimes. It is not affected by the
+    /// skew of the local clock but lags about an hour behind it.
+    MedianTimePast,
+    /// The height of the tip of the blockchain, the locktime of the refund
+    /// transaction being a block height. As oracle events mature at a unix
+    /// time, their maturity is evaluated against the median time past.
+    BlockHeight,
+}
+
+impl Default for TimeSource {
+    fn default() -> Self {
+        TimeSource::WallClock
+    }
+}
+
 /// Wallet trait to provide functionalities related to generating, storing and
 /// managing bitcoin addresses and UTXOs.
 pub trait Wallet {
@@ -70,13 +111,16 @@ pub trait Wallet {
     /// Get the secret key associated with the provided public key.
     fn get_secret_key_for_pubkey(&self, pubkey: &PublicKey) -> Result<SecretKey, Error>;
 
-    /// Signs a transaction input
-    fn sign_tx_input(
+    /// Signs and finalizes the input at the given index of the provided PSBT,
+    /// setting its `final_script_witness` (and `final_script_sig` for P2SH
+    /// wrapped inputs). The PSBT input contains the spent output as well as its
+    /// redeem and witness scripts when available, so that inputs locked by
+    /// scripts other than a single key (e.g. multisig or timelocked scripts)
+    /// can be finalized by an external signer.
+    fn sign_psbt_input(
         &self,
-        tx: &mut Transaction,
+        psbt: &mut PartiallySignedTransaction,
         input_index: usize,
-        tx_out: &TxOut,
-        redeem_script: Option<Script>,
     ) -> Result<(), Error>;
 
     /// Get a set of UTXOs to fund the given amount.
@@ -92,6 +136,12 @@ pub trait Wallet {
     fn get_transaction(&self, tx_id: &Txid) -> Result<Transaction, Error>;
     /// Get the number of confirmation for the transaction with given id.
     fn get_transaction_confirmations(&self, tx_id: &Txid) -> Result<u32, Error>;
+    /// Unlocks the given UTXOs, locked when returned by
+    /// [`Wallet::get_utxos_for_amount`], so that they can be selected again.
+    /// The default implementation does nothing.
+    fn unlock_utxos(&self, _outpoints: &[OutPoint]) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 /// Blockchain trait provides access to the bitcoin blockchain.
@@ -100,18 +150,87 @@ pub trait Blockchain {
     fn send_transaction(&self, transaction: &Transaction) -> Result<(), Error>;
     /// Returns the network currently used (mainnet, testnet or regtest).
     fn get_network(&self) -> Result<bitcoin::network::constants::Network, Error>;
+    /// Returns the height of the current tip of the blockchain.
+    fn get_block_height(&self) -> Result<u64, Error>;
+    /// Returns the median time past of the current tip of the blockchain,
+    /// that is the median of the timestamps of its last eleven blocks.
+    fn get_median_time_past(&self) -> Result<u64, Error>;
+    /// Returns whether the transaction with the given id is currently in the
+    /// mempool.
+    fn is_in_mempool(&self, txid: &Txid) -> Result<bool, Error>;
+    /// Returns the height of the block in which the transaction with the
+    /// given id was included, or `None` if it is not included in the
+    /// blockchain.
+    fn get_tx_block_height(&self, txid: &Txid) -> Result<Option<u64>, Error>;
+    /// Returns the transaction spending the given outpoint if any, looking in
+    /// both the mempool and the blockchain.
+    fn get_spending_transaction(&self, outpoint: &OutPoint) -> Result<Option<Transaction>, Error>;
 }
 
 /// Storage trait provides functionalities to store and retrieve DLCs.
 pub trait Storage {
-    /// Returns the contract with given id if found.
+    /// Returns the contract with given id if found. Contracts that were not
+    /// accepted yet are only identified by their temporary id and must be
+    /// retrieved using [`Storage::get_contract_by_temporary_id`].
     fn get_contract(&self, id: &ContractId) -> Result<Option<Contract>, Error>;
+    /// Returns the contract with the given temporary id if found and if it was
+    /// not accepted yet (i.e. it is in offered or failed accept state).
+    fn get_contract_by_temporary_id(
+        &self,
+        temporary_id: &TemporaryContractId,
+    ) -> Result<Option<Contract>, Error>;
     /// Return all contracts
     fn get_contracts(&self) -> Result<Vec<Contract>, Error>;
+    /// Returns at most `limit` contracts in the given state, skipping the
+    /// first `offset` ones, in the order in which [`Storage::get_contracts`]
+    /// returns them. The default implementation loads all the contracts and
+    /// should be overridden by implementations that can filter them more
+    /// efficiently.
+    fn get_contracts_by_state(
+        &self,
+        state: ContractState,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<Contract>, Error> {
+        Ok(self
+            .get_contracts()?
+            .into_iter()
+            .filter(|x| x.get_state() == state)
+            .skip(offset)
+            .take(limit)
+            .collect())
+    }
+    /// Returns the number of contracts in the given state. The default
+    /// implementation loads all the contracts.
+    fn count_contracts_by_state(&self, state: ContractState) -> Result<usize, Error> {
+        Ok(self
+            .get_contracts()?
+            .iter()
+            .filter(|x| x.get_state() == state)
+            .count())
+    }
+    /// Returns the contracts entered into with the given counter party, in any
+    /// state. The default implementation loads all the contracts.
+    fn get_contracts_by_counterparty(
+        &self,
+        counter_party: &PublicKey,
+    ) -> Result<Vec<Contract>, Error> {
+        Ok(self
+            .get_contracts()?
+            .into_iter()
+            .filter(|x| x.get_offered_contract().counter_party == *counter_party)
+            .collect())
+    }
     /// Create a record for the given contract.
     fn create_contract(&mut self, contract: &OfferedContract) -> Result<(), Error>;
     /// Delete the record for the contract with the given id.
     fn delete_contract(&mut self, id: &ContractId) -> Result<(), Error>;
+    /// Delete the record for the contract with the given temporary id, if it
+    /// was not accepted yet.
+    fn delete_contract_by_temporary_id(
+        &mut self,
+        temporary_id: &TemporaryContractId,
+    ) -> Result<(), Error>;
     /// Update the given contract.
     fn update_contract(&mut self, contract: &Contract) -> Result<(), Error>;
     /// Returns the set of contracts in offered state.
@@ -120,6 +239,69 @@ pub trait Storage {
     fn get_signed_contracts(&self) -> Result<Vec<SignedContract>, Error>;
     /// Returns the set of confirmed contracts.
     fn get_confirmed_contracts(&self) -> Result<Vec<SignedContract>, Error>;
+    /// Append an entry to the history of the contract with the given temporary
+    /// id.
+    fn add_contract_history_entry(
+        &mut self,
+        temporary_id: &TemporaryContractId,
+        entry: &ContractHistoryEntry,
+    ) -> Result<(), Error>;
+    /// Returns the history of the contract with the given temporary id, ordered
+    /// from the oldest to the most recent entry.
+    fn get_contract_history(
+        &self,
+        temporary_id: &TemporaryContractId,
+    ) -> Result<Vec<ContractHistoryEntry>, Error>;
+    /// Replaces the closed or refunded contract with the given id by its
+    /// [`ArchivedContract`] record, after which the contract is no longer
+    /// returned by the other methods of the storage. Its history is kept. The
+    /// default implementation returns an error for storages that do not
+    /// support archival.
+    fn archive_contract(&mut self, _id: &ContractId) -> Result<(), Error> {
+        Err(Error::StorageError(
+            "Contract archival is not supported".to_string(),
+        ))
+    }
+    /// Returns the adaptor information of the contract with the given id, one
+    /// entry per contract information. Storages can keep it separately from
+    /// the contracts, in which case they return contracts whose accepted
+    /// contract has empty `adaptor_infos` so that listing them does not
+    /// require loading it. The default implementation retrieves it from the
+    /// stored contract.
+    fn get_adaptor_info(&self, id: &ContractId) -> Result<Option<Vec<AdaptorInfo>>, Error> {
+        Ok(self
+            .get_contract(id)?
+            .and_then(|c| c.get_accepted_contract().map(|a| a.adaptor_infos.clone())))
+    }
+    /// Returns the archival records of the archived contracts. The default
+    /// implementation returns an empty list.
+    fn get_archived_contracts(&self) -> Result<Vec<ArchivedContract>, Error> {
+        Ok(Vec::new())
+    }
+    /// Inserts the given transaction in the pending broadcast queue, replacing
+    /// any entry for the same contract and kind of transaction. The default
+    /// implementation returns an error for storages that do not support the
+    /// queue.
+    fn save_pending_broadcast(&mut self, _pending: &PendingBroadcast) -> Result<(), Error> {
+        Err(Error::StorageError(
+            "Pending broadcast queue is not supported".to_string(),
+        ))
+    }
+    /// Removes the entry for the given contract and kind of transaction from
+    /// the pending broadcast queue, if any. The default implementation does
+    /// nothing.
+    fn delete_pending_broadcast(
+        &mut self,
+        _contract_id: &ContractId,
+        _kind: BroadcastKind,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+    /// Returns the entries of the pending broadcast queue. The default
+    /// implementation returns an empty list.
+    fn get_pending_broadcasts(&self) -> Result<Vec<PendingBroadcast>, Error> {
+        Ok(Vec::new())
+    }
 }
 
 /// Oracle trait provides access to oracle information.
@@ -132,6 +314,16 @@ pub trait Oracle {
     fn get_attestation(&self, event_id: &str) -> Result<OracleAttestation, Error>;
 }
 
+/// Resolves oracles from their public key when they are needed, so that
+/// contracts using oracles that were not given to the
+/// [`manager::Manager`] when it was created can still be offered, accepted
+/// and closed.
+pub trait OracleResolver {
+    /// Returns the oracle with the given public key, or `None` if it is
+    /// unknown.
+    fn resolve(&self, public_key: &SchnorrPublicKey) -> Option<Arc<dyn Oracle + Send + Sync>>;
+}
+
 /// Represents a UTXO.
 #[derive(Clone, Debug)]
 pub struct Utxo {
@@ -143,4 +335,10 @@ pub struct Utxo {
     pub address: Address,
     /// The redeem script for the referenced output.
     pub redeem_script: Script,
+    /// The witness script for the referenced output if it is a P2WSH (or P2SH
+    /// wrapped P2WSH) output.
+    pub witness_script: Option<Script>,
+    /// The maximum length of the witness required to spend the referenced
+    /// output. If not set, the output is assumed to be P2WPKH.
+    pub max_witness_len: Option<usize>,
 }
@@ -1,40 +1,223 @@
 //! #Manager a component to create and update DLCs.
 
-use super::{Blockchain, Oracle, Storage, Time, Wallet};
+use super::{Blockchain, Oracle, OracleResolver, Storage, Time, TimeSource, Utxo, Wallet};
+use crate::acceptance_policy::{AcceptancePolicy, PolicyViolation, PolicyViolationAction};
+use crate::attestation_provider::{AttestationProvider, AttestationSource};
+use crate::broadcast::{
+    get_retry_delay, BroadcastDecision, BroadcastInterceptor, BroadcastKind, PendingBroadcast,
+    MAX_BROADCAST_ATTEMPTS,
+};
 use crate::contract::{
-    accepted_contract::AcceptedContract, contract_info::ContractInfo,
+    accepted_contract::AcceptedContract, accepted_contract::PendingAccept,
+    contract_info::ContractInfo, contract_info::RangePayoutCet, contract_input::ChangePolicy,
     contract_input::ContractInput, contract_input::ContractInputInfo, contract_input::OracleInput,
-    offered_contract::OfferedContract, signed_contract::SignedContract, AdaptorInfo,
-    ClosedContract, Contract, FailedAcceptContract, FailedSignContract, FundingInputInfo,
+    contract_input::LOCKTIME_THRESHOLD, offered_contract::OfferedContract,
+    offered_contract::RenewalInfo, signed_contract::SignedContract, signed_contract::SignedFunding,
+    AdaptorInfo, ArchivedContract, ClosedContract, ClosedContractReport, Contract,
+    ContractDescriptor, ContractHistoryEntry, ContractState, FailedAcceptContract,
+    FailedSignContract, FundingInputInfo,
 };
+use crate::contract_tool;
 use crate::conversion_utils::get_tx_input_infos;
 use crate::error::Error;
-use crate::utils::get_new_serial_id;
-use crate::ContractId;
+use crate::events::{Event, EventHandler};
+use crate::metrics::{MessageDirection, Metrics, Operation};
+use crate::utils::{
+    get_chain_hash, get_common_fee_share, get_contract_id_string, get_new_serial_id,
+    validate_address_network, validate_chain_hash, validate_payout_script_pubkey, validate_premium,
+};
+use crate::{ContractId, TemporaryContractId};
 use bitcoin::{
+    blockdata::opcodes,
     consensus::{Decodable, Encodable},
-    Address, Transaction,
+    hashes::{sha256, Hash},
+    util::psbt::PartiallySignedTransaction,
+    Address, OutPoint, Script, SigHashType, Transaction, TxOut, Txid,
 };
-use dlc::{DlcTransactions, PartyParams, TxInputInfo};
-use dlc_messages::oracle_msgs::{OracleAnnouncement, OracleAttestation};
+use dlc::{DlcTransactions, PartyParams, PremiumInfo, TxInputInfo};
+use dlc_messages::oracle_msgs::{check_event_id, OracleAnnouncement, OracleAttestation};
 use dlc_messages::{
-    AcceptDlc, FundingInput, FundingSignature, FundingSignatures, Message as DlcMessage, OfferDlc,
-    SignDlc, WitnessElement,
+    AcceptDlc, FundingInput, FundingInputProof, FundingInputProofs, FundingSignature,
+    FundingSignatures, Message as DlcMessage, OfferDlc, ReestablishDlc, ReestablishState,
+    RenewOffer, SignDlc, WitnessElement,
 };
-use log::{error, warn};
+use dlc_trie::combination_iterator::CombinationIterator;
+use dlc_trie::RangeInfo;
+use lightning::ln::wire::Type;
+use lightning::util::ser::Writeable;
+use log::{error, info, warn};
 use secp256k1_zkp::schnorrsig::{PublicKey as SchnorrPublicKey, Signature as SchnorrSignature};
-use secp256k1_zkp::EcdsaAdaptorSignature;
-use secp256k1_zkp::{All, PublicKey, Secp256k1, SecretKey};
-use std::collections::HashMap;
+use secp256k1_zkp::{All, PublicKey, Secp256k1, SecretKey, Signature};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::ops::{Deref, DerefMut};
 use std::string::ToString;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
 
 /// The number of confirmations required before moving the the confirmed state.
 pub const NB_CONFIRMATIONS: u32 = 6;
+/// The default maximum number of confirmations that can be required by a
+/// received offer.
+pub const MAX_NB_CONFIRMATIONS: u32 = 144;
+/// The number of contracts retrieved at once from the storage when going
+/// through all the contracts in a given state.
+const CONTRACT_PAGE_SIZE: usize = 100;
 /// The delay to set the refund value to.
 pub const REFUND_DELAY: u32 = 86400 * 7;
+/// The maximum witness length of a 2-of-2 multisig fund output input, used
+/// when renewing a contract.
+const MULTISIG_WITNESS_LEN: u16 = 220;
+/// The state recorded in the history of a received offer when it is approved.
+const APPROVED_STATE: &str = "Approved";
+/// The default estimate of the time taken to create and verify an adaptor
+/// signature, used to estimate the computation time of accepting an offer.
+pub const DEFAULT_ADAPTOR_SIGNATURE_DURATION: Duration = Duration::from_micros(200);
+/// The length of a P2WPKH script pubkey, assumed for the payout and change
+/// outputs when previewing the acceptance of an offer.
+const P2WPKH_SCRIPT_PUBKEY_LEN: usize = 22;
+
+/// Information required to co-sign the input spending the fund output of a
+/// renewed contract.
+struct RenewedFundInfo {
+    outpoint: OutPoint,
+    funding_script_pubkey: Script,
+    fund_output_value: u64,
+    fund_secret_key: SecretKey,
+    other_fund_pubkey: PublicKey,
+}
+
+/// The status of a transaction as seen by the blockchain component.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TxStatus {
+    /// The transaction is neither in the mempool nor in the blockchain, either
+    /// because it was never broadcast or because it was evicted.
+    NotFound,
+    /// The transaction was broadcast but is not yet confirmed.
+    InMempool,
+    /// The transaction is included in the blockchain with the given number of
+    /// confirmations.
+    Confirmed(u32),
+}
+
+/// Policy used to pick the CET to broadcast when closing a contract and the
+/// available attestations make several CETs usable, for example when more
+/// oracles than the threshold attested to different outcomes or when the
+/// outcomes attested by the oracles fall in different rounding intervals.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClosePolicy {
+    /// Use the CET paying the largest amount to the local party, preferring
+    /// the one with the lowest adaptor signature index in case of tie.
+    MaximizeOwnPayout,
+    /// Use the CET corresponding to the outcome attested by the largest
+    /// number of oracles. This is the default.
+    HonorExactOutcome,
+    /// Use the usable CET whose adaptor signature has the lowest index.
+    EarliestAdaptorIndex,
+}
+
+impl Default for ClosePolicy {
+    fn default() -> Self {
+        ClosePolicy::HonorExactOutcome
+    }
+}
+
+/// Limits on the contracts that can be entered into with a counter party.
+/// Open contracts are the ones offered by the local party and not yet
+/// accepted, as well as the accepted, signed and confirmed ones, received
+/// offers only being taken into account when they are accepted.
+///
+/// The policy also bounds the resources that a counter party can make the
+/// local node use, by limiting the size of the messages it sends, the number
+/// of its offers that are kept until accepted or rejected, and the number of
+/// adaptor signatures that accepting one of its offers requires to create and
+/// verify. Offers exceeding the latter are stored but can only be accepted
+/// after being approved with [`Manager::approve_offer`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PeerPolicy {
+    /// The maximum number of open contracts with the counter party.
+    pub max_open_contracts: Option<usize>,
+    /// The maximum sum of the collaterals of the local party in open contracts
+    /// with the counter party.
+    pub max_collateral_at_risk: Option<u64>,
+    /// The maximum number of offers received from the counter party that are
+    /// neither accepted nor rejected.
+    pub max_pending_offers: Option<usize>,
+    /// The maximum size in bytes of the encoding of the messages received
+    /// from the counter party, enforced by [`Manager::decode_dlc_message`]
+    /// before they are parsed.
+    pub max_message_size: Option<usize>,
+    /// The maximum number of adaptor signatures, as estimated by
+    /// [`ContractInfo::get_max_nb_adaptor_signatures`], that an offer from
+    /// the counter party can require without being approved.
+    pub max_adaptor_signatures: Option<usize>,
+    /// Whether contracts with the counter party can be considered confirmed
+    /// as soon as their fund transaction is broadcast (zero-conf funding).
+    /// Until the fund transaction confirms, the counter party can invalidate
+    /// such contracts by double spending its funding inputs, so this should
+    /// only be allowed for trusted counter parties and low value contracts.
+    pub allow_zero_conf: bool,
+}
+
+/// Constraints on the funding inputs provided by counter parties in offer and
+/// accept messages, so that the fund transaction of a contract cannot be
+/// stalled by inputs that are unlikely to confirm or that could make its id
+/// change, which would invalidate the pre-signed CETs and refund transaction.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FundingInputPolicy {
+    /// The maximum number of funding inputs of the counter party.
+    pub max_inputs: Option<usize>,
+    /// The minimum number of confirmations of the transactions whose outputs
+    /// are spent by the funding inputs of the counter party.
+    pub min_confirmations: Option<u32>,
+    /// Whether the funding inputs of the counter party must spend standard
+    /// segwit outputs (P2WPKH, P2WSH, P2TR, or P2SH wrapping a witness
+    /// program).
+    pub standard_only: bool,
+    /// Whether the funding inputs of the counter party must come with a proof
+    /// that it controls the outputs they spend (see
+    /// [`dlc::ownership_proof`]). As proofs can only be verified for P2WPKH
+    /// outputs, native or P2SH wrapped, other inputs are then rejected. The
+    /// input spending the fund output of a renewed contract is exempted.
+    pub require_ownership_proofs: bool,
+}
+
+/// The requirements of accepting a received offer, computed by
+/// [`Manager::preview_accept`] so that they can be shown to the user before
+/// accepting it.
+#[derive(Clone, Debug)]
+pub struct AcceptPreview {
+    /// The collateral to put in the contract.
+    pub own_collateral: u64,
+    /// The total collateral of the contract.
+    pub total_collateral: u64,
+    /// The UTXOs of the wallet that would be used to fund the contract. They
+    /// are not reserved, so accepting the offer may select different ones.
+    pub utxos: Vec<Utxo>,
+    /// The amount funding the contract, including the payout of the renewed
+    /// contract for renewal offers.
+    pub input_amount: u64,
+    /// The premium to pay to the counter party.
+    pub premium: u64,
+    /// The share of the fee of the fund transaction to pay, assuming P2WPKH
+    /// payout and change outputs.
+    pub fund_tx_fee: u64,
+    /// The share of the fee of the CET or refund transaction to pay.
+    pub cet_fee: u64,
+    /// An upper bound on the number of adaptor signatures to produce (see
+    /// [`ContractInfo::get_max_nb_adaptor_signatures`]).
+    pub max_nb_adaptor_signatures: usize,
+    /// The estimated time required to produce the adaptor signatures and to
+    /// verify those of the counter party.
+    pub estimated_computation_time: Duration,
+}
 
-/// Used to create and update DLCs.
+/// Used to create and update DLCs. All the methods used to process messages,
+/// create, accept or check contracts take a shared reference so that a single
+/// manager can be used from multiple threads (e.g. wrapped in an
+/// [`std::sync::Arc`]). Operations on a given contract are serialized using a
+/// lock specific to that contract, while the store is only locked for the
+/// duration of each access.
 pub struct Manager<W: Deref, B: Deref, S: DerefMut, O: Deref, T: Deref>
 where
     W::Target: Wallet,
@@ -44,11 +227,34 @@ where
     T::Target: Time,
 {
     oracles: HashMap<SchnorrPublicKey, O>,
+    oracle_resolver: Option<Box<dyn OracleResolver + Send + Sync>>,
     wallet: W,
     blockchain: B,
-    store: S,
-    secp: Secp256k1<All>,
+    store: Mutex<S>,
+    contract_locks: Mutex<HashMap<[u8; 32], Arc<Mutex<()>>>>,
+    peer_locks: Mutex<HashMap<PublicKey, Arc<Mutex<()>>>>,
+    pending_accepts: Mutex<HashMap<[u8; 32], Vec<OutPoint>>>,
+    secp: Cow<'static, Secp256k1<All>>,
     time: T,
+    metrics: Option<Box<dyn Metrics + Send + Sync>>,
+    event_handler: Option<Box<dyn EventHandler + Send + Sync>>,
+    nb_confirmations_bounds: (u32, u32),
+    close_policy: ClosePolicy,
+    default_peer_policy: PeerPolicy,
+    peer_policies: HashMap<PublicKey, PeerPolicy>,
+    acceptance_policy: Option<AcceptancePolicy>,
+    funding_input_policy: FundingInputPolicy,
+    attestation_sources: Vec<AttestationSource>,
+    provided_attestations: Mutex<HashMap<(SchnorrPublicKey, String), OracleAttestation>>,
+    maturity_notification_window: Option<u64>,
+    notified_maturities: Mutex<HashSet<ContractId>>,
+    /// Contracts whose closing transaction reached `NB_CONFIRMATIONS` and
+    /// does not need to be watched anymore.
+    settled_contracts: Mutex<HashSet<ContractId>>,
+    attestation_delays: HashMap<SchnorrPublicKey, u64>,
+    broadcast_fund_tx: bool,
+    broadcast_interceptor: Option<Box<dyn BroadcastInterceptor + Send + Sync>>,
+    adaptor_signature_duration: Duration,
 }
 
 impl<W: Deref, B: Deref, S: DerefMut, O: Deref, T: Deref> Manager<W, B, S, O, T>
@@ -67,317 +273,2552 @@ where
         oracles: HashMap<SchnorrPublicKey, O>,
         time: T,
     ) -> Self {
-        Manager {
-            secp: secp256k1_zkp::Secp256k1::new(),
+        Self::new_with_secp(
+            wallet,
+            blockchain,
+            store,
+            oracles,
+            time,
+            Cow::Owned(Secp256k1::new()),
+        )
+    }
+
+    /// Create a new Manager struct using the global secp256k1 context instead
+    /// of creating its own.
+    #[cfg(feature = "global-context")]
+    pub fn new_with_global_context(
+        wallet: W,
+        blockchain: B,
+        store: S,
+        oracles: HashMap<SchnorrPublicKey, O>,
+        time: T,
+    ) -> Self {
+        Self::new_with_secp(
             wallet,
             blockchain,
             store,
             oracles,
             time,
+            Cow::Borrowed(secp256k1_zkp::SECP256K1),
+        )
+    }
+
+    /// Create a new Manager struct using the given secp256k1 context for
+    /// signing and verification, so that a context can be shared between
+    /// managers and other components rather than created for each of them.
+    pub fn new_with_secp(
+        wallet: W,
+        blockchain: B,
+        store: S,
+        oracles: HashMap<SchnorrPublicKey, O>,
+        time: T,
+        secp: Cow<'static, Secp256k1<All>>,
+    ) -> Self {
+        Manager {
+            secp,
+            wallet,
+            blockchain,
+            store: Mutex::new(store),
+            contract_locks: Mutex::new(HashMap::new()),
+            peer_locks: Mutex::new(HashMap::new()),
+            pending_accepts: Mutex::new(HashMap::new()),
+            oracles,
+            oracle_resolver: None,
+            time,
+            metrics: None,
+            event_handler: None,
+            nb_confirmations_bounds: (1, MAX_NB_CONFIRMATIONS),
+            close_policy: ClosePolicy::default(),
+            default_peer_policy: PeerPolicy::default(),
+            peer_policies: HashMap::new(),
+            acceptance_policy: None,
+            funding_input_policy: FundingInputPolicy::default(),
+            attestation_sources: Vec::new(),
+            provided_attestations: Mutex::new(HashMap::new()),
+            maturity_notification_window: None,
+            notified_maturities: Mutex::new(HashSet::new()),
+            settled_contracts: Mutex::new(HashSet::new()),
+            attestation_delays: HashMap::new(),
+            broadcast_fund_tx: true,
+            broadcast_interceptor: None,
+            adaptor_signature_duration: DEFAULT_ADAPTOR_SIGNATURE_DURATION,
         }
     }
 
-    /// Get the store from the Manager to access contracts.
-    pub fn get_store(&self) -> &S {
-        &self.store
+    /// Returns the secp256k1 context used by the manager, which can be reused
+    /// for example when signing CETs with [`crate::contract_tool`].
+    pub fn get_secp(&self) -> &Secp256k1<All> {
+        &self.secp
     }
 
-    /// Function called to pass a DlcMessage to the Manager.
-    pub fn on_dlc_message(
-        &mut self,
-        msg: &DlcMessage,
-        counter_party: PublicKey,
-    ) -> Result<Option<DlcMessage>, Error> {
-        match msg {
-            DlcMessage::Offer(o) => {
-                self.on_offer_message(o, counter_party)?;
-                Ok(None)
-            }
-            DlcMessage::Accept(a) => Ok(Some(self.on_accept_message(a)?)),
-            DlcMessage::Sign(s) => {
-                self.on_sign_message(s)?;
-                Ok(None)
-            }
+    /// Set the minimum and maximum number of confirmations of the fund
+    /// transaction that a received offer can require. Offers outside of these
+    /// bounds are rejected.
+    pub fn set_nb_confirmations_bounds(&mut self, min: u32, max: u32) -> Result<(), Error> {
+        if min == 0 || min > max {
+            return Err(Error::InvalidParameters(
+                "Invalid number of confirmations bounds.".to_string(),
+            ));
         }
+        self.nb_confirmations_bounds = (min, max);
+        Ok(())
     }
 
-    fn get_party_params(
-        &self,
-        own_collateral: u64,
-        fee_rate: u64,
-    ) -> Result<(PartyParams, SecretKey, Vec<FundingInputInfo>), Error> {
-        let funding_privkey = self.wallet.get_new_secret_key()?;
-        let funding_pubkey = PublicKey::from_secret_key(&self.secp, &funding_privkey);
+    /// Set the [`ClosePolicy`] used to choose the CET to broadcast when several
+    /// of them can be used to close a contract.
+    pub fn set_close_policy(&mut self, close_policy: ClosePolicy) {
+        self.close_policy = close_policy;
+    }
 
-        let payout_addr = self.wallet.get_new_address()?;
-        let payout_spk = payout_addr.script_pubkey();
-        let payout_serial_id = get_new_serial_id();
-        let change_addr = self.wallet.get_new_address()?;
-        let change_spk = change_addr.script_pubkey();
-        let change_serial_id = get_new_serial_id();
+    /// Set the [`PeerPolicy`] applied to counter parties for which no specific
+    /// policy was set. By default, no limit is applied.
+    pub fn set_default_peer_policy(&mut self, peer_policy: PeerPolicy) {
+        self.default_peer_policy = peer_policy;
+    }
 
-        let appr_required_amount = own_collateral + crate::utils::get_half_common_fee(fee_rate);
-        let utxos = self
-            .wallet
-            .get_utxos_for_amount(appr_required_amount, Some(fee_rate), true)?;
+    /// Set the [`PeerPolicy`] applied to the given counter party, replacing the
+    /// default one for it.
+    pub fn set_peer_policy(&mut self, counter_party: PublicKey, peer_policy: PeerPolicy) {
+        self.peer_policies.insert(counter_party, peer_policy);
+    }
 
-        let mut funding_inputs_info: Vec<FundingInputInfo> = Vec::new();
-        let mut funding_tx_info: Vec<TxInputInfo> = Vec::new();
-        let mut total_input = 0;
-        for utxo in utxos {
-            let prev_tx = self.wallet.get_transaction(&utxo.outpoint.txid)?;
-            let mut writer = Vec::new();
-            prev_tx.consensus_encode(&mut writer)?;
-            let prev_tx_vout = utxo.outpoint.vout;
-            let sequence = 0xffffffff;
-            // TODO(tibo): this assumes P2WPKH with low R
-            let max_witness_len = 107;
-            let funding_input = FundingInput {
-                input_serial_id: get_new_serial_id(),
-                prev_tx: writer,
-                prev_tx_vout,
-                sequence,
-                max_witness_len,
-                redeem_script: utxo.redeem_script,
-            };
-            total_input += prev_tx.output[prev_tx_vout as usize].value;
-            funding_tx_info.push((&funding_input).into());
-            let funding_input_info = FundingInputInfo {
-                funding_input,
-                address: Some(utxo.address.clone()),
-            };
-            funding_inputs_info.push(funding_input_info);
-        }
+    /// Set the [`AcceptancePolicy`] that received offers must satisfy. Offers
+    /// that do not are rejected or stored for review depending on
+    /// [`AcceptancePolicy::on_violation`].
+    pub fn set_acceptance_policy(&mut self, acceptance_policy: AcceptancePolicy) {
+        self.acceptance_policy = Some(acceptance_policy);
+    }
 
-        let party_params = PartyParams {
-            fund_pubkey: funding_pubkey,
-            change_script_pubkey: change_spk,
-            change_serial_id,
-            payout_script_pubkey: payout_spk,
-            payout_serial_id,
-            inputs: funding_tx_info,
-            collateral: own_collateral,
-            input_amount: total_input,
-        };
+    /// Set the [`FundingInputPolicy`] that the funding inputs of received
+    /// offer and accept messages must satisfy. By default, no constraint is
+    /// applied.
+    pub fn set_funding_input_policy(&mut self, funding_input_policy: FundingInputPolicy) {
+        self.funding_input_policy = funding_input_policy;
+    }
 
-        Ok((party_params, funding_privkey, funding_inputs_info))
+    /// Set the number of seconds before the maturity of signed and confirmed
+    /// contracts at which [`periodic_check`](Manager::periodic_check) reports
+    /// an [`Event::MaturityApproaching`] for them, so that applications can
+    /// prepare for their closing. No event is reported if not set.
+    pub fn set_maturity_notification_window(&mut self, window: u64) {
+        self.maturity_notification_window = Some(window);
     }
-    fn get_oracle_announcements(
-        &self,
-        oracle_inputs: &OracleInput,
-    ) -> Result<Vec<OracleAnnouncement>, Error> {
-        let mut announcements = Vec::new();
-        for pubkey in &oracle_inputs.public_keys {
-            let oracle = self
-                .oracles
-                .get(pubkey)
-                .ok_or_else(|| Error::InvalidParameters("Unknown oracle public key".to_string()))?;
-            announcements.push(oracle.get_announcement(&oracle_inputs.event_id)?.clone());
-        }
 
-        Ok(announcements)
+    /// Set the number of seconds after the maturity of its events at which the
+    /// given oracle is expected to publish its attestations, e.g. for events
+    /// settled at the end of the day. Closing contracts and requesting
+    /// attestations for the events of the oracle is deferred until then,
+    /// instead of starting at their maturity.
+    pub fn set_attestation_delay(&mut self, oracle_public_key: SchnorrPublicKey, delay: u64) {
+        self.attestation_delays.insert(oracle_public_key, delay);
     }
 
-    fn contract_view_info_to_contract_info(
-        &self,
-        contract_view_info: &ContractInputInfo,
-    ) -> Result<ContractInfo, Error> {
-        let oracle_announcements = self.get_oracle_announcements(&contract_view_info.oracles)?;
-        Ok(ContractInfo {
-            contract_descriptor: contract_view_info.contract_descriptor.clone(),
-            oracle_announcements,
-            threshold: contract_view_info.oracles.threshold as usize,
-        })
+    /// Set whether the manager broadcasts the fund transactions of the
+    /// contracts it completes, and rebroadcasts them if they get evicted from
+    /// the mempool. When disabled, the fully signed fund transaction must be
+    /// retrieved with [`Manager::get_signed_funding`] and broadcast through
+    /// other means. Enabled by default.
+    pub fn set_broadcast_fund_tx(&mut self, broadcast_fund_tx: bool) {
+        self.broadcast_fund_tx = broadcast_fund_tx;
     }
 
-    /// Function called to create a new DLC. The offered contract will be stored
-    /// and an OfferDlc message returned.
-    pub fn send_offer(
+    /// Set the [`BroadcastInterceptor`] consulted before each broadcast of a
+    /// transaction. Once set, transactions that are delayed or that fail to
+    /// be broadcast are kept in the pending broadcast queue of the storage and
+    /// retried by [`periodic_check`](Manager::periodic_check).
+    pub fn set_broadcast_interceptor(
         &mut self,
-        contract: &ContractInput,
-        counter_party: PublicKey,
-    ) -> Result<OfferDlc, Error> {
-        let total_collateral = contract.offer_collateral + contract.accept_collateral;
-        let (party_params, _, funding_inputs_info) =
-            self.get_party_params(contract.offer_collateral, contract.fee_rate)?;
+        broadcast_interceptor: Box<dyn BroadcastInterceptor + Send + Sync>,
+    ) {
+        self.broadcast_interceptor = Some(broadcast_interceptor);
+    }
 
-        let fund_output_serial_id = get_new_serial_id();
-        let contract_info = contract
-            .contract_infos
-            .iter()
-            .map(|x| self.contract_view_info_to_contract_info(x))
-            .collect::<Result<Vec<ContractInfo>, Error>>()?;
-        let mut offered_contract = OfferedContract {
-            id: [0u8; 32],
-            is_offer_party: true,
-            contract_info,
-            offer_params: party_params,
-            total_collateral,
-            funding_inputs_info,
-            fund_output_serial_id,
-            fee_rate_per_vb: contract.fee_rate,
-            contract_maturity_bound: contract.maturity_time,
-            contract_timeout: contract.maturity_time + REFUND_DELAY,
-            counter_party,
-        };
+    /// Returns the transactions waiting in the pending broadcast queue.
+    pub fn get_pending_broadcasts(&self) -> Result<Vec<PendingBroadcast>, Error> {
+        self.get_store().get_pending_broadcasts()
+    }
+
+    /// Set the estimated time taken to create or verify an adaptor signature on
+    /// the host running the manager, used by [`Manager::preview_accept`].
+    /// Defaults to [`DEFAULT_ADAPTOR_SIGNATURE_DURATION`].
+    pub fn set_adaptor_signature_duration(&mut self, duration: Duration) {
+        self.adaptor_signature_duration = duration;
+    }
 
-        let offer_msg: OfferDlc = (&offered_contract).into();
+    /// Returns the time at which the attestation of the event of the given
+    /// announcement is expected to be published, as a unix timestamp. This is
+    /// the maturity of the event, unless an attestation delay was set for its
+    /// oracle with [`Manager::set_attestation_delay`].
+    pub fn get_expected_attestation_time(&self, announcement: &OracleAnnouncement) -> u64 {
+        let delay = self
+            .attestation_delays
+            .get(&announcement.oracle_public_key)
+            .cloned()
+            .unwrap_or(0);
+        (announcement.get_event_maturity_epoch() as u64).saturating_add(delay)
+    }
 
-        offered_contract.id = offer_msg.get_hash()?;
+    /// Set the [`Metrics`] implementation to which the manager reports
+    /// durations, message sizes and contract counts.
+    pub fn set_metrics(&mut self, metrics: Box<dyn Metrics + Send + Sync>) {
+        self.metrics = Some(metrics);
+    }
 
-        self.store.create_contract(&offered_contract)?;
+    /// Set the [`EventHandler`] to which the manager reports the [`Event`]s
+    /// that occur during periodic checks.
+    pub fn set_event_handler(&mut self, event_handler: Box<dyn EventHandler + Send + Sync>) {
+        self.event_handler = Some(event_handler);
+    }
 
-        Ok(offer_msg)
+    /// Set the resolver queried for the oracles of a contract that are not in
+    /// the map given when creating the manager.
+    pub fn set_oracle_resolver(&mut self, oracle_resolver: Box<dyn OracleResolver + Send + Sync>) {
+        self.oracle_resolver = Some(oracle_resolver);
     }
 
-    fn on_offer_message(
+    /// Adds a provider from which attestations are requested when the oracle
+    /// of a contract is not registered on the manager or does not provide a
+    /// valid attestation, and to cross-check the attestations it provides.
+    /// Providers are queried in the order in which they were added, requests
+    /// taking longer than `timeout` being abandoned.
+    pub fn add_attestation_provider(
         &mut self,
-        offered_message: &OfferDlc,
-        counter_party: PublicKey,
-    ) -> Result<(), Error> {
-        let contract: OfferedContract =
-            OfferedContract::try_from_offer_dlc(offered_message, counter_party)?;
-        self.store.create_contract(&contract)?;
+        provider: Arc<dyn AttestationProvider + Send + Sync>,
+        timeout: Duration,
+    ) {
+        self.attestation_sources
+            .push(AttestationSource { provider, timeout });
+    }
 
-        Ok(())
+    /// Get the store from the Manager to access contracts. The store is locked
+    /// until the returned guard is dropped, which should therefore not be kept
+    /// while calling other methods of the manager.
+    pub fn get_store(&self) -> MutexGuard<'_, S> {
+        self.store.lock().unwrap()
     }
 
-    /// Function to call to accept a DLC for which an offer was received.
-    pub fn accept_contract_offer(
-        &mut self,
+    /// Returns the timestamped list of state transitions of the contract with
+    /// the given temporary id, from the oldest to the most recent. The
+    /// temporary id of a contract can be obtained through
+    /// [`Contract::get_temporary_id`].
+    pub fn get_contract_history(
+        &self,
+        temporary_id: &TemporaryContractId,
+    ) -> Result<Vec<ContractHistoryEntry>, Error> {
+        self.get_store().get_contract_history(temporary_id)
+    }
+
+    /// Returns the payouts, fees and attested outcomes of the closed or
+    /// refunded contract with the given id, which can also have been archived
+    /// (see [`Manager::prune_closed`]).
+    pub fn get_closed_contract_report(
+        &self,
         contract_id: &ContractId,
-    ) -> Result<(ContractId, PublicKey, AcceptDlc), Error> {
-        let contract = self.store.get_contract(contract_id)?;
-        let offered_contract = match contract {
-            Some(Contract::Offered(offered)) => offered,
-            None => return Err(Error::InvalidParameters("Unknown contract id.".to_string())),
-            _ => return Err(Error::InvalidState),
+    ) -> Result<ClosedContractReport, Error> {
+        let contract = self.get_store().get_contract(contract_id)?;
+        let archived = match contract {
+            Some(contract) => {
+                ArchivedContract::from_contract(&contract).ok_or(Error::InvalidState)?
+            }
+            None => self
+                .get_store()
+                .get_archived_contracts()?
+                .into_iter()
+                .find(|x| x.id == *contract_id)
+                .ok_or_else(|| Error::InvalidParameters("Unknown contract id.".to_string()))?,
         };
+        Ok((&archived).into())
+    }
 
-        let total_collateral = offered_contract.total_collateral;
+    /// Archives the closed and refunded contracts that reached their final
+    /// state before the unix time `older_than` and whose closing transaction
+    /// has enough confirmations, replacing them in the storage by their
+    /// [`ArchivedContract`] record which does not include their transactions
+    /// and adaptor information. Returns the ids of the archived contracts.
+    pub fn prune_closed(&self, older_than: u64) -> Result<Vec<ContractId>, Error> {
+        let mut candidates = Vec::new();
+        for state in &[
+            ContractState::Closed,
+            ContractState::Refunded,
+            ContractState::RefundedDueToNoAttestation,
+        ] {
+            self.for_each_contract_in_state(*state, |contract| {
+                candidates.extend(ArchivedContract::from_contract(&contract));
+                true
+            })?;
+        }
 
-        let (accept_params, fund_secret_key, funding_inputs) = self.get_party_params(
-            offered_contract.offer_params.collateral,
-            offered_contract.fee_rate_per_vb,
-        )?;
+        let mut pruned = Vec::new();
+        for candidate in candidates {
+            // Contracts stored before history was recorded are considered
+            // old enough.
+            let closed_at = self
+                .get_contract_history(&candidate.temporary_id)?
+                .last()
+                .map(|x| x.timestamp)
+                .unwrap_or(0);
+            if closed_at >= older_than {
+                continue;
+            }
+            match self.get_tx_status(&candidate.closing_txid)? {
+                TxStatus::Confirmed(n) if n >= NB_CONFIRMATIONS => {}
+                _ => continue,
+            }
+            self.with_contract_lock(&candidate.id.0, || {
+                self.get_store().archive_contract(&candidate.id)
+            })?;
+            pruned.push(candidate.id);
+        }
 
-        let dlc_transactions = dlc::create_dlc_transactions(
-            &offered_contract.offer_params,
-            &accept_params,
-            &offered_contract.contract_info[0].get_payouts(total_collateral),
-            offered_contract.contract_timeout,
-            offered_contract.fee_rate_per_vb,
-            0,
-            offered_contract.contract_maturity_bound,
-            offered_contract.fund_output_serial_id,
-        )?;
+        Ok(pruned)
+    }
 
-        self.wallet.import_address(&Address::p2wsh(
-            &dlc_transactions.funding_script_pubkey,
-            self.blockchain.get_network()?,
-        ))?;
+    /// Decodes a message received from the counter party in its wire encoding,
+    /// prefixed by its type. Messages larger than the `max_message_size` of
+    /// the [`PeerPolicy`] of the counter party are rejected without being
+    /// parsed, so transports should decode messages with this function before
+    /// passing them to [`Manager::on_dlc_message`].
+    pub fn decode_dlc_message(
+        &self,
+        buf: &[u8],
+        counter_party: &PublicKey,
+    ) -> Result<DlcMessage, Error> {
+        if let Some(max_message_size) = self.get_peer_policy(counter_party).max_message_size {
+            // The type prefix is not part of the encoding of the message.
+            let size = buf.len().saturating_sub(2);
+            if size > max_message_size {
+                error!("Rejecting message of {} bytes from {}", size, counter_party);
+                return Err(Error::InvalidParameters(format!(
+                    "Message size {} exceeds the maximum of {} bytes.",
+                    size, max_message_size
+                )));
+            }
+        }
+        dlc_messages::read_message(&mut std::io::Cursor::new(buf))
+            .map_err(|e| Error::InvalidParameters(format!("Invalid message: {:?}", e)))
+    }
 
-        let fund_output_value = dlc_transactions.get_fund_output().value;
+    /// Function called to pass a DlcMessage to the Manager.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(message_type = msg.type_id()))
+    )]
+    pub fn on_dlc_message(
+        &self,
+        msg: &DlcMessage,
+        counter_party: PublicKey,
+    ) -> Result<Option<DlcMessage>, Error> {
+        self.record_message_size(msg, MessageDirection::Incoming);
+        let result = match msg {
+            DlcMessage::Offer(o) => self.on_offer_message(o, counter_party).map(|_| None),
+            DlcMessage::Accept(a) => self
+                .with_contract_lock(&a.temporary_contract_id.0, || {
+                    self.on_accept_message(a, &counter_party)
+                })
+                .map(Some),
+            DlcMessage::Sign(s) => self
+                .with_contract_lock(&s.contract_id.0, || self.on_sign_message(s, &counter_party))
+                .map(|_| None),
+            DlcMessage::RenewOffer(r) => self
+                .with_contract_lock(&r.contract_id.0, || {
+                    self.on_renew_offer_message(r, counter_party)
+                })
+                .map(|_| None),
+            DlcMessage::Reestablish(r) => self.on_reestablish_message(r, counter_party),
+        };
 
-        let cet_input = dlc_transactions.cets[0].input[0].clone();
-        let (adaptor_info, adaptor_sig) = offered_contract.contract_info[0].get_adaptor_info(
-            &self.secp,
-            offered_contract.total_collateral,
-            &fund_secret_key,
-            &dlc_transactions.funding_script_pubkey,
-            fund_output_value,
-            &dlc_transactions.cets,
-            0,
-        )?;
-        let mut adaptor_infos = vec![adaptor_info];
-        let mut adaptor_sigs = adaptor_sig;
+        let response = result.map_err(|e| {
+            error!(
+                "Error processing {} message from {}: {}",
+                get_message_name(msg),
+                counter_party,
+                e
+            );
+            e
+        })?;
 
-        let DlcTransactions {
-            fund,
-            mut cets,
-            refund,
-            funding_script_pubkey,
-        } = dlc_transactions;
+        if let Some(response) = &response {
+            self.record_message_size(response, MessageDirection::Outgoing);
+        }
 
-        for contract_info in offered_contract.contract_info.iter().skip(1) {
-            let payouts = contract_info.get_payouts(total_collateral);
-
-            let tmp_cets = dlc::create_cets(
-                &cet_input,
-                &offered_contract.offer_params.payout_script_pubkey,
-                offered_contract.offer_params.payout_serial_id,
-                &accept_params.payout_script_pubkey,
-                accept_params.payout_serial_id,
-                &payouts,
-                0,
-            );
+        Ok(response)
+    }
 
-            let (adaptor_info, adaptor_sig) = contract_info.get_adaptor_info(
-                &self.secp,
-                offered_contract.total_collateral,
-                &fund_secret_key,
-                &funding_script_pubkey,
-                fund_output_value,
-                &tmp_cets,
-                adaptor_sigs.len(),
-            )?;
+    fn update_contract(
+        &self,
+        contract: &Contract,
+        txid: Option<Txid>,
+        message_hash: Option<[u8; 32]>,
+    ) -> Result<(), Error> {
+        self.get_store().update_contract(contract)?;
+        self.add_history_entry(contract, txid, message_hash)
+    }
 
-            cets.extend(tmp_cets);
+    fn add_history_entry(
+        &self,
+        contract: &Contract,
+        txid: Option<Txid>,
+        message_hash: Option<[u8; 32]>,
+    ) -> Result<(), Error> {
+        let entry = ContractHistoryEntry {
+            timestamp: self.time.unix_time_now(),
+            state: contract.get_state_name().to_string(),
+            txid,
+            message_hash,
+        };
+        self.get_store()
+            .add_contract_history_entry(&contract.get_temporary_id(), &entry)
+    }
 
-            adaptor_infos.push(adaptor_info);
-            adaptor_sigs.extend(adaptor_sig);
+    fn record_message_size<M: Type + Writeable>(&self, msg: &M, direction: MessageDirection) {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_message_size(msg.type_id(), direction, msg.encode().len());
         }
+    }
 
-        let refund_signature = dlc::util::get_raw_sig_for_tx_input(
-            &self.secp,
-            &refund,
-            0,
-            &funding_script_pubkey,
-            fund_output_value,
-            &fund_secret_key,
-        );
+    fn timed<R, F: FnOnce() -> R>(&self, operation: Operation, f: F) -> R {
+        let start = Instant::now();
+        let res = f();
+        if let Some(metrics) = &self.metrics {
+            metrics.record_duration(operation, start.elapsed());
+        }
+        res
+    }
 
-        let dlc_transactions = DlcTransactions {
-            fund,
-            cets,
-            refund,
-            funding_script_pubkey,
+    /// Runs `f` while holding the lock of the contract with the given id (or
+    /// temporary id for contracts that were not accepted yet), so that
+    /// concurrent operations on the same contract cannot overwrite each other.
+    fn with_contract_lock<R, F: FnOnce() -> R>(&self, id: &[u8; 32], f: F) -> R {
+        let lock = {
+            let mut locks = self.contract_locks.lock().unwrap();
+            // Locks only referenced by the map are not used anymore.
+            locks.retain(|_, l| Arc::strong_count(l) > 1);
+            Arc::clone(locks.entry(*id).or_insert_with(|| Arc::new(Mutex::new(()))))
         };
+        let _guard = lock.lock().unwrap();
+        f()
+    }
 
-        let counter_party = offered_contract.counter_party;
+    /// Runs `f` while holding the lock of the given counter party, so that
+    /// the [`PeerPolicy`] limits cannot be exceeded by concurrent operations
+    /// opening contracts with it. Peer locks are always taken after contract
+    /// locks.
+    fn with_peer_lock<R, F: FnOnce() -> R>(&self, counter_party: &PublicKey, f: F) -> R {
+        let lock = {
+            let mut locks = self.peer_locks.lock().unwrap();
+            locks.retain(|_, l| Arc::strong_count(l) > 1);
+            Arc::clone(
+                locks
+                    .entry(*counter_party)
+                    .or_insert_with(|| Arc::new(Mutex::new(()))),
+            )
+        };
+        let _guard = lock.lock().unwrap();
+        f()
+    }
 
-        let mut accepted_contract = AcceptedContract {
-            offered_contract,
-            adaptor_infos,
-            adaptor_signatures: Some(adaptor_sigs),
+    /// Returns the counter party of the contract with the given temporary id.
+    fn get_counter_party(&self, temporary_id: &TemporaryContractId) -> Result<PublicKey, Error> {
+        match self
+            .get_store()
+            .get_contract_by_temporary_id(temporary_id)?
+        {
+            Some(contract) => Ok(contract.get_offered_contract().counter_party),
+            None => Err(Error::InvalidParameters("Unknown contract id.".to_string())),
+        }
+    }
+
+    /// Returns the adaptor information of the given contract, loading it from
+    /// the storage if the contract was retrieved without it.
+    fn get_adaptor_infos<'a>(
+        &self,
+        accepted_contract: &'a AcceptedContract,
+    ) -> Result<Cow<'a, [AdaptorInfo]>, Error> {
+        if !accepted_contract.adaptor_infos.is_empty() {
+            return Ok(Cow::Borrowed(&accepted_contract.adaptor_infos));
+        }
+        let adaptor_infos = self
+            .get_store()
+            .get_adaptor_info(&accepted_contract.get_contract_id())?
+            .ok_or_else(|| {
+                Error::StorageError(format!(
+                    "Missing adaptor information of contract {}",
+                    accepted_contract.get_contract_id_string()
+                ))
+            })?;
+        Ok(Cow::Owned(adaptor_infos))
+    }
+
+    fn get_peer_policy(&self, counter_party: &PublicKey) -> &PeerPolicy {
+        self.peer_policies
+            .get(counter_party)
+            .unwrap_or(&self.default_peer_policy)
+    }
+
+    /// Checks that a contract with the given counter party requiring
+    /// `nb_confirmations` of its fund transaction can be entered into, zero
+    /// confirmations being only allowed by the [`PeerPolicy`] of the counter
+    /// party and not for renewals, as the renewed contract would be
+    /// considered closed before the fund transaction is confirmed.
+    fn check_zero_conf(
+        &self,
+        counter_party: &PublicKey,
+        nb_confirmations: u32,
+        is_renewal: bool,
+    ) -> Result<(), Error> {
+        if nb_confirmations > 0 {
+            return Ok(());
+        }
+
+        if is_renewal {
+            return Err(Error::InvalidParameters(
+                "Renewals cannot use zero-conf funding.".to_string(),
+            ));
+        }
+
+        if !self.get_peer_policy(counter_party).allow_zero_conf {
+            return Err(Error::InvalidParameters(format!(
+                "Zero-conf funding is not allowed with {}.",
+                counter_party
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that entering into a new contract with the given counter party,
+    /// putting `collateral` at risk, does not exceed the limits of the
+    /// [`PeerPolicy`] applying to it. Offers with a pending acceptance count
+    /// as open contracts, while the contract being renewed by the new one, if
+    /// any, is not taken into account. Must be called while holding the peer
+    /// lock of the counter party until the new contract is stored.
+    fn check_peer_policy(
+        &self,
+        counter_party: &PublicKey,
+        collateral: u64,
+        renewed_contract_id: Option<&ContractId>,
+    ) -> Result<(), Error> {
+        let peer_policy = self.get_peer_policy(counter_party);
+        if peer_policy.max_open_contracts.is_none() && peer_policy.max_collateral_at_risk.is_none()
+        {
+            return Ok(());
+        }
+
+        let contracts = self
+            .get_store()
+            .get_contracts_by_counterparty(counter_party)?;
+        let pending_accepts: HashSet<[u8; 32]> = self
+            .pending_accepts
+            .lock()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        let open_contracts: Vec<_> = contracts
+            .iter()
+            .filter(|c| is_open_contract(c) || pending_accepts.contains(&c.get_temporary_id().0))
+            .filter(|c| renewed_contract_id.is_none() || c.get_id().as_ref() != renewed_contract_id)
+            .collect();
+
+        if let Some(max_open_contracts) = peer_policy.max_open_contracts {
+            if open_contracts.len() >= max_open_contracts {
+                return Err(Error::InvalidParameters(format!(
+                    "Maximum number of open contracts with {} reached.",
+                    counter_party
+                )));
+            }
+        }
+
+        if let Some(max_collateral_at_risk) = peer_policy.max_collateral_at_risk {
+            let collateral_at_risk: u64 = open_contracts
+                .iter()
+                .map(|c| get_own_collateral(c.get_offered_contract()))
+                .sum();
+            if collateral_at_risk + collateral > max_collateral_at_risk {
+                return Err(Error::InvalidParameters(format!(
+                    "Maximum collateral at risk with {} exceeded.",
+                    counter_party
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the funding inputs of the counter party satisfy the
+    /// [`FundingInputPolicy`], the confirmations of the transactions they
+    /// spend being queried from the blockchain. The ownership proofs are
+    /// checked against the fund public key of the counter party, except for
+    /// the input spending `renewed_fund_outpoint`.
+    fn validate_counter_party_funding_inputs(
+        &self,
+        funding_inputs: &[FundingInput],
+        fund_pubkey: &PublicKey,
+        funding_input_proofs: Option<&FundingInputProofs>,
+        renewed_fund_outpoint: Option<&OutPoint>,
+    ) -> Result<(), Error> {
+        let policy = &self.funding_input_policy;
+
+        if let Some(max_inputs) = policy.max_inputs {
+            if funding_inputs.len() > max_inputs {
+                return Err(Error::InvalidParameters(format!(
+                    "Counter party uses {} funding inputs, at most {} are accepted.",
+                    funding_inputs.len(),
+                    max_inputs
+                )));
+            }
+        }
+
+        if policy.min_confirmations.is_none()
+            && !policy.standard_only
+            && !policy.require_ownership_proofs
+        {
+            return Ok(());
+        }
+
+        let block_height = match policy.min_confirmations {
+            Some(_) => self.blockchain.get_block_height()?,
+            None => 0,
+        };
+
+        for funding_input in funding_inputs {
+            let prev_tx = Transaction::consensus_decode(&*funding_input.prev_tx).map_err(|_| {
+                Error::InvalidParameters("Could not decode funding input previous tx.".to_string())
+            })?;
+            let prev_txid = prev_tx.txid();
+            let outpoint = OutPoint {
+                txid: prev_txid,
+                vout: funding_input.prev_tx_vout,
+            };
+
+            if policy.require_ownership_proofs && Some(&outpoint) != renewed_fund_outpoint {
+                let proof = funding_input_proofs
+                    .and_then(|x| {
+                        x.funding_input_proofs
+                            .iter()
+                            .find(|p| p.input_serial_id == funding_input.input_serial_id)
+                    })
+                    .ok_or_else(|| {
+                        Error::InvalidParameters(format!(
+                            "Missing ownership proof for funding input {}.",
+                            outpoint
+                        ))
+                    })?;
+                let script_pubkey = &prev_tx
+                    .output
+                    .get(outpoint.vout as usize)
+                    .ok_or_else(|| {
+                        Error::InvalidParameters(format!("Funding input {} not found.", outpoint))
+                    })?
+                    .script_pubkey;
+                let witness: Vec<_> = proof
+                    .witness_elements
+                    .iter()
+                    .map(|x| x.witness.clone())
+                    .collect();
+                dlc::ownership_proof::verify_ownership_proof(
+                    &self.secp,
+                    fund_pubkey,
+                    &outpoint,
+                    script_pubkey,
+                    &funding_input.redeem_script,
+                    &witness,
+                )
+                .map_err(|_| {
+                    Error::InvalidParameters(format!(
+                        "Invalid ownership proof for funding input {}.",
+                        outpoint
+                    ))
+                })?;
+            }
+
+            if policy.standard_only {
+                let is_standard = prev_tx
+                    .output
+                    .get(funding_input.prev_tx_vout as usize)
+                    .map_or(false, |x| {
+                        is_standard_funding_script(&x.script_pubkey, &funding_input.redeem_script)
+                    });
+                if !is_standard {
+                    return Err(Error::InvalidParameters(format!(
+                        "Funding input {} does not spend a standard segwit output.",
+                        outpoint
+                    )));
+                }
+            }
+
+            if let Some(min_confirmations) = policy.min_confirmations {
+                let confirmations = match self.blockchain.get_tx_block_height(&outpoint.txid)? {
+                    Some(height) if height <= block_height => block_height - height + 1,
+                    _ => 0,
+                };
+                if confirmations < min_confirmations as u64 {
+                    return Err(Error::InvalidParameters(format!(
+                        "Funding input {} has {} confirmations, at least {} are required.",
+                        outpoint, confirmations, min_confirmations
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the terms of the given received offer that do not satisfy the
+    /// acceptance policy, including the total collateral at risk of the local
+    /// party in open contracts if it was accepted.
+    fn get_policy_violations(
+        &self,
+        acceptance_policy: &AcceptancePolicy,
+        offered_contract: &OfferedContract,
+    ) -> Result<Vec<PolicyViolation>, Error> {
+        let mut violations =
+            acceptance_policy.evaluate(offered_contract, self.time.unix_time_now());
+
+        if let Some(max_total_collateral_at_risk) = acceptance_policy.max_total_collateral_at_risk {
+            let renewed_contract_id = offered_contract.renewal.as_ref().map(|x| x.contract_id);
+            let contracts = self.get_store().get_contracts()?;
+            let collateral_at_risk: u64 = contracts
+                .iter()
+                .filter(|c| is_open_contract(c))
+                .filter(|c| renewed_contract_id.is_none() || c.get_id() != renewed_contract_id)
+                .map(|c| get_own_collateral(c.get_offered_contract()))
+                .sum::<u64>()
+                + get_own_collateral(offered_contract);
+            if collateral_at_risk > max_total_collateral_at_risk {
+                violations.push(PolicyViolation::TotalCollateralAtRiskTooHigh(
+                    collateral_at_risk,
+                ));
+            }
+        }
+
+        Ok(violations)
+    }
+
+    /// Returns the terms of the received offer with the given temporary id that
+    /// do not satisfy the acceptance policy, evaluated at the time of the call.
+    /// Offers stored for review can be accepted programmatically when the
+    /// returned list is empty.
+    pub fn get_offer_policy_violations(
+        &self,
+        temporary_id: &TemporaryContractId,
+    ) -> Result<Vec<PolicyViolation>, Error> {
+        let contract = self
+            .get_store()
+            .get_contract_by_temporary_id(temporary_id)?;
+        let offered_contract = match contract {
+            Some(Contract::Offered(offered)) => offered,
+            None => return Err(Error::InvalidParameters("Unknown contract id.".to_string())),
+            _ => return Err(Error::InvalidState),
+        };
+
+        match &self.acceptance_policy {
+            Some(acceptance_policy) => {
+                self.get_policy_violations(acceptance_policy, &offered_contract)
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Imports the address corresponding to a custom payout script pubkey in
+    /// the wallet so that payouts to it are watched, unless it was marked as
+    /// external. Scripts without an address representation are skipped.
+    fn watch_payout_script_pubkey(
+        &self,
+        payout_script_pubkey: &Script,
+        is_payout_external: bool,
+    ) -> Result<(), Error> {
+        if is_payout_external {
+            return Ok(());
+        }
+
+        if let Some(address) =
+            Address::from_script(payout_script_pubkey, self.blockchain.get_network()?)
+        {
+            self.wallet.import_address(&address)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the information required to co-sign the input spending the fund
+    /// output of the contract being renewed.
+    fn get_renewed_contract_fund_info(
+        &self,
+        renewal: &RenewalInfo,
+    ) -> Result<RenewedFundInfo, Error> {
+        let signed_contract = match self.get_store().get_contract(&renewal.contract_id)? {
+            Some(Contract::Confirmed(c)) => c,
+            None => return Err(Error::InvalidParameters("Unknown contract id.".to_string())),
+            _ => return Err(Error::InvalidState),
+        };
+        let accepted_contract = &signed_contract.accepted_contract;
+        let offer_fund_pubkey = accepted_contract.offered_contract.offer_params.fund_pubkey;
+        let accept_fund_pubkey = accepted_contract.accept_params.fund_pubkey;
+        let (own_fund_pubkey, other_fund_pubkey) =
+            if accepted_contract.offered_contract.is_offer_party {
+                (offer_fund_pubkey, accept_fund_pubkey)
+            } else {
+                (accept_fund_pubkey, offer_fund_pubkey)
+            };
+        let fund_secret_key = self.wallet.get_secret_key_for_pubkey(&own_fund_pubkey)?;
+        let dlc_transactions = &accepted_contract.dlc_transactions;
+
+        Ok(RenewedFundInfo {
+            outpoint: OutPoint {
+                txid: dlc_transactions.fund.txid(),
+                vout: dlc_transactions.get_fund_output_index() as u32,
+            },
+            funding_script_pubkey: dlc_transactions.funding_script_pubkey.clone(),
+            fund_output_value: dlc_transactions.get_fund_output().value,
+            fund_secret_key,
+            other_fund_pubkey,
+        })
+    }
+
+    fn get_party_params(
+        &self,
+        own_collateral: u64,
+        fee_rate: u64,
+        common_fee: u64,
+        payout_script_pubkey: Option<Script>,
+        premium_amount: u64,
+        funding: Option<(Vec<FundingInput>, u64)>,
+        change_policy: Option<&ChangePolicy>,
+    ) -> Result<(PartyParams, SecretKey, Vec<FundingInputInfo>), Error> {
+        let funding_privkey = self.wallet.get_new_secret_key()?;
+        let funding_pubkey = PublicKey::from_secret_key(&self.secp, &funding_privkey);
+
+        let payout_spk = match payout_script_pubkey {
+            Some(spk) => spk,
+            None => self.get_new_address()?.script_pubkey(),
+        };
+        let payout_serial_id = get_new_serial_id();
+        let change_spk = self.get_new_address()?.script_pubkey();
+        let change_serial_id = get_new_serial_id();
+
+        let appr_required_amount = own_collateral + premium_amount + common_fee;
+        let (funding_inputs_info, funding_tx_info, total_input) = match funding {
+            Some((funding_inputs, input_amount)) => {
+                let mut funding_inputs_info: Vec<FundingInputInfo> = funding_inputs
+                    .iter()
+                    .map(|x| FundingInputInfo {
+                        funding_input: x.clone(),
+                        address: None,
+                        witness_script: None,
+                    })
+                    .collect();
+                let mut funding_tx_info: Vec<TxInputInfo> =
+                    funding_inputs.iter().map(|x| x.into()).collect();
+                let mut total_input = input_amount;
+                // Wallet inputs are added when the provided funds do not cover
+                // the collateral, e.g. when splicing funds into a contract.
+                if input_amount < appr_required_amount {
+                    let (wallet_inputs_info, wallet_tx_info, wallet_input) = self
+                        .get_wallet_funding_inputs(appr_required_amount - input_amount, fee_rate)?;
+                    funding_inputs_info.extend(wallet_inputs_info);
+                    funding_tx_info.extend(wallet_tx_info);
+                    total_input += wallet_input;
+                }
+                (funding_inputs_info, funding_tx_info, total_input)
+            }
+            None => self.get_wallet_funding_inputs(appr_required_amount, fee_rate)?,
+        };
+
+        let party_params = PartyParams {
+            fund_pubkey: funding_pubkey,
+            change_script_pubkey: change_spk,
+            change_serial_id,
+            payout_script_pubkey: payout_spk,
+            payout_serial_id,
+            inputs: funding_tx_info,
+            collateral: own_collateral,
+            input_amount: total_input,
+        };
+
+        // An empty change script pubkey is never sent to the counter party,
+        // the change output being instead discarded by both parties when it
+        // is dust.
+        if let Some(change_policy) = change_policy {
+            let (fund_fee, cet_fee) = party_params.get_fees(fee_rate);
+            let change_value =
+                total_input.saturating_sub(own_collateral + premium_amount + fund_fee + cet_fee);
+            let min_change_value = match change_policy {
+                ChangePolicy::NoChange => u64::MAX,
+                ChangePolicy::MinChangeValue(min_change_value) => *min_change_value,
+            };
+            if change_value >= dlc::DUST_LIMIT && change_value < min_change_value {
+                self.unlock_utxos(&get_wallet_outpoints(&funding_inputs_info));
+                return Err(Error::InvalidParameters(format!(
+                    "Funding inputs leave a change of {} which does not satisfy the change policy.",
+                    change_value
+                )));
+            }
+        }
+
+        Ok((party_params, funding_privkey, funding_inputs_info))
+    }
+
+    /// Returns a new address from the wallet, checking that it is for the
+    /// network of the blockchain.
+    fn get_new_address(&self) -> Result<Address, Error> {
+        let address = self.wallet.get_new_address()?;
+        validate_address_network(&address, self.blockchain.get_network()?)?;
+        Ok(address)
+    }
+
+    /// Returns the offer message for the given offered contract, its chain hash
+    /// identifying the network of the blockchain.
+    fn get_offer_message(&self, offered_contract: &OfferedContract) -> Result<OfferDlc, Error> {
+        let mut offer_msg: OfferDlc = offered_contract.into();
+        offer_msg.chain_hash = get_chain_hash(self.blockchain.get_network()?);
+        Ok(offer_msg)
+    }
+
+    fn get_wallet_funding_inputs(
+        &self,
+        amount: u64,
+        fee_rate: u64,
+    ) -> Result<(Vec<FundingInputInfo>, Vec<TxInputInfo>, u64), Error> {
+        let utxos = self
+            .wallet
+            .get_utxos_for_amount(amount, Some(fee_rate), true)?;
+        let network = self.blockchain.get_network()?;
+        for utxo in &utxos {
+            validate_address_network(&utxo.address, network)?;
+        }
+
+        let mut funding_inputs_info: Vec<FundingInputInfo> = Vec::new();
+        let mut funding_tx_info: Vec<TxInputInfo> = Vec::new();
+        let mut total_input = 0;
+        for utxo in utxos {
+            let prev_tx = self.wallet.get_transaction(&utxo.outpoint.txid)?;
+            let mut writer = Vec::new();
+            prev_tx.consensus_encode(&mut writer)?;
+            let prev_tx_vout = utxo.outpoint.vout;
+            let sequence = 0xffffffff;
+            // TODO(tibo): this assumes P2WPKH with low R if not specified
+            let max_witness_len = utxo.max_witness_len.unwrap_or(107) as u16;
+            let funding_input = FundingInput {
+                input_serial_id: get_new_serial_id(),
+                prev_tx: writer,
+                prev_tx_vout,
+                sequence,
+                max_witness_len,
+                redeem_script: utxo.redeem_script,
+            };
+            total_input += prev_tx.output[prev_tx_vout as usize].value;
+            funding_tx_info.push((&funding_input).into());
+            let funding_input_info = FundingInputInfo {
+                funding_input,
+                address: Some(utxo.address.clone()),
+                witness_script: utxo.witness_script,
+            };
+            funding_inputs_info.push(funding_input_info);
+        }
+
+        Ok((funding_inputs_info, funding_tx_info, total_input))
+    }
+
+    /// Returns the proofs that the local party controls the outputs spent by
+    /// the given funding inputs, signed through the wallet. Only the inputs
+    /// selected from the wallet get a proof, others, such as the fund output
+    /// of a renewed contract, cannot be signed by the wallet alone.
+    fn get_funding_input_proofs(
+        &self,
+        fund_pubkey: &PublicKey,
+        funding_inputs_info: &[FundingInputInfo],
+    ) -> Result<Option<FundingInputProofs>, Error> {
+        let mut funding_input_proofs = Vec::new();
+
+        for funding_input_info in funding_inputs_info.iter().filter(|x| x.address.is_some()) {
+            let funding_input = &funding_input_info.funding_input;
+            let prev_tx = Transaction::consensus_decode(&*funding_input.prev_tx).map_err(|_| {
+                Error::InvalidParameters("Could not decode funding input previous tx.".to_string())
+            })?;
+            let outpoint = OutPoint {
+                txid: prev_tx.txid(),
+                vout: funding_input.prev_tx_vout,
+            };
+            let script_pubkey = prev_tx
+                .output
+                .get(outpoint.vout as usize)
+                .ok_or(Error::InvalidState)?
+                .script_pubkey
+                .clone();
+
+            let proof_tx = dlc::ownership_proof::get_ownership_proof_transaction(
+                fund_pubkey,
+                &outpoint,
+                &script_pubkey,
+            );
+            let mut psbt = PartiallySignedTransaction::from_unsigned_tx(proof_tx)
+                .map_err(|_| Error::InvalidState)?;
+            {
+                let psbt_input = &mut psbt.inputs[0];
+                psbt_input.witness_utxo = Some(TxOut {
+                    value: 0,
+                    script_pubkey,
+                });
+                psbt_input.sighash_type = Some(SigHashType::All);
+                if !funding_input.redeem_script.is_empty() {
+                    psbt_input.redeem_script = Some(funding_input.redeem_script.clone());
+                }
+                psbt_input.witness_script = funding_input_info.witness_script.clone();
+            }
+
+            self.wallet.sign_psbt_input(&mut psbt, 0)?;
+
+            let witness_elements = psbt.inputs[0]
+                .final_script_witness
+                .take()
+                .ok_or_else(|| {
+                    Error::InvalidParameters(
+                        "Ownership proof input was not finalized by the wallet".to_string(),
+                    )
+                })?
+                .into_iter()
+                .map(|witness| WitnessElement { witness })
+                .collect();
+            funding_input_proofs.push(FundingInputProof {
+                input_serial_id: funding_input.input_serial_id,
+                witness_elements,
+            });
+        }
+
+        if funding_input_proofs.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(FundingInputProofs {
+            funding_input_proofs,
+        }))
+    }
+
+    /// Signs the input at the given index of the fund transaction through the
+    /// wallet, providing it with a PSBT containing the previous output and the
+    /// scripts required to finalize the input.
+    fn sign_funding_input(
+        &self,
+        fund_tx: &mut Transaction,
+        input_index: usize,
+        funding_input_info: &FundingInputInfo,
+    ) -> Result<(), Error> {
+        let funding_input = &funding_input_info.funding_input;
+        let prev_tx = Transaction::consensus_decode(&*funding_input.prev_tx).map_err(|_| {
+            Error::InvalidParameters(
+                "Could not decode funding input previous tx parameter".to_string(),
+            )
+        })?;
+        let vout = funding_input.prev_tx_vout;
+        let tx_out = prev_tx.output.get(vout as usize).cloned().ok_or_else(|| {
+            Error::InvalidParameters(format!("Previous tx output not found at index {}", vout))
+        })?;
+
+        let mut unsigned_tx = fund_tx.clone();
+        for input in unsigned_tx.input.iter_mut() {
+            input.script_sig = Script::new();
+            input.witness = Vec::new();
+        }
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(unsigned_tx)
+            .map_err(|_| Error::InvalidState)?;
+        {
+            let psbt_input = &mut psbt.inputs[input_index];
+            psbt_input.non_witness_utxo = Some(prev_tx);
+            psbt_input.witness_utxo = Some(tx_out);
+            psbt_input.sighash_type = Some(SigHashType::All);
+            if !funding_input.redeem_script.is_empty() {
+                psbt_input.redeem_script = Some(funding_input.redeem_script.clone());
+            }
+            psbt_input.witness_script = funding_input_info.witness_script.clone();
+        }
+
+        self.wallet.sign_psbt_input(&mut psbt, input_index)?;
+
+        let psbt_input = &psbt.inputs[input_index];
+        if let Some(script_sig) = &psbt_input.final_script_sig {
+            fund_tx.input[input_index].script_sig = script_sig.clone();
+        }
+        fund_tx.input[input_index].witness =
+            psbt_input.final_script_witness.clone().ok_or_else(|| {
+                Error::InvalidParameters(format!(
+                    "Funding input at index {} was not finalized by the wallet",
+                    input_index
+                ))
+            })?;
+
+        Ok(())
+    }
+    fn get_oracle_announcements(
+        &self,
+        oracle_inputs: &OracleInput,
+    ) -> Result<Vec<OracleAnnouncement>, Error> {
+        let mut announcements = Vec::new();
+        for pubkey in &oracle_inputs.public_keys {
+            announcements.push(self.get_oracle_announcement(pubkey, &oracle_inputs.event_id)?);
+        }
+
+        Ok(announcements)
+    }
+
+    fn get_oracle_announcement(
+        &self,
+        pubkey: &SchnorrPublicKey,
+        event_id: &str,
+    ) -> Result<OracleAnnouncement, Error> {
+        if let Some(oracle) = self.oracles.get(pubkey) {
+            return oracle.get_announcement(event_id);
+        }
+        match self.resolve_oracle(pubkey) {
+            Some(oracle) => oracle.get_announcement(event_id),
+            None => Err(Error::InvalidParameters(
+                "Unknown oracle public key".to_string(),
+            )),
+        }
+    }
+
+    fn get_oracle_attestation(
+        &self,
+        pubkey: &SchnorrPublicKey,
+        event_id: &str,
+    ) -> Option<Result<OracleAttestation, Error>> {
+        if let Some(oracle) = self.oracles.get(pubkey) {
+            return Some(oracle.get_attestation(event_id));
+        }
+        self.resolve_oracle(pubkey)
+            .map(|oracle| oracle.get_attestation(event_id))
+    }
+
+    fn resolve_oracle(&self, pubkey: &SchnorrPublicKey) -> Option<Arc<dyn Oracle + Send + Sync>> {
+        self.oracle_resolver
+            .as_ref()
+            .and_then(|resolver| resolver.resolve(pubkey))
+    }
+
+    fn contract_view_info_to_contract_info(
+        &self,
+        contract_view_info: &ContractInputInfo,
+    ) -> Result<ContractInfo, Error> {
+        let oracles = &contract_view_info.oracles;
+        let oracle_announcements = self.get_oracle_announcements(oracles)?;
+        let mut contract_descriptor = contract_view_info.contract_descriptor.clone();
+        if let ContractDescriptor::Hybrid(h) = &mut contract_descriptor {
+            h.selector_announcements = self.get_oracle_announcements(&OracleInput {
+                public_keys: oracles.public_keys.clone(),
+                event_id: h.selector_event_id.clone(),
+                threshold: oracles.threshold,
+            })?;
+        }
+        Ok(ContractInfo {
+            contract_descriptor,
+            oracle_announcements,
+            threshold: contract_view_info.oracles.threshold as usize,
+        })
+    }
+
+    /// Function called to create a new DLC. The offered contract will be stored
+    /// and an OfferDlc message returned.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn send_offer(
+        &self,
+        contract: &ContractInput,
+        counter_party: PublicKey,
+    ) -> Result<OfferDlc, Error> {
+        let offer_msg = self.with_peer_lock(&counter_party, || {
+            self.check_peer_policy(&counter_party, contract.offer_collateral, None)?;
+            self.offer_contract_internal(contract, counter_party, None)
+        })?;
+
+        self.record_message_size(&offer_msg, MessageDirection::Outgoing);
+
+        Ok(offer_msg)
+    }
+
+    /// Function called to renew a confirmed contract by spending its fund
+    /// output directly into the fund transaction of a new contract, avoiding
+    /// a round trip through the wallets of the parties. `counter_payout` is the
+    /// part of the fund output value attributed to the counter party as
+    /// settlement of the current contract, the remainder being attributed to
+    /// the local party. When the share of a party does not cover its collateral
+    /// in the new contract and its part of the fees, inputs from its wallet
+    /// are added to the fund transaction, while any excess is sent to its
+    /// change output. The renewed contract will be stored and a RenewOffer
+    /// message returned together with the counter party id.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn renew_contract(
+        &self,
+        contract_id: &ContractId,
+        contract_input: &ContractInput,
+        counter_payout: u64,
+    ) -> Result<(PublicKey, RenewOffer), Error> {
+        self.with_contract_lock(&contract_id.0, || {
+            let counter_party = match self.get_store().get_contract(contract_id)? {
+                Some(contract) => contract.get_offered_contract().counter_party,
+                None => return Err(Error::InvalidParameters("Unknown contract id.".to_string())),
+            };
+            self.with_peer_lock(&counter_party, || {
+                self.renew_contract_internal(contract_id, contract_input, counter_payout)
+            })
+        })
+    }
+
+    fn renew_contract_internal(
+        &self,
+        contract_id: &ContractId,
+        contract_input: &ContractInput,
+        counter_payout: u64,
+    ) -> Result<(PublicKey, RenewOffer), Error> {
+        let signed_contract = match self.get_store().get_contract(contract_id)? {
+            Some(Contract::Confirmed(c)) => c,
+            None => return Err(Error::InvalidParameters("Unknown contract id.".to_string())),
+            _ => return Err(Error::InvalidState),
+        };
+
+        if contract_input.premium.is_some() {
+            return Err(Error::InvalidParameters(
+                "Premium is not supported when renewing a contract.".to_string(),
+            ));
+        }
+
+        let contract_id = signed_contract.accepted_contract.get_contract_id();
+        let dlc_transactions = &signed_contract.accepted_contract.dlc_transactions;
+        let fund_output_value = dlc_transactions.get_fund_output().value;
+        if counter_payout > fund_output_value {
+            return Err(Error::InvalidParameters(
+                "Counter payout is greater than the fund output value.".to_string(),
+            ));
+        }
+
+        self.check_peer_policy(
+            &signed_contract
+                .accepted_contract
+                .offered_contract
+                .counter_party,
+            contract_input.offer_collateral,
+            Some(&contract_id),
+        )?;
+
+        let mut prev_tx = Vec::new();
+        dlc_transactions.fund.consensus_encode(&mut prev_tx)?;
+        let funding_input = FundingInput {
+            input_serial_id: get_new_serial_id(),
+            prev_tx,
+            prev_tx_vout: dlc_transactions.get_fund_output_index() as u32,
+            sequence: 0xffffffff,
+            max_witness_len: MULTISIG_WITNESS_LEN,
+            redeem_script: Script::new(),
+        };
+
+        let counter_party = signed_contract
+            .accepted_contract
+            .offered_contract
+            .counter_party;
+        let renewal = RenewalInfo {
+            contract_id,
+            counter_payout,
+        };
+        let offer_dlc = self.offer_contract_internal(
+            contract_input,
+            counter_party,
+            Some((renewal, funding_input, fund_output_value - counter_payout)),
+        )?;
+
+        let renew_offer = RenewOffer {
+            contract_id,
+            counter_payout,
+            offer_dlc,
+        };
+
+        self.record_message_size(&renew_offer, MessageDirection::Outgoing);
+
+        Ok((counter_party, renew_offer))
+    }
+
+    /// Returns, for each contract information of a signed contract, its ranges
+    /// of outcomes with the CET and the adaptor signature indexes enforcing
+    /// their payouts (see [`ContractInfo::get_range_payout_cets`]). The CET
+    /// indexes are relative to the CETs of the contract information, which
+    /// follow the ones of the previous contract information in the contract
+    /// transactions. Only numerical and hybrid contracts are supported.
+    pub fn get_range_payout_cets(
+        &self,
+        contract_id: &ContractId,
+    ) -> Result<Vec<Vec<RangePayoutCet>>, Error> {
+        let signed_contract = match self.get_store().get_contract(contract_id)? {
+            Some(Contract::Signed(c))
+            | Some(Contract::Confirmed(c))
+            | Some(Contract::Refunded(c))
+            | Some(Contract::RefundedDueToNoAttestation(c))
+            | Some(Contract::Renewed(c)) => c,
+            Some(Contract::Closed(c)) => c.signed_contract,
+            None => return Err(Error::InvalidParameters("Unknown contract id.".to_string())),
+            _ => return Err(Error::InvalidState),
+        };
+
+        let accepted_contract = &signed_contract.accepted_contract;
+        let offered_contract = &accepted_contract.offered_contract;
+        let adaptor_infos = self.get_adaptor_infos(accepted_contract)?;
+        let cets = &accepted_contract.dlc_transactions.cets;
+        let mut cet_offset = 0;
+        let mut res = Vec::new();
+        for (contract_info, adaptor_info) in offered_contract
+            .contract_info
+            .iter()
+            .zip(adaptor_infos.iter())
+        {
+            let nb_cets = contract_info
+                .get_payouts(offered_contract.total_collateral)?
+                .len();
+            let contract_info_cets = cets
+                .get(cet_offset..cet_offset + nb_cets)
+                .ok_or(Error::InvalidState)?;
+            res.push(contract_info.get_range_payout_cets(
+                adaptor_info,
+                offered_contract.total_collateral,
+                contract_info_cets,
+            )?);
+            cet_offset += nb_cets;
+        }
+
+        Ok(res)
+    }
+
+    /// Returns the output descriptor of the fund output of the contract with
+    /// the given id, in the `wsh(multi(2,A,B))#checksum` form, together with
+    /// the outpoint of the fund output, so that external wallets and watch-only
+    /// setups can track it. The contract must have been accepted.
+    pub fn get_funding_descriptor(
+        &self,
+        contract_id: &ContractId,
+    ) -> Result<(String, OutPoint), Error> {
+        let contract = self
+            .get_store()
+            .get_contract(contract_id)?
+            .ok_or_else(|| Error::InvalidParameters("Unknown contract id.".to_string()))?;
+        let accepted_contract = contract
+            .get_accepted_contract()
+            .ok_or(Error::InvalidState)?;
+        let dlc_transactions = &accepted_contract.dlc_transactions;
+        let descriptor = dlc::get_funding_descriptor(
+            &accepted_contract.offered_contract.offer_params.fund_pubkey,
+            &accepted_contract.accept_params.fund_pubkey,
+        );
+        let outpoint = OutPoint {
+            txid: dlc_transactions.fund.txid(),
+            vout: dlc_transactions.get_fund_output_index() as u32,
+        };
+        Ok((descriptor, outpoint))
+    }
+
+    /// Returns the fully signed fund and refund transactions of the signed
+    /// or confirmed contract with the given id, e.g. to broadcast them through
+    /// separate infrastructure. Only the accepting party, which completes the
+    /// fund transaction, can retrieve them.
+    pub fn get_signed_funding(&self, contract_id: &ContractId) -> Result<SignedFunding, Error> {
+        let contract = self
+            .get_store()
+            .get_contract(contract_id)?
+            .ok_or_else(|| Error::InvalidParameters("Unknown contract id.".to_string()))?;
+        let signed_contract = match &contract {
+            Contract::Signed(c) | Contract::Confirmed(c) => c,
+            _ => return Err(Error::InvalidState),
+        };
+        let fund = signed_contract.signed_fund_tx.clone().ok_or_else(|| {
+            Error::InvalidParameters(
+                "The fund transaction is completed by the accepting party.".to_string(),
+            )
+        })?;
+        let refund = self.get_signed_refund(signed_contract)?;
+        Ok(SignedFunding { fund, refund })
+    }
+
+    /// Provides an attestation obtained out of band for one of the events of
+    /// the signed or confirmed contract with the given id, e.g. when the API
+    /// of its oracle is unavailable. The attestation must be valid for one of
+    /// the announcements of the contract. It is used in place of the one of
+    /// the oracle to close the contract, right away if it is confirmed and
+    /// enough attestations are available, or during the following periodic
+    /// checks otherwise.
+    pub fn provide_attestation(
+        &self,
+        contract_id: &ContractId,
+        attestation: OracleAttestation,
+    ) -> Result<(), Error> {
+        self.with_contract_lock(&contract_id.0, || {
+            let contract = self
+                .get_store()
+                .get_contract(contract_id)?
+                .ok_or_else(|| Error::InvalidParameters("Unknown contract id.".to_string()))?;
+            let signed_contract = match &contract {
+                Contract::Signed(c) | Contract::Confirmed(c) => c,
+                _ => return Err(Error::InvalidState),
+            };
+
+            let announcement = signed_contract
+                .accepted_contract
+                .offered_contract
+                .contract_info
+                .iter()
+                .flat_map(|x| {
+                    let selector_announcements = match &x.contract_descriptor {
+                        ContractDescriptor::Hybrid(h) => h.selector_announcements.as_slice(),
+                        _ => &[][..],
+                    };
+                    x.oracle_announcements
+                        .iter()
+                        .chain(selector_announcements.iter())
+                })
+                .find(|x| attestation.validate(&self.secp, x).is_ok())
+                .ok_or_else(|| {
+                    Error::InvalidParameters(
+                        "Attestation is not valid for any announcement of the contract."
+                            .to_string(),
+                    )
+                })?;
+            self.provided_attestations.lock().unwrap().insert(
+                (
+                    announcement.oracle_public_key,
+                    announcement.get_event_id().to_string(),
+                ),
+                attestation,
+            );
+
+            match &contract {
+                Contract::Confirmed(c) => self.check_confirmed_contract(c),
+                _ => Ok(()),
+            }
+        })
+    }
+
+    /// Function called to reduce the position of a confirmed numerical
+    /// contract. The contract is renewed into one with a lower total
+    /// collateral created from the given contract input, the freed collateral
+    /// being paid out immediately through the change outputs of the new fund
+    /// transaction. `counter_payout` is the part of the fund output value
+    /// attributed to the counter party, as in [`Manager::renew_contract`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn reduce_contract(
+        &self,
+        contract_id: &ContractId,
+        contract_input: &ContractInput,
+        counter_payout: u64,
+    ) -> Result<(PublicKey, RenewOffer), Error> {
+        self.with_contract_lock(&contract_id.0, || {
+            self.reduce_contract_internal(contract_id, contract_input, counter_payout)
+        })
+    }
+
+    fn reduce_contract_internal(
+        &self,
+        contract_id: &ContractId,
+        contract_input: &ContractInput,
+        counter_payout: u64,
+    ) -> Result<(PublicKey, RenewOffer), Error> {
+        let signed_contract = match self.get_store().get_contract(contract_id)? {
+            Some(Contract::Confirmed(c)) => c,
+            None => return Err(Error::InvalidParameters("Unknown contract id.".to_string())),
+            _ => return Err(Error::InvalidState),
+        };
+
+        let offered_contract = &signed_contract.accepted_contract.offered_contract;
+        let is_numerical = offered_contract
+            .contract_info
+            .iter()
+            .map(|x| &x.contract_descriptor)
+            .chain(
+                contract_input
+                    .contract_infos
+                    .iter()
+                    .map(|x| &x.contract_descriptor),
+            )
+            .all(|x| matches!(x, ContractDescriptor::Numerical(_)));
+        if !is_numerical {
+            return Err(Error::InvalidParameters(
+                "Only numerical contracts can be reduced.".to_string(),
+            ));
+        }
+
+        if contract_input.offer_collateral + contract_input.accept_collateral
+            >= offered_contract.total_collateral
+        {
+            return Err(Error::InvalidParameters(
+                "Reduced contract total collateral must be lower than the current one.".to_string(),
+            ));
+        }
+
+        self.renew_contract_internal(contract_id, contract_input, counter_payout)
+    }
+
+    /// Function called to splice funds into or out of a confirmed contract.
+    /// The contract is renewed into one created from the given contract input,
+    /// with each party keeping its collateral and fee reserve from the fund
+    /// output of the current contract. Increased collaterals are funded with
+    /// additional wallet inputs, while decreased ones are returned to the
+    /// change outputs of the new fund transaction.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn splice_contract(
+        &self,
+        contract_id: &ContractId,
+        contract_input: &ContractInput,
+    ) -> Result<(PublicKey, RenewOffer), Error> {
+        self.with_contract_lock(&contract_id.0, || {
+            self.splice_contract_internal(contract_id, contract_input)
+        })
+    }
+
+    fn splice_contract_internal(
+        &self,
+        contract_id: &ContractId,
+        contract_input: &ContractInput,
+    ) -> Result<(PublicKey, RenewOffer), Error> {
+        let signed_contract = match self.get_store().get_contract(contract_id)? {
+            Some(Contract::Confirmed(c)) => c,
+            None => return Err(Error::InvalidParameters("Unknown contract id.".to_string())),
+            _ => return Err(Error::InvalidState),
+        };
+
+        let accepted_contract = &signed_contract.accepted_contract;
+        let offered_contract = &accepted_contract.offered_contract;
+        let counter_params = if offered_contract.is_offer_party {
+            &accepted_contract.accept_params
+        } else {
+            &offered_contract.offer_params
+        };
+        let (_, _, counter_cet_fee) =
+            counter_params.get_change_output_and_fees(offered_contract.fee_rate_per_vb)?;
+
+        self.renew_contract_internal(
+            contract_id,
+            contract_input,
+            counter_params.collateral + counter_cet_fee,
+        )
+    }
+
+    /// Function called to replace, in a contract offered by the local party
+    /// and not accepted yet, the announcement of the oracle event with the
+    /// same oracle and event id as `announcement` (e.g. after the oracle
+    /// rotated its nonces). The amended offer keeps the funding inputs, which
+    /// remain reserved, and the other parameters of the original one, but has
+    /// a new temporary id, under which it is stored in place of the original
+    /// offer. The history of the original offer is terminated with an
+    /// `Amended` entry referencing the new temporary id. The returned OfferDlc
+    /// message replaces the original offer on the counter party side.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn amend_offer(
+        &self,
+        temporary_id: &TemporaryContractId,
+        announcement: OracleAnnouncement,
+    ) -> Result<OfferDlc, Error> {
+        self.with_contract_lock(&temporary_id.0, || {
+            let mut offered_contract = match self
+                .get_store()
+                .get_contract_by_temporary_id(temporary_id)?
+            {
+                Some(Contract::Offered(c)) if c.is_offer_party => c,
+                None => return Err(Error::InvalidParameters("Unknown contract id.".to_string())),
+                _ => return Err(Error::InvalidState),
+            };
+            if offered_contract.renewal.is_some() {
+                return Err(Error::InvalidParameters(
+                    "Renewal offers cannot be amended.".to_string(),
+                ));
+            }
+
+            announcement.validate(&self.secp).map_err(|e| {
+                Error::InvalidParameters(format!("Invalid oracle announcement: {}", e))
+            })?;
+            check_event_id(announcement.get_event_id()).map_err(|e| {
+                Error::InvalidParameters(format!("Invalid oracle announcement: {}", e))
+            })?;
+            if announcement.get_event_maturity_epoch() > offered_contract.contract_maturity_bound {
+                return Err(Error::InvalidParameters(
+                    "Oracle event maturity is after the contract maturity bound.".to_string(),
+                ));
+            }
+
+            let mut nb_replaced = 0;
+            for contract_info in offered_contract.contract_info.iter_mut() {
+                for existing in contract_info.oracle_announcements.iter_mut() {
+                    if existing.oracle_public_key == announcement.oracle_public_key
+                        && existing.get_event_id() == announcement.get_event_id()
+                    {
+                        if existing.oracle_event.event_descriptor
+                            != announcement.oracle_event.event_descriptor
+                        {
+                            return Err(Error::InvalidParameters(
+                                "Replacement announcement has a different event descriptor."
+                                    .to_string(),
+                            ));
+                        }
+                        *existing = announcement.clone();
+                        nb_replaced += 1;
+                    }
+                }
+                contract_info.validate(offered_contract.total_collateral)?;
+            }
+            if nb_replaced == 0 {
+                return Err(Error::InvalidParameters(
+                    "No announcement of the contract matches the given oracle event.".to_string(),
+                ));
+            }
+
+            if self
+                .get_offer_message(&offered_contract)?
+                .get_temporary_contract_id()?
+                == *temporary_id
+            {
+                return Err(Error::InvalidParameters(
+                    "Announcement is identical to the one of the offer.".to_string(),
+                ));
+            }
+            let offer_msg = self.get_unused_offer_message(&mut offered_contract)?;
+
+            self.replace_offer(temporary_id, offered_contract)?;
+            self.record_message_size(&offer_msg, MessageDirection::Outgoing);
+
+            Ok(offer_msg)
+        })
+    }
+
+    /// Stores the given offered contract in place of the one with the given
+    /// temporary id, recording in the history of the latter the temporary id
+    /// of the contract replacing it.
+    fn replace_offer(
+        &self,
+        temporary_id: &TemporaryContractId,
+        offered_contract: OfferedContract,
+    ) -> Result<(), Error> {
+        let entry = ContractHistoryEntry {
+            timestamp: self.time.unix_time_now(),
+            state: "Amended".to_string(),
+            txid: None,
+            message_hash: Some(offered_contract.id.0),
+        };
+        {
+            let mut store = self.get_store();
+            store.create_contract(&offered_contract)?;
+            store.delete_contract_by_temporary_id(temporary_id)?;
+            store.add_contract_history_entry(temporary_id, &entry)?;
+        }
+        let message_hash = Some(offered_contract.id.0);
+        self.add_history_entry(&Contract::Offered(offered_contract), None, message_hash)
+    }
+
+    /// Returns the offer message for the given offered contract, setting the
+    /// temporary id of the contract from it. Contracts offered and received
+    /// are stored under their temporary ids, so an id that is already in use
+    /// (e.g. by an identical offer received from the counter party, or by a
+    /// previous version of an amended offer) is replaced by drawing a new
+    /// fund output serial id, which changes the hash of the offer.
+    fn get_unused_offer_message(
+        &self,
+        offered_contract: &mut OfferedContract,
+    ) -> Result<OfferDlc, Error> {
+        let mut offer_msg = self.get_offer_message(offered_contract)?;
+        offered_contract.id = offer_msg.get_temporary_contract_id()?;
+        while !self
+            .get_store()
+            .get_contract_history(&offered_contract.id)?
+            .is_empty()
+        {
+            offered_contract.fund_output_serial_id = get_new_serial_id();
+            offer_msg = self.get_offer_message(offered_contract)?;
+            offered_contract.id = offer_msg.get_temporary_contract_id()?;
+        }
+        Ok(offer_msg)
+    }
+
+    fn offer_contract_internal(
+        &self,
+        contract: &ContractInput,
+        counter_party: PublicKey,
+        renewal: Option<(RenewalInfo, FundingInput, u64)>,
+    ) -> Result<OfferDlc, Error> {
+        let mut contract_info = contract
+            .contract_infos
+            .iter()
+            .map(|x| self.contract_view_info_to_contract_info(x))
+            .collect::<Result<Vec<ContractInfo>, Error>>()?;
+        for info in contract_info.iter_mut() {
+            info.contract_descriptor
+                .set_exact_evaluation(contract.exact_payouts);
+        }
+        let announcements: Vec<_> = contract_info
+            .iter()
+            .flat_map(|x| {
+                let selector_announcements = match &x.contract_descriptor {
+                    ContractDescriptor::Hybrid(h) => h.selector_announcements.as_slice(),
+                    _ => &[][..],
+                };
+                x.oracle_announcements
+                    .iter()
+                    .chain(selector_announcements.iter())
+                    .cloned()
+            })
+            .collect();
+        contract.validate(&announcements)?;
+
+        let total_collateral = contract.offer_collateral + contract.accept_collateral;
+        let nb_confirmations = contract.nb_confirmations.unwrap_or(NB_CONFIRMATIONS);
+        self.check_zero_conf(&counter_party, nb_confirmations, renewal.is_some())?;
+        if let Some(payout_script_pubkey) = &contract.payout_script_pubkey {
+            validate_payout_script_pubkey(payout_script_pubkey, total_collateral)?;
+            self.watch_payout_script_pubkey(payout_script_pubkey, contract.is_payout_external)?;
+        }
+        let premium = contract.premium.as_ref().map(|x| PremiumInfo {
+            amount: x.amount,
+            paid_by_offer: x.paid_by_offer,
+            serial_id: get_new_serial_id(),
+        });
+        let common_fee =
+            get_common_fee_share(contract.fee_rate, contract.fee_split.as_ref(), true)?;
+        let (party_params, _, funding_inputs_info) = self.get_party_params(
+            contract.offer_collateral,
+            contract.fee_rate,
+            common_fee,
+            contract.payout_script_pubkey.clone(),
+            get_premium_amount(premium.as_ref(), true),
+            renewal.as_ref().map(|(_, funding_input, input_amount)| {
+                (vec![funding_input.clone()], *input_amount)
+            }),
+            contract.change_policy.as_ref(),
+        )?;
+
+        if let Some(premium) = premium.as_ref().filter(|x| !x.paid_by_offer) {
+            validate_premium(premium, &party_params.payout_script_pubkey)?;
+        }
+
+        let funding_input_proofs =
+            self.get_funding_input_proofs(&party_params.fund_pubkey, &funding_inputs_info)?;
+
+        let fund_output_serial_id = get_new_serial_id();
+        let mut offered_contract = OfferedContract {
+            id: [0u8; 32],
+            is_offer_party: true,
+            is_payout_external: contract.payout_script_pubkey.is_some()
+                && contract.is_payout_external,
+            contract_info,
+            offer_params: party_params,
+            total_collateral,
+            funding_inputs_info,
+            fund_output_serial_id,
+            fee_rate_per_vb: contract.fee_rate,
+            contract_maturity_bound: contract.maturity_time,
+            contract_timeout: contract
+                .refund_locktime
+                .unwrap_or(contract.maturity_time + REFUND_DELAY),
+            time_source: contract.time_source,
+            counter_party,
+            premium,
+            renewal: renewal.map(|(renewal, _, _)| renewal),
+            fee_split: contract.fee_split.clone(),
+            nb_confirmations,
+            funding_input_proofs,
+        };
+
+        let offer_msg = self.get_unused_offer_message(&mut offered_contract)?;
+
+        self.get_store().create_contract(&offered_contract)?;
+        self.add_history_entry(
+            &Contract::Offered(offered_contract.clone()),
+            None,
+            Some(offered_contract.id.0),
+        )?;
+
+        Ok(offer_msg)
+    }
+
+    fn on_offer_message(
+        &self,
+        offered_message: &OfferDlc,
+        counter_party: PublicKey,
+    ) -> Result<(), Error> {
+        validate_chain_hash(&offered_message.chain_hash, self.blockchain.get_network()?)?;
+        let contract: OfferedContract =
+            OfferedContract::try_from_offer_dlc(offered_message, counter_party)?;
+        self.save_received_offer(contract)
+    }
+
+    fn on_renew_offer_message(
+        &self,
+        renew_offer: &RenewOffer,
+        counter_party: PublicKey,
+    ) -> Result<(), Error> {
+        let signed_contract = match self.get_store().get_contract(&renew_offer.contract_id)? {
+            Some(Contract::Confirmed(c)) => c,
+            None => return Err(Error::InvalidParameters("Unknown contract id.".to_string())),
+            _ => return Err(Error::InvalidState),
+        };
+
+        if signed_contract
+            .accepted_contract
+            .offered_contract
+            .counter_party
+            != counter_party
+        {
+            return Err(Error::InvalidParameters(
+                "Renew offer received from a party that is not the contract counter party."
+                    .to_string(),
+            ));
+        }
+
+        let dlc_transactions = &signed_contract.accepted_contract.dlc_transactions;
+        let fund_outpoint = OutPoint {
+            txid: dlc_transactions.fund.txid(),
+            vout: dlc_transactions.get_fund_output_index() as u32,
+        };
+
+        validate_chain_hash(
+            &renew_offer.offer_dlc.chain_hash,
+            self.blockchain.get_network()?,
+        )?;
+        let mut contract: OfferedContract =
+            OfferedContract::try_from_offer_dlc(&renew_offer.offer_dlc, counter_party)?;
+
+        if !contract
+            .offer_params
+            .inputs
+            .iter()
+            .any(|x| x.outpoint == fund_outpoint)
+        {
+            return Err(Error::InvalidParameters(
+                "Renew offer must spend the fund output of the renewed contract.".to_string(),
+            ));
+        }
+
+        if contract.premium.is_some() {
+            return Err(Error::InvalidParameters(
+                "Premium is not supported when renewing a contract.".to_string(),
+            ));
+        }
+
+        if renew_offer.counter_payout > dlc_transactions.get_fund_output().value {
+            return Err(Error::InvalidParameters(
+                "Counter payout is greater than the fund output value.".to_string(),
+            ));
+        }
+
+        // The offer party must fund its collateral in the renewed contract
+        // with what remains of its inputs once the counter payout is deducted.
+        contract.offer_params.input_amount = contract
+            .offer_params
+            .input_amount
+            .checked_sub(renew_offer.counter_payout)
+            .filter(|x| *x >= contract.offer_params.collateral)
+            .ok_or_else(|| {
+                Error::InvalidParameters(
+                    "Counter payout leaves the offer party inputs below its collateral."
+                        .to_string(),
+                )
+            })?;
+        contract.renewal = Some(RenewalInfo {
+            contract_id: renew_offer.contract_id,
+            counter_payout: renew_offer.counter_payout,
+        });
+
+        self.save_received_offer(contract)
+    }
+
+    fn save_received_offer(&self, contract: OfferedContract) -> Result<(), Error> {
+        self.with_contract_lock(&contract.id.0, || {
+            if let Some(existing) = self
+                .get_store()
+                .get_contract_by_temporary_id(&contract.id)?
+            {
+                if existing.get_offered_contract().is_offer_party {
+                    return Err(Error::InvalidParameters(
+                        "Received offer has the temporary id of an offer made by this party."
+                            .to_string(),
+                    ));
+                }
+            }
+            // The temporary id of a contract being the hash of the offer, an
+            // existing history means that the offer was already received.
+            if !self
+                .get_store()
+                .get_contract_history(&contract.id)?
+                .is_empty()
+            {
+                info!(
+                    "Ignoring already received offer {}",
+                    get_contract_id_string(&contract.id)
+                );
+                return Ok(());
+            }
+            self.save_received_offer_internal(contract)
+        })
+    }
+
+    fn save_received_offer_internal(&self, contract: OfferedContract) -> Result<(), Error> {
+        let (min_nb_confirmations, max_nb_confirmations) = self.nb_confirmations_bounds;
+        if contract.nb_confirmations == 0 {
+            self.check_zero_conf(&contract.counter_party, 0, contract.renewal.is_some())?;
+        } else if contract.nb_confirmations < min_nb_confirmations
+            || contract.nb_confirmations > max_nb_confirmations
+        {
+            return Err(Error::InvalidParameters(format!(
+                "Number of confirmations {} outside of accepted bounds.",
+                contract.nb_confirmations
+            )));
+        }
+        validate_payout_script_pubkey(
+            &contract.offer_params.payout_script_pubkey,
+            contract.total_collateral,
+        )?;
+        let funding_inputs: Vec<_> = contract
+            .funding_inputs_info
+            .iter()
+            .map(|x| x.funding_input.clone())
+            .collect();
+        let renewed_fund_outpoint = match &contract.renewal {
+            Some(renewal) => Some(self.get_renewed_contract_fund_info(renewal)?.outpoint),
+            None => None,
+        };
+        self.validate_counter_party_funding_inputs(
+            &funding_inputs,
+            &contract.offer_params.fund_pubkey,
+            contract.funding_input_proofs.as_ref(),
+            renewed_fund_outpoint.as_ref(),
+        )?;
+        if let Some(premium) = contract.premium.as_ref().filter(|x| !x.paid_by_offer) {
+            validate_premium(premium, &contract.offer_params.payout_script_pubkey)?;
+        }
+        for contract_info in &contract.contract_info {
+            contract_info.validate(contract.total_collateral)?;
+        }
+        if let Some(acceptance_policy) = &self.acceptance_policy {
+            let violations = self.get_policy_violations(acceptance_policy, &contract)?;
+            if !violations.is_empty() {
+                match acceptance_policy.on_violation {
+                    PolicyViolationAction::Reject => {
+                        return Err(Error::InvalidParameters(format!(
+                            "Offer rejected by acceptance policy: {:?}",
+                            violations
+                        )))
+                    }
+                    PolicyViolationAction::Review => warn!(
+                        "Offer {} stored for review, acceptance policy violations: {:?}",
+                        get_contract_id_string(&contract.id),
+                        violations
+                    ),
+                }
+            }
+        }
+        // An offer from the same counter party spending the same inputs as a
+        // pending one amends it if it only replaces an oracle announcement.
+        let conflicting_offer = self
+            .get_store()
+            .get_contract_offers()?
+            .into_iter()
+            .find(|x| {
+                !x.is_offer_party
+                    && x.counter_party == contract.counter_party
+                    && x.renewal.is_none()
+                    && contract.renewal.is_none()
+                    && x.offer_params.inputs.iter().any(|i| {
+                        contract
+                            .offer_params
+                            .inputs
+                            .iter()
+                            .any(|j| i.outpoint == j.outpoint)
+                    })
+            });
+        match conflicting_offer {
+            Some(amended_offer) if is_amendment(&amended_offer, &contract) => {
+                self.with_contract_lock(&amended_offer.id.0, || {
+                    // The amended offer could have been accepted in between.
+                    match self
+                        .get_store()
+                        .get_contract_by_temporary_id(&amended_offer.id)?
+                    {
+                        Some(Contract::Offered(_)) => {}
+                        _ => return Err(Error::InvalidState),
+                    };
+                    info!(
+                        "Offer {} amends offer {}",
+                        get_contract_id_string(&contract.id),
+                        get_contract_id_string(&amended_offer.id)
+                    );
+                    self.replace_offer(&amended_offer.id, contract)
+                })?;
+            }
+            conflicting_offer => {
+                if let Some(max_pending_offers) = self
+                    .get_peer_policy(&contract.counter_party)
+                    .max_pending_offers
+                {
+                    let nb_pending_offers = self
+                        .get_store()
+                        .get_contracts_by_counterparty(&contract.counter_party)?
+                        .iter()
+                        .filter(|c| matches!(c, Contract::Offered(o) if !o.is_offer_party))
+                        .count();
+                    if nb_pending_offers >= max_pending_offers {
+                        return Err(Error::InvalidParameters(format!(
+                            "Maximum number of pending offers from {} reached.",
+                            contract.counter_party
+                        )));
+                    }
+                }
+                self.get_store().create_contract(&contract)?;
+                let temporary_id = contract.id;
+                self.add_history_entry(&Contract::Offered(contract), None, Some(temporary_id.0))?;
+                if let Some(conflicting_offer) = conflicting_offer {
+                    warn!(
+                        "Offer {} spends inputs of offer {}",
+                        get_contract_id_string(&temporary_id),
+                        get_contract_id_string(&conflicting_offer.id)
+                    );
+                    if let Some(event_handler) = &self.event_handler {
+                        event_handler.handle_event(Event::ConflictingOfferReceived {
+                            temporary_id,
+                            conflicting_temporary_id: conflicting_offer.id,
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Approves the received offer with the given temporary id, enabling it to
+    /// be accepted even if it requires more adaptor signatures than allowed by
+    /// the [`PeerPolicy`] of its counter party. The approval is recorded in the
+    /// history of the contract.
+    pub fn approve_offer(&self, temporary_id: &TemporaryContractId) -> Result<(), Error> {
+        self.with_contract_lock(&temporary_id.0, || {
+            match self
+                .get_store()
+                .get_contract_by_temporary_id(temporary_id)?
+            {
+                Some(Contract::Offered(o)) if !o.is_offer_party => {}
+                None => return Err(Error::InvalidParameters("Unknown contract id.".to_string())),
+                _ => return Err(Error::InvalidState),
+            };
+            let entry = ContractHistoryEntry {
+                timestamp: self.time.unix_time_now(),
+                state: APPROVED_STATE.to_string(),
+                txid: None,
+                message_hash: None,
+            };
+            self.get_store()
+                .add_contract_history_entry(temporary_id, &entry)
+        })
+    }
+
+    fn is_offer_approved(&self, temporary_id: &TemporaryContractId) -> Result<bool, Error> {
+        Ok(self
+            .get_store()
+            .get_contract_history(temporary_id)?
+            .iter()
+            .any(|x| x.state == APPROVED_STATE))
+    }
+
+    /// Returns whether accepting the given received offer requires no more
+    /// adaptor signatures than allowed by the [`PeerPolicy`] of its counter
+    /// party.
+    fn is_offer_within_adaptor_signature_budget(
+        &self,
+        offered_contract: &OfferedContract,
+    ) -> Result<bool, Error> {
+        let max_adaptor_signatures = match self
+            .get_peer_policy(&offered_contract.counter_party)
+            .max_adaptor_signatures
+        {
+            Some(max_adaptor_signatures) => max_adaptor_signatures,
+            None => return Ok(true),
+        };
+        let mut nb_adaptor_signatures: usize = 0;
+        for contract_info in &offered_contract.contract_info {
+            nb_adaptor_signatures = nb_adaptor_signatures.saturating_add(
+                contract_info.get_max_nb_adaptor_signatures(offered_contract.total_collateral)?,
+            );
+        }
+        Ok(nb_adaptor_signatures <= max_adaptor_signatures)
+    }
+
+    /// Computes the requirements of accepting the offer with the given
+    /// temporary id: collateral, UTXOs that would be selected, fees and number
+    /// of adaptor signatures. Neither the UTXOs nor new addresses are reserved
+    /// and the storage is left untouched.
+    pub fn preview_accept(
+        &self,
+        temporary_id: &TemporaryContractId,
+    ) -> Result<AcceptPreview, Error> {
+        let offered_contract = match self
+            .get_store()
+            .get_contract_by_temporary_id(temporary_id)?
+        {
+            Some(Contract::Offered(o)) if !o.is_offer_party => o,
+            None => return Err(Error::InvalidParameters("Unknown contract id.".to_string())),
+            _ => return Err(Error::InvalidState),
+        };
+
+        let total_collateral = offered_contract.total_collateral;
+        let own_collateral = total_collateral - offered_contract.offer_params.collateral;
+        let fee_rate = offered_contract.fee_rate_per_vb;
+        let premium = get_premium_amount(offered_contract.premium.as_ref(), false);
+        let common_fee =
+            get_common_fee_share(fee_rate, offered_contract.fee_split.as_ref(), false)?;
+        let required_amount = own_collateral + premium + common_fee;
+
+        // Renewal offers are funded by the payout of the renewed contract,
+        // whose fund output is spent by the input of the offering party.
+        let mut input_amount = offered_contract
+            .renewal
+            .as_ref()
+            .map(|x| x.counter_payout)
+            .unwrap_or(0);
+        let mut inputs = Vec::new();
+        let utxos = if input_amount < required_amount {
+            self.wallet.get_utxos_for_amount(
+                required_amount - input_amount,
+                Some(fee_rate),
+                false,
+            )?
+        } else {
+            Vec::new()
+        };
+        for utxo in &utxos {
+            input_amount += utxo.tx_out.value;
+            inputs.push(TxInputInfo {
+                outpoint: utxo.outpoint,
+                max_witness_len: utxo.max_witness_len.unwrap_or(107),
+                redeem_script: utxo.redeem_script.clone(),
+                serial_id: 0,
+            });
+        }
+
+        // Only the inputs and script pubkey lengths are used to compute the
+        // fees.
+        let placeholder_script = Script::from(vec![0u8; P2WPKH_SCRIPT_PUBKEY_LEN]);
+        let party_params = PartyParams {
+            fund_pubkey: offered_contract.offer_params.fund_pubkey,
+            change_script_pubkey: placeholder_script.clone(),
+            change_serial_id: 0,
+            payout_script_pubkey: placeholder_script,
+            payout_serial_id: 0,
+            inputs,
+            collateral: own_collateral,
+            input_amount,
+        };
+        let (fund_tx_fee, cet_fee) = party_params.get_fees(fee_rate);
+
+        let mut max_nb_adaptor_signatures: usize = 0;
+        for contract_info in &offered_contract.contract_info {
+            max_nb_adaptor_signatures = max_nb_adaptor_signatures
+                .saturating_add(contract_info.get_max_nb_adaptor_signatures(total_collateral)?);
+        }
+        // The signatures of the counter party are verified as well.
+        let estimated_computation_time = self.adaptor_signature_duration.saturating_mul(
+            max_nb_adaptor_signatures
+                .saturating_mul(2)
+                .min(u32::MAX as usize) as u32,
+        );
+
+        Ok(AcceptPreview {
+            own_collateral,
+            total_collateral,
+            utxos,
+            input_amount,
+            premium,
+            fund_tx_fee: fund_tx_fee + common_fee,
+            cet_fee,
+            max_nb_adaptor_signatures,
+            estimated_computation_time,
+        })
+    }
+
+    /// Function to call to accept a DLC for which an offer was received.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn accept_contract_offer(
+        &self,
+        temporary_id: &TemporaryContractId,
+    ) -> Result<(ContractId, PublicKey, AcceptDlc), Error> {
+        self.with_contract_lock(&temporary_id.0, || {
+            self.accept_contract_offer_internal(temporary_id, None, false, None)
+        })
+    }
+
+    /// Function to call to accept a DLC for which an offer was received,
+    /// applying the given policy to the change output of the fund
+    /// transaction.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn accept_contract_offer_with_change_policy(
+        &self,
+        temporary_id: &TemporaryContractId,
+        change_policy: &ChangePolicy,
+    ) -> Result<(ContractId, PublicKey, AcceptDlc), Error> {
+        self.with_contract_lock(&temporary_id.0, || {
+            self.accept_contract_offer_internal(temporary_id, None, false, Some(change_policy))
+        })
+    }
+
+    /// Function to call to accept a DLC for which an offer was received,
+    /// directing own payouts to the given script pubkey instead of a new
+    /// wallet address. The script pubkey must be of a standard type (P2PKH,
+    /// P2SH, P2WPKH, P2WSH or P2TR). If `is_payout_external` is true, the
+    /// script pubkey is considered not to be controlled by the wallet (e.g.
+    /// cold storage) and is not imported for watching.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn accept_contract_offer_with_payout_script_pubkey(
+        &self,
+        temporary_id: &TemporaryContractId,
+        payout_script_pubkey: Script,
+        is_payout_external: bool,
+    ) -> Result<(ContractId, PublicKey, AcceptDlc), Error> {
+        self.with_contract_lock(&temporary_id.0, || {
+            self.accept_contract_offer_internal(
+                temporary_id,
+                Some(payout_script_pubkey),
+                is_payout_external,
+                None,
+            )
+        })
+    }
+
+    fn accept_contract_offer_internal(
+        &self,
+        temporary_id: &TemporaryContractId,
+        payout_script_pubkey: Option<Script>,
+        is_payout_external: bool,
+        change_policy: Option<&ChangePolicy>,
+    ) -> Result<(ContractId, PublicKey, AcceptDlc), Error> {
+        let counter_party = self.get_counter_party(temporary_id)?;
+        self.with_peer_lock(&counter_party, || {
+            let pending_accept = self.prepare_accept_internal(
+                temporary_id,
+                payout_script_pubkey,
+                is_payout_external,
+                change_policy,
+            )?;
+            let outpoints = get_wallet_outpoints(&pending_accept.funding_inputs);
+            let res = self
+                .timed(Operation::AdaptorSignatureCreation, || {
+                    pending_accept.compute(&self.secp)
+                })
+                .and_then(|x| self.commit_accepted_contract_internal(x));
+            if res.is_err() {
+                self.unlock_utxos(&outpoints);
+            }
+            res
+        })
+    }
+
+    /// Selects the funding inputs and creates the transactions of a contract
+    /// for which an offer was received, without computing the adaptor
+    /// signatures. The returned [`PendingAccept`] can be computed on any
+    /// thread and the result committed with
+    /// [`Manager::commit_accepted_contract`], so that the manager remains
+    /// available while the signatures of large contracts are computed. The
+    /// funding inputs stay locked in the wallet until the acceptance is
+    /// committed or aborted with [`Manager::abort_accept`], and an offer can
+    /// only have one pending acceptance at a time.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn prepare_accept_contract_offer(
+        &self,
+        temporary_id: &TemporaryContractId,
+    ) -> Result<PendingAccept, Error> {
+        self.with_contract_lock(&temporary_id.0, || {
+            if self
+                .pending_accepts
+                .lock()
+                .unwrap()
+                .contains_key(&temporary_id.0)
+            {
+                return Err(Error::InvalidParameters(
+                    "Offer already has a pending acceptance.".to_string(),
+                ));
+            }
+            let counter_party = self.get_counter_party(temporary_id)?;
+            // The acceptance is recorded as pending before releasing the peer
+            // lock so that it counts towards the peer policy.
+            self.with_peer_lock(&counter_party, || {
+                let pending_accept =
+                    self.prepare_accept_internal(temporary_id, None, false, None)?;
+                self.pending_accepts.lock().unwrap().insert(
+                    temporary_id.0,
+                    get_wallet_outpoints(&pending_accept.funding_inputs),
+                );
+                Ok(pending_accept)
+            })
+        })
+    }
+
+    /// Stores the given contract, computed from the [`PendingAccept`] returned
+    /// by [`Manager::prepare_accept_contract_offer`], as accepted and returns
+    /// the accept message to send to the counter party. Fails if the contract
+    /// was not prepared for acceptance, and releases the funding inputs if the
+    /// offer was accepted or removed in the meantime.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn commit_accepted_contract(
+        &self,
+        accepted_contract: AcceptedContract,
+    ) -> Result<(ContractId, PublicKey, AcceptDlc), Error> {
+        let temporary_id = accepted_contract.offered_contract.id;
+        self.with_contract_lock(&temporary_id.0, || {
+            let outpoints = get_wallet_outpoints(&accepted_contract.funding_inputs);
+            {
+                let mut pending_accepts = self.pending_accepts.lock().unwrap();
+                match pending_accepts.get(&temporary_id.0) {
+                    Some(prepared) if *prepared == outpoints => {
+                        pending_accepts.remove(&temporary_id.0);
+                    }
+                    _ => {
+                        return Err(Error::InvalidParameters(
+                            "Contract does not match a pending acceptance.".to_string(),
+                        ))
+                    }
+                }
+            }
+            let res = match self
+                .get_store()
+                .get_contract_by_temporary_id(&temporary_id)?
+            {
+                Some(Contract::Offered(o)) if !o.is_offer_party => Ok(()),
+                None => Err(Error::InvalidParameters("Unknown contract id.".to_string())),
+                _ => Err(Error::InvalidState),
+            }
+            .and_then(|_| self.commit_accepted_contract_internal(accepted_contract));
+            if res.is_err() {
+                self.unlock_utxos(&outpoints);
+            }
+            res
+        })
+    }
+
+    /// Aborts the pending acceptance of the offer with the given temporary id,
+    /// prepared with [`Manager::prepare_accept_contract_offer`], unlocking its
+    /// funding inputs in the wallet.
+    pub fn abort_accept(&self, temporary_id: &TemporaryContractId) -> Result<(), Error> {
+        self.with_contract_lock(&temporary_id.0, || {
+            let outpoints = self
+                .pending_accepts
+                .lock()
+                .unwrap()
+                .remove(&temporary_id.0)
+                .ok_or_else(|| {
+                    Error::InvalidParameters("Offer has no pending acceptance.".to_string())
+                })?;
+            self.wallet.unlock_utxos(&outpoints)
+        })
+    }
+
+    /// Unlocks the given UTXOs in the wallet when the contract they were
+    /// selected for is not going further, only logging failures so as not to
+    /// hide the reason for which the contract was abandoned.
+    fn unlock_utxos(&self, outpoints: &[OutPoint]) {
+        if outpoints.is_empty() {
+            return;
+        }
+        if let Err(e) = self.wallet.unlock_utxos(outpoints) {
+            warn!("Could not unlock UTXOs {:?}: {}", outpoints, e);
+        }
+    }
+
+    fn prepare_accept_internal(
+        &self,
+        temporary_id: &TemporaryContractId,
+        payout_script_pubkey: Option<Script>,
+        is_payout_external: bool,
+        change_policy: Option<&ChangePolicy>,
+    ) -> Result<PendingAccept, Error> {
+        let contract = self
+            .get_store()
+            .get_contract_by_temporary_id(temporary_id)?;
+        let mut offered_contract = match contract {
+            Some(Contract::Offered(offered)) => offered,
+            None => return Err(Error::InvalidParameters("Unknown contract id.".to_string())),
+            _ => return Err(Error::InvalidState),
+        };
+
+        let total_collateral = offered_contract.total_collateral;
+        let accept_collateral = total_collateral - offered_contract.offer_params.collateral;
+
+        self.check_peer_policy(
+            &offered_contract.counter_party,
+            accept_collateral,
+            offered_contract.renewal.as_ref().map(|x| &x.contract_id),
+        )?;
+
+        if let Some(acceptance_policy) = self
+            .acceptance_policy
+            .as_ref()
+            .filter(|x| x.on_violation == PolicyViolationAction::Reject)
+        {
+            let violations = self.get_policy_violations(acceptance_policy, &offered_contract)?;
+            if !violations.is_empty() {
+                return Err(Error::InvalidParameters(format!(
+                    "Offer does not satisfy acceptance policy: {:?}",
+                    violations
+                )));
+            }
+        }
+
+        self.check_zero_conf(
+            &offered_contract.counter_party,
+            offered_contract.nb_confirmations,
+            offered_contract.renewal.is_some(),
+        )?;
+
+        if !self.is_offer_within_adaptor_signature_budget(&offered_contract)?
+            && !self.is_offer_approved(temporary_id)?
+        {
+            return Err(Error::InvalidParameters(
+                "Offer requires too many adaptor signatures and must be approved first."
+                    .to_string(),
+            ));
+        }
+
+        if let Some(payout_script_pubkey) = &payout_script_pubkey {
+            validate_payout_script_pubkey(payout_script_pubkey, total_collateral)?;
+            self.watch_payout_script_pubkey(payout_script_pubkey, is_payout_external)?;
+            offered_contract.is_payout_external = is_payout_external;
+        }
+
+        let common_fee = get_common_fee_share(
+            offered_contract.fee_rate_per_vb,
+            offered_contract.fee_split.as_ref(),
+            false,
+        )?;
+        let (accept_params, fund_secret_key, funding_inputs) = self.get_party_params(
+            accept_collateral,
+            offered_contract.fee_rate_per_vb,
+            common_fee,
+            payout_script_pubkey,
+            get_premium_amount(offered_contract.premium.as_ref(), false),
+            offered_contract
+                .renewal
+                .as_ref()
+                .map(|x| (Vec::new(), x.counter_payout)),
+            change_policy,
+        )?;
+
+        let res =
+            self.create_accept_transactions(&offered_contract, &accept_params, &funding_inputs);
+        let (dlc_transactions, funding_input_proofs) = match res {
+            Ok(res) => res,
+            Err(e) => {
+                self.unlock_utxos(&get_wallet_outpoints(&funding_inputs));
+                return Err(e);
+            }
+        };
+
+        Ok(PendingAccept {
+            offered_contract,
             accept_params,
+            fund_secret_key,
             funding_inputs,
+            funding_input_proofs,
             dlc_transactions,
-            accept_refund_signature: refund_signature,
-        };
+        })
+    }
 
-        let accept_msg: AcceptDlc = (&accepted_contract).into();
+    /// Creates the transactions of a contract being accepted with the given
+    /// parameters, returning them with the proofs of the funding inputs.
+    fn create_accept_transactions(
+        &self,
+        offered_contract: &OfferedContract,
+        accept_params: &PartyParams,
+        funding_inputs: &[FundingInputInfo],
+    ) -> Result<(DlcTransactions, Option<FundingInputProofs>), Error> {
+        let total_collateral = offered_contract.total_collateral;
+        if let Some(premium) = offered_contract
+            .premium
+            .as_ref()
+            .filter(|x| x.paid_by_offer)
+        {
+            validate_premium(premium, &accept_params.payout_script_pubkey)?;
+        }
+
+        let dlc_transactions = dlc::create_dlc_transactions_with_fee_split(
+            &offered_contract.offer_params,
+            accept_params,
+            &offered_contract.contract_info[0].get_payouts(total_collateral)?,
+            offered_contract.contract_timeout,
+            offered_contract.fee_rate_per_vb,
+            0,
+            offered_contract.contract_maturity_bound,
+            offered_contract.fund_output_serial_id,
+            offered_contract.premium.as_ref(),
+            offered_contract.fee_split.as_ref(),
+        )?;
+
+        self.wallet.import_address(&Address::p2wsh(
+            &dlc_transactions.funding_script_pubkey,
+            self.blockchain.get_network()?,
+        ))?;
 
-        // Drop own adaptor signatures as no point keeping them.
-        accepted_contract.adaptor_signatures = None;
+        let funding_input_proofs =
+            self.get_funding_input_proofs(&accept_params.fund_pubkey, funding_inputs)?;
+
+        Ok((dlc_transactions, funding_input_proofs))
+    }
+
+    fn commit_accepted_contract_internal(
+        &self,
+        accepted_contract: AcceptedContract,
+    ) -> Result<(ContractId, PublicKey, AcceptDlc), Error> {
+        let counter_party = accepted_contract.offered_contract.counter_party;
+
+        let accept_msg: AcceptDlc = (&accepted_contract).into();
 
         let contract_id = accepted_contract.get_contract_id();
 
-        self.store
-            .update_contract(&Contract::Accepted(accepted_contract))?;
+        self.update_contract(
+            &Contract::Accepted(accepted_contract),
+            None,
+            Some(get_message_hash(&accept_msg)),
+        )?;
+
+        self.record_message_size(&accept_msg, MessageDirection::Outgoing);
 
         Ok((contract_id, counter_party, accept_msg))
     }
 
-    fn on_accept_message(&mut self, accept_msg: &AcceptDlc) -> Result<DlcMessage, Error> {
-        let contract = self.store.get_contract(&accept_msg.temporary_contract_id)?;
+    fn on_accept_message(
+        &self,
+        accept_msg: &AcceptDlc,
+        counter_party: &PublicKey,
+    ) -> Result<DlcMessage, Error> {
+        let contract = self
+            .get_store()
+            .get_contract_by_temporary_id(&accept_msg.temporary_contract_id)?;
 
         let offered_contract = match contract {
+            // Offers received from the counter party are stored alongside the
+            // ones sent to it, only the latter can be accepted by it.
+            Some(Contract::Offered(offered))
+                if !offered.is_offer_party || offered.counter_party != *counter_party =>
+            {
+                return Err(Error::InvalidParameters(
+                    "Accept message for a contract that was not offered to the sender.".to_string(),
+                ))
+            }
             Some(Contract::Offered(offered)) => offered,
-            None => return Err(Error::InvalidParameters("Unknown contract id.".to_string())),
+            None => {
+                // The counter party did not receive our sign message and sent
+                // its accept message again.
+                if let Some(sign_msg) = self.get_sent_sign_message(accept_msg)? {
+                    info!(
+                        "Sending again sign message of contract {}",
+                        get_contract_id_string(&sign_msg.contract_id)
+                    );
+                    return Ok(DlcMessage::Sign(sign_msg));
+                }
+                return Err(Error::InvalidParameters("Unknown contract id.".to_string()));
+            }
             _ => return Err(Error::InvalidState),
         };
 
+        self.validate_counter_party_funding_inputs(
+            &accept_msg.funding_inputs,
+            &accept_msg.funding_pubkey,
+            accept_msg.funding_input_proofs.as_ref(),
+            None,
+        )?;
+
         let (tx_input_infos, input_amount) = get_tx_input_infos(&accept_msg.funding_inputs)?;
 
         let accept_params = PartyParams {
@@ -387,22 +2828,57 @@ where
             payout_script_pubkey: accept_msg.payout_spk.clone(),
             payout_serial_id: accept_msg.payout_serial_id,
             inputs: tx_input_infos,
-            input_amount,
+            input_amount: input_amount
+                + offered_contract
+                    .renewal
+                    .as_ref()
+                    .map_or(0, |x| x.counter_payout),
             collateral: accept_msg.accept_collateral,
         };
 
-        let total_collateral =
-            offered_contract.offer_params.collateral + accept_msg.accept_collateral;
+        let total_collateral = offered_contract.total_collateral;
+        let expected_accept_collateral =
+            total_collateral - offered_contract.offer_params.collateral;
+        if accept_msg.accept_collateral != expected_accept_collateral {
+            return self.accept_fail_on_error(
+                &offered_contract,
+                accept_msg,
+                Err(Error::InvalidParameters(format!(
+                    "Accept collateral {} does not match the offered one {}.",
+                    accept_msg.accept_collateral, expected_accept_collateral
+                ))),
+            );
+        }
+
+        self.accept_fail_on_error(
+            &offered_contract,
+            accept_msg,
+            validate_payout_script_pubkey(&accept_params.payout_script_pubkey, total_collateral),
+        )?;
+
+        if let Some(premium) = offered_contract
+            .premium
+            .as_ref()
+            .filter(|x| x.paid_by_offer)
+        {
+            self.accept_fail_on_error(
+                &offered_contract,
+                accept_msg,
+                validate_premium(premium, &accept_params.payout_script_pubkey),
+            )?;
+        }
 
-        let dlc_transactions = dlc::create_dlc_transactions(
+        let mut dlc_transactions = dlc::create_dlc_transactions_with_fee_split(
             &offered_contract.offer_params,
             &accept_params,
-            &offered_contract.contract_info[0].get_payouts(total_collateral),
+            &offered_contract.contract_info[0].get_payouts(total_collateral)?,
             offered_contract.contract_timeout,
             offered_contract.fee_rate_per_vb,
             0,
             offered_contract.contract_maturity_bound,
             offered_contract.fund_output_serial_id,
+            offered_contract.premium.as_ref(),
+            offered_contract.fee_split.as_ref(),
         )?;
 
         self.wallet.import_address(&Address::p2wsh(
@@ -412,23 +2888,19 @@ where
 
         let fund_output_value = dlc_transactions.get_fund_output().value;
 
-        let DlcTransactions {
-            mut fund,
-            mut cets,
-            refund,
-            funding_script_pubkey,
-        } = dlc_transactions;
-
-        let refund_verify_result = dlc::verify_tx_input_sig(
-            &self.secp,
-            &accept_msg.refund_signature,
-            &refund,
-            0,
-            &funding_script_pubkey,
-            fund_output_value,
-            &accept_params.fund_pubkey,
-        )
-        .map_err(|e| e.into());
+        let refund_verify_result = self
+            .timed(Operation::RefundSignatureVerification, || {
+                dlc::verify_tx_input_sig(
+                    &self.secp,
+                    &accept_msg.refund_signature,
+                    &dlc_transactions.refund,
+                    0,
+                    &dlc_transactions.funding_script_pubkey,
+                    fund_output_value,
+                    &accept_params.fund_pubkey,
+                )
+            })
+            .map_err(|e| e.into());
 
         self.accept_fail_on_error(&offered_contract, accept_msg, refund_verify_result)?;
 
@@ -439,76 +2911,40 @@ where
             .map(|x| x.signature)
             .collect();
 
-        let adaptor_verify_result = offered_contract.contract_info[0].verify_and_get_adaptor_info(
-            &self.secp,
-            offered_contract.total_collateral,
-            &accept_params.fund_pubkey,
-            &funding_script_pubkey,
-            fund_output_value,
-            &cets,
-            &adaptor_signatures,
-            0,
-        );
-
-        let (adaptor_info, mut adaptor_index) =
-            self.accept_fail_on_error(&offered_contract, accept_msg, adaptor_verify_result)?;
-
-        let mut adaptor_infos = vec![adaptor_info];
-
-        let cet_input = cets[0].input[0].clone();
-
-        for contract_info in offered_contract.contract_info.iter().skip(1) {
-            let payouts = contract_info.get_payouts(total_collateral);
-
-            let tmp_cets = dlc::create_cets(
-                &cet_input,
-                &offered_contract.offer_params.payout_script_pubkey,
-                offered_contract.offer_params.payout_serial_id,
-                &accept_params.payout_script_pubkey,
-                accept_params.payout_serial_id,
-                &payouts,
-                0,
-            );
-
-            let (adaptor_info, tmp_adaptor_index) = contract_info.verify_and_get_adaptor_info(
+        let adaptor_verify_result = self.timed(Operation::AdaptorSignatureVerification, || {
+            contract_tool::verify_cets(
                 &self.secp,
-                offered_contract.total_collateral,
-                &accept_params.fund_pubkey,
-                &funding_script_pubkey,
-                fund_output_value,
-                &tmp_cets,
+                &offered_contract,
+                &accept_params,
+                &dlc_transactions,
                 &adaptor_signatures,
-                adaptor_index,
-            )?;
-
-            adaptor_index = tmp_adaptor_index;
-
-            cets.extend(tmp_cets);
+            )
+        });
 
-            adaptor_infos.push(adaptor_info);
-        }
-
-        let mut own_signatures: Vec<EcdsaAdaptorSignature> = Vec::new();
+        let (adaptor_infos, cets) =
+            self.accept_fail_on_error(&offered_contract, accept_msg, adaptor_verify_result)?;
+        dlc_transactions.cets = cets;
 
         let fund_privkey = self
             .wallet
             .get_secret_key_for_pubkey(&offered_contract.offer_params.fund_pubkey)?;
 
-        for (contract_info, adaptor_info) in offered_contract
-            .contract_info
-            .iter()
-            .zip(adaptor_infos.iter())
-        {
-            let sigs = contract_info.get_adaptor_signatures(
+        let own_signatures = self.timed(Operation::AdaptorSignatureCreation, || {
+            contract_tool::sign_cets_with_adaptor_infos(
                 &self.secp,
-                adaptor_info,
+                &offered_contract.contract_info,
+                &adaptor_infos,
+                &dlc_transactions,
                 &fund_privkey,
-                &funding_script_pubkey,
-                fund_output_value,
-                &cets,
-            )?;
-            own_signatures.extend(sigs);
-        }
+            )
+        })?;
+
+        let DlcTransactions {
+            mut fund,
+            cets,
+            refund,
+            funding_script_pubkey,
+        } = dlc_transactions;
 
         let mut input_serial_ids: Vec<_> = offered_contract
             .funding_inputs_info
@@ -519,6 +2955,11 @@ where
             .collect();
         input_serial_ids.sort_unstable();
 
+        let renewed_fund_info = match &offered_contract.renewal {
+            Some(renewal) => Some(self.get_renewed_contract_fund_info(renewal)?),
+            None => None,
+        };
+
         // Vec<Witness>
         let witnesses: Vec<Vec<Vec<u8>>> = offered_contract
             .funding_inputs_info
@@ -528,22 +2969,23 @@ where
                     .iter()
                     .position(|y| y == &x.funding_input.input_serial_id)
                     .ok_or(Error::InvalidState)?;
-                let tx =
-                    Transaction::consensus_decode(&*x.funding_input.prev_tx).map_err(|_| {
-                        Error::InvalidParameters(
-                            "Could not decode funding input previous tx parameter".to_string(),
-                        )
-                    })?;
-                let vout = x.funding_input.prev_tx_vout;
-                let tx_out = tx.output.get(vout as usize).ok_or_else(|| {
-                    Error::InvalidParameters(format!(
-                        "Previous tx output not found at index {}",
-                        vout
-                    ))
-                })?;
-
-                self.wallet
-                    .sign_tx_input(&mut fund, input_index, tx_out, None)?;
+                // The fund output of a renewed contract requires signatures
+                // from both parties, only the raw signature is sent.
+                if let Some(info) = renewed_fund_info
+                    .as_ref()
+                    .filter(|y| y.outpoint == fund.input[input_index].previous_output)
+                {
+                    let sig = dlc::util::get_raw_sig_for_tx_input(
+                        &self.secp,
+                        &fund,
+                        input_index,
+                        &info.funding_script_pubkey,
+                        info.fund_output_value,
+                        &info.fund_secret_key,
+                    );
+                    return Ok(vec![sig.serialize_der().to_vec()]);
+                }
+                self.sign_funding_input(&mut fund, input_index, x)?;
 
                 Ok(fund.input[input_index].witness.clone())
             })
@@ -582,50 +3024,112 @@ where
             offered_contract,
             accept_params,
             funding_inputs: accept_msg.funding_inputs.iter().map(|x| x.into()).collect(),
+            funding_input_proofs: accept_msg.funding_input_proofs.clone(),
             adaptor_infos,
             adaptor_signatures: Some(adaptor_signatures),
             accept_refund_signature: accept_msg.refund_signature,
             dlc_transactions,
         };
 
-        let mut signed_contract = SignedContract {
+        let signed_contract = SignedContract {
             accepted_contract,
             adaptor_signatures: Some(own_signatures),
             offer_refund_signature,
             funding_signatures: FundingSignatures { funding_signatures },
+            signed_fund_tx: None,
         };
 
         let signed_msg: SignDlc = (&signed_contract).into();
 
-        // Drop own adaptor signatures as no point keeping them.
-        signed_contract.adaptor_signatures = None;
-
-        self.store
-            .update_contract(&Contract::Signed(signed_contract))?;
+        let fund_txid = signed_contract
+            .accepted_contract
+            .dlc_transactions
+            .fund
+            .txid();
+        self.update_contract(
+            &Contract::Signed(signed_contract),
+            Some(fund_txid),
+            Some(get_message_hash(&signed_msg)),
+        )?;
 
         Ok(DlcMessage::Sign(signed_msg))
     }
 
-    fn on_sign_message(&mut self, sign_message: &SignDlc) -> Result<(), Error> {
-        let contract = self.store.get_contract(&sign_message.contract_id)?;
+    /// Returns the sign message that was sent in response to the given accept
+    /// message if it was already processed and the fund transaction is not yet
+    /// confirmed.
+    fn get_sent_sign_message(&self, accept_msg: &AcceptDlc) -> Result<Option<SignDlc>, Error> {
+        let signed_contracts = self.get_store().get_signed_contracts()?;
+        Ok(signed_contracts
+            .iter()
+            .find(|c| {
+                let offered_contract = &c.accepted_contract.offered_contract;
+                offered_contract.id == accept_msg.temporary_contract_id
+                    && offered_contract.is_offer_party
+                    && c.adaptor_signatures.is_some()
+                    && c.accepted_contract.accept_refund_signature == accept_msg.refund_signature
+            })
+            .map(|c| c.into()))
+    }
+
+    fn on_sign_message(
+        &self,
+        sign_message: &SignDlc,
+        counter_party: &PublicKey,
+    ) -> Result<(), Error> {
+        let contract = self.get_store().get_contract(&sign_message.contract_id)?;
         let accepted_contract = match contract {
+            Some(Contract::Accepted(accepted))
+                if accepted.offered_contract.is_offer_party
+                    || accepted.offered_contract.counter_party != *counter_party =>
+            {
+                return Err(Error::InvalidParameters(
+                    "Sign message for a contract that was not accepted from the sender."
+                        .to_string(),
+                ))
+            }
             Some(Contract::Accepted(accepted)) => accepted,
             None => return Err(Error::InvalidParameters("Unknown contract id.".to_string())),
-            _ => return Err(Error::InvalidState),
+            Some(Contract::FailedSign(_)) => return Err(Error::InvalidState),
+            Some(c) => {
+                let message_hash = get_message_hash(sign_message);
+                let is_processed = self
+                    .get_store()
+                    .get_contract_history(&c.get_temporary_id())?
+                    .iter()
+                    .any(|x| x.message_hash == Some(message_hash));
+                if is_processed {
+                    info!(
+                        "Ignoring already processed sign message of contract {}",
+                        get_contract_id_string(&sign_message.contract_id)
+                    );
+                    return Ok(());
+                }
+                return Err(Error::InvalidState);
+            }
         };
 
+        if accepted_contract.get_contract_id() != sign_message.contract_id {
+            return Err(Error::InvalidParameters(
+                "Sign message references an unexpected contract id.".to_string(),
+            ));
+        }
+
         let offered_contract = &accepted_contract.offered_contract;
 
-        let verify_result = dlc::verify_tx_input_sig(
-            &self.secp,
-            &sign_message.refund_signature,
-            &accepted_contract.dlc_transactions.refund,
-            0,
-            &accepted_contract.dlc_transactions.funding_script_pubkey,
-            accepted_contract.dlc_transactions.get_fund_output().value,
-            &offered_contract.offer_params.fund_pubkey,
-        )
-        .map_err(|e| e.into());
+        let verify_result = self
+            .timed(Operation::RefundSignatureVerification, || {
+                dlc::verify_tx_input_sig(
+                    &self.secp,
+                    &sign_message.refund_signature,
+                    &accepted_contract.dlc_transactions.refund,
+                    0,
+                    &accepted_contract.dlc_transactions.funding_script_pubkey,
+                    accepted_contract.dlc_transactions.get_fund_output().value,
+                    &offered_contract.offer_params.fund_pubkey,
+                )
+            })
+            .map_err(|e| e.into());
 
         self.sign_fail_on_error(&accepted_contract, sign_message, verify_result)?;
 
@@ -636,27 +3140,19 @@ where
             .map(|x| x.signature)
             .collect();
 
-        let mut adaptor_sig_start = 0;
-
-        for (adaptor_info, contract_info) in accepted_contract
-            .adaptor_infos
-            .iter()
-            .zip(offered_contract.contract_info.iter())
-        {
-            let adaptor_verify_result = contract_info.verify_adaptor_info(
+        let adaptor_infos = self.get_adaptor_infos(&accepted_contract)?;
+        let adaptor_verify_result = self.timed(Operation::AdaptorSignatureVerification, || {
+            contract_tool::verify_cets_with_adaptor_infos(
                 &self.secp,
+                &offered_contract.contract_info,
+                &adaptor_infos,
+                &accepted_contract.dlc_transactions,
                 &offered_contract.offer_params.fund_pubkey,
-                &accepted_contract.dlc_transactions.funding_script_pubkey,
-                accepted_contract.dlc_transactions.get_fund_output().value,
-                &accepted_contract.dlc_transactions.cets,
                 &adaptor_signatures,
-                adaptor_sig_start,
-                adaptor_info,
-            );
+            )
+        });
 
-            adaptor_sig_start =
-                self.sign_fail_on_error(&accepted_contract, sign_message, adaptor_verify_result)?;
-        }
+        self.sign_fail_on_error(&accepted_contract, sign_message, adaptor_verify_result)?;
 
         let mut input_serials: Vec<_> = offered_contract
             .funding_inputs_info
@@ -668,6 +3164,11 @@ where
 
         let mut fund_tx = accepted_contract.dlc_transactions.fund.clone();
 
+        let renewed_fund_info = match &offered_contract.renewal {
+            Some(renewal) => Some(self.get_renewed_contract_fund_info(renewal)?),
+            None => None,
+        };
+
         for (funding_input, funding_signatures) in offered_contract
             .funding_inputs_info
             .iter()
@@ -678,6 +3179,45 @@ where
                 .position(|x| x == &funding_input.funding_input.input_serial_id)
                 .ok_or(Error::InvalidState)?;
 
+            if let Some(info) = renewed_fund_info
+                .as_ref()
+                .filter(|x| x.outpoint == fund_tx.input[input_index].previous_output)
+            {
+                let sig_result = funding_signatures
+                    .witness_elements
+                    .get(0)
+                    .and_then(|x| Signature::from_der(&x.witness).ok())
+                    .ok_or_else(|| {
+                        Error::InvalidParameters(
+                            "Invalid renewed contract fund output signature.".to_string(),
+                        )
+                    })
+                    .and_then(|sig| {
+                        dlc::verify_tx_input_sig(
+                            &self.secp,
+                            &sig,
+                            &fund_tx,
+                            input_index,
+                            &info.funding_script_pubkey,
+                            info.fund_output_value,
+                            &info.other_fund_pubkey,
+                        )?;
+                        Ok(sig)
+                    });
+                let sig = self.sign_fail_on_error(&accepted_contract, sign_message, sig_result)?;
+                dlc::util::sign_multi_sig_input(
+                    &self.secp,
+                    &mut fund_tx,
+                    &sig,
+                    &info.other_fund_pubkey,
+                    &info.fund_secret_key,
+                    &info.funding_script_pubkey,
+                    info.fund_output_value,
+                    input_index,
+                );
+                continue;
+            }
+
             fund_tx.input[input_index].witness = funding_signatures
                 .witness_elements
                 .iter()
@@ -690,19 +3230,7 @@ where
                 .iter()
                 .position(|x| x == &funding_input_info.funding_input.input_serial_id)
                 .ok_or(Error::InvalidState)?;
-            let tx = Transaction::consensus_decode(&*funding_input_info.funding_input.prev_tx)
-                .map_err(|_| {
-                    Error::InvalidParameters(
-                        "Could not decode funding input previous tx parameter".to_string(),
-                    )
-                })?;
-            let vout = funding_input_info.funding_input.prev_tx_vout;
-            let tx_out = tx.output.get(vout as usize).ok_or_else(|| {
-                Error::InvalidParameters(format!("Previous tx output not found at index {}", vout))
-            })?;
-
-            self.wallet
-                .sign_tx_input(&mut fund_tx, input_index, tx_out, None)?;
+            self.sign_funding_input(&mut fund_tx, input_index, funding_input_info)?;
         }
 
         let signed_contract = SignedContract {
@@ -710,31 +3238,66 @@ where
             adaptor_signatures: Some(adaptor_signatures),
             offer_refund_signature: sign_message.refund_signature,
             funding_signatures: sign_message.funding_signatures.clone(),
+            signed_fund_tx: Some(fund_tx.clone()),
         };
 
-        self.store
-            .update_contract(&Contract::Signed(signed_contract))?;
+        let contract_id = signed_contract.accepted_contract.get_contract_id_string();
+
+        self.update_contract(
+            &Contract::Signed(signed_contract),
+            Some(fund_tx.txid()),
+            Some(get_message_hash(sign_message)),
+        )?;
+
+        if !self.broadcast_fund_tx {
+            info!(
+                "Contract {} signed, fund transaction to be broadcast externally",
+                contract_id
+            );
+            return Ok(());
+        }
+
+        self.broadcast(&sign_message.contract_id, BroadcastKind::Fund, &fund_tx)
+            .map_err(|e| {
+                error!(
+                    "Could not broadcast fund transaction {} of contract {}: {}",
+                    fund_tx.txid(),
+                    contract_id,
+                    e
+                );
+                e
+            })?;
 
-        self.blockchain.send_transaction(&fund_tx)?;
+        info!(
+            "Contract {} signed and fund transaction broadcast",
+            contract_id
+        );
 
         Ok(())
     }
 
     fn sign_fail_on_error<R>(
-        &mut self,
+        &self,
         accepted_contract: &AcceptedContract,
         sign_message: &SignDlc,
         result: Result<R, Error>,
     ) -> Result<R, Error> {
         match result {
             Err(e) => {
-                error!("Error in on_sign {}", e);
-                self.store
-                    .update_contract(&Contract::FailedSign(FailedSignContract {
+                error!(
+                    "Error in on_sign for contract {}: {}",
+                    accepted_contract.get_contract_id_string(),
+                    e
+                );
+                self.update_contract(
+                    &Contract::FailedSign(FailedSignContract {
                         accepted_contract: accepted_contract.clone(),
                         sign_message: sign_message.clone(),
                         error_message: e.to_string(),
-                    }))?;
+                    }),
+                    None,
+                    Some(get_message_hash(sign_message)),
+                )?;
                 Err(e)
             }
             Ok(val) => Ok(val),
@@ -742,49 +3305,560 @@ where
     }
 
     fn accept_fail_on_error<R>(
-        &mut self,
+        &self,
         offered_contract: &OfferedContract,
         accept_message: &AcceptDlc,
         result: Result<R, Error>,
     ) -> Result<R, Error> {
         match result {
             Err(e) => {
-                error!("Error in on_accept {}", e);
-                self.store
-                    .update_contract(&Contract::FailedAccept(FailedAcceptContract {
+                error!(
+                    "Error in on_accept for contract {}: {}",
+                    get_contract_id_string(&offered_contract.id),
+                    e
+                );
+                self.update_contract(
+                    &Contract::FailedAccept(FailedAcceptContract {
                         offered_contract: offered_contract.clone(),
                         accept_message: accept_message.clone(),
                         error_message: e.to_string(),
-                    }))?;
+                    }),
+                    None,
+                    Some(get_message_hash(accept_message)),
+                )?;
                 Err(e)
             }
             Ok(val) => Ok(val),
         }
     }
 
-    /// Function to call to check the state of the currently executing DLCs and
-    /// update them if possible.
-    pub fn periodic_check(&mut self) -> Result<(), Error> {
-        self.check_signed_contracts()?;
-        self.check_confirmed_contracts()?;
+    /// Returns the messages to send to the given counter party after
+    /// reconnecting to it, one for each contract with it whose setup might not
+    /// have completed. When processing them, the counter party sends again the
+    /// messages that were lost during the disconnection, or replies with its
+    /// own state if this party is the one that needs to send them again.
+    pub fn get_reestablish_messages(
+        &self,
+        counter_party: &PublicKey,
+    ) -> Result<Vec<ReestablishDlc>, Error> {
+        let mut messages = Vec::new();
+        for state in &[
+            ContractState::Offered,
+            ContractState::Accepted,
+            ContractState::Signed,
+        ] {
+            self.for_each_contract_in_state(*state, |contract| {
+                if contract.get_offered_contract().counter_party == *counter_party {
+                    messages.extend(get_reestablish_message(&contract));
+                }
+                true
+            })?;
+        }
+
+        Ok(messages)
+    }
+
+    fn on_reestablish_message(
+        &self,
+        reestablish: &ReestablishDlc,
+        counter_party: PublicKey,
+    ) -> Result<Option<DlcMessage>, Error> {
+        // The contract is looked up again once locked, as its state may have
+        // changed in the meantime. Contracts that were accepted are locked
+        // using their contract id, as when processing the sign message.
+        let lock_id = match self.get_reestablished_contract(reestablish)? {
+            Some(contract) => contract
+                .get_id()
+                .map(|x| x.0)
+                .unwrap_or_else(|| contract.get_temporary_id().0),
+            None => reestablish.temporary_contract_id.0,
+        };
+        self.with_contract_lock(&lock_id, || {
+            self.on_reestablish_message_internal(reestablish, counter_party)
+        })
+    }
+
+    fn on_reestablish_message_internal(
+        &self,
+        reestablish: &ReestablishDlc,
+        counter_party: PublicKey,
+    ) -> Result<Option<DlcMessage>, Error> {
+        let contract = match self.get_reestablished_contract(reestablish)? {
+            Some(contract) => contract,
+            None => {
+                let history = self
+                    .get_store()
+                    .get_contract_history(&reestablish.temporary_contract_id)?;
+                if !history.is_empty() || reestablish.state == ReestablishState::Unknown {
+                    return Ok(None);
+                }
+                return Ok(Some(DlcMessage::Reestablish(ReestablishDlc {
+                    temporary_contract_id: reestablish.temporary_contract_id,
+                    contract_id: None,
+                    state: ReestablishState::Unknown,
+                })));
+            }
+        };
+
+        if contract.get_offered_contract().counter_party != counter_party {
+            return Err(Error::InvalidParameters(
+                "Reestablish message received from a party that is not the contract counter party."
+                    .to_string(),
+            ));
+        }
+
+        let own_message = match get_reestablish_message(&contract) {
+            Some(own_message) => own_message,
+            None => return Ok(None),
+        };
+
+        // The counter party is ahead and will send again its last message upon
+        // receiving our state.
+        if own_message.state < reestablish.state {
+            return Ok(Some(DlcMessage::Reestablish(own_message)));
+        }
+
+        let msg = match (&contract, reestablish.state) {
+            (Contract::Offered(o), ReestablishState::Unknown) if o.is_offer_party => {
+                let offer_dlc = self.get_offer_message(o)?;
+                match &o.renewal {
+                    Some(renewal) => Some(DlcMessage::RenewOffer(RenewOffer {
+                        contract_id: renewal.contract_id,
+                        counter_payout: renewal.counter_payout,
+                        offer_dlc,
+                    })),
+                    None => Some(DlcMessage::Offer(offer_dlc)),
+                }
+            }
+            (Contract::Accepted(a), ReestablishState::Offered)
+                if !a.offered_contract.is_offer_party && a.adaptor_signatures.is_some() =>
+            {
+                Some(DlcMessage::Accept(a.into()))
+            }
+            (Contract::Signed(s), ReestablishState::Accepted)
+            | (Contract::Confirmed(s), ReestablishState::Accepted)
+                if s.accepted_contract.offered_contract.is_offer_party
+                    && s.adaptor_signatures.is_some() =>
+            {
+                Some(DlcMessage::Sign(s.into()))
+            }
+            _ => None,
+        };
+
+        if let Some(msg) = &msg {
+            info!(
+                "Sending again {} message of contract {}",
+                get_message_name(msg),
+                get_contract_id_string(&reestablish.temporary_contract_id)
+            );
+        }
+
+        Ok(msg)
+    }
+
+    /// Returns the contract referred to by the given reestablish message.
+    /// Contracts that were accepted being stored under their contract id, they
+    /// are looked up among accepted, signed and confirmed contracts when the
+    /// counter party does not know the contract id yet.
+    fn get_reestablished_contract(
+        &self,
+        reestablish: &ReestablishDlc,
+    ) -> Result<Option<Contract>, Error> {
+        if let Some(contract_id) = &reestablish.contract_id {
+            let contract = self.get_store().get_contract(contract_id)?;
+            if contract.is_some() {
+                return Ok(contract);
+            }
+        }
+
+        let contract = self
+            .get_store()
+            .get_contract_by_temporary_id(&reestablish.temporary_contract_id)?;
+        if contract.is_some() {
+            return Ok(contract);
+        }
+
+        let mut res = None;
+        for state in &[
+            ContractState::Accepted,
+            ContractState::Signed,
+            ContractState::Confirmed,
+        ] {
+            self.for_each_contract_in_state(*state, |contract| {
+                if contract.get_temporary_id() == reestablish.temporary_contract_id {
+                    res = Some(contract);
+                    return false;
+                }
+                true
+            })?;
+            if res.is_some() {
+                break;
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Calls `f` on each contract in the given state, retrieving them from the
+    /// store one page at a time, until `f` returns false.
+    fn for_each_contract_in_state<F: FnMut(Contract) -> bool>(
+        &self,
+        state: ContractState,
+        mut f: F,
+    ) -> Result<(), Error> {
+        let mut offset = 0;
+        loop {
+            let contracts =
+                self.get_store()
+                    .get_contracts_by_state(state, offset, CONTRACT_PAGE_SIZE)?;
+            let nb_contracts = contracts.len();
+            for contract in contracts {
+                if !f(contract) {
+                    return Ok(());
+                }
+            }
+            if nb_contracts < CONTRACT_PAGE_SIZE {
+                return Ok(());
+            }
+            offset += CONTRACT_PAGE_SIZE;
+        }
+    }
+
+    /// Function to call to check the state of the currently executing DLCs and
+    /// update them if possible.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn periodic_check(&self) -> Result<(), Error> {
+        self.check_signed_contracts()?;
+        self.check_confirmed_contracts()?;
+        self.check_closing_transactions()?;
+        self.process_pending_broadcasts()?;
+        self.notify_upcoming_maturities()?;
+        self.record_contract_counts()?;
+
+        Ok(())
+    }
+
+    /// Returns the ids and maturity times of the signed and confirmed
+    /// contracts maturing within `window` seconds, ordered by maturity. The
+    /// current time is evaluated using the time source of each contract.
+    pub fn upcoming_maturities(&self, window: u64) -> Result<Vec<(ContractId, u32)>, Error> {
+        let mut contracts = Vec::new();
+        for state in &[ContractState::Signed, ContractState::Confirmed] {
+            self.for_each_contract_in_state(*state, |contract| {
+                contracts.push(contract);
+                true
+            })?;
+        }
+
+        let mut upcoming = Vec::new();
+        for contract in contracts {
+            let contract_id = match contract.get_id() {
+                Some(contract_id) => contract_id,
+                None => continue,
+            };
+            let offered_contract = contract.get_offered_contract();
+            let maturity = offered_contract.contract_maturity_bound;
+            let now = self.get_time(offered_contract.time_source)?;
+            if (maturity as u64) > now && (maturity as u64) <= now.saturating_add(window) {
+                upcoming.push((contract_id, maturity));
+            }
+        }
+        upcoming.sort_by_key(|(_, maturity)| *maturity);
+
+        Ok(upcoming)
+    }
+
+    /// Reports an [`Event::MaturityApproaching`] once for each contract
+    /// entering the maturity notification window. Contracts that left the
+    /// window are forgotten as they cannot enter it again.
+    fn notify_upcoming_maturities(&self) -> Result<(), Error> {
+        let (window, event_handler) = match (self.maturity_notification_window, &self.event_handler)
+        {
+            (Some(window), Some(event_handler)) => (window, event_handler),
+            _ => return Ok(()),
+        };
+
+        let upcoming = self.upcoming_maturities(window)?;
+        let mut notified = self.notified_maturities.lock().unwrap();
+        notified.retain(|id| upcoming.iter().any(|(x, _)| x == id));
+        for (contract_id, maturity_time) in upcoming {
+            if notified.insert(contract_id) {
+                event_handler.handle_event(Event::MaturityApproaching {
+                    contract_id,
+                    maturity_time,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn record_contract_counts(&self) -> Result<(), Error> {
+        if let Some(metrics) = &self.metrics {
+            for state in ContractState::ALL.iter() {
+                let count = self.get_store().count_contracts_by_state(*state)?;
+                if count > 0 {
+                    metrics.record_contract_count(state.get_name(), count);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_tx_status(&self, txid: &Txid) -> Result<TxStatus, Error> {
+        if let Some(height) = self.blockchain.get_tx_block_height(txid)? {
+            let tip_height = self.blockchain.get_block_height()?;
+            let confirmations = tip_height.saturating_sub(height) + 1;
+            return Ok(TxStatus::Confirmed(confirmations as u32));
+        }
+
+        if self.blockchain.is_in_mempool(txid)? {
+            Ok(TxStatus::InMempool)
+        } else {
+            Ok(TxStatus::NotFound)
+        }
+    }
+
+    /// Broadcasts again the given transaction of a contract after it was
+    /// evicted from the mempool. Unless the signed transaction is provided, it
+    /// is retrieved from the wallet as the stored contract does not contain its
+    /// witnesses.
+    fn rebroadcast_evicted_tx(
+        &self,
+        txid: &Txid,
+        signed_tx: Option<&Transaction>,
+        kind: BroadcastKind,
+        contract_id: &ContractId,
+    ) -> Result<(), Error> {
+        let contract_id_string = get_contract_id_string(contract_id);
+        warn!(
+            "{} {} of contract {} not found in mempool nor blockchain, rebroadcasting",
+            kind, txid, contract_id_string
+        );
+        let tx = match signed_tx {
+            Some(tx) => tx.clone(),
+            None => self.wallet.get_transaction(txid)?,
+        };
+        self.broadcast(contract_id, kind, &tx).map_err(|e| {
+            error!(
+                "Could not rebroadcast {} {} of contract {}: {}",
+                kind, txid, contract_id_string, e
+            );
+            e
+        })
+    }
+
+    /// Broadcasts the given transaction of a contract, after consulting the
+    /// broadcast interceptor if one is set. With an interceptor, transactions
+    /// that are delayed or whose broadcast fails are put in the pending
+    /// broadcast queue instead of returning an error, and transactions already
+    /// in the queue are left to it.
+    fn broadcast(
+        &self,
+        contract_id: &ContractId,
+        kind: BroadcastKind,
+        tx: &Transaction,
+    ) -> Result<(), Error> {
+        let interceptor = match &self.broadcast_interceptor {
+            Some(interceptor) => interceptor,
+            None => return self.blockchain.send_transaction(tx),
+        };
+        let is_pending = self
+            .get_store()
+            .get_pending_broadcasts()?
+            .iter()
+            .any(|x| x.contract_id == *contract_id && x.kind == kind);
+        if is_pending {
+            return Ok(());
+        }
+        let pending = PendingBroadcast {
+            contract_id: *contract_id,
+            kind,
+            transaction: tx.clone(),
+            not_before: self.time.unix_time_now(),
+            nb_attempts: 0,
+        };
+        self.attempt_broadcast(interceptor.as_ref(), pending)
+    }
+
+    /// Attempts to broadcast the given pending transaction following the
+    /// decision of the interceptor, updating the pending broadcast queue
+    /// accordingly.
+    fn attempt_broadcast(
+        &self,
+        interceptor: &(dyn BroadcastInterceptor + Send + Sync),
+        mut pending: PendingBroadcast,
+    ) -> Result<(), Error> {
+        let now = self.time.unix_time_now();
+        let mut child = None;
+        match interceptor.on_broadcast(&pending) {
+            BroadcastDecision::Approve => {}
+            BroadcastDecision::Delay(delay) => {
+                pending.not_before = now.saturating_add(delay);
+                return self.get_store().save_pending_broadcast(&pending);
+            }
+            BroadcastDecision::Replace(tx) => {
+                if tx.txid() != pending.transaction.txid() {
+                    return Err(Error::InvalidParameters(
+                        "Replacement transaction must have the same txid as the original one."
+                            .to_string(),
+                    ));
+                }
+                pending.transaction = tx;
+            }
+            BroadcastDecision::Cpfp(tx) => {
+                let txid = pending.transaction.txid();
+                if !tx.input.iter().any(|x| x.previous_output.txid == txid) {
+                    return Err(Error::InvalidParameters(
+                        "Child transaction must spend an output of the broadcast transaction."
+                            .to_string(),
+                    ));
+                }
+                child = Some(tx);
+            }
+        }
+
+        pending.nb_attempts += 1;
+        match self.blockchain.send_transaction(&pending.transaction) {
+            Ok(()) => {
+                if let Some(child) = child {
+                    // The transaction made it to the mempool, failing to
+                    // bump its fee is not a reason to broadcast it again.
+                    if let Err(e) = self.blockchain.send_transaction(&child) {
+                        warn!(
+                            "Could not broadcast child transaction {} of {} {} of contract {}: {}",
+                            child.txid(),
+                            pending.kind,
+                            pending.transaction.txid(),
+                            get_contract_id_string(&pending.contract_id),
+                            e
+                        );
+                    }
+                }
+                self.get_store()
+                    .delete_pending_broadcast(&pending.contract_id, pending.kind)
+            }
+            Err(e) if pending.nb_attempts >= MAX_BROADCAST_ATTEMPTS => {
+                error!(
+                    "Giving up broadcasting {} {} of contract {} after {} attempts: {}",
+                    pending.kind,
+                    pending.transaction.txid(),
+                    get_contract_id_string(&pending.contract_id),
+                    pending.nb_attempts,
+                    e
+                );
+                self.get_store()
+                    .delete_pending_broadcast(&pending.contract_id, pending.kind)
+            }
+            Err(e) => {
+                warn!(
+                    "Could not broadcast {} {} of contract {}, retrying later: {}",
+                    pending.kind,
+                    pending.transaction.txid(),
+                    get_contract_id_string(&pending.contract_id),
+                    e
+                );
+                pending.not_before = now.saturating_add(get_retry_delay(pending.nb_attempts));
+                self.get_store().save_pending_broadcast(&pending)
+            }
+        }
+    }
+
+    /// Retries the broadcast of the transactions of the pending broadcast
+    /// queue whose delay elapsed. Transactions that reached the mempool or
+    /// the blockchain in the meantime are removed from the queue.
+    fn process_pending_broadcasts(&self) -> Result<(), Error> {
+        let interceptor = match &self.broadcast_interceptor {
+            Some(interceptor) => interceptor,
+            None => return Ok(()),
+        };
+        let now = self.time.unix_time_now();
+        let pending_broadcasts = self.get_store().get_pending_broadcasts()?;
+        for pending in pending_broadcasts {
+            if pending.not_before > now {
+                continue;
+            }
+            let res =
+                self.get_tx_status(&pending.transaction.txid())
+                    .and_then(|status| match status {
+                        TxStatus::NotFound => {
+                            self.attempt_broadcast(interceptor.as_ref(), pending.clone())
+                        }
+                        _ => self
+                            .get_store()
+                            .delete_pending_broadcast(&pending.contract_id, pending.kind),
+                    });
+            if let Err(e) = res {
+                error!(
+                    "Error processing pending broadcast of {} of contract {}: {}",
+                    pending.kind,
+                    get_contract_id_string(&pending.contract_id),
+                    e
+                );
+            }
+        }
 
         Ok(())
     }
 
-    fn check_signed_contract(&mut self, contract: &SignedContract) -> Result<(), Error> {
-        let confirmations = self.wallet.get_transaction_confirmations(
-            &contract.accepted_contract.dlc_transactions.fund.txid(),
-        )?;
-        if confirmations >= NB_CONFIRMATIONS {
-            self.store
-                .update_contract(&Contract::Confirmed(contract.clone()))?;
+    fn check_signed_contract(&self, contract: &SignedContract) -> Result<(), Error> {
+        let fund_txid = contract.accepted_contract.dlc_transactions.fund.txid();
+        let confirmations = match self.get_tx_status(&fund_txid)? {
+            TxStatus::Confirmed(confirmations) => confirmations,
+            TxStatus::InMempool => 0,
+            TxStatus::NotFound if !self.broadcast_fund_tx => return Ok(()),
+            TxStatus::NotFound => {
+                // The error is already logged, the rebroadcast is attempted
+                // again on the next check.
+                let _ = self.rebroadcast_evicted_tx(
+                    &fund_txid,
+                    contract.signed_fund_tx.as_ref(),
+                    BroadcastKind::Fund,
+                    &contract.accepted_contract.get_contract_id(),
+                );
+                return Ok(());
+            }
+        };
+        if confirmations >= contract.accepted_contract.offered_contract.nb_confirmations {
+            self.update_contract(
+                &Contract::Confirmed(contract.clone()),
+                Some(fund_txid),
+                None,
+            )?;
+            info!(
+                "Fund transaction of contract {} confirmed",
+                contract.accepted_contract.get_contract_id_string()
+            );
+            if let Some(renewal) = &contract.accepted_contract.offered_contract.renewal {
+                let renewed = self.get_store().get_contract(&renewal.contract_id)?;
+                if let Some(Contract::Confirmed(renewed)) = renewed {
+                    self.update_contract(&Contract::Renewed(renewed), Some(fund_txid), None)?;
+                    info!(
+                        "Contract {} renewed into contract {}",
+                        get_contract_id_string(&renewal.contract_id),
+                        contract.accepted_contract.get_contract_id_string()
+                    );
+                }
+            }
         }
         Ok(())
     }
 
-    fn check_signed_contracts(&mut self) -> Result<(), Error> {
-        for c in self.store.get_signed_contracts()? {
-            if let Err(e) = self.check_signed_contract(&c) {
+    fn check_signed_contracts(&self) -> Result<(), Error> {
+        let signed_contracts = self.get_store().get_signed_contracts()?;
+        for c in signed_contracts {
+            let contract_id = c.accepted_contract.get_contract_id();
+            let res = self.with_contract_lock(&contract_id.0, || {
+                // The contract might have been updated by another thread since
+                // the list was retrieved.
+                let contract = self.get_store().get_contract(&contract_id)?;
+                match contract {
+                    Some(Contract::Signed(c)) => self.check_signed_contract(&c),
+                    _ => Ok(()),
+                }
+            });
+            if let Err(e) = res {
                 error!(
                     "Error checking confirmed contract {}: {}",
                     c.accepted_contract.get_contract_id_string(),
@@ -796,9 +3870,18 @@ where
         Ok(())
     }
 
-    fn check_confirmed_contracts(&mut self) -> Result<(), Error> {
-        for c in self.store.get_confirmed_contracts()? {
-            if let Err(e) = self.check_confirmed_contract(&c) {
+    fn check_confirmed_contracts(&self) -> Result<(), Error> {
+        let confirmed_contracts = self.get_store().get_confirmed_contracts()?;
+        for c in confirmed_contracts {
+            let contract_id = c.accepted_contract.get_contract_id();
+            let res = self.with_contract_lock(&contract_id.0, || {
+                let contract = self.get_store().get_contract(&contract_id)?;
+                match contract {
+                    Some(Contract::Confirmed(c)) => self.check_confirmed_contract(&c),
+                    _ => Ok(()),
+                }
+            });
+            if let Err(e) = res {
                 error!(
                     "Error checking confirmed contract {}: {}",
                     c.accepted_contract.get_contract_id_string(),
@@ -810,38 +3893,142 @@ where
         Ok(())
     }
 
-    fn check_confirmed_contract(&mut self, contract: &SignedContract) -> Result<(), Error> {
-        let contract_infos = &contract.accepted_contract.offered_contract.contract_info;
-        for (contract_info, adaptor_info) in contract_infos
-            .iter()
-            .zip(contract.accepted_contract.adaptor_infos.iter())
+    /// Makes sure that the CETs and refund transactions of closed and refunded
+    /// contracts are still in the mempool or in the blockchain, until they
+    /// reach `NB_CONFIRMATIONS`.
+    fn check_closing_transactions(&self) -> Result<(), Error> {
+        for state in &[
+            ContractState::Closed,
+            ContractState::Refunded,
+            ContractState::RefundedDueToNoAttestation,
+        ] {
+            self.for_each_contract_in_state(*state, |contract| {
+                self.check_closing_transaction(&contract);
+                true
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn check_closing_transaction(&self, contract: &Contract) {
+        let (txid, contract_id) = match contract {
+            Contract::Closed(c) => (
+                c.signed_contract.accepted_contract.dlc_transactions.cets[c.cet_index].txid(),
+                c.signed_contract.accepted_contract.get_contract_id(),
+            ),
+            Contract::Refunded(c) | Contract::RefundedDueToNoAttestation(c) => (
+                c.accepted_contract.dlc_transactions.refund.txid(),
+                c.accepted_contract.get_contract_id(),
+            ),
+            _ => return,
+        };
+        if self
+            .settled_contracts
+            .lock()
+            .unwrap()
+            .contains(&contract_id)
         {
+            return;
+        }
+        let contract_id_string = get_contract_id_string(&contract_id);
+        let res = self
+            .get_tx_status(&txid)
+            .and_then(|status| match (status, contract) {
+                (TxStatus::Confirmed(confirmations), _) if confirmations >= NB_CONFIRMATIONS => {
+                    self.settled_contracts.lock().unwrap().insert(contract_id);
+                    Ok(())
+                }
+                (TxStatus::NotFound, Contract::Closed(c)) => self.check_evicted_cet(c),
+                (TxStatus::NotFound, Contract::Refunded(c))
+                | (TxStatus::NotFound, Contract::RefundedDueToNoAttestation(c)) => {
+                    // The refund transaction is signed again as it is not
+                    // necessarily known to the wallet.
+                    let refund = self.get_signed_refund(c)?;
+                    self.rebroadcast_evicted_tx(
+                        &txid,
+                        Some(&refund),
+                        BroadcastKind::Refund,
+                        &contract_id,
+                    )
+                }
+                _ => Ok(()),
+            });
+        if let Err(e) = res {
+            error!(
+                "Error checking closing transaction of contract {}: {}",
+                contract_id_string, e
+            )
+        }
+    }
+
+    /// Handles the CET of a closed contract being neither in the mempool nor
+    /// in the blockchain. If another CET or the refund transaction spent the
+    /// fund output first, typically the counter party's CET for a different
+    /// outcome, the contract is updated to reflect it. Otherwise the CET is
+    /// broadcast again.
+    fn check_evicted_cet(&self, contract: &ClosedContract) -> Result<(), Error> {
+        let signed_contract = &contract.signed_contract;
+        let cet_txid =
+            signed_contract.accepted_contract.dlc_transactions.cets[contract.cet_index].txid();
+        if self.check_fund_output_spent(signed_contract)? {
+            warn!(
+                "CET {} of contract {} was superseded by another transaction spending \
+                 the fund output",
+                cet_txid,
+                signed_contract.accepted_contract.get_contract_id_string()
+            );
+            return Ok(());
+        }
+
+        self.rebroadcast_evicted_tx(
+            &cet_txid,
+            contract.signed_cet.as_ref(),
+            BroadcastKind::Cet,
+            &signed_contract.accepted_contract.get_contract_id(),
+        )
+    }
+
+    fn check_confirmed_contract(&self, contract: &SignedContract) -> Result<(), Error> {
+        if self.check_zero_conf_funding_reverted(contract)? {
+            return Ok(());
+        }
+
+        if self.check_fund_output_spent(contract)? {
+            return Ok(());
+        }
+
+        // Closing or refunding the contract would conflict with the fund
+        // transaction of its renewal.
+        if self.has_pending_renewal(&contract.accepted_contract.get_contract_id())? {
+            return Ok(());
+        }
+
+        let offered_contract = &contract.accepted_contract.offered_contract;
+        let now = self.get_time(offered_contract.time_source)?;
+        for (index, contract_info) in offered_contract.contract_info.iter().enumerate() {
             let matured: Vec<_> = contract_info
                 .oracle_announcements
                 .iter()
-                .filter(|x| {
-                    (x.oracle_event.event_maturity_epoch as u64) <= self.time.unix_time_now()
-                })
                 .enumerate()
+                .filter(|(_, x)| self.get_expected_attestation_time(x) <= now)
                 .collect();
             if matured.len() >= contract_info.threshold {
                 let attestations: Vec<_> = matured
                     .iter()
                     .filter_map(|(i, announcement)| {
-                        let oracle = self.oracles.get(&announcement.oracle_public_key)?;
                         Some((
                             *i,
-                            oracle
-                                .get_attestation(&announcement.oracle_event.event_id)
-                                .ok()?,
+                            self.get_contract_attestation(contract_info, announcement)?,
                         ))
                     })
                     .collect();
                 if attestations.len() >= contract_info.threshold {
+                    let adaptor_infos = self.get_adaptor_infos(&contract.accepted_contract)?;
                     match self.try_close_contract(
                         contract,
                         contract_info,
-                        adaptor_info,
+                        &adaptor_infos[index],
                         &attestations,
                     ) {
                         Ok(()) => return Ok(()),
@@ -863,19 +4050,313 @@ where
         Ok(())
     }
 
+    /// Returns whether a renewal of the contract with the given id is signed
+    /// but not confirmed yet, in which case its fund transaction, spending the
+    /// fund output of the contract, can be broadcast at any time.
+    fn has_pending_renewal(&self, contract_id: &ContractId) -> Result<bool, Error> {
+        Ok(self
+            .get_renewal(contract_id, &[ContractState::Signed])?
+            .is_some())
+    }
+
+    /// Returns the renewal of the contract with the given id in one of the
+    /// given states, if any.
+    fn get_renewal(
+        &self,
+        contract_id: &ContractId,
+        states: &[ContractState],
+    ) -> Result<Option<Contract>, Error> {
+        let mut renewal = None;
+        for state in states {
+            self.for_each_contract_in_state(*state, |contract| {
+                let is_renewal = contract
+                    .get_offered_contract()
+                    .renewal
+                    .as_ref()
+                    .map_or(false, |x| x.contract_id == *contract_id);
+                if is_renewal {
+                    renewal = Some(contract);
+                }
+                !is_renewal
+            })?;
+            if renewal.is_some() {
+                break;
+            }
+        }
+        Ok(renewal)
+    }
+
+    /// Moves a contract confirmed with zero-conf funding back to the signed
+    /// state if its fund transaction is neither in the blockchain nor in the
+    /// mempool anymore, e.g. following a reorganization or a double spend of
+    /// one of its inputs. Returns whether the contract was moved.
+    fn check_zero_conf_funding_reverted(&self, contract: &SignedContract) -> Result<bool, Error> {
+        if contract.accepted_contract.offered_contract.nb_confirmations > 0 {
+            return Ok(false);
+        }
+
+        let fund_txid = contract.accepted_contract.dlc_transactions.fund.txid();
+        if self.get_tx_status(&fund_txid)? != TxStatus::NotFound {
+            return Ok(false);
+        }
+
+        let contract_id = contract.accepted_contract.get_contract_id();
+        warn!(
+            "Fund transaction of zero-conf contract {} not found, moving it back to signed",
+            get_contract_id_string(&contract_id)
+        );
+        self.update_contract(&Contract::Signed(contract.clone()), Some(fund_txid), None)?;
+        if let Some(event_handler) = &self.event_handler {
+            event_handler.handle_event(Event::ZeroConfFundingReverted { contract_id });
+        }
+        Ok(true)
+    }
+
+    /// Returns the attestation to use to close a contract for the given
+    /// announcement. For hybrid contracts, the attestation of the selector
+    /// event by the same oracle is prepended to it, as adaptor points combine
+    /// the signatures of both events.
+    fn get_contract_attestation(
+        &self,
+        contract_info: &ContractInfo,
+        announcement: &OracleAnnouncement,
+    ) -> Option<OracleAttestation> {
+        let attestation = self.get_attestation(announcement)?;
+        let selector_announcement = match &contract_info.contract_descriptor {
+            ContractDescriptor::Hybrid(h) => h
+                .selector_announcements
+                .iter()
+                .find(|x| x.oracle_public_key == announcement.oracle_public_key)?,
+            _ => return Some(attestation),
+        };
+        let selector_attestation = self.get_attestation(selector_announcement)?;
+
+        Some(OracleAttestation {
+            oracle_public_key: attestation.oracle_public_key,
+            signatures: selector_attestation
+                .signatures
+                .into_iter()
+                .chain(attestation.signatures)
+                .collect(),
+            outcomes: selector_attestation
+                .outcomes
+                .into_iter()
+                .chain(attestation.outcomes)
+                .collect(),
+        })
+    }
+
+    /// Returns a valid attestation for the given announcement, looking it up
+    /// from the attestations provided with [`Manager::provide_attestation`]
+    /// and the oracle registered for it first, and then from the attestation
+    /// providers. Invalid attestations are ignored. Providers are queried even
+    /// once a valid attestation was found, to detect oracles signing different
+    /// outcomes for the event.
+    fn get_attestation(&self, announcement: &OracleAnnouncement) -> Option<OracleAttestation> {
+        let event_id = announcement.get_event_id();
+        let is_valid = |attestation: &OracleAttestation, source: &str| match attestation
+            .validate(&self.secp, announcement)
+        {
+            Ok(()) => true,
+            Err(e) => {
+                warn!(
+                    "Ignoring invalid attestation for event {} from {}: {}",
+                    event_id, source, e
+                );
+                false
+            }
+        };
+
+        let mut res = self
+            .provided_attestations
+            .lock()
+            .unwrap()
+            .get(&(announcement.oracle_public_key, event_id.to_string()))
+            .cloned();
+
+        if let Some(Ok(attestation)) =
+            self.get_oracle_attestation(&announcement.oracle_public_key, event_id)
+        {
+            if is_valid(&attestation, "oracle") {
+                match &res {
+                    Some(first) => self.check_oracle_equivocation(first, &attestation),
+                    None => res = Some(attestation),
+                }
+            }
+        }
+
+        for source in &self.attestation_sources {
+            match source.get_attestation(&announcement.oracle_public_key, event_id) {
+                Ok(Some(attestation)) => {
+                    if !is_valid(&attestation, &source.provider.get_name()) {
+                        continue;
+                    }
+                    match &res {
+                        Some(first) => self.check_oracle_equivocation(first, &attestation),
+                        None => res = Some(attestation),
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => warn!(
+                    "Error getting attestation for event {} from {}: {}",
+                    event_id,
+                    source.provider.get_name(),
+                    e
+                ),
+            }
+        }
+
+        res
+    }
+
+    /// Reports an [`Event::OracleEquivocation`] if the given attestations for
+    /// the same event contain signatures of different outcomes using the same
+    /// nonce.
+    fn check_oracle_equivocation(
+        &self,
+        attestation: &OracleAttestation,
+        other: &OracleAttestation,
+    ) {
+        if let Some(proof) = attestation.get_equivocation_proof(other) {
+            error!(
+                "Oracle {} attested to both {} and {} using the same nonce",
+                proof.oracle_public_key, proof.first_outcome, proof.second_outcome
+            );
+            if let Some(event_handler) = &self.event_handler {
+                event_handler.handle_event(Event::OracleEquivocation { proof });
+            }
+        }
+    }
+
+    /// Checks whether the fund output of the contract was spent by a CET or by
+    /// the refund transaction, which happens when the counter party closes the
+    /// contract first. Returns `true` if the contract was moved to the closed
+    /// or refunded state.
+    fn check_fund_output_spent(&self, contract: &SignedContract) -> Result<bool, Error> {
+        let accepted_contract = &contract.accepted_contract;
+        let dlc_transactions = &accepted_contract.dlc_transactions;
+        let fund_outpoint = OutPoint {
+            txid: dlc_transactions.fund.txid(),
+            vout: dlc_transactions.get_fund_output_index() as u32,
+        };
+        let spending_tx = match self.blockchain.get_spending_transaction(&fund_outpoint)? {
+            Some(tx) => tx,
+            None => return Ok(false),
+        };
+        let spending_txid = spending_tx.txid();
+
+        if spending_txid == dlc_transactions.refund.txid() {
+            // As when refunding the contract ourselves, the refund is due to
+            // the oracles not attesting in time unless enough attestations
+            // are available to close it.
+            if self.has_enough_attestations(contract) {
+                self.update_contract(
+                    &Contract::Refunded(contract.clone()),
+                    Some(spending_txid),
+                    None,
+                )?;
+                info!(
+                    "Contract {} refunded by the counter party",
+                    accepted_contract.get_contract_id_string()
+                );
+            } else {
+                self.set_refunded_due_to_no_attestation(contract, spending_txid)?;
+            }
+            return Ok(true);
+        }
+
+        let cet_index = match dlc_transactions
+            .cets
+            .iter()
+            .position(|x| x.txid() == spending_txid)
+        {
+            Some(cet_index) => cet_index,
+            // The fund output can also be spent by the fund transaction of a
+            // renewal of the contract, in which case the state is updated
+            // when the renewal gets confirmed.
+            None => {
+                let contract_id = accepted_contract.get_contract_id();
+                let is_renewal = self
+                    .get_renewal(
+                        &contract_id,
+                        &[ContractState::Signed, ContractState::Confirmed],
+                    )?
+                    .map_or(false, |x| match x {
+                        Contract::Signed(c) | Contract::Confirmed(c) => {
+                            c.accepted_contract.dlc_transactions.fund.txid() == spending_txid
+                        }
+                        _ => false,
+                    });
+                if !is_renewal {
+                    warn!(
+                        "Fund output of contract {} spent by unknown transaction {}",
+                        accepted_contract.get_contract_id_string(),
+                        spending_txid
+                    );
+                }
+                return Ok(is_renewal);
+            }
+        };
+
+        let own_adaptor_sigs = if accepted_contract.offered_contract.is_offer_party {
+            contract.adaptor_signatures.as_ref()
+        } else {
+            accepted_contract.adaptor_signatures.as_ref()
+        };
+        let adaptor_infos = self.get_adaptor_infos(accepted_contract)?;
+        let adaptor_secret = own_adaptor_sigs.and_then(|adaptor_sigs| {
+            accepted_contract
+                .offered_contract
+                .contract_info
+                .iter()
+                .zip(adaptor_infos.iter())
+                .find_map(|(contract_info, adaptor_info)| {
+                    contract_info
+                        .recover_adaptor_secret(
+                            &self.secp,
+                            adaptor_info,
+                            adaptor_sigs,
+                            cet_index,
+                            &spending_tx,
+                        )
+                        .ok()
+                        .flatten()
+                })
+        });
+
+        let closed_contract = ClosedContract {
+            signed_contract: contract.clone(),
+            attestations: Vec::new(),
+            cet_index,
+            own_payout: get_own_payout(contract, &spending_tx),
+            adaptor_secret,
+            signed_cet: None,
+        };
+
+        self.update_contract(
+            &Contract::Closed(closed_contract),
+            Some(spending_txid),
+            None,
+        )?;
+        info!(
+            "Contract {} closed by the counter party with CET {}",
+            accepted_contract.get_contract_id_string(),
+            spending_txid
+        );
+
+        Ok(true)
+    }
+
     fn try_close_contract(
-        &mut self,
+        &self,
         contract: &SignedContract,
         contract_info: &ContractInfo,
         adaptor_info: &AdaptorInfo,
         attestations: &[(usize, OracleAttestation)],
     ) -> Result<(), Error> {
         let offered_contract = &contract.accepted_contract.offered_contract;
-        let outcomes = attestations
-            .iter()
-            .map(|(i, x)| (*i, &x.outcomes))
-            .collect::<Vec<(usize, &Vec<String>)>>();
-        let info_opt = contract_info.get_range_info_for_outcome(adaptor_info, &outcomes, 0)?;
+        let info_opt =
+            self.select_range_info(contract, contract_info, adaptor_info, attestations)?;
         if let Some((sig_infos, range_info)) = info_opt {
             let sigs: Vec<Vec<SchnorrSignature>> = attestations
                 .iter()
@@ -887,12 +4368,7 @@ where
             let mut cet =
                 contract.accepted_contract.dlc_transactions.cets[range_info.cet_index].clone();
 
-            let confirmations = self
-                .wallet
-                .get_transaction_confirmations(&cet.txid())
-                .unwrap();
-
-            if confirmations < 1 {
+            if self.get_tx_status(&cet.txid())? == TxStatus::NotFound {
                 let (adaptor_sigs, fund_pubkey, other_pubkey) = if offered_contract.is_offer_party {
                     (
                         contract
@@ -931,73 +4407,402 @@ where
                         .value,
                 )?;
 
-                // TODO(tibo): if this fails because another tx is already in
-                // mempool or blockchain, we might have been cheated. There is
-                // not much to be done apart from possibly extracting a fraud
-                // proof but ideally it should be handled.
-                self.blockchain.send_transaction(&cet)?;
-            }
+                // The CET is persisted before being broadcast so that it can
+                // be broadcast again if it does not make it to the mempool.
+                let closed_contract = ClosedContract {
+                    signed_contract: contract.clone(),
+                    attestations: attestations.iter().map(|x| x.1.clone()).collect(),
+                    cet_index: range_info.cet_index,
+                    own_payout: get_own_payout(contract, &cet),
+                    adaptor_secret: None,
+                    signed_cet: Some(cet.clone()),
+                };
+                self.update_contract(&Contract::Closed(closed_contract), Some(cet.txid()), None)?;
 
-            let closed_contract = ClosedContract {
-                signed_contract: contract.clone(),
-                attestations: attestations.iter().map(|x| x.1.clone()).collect(),
-                cet_index: range_info.cet_index,
-            };
+                if let Err(e) = self.broadcast(
+                    &contract.accepted_contract.get_contract_id(),
+                    BroadcastKind::Cet,
+                    &cet,
+                ) {
+                    // The counter party may have closed the contract first
+                    // with another CET, or with the refund transaction.
+                    if self.check_fund_output_spent(contract)? {
+                        warn!(
+                            "CET {} of contract {} conflicts with a transaction already \
+                             spending the fund output",
+                            cet.txid(),
+                            contract.accepted_contract.get_contract_id_string()
+                        );
+                        return Ok(());
+                    }
+                    error!(
+                        "Could not broadcast CET {} of contract {}, it will be \
+                         broadcast again later: {}",
+                        cet.txid(),
+                        contract.accepted_contract.get_contract_id_string(),
+                        e
+                    );
+                }
+            } else {
+                let closed_contract = ClosedContract {
+                    signed_contract: contract.clone(),
+                    attestations: attestations.iter().map(|x| x.1.clone()).collect(),
+                    cet_index: range_info.cet_index,
+                    own_payout: get_own_payout(contract, &cet),
+                    adaptor_secret: None,
+                    signed_cet: None,
+                };
+                self.update_contract(&Contract::Closed(closed_contract), Some(cet.txid()), None)?;
+            }
 
-            self.store
-                .update_contract(&Contract::Closed(closed_contract))?;
+            info!(
+                "Contract {} closed with CET {}",
+                contract.accepted_contract.get_contract_id_string(),
+                cet.txid()
+            );
         }
 
         Ok(())
     }
 
-    fn check_refund(&mut self, contract: &SignedContract) -> Result<(), Error> {
-        // TODO(tibo): should check for confirmation of refund before updating state
-        if contract.accepted_contract.dlc_transactions.refund.lock_time as u64
-            <= self.time.unix_time_now()
+    /// Returns the oracle signature information and the CET and adaptor
+    /// signature indexes to use to close the contract with the given
+    /// attestations, according to the manager's [`ClosePolicy`].
+    fn select_range_info(
+        &self,
+        contract: &SignedContract,
+        contract_info: &ContractInfo,
+        adaptor_info: &AdaptorInfo,
+        attestations: &[(usize, OracleAttestation)],
+    ) -> Result<Option<(Vec<(usize, usize)>, RangeInfo)>, Error> {
+        let outcomes = attestations
+            .iter()
+            .map(|(i, x)| (*i, &x.outcomes))
+            .collect::<Vec<(usize, &Vec<String>)>>();
+
+        if self.close_policy == ClosePolicy::HonorExactOutcome
+            || outcomes.len() < contract_info.threshold
         {
-            let offered_contract = &contract.accepted_contract.offered_contract;
-            let accepted_contract = &contract.accepted_contract;
-            let mut refund = accepted_contract.dlc_transactions.refund.clone();
-            let confirmations = self.wallet.get_transaction_confirmations(&refund.txid())?;
-            if confirmations == 0 {
-                let funding_script_pubkey =
-                    &accepted_contract.dlc_transactions.funding_script_pubkey;
-                let fund_output_value = accepted_contract.dlc_transactions.get_fund_output().value;
-                let (fund_pubkey, other_fund_pubkey, other_sig) = if offered_contract.is_offer_party
-                {
-                    (
-                        &offered_contract.offer_params.fund_pubkey,
-                        &accepted_contract.accept_params.fund_pubkey,
-                        &accepted_contract.accept_refund_signature,
-                    )
-                } else {
-                    (
-                        &accepted_contract.accept_params.fund_pubkey,
-                        &offered_contract.offer_params.fund_pubkey,
-                        &contract.offer_refund_signature,
-                    )
-                };
+            return contract_info.get_range_info_for_outcome(adaptor_info, &outcomes, 0);
+        }
 
-                let fund_priv_key = self.wallet.get_secret_key_for_pubkey(fund_pubkey)?;
-                dlc::util::sign_multi_sig_input(
-                    &self.secp,
-                    &mut refund,
-                    other_sig,
-                    other_fund_pubkey,
-                    &fund_priv_key,
-                    funding_script_pubkey,
-                    fund_output_value,
-                    0,
-                );
+        // Each combination of attestations agreeing on an outcome gives a
+        // usable CET.
+        let mut candidates = Vec::new();
+        for combination in CombinationIterator::new(outcomes.len(), contract_info.threshold) {
+            let selected: Vec<_> = combination.iter().map(|i| outcomes[*i]).collect();
+            if let Ok(Some(info)) =
+                contract_info.get_range_info_for_outcome(adaptor_info, &selected, 0)
+            {
+                candidates.push(info);
+            }
+        }
+
+        let cets = &contract.accepted_contract.dlc_transactions.cets;
+        let selected = match self.close_policy {
+            ClosePolicy::MaximizeOwnPayout => candidates.into_iter().max_by_key(|(_, x)| {
+                (
+                    get_own_payout(contract, &cets[x.cet_index]),
+                    std::cmp::Reverse(x.adaptor_index),
+                )
+            }),
+            ClosePolicy::EarliestAdaptorIndex => {
+                candidates.into_iter().min_by_key(|(_, x)| x.adaptor_index)
+            }
+            ClosePolicy::HonorExactOutcome => unreachable!(),
+        };
+
+        Ok(selected)
+    }
+
+    /// Returns the current time according to the given source, in seconds
+    /// since the unix epoch. Block height time uses the median time past as
+    /// the maturity of oracle events is a unix time.
+    fn get_time(&self, time_source: TimeSource) -> Result<u64, Error> {
+        match time_source {
+            TimeSource::WallClock => Ok(self.time.unix_time_now()),
+            TimeSource::MedianTimePast | TimeSource::BlockHeight => {
+                self.blockchain.get_median_time_past()
+            }
+        }
+    }
+
+    /// Returns whether the refund transaction of the given contract can be
+    /// broadcast. A locktime below the threshold is a block height which is
+    /// compared with the height of the tip, whatever the time source.
+    fn is_refund_locktime_reached(&self, contract: &SignedContract) -> Result<bool, Error> {
+        let lock_time = contract.accepted_contract.dlc_transactions.refund.lock_time;
+        if lock_time < LOCKTIME_THRESHOLD {
+            return Ok(lock_time as u64 <= self.blockchain.get_block_height()?);
+        }
+        let time_source = contract.accepted_contract.offered_contract.time_source;
+        Ok(lock_time as u64 <= self.get_time(time_source)?)
+    }
+
+    /// Returns the refund transaction of the given contract signed by both
+    /// parties.
+    fn get_signed_refund(&self, contract: &SignedContract) -> Result<Transaction, Error> {
+        let offered_contract = &contract.accepted_contract.offered_contract;
+        let accepted_contract = &contract.accepted_contract;
+        let mut refund = accepted_contract.dlc_transactions.refund.clone();
+        let funding_script_pubkey = &accepted_contract.dlc_transactions.funding_script_pubkey;
+        let fund_output_value = accepted_contract.dlc_transactions.get_fund_output().value;
+        let (fund_pubkey, other_fund_pubkey, other_sig) = if offered_contract.is_offer_party {
+            (
+                &offered_contract.offer_params.fund_pubkey,
+                &accepted_contract.accept_params.fund_pubkey,
+                &accepted_contract.accept_refund_signature,
+            )
+        } else {
+            (
+                &accepted_contract.accept_params.fund_pubkey,
+                &offered_contract.offer_params.fund_pubkey,
+                &contract.offer_refund_signature,
+            )
+        };
 
-                self.blockchain.send_transaction(&refund)?;
+        let fund_priv_key = self.wallet.get_secret_key_for_pubkey(fund_pubkey)?;
+        dlc::util::sign_multi_sig_input(
+            &self.secp,
+            &mut refund,
+            other_sig,
+            other_fund_pubkey,
+            &fund_priv_key,
+            funding_script_pubkey,
+            fund_output_value,
+            0,
+        );
+        Ok(refund)
+    }
+
+    fn check_refund(&self, contract: &SignedContract) -> Result<(), Error> {
+        // TODO(tibo): should check for confirmation of refund before updating state
+        if self.is_refund_locktime_reached(contract)? {
+            let refund = &contract.accepted_contract.dlc_transactions.refund;
+            if self.get_tx_status(&refund.txid())? == TxStatus::NotFound {
+                let refund = self.get_signed_refund(contract)?;
+                self.broadcast(
+                    &contract.accepted_contract.get_contract_id(),
+                    BroadcastKind::Refund,
+                    &refund,
+                )
+                .map_err(|e| {
+                    error!(
+                        "Could not broadcast refund transaction {} of contract {}: {}",
+                        refund.txid(),
+                        contract.accepted_contract.get_contract_id_string(),
+                        e
+                    );
+                    e
+                })?;
             }
 
-            self.store
-                .update_contract(&Contract::Refunded(contract.clone()))?;
+            // Contracts are only refunded here when the oracles did not
+            // attest to their outcome in time.
+            self.set_refunded_due_to_no_attestation(contract, refund.txid())?;
         }
 
         Ok(())
     }
+
+    /// Returns whether enough attestations are available to close the given
+    /// contract with one of its contract information.
+    fn has_enough_attestations(&self, contract: &SignedContract) -> bool {
+        contract
+            .accepted_contract
+            .offered_contract
+            .contract_info
+            .iter()
+            .any(|contract_info| {
+                contract_info
+                    .oracle_announcements
+                    .iter()
+                    .filter(|x| self.get_contract_attestation(contract_info, x).is_some())
+                    .count()
+                    >= contract_info.threshold
+            })
+    }
+
+    /// Moves the given contract to the `RefundedDueToNoAttestation` state with
+    /// the given refund transaction id, notifying the event handler.
+    fn set_refunded_due_to_no_attestation(
+        &self,
+        contract: &SignedContract,
+        refund_txid: Txid,
+    ) -> Result<(), Error> {
+        self.update_contract(
+            &Contract::RefundedDueToNoAttestation(contract.clone()),
+            Some(refund_txid),
+            None,
+        )?;
+        warn!(
+            "Contract {} refunded as no attestation was received in time",
+            contract.accepted_contract.get_contract_id_string()
+        );
+        if let Some(event_handler) = &self.event_handler {
+            event_handler.handle_event(Event::ContractRefundedDueToNoAttestation {
+                contract_id: contract.accepted_contract.get_contract_id(),
+            });
+        }
+        Ok(())
+    }
+}
+
+fn get_message_name(msg: &DlcMessage) -> &'static str {
+    match msg {
+        DlcMessage::Offer(_) => "offer",
+        DlcMessage::Accept(_) => "accept",
+        DlcMessage::Sign(_) => "sign",
+        DlcMessage::RenewOffer(_) => "renew offer",
+        DlcMessage::Reestablish(_) => "reestablish",
+    }
+}
+
+/// Returns the message describing the progress of the setup of the given
+/// contract to its counter party, or `None` if the setup failed.
+fn get_reestablish_message(contract: &Contract) -> Option<ReestablishDlc> {
+    let state = match contract {
+        Contract::Offered(_) => ReestablishState::Offered,
+        Contract::Accepted(_) => ReestablishState::Accepted,
+        Contract::Signed(_)
+        | Contract::Confirmed(_)
+        | Contract::Closed(_)
+        | Contract::Refunded(_)
+        | Contract::RefundedDueToNoAttestation(_)
+        | Contract::Renewed(_) => ReestablishState::Signed,
+        Contract::FailedAccept(_) | Contract::FailedSign(_) => return None,
+    };
+
+    Some(ReestablishDlc {
+        temporary_contract_id: contract.get_temporary_id(),
+        contract_id: contract.get_id(),
+        state,
+    })
+}
+
+/// Returns whether the given received offer amends the given pending one, that
+/// is whether it only differs from it by the replacement of the announcement
+/// of an oracle event by another announcement of the same oracle and event.
+fn is_amendment(pending: &OfferedContract, offer: &OfferedContract) -> bool {
+    if pending.contract_info.len() != offer.contract_info.len() {
+        return false;
+    }
+    let mut normalized = offer.clone();
+    normalized.id = pending.id;
+    let mut replacement = None;
+    for (pending_info, info) in pending
+        .contract_info
+        .iter()
+        .zip(normalized.contract_info.iter_mut())
+    {
+        if pending_info.oracle_announcements.len() != info.oracle_announcements.len() {
+            return false;
+        }
+        for (pending_announcement, announcement) in pending_info
+            .oracle_announcements
+            .iter()
+            .zip(info.oracle_announcements.iter_mut())
+        {
+            if pending_announcement == announcement {
+                continue;
+            }
+            if pending_announcement.oracle_public_key != announcement.oracle_public_key
+                || pending_announcement.get_event_id() != announcement.get_event_id()
+            {
+                return false;
+            }
+            // A single announcement is replaced, possibly in several contract
+            // infos.
+            match &replacement {
+                Some(replacement) if replacement != announcement => return false,
+                Some(_) => {}
+                None => replacement = Some(announcement.clone()),
+            }
+            *announcement = pending_announcement.clone();
+        }
+    }
+    replacement.is_some() && normalized.encode() == pending.encode()
+}
+
+/// Returns the outpoints spent by the given funding inputs that were selected
+/// from the wallet, and thus locked in it.
+fn get_wallet_outpoints(funding_inputs: &[FundingInputInfo]) -> Vec<OutPoint> {
+    funding_inputs
+        .iter()
+        .filter(|x| x.address.is_some())
+        .filter_map(|x| {
+            let prev_tx = Transaction::consensus_decode(&*x.funding_input.prev_tx).ok()?;
+            Some(OutPoint {
+                txid: prev_tx.txid(),
+                vout: x.funding_input.prev_tx_vout,
+            })
+        })
+        .collect()
+}
+
+fn get_message_hash<M: Writeable>(msg: &M) -> [u8; 32] {
+    sha256::Hash::hash(&msg.encode()).into_inner()
+}
+
+// Inputs spending legacy outputs would make the id of the fund transaction
+// malleable, so only native and P2SH wrapped segwit outputs are accepted.
+fn is_standard_funding_script(script_pubkey: &Script, redeem_script: &Script) -> bool {
+    script_pubkey.is_v0_p2wpkh()
+        || script_pubkey.is_v0_p2wsh()
+        || is_v1_p2tr(script_pubkey)
+        || (script_pubkey.is_p2sh() && redeem_script.is_witness_program())
+}
+
+fn is_v1_p2tr(script_pubkey: &Script) -> bool {
+    let bytes = script_pubkey.as_bytes();
+    bytes.len() == 34
+        && bytes[0] == opcodes::all::OP_PUSHNUM_1.into_u8()
+        && bytes[1] == opcodes::all::OP_PUSHBYTES_32.into_u8()
+}
+
+/// Returns whether the given contract is open, i.e. offered by the local party
+/// and not yet accepted, or accepted and not yet closed.
+fn is_open_contract(contract: &Contract) -> bool {
+    match contract {
+        Contract::Offered(o) => o.is_offer_party,
+        Contract::Accepted(_) | Contract::Signed(_) | Contract::Confirmed(_) => true,
+        _ => false,
+    }
+}
+
+/// Returns the collateral of the local party in the given contract.
+fn get_own_collateral(offered_contract: &OfferedContract) -> u64 {
+    if offered_contract.is_offer_party {
+        offered_contract.offer_params.collateral
+    } else {
+        offered_contract.total_collateral - offered_contract.offer_params.collateral
+    }
+}
+
+/// Returns the premium amount that the party (offer party if `is_offer_party`)
+/// needs to fund in addition to its collateral.
+fn get_premium_amount(premium: Option<&PremiumInfo>, is_offer_party: bool) -> u64 {
+    match premium {
+        Some(premium) if premium.paid_by_offer == is_offer_party => premium.amount,
+        _ => 0,
+    }
+}
+
+/// Returns the sum of the outputs of the given transaction paying to the local
+/// party of the contract.
+pub(crate) fn get_own_payout(contract: &SignedContract, tx: &Transaction) -> u64 {
+    let offered_contract = &contract.accepted_contract.offered_contract;
+    let payout_script_pubkey = if offered_contract.is_offer_party {
+        &offered_contract.offer_params.payout_script_pubkey
+    } else {
+        &contract
+            .accepted_contract
+            .accept_params
+            .payout_script_pubkey
+    };
+    tx.output
+        .iter()
+        .filter(|x| &x.script_pubkey == payout_script_pubkey)
+        .map(|x| x.value)
+        .sum()
 }
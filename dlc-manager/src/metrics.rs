@@ -0,0 +1,39 @@
+//! #Metrics interface used by the Manager to report operational data.
+
+use std::time::Duration;
+
+/// Operations performed by the [`crate::manager::Manager`] whose duration is
+/// reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Operation {
+    /// Creation of own adaptor signatures for the CETs of a contract.
+    AdaptorSignatureCreation,
+    /// Verification of the adaptor signatures provided by the counter party.
+    AdaptorSignatureVerification,
+    /// Verification of the refund signature provided by the counter party.
+    RefundSignatureVerification,
+}
+
+/// Direction of a message exchanged with a counter party.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MessageDirection {
+    /// The message was received from the counter party.
+    Incoming,
+    /// The message was generated to be sent to the counter party.
+    Outgoing,
+}
+
+/// Receives metrics from the manager so that they can be exported to a
+/// monitoring system. All methods have a default empty implementation so that
+/// implementers can choose which metrics they are interested in.
+pub trait Metrics {
+    /// Called with the time taken to perform the given operation.
+    fn record_duration(&self, _operation: Operation, _duration: Duration) {}
+
+    /// Called with the serialized size of a message of the given type.
+    fn record_message_size(&self, _message_type: u16, _direction: MessageDirection, _size: usize) {}
+
+    /// Called during periodic checks with the number of contracts currently
+    /// in the given state.
+    fn record_contract_count(&self, _state: &str, _count: usize) {}
+}
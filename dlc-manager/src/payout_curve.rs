@@ -5,6 +5,10 @@ use dlc::{Payout, RangePayout};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+/// The number of fractional bits of the fixed point numbers used to evaluate
+/// hyperbola pieces in [`PayoutFunction::to_range_payouts_exact`].
+pub const EXACT_FRACTIONAL_BITS: u32 = 32;
+
 /// Contains information to compute the set of payouts based on the outcomes.
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(
@@ -35,6 +39,89 @@ impl PayoutFunction {
         }
     }
 
+    /// Create a payout function following `inner` within the collar defined by
+    /// `floor` and `cap`, and flat at these values for outcomes where `inner`
+    /// would go below or above them. The outcomes at which `inner` crosses the
+    /// collar are computed automatically and its pieces split accordingly.
+    pub fn clamped(inner: &PayoutFunction, floor: u64, cap: u64) -> Result<PayoutFunction, Error> {
+        if floor > cap {
+            return Err(Error::InvalidParameters(
+                "Floor cannot be greater than cap.".to_string(),
+            ));
+        }
+
+        if inner.payout_function_pieces.is_empty() {
+            return Err(Error::InvalidParameters(
+                "Payout function must have at least one piece.".to_string(),
+            ));
+        }
+
+        // Split the outcomes into consecutive segments where the function is
+        // either below the floor, above the cap or within the collar.
+        let mut segments: Vec<(CollarPosition, u64, u64)> = Vec::new();
+        let mut piece_index = 0;
+        for outcome in inner.get_first_outcome()..(inner.get_last_outcome() + 1) {
+            while inner.payout_function_pieces[piece_index]
+                .get_last_point()
+                .event_outcome
+                < outcome
+            {
+                piece_index += 1;
+            }
+            let payout = inner.payout_function_pieces[piece_index].evaluate(outcome);
+            let position = if payout < floor as f64 {
+                CollarPosition::Floor
+            } else if payout > cap as f64 {
+                CollarPosition::Cap
+            } else {
+                CollarPosition::Within(piece_index)
+            };
+            match segments.last_mut() {
+                Some((cur_position, _, end)) if *cur_position == position => *end = outcome,
+                _ => segments.push((position, outcome, outcome)),
+            }
+        }
+
+        let get_clamped_point = |outcome: u64| {
+            PayoutPoint::from_payout(
+                outcome,
+                inner.evaluate(outcome).max(floor as f64).min(cap as f64),
+            )
+        };
+
+        let mut pieces = Vec::new();
+        for (i, (position, start, end)) in segments.iter().enumerate() {
+            if i > 0 {
+                // Consecutive segments are connected by a linear piece over the
+                // last outcome of the previous segment and the first of this one.
+                pieces.push(PayoutFunctionPiece::PolynomialPayoutCurvePiece(
+                    PolynomialPayoutCurvePiece {
+                        payout_points: vec![
+                            get_clamped_point(start - 1),
+                            get_clamped_point(*start),
+                        ],
+                    },
+                ));
+            }
+
+            if start == end {
+                continue;
+            }
+
+            let piece = match position {
+                CollarPosition::Within(index) => {
+                    inner.payout_function_pieces[*index].restrict(*start, *end)
+                }
+                _ => PayoutFunctionPiece::PolynomialPayoutCurvePiece(PolynomialPayoutCurvePiece {
+                    payout_points: vec![get_clamped_point(*start), get_clamped_point(*end)],
+                }),
+            };
+            pieces.push(piece);
+        }
+
+        PayoutFunction::new(pieces)
+    }
+
     /// Generate the range payouts from the function.
     pub fn to_range_payouts(
         &self,
@@ -47,6 +134,320 @@ impl PayoutFunction {
         }
         range_payouts
     }
+
+    /// Generate the range payouts from the function, evaluating it using
+    /// integer arithmetic instead of floating point so that all parties
+    /// compute the same payouts regardless of their platform. Polynomial
+    /// pieces are evaluated exactly, and hyperbola pieces in fixed point with
+    /// [`EXACT_FRACTIONAL_BITS`] fractional bits. An error is returned if an
+    /// intermediate value overflows or if a payout exceeds the total
+    /// collateral.
+    pub fn to_range_payouts_exact(
+        &self,
+        total_collateral: u64,
+        rounding_intervals: &RoundingIntervals,
+    ) -> Result<Vec<RangePayout>, Error> {
+        let mut range_payouts = Vec::new();
+        for piece in &self.payout_function_pieces {
+            piece.to_range_payouts_exact(
+                total_collateral,
+                rounding_intervals,
+                &mut range_payouts,
+            )?;
+        }
+        Ok(range_payouts)
+    }
+
+    /// Generate the range payouts from the sum of the given functions, the
+    /// payouts of each function being added up before rounding. All functions
+    /// must be defined over the same outcome interval, and an error is returned
+    /// if the rounded sum exceeds the total collateral for any outcome.
+    pub fn sum_to_range_payouts(
+        payout_functions: &[&PayoutFunction],
+        total_collateral: u64,
+        rounding_intervals: &RoundingIntervals,
+    ) -> Result<Vec<RangePayout>, Error> {
+        PayoutFunction::sum_to_range_payouts_internal(
+            payout_functions,
+            total_collateral,
+            rounding_intervals,
+            false,
+        )
+    }
+
+    /// Same as [`PayoutFunction::sum_to_range_payouts`], the functions being
+    /// evaluated and summed as in [`PayoutFunction::to_range_payouts_exact`].
+    pub fn sum_to_range_payouts_exact(
+        payout_functions: &[&PayoutFunction],
+        total_collateral: u64,
+        rounding_intervals: &RoundingIntervals,
+    ) -> Result<Vec<RangePayout>, Error> {
+        PayoutFunction::sum_to_range_payouts_internal(
+            payout_functions,
+            total_collateral,
+            rounding_intervals,
+            true,
+        )
+    }
+
+    fn sum_to_range_payouts_internal(
+        payout_functions: &[&PayoutFunction],
+        total_collateral: u64,
+        rounding_intervals: &RoundingIntervals,
+        exact: bool,
+    ) -> Result<Vec<RangePayout>, Error> {
+        let first_outcome = payout_functions
+            .first()
+            .map_or(0, |x| x.get_first_outcome());
+        let last_outcome = payout_functions.first().map_or(0, |x| x.get_last_outcome());
+        if payout_functions.is_empty()
+            || payout_functions.iter().any(|x| {
+                x.payout_function_pieces.is_empty()
+                    || x.get_first_outcome() != first_outcome
+                    || x.get_last_outcome() != last_outcome
+            })
+        {
+            return Err(Error::InvalidParameters(
+                "Summed payout functions must be defined over the same outcome interval."
+                    .to_string(),
+            ));
+        }
+
+        let mut range_payouts: Vec<RangePayout> = Vec::new();
+        for outcome in first_outcome..(last_outcome + 1) {
+            let payout = if exact {
+                let mut sum = Rational::from_integer(0);
+                for payout_function in payout_functions {
+                    sum = sum.add(payout_function.evaluate_exact(outcome)?)?;
+                }
+                rounding_intervals.round_exact(outcome, sum)?
+            } else {
+                let payout_double = payout_functions.iter().map(|x| x.evaluate(outcome)).sum();
+                rounding_intervals.round(outcome, payout_double)
+            };
+            if payout > total_collateral {
+                return Err(Error::InvalidParameters(format!(
+                    "Summed payout for outcome {} exceeds the total collateral.",
+                    outcome
+                )));
+            }
+            match range_payouts.last_mut() {
+                Some(cur_range) if cur_range.payout.offer == payout => cur_range.count += 1,
+                _ => range_payouts.push(RangePayout {
+                    start: outcome as usize,
+                    count: 1,
+                    payout: Payout {
+                        offer: payout,
+                        accept: total_collateral - payout,
+                    },
+                }),
+            }
+        }
+
+        Ok(range_payouts)
+    }
+
+    /// Sample the function at `nb_points` outcomes evenly distributed between
+    /// its first and last outcome (both included), returning the outcome and
+    /// the unrounded payout computed for it. Can be used to visually check that
+    /// the function matches the intended payout profile.
+    pub fn sample(&self, nb_points: usize) -> Vec<(u64, f64)> {
+        if nb_points == 0 || self.payout_function_pieces.is_empty() {
+            return Vec::new();
+        }
+
+        let first_outcome = self.get_first_outcome();
+        let last_outcome = self.get_last_outcome();
+
+        if nb_points == 1 || first_outcome == last_outcome {
+            return vec![(first_outcome, self.evaluate(first_outcome))];
+        }
+
+        let span = (last_outcome - first_outcome) as u128;
+        let mut samples: Vec<(u64, f64)> = Vec::with_capacity(nb_points);
+        for i in 0..nb_points {
+            let outcome = first_outcome + ((span * i as u128) / ((nb_points - 1) as u128)) as u64;
+            if samples.last().map(|(o, _)| *o) == Some(outcome) {
+                continue;
+            }
+            samples.push((outcome, self.evaluate(outcome)));
+        }
+
+        samples
+    }
+
+    /// Render the function and the step function actually enforced by the
+    /// CETs (after rounding) as an SVG document of the given dimensions. The
+    /// function is drawn in blue and the rounded payouts in red.
+    #[cfg(feature = "plot")]
+    pub fn to_svg(
+        &self,
+        total_collateral: u64,
+        rounding_intervals: &RoundingIntervals,
+        width: u32,
+        height: u32,
+    ) -> String {
+        use std::fmt::Write;
+
+        let first_outcome = self.get_first_outcome() as f64;
+        let outcome_span = ((self.get_last_outcome() as f64) - first_outcome).max(1.0);
+        let payout_span = (total_collateral as f64).max(1.0);
+        let to_x = |outcome: f64| (outcome - first_outcome) * (width as f64) / outcome_span;
+        let to_y = |payout: f64| (height as f64) - payout * (height as f64) / payout_span;
+
+        let mut curve_points = String::new();
+        for (outcome, payout) in self.sample(width.max(2) as usize) {
+            write!(
+                curve_points,
+                "{:.2},{:.2} ",
+                to_x(outcome as f64),
+                to_y(payout)
+            )
+            .expect("to be able to write to string");
+        }
+
+        let mut step_points = String::new();
+        for range in self.to_range_payouts(total_collateral, rounding_intervals) {
+            let y = to_y(range.payout.offer as f64);
+            write!(
+                step_points,
+                "{:.2},{:.2} {:.2},{:.2} ",
+                to_x(range.start as f64),
+                y,
+                to_x((range.start + range.count - 1) as f64),
+                y
+            )
+            .expect("to be able to write to string");
+        }
+
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n\
+             <polyline fill=\"none\" stroke=\"blue\" points=\"{}\"/>\n\
+             <polyline fill=\"none\" stroke=\"red\" points=\"{}\"/>\n\
+             </svg>\n",
+            curve_points.trim_end(),
+            step_points.trim_end(),
+            w = width,
+            h = height
+        )
+    }
+
+    /// Returns the sensitivity (delta) of the unrounded payout to the outcome,
+    /// estimated by the central difference of the payouts `step` outcomes
+    /// before and after the given one. The difference is one sided at the
+    /// boundaries of the function domain.
+    pub fn delta(&self, outcome: u64, step: u64) -> f64 {
+        let step = step.max(1);
+        let low = outcome.saturating_sub(step).max(self.get_first_outcome());
+        let high = outcome.saturating_add(step).min(self.get_last_outcome());
+        if high <= low {
+            return 0.0;
+        }
+        (self.evaluate(high) - self.evaluate(low)) / ((high - low) as f64)
+    }
+
+    /// Returns, for each rounding interval overlapping the domain of the
+    /// function, the largest difference between the unrounded payout and the
+    /// payout enforced by the CETs, which quantifies the discretization error
+    /// introduced by the rounding intervals. The function is evaluated at
+    /// every outcome of its domain.
+    pub fn get_rounding_errors(
+        &self,
+        total_collateral: u64,
+        rounding_intervals: &RoundingIntervals,
+    ) -> Vec<RoundingError> {
+        let mut errors: Vec<RoundingError> = Vec::new();
+        let mut cur_interval = None;
+        for range in self.to_range_payouts(total_collateral, rounding_intervals) {
+            for outcome in (range.start as u64)..((range.start + range.count) as u64) {
+                let error = (self.evaluate(outcome) - range.payout.offer as f64).abs();
+                let interval = rounding_intervals.get_interval_index(outcome);
+                match errors.last_mut() {
+                    Some(last) if cur_interval == Some(interval) => {
+                        last.end_interval = outcome;
+                        if error > last.max_error {
+                            last.max_error = error;
+                            last.max_error_outcome = outcome;
+                        }
+                    }
+                    _ => {
+                        cur_interval = Some(interval);
+                        errors.push(RoundingError {
+                            begin_interval: outcome,
+                            end_interval: outcome,
+                            rounding_mod: rounding_intervals.intervals[interval].rounding_mod,
+                            max_error: error,
+                            max_error_outcome: outcome,
+                        });
+                    }
+                }
+            }
+        }
+        errors
+    }
+
+    fn get_first_outcome(&self) -> u64 {
+        self.payout_function_pieces
+            .first()
+            .map_or(0, |x| x.get_first_point().event_outcome)
+    }
+
+    pub(crate) fn get_last_outcome(&self) -> u64 {
+        self.payout_function_pieces
+            .last()
+            .map_or(0, |x| x.get_last_point().event_outcome)
+    }
+
+    /// Returns the function restricted to the outcomes up to `max_outcome`,
+    /// the piece containing it ending at it and the following ones being
+    /// removed.
+    pub(crate) fn truncated(&self, max_outcome: u64) -> PayoutFunction {
+        let payout_function_pieces = self
+            .payout_function_pieces
+            .iter()
+            .enumerate()
+            .filter(|(i, x)| *i == 0 || x.get_first_point().event_outcome < max_outcome)
+            .map(|(_, x)| {
+                let first_outcome = x.get_first_point().event_outcome;
+                if x.get_last_point().event_outcome > max_outcome && first_outcome <= max_outcome {
+                    x.restrict(first_outcome, max_outcome)
+                } else {
+                    x.clone()
+                }
+            })
+            .collect();
+        PayoutFunction {
+            payout_function_pieces,
+        }
+    }
+
+    /// Returns the unrounded payout of the function for the given outcome.
+    pub fn evaluate(&self, outcome: u64) -> f64 {
+        let piece = self
+            .payout_function_pieces
+            .iter()
+            .find(|x| x.get_last_point().event_outcome >= outcome)
+            .unwrap_or_else(|| self.payout_function_pieces.last().unwrap());
+        piece.evaluate(outcome)
+    }
+
+    fn evaluate_exact(&self, outcome: u64) -> Result<Rational, Error> {
+        let piece = self
+            .payout_function_pieces
+            .iter()
+            .find(|x| x.get_last_point().event_outcome >= outcome)
+            .unwrap_or_else(|| self.payout_function_pieces.last().unwrap());
+        piece.evaluate_exact(outcome)
+    }
+}
+
+/// Position of a payout function relative to a collar, with the index of the
+/// piece being used when within it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum CollarPosition {
+    Floor,
+    Cap,
+    Within(usize),
 }
 
 /// A piece of a payout function.
@@ -81,6 +482,75 @@ impl PayoutFunctionPiece {
         }
     }
 
+    /// Generate the range payouts for the function piece using integer
+    /// arithmetic (see [`PayoutFunction::to_range_payouts_exact`]).
+    pub fn to_range_payouts_exact(
+        &self,
+        total_collateral: u64,
+        rounding_intervals: &RoundingIntervals,
+        range_payouts: &mut Vec<RangePayout>,
+    ) -> Result<(), Error> {
+        match self {
+            PayoutFunctionPiece::PolynomialPayoutCurvePiece(p) => {
+                p.to_range_payouts_exact(rounding_intervals, total_collateral, range_payouts)
+            }
+            PayoutFunctionPiece::HyperbolaPayoutCurvePiece(h) => {
+                h.to_range_payouts_exact(rounding_intervals, total_collateral, range_payouts)
+            }
+        }
+    }
+
+    fn evaluate(&self, outcome: u64) -> f64 {
+        match self {
+            PayoutFunctionPiece::PolynomialPayoutCurvePiece(p) => p.evaluate(outcome),
+            PayoutFunctionPiece::HyperbolaPayoutCurvePiece(h) => h.evaluate(outcome),
+        }
+    }
+
+    fn evaluate_exact(&self, outcome: u64) -> Result<Rational, Error> {
+        match self {
+            PayoutFunctionPiece::PolynomialPayoutCurvePiece(p) => p.evaluate_exact(outcome),
+            PayoutFunctionPiece::HyperbolaPayoutCurvePiece(h) => h.evaluate_exact(outcome),
+        }
+    }
+
+    /// Returns a piece following this one between the given outcomes.
+    fn restrict(&self, first_outcome: u64, last_outcome: u64) -> PayoutFunctionPiece {
+        match self {
+            PayoutFunctionPiece::PolynomialPayoutCurvePiece(p) => {
+                let nb_points = p.payout_points.len() as u64;
+                let outcomes: Vec<u64> = if last_outcome - first_outcome + 1 >= nb_points {
+                    (0..nb_points)
+                        .map(|i| {
+                            first_outcome + (last_outcome - first_outcome) * i / (nb_points - 1)
+                        })
+                        .collect()
+                } else {
+                    (first_outcome..(last_outcome + 1)).collect()
+                };
+                PayoutFunctionPiece::PolynomialPayoutCurvePiece(PolynomialPayoutCurvePiece {
+                    payout_points: outcomes
+                        .into_iter()
+                        .map(|x| PayoutPoint::from_payout(x, p.evaluate(x)))
+                        .collect(),
+                })
+            }
+            PayoutFunctionPiece::HyperbolaPayoutCurvePiece(h) => {
+                PayoutFunctionPiece::HyperbolaPayoutCurvePiece(HyperbolaPayoutCurvePiece {
+                    left_end_point: PayoutPoint::from_payout(
+                        first_outcome,
+                        h.evaluate(first_outcome),
+                    ),
+                    right_end_point: PayoutPoint::from_payout(
+                        last_outcome,
+                        h.evaluate(last_outcome),
+                    ),
+                    ..h.clone()
+                })
+            }
+        }
+    }
+
     fn get_first_point(&self) -> &PayoutPoint {
         match self {
             PayoutFunctionPiece::PolynomialPayoutCurvePiece(p) => &p.payout_points[0],
@@ -99,6 +569,8 @@ impl PayoutFunctionPiece {
 trait Evaluable {
     fn evaluate(&self, outcome: u64) -> f64;
 
+    fn evaluate_exact(&self, outcome: u64) -> Result<Rational, Error>;
+
     fn get_rounded_payout(&self, outcome: u64, rounding_intervals: &RoundingIntervals) -> u64 {
         let payout_double = self.evaluate(outcome);
         rounding_intervals.round(outcome, payout_double)
@@ -114,38 +586,83 @@ trait Evaluable {
         total_collateral: u64,
         range_payouts: &mut Vec<RangePayout>,
     ) {
-        let first_outcome = self.get_first_outcome();
-        let mut cur_range = range_payouts.pop().unwrap_or_else(|| {
-            let first_payout = self.get_rounded_payout(first_outcome, rounding_intervals);
-            RangePayout {
-                start: first_outcome as usize,
-                count: 1,
-                payout: Payout {
-                    offer: first_payout,
-                    accept: total_collateral - first_payout,
-                },
-            }
-        });
+        let payouts = (self.get_first_outcome()..(self.get_last_outcome() + 1))
+            .map(|x| self.get_rounded_payout(x, rounding_intervals));
+        push_range_payouts(
+            range_payouts,
+            self.get_first_outcome(),
+            total_collateral,
+            payouts,
+        );
+    }
 
-        for outcome in (first_outcome + 1)..(self.get_last_outcome() + 1) {
-            let payout = self.get_rounded_payout(outcome, rounding_intervals);
-            if cur_range.payout.offer == payout {
-                cur_range.count += 1;
-            } else {
-                range_payouts.push(cur_range);
-                cur_range = RangePayout {
-                    start: outcome as usize,
-                    count: 1,
-                    payout: Payout {
-                        offer: payout,
-                        accept: total_collateral - payout,
-                    },
-                };
+    fn to_range_payouts_exact(
+        &self,
+        rounding_intervals: &RoundingIntervals,
+        total_collateral: u64,
+        range_payouts: &mut Vec<RangePayout>,
+    ) -> Result<(), Error> {
+        let mut payouts = Vec::new();
+        for outcome in self.get_first_outcome()..(self.get_last_outcome() + 1) {
+            let payout = rounding_intervals.round_exact(outcome, self.evaluate_exact(outcome)?)?;
+            if payout > total_collateral {
+                return Err(Error::InvalidParameters(format!(
+                    "Payout for outcome {} exceeds the total collateral.",
+                    outcome
+                )));
             }
+            payouts.push(payout);
         }
+        push_range_payouts(
+            range_payouts,
+            self.get_first_outcome(),
+            total_collateral,
+            payouts.into_iter(),
+        );
+        Ok(())
+    }
+}
 
-        range_payouts.push(cur_range);
+/// Appends the given rounded payouts of consecutive outcomes starting at
+/// `first_outcome` to the range payouts. If the range payouts are not empty,
+/// the first outcome is the last one of the previous function piece, whose
+/// range is extended.
+fn push_range_payouts<I: Iterator<Item = u64>>(
+    range_payouts: &mut Vec<RangePayout>,
+    first_outcome: u64,
+    total_collateral: u64,
+    mut payouts: I,
+) {
+    let first_payout = payouts.next();
+    let mut cur_range = range_payouts.pop().unwrap_or_else(|| {
+        let first_payout = first_payout.expect("a payout for the first outcome");
+        RangePayout {
+            start: first_outcome as usize,
+            count: 1,
+            payout: Payout {
+                offer: first_payout,
+                accept: total_collateral - first_payout,
+            },
+        }
+    });
+
+    for (outcome, payout) in ((first_outcome + 1)..).zip(payouts) {
+        if cur_range.payout.offer == payout {
+            cur_range.count += 1;
+        } else {
+            range_payouts.push(cur_range);
+            cur_range = RangePayout {
+                start: outcome as usize,
+                count: 1,
+                payout: Payout {
+                    offer: payout,
+                    accept: total_collateral - payout,
+                },
+            };
+        }
     }
+
+    range_payouts.push(cur_range);
 }
 
 /// A function piece represented by a polynomial.
@@ -204,6 +721,25 @@ impl Evaluable for PolynomialPayoutCurvePiece {
         result
     }
 
+    fn evaluate_exact(&self, outcome: u64) -> Result<Rational, Error> {
+        let mut result = Rational::from_integer(0);
+
+        for (i, point) in self.payout_points.iter().enumerate() {
+            let mut l = point.get_exact_outcome_payout()?;
+            for (j, other) in self.payout_points.iter().enumerate() {
+                if i != j {
+                    l = l.mul(Rational::new(
+                        outcome as i128 - other.event_outcome as i128,
+                        point.event_outcome as i128 - other.event_outcome as i128,
+                    )?)?;
+                }
+            }
+            result = result.add(l)?;
+        }
+
+        Ok(result)
+    }
+
     fn get_first_outcome(&self) -> u64 {
         self.payout_points[0].event_outcome
     }
@@ -233,6 +769,23 @@ impl PayoutPoint {
     fn get_outcome_payout(&self) -> f64 {
         (self.outcome_payout as f64) + ((self.extra_precision as f64) / ((1 << 16) as f64))
     }
+
+    fn get_exact_outcome_payout(&self) -> Result<Rational, Error> {
+        Rational::new(
+            ((self.outcome_payout as i128) << 16) + self.extra_precision as i128,
+            1 << 16,
+        )
+    }
+
+    fn from_payout(event_outcome: u64, payout: f64) -> PayoutPoint {
+        let payout = payout.max(0.0);
+        let outcome_payout = payout.floor();
+        PayoutPoint {
+            event_outcome,
+            outcome_payout: outcome_payout as u64,
+            extra_precision: ((payout - outcome_payout) * ((1 << 16) as f64)).floor() as u16,
+        }
+    }
 }
 
 /// A function piece represented by a hyperbola.
@@ -317,6 +870,33 @@ impl Evaluable for HyperbolaPayoutCurvePiece {
         first_term + second_term + self.translate_payout
     }
 
+    fn evaluate_exact(&self, outcome: u64) -> Result<Rational, Error> {
+        let a = Fixed::from_f64(self.a)?;
+        let translated_outcome =
+            Fixed::from_integer(outcome as i128)?.sub(Fixed::from_f64(self.translate_outcome)?)?;
+        let four_ab = Fixed::from_integer(4)?
+            .mul(a)?
+            .mul(Fixed::from_f64(self.b)?)?;
+        let sqrt_term_abs_val = translated_outcome
+            .mul(translated_outcome)?
+            .sub(four_ab)?
+            .sqrt()?;
+        let sqrt_term = if self.use_positive_piece {
+            sqrt_term_abs_val
+        } else {
+            Fixed::from_integer(0)?.sub(sqrt_term_abs_val)?
+        };
+
+        let two_a = Fixed::from_integer(2)?.mul(a)?;
+        let sum = translated_outcome.add(sqrt_term)?;
+        let first_term = Fixed::from_f64(self.c)?.mul(sum)?.div(two_a)?;
+        let second_term = two_a.mul(Fixed::from_f64(self.d)?)?.div(sum)?;
+        first_term
+            .add(second_term)?
+            .add(Fixed::from_f64(self.translate_payout)?)?
+            .to_rational()
+    }
+
     fn get_first_outcome(&self) -> u64 {
         self.left_end_point.event_outcome
     }
@@ -328,7 +908,7 @@ impl Evaluable for HyperbolaPayoutCurvePiece {
 /// Provides information on if and how to round the payouts of a payout function
 /// to reduce the number of adaptor signatures required. A `rounding_mod` value
 /// of 1 indicates that no rounding is performed.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(
     feature = "serde",
     derive(Serialize, Deserialize),
@@ -342,7 +922,7 @@ pub struct RoundingInterval {
 }
 
 /// A set of rounding intervals.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(
     feature = "serde",
     derive(Serialize, Deserialize),
@@ -357,14 +937,7 @@ impl RoundingIntervals {
     /// Round the given payout based on the rounding modulus matching the given
     /// outcome.
     pub fn round(&self, outcome: u64, payout: f64) -> u64 {
-        let rounding_mod = match self
-            .intervals
-            .binary_search_by(|x| x.begin_interval.cmp(&outcome))
-        {
-            Ok(index) => self.intervals[index].rounding_mod,
-            Err(index) if index != 0 => self.intervals[index - 1].rounding_mod,
-            _ => unreachable!(),
-        } as f64;
+        let rounding_mod = self.intervals[self.get_interval_index(outcome)].rounding_mod as f64;
 
         let m = if payout >= 0.0 {
             payout % rounding_mod
@@ -378,6 +951,268 @@ impl RoundingIntervals {
             (payout - m).round() as u64
         }
     }
+
+    /// Suggests rounding intervals for the given payout function such that
+    /// the number of range payouts, and thus of CETs, is at most `max_cets`.
+    /// An interval is created for each piece of the function, pieces with a
+    /// constant payout using a modulus of one, and the other ones sharing the
+    /// smallest modulus found to satisfy the constraint by binary search. As
+    /// the rounding error is bounded by half of the modulus, this minimizes
+    /// the worst case error of the payouts.
+    pub fn suggest(
+        payout_function: &PayoutFunction,
+        total_collateral: u64,
+        max_cets: usize,
+    ) -> Result<RoundingIntervals, Error> {
+        let pieces = &payout_function.payout_function_pieces;
+        let is_constant: Vec<bool> = pieces
+            .iter()
+            .map(|piece| {
+                let mut range_payouts = Vec::new();
+                piece.to_range_payouts(
+                    total_collateral,
+                    &RoundingIntervals::with_modulus(1),
+                    &mut range_payouts,
+                );
+                range_payouts.len() <= 1
+            })
+            .collect();
+
+        let get_intervals = |rounding_mod: u64| {
+            let mut intervals: Vec<RoundingInterval> = Vec::new();
+            for (i, piece) in pieces.iter().enumerate() {
+                let rounding_mod = if is_constant[i] { 1 } else { rounding_mod };
+                if intervals.last().map(|x| x.rounding_mod) != Some(rounding_mod) {
+                    intervals.push(RoundingInterval {
+                        begin_interval: if i == 0 {
+                            0
+                        } else {
+                            piece.get_first_point().event_outcome
+                        },
+                        rounding_mod,
+                    });
+                }
+            }
+            RoundingIntervals { intervals }
+        };
+        let get_nb_cets = |rounding_intervals: &RoundingIntervals| {
+            payout_function
+                .to_range_payouts(total_collateral, rounding_intervals)
+                .len()
+        };
+
+        let mut low = 1;
+        let mut high = total_collateral.max(1);
+        if get_nb_cets(&get_intervals(high)) > max_cets {
+            return Err(Error::InvalidParameters(format!(
+                "Payout function cannot be rounded to at most {} CETs.",
+                max_cets
+            )));
+        }
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if get_nb_cets(&get_intervals(mid)) <= max_cets {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+
+        Ok(get_intervals(high))
+    }
+
+    /// Round the given exact payout as [`RoundingIntervals::round`] does.
+    fn round_exact(&self, outcome: u64, payout: Rational) -> Result<u64, Error> {
+        let rounding_mod = self.intervals[self.get_interval_index(outcome)].rounding_mod as i128;
+        let step = checked(payout.den.checked_mul(rounding_mod))?;
+        let m = payout.num.rem_euclid(step);
+        let rounded_down = checked(payout.num.checked_sub(m))? / payout.den;
+        let rounded = if m >= step - m {
+            checked(rounded_down.checked_add(rounding_mod))?
+        } else {
+            rounded_down
+        };
+
+        Ok(rounded.max(0).min(u64::MAX as i128) as u64)
+    }
+
+    fn with_modulus(rounding_mod: u64) -> RoundingIntervals {
+        RoundingIntervals {
+            intervals: vec![RoundingInterval {
+                begin_interval: 0,
+                rounding_mod,
+            }],
+        }
+    }
+
+    fn get_interval_index(&self, outcome: u64) -> usize {
+        match self
+            .intervals
+            .binary_search_by(|x| x.begin_interval.cmp(&outcome))
+        {
+            Ok(index) => index,
+            Err(index) if index != 0 => index - 1,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// The largest difference between the unrounded payout of a function and the
+/// payout enforced by the CETs over the outcomes of a rounding interval.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoundingError {
+    /// The first outcome of the interval within the function domain.
+    pub begin_interval: u64,
+    /// The last outcome of the interval within the function domain.
+    pub end_interval: u64,
+    /// The rounding modulus used for the interval.
+    pub rounding_mod: u64,
+    /// The largest absolute difference between the unrounded and the enforced
+    /// payout.
+    pub max_error: f64,
+    /// The first outcome at which the largest difference is reached.
+    pub max_error_outcome: u64,
+}
+
+fn overflow_error() -> Error {
+    Error::InvalidParameters("Overflow in exact evaluation of payout function.".to_string())
+}
+
+fn checked(value: Option<i128>) -> Result<i128, Error> {
+    value.ok_or_else(overflow_error)
+}
+
+/// A rational number with a positive denominator, in lowest terms.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Rational {
+    num: i128,
+    den: i128,
+}
+
+impl Rational {
+    fn new(num: i128, den: i128) -> Result<Rational, Error> {
+        if den == 0 {
+            return Err(Error::InvalidParameters(
+                "Division by zero in exact evaluation of payout function.".to_string(),
+            ));
+        }
+        if num == i128::MIN || den == i128::MIN {
+            return Err(overflow_error());
+        }
+        let gcd = gcd(num.unsigned_abs(), den.unsigned_abs()) as i128;
+        let sign = den.signum();
+        Ok(Rational {
+            num: sign * num / gcd,
+            den: sign * den / gcd,
+        })
+    }
+
+    fn from_integer(value: i128) -> Rational {
+        Rational { num: value, den: 1 }
+    }
+
+    fn add(self, other: Rational) -> Result<Rational, Error> {
+        let num = checked(self.num.checked_mul(other.den))?;
+        let other_num = checked(other.num.checked_mul(self.den))?;
+        Rational::new(
+            checked(num.checked_add(other_num))?,
+            checked(self.den.checked_mul(other.den))?,
+        )
+    }
+
+    fn mul(self, other: Rational) -> Result<Rational, Error> {
+        Rational::new(
+            checked(self.num.checked_mul(other.num))?,
+            checked(self.den.checked_mul(other.den))?,
+        )
+    }
+}
+
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+/// A signed fixed point number with [`EXACT_FRACTIONAL_BITS`] fractional
+/// bits. Results of multiplications, divisions and square roots are rounded
+/// towards negative infinity.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Fixed(i128);
+
+impl Fixed {
+    fn from_integer(value: i128) -> Result<Fixed, Error> {
+        Ok(Fixed(checked(
+            value.checked_mul(1 << EXACT_FRACTIONAL_BITS),
+        )?))
+    }
+
+    /// Converts the given value, rounding it to the closest fixed point
+    /// number. The multiplication by a power of two being exact, the
+    /// conversion gives the same result on all platforms.
+    fn from_f64(value: f64) -> Result<Fixed, Error> {
+        let scaled = (value * (1u64 << EXACT_FRACTIONAL_BITS) as f64).round();
+        if !scaled.is_finite() || scaled.abs() >= 2f64.powi(126) {
+            return Err(overflow_error());
+        }
+        Ok(Fixed(scaled as i128))
+    }
+
+    fn add(self, other: Fixed) -> Result<Fixed, Error> {
+        Ok(Fixed(checked(self.0.checked_add(other.0))?))
+    }
+
+    fn sub(self, other: Fixed) -> Result<Fixed, Error> {
+        Ok(Fixed(checked(self.0.checked_sub(other.0))?))
+    }
+
+    fn mul(self, other: Fixed) -> Result<Fixed, Error> {
+        Ok(Fixed(
+            checked(self.0.checked_mul(other.0))? >> EXACT_FRACTIONAL_BITS,
+        ))
+    }
+
+    fn div(self, other: Fixed) -> Result<Fixed, Error> {
+        if other.0 == 0 {
+            return Err(Error::InvalidParameters(
+                "Division by zero in exact evaluation of payout function.".to_string(),
+            ));
+        }
+        let num = checked(self.0.checked_mul(1 << EXACT_FRACTIONAL_BITS))?;
+        Ok(Fixed(checked(num.checked_div_euclid(other.0))?))
+    }
+
+    fn sqrt(self) -> Result<Fixed, Error> {
+        if self.0 < 0 {
+            return Err(Error::InvalidParameters(
+                "Square root of negative value in exact evaluation of payout function.".to_string(),
+            ));
+        }
+        let scaled = checked(self.0.checked_mul(1 << EXACT_FRACTIONAL_BITS))?;
+        Ok(Fixed(isqrt(scaled as u128) as i128))
+    }
+
+    fn to_rational(self) -> Result<Rational, Error> {
+        Rational::new(self.0, 1 << EXACT_FRACTIONAL_BITS)
+    }
+}
+
+/// Returns the largest integer whose square is at most `n`.
+fn isqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+    let mut x = 1u128 << ((128 - n.leading_zeros() + 1) / 2);
+    loop {
+        let y = (x + n / x) / 2;
+        if y >= x {
+            return x;
+        }
+        x = y;
+    }
 }
 
 #[cfg(test)]
@@ -629,6 +1464,381 @@ mod test {
         );
     }
 
+    #[test]
+    fn payout_function_sample_test() {
+        let payout_function = PayoutFunction::new(vec![
+            PayoutFunctionPiece::PolynomialPayoutCurvePiece(
+                PolynomialPayoutCurvePiece::new(vec![
+                    PayoutPoint {
+                        event_outcome: 0,
+                        outcome_payout: 0,
+                        extra_precision: 0,
+                    },
+                    PayoutPoint {
+                        event_outcome: 10,
+                        outcome_payout: 10,
+                        extra_precision: 0,
+                    },
+                ])
+                .unwrap(),
+            ),
+            PayoutFunctionPiece::PolynomialPayoutCurvePiece(
+                PolynomialPayoutCurvePiece::new(vec![
+                    PayoutPoint {
+                        event_outcome: 10,
+                        outcome_payout: 10,
+                        extra_precision: 0,
+                    },
+                    PayoutPoint {
+                        event_outcome: 20,
+                        outcome_payout: 10,
+                        extra_precision: 0,
+                    },
+                ])
+                .unwrap(),
+            ),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            vec![(0, 0.0), (5, 5.0), (10, 10.0), (15, 10.0), (20, 10.0)],
+            payout_function.sample(5)
+        );
+        assert_eq!(vec![(0, 0.0)], payout_function.sample(1));
+        assert!(payout_function.sample(0).is_empty());
+        // Outcomes are not repeated when requesting more points than outcomes.
+        assert_eq!(21, payout_function.sample(100).len());
+    }
+
+    fn linear_function(points: &[(u64, u64)]) -> PayoutFunction {
+        PayoutFunction::new(
+            points
+                .iter()
+                .zip(points.iter().skip(1))
+                .map(|(cur, next)| {
+                    PayoutFunctionPiece::PolynomialPayoutCurvePiece(
+                        PolynomialPayoutCurvePiece::new(vec![
+                            PayoutPoint {
+                                event_outcome: cur.0,
+                                outcome_payout: cur.1,
+                                extra_precision: 0,
+                            },
+                            PayoutPoint {
+                                event_outcome: next.0,
+                                outcome_payout: next.1,
+                                extra_precision: 0,
+                            },
+                        ])
+                        .unwrap(),
+                    )
+                })
+                .collect(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn payout_function_delta_test() {
+        let payout_function = linear_function(&[(0, 0), (100, 50), (200, 50)]);
+
+        assert!((payout_function.delta(0, 10) - 0.5).abs() < 1e-9);
+        assert!((payout_function.delta(50, 10) - 0.5).abs() < 1e-9);
+        assert!((payout_function.delta(100, 10) - 0.25).abs() < 1e-9);
+        assert!(payout_function.delta(150, 10).abs() < 1e-9);
+    }
+
+    #[test]
+    fn payout_function_rounding_errors_test() {
+        let payout_function = linear_function(&[(0, 0), (100, 100)]);
+        let rounding_intervals = RoundingIntervals {
+            intervals: vec![
+                RoundingInterval {
+                    begin_interval: 0,
+                    rounding_mod: 1,
+                },
+                RoundingInterval {
+                    begin_interval: 50,
+                    rounding_mod: 10,
+                },
+            ],
+        };
+
+        let errors = payout_function.get_rounding_errors(100, &rounding_intervals);
+
+        assert_eq!(2, errors.len());
+        assert_eq!(
+            (0, 49, 1),
+            (
+                errors[0].begin_interval,
+                errors[0].end_interval,
+                errors[0].rounding_mod
+            )
+        );
+        assert!(errors[0].max_error < 1e-9);
+        assert_eq!(
+            (50, 100, 10),
+            (
+                errors[1].begin_interval,
+                errors[1].end_interval,
+                errors[1].rounding_mod
+            )
+        );
+        assert!((errors[1].max_error - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn suggest_rounding_intervals_test() {
+        let payout_function = linear_function(&[(0, 0), (100, 100)]);
+
+        let rounding_intervals = RoundingIntervals::suggest(&payout_function, 100, 11).unwrap();
+
+        assert_eq!(1, rounding_intervals.intervals.len());
+        assert_eq!(10, rounding_intervals.intervals[0].rounding_mod);
+        assert_eq!(
+            11,
+            payout_function
+                .to_range_payouts(100, &rounding_intervals)
+                .len()
+        );
+    }
+
+    #[test]
+    fn suggest_rounding_intervals_with_constant_piece_test() {
+        let payout_function = linear_function(&[(0, 0), (100, 0), (200, 100)]);
+
+        let rounding_intervals = RoundingIntervals::suggest(&payout_function, 100, 20).unwrap();
+
+        assert_eq!(2, rounding_intervals.intervals.len());
+        assert_eq!(1, rounding_intervals.intervals[0].rounding_mod);
+        assert_eq!(100, rounding_intervals.intervals[1].begin_interval);
+        assert!(
+            payout_function
+                .to_range_payouts(100, &rounding_intervals)
+                .len()
+                <= 20
+        );
+        assert!(RoundingIntervals::suggest(&payout_function, 100, 1).is_err());
+    }
+
+    #[test]
+    fn clamped_payout_function_test() {
+        let inner = linear_function(&[(0, 0), (50, 50), (100, 100)]);
+
+        let clamped = PayoutFunction::clamped(&inner, 20, 80).expect("to be able to clamp");
+
+        for (outcome, expected) in [
+            (0, 20.0),
+            (10, 20.0),
+            (20, 20.0),
+            (30, 30.0),
+            (50, 50.0),
+            (70, 70.0),
+            (80, 80.0),
+            (100, 80.0),
+        ]
+        .iter()
+        {
+            assert!((clamped.evaluate(*outcome) - expected).abs() < 0.001);
+        }
+        assert_eq!(0, clamped.get_first_outcome());
+        assert_eq!(100, clamped.get_last_outcome());
+
+        PayoutFunction::clamped(&inner, 80, 20).expect_err("Floor above cap should error");
+    }
+
+    #[test]
+    fn sum_to_range_payouts_test() {
+        let call = linear_function(&[(0, 0), (10, 0), (20, 10)]);
+        let put = linear_function(&[(0, 10), (10, 0), (20, 0)]);
+        let rounding_intervals = RoundingIntervals {
+            intervals: vec![RoundingInterval {
+                begin_interval: 0,
+                rounding_mod: 1,
+            }],
+        };
+
+        let range_payouts =
+            PayoutFunction::sum_to_range_payouts(&[&call, &put], 10, &rounding_intervals)
+                .expect("to be able to sum the functions");
+
+        assert_eq!(21, range_payouts.len());
+        assert_eq!(10, range_payouts[0].payout.offer);
+        assert_eq!(0, range_payouts[10].payout.offer);
+        assert_eq!(10, range_payouts[10].payout.accept);
+        assert_eq!(10, range_payouts[20].payout.offer);
+
+        PayoutFunction::sum_to_range_payouts(&[&call, &put], 5, &rounding_intervals)
+            .expect_err("Sum exceeding the total collateral should error");
+
+        let shorter = linear_function(&[(0, 0), (10, 10)]);
+        PayoutFunction::sum_to_range_payouts(&[&call, &shorter], 10, &rounding_intervals)
+            .expect_err("Functions over different outcomes should error");
+    }
+
+    #[test]
+    fn exact_lagrange_interpolate_test() {
+        let polynomial = PolynomialPayoutCurvePiece {
+            payout_points: vec![
+                PayoutPoint {
+                    event_outcome: 0,
+                    outcome_payout: 1,
+                    extra_precision: 0,
+                },
+                PayoutPoint {
+                    event_outcome: 2,
+                    outcome_payout: 5,
+                    extra_precision: 0,
+                },
+                PayoutPoint {
+                    event_outcome: 4,
+                    outcome_payout: 17,
+                    extra_precision: 1 << 15,
+                },
+            ],
+        };
+
+        assert_eq!(
+            Rational::new(106, 1).unwrap(),
+            polynomial.evaluate_exact(10).unwrap()
+        );
+        assert_eq!(
+            Rational::new(31, 16).unwrap(),
+            polynomial.evaluate_exact(1).unwrap()
+        );
+
+        let overflowing = PolynomialPayoutCurvePiece {
+            payout_points: (40..45)
+                .map(|x| PayoutPoint {
+                    event_outcome: 1 << x,
+                    outcome_payout: 1,
+                    extra_precision: 0,
+                })
+                .collect(),
+        };
+        overflowing
+            .evaluate_exact(1)
+            .expect_err("Overflowing evaluation should error");
+    }
+
+    #[test]
+    fn to_range_payouts_exact_test() {
+        let payout_function = linear_function(&[(0, 0), (10, 1), (20, 11)]);
+        let rounding_intervals = RoundingIntervals {
+            intervals: vec![
+                RoundingInterval {
+                    begin_interval: 0,
+                    rounding_mod: 1,
+                },
+                RoundingInterval {
+                    begin_interval: 10,
+                    rounding_mod: 4,
+                },
+            ],
+        };
+        let range_payout = |start: usize, count: usize, offer: u64| RangePayout {
+            start,
+            count,
+            payout: Payout {
+                offer,
+                accept: 12 - offer,
+            },
+        };
+
+        // Halves are rounded up, e.g. 0.5 at outcome 5 and 2 at outcome 11.
+        assert_eq!(
+            vec![
+                range_payout(0, 5, 0),
+                range_payout(5, 5, 1),
+                range_payout(10, 1, 0),
+                range_payout(11, 4, 4),
+                range_payout(15, 4, 8),
+                range_payout(19, 2, 12),
+            ],
+            payout_function
+                .to_range_payouts_exact(12, &rounding_intervals)
+                .expect("to be able to compute the range payouts")
+        );
+
+        payout_function
+            .to_range_payouts_exact(11, &rounding_intervals)
+            .expect_err("Payouts exceeding the total collateral should error");
+    }
+
+    #[test]
+    fn hyperbola_exact_test() {
+        let hyperbola = HyperbolaPayoutCurvePiece {
+            left_end_point: PayoutPoint {
+                event_outcome: 1,
+                outcome_payout: 0,
+                extra_precision: 0,
+            },
+            right_end_point: PayoutPoint {
+                event_outcome: 1000,
+                outcome_payout: 0,
+                extra_precision: 0,
+            },
+            use_positive_piece: true,
+            translate_outcome: 0.0,
+            translate_payout: 10.25,
+            a: 1.0,
+            b: 0.0,
+            c: 0.0,
+            d: 1000.5,
+        };
+
+        for outcome in 1..1000 {
+            let exact = hyperbola.evaluate_exact(outcome).unwrap();
+            let difference = exact.num as f64 / exact.den as f64 - hyperbola.evaluate(outcome);
+            assert!(difference.abs() < 1e-6);
+        }
+
+        let negative_sqrt = HyperbolaPayoutCurvePiece {
+            b: 1e9,
+            ..hyperbola
+        };
+        negative_sqrt
+            .evaluate_exact(1)
+            .expect_err("Square root of negative value should error");
+    }
+
+    #[cfg(feature = "plot")]
+    #[test]
+    fn payout_function_to_svg_test() {
+        let payout_function =
+            PayoutFunction::new(vec![PayoutFunctionPiece::PolynomialPayoutCurvePiece(
+                PolynomialPayoutCurvePiece::new(vec![
+                    PayoutPoint {
+                        event_outcome: 0,
+                        outcome_payout: 0,
+                        extra_precision: 0,
+                    },
+                    PayoutPoint {
+                        event_outcome: 10,
+                        outcome_payout: 10,
+                        extra_precision: 0,
+                    },
+                ])
+                .unwrap(),
+            )])
+            .unwrap();
+
+        let svg = payout_function.to_svg(
+            10,
+            &RoundingIntervals {
+                intervals: vec![RoundingInterval {
+                    begin_interval: 0,
+                    rounding_mod: 2,
+                }],
+            },
+            100,
+            100,
+        );
+
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(2, svg.matches("<polyline").count());
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+
     #[test]
     fn polynomial_payout_curve_validity_test() {
         let invalid = vec![
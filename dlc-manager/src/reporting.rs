@@ -0,0 +1,335 @@
+//! # Reporting
+//! Accounting records of the contracts held in a [`Storage`], with their
+//! collateral, fees, payouts and timestamps, which can be exported as CSV or
+//! JSON for tax and treasury reporting.
+
+use crate::contract::{ArchivedContract, Contract, ContractState};
+use crate::error::Error;
+use crate::{ContractId, Storage, TemporaryContractId};
+use secp256k1_zkp::PublicKey;
+use std::fmt::Write;
+
+const CSV_HEADER: &str = "temporary_id,contract_id,state,is_offer_party,counter_party,own_collateral,total_collateral,locked_collateral,fund_tx_fee,closing_tx_fee,own_payout,opened_at,closed_at";
+
+/// The accounting information of a contract.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContractRecord {
+    /// The temporary id of the contract.
+    pub temporary_id: TemporaryContractId,
+    /// The id of the contract, `None` if it was not accepted.
+    pub contract_id: Option<ContractId>,
+    /// The state of the contract.
+    pub state: ContractState,
+    /// Whether the local party is the offering party.
+    pub is_offer_party: bool,
+    /// The public key of the counter party.
+    pub counter_party: PublicKey,
+    /// The collateral put in the contract by the local party.
+    pub own_collateral: u64,
+    /// The total collateral of the contract.
+    pub total_collateral: u64,
+    /// The collateral of the local party currently locked in the fund output
+    /// of the contract, zero once the contract is closed.
+    pub locked_collateral: u64,
+    /// The fee paid by the fund transaction, `None` if it was not broadcast.
+    pub fund_tx_fee: Option<u64>,
+    /// The fee paid by the closing transaction, `None` if the contract is not
+    /// closed.
+    pub closing_tx_fee: Option<u64>,
+    /// The amount paid to the local party when closing the contract, `None`
+    /// if the contract is not closed.
+    pub own_payout: Option<u64>,
+    /// The unix time at which the contract was offered or received, if known.
+    pub opened_at: Option<u64>,
+    /// The unix time at which the contract reached its final state, if it did.
+    pub closed_at: Option<u64>,
+}
+
+/// Totals computed over a set of [`ContractRecord`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AggregateRecord {
+    /// The number of contracts.
+    pub nb_contracts: usize,
+    /// The number of closed or refunded contracts.
+    pub nb_closed_contracts: usize,
+    /// The collateral of the local party currently locked in contracts.
+    pub locked_collateral: u64,
+    /// The fees paid by the fund and closing transactions of the contracts.
+    pub fees_paid: u64,
+    /// The payouts received by the local party from closed contracts.
+    pub payouts_received: u64,
+    /// The payouts received by the local party minus its collateral, over the
+    /// closed contracts.
+    pub realized_pnl: i64,
+}
+
+impl ContractRecord {
+    fn from_contract(contract: &Contract) -> ContractRecord {
+        if let Some(archived) = ArchivedContract::from_contract(contract) {
+            return ContractRecord::from_archived(&archived);
+        }
+
+        let offered_contract = contract.get_offered_contract();
+        let own_collateral = if offered_contract.is_offer_party {
+            offered_contract.offer_params.collateral
+        } else {
+            offered_contract.total_collateral - offered_contract.offer_params.collateral
+        };
+        let state = contract.get_state();
+        let (locked_collateral, fund_tx_fee) = match contract {
+            Contract::Signed(c) | Contract::Confirmed(c) => {
+                (own_collateral, Some(c.accepted_contract.get_fund_tx_fee()))
+            }
+            Contract::Renewed(c) => (0, Some(c.accepted_contract.get_fund_tx_fee())),
+            _ => (0, None),
+        };
+
+        ContractRecord {
+            temporary_id: offered_contract.id,
+            contract_id: contract.get_id(),
+            state,
+            is_offer_party: offered_contract.is_offer_party,
+            counter_party: offered_contract.counter_party,
+            own_collateral,
+            total_collateral: offered_contract.total_collateral,
+            locked_collateral,
+            fund_tx_fee,
+            closing_tx_fee: None,
+            own_payout: None,
+            opened_at: None,
+            closed_at: None,
+        }
+    }
+
+    fn from_archived(archived: &ArchivedContract) -> ContractRecord {
+        ContractRecord {
+            temporary_id: archived.temporary_id,
+            contract_id: Some(archived.id),
+            state: archived.state,
+            is_offer_party: archived.is_offer_party,
+            counter_party: archived.counter_party,
+            own_collateral: archived.own_collateral,
+            total_collateral: archived.total_collateral,
+            locked_collateral: 0,
+            fund_tx_fee: Some(archived.fund_tx_fee),
+            closing_tx_fee: Some(archived.closing_tx_fee),
+            own_payout: Some(archived.own_payout),
+            opened_at: None,
+            closed_at: None,
+        }
+    }
+
+    /// Returns the header of the CSV rows returned by
+    /// [`ContractRecord::to_csv_row`].
+    pub fn get_csv_header() -> &'static str {
+        CSV_HEADER
+    }
+
+    /// Returns the record as a CSV row, unknown values being left empty.
+    pub fn to_csv_row(&self) -> String {
+        let opt = |x: Option<u64>| x.map(|x| x.to_string()).unwrap_or_default();
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            self.temporary_id,
+            self.contract_id.map(|x| x.to_string()).unwrap_or_default(),
+            self.state.get_name(),
+            self.is_offer_party,
+            self.counter_party,
+            self.own_collateral,
+            self.total_collateral,
+            self.locked_collateral,
+            opt(self.fund_tx_fee),
+            opt(self.closing_tx_fee),
+            opt(self.own_payout),
+            opt(self.opened_at),
+            opt(self.closed_at),
+        )
+    }
+
+    /// Returns the record as a JSON object, unknown values being `null`.
+    pub fn to_json(&self) -> String {
+        let opt = |x: Option<u64>| {
+            x.map(|x| x.to_string())
+                .unwrap_or_else(|| "null".to_string())
+        };
+        format!(
+            "{{\"temporaryId\":\"{}\",\"contractId\":{},\"state\":\"{}\",\"isOfferParty\":{},\"counterParty\":\"{}\",\"ownCollateral\":{},\"totalCollateral\":{},\"lockedCollateral\":{},\"fundTxFee\":{},\"closingTxFee\":{},\"ownPayout\":{},\"openedAt\":{},\"closedAt\":{}}}",
+            self.temporary_id,
+            self.contract_id
+                .map(|x| format!("\"{}\"", x))
+                .unwrap_or_else(|| "null".to_string()),
+            self.state.get_name(),
+            self.is_offer_party,
+            self.counter_party,
+            self.own_collateral,
+            self.total_collateral,
+            self.locked_collateral,
+            opt(self.fund_tx_fee),
+            opt(self.closing_tx_fee),
+            opt(self.own_payout),
+            opt(self.opened_at),
+            opt(self.closed_at),
+        )
+    }
+}
+
+impl AggregateRecord {
+    /// Computes the totals of the given records.
+    pub fn from_records(records: &[ContractRecord]) -> AggregateRecord {
+        let mut aggregate = AggregateRecord {
+            nb_contracts: records.len(),
+            ..Default::default()
+        };
+        for record in records {
+            aggregate.locked_collateral += record.locked_collateral;
+            aggregate.fees_paid += record.fund_tx_fee.unwrap_or(0);
+            aggregate.fees_paid += record.closing_tx_fee.unwrap_or(0);
+            if let Some(own_payout) = record.own_payout {
+                aggregate.nb_closed_contracts += 1;
+                aggregate.payouts_received += own_payout;
+                aggregate.realized_pnl += own_payout as i64 - record.own_collateral as i64;
+            }
+        }
+        aggregate
+    }
+
+    /// Returns the aggregate as a CSV header line followed by a row.
+    pub fn to_csv(&self) -> String {
+        format!(
+            "nb_contracts,nb_closed_contracts,locked_collateral,fees_paid,payouts_received,realized_pnl\n{},{},{},{},{},{}\n",
+            self.nb_contracts,
+            self.nb_closed_contracts,
+            self.locked_collateral,
+            self.fees_paid,
+            self.payouts_received,
+            self.realized_pnl
+        )
+    }
+
+    /// Returns the aggregate as a JSON object.
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"nbContracts\":{},\"nbClosedContracts\":{},\"lockedCollateral\":{},\"feesPaid\":{},\"payoutsReceived\":{},\"realizedPnl\":{}}}",
+            self.nb_contracts,
+            self.nb_closed_contracts,
+            self.locked_collateral,
+            self.fees_paid,
+            self.payouts_received,
+            self.realized_pnl
+        )
+    }
+}
+
+/// Returns the records of all the contracts of the given storage, including
+/// archived ones, with their timestamps taken from the contract histories.
+pub fn get_contract_records<S: Storage + ?Sized>(
+    storage: &S,
+) -> Result<Vec<ContractRecord>, Error> {
+    let mut records: Vec<ContractRecord> = storage
+        .get_contracts()?
+        .iter()
+        .map(ContractRecord::from_contract)
+        .collect();
+    records.extend(
+        storage
+            .get_archived_contracts()?
+            .iter()
+            .map(ContractRecord::from_archived),
+    );
+
+    for record in records.iter_mut() {
+        let history = storage.get_contract_history(&record.temporary_id)?;
+        record.opened_at = history.first().map(|x| x.timestamp);
+        if record.own_payout.is_some()
+            || record.state == ContractState::Renewed
+            || record.state == ContractState::FailedAccept
+            || record.state == ContractState::FailedSign
+        {
+            record.closed_at = history.last().map(|x| x.timestamp);
+        }
+    }
+
+    Ok(records)
+}
+
+/// Returns the given records as CSV, with a header line.
+pub fn to_csv(records: &[ContractRecord]) -> String {
+    let mut res = String::new();
+    writeln!(res, "{}", CSV_HEADER).unwrap();
+    for record in records {
+        writeln!(res, "{}", record.to_csv_row()).unwrap();
+    }
+    res
+}
+
+/// Returns the given records as a JSON array.
+pub fn to_json(records: &[ContractRecord]) -> String {
+    let records: Vec<String> = records.iter().map(|x| x.to_json()).collect();
+    format!("[{}]", records.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1_zkp::{Secp256k1, SecretKey};
+
+    fn get_record(own_payout: Option<u64>, locked_collateral: u64) -> ContractRecord {
+        let secp = Secp256k1::new();
+        ContractRecord {
+            temporary_id: TemporaryContractId([1u8; 32]),
+            contract_id: own_payout.map(|_| ContractId([2u8; 32])),
+            state: if own_payout.is_some() {
+                ContractState::Closed
+            } else {
+                ContractState::Confirmed
+            },
+            is_offer_party: true,
+            counter_party: PublicKey::from_secret_key(
+                &secp,
+                &SecretKey::from_slice(&[3u8; 32]).unwrap(),
+            ),
+            own_collateral: 100000,
+            total_collateral: 200000,
+            locked_collateral,
+            fund_tx_fee: Some(1000),
+            closing_tx_fee: own_payout.map(|_| 500),
+            own_payout,
+            opened_at: Some(10),
+            closed_at: own_payout.map(|_| 20),
+        }
+    }
+
+    #[test]
+    fn aggregate_record_test() {
+        let records = vec![
+            get_record(Some(150000), 0),
+            get_record(Some(20000), 0),
+            get_record(None, 100000),
+        ];
+
+        let aggregate = AggregateRecord::from_records(&records);
+
+        assert_eq!(
+            AggregateRecord {
+                nb_contracts: 3,
+                nb_closed_contracts: 2,
+                locked_collateral: 100000,
+                fees_paid: 4000,
+                payouts_received: 170000,
+                realized_pnl: -30000,
+            },
+            aggregate
+        );
+    }
+
+    #[test]
+    fn to_csv_test() {
+        let csv = to_csv(&[get_record(None, 100000)]);
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(2, lines.len());
+        assert_eq!(CSV_HEADER, lines[0]);
+        assert_eq!(CSV_HEADER.split(',').count(), lines[1].split(',').count());
+        assert!(lines[1].ends_with(",100000,200000,100000,1000,,,10,"));
+    }
+}
@@ -0,0 +1,133 @@
+//! #Transaction test vectors
+//! Generation of the transactions of a contract from the offer, accept and
+//! sign messages exchanged to establish it, in the format of the transaction
+//! test vectors of the DLC specifications, enabling implementations to
+//! verify that they build byte for byte identical transactions.
+
+use crate::contract::offered_contract::OfferedContract;
+use crate::conversion_utils::get_tx_input_infos;
+use crate::error::Error;
+use bitcoin::consensus::encode::serialize_hex;
+use dlc::{DlcTransactions, PartyParams};
+use dlc_messages::{compute_contract_id, AcceptDlc, OfferDlc, SignDlc};
+use secp256k1_zkp::{Secp256k1, Verification};
+
+/// The hex encoded unsigned transactions of a contract.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct TransactionTestVectors {
+    /// The fund transaction.
+    pub funding_tx: String,
+    /// The CETs, in the order in which their adaptor signatures are sent.
+    pub cets: Vec<String>,
+    /// The refund transaction.
+    pub refund_tx: String,
+}
+
+/// Returns the transactions of the contract established using the given
+/// messages. The messages are checked to belong to the same contract by
+/// verifying the contract id of the sign message and the refund signatures
+/// of both parties against the generated transactions.
+pub fn get_transaction_test_vectors<C: Verification>(
+    secp: &Secp256k1<C>,
+    offer: &OfferDlc,
+    accept: &AcceptDlc,
+    sign: &SignDlc,
+) -> Result<TransactionTestVectors, Error> {
+    let offered_contract = OfferedContract::try_from_offer_dlc(offer, accept.funding_pubkey)?;
+
+    if accept.temporary_contract_id != offered_contract.id {
+        return Err(Error::InvalidParameters(
+            "Accept message does not reference the offer.".to_string(),
+        ));
+    }
+
+    let (inputs, input_amount) = get_tx_input_infos(&accept.funding_inputs)?;
+
+    let accept_params = PartyParams {
+        fund_pubkey: accept.funding_pubkey,
+        change_script_pubkey: accept.change_spk.clone(),
+        change_serial_id: accept.change_serial_id,
+        payout_script_pubkey: accept.payout_spk.clone(),
+        payout_serial_id: accept.payout_serial_id,
+        inputs,
+        input_amount,
+        collateral: accept.accept_collateral,
+    };
+
+    let total_collateral = offered_contract.offer_params.collateral + accept.accept_collateral;
+
+    let dlc_transactions = dlc::create_dlc_transactions_with_fee_split(
+        &offered_contract.offer_params,
+        &accept_params,
+        &offered_contract.contract_info[0].get_payouts(total_collateral)?,
+        offered_contract.contract_timeout,
+        offered_contract.fee_rate_per_vb,
+        0,
+        offered_contract.contract_maturity_bound,
+        offered_contract.fund_output_serial_id,
+        offered_contract.premium.as_ref(),
+        offered_contract.fee_split.as_ref(),
+    )?;
+
+    let contract_id = compute_contract_id(
+        dlc_transactions.fund.txid(),
+        dlc_transactions.get_fund_output_index() as u16,
+        offered_contract.id,
+    );
+
+    if contract_id != sign.contract_id {
+        return Err(Error::InvalidParameters(
+            "Sign message does not reference the contract.".to_string(),
+        ));
+    }
+
+    let fund_output_value = dlc_transactions.get_fund_output().value;
+
+    let DlcTransactions {
+        fund,
+        mut cets,
+        refund,
+        funding_script_pubkey,
+    } = dlc_transactions;
+
+    for &(signature, fund_pubkey) in &[
+        (&accept.refund_signature, &accept.funding_pubkey),
+        (&sign.refund_signature, &offer.funding_pubkey),
+    ] {
+        dlc::verify_tx_input_sig(
+            secp,
+            signature,
+            &refund,
+            0,
+            &funding_script_pubkey,
+            fund_output_value,
+            fund_pubkey,
+        )
+        .map_err(|_| Error::InvalidParameters("Invalid refund signature.".to_string()))?;
+    }
+
+    let cet_input = cets[0].input[0].clone();
+
+    for contract_info in offered_contract.contract_info.iter().skip(1) {
+        cets.extend(dlc::create_cets(
+            &cet_input,
+            &offered_contract.offer_params.payout_script_pubkey,
+            offered_contract.offer_params.payout_serial_id,
+            &accept_params.payout_script_pubkey,
+            accept_params.payout_serial_id,
+            &contract_info.get_payouts(total_collateral)?,
+            0,
+        ));
+    }
+
+    Ok(TransactionTestVectors {
+        funding_tx: serialize_hex(&fund),
+        cets: cets.iter().map(serialize_hex).collect(),
+        refund_tx: serialize_hex(&refund),
+    })
+}
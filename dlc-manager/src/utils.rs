@@ -1,3 +1,9 @@
+use crate::error::Error;
+use bitcoin::blockdata::constants::genesis_block;
+use bitcoin::hashes::Hash;
+use bitcoin::network::constants::Network;
+use bitcoin::{Address, Script};
+use dlc::{FeeSplit, PremiumInfo};
 #[cfg(not(feature = "fuzztarget"))]
 use secp256k1_zkp::rand::{thread_rng, RngCore};
 
@@ -13,6 +19,115 @@ pub fn get_half_common_fee(fee_rate: u64) -> u64 {
     (common_fee as f64 / 2_f64).ceil() as u64
 }
 
+/// Returns the approximate part of the common fee that a party will pay given
+/// the fee split of the contract (half of it if no fee split is specified).
+pub fn get_common_fee_share(
+    fee_rate: u64,
+    fee_split: Option<&FeeSplit>,
+    is_offer_party: bool,
+) -> Result<u64, Error> {
+    let fee_split = match fee_split {
+        Some(fee_split) => fee_split,
+        None => return Ok(get_half_common_fee(fee_rate)),
+    };
+    let (offer_fee, accept_fee) = fee_split
+        .split(get_common_fee(fee_rate))
+        .map_err(|_| Error::InvalidParameters("Invalid fee split".to_string()))?;
+    if is_offer_party {
+        Ok(offer_fee)
+    } else {
+        Ok(accept_fee)
+    }
+}
+
+/// Checks that the given payout script pubkey is of a standard type and that
+/// the total collateral of the contract is enough for a payout to it not to be
+/// considered as dust.
+pub(crate) fn validate_payout_script_pubkey(
+    payout_script_pubkey: &Script,
+    total_collateral: u64,
+) -> Result<(), Error> {
+    match dlc::util::get_dust_limit(payout_script_pubkey) {
+        None => Err(Error::InvalidParameters(
+            "Payout script pubkey is not of a standard type.".to_string(),
+        )),
+        Some(dust_limit) if total_collateral < dust_limit => {
+            Err(Error::InvalidParameters(format!(
+                "Total collateral {} is lower than the dust limit {} of the payout script pubkey.",
+                total_collateral, dust_limit
+            )))
+        }
+        Some(_) => Ok(()),
+    }
+}
+
+/// Checks that the premium amount is not lower than the dust limit of the
+/// payout script pubkey of the party receiving it.
+pub(crate) fn validate_premium(
+    premium: &PremiumInfo,
+    receiver_payout_script_pubkey: &Script,
+) -> Result<(), Error> {
+    let dust_limit = dlc::util::get_dust_limit(receiver_payout_script_pubkey).ok_or_else(|| {
+        Error::InvalidParameters("Payout script pubkey is not of a standard type.".to_string())
+    })?;
+    if premium.amount < dust_limit {
+        return Err(Error::InvalidParameters(format!(
+            "Premium amount {} is lower than the dust limit {} of the receiving party.",
+            premium.amount, dust_limit
+        )));
+    }
+    Ok(())
+}
+
+/// Returns the chain hash identifying the given network in DLC messages, that
+/// is the hash of its genesis block.
+pub(crate) fn get_chain_hash(network: Network) -> [u8; 32] {
+    genesis_block(network).block_hash().into_inner()
+}
+
+/// Returns the network identified by the given chain hash, if known.
+pub(crate) fn get_network_from_chain_hash(chain_hash: &[u8; 32]) -> Option<Network> {
+    [
+        Network::Bitcoin,
+        Network::Testnet,
+        Network::Signet,
+        Network::Regtest,
+    ]
+    .iter()
+    .find(|network| get_chain_hash(**network) == *chain_hash)
+    .copied()
+}
+
+/// Checks that the given chain hash identifies the given network.
+pub(crate) fn validate_chain_hash(chain_hash: &[u8; 32], network: Network) -> Result<(), Error> {
+    match get_network_from_chain_hash(chain_hash) {
+        Some(actual) if actual == network => Ok(()),
+        Some(actual) => Err(Error::NetworkMismatch {
+            expected: network,
+            actual,
+        }),
+        None => Err(Error::InvalidParameters("Unknown chain hash.".to_string())),
+    }
+}
+
+/// Checks that the given address can be used on the given network. Test
+/// networks share their base58 address prefixes, so that an address parsed
+/// from its string representation can report any of them, and only mainnet
+/// addresses are thus distinguished from the others.
+pub(crate) fn validate_address_network(address: &Address, network: Network) -> Result<(), Error> {
+    if (address.network == Network::Bitcoin) != (network == Network::Bitcoin) {
+        return Err(Error::NetworkMismatch {
+            expected: network,
+            actual: address.network,
+        });
+    }
+    Ok(())
+}
+
+pub(crate) fn get_contract_id_string<T: std::fmt::Display>(contract_id: &T) -> String {
+    std::format!("0x{}", contract_id)
+}
+
 #[cfg(not(feature = "fuzztarget"))]
 pub(crate) fn get_new_serial_id() -> u64 {
     thread_rng().next_u64()
@@ -4,41 +4,71 @@ extern crate bitcoincore_rpc;
 extern crate bitcoincore_rpc_json;
 extern crate dlc_manager;
 
+use bitcoin::blockdata::constants::genesis_block;
+use bitcoin::hashes::Hash;
+use bitcoin::network::constants::Network;
+use bitcoin::util::psbt::PartiallySignedTransaction;
+use bitcoin::{Address, OutPoint, Script, SigHashType, Transaction, TxIn, TxOut, Txid};
 use bitcoin_rpc_provider::BitcoinCoreProvider;
 use bitcoin_test_utils::rpc_helpers::init_clients;
 use bitcoincore_rpc::RpcApi;
-use dlc::{EnumerationPayout, Payout};
+use dlc::{EnumerationPayout, PartyParams, Payout};
+use dlc_manager::broadcast::{
+    BroadcastDecision, BroadcastInterceptor, BroadcastKind, PendingBroadcast,
+};
 use dlc_manager::contract::{
-    contract_input::{ContractInput, ContractInputInfo, OracleInput},
+    contract_input::{ChangePolicy, ContractInput, ContractInputInfo, OracleInput, PremiumInput},
     enum_descriptor::EnumDescriptor,
+    hybrid_descriptor::{HybridBranch, HybridDescriptor},
     numerical_descriptor::{DifferenceParams, NumericalDescriptor, NumericalEventInfo},
-    Contract, ContractDescriptor,
+    signed_contract::SignedContract,
+    ClosedContract, Contract, ContractDescriptor,
+};
+use dlc_manager::contract_tool;
+use dlc_manager::error::Error;
+use dlc_manager::events::{Event, EventHandler};
+use dlc_manager::manager::{
+    ClosePolicy, FundingInputPolicy, Manager, PeerPolicy, NB_CONFIRMATIONS,
 };
-use dlc_manager::manager::Manager;
+use dlc_manager::metrics::{MessageDirection, Metrics, Operation};
 use dlc_manager::payout_curve::{
     PayoutFunction, PayoutFunctionPiece, PayoutPoint, PolynomialPayoutCurvePiece, RoundingInterval,
     RoundingIntervals,
 };
-use dlc_manager::{Oracle, Storage};
+use dlc_manager::test_vectors::{get_transaction_test_vectors, TransactionTestVectors};
+use dlc_manager::{
+    Blockchain, ContractId, Oracle, OracleResolver, Storage, TemporaryContractId, TimeSource, Utxo,
+    Wallet,
+};
 use dlc_messages::oracle_msgs::{
-    DigitDecompositionEventDescriptor, EnumEventDescriptor, EventDescriptor,
+    DigitDecompositionEventDescriptor, EnumEventDescriptor, EventDescriptor, OracleAttestation,
 };
-use dlc_messages::{AcceptDlc, OfferDlc, SignDlc};
-use dlc_messages::{CetAdaptorSignatures, Message};
+use dlc_messages::{AcceptDlc, OfferDlc, RenewOffer, SignDlc};
+use dlc_messages::{CetAdaptorSignatures, Message, ReestablishState};
 use dlc_trie::digit_decomposition::decompose_value;
+use dlc_trie::multi_oracle::CoverageStrategy;
 use lightning::ln::wire::Type;
 use lightning::util::ser::Writeable;
+use mocks::memory_storage_provider::MemoryStorage;
+use mocks::mock_blockchain::MockBlockchain;
 use mocks::mock_oracle_provider::MockOracle;
+use mocks::mock_time::{set_time, MockTime};
+use mocks::mock_wallet::MockWallet;
 use secp256k1_zkp::rand::{seq::SliceRandom, thread_rng, RngCore};
-use secp256k1_zkp::{EcdsaAdaptorSignature, Signature};
+use secp256k1_zkp::schnorrsig::PublicKey as SchnorrPublicKey;
+use secp256k1_zkp::{
+    All, EcdsaAdaptorSignature, PublicKey, Secp256k1, SecretKey, Signature, SECP256K1,
+};
 use serde_json::{from_str, to_writer_pretty};
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     mpsc::channel,
-    Arc, Mutex,
+    Arc,
 };
 use std::thread;
+use std::time::Duration;
 
 #[derive(serde::Serialize, serde::Deserialize)]
 struct TestVectorPart<T> {
@@ -58,6 +88,7 @@ struct TestVector {
     offer_message: TestVectorPart<OfferDlc>,
     accept_message: TestVectorPart<AcceptDlc>,
     sign_message: TestVectorPart<SignDlc>,
+    transactions: Option<TransactionTestVectors>,
 }
 
 fn write_message<T: Writeable + serde::Serialize + Type>(msg_name: &str, s: T) {
@@ -79,12 +110,26 @@ fn write_message<T: Writeable + serde::Serialize + Type>(msg_name: &str, s: T) {
 
 fn create_test_vector() {
     if std::env::var("GENERATE_TEST_VECTOR").is_ok() {
+        let offer_message: TestVectorPart<OfferDlc> =
+            from_str(&std::fs::read_to_string("offer_message.json").unwrap()).unwrap();
+        let accept_message: TestVectorPart<AcceptDlc> =
+            from_str(&std::fs::read_to_string("accept_message.json").unwrap()).unwrap();
+        let sign_message: TestVectorPart<SignDlc> =
+            from_str(&std::fs::read_to_string("sign_message.json").unwrap()).unwrap();
+        // Transactions can only be generated from consistent messages, which
+        // is not the case when testing invalid signatures.
+        let transactions = get_transaction_test_vectors(
+            &secp256k1_zkp::Secp256k1::verification_only(),
+            &offer_message.message,
+            &accept_message.message,
+            &sign_message.message,
+        )
+        .ok();
         let test_vector = TestVector {
-            offer_message: from_str(&std::fs::read_to_string("offer_message.json").unwrap())
-                .unwrap(),
-            accept_message: from_str(&std::fs::read_to_string("accept_message.json").unwrap())
-                .unwrap(),
-            sign_message: from_str(&std::fs::read_to_string("sign_message.json").unwrap()).unwrap(),
+            offer_message,
+            accept_message,
+            sign_message,
+            transactions,
         };
         let file_name = std::env::var("TEST_VECTOR_OUTPUT_NAME")
             .unwrap_or_else(|_| "test_vector.json".to_string());
@@ -95,8 +140,6 @@ fn create_test_vector() {
 macro_rules! assert_contract_state {
     ($d:expr, $id:expr, $p:ident) => {
         let res = $d
-            .lock()
-            .unwrap()
             .get_store()
             .get_contract(&$id)
             .expect("Could not retrieve contract");
@@ -107,12 +150,22 @@ macro_rules! assert_contract_state {
     };
 }
 
+macro_rules! assert_contract_state_by_temporary_id {
+    ($d:expr, $id:expr, $p:ident) => {
+        let res = $d
+            .get_store()
+            .get_contract_by_temporary_id(&$id)
+            .expect("Could not retrieve contract");
+        if let Some(Contract::$p(_)) = res {
+        } else {
+            panic!("Unexpected contract state {:?}", res);
+        }
+    };
+}
+
 macro_rules! periodic_check {
     ($d:expr, $id:expr, $p:ident) => {
-        $d.lock()
-            .unwrap()
-            .periodic_check()
-            .expect("Periodic check error");
+        $d.periodic_check().expect("Periodic check error");
 
         assert_contract_state!($d, $id, $p);
     };
@@ -122,7 +175,7 @@ macro_rules! receive_loop {
     ($receive:expr, $manager:expr, $send:expr, $expect_err:expr, $sync_send:expr, $rcv_callback: expr) => {
         thread::spawn(move || loop {
             match $receive.recv() {
-                Ok(Some(msg)) => match $manager.lock().unwrap().on_dlc_message(
+                Ok(Some(msg)) => match $manager.on_dlc_message(
                     &msg,
                     "0218845781f631c48f1c9709e23092067d06837f30aa0cd0544ac887fe91ddd166"
                         .parse()
@@ -165,6 +218,7 @@ const MAX_ERROR_EXP: usize = 2;
 const BASE: u32 = 2;
 const EVENT_MATURITY: u32 = 1623133104;
 const EVENT_ID: &str = "Test";
+const SELECTOR_EVENT_ID: &str = "Test-Selector";
 const COLLATERAL: u64 = 100000000;
 const MID_POINT: u64 = 5;
 const ROUNDING_MOD: u64 = 1;
@@ -214,7 +268,7 @@ fn get_difference_params() -> DifferenceParams {
     DifferenceParams {
         max_error_exp: MAX_ERROR_EXP,
         min_support_exp: MIN_SUPPORT_EXP,
-        maximize_coverage: false,
+        coverage_strategy: CoverageStrategy::MinimizeCetCount,
     }
 }
 
@@ -292,6 +346,15 @@ fn get_enum_test_params(
         maturity_time: EVENT_MATURITY,
         fee_rate: 2,
         contract_infos: vec![contract_info],
+        payout_script_pubkey: None,
+        is_payout_external: false,
+        premium: None,
+        fee_split: None,
+        change_policy: None,
+        nb_confirmations: None,
+        time_source: TimeSource::WallClock,
+        refund_locktime: None,
+        exact_payouts: false,
     };
 
     TestParams {
@@ -349,6 +412,10 @@ fn get_numerical_contract_descriptor(
             unit: "sats/sec".to_owned(),
         },
         difference_params,
+        additional_payout_functions: Vec::new(),
+        exact_evaluation: false,
+        max_value: None,
+        range_payouts_cache: Default::default(),
     })
 }
 
@@ -438,6 +505,15 @@ fn get_numerical_test_params(
         maturity_time: EVENT_MATURITY,
         fee_rate: 2,
         contract_infos: vec![contract_info],
+        payout_script_pubkey: None,
+        is_payout_external: false,
+        premium: None,
+        fee_split: None,
+        change_policy: None,
+        nb_confirmations: None,
+        time_source: TimeSource::WallClock,
+        refund_locktime: None,
+        exact_payouts: false,
     };
 
     TestParams {
@@ -500,6 +576,15 @@ fn get_enum_and_numerical_test_params(
         maturity_time: EVENT_MATURITY,
         fee_rate: 2,
         contract_infos,
+        payout_script_pubkey: None,
+        is_payout_external: false,
+        premium: None,
+        fee_split: None,
+        change_policy: None,
+        nb_confirmations: None,
+        time_source: TimeSource::WallClock,
+        refund_locktime: None,
+        exact_payouts: false,
     };
 
     TestParams {
@@ -511,12 +596,66 @@ fn get_enum_and_numerical_test_params(
     }
 }
 
+fn selector_outcomes() -> Vec<String> {
+    vec!["normal".to_owned(), "halted".to_owned()]
+}
+
+fn get_hybrid_contract_descriptor() -> ContractDescriptor {
+    let numerical = match get_numerical_contract_descriptor(None) {
+        ContractDescriptor::Numerical(n) => n,
+        _ => unreachable!(),
+    };
+    ContractDescriptor::Hybrid(HybridDescriptor {
+        selector_event_id: SELECTOR_EVENT_ID.to_owned(),
+        selector_announcements: Vec::new(),
+        branches: selector_outcomes()
+            .into_iter()
+            .map(|outcome| HybridBranch {
+                outcome,
+                payout_function: numerical.payout_function.clone(),
+                rounding_intervals: numerical.rounding_intervals.clone(),
+            })
+            .collect(),
+        info: numerical.info,
+        exact_evaluation: false,
+    })
+}
+
+fn get_hybrid_test_params(nb_oracles: usize, threshold: usize) -> TestParams {
+    let mut test_params = get_numerical_test_params(
+        nb_oracles,
+        threshold,
+        false,
+        get_hybrid_contract_descriptor(),
+    );
+    let outcomes = selector_outcomes();
+    let outcome = outcomes[(thread_rng().next_u32() as usize) % outcomes.len()].clone();
+    let event = EventDescriptor::EnumEvent(EnumEventDescriptor {
+        outcomes: outcomes.clone(),
+    });
+    for oracle in test_params.oracles.iter_mut() {
+        oracle.add_event(SELECTOR_EVENT_ID, &event, EVENT_MATURITY);
+        oracle.add_attestation(SELECTOR_EVENT_ID, &[outcome.clone()]);
+    }
+
+    test_params
+}
+
 #[test]
 #[ignore]
 fn single_oracle_numerical_test() {
     numerical_common(1, 1, false, get_numerical_contract_descriptor(None));
 }
 
+#[test]
+#[ignore]
+fn single_oracle_numerical_exact_payouts_test() {
+    let mut test_params =
+        get_numerical_test_params(1, 1, false, get_numerical_contract_descriptor(None));
+    test_params.contract_input.exact_payouts = true;
+    manager_execution_test(test_params, TestPath::Close);
+}
+
 #[test]
 #[ignore]
 fn three_of_three_oracle_numerical_test() {
@@ -616,6 +755,18 @@ fn enum_and_numerical_5_of_5_test() {
     );
 }
 
+#[test]
+#[ignore]
+fn hybrid_single_oracle_test() {
+    manager_execution_test(get_hybrid_test_params(1, 1), TestPath::Close);
+}
+
+#[test]
+#[ignore]
+fn hybrid_3_of_5_test() {
+    manager_execution_test(get_hybrid_test_params(5, 3), TestPath::Close);
+}
+
 #[test]
 #[ignore]
 fn enum_single_oracle_refund_test() {
@@ -681,6 +832,20 @@ fn alter_refund_sig(refund_signature: &Signature) -> Signature {
     Signature::from_compact(&copy).unwrap()
 }
 
+/// Resolves the oracles of the tests on demand, so that the oracles of bob's
+/// manager are not given when creating it.
+struct MockOracleResolver {
+    oracles: HashMap<SchnorrPublicKey, Arc<MockOracle>>,
+}
+
+impl OracleResolver for MockOracleResolver {
+    fn resolve(&self, public_key: &SchnorrPublicKey) -> Option<Arc<dyn Oracle + Send + Sync>> {
+        self.oracles
+            .get(public_key)
+            .map(|oracle| Arc::clone(oracle) as Arc<dyn Oracle + Send + Sync>)
+    }
+}
+
 fn manager_execution_test(test_params: TestParams, path: TestPath) {
     env_logger::init();
     let (alice_send, bob_receive) = channel::<Option<Message>>();
@@ -690,8 +855,8 @@ fn manager_execution_test(test_params: TestParams, path: TestPath) {
     let bob_sync_send = sync_send;
     let (alice_rpc, bob_rpc, sink_rpc) = init_clients();
 
-    let alice_bitcoin_core = Arc::new(BitcoinCoreProvider { client: alice_rpc });
-    let bob_bitcoin_core = Arc::new(BitcoinCoreProvider { client: bob_rpc });
+    let alice_bitcoin_core = Arc::new(BitcoinCoreProvider::from_client(alice_rpc));
+    let bob_bitcoin_core = Arc::new(BitcoinCoreProvider::from_client(bob_rpc));
 
     let mut alice_oracles = HashMap::with_capacity(1);
     let mut bob_oracles = HashMap::with_capacity(1);
@@ -707,24 +872,28 @@ fn manager_execution_test(test_params: TestParams, path: TestPath) {
     let mock_time = Arc::new(mocks::mock_time::MockTime {});
     mocks::mock_time::set_time((test_params.contract_input.maturity_time as u64) - 1);
 
-    let alice_manager = Arc::new(Mutex::new(Manager::new(
+    let alice_manager = Arc::new(Manager::new(
         Arc::clone(&alice_bitcoin_core),
         Arc::clone(&alice_bitcoin_core),
         Box::new(alice_store),
         alice_oracles,
         Arc::clone(&mock_time),
-    )));
+    ));
 
     let alice_manager_loop = Arc::clone(&alice_manager);
     let alice_manager_send = Arc::clone(&alice_manager);
 
-    let bob_manager = Arc::new(Mutex::new(Manager::new(
+    let mut bob_manager = Manager::new(
         Arc::clone(&bob_bitcoin_core),
         Arc::clone(&bob_bitcoin_core),
         Box::new(bob_store),
-        bob_oracles,
+        HashMap::<SchnorrPublicKey, Arc<MockOracle>>::new(),
         Arc::clone(&mock_time),
-    )));
+    );
+    bob_manager.set_oracle_resolver(Box::new(MockOracleResolver {
+        oracles: bob_oracles,
+    }));
+    let bob_manager = Arc::new(bob_manager);
 
     let bob_manager_loop = Arc::clone(&bob_manager);
     let bob_manager_send = Arc::clone(&bob_manager);
@@ -773,8 +942,6 @@ fn manager_execution_test(test_params: TestParams, path: TestPath) {
     );
 
     let offer_msg = bob_manager_send
-        .lock()
-        .unwrap()
         .send_offer(
             &test_params.contract_input,
             "0218845781f631c48f1c9709e23092067d06837f30aa0cd0544ac887fe91ddd166"
@@ -784,21 +951,33 @@ fn manager_execution_test(test_params: TestParams, path: TestPath) {
         .expect("Send offer error");
 
     write_message("offer_message", offer_msg.clone());
-    let temporary_contract_id = offer_msg.get_hash().unwrap();
+    let temporary_contract_id = offer_msg.get_temporary_contract_id().unwrap();
     bob_send.send(Some(Message::Offer(offer_msg))).unwrap();
 
-    assert_contract_state!(bob_manager_send, temporary_contract_id, Offered);
+    assert_contract_state_by_temporary_id!(bob_manager_send, temporary_contract_id, Offered);
 
     sync_receive.recv().expect("Error synchronizing");
 
-    assert_contract_state!(alice_manager_send, temporary_contract_id, Offered);
+    assert_contract_state_by_temporary_id!(alice_manager_send, temporary_contract_id, Offered);
+
+    let accept_preview = alice_manager_send
+        .preview_accept(&temporary_contract_id)
+        .expect("Error previewing contract acceptance");
+    assert_contract_state_by_temporary_id!(alice_manager_send, temporary_contract_id, Offered);
 
     let (contract_id, _, mut accept_msg) = alice_manager_send
-        .lock()
-        .unwrap()
         .accept_contract_offer(&temporary_contract_id)
         .expect("Error accepting contract offer");
 
+    assert!(accept_preview.input_amount >= accept_preview.own_collateral);
+    assert!(
+        accept_preview.max_nb_adaptor_signatures
+            >= accept_msg
+                .cet_adaptor_signatures
+                .ecdsa_adaptor_signatures
+                .len()
+    );
+
     write_message("accept_message", accept_msg.clone());
 
     assert_contract_state!(alice_manager_send, contract_id, Accepted);
@@ -817,7 +996,11 @@ fn manager_execution_test(test_params: TestParams, path: TestPath) {
             bob_expect_error.store(true, Ordering::Relaxed);
             alice_send.send(Some(Message::Accept(accept_msg))).unwrap();
             sync_receive.recv().expect("Error synchronizing");
-            assert_contract_state!(bob_manager_send, temporary_contract_id, FailedAccept);
+            assert_contract_state_by_temporary_id!(
+                bob_manager_send,
+                temporary_contract_id,
+                FailedAccept
+            );
         }
         TestPath::BadSignCetSignature | TestPath::BadSignRefundSignature => {
             alice_expect_error.store(true, Ordering::Relaxed);
@@ -829,11 +1012,29 @@ fn manager_execution_test(test_params: TestParams, path: TestPath) {
             assert_contract_state!(alice_manager_send, contract_id, FailedSign);
         }
         _ => {
-            alice_send.send(Some(Message::Accept(accept_msg))).unwrap();
+            alice_send
+                .send(Some(Message::Accept(accept_msg.clone())))
+                .unwrap();
             sync_receive.recv().expect("Error synchronizing");
 
             assert_contract_state!(bob_manager_send, contract_id, Signed);
 
+            // A redelivered accept message should be answered with the same
+            // sign message without changing the contract state.
+            let resent_sign = bob_manager_send
+                .on_dlc_message(
+                    &Message::Accept(accept_msg),
+                    "0218845781f631c48f1c9709e23092067d06837f30aa0cd0544ac887fe91ddd166"
+                        .parse()
+                        .unwrap(),
+                )
+                .expect("Error processing redelivered accept message");
+            match resent_sign {
+                Some(Message::Sign(s)) => assert_eq!(contract_id, s.contract_id),
+                _ => panic!("Expected sign message"),
+            }
+            assert_contract_state!(bob_manager_send, contract_id, Signed);
+
             // Should not change state and should not error
             periodic_check!(bob_manager_send, contract_id, Signed);
 
@@ -841,6 +1042,34 @@ fn manager_execution_test(test_params: TestParams, path: TestPath) {
 
             assert_contract_state!(alice_manager_send, contract_id, Signed);
 
+            let counter_party =
+                "0218845781f631c48f1c9709e23092067d06837f30aa0cd0544ac887fe91ddd166"
+                    .parse()
+                    .unwrap();
+            let reestablish_msgs = alice_manager_send
+                .get_reestablish_messages(&counter_party)
+                .expect("Error getting reestablish messages");
+            assert_eq!(1, reestablish_msgs.len());
+            assert_eq!(ReestablishState::Signed, reestablish_msgs[0].state);
+            assert!(bob_manager_send
+                .on_dlc_message(
+                    &Message::Reestablish(reestablish_msgs[0].clone()),
+                    counter_party
+                )
+                .expect("Error processing reestablish message")
+                .is_none());
+
+            // Had the sign message been lost, it should be sent again.
+            let mut lost_sign_reestablish = reestablish_msgs[0].clone();
+            lost_sign_reestablish.state = ReestablishState::Accepted;
+            match bob_manager_send
+                .on_dlc_message(&Message::Reestablish(lost_sign_reestablish), counter_party)
+                .expect("Error processing reestablish message")
+            {
+                Some(Message::Sign(s)) => assert_eq!(contract_id, s.contract_id),
+                _ => panic!("Expected sign message"),
+            }
+
             let sink_address = sink_rpc.get_new_address(None, None).expect("RPC Error");
             sink_rpc
                 .generate_to_address(6, &sink_address)
@@ -885,7 +1114,7 @@ fn manager_execution_test(test_params: TestParams, path: TestPath) {
                         .generate_to_address(10, &sink_address)
                         .expect("RPC Error");
 
-                    periodic_check!(first, contract_id, Refunded);
+                    periodic_check!(first, contract_id, RefundedDueToNoAttestation);
 
                     // Randomly check with or without having the Refund mined.
                     if thread_rng().next_u32() % 2 == 0 {
@@ -894,10 +1123,40 @@ fn manager_execution_test(test_params: TestParams, path: TestPath) {
                             .expect("RPC Error");
                     }
 
-                    periodic_check!(second, contract_id, Refunded);
+                    periodic_check!(second, contract_id, RefundedDueToNoAttestation);
                 }
                 _ => unreachable!(),
             }
+
+            sink_rpc
+                .generate_to_address(6, &sink_address)
+                .expect("RPC Error");
+            let report = first
+                .get_closed_contract_report(&contract_id)
+                .expect("Error getting closed contract report");
+            let pruned = first
+                .prune_closed(u64::MAX)
+                .expect("Error pruning contracts");
+            assert_eq!(vec![contract_id], pruned);
+            assert_eq!(
+                report,
+                first
+                    .get_closed_contract_report(&contract_id)
+                    .expect("Error getting archived contract report")
+            );
+            assert!(first
+                .get_store()
+                .get_contract(&contract_id)
+                .expect("Could not retrieve contract")
+                .is_none());
+            assert_eq!(
+                1,
+                first
+                    .get_store()
+                    .get_archived_contracts()
+                    .expect("Could not retrieve archived contracts")
+                    .len()
+            );
         }
     }
 
@@ -909,3 +1168,3171 @@ fn manager_execution_test(test_params: TestParams, path: TestPath) {
 
     create_test_vector();
 }
+
+const BLOCK_INTERVAL: u64 = 600;
+
+type MockManager = Manager<
+    Arc<MockWallet>,
+    Arc<MockBlockchain>,
+    Box<MemoryStorage>,
+    Arc<MockOracle>,
+    Arc<MockTime>,
+>;
+
+/// A party of the tests using an in memory blockchain and wallet, which do
+/// not require a bitcoin node.
+struct MockParty {
+    manager: MockManager,
+    wallet: Arc<MockWallet>,
+    node_id: PublicKey,
+}
+
+/// Sets the mock time before the maturity of the contract and returns the
+/// blockchain and oracles shared by the parties.
+fn mock_test_setup(
+    test_params: TestParams,
+) -> (Arc<MockBlockchain>, Vec<Arc<MockOracle>>, ContractInput) {
+    set_time((test_params.contract_input.maturity_time as u64) - 1);
+    let blockchain = Arc::new(MockBlockchain::new(Network::Regtest, BLOCK_INTERVAL));
+    let oracles = test_params.oracles.into_iter().map(Arc::new).collect();
+    (blockchain, oracles, test_params.contract_input)
+}
+
+/// Returns a party whose wallet holds a single UTXO of the given value.
+fn new_mock_party(
+    blockchain: &Arc<MockBlockchain>,
+    oracles: &[Arc<MockOracle>],
+    utxo_value: u64,
+) -> MockParty {
+    new_mock_party_with_storage(blockchain, oracles, utxo_value, MemoryStorage::new())
+}
+
+fn new_mock_party_with_storage(
+    blockchain: &Arc<MockBlockchain>,
+    oracles: &[Arc<MockOracle>],
+    utxo_value: u64,
+    storage: MemoryStorage,
+) -> MockParty {
+    let wallet = Arc::new(MockWallet::new(Network::Regtest));
+    wallet.add_utxo(utxo_value);
+    let oracles = oracles
+        .iter()
+        .map(|x| (x.get_public_key(), Arc::clone(x)))
+        .collect();
+    let node_id = PublicKey::from_secret_key(&Secp256k1::new(), &SecretKey::new(&mut thread_rng()));
+    MockParty {
+        manager: Manager::new(
+            Arc::clone(&wallet),
+            Arc::clone(blockchain),
+            Box::new(storage),
+            oracles,
+            Arc::new(MockTime {}),
+        ),
+        wallet,
+        node_id,
+    }
+}
+
+/// Sends an offer from the offer party to the accept party, returning its
+/// temporary id.
+fn deliver_mock_offer(
+    offer_party: &MockParty,
+    accept_party: &MockParty,
+    contract_input: &ContractInput,
+) -> TemporaryContractId {
+    let offer = offer_party
+        .manager
+        .send_offer(contract_input, accept_party.node_id)
+        .expect("Error sending offer");
+    let temporary_id = offer.get_temporary_contract_id().unwrap();
+    accept_party
+        .manager
+        .on_dlc_message(&Message::Offer(offer), offer_party.node_id)
+        .expect("Error processing offer");
+    temporary_id
+}
+
+/// Exchanges the offer and accept messages of a contract between the given
+/// parties, returning the id of the contract and the sign message to be
+/// processed by the accepting party.
+fn exchange_offer_and_accept(
+    offer_party: &MockParty,
+    accept_party: &MockParty,
+    contract_input: &ContractInput,
+) -> (ContractId, Message) {
+    let temporary_id = deliver_mock_offer(offer_party, accept_party, contract_input);
+    let (contract_id, _, accept) = accept_party
+        .manager
+        .accept_contract_offer(&temporary_id)
+        .expect("Error accepting offer");
+    let sign = offer_party
+        .manager
+        .on_dlc_message(&Message::Accept(accept), accept_party.node_id)
+        .expect("Error processing accept")
+        .expect("Expected sign message");
+    (contract_id, sign)
+}
+
+/// Exchanges the offer, accept and sign messages of a contract between the
+/// given parties, returning the id of the signed contract.
+fn establish_mock_contract(
+    offer_party: &MockParty,
+    accept_party: &MockParty,
+    contract_input: &ContractInput,
+) -> ContractId {
+    let (contract_id, sign) = exchange_offer_and_accept(offer_party, accept_party, contract_input);
+    accept_party
+        .manager
+        .on_dlc_message(&sign, offer_party.node_id)
+        .expect("Error processing sign");
+    assert_contract_state!(offer_party.manager, contract_id, Signed);
+    assert_contract_state!(accept_party.manager, contract_id, Signed);
+    contract_id
+}
+
+fn get_signed_contract(party: &MockParty, contract_id: &ContractId) -> SignedContract {
+    match party
+        .manager
+        .get_store()
+        .get_contract(contract_id)
+        .expect("Could not retrieve contract")
+    {
+        Some(Contract::Signed(c)) | Some(Contract::Confirmed(c)) => c,
+        res => panic!("Unexpected contract state {:?}", res),
+    }
+}
+
+#[test]
+fn no_change_policy_rejects_funding_inputs_with_change_test() {
+    let (blockchain, oracles, mut contract_input) =
+        mock_test_setup(get_enum_test_params(1, 1, None));
+    contract_input.change_policy = Some(ChangePolicy::NoChange);
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+
+    assert!(matches!(
+        offer_party
+            .manager
+            .send_offer(&contract_input, accept_party.node_id),
+        Err(Error::InvalidParameters(_))
+    ));
+    // The rejected funding inputs are unlocked.
+    assert_eq!(3 * COLLATERAL, offer_party.wallet.get_balance());
+}
+
+#[test]
+fn no_change_policy_test() {
+    let (blockchain, oracles, mut contract_input) =
+        mock_test_setup(get_enum_test_params(1, 1, None));
+    contract_input.change_policy = Some(ChangePolicy::NoChange);
+    // The input value in excess of the collateral is lower than the dust
+    // limit once the fees are paid.
+    let offer_party = new_mock_party(&blockchain, &oracles, COLLATERAL + dlc::DUST_LIMIT);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+
+    let contract = get_signed_contract(&accept_party, &contract_id);
+    let offer_params = &contract.accepted_contract.offered_contract.offer_params;
+    assert!(!offer_params.change_script_pubkey.is_empty());
+    let fund = &contract.accepted_contract.dlc_transactions.fund;
+    assert_eq!(2, fund.output.len());
+    assert!(fund
+        .output
+        .iter()
+        .all(|x| x.script_pubkey != offer_params.change_script_pubkey));
+}
+
+#[test]
+fn confirmed_contract_sign_message_is_sent_again_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+
+    let mut reestablish_msgs = accept_party
+        .manager
+        .get_reestablish_messages(&offer_party.node_id)
+        .expect("Error getting reestablish messages");
+    assert_eq!(1, reestablish_msgs.len());
+
+    // Had the sign message been lost, it should be sent again.
+    let mut lost_sign_reestablish = reestablish_msgs.remove(0);
+    lost_sign_reestablish.state = ReestablishState::Accepted;
+    match offer_party
+        .manager
+        .on_dlc_message(
+            &Message::Reestablish(lost_sign_reestablish),
+            accept_party.node_id,
+        )
+        .expect("Error processing reestablish message")
+    {
+        Some(Message::Sign(s)) => assert_eq!(contract_id, s.contract_id),
+        _ => panic!("Expected sign message"),
+    }
+    assert_contract_state!(offer_party.manager, contract_id, Confirmed);
+}
+
+/// Records the events emitted by a manager.
+#[derive(Clone, Default)]
+struct EventRecorder {
+    events: Arc<std::sync::Mutex<Vec<Event>>>,
+}
+
+impl EventHandler for EventRecorder {
+    fn handle_event(&self, event: Event) {
+        self.events.lock().unwrap().push(event);
+    }
+}
+
+#[test]
+fn amended_offer_replaces_pending_offer_test() {
+    let test_params = get_enum_test_params(1, 1, None);
+    // The oracle announces the event again with new nonces.
+    let mut oracle = test_params.oracles[0].clone();
+    oracle.add_event(
+        EVENT_ID,
+        &EventDescriptor::EnumEvent(EnumEventDescriptor {
+            outcomes: enum_outcomes(),
+        }),
+        EVENT_MATURITY,
+    );
+    let announcement = oracle.get_announcement(EVENT_ID).unwrap();
+    let (blockchain, oracles, contract_input) = mock_test_setup(test_params);
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let mut accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let event_recorder = EventRecorder::default();
+    accept_party
+        .manager
+        .set_event_handler(Box::new(event_recorder.clone()));
+
+    let offer = offer_party
+        .manager
+        .send_offer(&contract_input, accept_party.node_id)
+        .expect("Error sending offer");
+    let temporary_id = offer.get_temporary_contract_id().unwrap();
+    accept_party
+        .manager
+        .on_dlc_message(&Message::Offer(offer), offer_party.node_id)
+        .expect("Error processing offer");
+    let amended_offer = offer_party
+        .manager
+        .amend_offer(&temporary_id, announcement)
+        .expect("Error amending offer");
+    let amended_id = amended_offer.get_temporary_contract_id().unwrap();
+    accept_party
+        .manager
+        .on_dlc_message(&Message::Offer(amended_offer), offer_party.node_id)
+        .expect("Error processing amended offer");
+
+    assert!(accept_party
+        .manager
+        .get_store()
+        .get_contract_by_temporary_id(&temporary_id)
+        .expect("Could not retrieve contract")
+        .is_none());
+    assert_contract_state_by_temporary_id!(accept_party.manager, amended_id, Offered);
+    assert!(event_recorder.events.lock().unwrap().is_empty());
+}
+
+#[test]
+fn offer_amended_back_gets_unused_id_test() {
+    let test_params = get_enum_test_params(1, 1, None);
+    let mut oracle = test_params.oracles[0].clone();
+    let original_announcement = oracle.get_announcement(EVENT_ID).unwrap();
+    oracle.add_event(
+        EVENT_ID,
+        &EventDescriptor::EnumEvent(EnumEventDescriptor {
+            outcomes: enum_outcomes(),
+        }),
+        EVENT_MATURITY,
+    );
+    let announcement = oracle.get_announcement(EVENT_ID).unwrap();
+    let (blockchain, oracles, contract_input) = mock_test_setup(test_params);
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+
+    let temporary_id = offer_party
+        .manager
+        .send_offer(&contract_input, accept_party.node_id)
+        .expect("Error sending offer")
+        .get_temporary_contract_id()
+        .unwrap();
+    let amended_id = offer_party
+        .manager
+        .amend_offer(&temporary_id, announcement)
+        .expect("Error amending offer")
+        .get_temporary_contract_id()
+        .unwrap();
+    // Going back to the original announcement would give the offer its
+    // original temporary id, whose history is terminated.
+    let history = offer_party
+        .manager
+        .get_contract_history(&temporary_id)
+        .unwrap();
+    let reamended_id = offer_party
+        .manager
+        .amend_offer(&amended_id, original_announcement)
+        .expect("Error amending offer")
+        .get_temporary_contract_id()
+        .unwrap();
+
+    assert_ne!(temporary_id, reamended_id);
+    assert_ne!(amended_id, reamended_id);
+    assert_contract_state_by_temporary_id!(offer_party.manager, reamended_id, Offered);
+    assert_eq!(
+        history,
+        offer_party
+            .manager
+            .get_contract_history(&temporary_id)
+            .unwrap()
+    );
+}
+
+#[test]
+fn conflicting_offer_is_stored_as_new_offer_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let mut accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let event_recorder = EventRecorder::default();
+    accept_party
+        .manager
+        .set_event_handler(Box::new(event_recorder.clone()));
+
+    let offer = offer_party
+        .manager
+        .send_offer(&contract_input, accept_party.node_id)
+        .expect("Error sending offer");
+    let temporary_id = offer.get_temporary_contract_id().unwrap();
+    // An offer spending the same inputs with a different fee rate is not an
+    // amendment of the first one.
+    let mut conflicting_offer = offer.clone();
+    conflicting_offer.fee_rate_per_vb += 1;
+    let conflicting_id = conflicting_offer.get_temporary_contract_id().unwrap();
+    accept_party
+        .manager
+        .on_dlc_message(&Message::Offer(offer), offer_party.node_id)
+        .expect("Error processing offer");
+    accept_party
+        .manager
+        .on_dlc_message(&Message::Offer(conflicting_offer), offer_party.node_id)
+        .expect("Error processing conflicting offer");
+
+    assert_contract_state_by_temporary_id!(accept_party.manager, temporary_id, Offered);
+    assert_contract_state_by_temporary_id!(accept_party.manager, conflicting_id, Offered);
+    assert_eq!(
+        vec![Event::ConflictingOfferReceived {
+            temporary_id: conflicting_id,
+            conflicting_temporary_id: temporary_id,
+        }],
+        *event_recorder.events.lock().unwrap()
+    );
+}
+
+/// Broadcast interceptor returning the decision computed by the given function
+/// and recording the intercepted transactions.
+struct MockInterceptor<F> {
+    decide: F,
+    intercepted: Arc<std::sync::Mutex<Vec<PendingBroadcast>>>,
+}
+
+impl<F: Fn(&PendingBroadcast) -> BroadcastDecision> BroadcastInterceptor for MockInterceptor<F> {
+    fn on_broadcast(&self, pending: &PendingBroadcast) -> BroadcastDecision {
+        self.intercepted.lock().unwrap().push(pending.clone());
+        (self.decide)(pending)
+    }
+}
+
+fn set_mock_interceptor<F>(
+    party: &mut MockParty,
+    decide: F,
+) -> Arc<std::sync::Mutex<Vec<PendingBroadcast>>>
+where
+    F: Fn(&PendingBroadcast) -> BroadcastDecision + Send + Sync + 'static,
+{
+    let intercepted = Arc::new(std::sync::Mutex::new(Vec::new()));
+    party
+        .manager
+        .set_broadcast_interceptor(Box::new(MockInterceptor {
+            decide,
+            intercepted: Arc::clone(&intercepted),
+        }));
+    intercepted
+}
+
+fn is_broadcast(blockchain: &MockBlockchain, tx: &Transaction) -> bool {
+    blockchain
+        .get_broadcast_transactions()
+        .iter()
+        .any(|x| x.txid() == tx.txid())
+}
+
+#[test]
+fn delayed_broadcast_is_retried_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let now = (contract_input.maturity_time as u64) - 1;
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let mut accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let intercepted = set_mock_interceptor(&mut accept_party, move |pending| {
+        if pending.not_before < now + BLOCK_INTERVAL {
+            BroadcastDecision::Delay(BLOCK_INTERVAL)
+        } else {
+            BroadcastDecision::Approve
+        }
+    });
+
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    let fund = get_signed_contract(&accept_party, &contract_id)
+        .accepted_contract
+        .dlc_transactions
+        .fund;
+    assert!(!is_broadcast(&blockchain, &fund));
+    let pending = accept_party
+        .manager
+        .get_store()
+        .get_pending_broadcasts()
+        .unwrap();
+    assert_eq!(1, pending.len());
+    assert_eq!(BroadcastKind::Fund, pending[0].kind);
+    assert_eq!(now + BLOCK_INTERVAL, pending[0].not_before);
+
+    // The delayed transaction is left to the queue until the delay elapses.
+    accept_party
+        .manager
+        .periodic_check()
+        .expect("Periodic check error");
+    assert!(!is_broadcast(&blockchain, &fund));
+
+    set_time(now + BLOCK_INTERVAL);
+    accept_party
+        .manager
+        .periodic_check()
+        .expect("Periodic check error");
+    assert!(is_broadcast(&blockchain, &fund));
+    assert!(accept_party
+        .manager
+        .get_store()
+        .get_pending_broadcasts()
+        .unwrap()
+        .is_empty());
+    assert_eq!(2, intercepted.lock().unwrap().len());
+}
+
+#[test]
+fn replacement_changing_txid_is_rejected_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let mut accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    set_mock_interceptor(&mut accept_party, |pending| {
+        let mut tx = pending.transaction.clone();
+        tx.lock_time += 1;
+        BroadcastDecision::Replace(tx)
+    });
+
+    let (contract_id, sign) =
+        exchange_offer_and_accept(&offer_party, &accept_party, &contract_input);
+    assert!(matches!(
+        accept_party
+            .manager
+            .on_dlc_message(&sign, offer_party.node_id),
+        Err(Error::InvalidParameters(_))
+    ));
+    assert_contract_state!(accept_party.manager, contract_id, Signed);
+    assert!(blockchain.get_broadcast_transactions().is_empty());
+    assert!(accept_party
+        .manager
+        .get_store()
+        .get_pending_broadcasts()
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn cpfp_child_is_broadcast_after_parent_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let mut accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let get_child = |parent: &Transaction| Transaction {
+        version: 2,
+        lock_time: 0,
+        input: vec![TxIn {
+            previous_output: OutPoint {
+                txid: parent.txid(),
+                vout: 1,
+            },
+            script_sig: Script::new(),
+            sequence: 0xffffffff,
+            witness: Vec::new(),
+        }],
+        output: vec![TxOut {
+            value: parent.output[1].value - 1000,
+            script_pubkey: parent.output[1].script_pubkey.clone(),
+        }],
+    };
+    set_mock_interceptor(&mut accept_party, move |pending| {
+        BroadcastDecision::Cpfp(get_child(&pending.transaction))
+    });
+
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    let fund = get_signed_contract(&accept_party, &contract_id)
+        .accepted_contract
+        .dlc_transactions
+        .fund;
+    let broadcast: Vec<_> = blockchain
+        .get_broadcast_transactions()
+        .iter()
+        .map(|x| x.txid())
+        .collect();
+    assert_eq!(vec![fund.txid(), get_child(&fund).txid()], broadcast);
+    assert!(accept_party
+        .manager
+        .get_store()
+        .get_pending_broadcasts()
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn prepared_accept_is_committed_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let temporary_id = deliver_mock_offer(&offer_party, &accept_party, &contract_input);
+
+    let pending_accept = accept_party
+        .manager
+        .prepare_accept_contract_offer(&temporary_id)
+        .expect("Error preparing accept");
+    assert_eq!(0, accept_party.wallet.get_balance());
+    assert!(matches!(
+        accept_party
+            .manager
+            .prepare_accept_contract_offer(&temporary_id),
+        Err(Error::InvalidParameters(_))
+    ));
+    let accepted_contract = pending_accept
+        .compute(accept_party.manager.get_secp())
+        .expect("Error computing accept");
+    let (contract_id, _, _) = accept_party
+        .manager
+        .commit_accepted_contract(accepted_contract.clone())
+        .expect("Error committing accept");
+
+    assert_contract_state!(accept_party.manager, contract_id, Accepted);
+    assert_eq!(0, accept_party.wallet.get_balance());
+    // The acceptance cannot be committed twice.
+    assert!(matches!(
+        accept_party
+            .manager
+            .commit_accepted_contract(accepted_contract),
+        Err(Error::InvalidParameters(_))
+    ));
+}
+
+#[test]
+fn aborted_accept_unlocks_funding_inputs_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let temporary_id = deliver_mock_offer(&offer_party, &accept_party, &contract_input);
+
+    let pending_accept = accept_party
+        .manager
+        .prepare_accept_contract_offer(&temporary_id)
+        .expect("Error preparing accept");
+    accept_party
+        .manager
+        .abort_accept(&temporary_id)
+        .expect("Error aborting accept");
+
+    assert_eq!(3 * COLLATERAL, accept_party.wallet.get_balance());
+    assert!(matches!(
+        accept_party.manager.abort_accept(&temporary_id),
+        Err(Error::InvalidParameters(_))
+    ));
+    // An aborted acceptance cannot be committed.
+    let accepted_contract = pending_accept
+        .compute(accept_party.manager.get_secp())
+        .expect("Error computing accept");
+    assert!(matches!(
+        accept_party
+            .manager
+            .commit_accepted_contract(accepted_contract),
+        Err(Error::InvalidParameters(_))
+    ));
+    assert_contract_state_by_temporary_id!(accept_party.manager, temporary_id, Offered);
+    assert_eq!(3 * COLLATERAL, accept_party.wallet.get_balance());
+}
+
+#[test]
+fn failed_commit_unlocks_funding_inputs_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 6 * COLLATERAL);
+    let temporary_id = deliver_mock_offer(&offer_party, &accept_party, &contract_input);
+
+    let pending_accept = accept_party
+        .manager
+        .prepare_accept_contract_offer(&temporary_id)
+        .expect("Error preparing accept");
+    // The offer is accepted through another path in the meantime.
+    accept_party.wallet.add_utxo(3 * COLLATERAL);
+    accept_party
+        .manager
+        .accept_contract_offer(&temporary_id)
+        .expect("Error accepting offer");
+    let accepted_contract = pending_accept
+        .compute(accept_party.manager.get_secp())
+        .expect("Error computing accept");
+
+    assert!(matches!(
+        accept_party
+            .manager
+            .commit_accepted_contract(accepted_contract),
+        Err(Error::InvalidState)
+    ));
+    assert_eq!(6 * COLLATERAL, accept_party.wallet.get_balance());
+}
+
+#[test]
+fn renewal_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    // UTXOs covering the fees of the renewal fund transaction.
+    offer_party.wallet.add_utxo(COLLATERAL / 10);
+    accept_party.wallet.add_utxo(COLLATERAL / 10);
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+    periodic_check!(accept_party.manager, contract_id, Confirmed);
+
+    let dlc_transactions = get_signed_contract(&offer_party, &contract_id)
+        .accepted_contract
+        .dlc_transactions;
+    let fund_output_value = dlc_transactions.get_fund_output().value;
+    let (_, renew_offer) = offer_party
+        .manager
+        .renew_contract(&contract_id, &contract_input, fund_output_value / 2)
+        .expect("Error renewing contract");
+
+    // A counter payout leaving the offer party unable to fund its collateral
+    // is rejected.
+    let mut invalid_renew_offer = renew_offer.clone();
+    invalid_renew_offer.counter_payout = fund_output_value;
+    assert!(matches!(
+        accept_party.manager.on_dlc_message(
+            &Message::RenewOffer(invalid_renew_offer),
+            offer_party.node_id
+        ),
+        Err(Error::InvalidParameters(_))
+    ));
+
+    let temporary_id = renew_offer.offer_dlc.get_temporary_contract_id().unwrap();
+    accept_party
+        .manager
+        .on_dlc_message(&Message::RenewOffer(renew_offer), offer_party.node_id)
+        .expect("Error processing renew offer");
+    let (renewal_id, _, accept) = accept_party
+        .manager
+        .accept_contract_offer(&temporary_id)
+        .expect("Error accepting renew offer");
+    let sign = offer_party
+        .manager
+        .on_dlc_message(&Message::Accept(accept), accept_party.node_id)
+        .expect("Error processing accept")
+        .expect("Expected sign message");
+    assert_contract_state!(offer_party.manager, renewal_id, Signed);
+
+    // The renewed contract is not closed while its renewal is pending, even
+    // though the oracle attested.
+    set_time(contract_input.maturity_time as u64 + 1);
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+    assert_eq!(1, blockchain.get_broadcast_transactions().len());
+
+    accept_party
+        .manager
+        .on_dlc_message(&sign, offer_party.node_id)
+        .expect("Error processing sign");
+    let renewal_fund = get_signed_contract(&accept_party, &renewal_id)
+        .accepted_contract
+        .dlc_transactions
+        .fund;
+    assert!(is_broadcast(&blockchain, &renewal_fund));
+    assert!(renewal_fund
+        .input
+        .iter()
+        .any(|x| x.previous_output.txid == dlc_transactions.fund.txid()));
+    // The fund output being spent by the renewal, the contract is not closed.
+    periodic_check!(accept_party.manager, contract_id, Confirmed);
+    assert_eq!(2, blockchain.get_broadcast_transactions().len());
+
+    // The renewal has the same maturity, which must not be reached when it
+    // gets confirmed for it to remain open.
+    set_time(contract_input.maturity_time as u64 - 1);
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    periodic_check!(offer_party.manager, contract_id, Renewed);
+    periodic_check!(accept_party.manager, contract_id, Renewed);
+    assert_contract_state!(offer_party.manager, renewal_id, Confirmed);
+    assert_contract_state!(accept_party.manager, renewal_id, Confirmed);
+}
+
+/// Multiplies the collaterals and payouts of an enum contract input.
+fn scale_enum_contract_input(contract_input: &mut ContractInput, numerator: u64, denominator: u64) {
+    contract_input.offer_collateral = contract_input.offer_collateral * numerator / denominator;
+    contract_input.accept_collateral = contract_input.accept_collateral * numerator / denominator;
+    for contract_info in contract_input.contract_infos.iter_mut() {
+        if let ContractDescriptor::Enum(d) = &mut contract_info.contract_descriptor {
+            for x in d.outcome_payouts.iter_mut() {
+                x.payout.offer = x.payout.offer * numerator / denominator;
+                x.payout.accept = x.payout.accept * numerator / denominator;
+            }
+        }
+    }
+}
+
+/// Exchanges the messages renewing a contract from the given renew offer,
+/// returning the id of the new contract.
+fn establish_mock_renewal(
+    offer_party: &MockParty,
+    accept_party: &MockParty,
+    renew_offer: RenewOffer,
+) -> ContractId {
+    let temporary_id = renew_offer.offer_dlc.get_temporary_contract_id().unwrap();
+    accept_party
+        .manager
+        .on_dlc_message(&Message::RenewOffer(renew_offer), offer_party.node_id)
+        .expect("Error processing renew offer");
+    let (renewal_id, _, accept) = accept_party
+        .manager
+        .accept_contract_offer(&temporary_id)
+        .expect("Error accepting renew offer");
+    let sign = offer_party
+        .manager
+        .on_dlc_message(&Message::Accept(accept), accept_party.node_id)
+        .expect("Error processing accept")
+        .expect("Expected sign message");
+    accept_party
+        .manager
+        .on_dlc_message(&sign, offer_party.node_id)
+        .expect("Error processing sign");
+    renewal_id
+}
+
+#[test]
+fn splice_in_test() {
+    let (blockchain, oracles, mut contract_input) =
+        mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    // Only confirmed contracts can be spliced.
+    assert!(matches!(
+        offer_party
+            .manager
+            .splice_contract(&contract_id, &contract_input),
+        Err(Error::InvalidState)
+    ));
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+    periodic_check!(accept_party.manager, contract_id, Confirmed);
+    let fund = get_signed_contract(&offer_party, &contract_id)
+        .accepted_contract
+        .dlc_transactions
+        .fund;
+
+    // The collateral added by each party comes from its wallet.
+    offer_party.wallet.add_utxo(2 * COLLATERAL);
+    accept_party.wallet.add_utxo(2 * COLLATERAL);
+    scale_enum_contract_input(&mut contract_input, 2, 1);
+    let (_, splice_offer) = offer_party
+        .manager
+        .splice_contract(&contract_id, &contract_input)
+        .expect("Error splicing contract");
+    let splice_id = establish_mock_renewal(&offer_party, &accept_party, splice_offer);
+
+    let splice_fund = get_signed_contract(&accept_party, &splice_id)
+        .accepted_contract
+        .dlc_transactions;
+    assert!(is_broadcast(&blockchain, &splice_fund.fund));
+    assert_eq!(3, splice_fund.fund.input.len());
+    assert!(splice_fund
+        .fund
+        .input
+        .iter()
+        .any(|x| x.previous_output.txid == fund.txid()));
+    assert!(splice_fund.get_fund_output().value > 4 * COLLATERAL);
+    assert_eq!(0, offer_party.wallet.get_balance());
+    assert_eq!(0, accept_party.wallet.get_balance());
+
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    periodic_check!(offer_party.manager, contract_id, Renewed);
+    periodic_check!(accept_party.manager, contract_id, Renewed);
+    assert_contract_state!(offer_party.manager, splice_id, Confirmed);
+    assert_contract_state!(accept_party.manager, splice_id, Confirmed);
+}
+
+#[test]
+fn splice_out_test() {
+    let (blockchain, oracles, mut contract_input) =
+        mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+    periodic_check!(accept_party.manager, contract_id, Confirmed);
+
+    // The collateral removed by each party is sent to its change output.
+    scale_enum_contract_input(&mut contract_input, 1, 2);
+    let (_, splice_offer) = offer_party
+        .manager
+        .splice_contract(&contract_id, &contract_input)
+        .expect("Error splicing contract");
+    let splice_id = establish_mock_renewal(&offer_party, &accept_party, splice_offer);
+
+    let splice_contract = get_signed_contract(&accept_party, &splice_id).accepted_contract;
+    let splice_fund = &splice_contract.dlc_transactions;
+    assert_eq!(1, splice_fund.fund.input.len());
+    assert!(splice_fund.get_fund_output().value < COLLATERAL + COLLATERAL / 10);
+    let change_values: Vec<u64> = [
+        &splice_contract.offered_contract.offer_params,
+        &splice_contract.accept_params,
+    ]
+    .iter()
+    .map(|params| {
+        splice_fund
+            .fund
+            .output
+            .iter()
+            .find(|x| x.script_pubkey == params.change_script_pubkey)
+            .expect("Expected a change output")
+            .value
+    })
+    .collect();
+    for value in change_values {
+        assert!(value > COLLATERAL / 2 - COLLATERAL / 10);
+    }
+}
+
+#[test]
+fn splice_in_offer_side_only_test() {
+    let (blockchain, oracles, mut contract_input) =
+        mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+    periodic_check!(accept_party.manager, contract_id, Confirmed);
+
+    // Only the offer party adds collateral, the accept party only funding its
+    // share of the fees of the new fund transaction.
+    offer_party.wallet.add_utxo(3 * COLLATERAL);
+    accept_party.wallet.add_utxo(COLLATERAL / 10);
+    contract_input.offer_collateral = 3 * COLLATERAL;
+    let total_collateral = contract_input.offer_collateral + contract_input.accept_collateral;
+    for contract_info in contract_input.contract_infos.iter_mut() {
+        if let ContractDescriptor::Enum(d) = &mut contract_info.contract_descriptor {
+            for x in d.outcome_payouts.iter_mut() {
+                if x.payout.offer > 0 {
+                    x.payout.offer = total_collateral;
+                } else {
+                    x.payout.accept = total_collateral;
+                }
+            }
+        }
+    }
+    let (_, splice_offer) = offer_party
+        .manager
+        .splice_contract(&contract_id, &contract_input)
+        .expect("Error splicing contract");
+    let splice_id = establish_mock_renewal(&offer_party, &accept_party, splice_offer);
+
+    let splice_contract = get_signed_contract(&accept_party, &splice_id).accepted_contract;
+    assert_eq!(
+        3 * COLLATERAL,
+        splice_contract.offered_contract.offer_params.collateral
+    );
+    assert_eq!(COLLATERAL, splice_contract.accept_params.collateral);
+    let splice_fund = &splice_contract.dlc_transactions;
+    assert!(is_broadcast(&blockchain, &splice_fund.fund));
+    assert!(splice_fund.get_fund_output().value > 4 * COLLATERAL);
+    assert!(splice_fund.get_fund_output().value < 4 * COLLATERAL + COLLATERAL / 10);
+    // The accept party funds at most the fees from its wallet.
+    assert!(
+        splice_contract.accept_params.input_amount
+            < COLLATERAL + COLLATERAL / 10 + COLLATERAL / 100
+    );
+    assert_eq!(0, offer_party.wallet.get_balance());
+
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    periodic_check!(offer_party.manager, contract_id, Renewed);
+    periodic_check!(accept_party.manager, contract_id, Renewed);
+    assert_contract_state!(offer_party.manager, splice_id, Confirmed);
+    assert_contract_state!(accept_party.manager, splice_id, Confirmed);
+}
+
+/// Returns a numerical descriptor whose payout to the offer party grows
+/// linearly with the outcome, up to the given total collateral.
+fn get_linear_numerical_contract_descriptor(total_collateral: u64) -> ContractDescriptor {
+    ContractDescriptor::Numerical(NumericalDescriptor {
+        payout_function: PayoutFunction::new(vec![
+            PayoutFunctionPiece::PolynomialPayoutCurvePiece(
+                PolynomialPayoutCurvePiece::new(vec![
+                    PayoutPoint {
+                        event_outcome: 0,
+                        outcome_payout: 0,
+                        extra_precision: 0,
+                    },
+                    PayoutPoint {
+                        event_outcome: max_value() as u64,
+                        outcome_payout: total_collateral,
+                        extra_precision: 0,
+                    },
+                ])
+                .unwrap(),
+            ),
+        ])
+        .unwrap(),
+        rounding_intervals: RoundingIntervals {
+            intervals: vec![RoundingInterval {
+                begin_interval: 0,
+                rounding_mod: ROUNDING_MOD,
+            }],
+        },
+        info: NumericalEventInfo {
+            base: BASE as usize,
+            nb_digits: NB_DIGITS as usize,
+            unit: "sats/sec".to_owned(),
+        },
+        difference_params: None,
+        additional_payout_functions: Vec::new(),
+        exact_evaluation: false,
+        max_value: None,
+        range_payouts_cache: Default::default(),
+    })
+}
+
+#[test]
+fn position_reduction_test() {
+    let (blockchain, oracles, mut contract_input) = mock_test_setup(get_numerical_test_params(
+        1,
+        1,
+        false,
+        get_numerical_contract_descriptor(None),
+    ));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+    periodic_check!(accept_party.manager, contract_id, Confirmed);
+    let fund_output_value = get_signed_contract(&offer_party, &contract_id)
+        .accepted_contract
+        .dlc_transactions
+        .get_fund_output()
+        .value;
+
+    // The total collateral must decrease.
+    assert!(matches!(
+        offer_party
+            .manager
+            .reduce_contract(&contract_id, &contract_input, fund_output_value / 2),
+        Err(Error::InvalidParameters(_))
+    ));
+    contract_input.offer_collateral = COLLATERAL / 2;
+    contract_input.accept_collateral = COLLATERAL / 2;
+    // Only numerical contracts can be reduced.
+    contract_input.contract_infos[0].contract_descriptor = get_enum_contract_descriptor();
+    assert!(matches!(
+        offer_party
+            .manager
+            .reduce_contract(&contract_id, &contract_input, fund_output_value / 2),
+        Err(Error::InvalidParameters(_))
+    ));
+
+    contract_input.contract_infos[0].contract_descriptor =
+        get_linear_numerical_contract_descriptor(COLLATERAL);
+    let (_, reduce_offer) = offer_party
+        .manager
+        .reduce_contract(&contract_id, &contract_input, fund_output_value / 2)
+        .expect("Error reducing contract");
+    let reduced_id = establish_mock_renewal(&offer_party, &accept_party, reduce_offer);
+
+    // The freed collateral is paid out through the change outputs of the new
+    // fund transaction, which only spends the current fund output.
+    let reduced_contract = get_signed_contract(&accept_party, &reduced_id).accepted_contract;
+    let reduced_fund = &reduced_contract.dlc_transactions;
+    assert!(is_broadcast(&blockchain, &reduced_fund.fund));
+    assert_eq!(1, reduced_fund.fund.input.len());
+    assert!(reduced_fund.get_fund_output().value < COLLATERAL + COLLATERAL / 10);
+    for params in &[
+        &reduced_contract.offered_contract.offer_params,
+        &reduced_contract.accept_params,
+    ] {
+        let change = reduced_fund
+            .fund
+            .output
+            .iter()
+            .find(|x| x.script_pubkey == params.change_script_pubkey)
+            .expect("Expected a change output");
+        assert!(change.value > COLLATERAL / 2 - COLLATERAL / 10);
+    }
+
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    periodic_check!(offer_party.manager, contract_id, Renewed);
+    periodic_check!(accept_party.manager, contract_id, Renewed);
+    assert_contract_state!(offer_party.manager, reduced_id, Confirmed);
+    assert_contract_state!(accept_party.manager, reduced_id, Confirmed);
+
+    // Only the offer party reduces its position, the accept party keeping its
+    // collateral.
+    let fund_output_value = reduced_fund.get_fund_output().value;
+    contract_input.offer_collateral = COLLATERAL / 4;
+    contract_input.contract_infos[0].contract_descriptor =
+        get_linear_numerical_contract_descriptor(COLLATERAL / 4 + COLLATERAL / 2);
+    let (_, reduce_offer) = offer_party
+        .manager
+        .reduce_contract(
+            &reduced_id,
+            &contract_input,
+            fund_output_value / 2 + COLLATERAL / 10,
+        )
+        .expect("Error reducing contract");
+    let reduced_id = establish_mock_renewal(&offer_party, &accept_party, reduce_offer);
+
+    let reduced_contract = get_signed_contract(&offer_party, &reduced_id).accepted_contract;
+    assert_eq!(
+        COLLATERAL / 4,
+        reduced_contract.offered_contract.offer_params.collateral
+    );
+    assert_eq!(COLLATERAL / 2, reduced_contract.accept_params.collateral);
+    let reduced_fund = &reduced_contract.dlc_transactions;
+    assert_eq!(1, reduced_fund.fund.input.len());
+    let get_change_value = |params: &PartyParams| {
+        reduced_fund
+            .fund
+            .output
+            .iter()
+            .find(|x| x.script_pubkey == params.change_script_pubkey)
+            .expect("Expected a change output")
+            .value
+    };
+    assert!(get_change_value(&reduced_contract.offered_contract.offer_params) > COLLATERAL / 10);
+    assert!(get_change_value(&reduced_contract.accept_params) < COLLATERAL / 10 + COLLATERAL / 100);
+}
+
+const PSBT_MAX_WITNESS_LEN: usize = 150;
+
+/// Wallet delegating to a mock wallet, attaching witness script metadata to
+/// the UTXOs it returns and recording the PSBTs it is asked to sign. Unless
+/// `finalize_fund_inputs` is set, only the ownership proofs, whose PSBT does
+/// not contain the previous transaction, are finalized.
+struct PsbtRecordingWallet {
+    inner: MockWallet,
+    witness_script: Script,
+    finalize_fund_inputs: bool,
+    psbts: std::sync::Mutex<Vec<(PartiallySignedTransaction, usize)>>,
+}
+
+impl Wallet for PsbtRecordingWallet {
+    fn get_new_address(&self) -> Result<Address, Error> {
+        self.inner.get_new_address()
+    }
+
+    fn get_new_secret_key(&self) -> Result<SecretKey, Error> {
+        self.inner.get_new_secret_key()
+    }
+
+    fn get_secret_key_for_pubkey(&self, pubkey: &PublicKey) -> Result<SecretKey, Error> {
+        self.inner.get_secret_key_for_pubkey(pubkey)
+    }
+
+    fn sign_psbt_input(
+        &self,
+        psbt: &mut PartiallySignedTransaction,
+        input_index: usize,
+    ) -> Result<(), Error> {
+        self.psbts.lock().unwrap().push((psbt.clone(), input_index));
+        if self.finalize_fund_inputs || psbt.inputs[input_index].non_witness_utxo.is_none() {
+            self.inner.sign_psbt_input(psbt, input_index)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn get_utxos_for_amount(
+        &self,
+        amount: u64,
+        fee_rate: Option<u64>,
+        lock_utxos: bool,
+    ) -> Result<Vec<Utxo>, Error> {
+        let mut utxos = self
+            .inner
+            .get_utxos_for_amount(amount, fee_rate, lock_utxos)?;
+        for utxo in utxos.iter_mut() {
+            utxo.witness_script = Some(self.witness_script.clone());
+            utxo.max_witness_len = Some(PSBT_MAX_WITNESS_LEN);
+        }
+        Ok(utxos)
+    }
+
+    fn import_address(&self, address: &Address) -> Result<(), Error> {
+        self.inner.import_address(address)
+    }
+
+    fn get_transaction(&self, tx_id: &Txid) -> Result<Transaction, Error> {
+        self.inner.get_transaction(tx_id)
+    }
+
+    fn get_transaction_confirmations(&self, tx_id: &Txid) -> Result<u32, Error> {
+        self.inner.get_transaction_confirmations(tx_id)
+    }
+
+    fn unlock_utxos(&self, outpoints: &[OutPoint]) -> Result<(), Error> {
+        self.inner.unlock_utxos(outpoints)
+    }
+}
+
+/// Runs the offer, accept and sign steps of a contract with an accepting
+/// party using a [`PsbtRecordingWallet`], returning its wallet, the id of the
+/// contract, the utxo funding the accept collateral and the result of
+/// processing the sign message.
+fn establish_psbt_contract(
+    finalize_fund_inputs: bool,
+) -> (
+    Arc<MockBlockchain>,
+    Arc<PsbtRecordingWallet>,
+    ContractId,
+    Utxo,
+    Result<Option<Message>, Error>,
+) {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let wallet = Arc::new(PsbtRecordingWallet {
+        inner: MockWallet::new(Network::Regtest),
+        witness_script: Script::from(vec![0x51]),
+        finalize_fund_inputs,
+        psbts: std::sync::Mutex::new(Vec::new()),
+    });
+    let utxo = wallet.inner.add_utxo(3 * COLLATERAL);
+    let accept_node_id =
+        PublicKey::from_secret_key(&Secp256k1::new(), &SecretKey::new(&mut thread_rng()));
+    let accept_manager = Manager::new(
+        Arc::clone(&wallet),
+        Arc::clone(&blockchain),
+        Box::new(MemoryStorage::new()),
+        oracles
+            .iter()
+            .map(|x| (x.get_public_key(), Arc::clone(x)))
+            .collect(),
+        Arc::new(MockTime {}),
+    );
+
+    let offer = offer_party
+        .manager
+        .send_offer(&contract_input, accept_node_id)
+        .expect("Error sending offer");
+    let temporary_id = offer.get_temporary_contract_id().unwrap();
+    accept_manager
+        .on_dlc_message(&Message::Offer(offer), offer_party.node_id)
+        .expect("Error processing offer");
+    let (contract_id, _, accept) = accept_manager
+        .accept_contract_offer(&temporary_id)
+        .expect("Error accepting offer");
+    // The metadata of the UTXO is used to estimate the fund transaction fee.
+    assert_eq!(1, accept.funding_inputs.len());
+    assert_eq!(
+        PSBT_MAX_WITNESS_LEN as u16,
+        accept.funding_inputs[0].max_witness_len
+    );
+    let sign = offer_party
+        .manager
+        .on_dlc_message(&Message::Accept(accept), accept_node_id)
+        .expect("Error processing accept")
+        .expect("Expected sign message");
+    let res = accept_manager.on_dlc_message(&sign, offer_party.node_id);
+    (blockchain, wallet, contract_id, utxo, res)
+}
+
+#[test]
+fn funding_input_is_signed_through_psbt_test() {
+    let (blockchain, wallet, _, utxo, res) = establish_psbt_contract(true);
+    res.expect("Error processing sign");
+
+    let psbts = wallet.psbts.lock().unwrap();
+    assert_eq!(2, psbts.len());
+    // The ownership proof of the input is signed when accepting.
+    let (proof_psbt, _) = &psbts[0];
+    assert_eq!(
+        Some(wallet.witness_script.clone()),
+        proof_psbt.inputs[0].witness_script
+    );
+    assert_eq!(
+        Some(&utxo.tx_out.script_pubkey),
+        proof_psbt.inputs[0]
+            .witness_utxo
+            .as_ref()
+            .map(|x| &x.script_pubkey)
+    );
+
+    let (psbt, input_index) = &psbts[1];
+    let psbt_input = &psbt.inputs[*input_index];
+    assert_eq!(
+        utxo.outpoint,
+        psbt.global.unsigned_tx.input[*input_index].previous_output
+    );
+    assert_eq!(Some(&utxo.tx_out), psbt_input.witness_utxo.as_ref());
+    assert_eq!(
+        Some(utxo.outpoint.txid),
+        psbt_input.non_witness_utxo.as_ref().map(|x| x.txid())
+    );
+    assert_eq!(
+        Some(wallet.witness_script.clone()),
+        psbt_input.witness_script
+    );
+    assert_eq!(None, psbt_input.redeem_script);
+    assert_eq!(Some(SigHashType::All), psbt_input.sighash_type);
+    // The PSBT is unsigned, the other inputs being signed afterwards.
+    assert!(psbt
+        .global
+        .unsigned_tx
+        .input
+        .iter()
+        .all(|x| x.witness.is_empty()));
+
+    // The finalized witness is set in the broadcast fund transaction.
+    let fund = blockchain.get_broadcast_transactions().remove(0);
+    let input = fund
+        .input
+        .iter()
+        .find(|x| x.previous_output == utxo.outpoint)
+        .expect("Expected the utxo to fund the contract");
+    assert!(!input.witness.is_empty());
+}
+
+#[test]
+fn funding_input_not_finalized_by_wallet_is_rejected_test() {
+    let (blockchain, wallet, _, _, res) = establish_psbt_contract(false);
+    match res {
+        Err(Error::InvalidParameters(e)) => assert!(e.contains("not finalized")),
+        _ => panic!("Expected the unfinalized input to be rejected"),
+    }
+    assert_eq!(2, wallet.psbts.lock().unwrap().len());
+    assert!(blockchain.get_broadcast_transactions().is_empty());
+}
+
+/// Closes an enum contract using all the given number of oracles through the
+/// offer party once they attested, returning the accepting party, the contract id,
+/// the CET published by the offer party and the oracles.
+fn close_enum_contract_by_offer_party(
+    nb_oracles: usize,
+) -> (MockParty, ContractId, Transaction, Vec<Arc<MockOracle>>) {
+    let (blockchain, oracles, contract_input) =
+        mock_test_setup(get_enum_test_params(nb_oracles, nb_oracles, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+    set_time(contract_input.maturity_time as u64 + 1);
+    periodic_check!(offer_party.manager, contract_id, Closed);
+    let cet = blockchain
+        .get_broadcast_transactions()
+        .pop()
+        .expect("Expected the CET to be broadcast");
+    (accept_party, contract_id, cet, oracles)
+}
+
+#[test]
+fn attestation_is_recovered_from_cet_test() {
+    let (accept_party, contract_id, cet, oracles) = close_enum_contract_by_offer_party(1);
+    let signed_contract = get_signed_contract(&accept_party, &contract_id);
+    let accepted_contract = &signed_contract.accepted_contract;
+    let adaptor_infos = accept_party
+        .manager
+        .get_store()
+        .get_adaptor_info(&contract_id)
+        .expect("Error retrieving adaptor info")
+        .expect("Missing adaptor info");
+    let cet_index = accepted_contract
+        .dlc_transactions
+        .cets
+        .iter()
+        .position(|x| x.txid() == cet.txid())
+        .expect("Expected a CET of the contract");
+    let contract_info = &accepted_contract.offered_contract.contract_info[0];
+
+    let attestation = contract_info
+        .recover_attestation_from_cet(
+            accept_party.manager.get_secp(),
+            &cet,
+            &adaptor_infos[0],
+            accepted_contract.adaptor_signatures.as_ref().unwrap(),
+            cet_index,
+        )
+        .expect("Error recovering attestation")
+        .expect("Expected an attestation");
+    let expected = oracles[0]
+        .get_attestation(EVENT_ID)
+        .expect("Error getting attestation");
+    assert_eq!(expected.oracle_public_key, attestation.oracle_public_key);
+    assert_eq!(expected.signatures, attestation.signatures);
+    assert_eq!(expected.outcomes, attestation.outcomes);
+
+    // The signatures of the offer party do not decrypt its own CET.
+    assert!(contract_info
+        .recover_attestation_from_cet(
+            accept_party.manager.get_secp(),
+            &cet,
+            &adaptor_infos[0],
+            signed_contract.adaptor_signatures.as_ref().unwrap(),
+            cet_index,
+        )
+        .expect("Error recovering attestation")
+        .is_none());
+}
+
+#[test]
+fn attestation_is_not_recovered_from_multi_oracle_cet_test() {
+    let (accept_party, contract_id, cet, _) = close_enum_contract_by_offer_party(2);
+    let accepted_contract = get_signed_contract(&accept_party, &contract_id).accepted_contract;
+    let adaptor_infos = accept_party
+        .manager
+        .get_store()
+        .get_adaptor_info(&contract_id)
+        .expect("Error retrieving adaptor info")
+        .expect("Missing adaptor info");
+    let cet_index = accepted_contract
+        .dlc_transactions
+        .cets
+        .iter()
+        .position(|x| x.txid() == cet.txid())
+        .expect("Expected a CET of the contract");
+
+    // The adaptor secret is the sum of the signatures of both oracles.
+    assert!(matches!(
+        accepted_contract.offered_contract.contract_info[0].recover_attestation_from_cet(
+            accept_party.manager.get_secp(),
+            &cet,
+            &adaptor_infos[0],
+            accepted_contract.adaptor_signatures.as_ref().unwrap(),
+            cet_index,
+        ),
+        Err(Error::InvalidParameters(_))
+    ));
+}
+
+/// Closes, with the given close policy, an enum contract whose three oracles
+/// attested to different outcomes, returning the payout of the closing party.
+/// Two oracles attest to the outcome paying the accept party and one to the
+/// one paying the offer party, any of them being enough to close.
+fn close_with_policy(close_policy: ClosePolicy, close_by_offer_party: bool) -> u64 {
+    let oracles = ["b", "a", "b"]
+        .iter()
+        .map(|outcome| {
+            let mut oracle = get_enum_oracle();
+            oracle.add_attestation(EVENT_ID, &[outcome.to_string()]);
+            oracle
+        })
+        .collect();
+    let (blockchain, oracles, contract_input) =
+        mock_test_setup(get_enum_test_params(3, 1, Some(oracles)));
+    let mut offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let mut accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    let closing_party = if close_by_offer_party {
+        &mut offer_party
+    } else {
+        &mut accept_party
+    };
+    closing_party.manager.set_close_policy(close_policy);
+    periodic_check!(closing_party.manager, contract_id, Confirmed);
+
+    set_time(contract_input.maturity_time as u64 + 1);
+    periodic_check!(closing_party.manager, contract_id, Closed);
+    match closing_party
+        .manager
+        .get_store()
+        .get_contract(&contract_id)
+        .expect("Could not retrieve contract")
+    {
+        Some(Contract::Closed(c)) => c.own_payout,
+        res => panic!("Unexpected contract state {:?}", res),
+    }
+}
+
+#[test]
+fn honor_exact_outcome_close_policy_test() {
+    // The outcome attested by most oracles pays the accept party.
+    assert!(close_with_policy(ClosePolicy::HonorExactOutcome, true) < COLLATERAL);
+    assert!(close_with_policy(ClosePolicy::HonorExactOutcome, false) > COLLATERAL);
+}
+
+#[test]
+fn maximize_own_payout_close_policy_test() {
+    assert!(close_with_policy(ClosePolicy::MaximizeOwnPayout, true) > COLLATERAL);
+    assert!(close_with_policy(ClosePolicy::MaximizeOwnPayout, false) > COLLATERAL);
+}
+
+#[test]
+fn earliest_adaptor_index_close_policy_test() {
+    // The first outcome of the contract pays the offer party.
+    assert!(close_with_policy(ClosePolicy::EarliestAdaptorIndex, true) > COLLATERAL);
+    assert!(close_with_policy(ClosePolicy::EarliestAdaptorIndex, false) < COLLATERAL);
+}
+
+#[test]
+fn mirrored_contracts_with_same_peer_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let alice = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let bob = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    alice.wallet.add_utxo(3 * COLLATERAL);
+    bob.wallet.add_utxo(3 * COLLATERAL);
+
+    let alice_offer_id = deliver_mock_offer(&alice, &bob, &contract_input);
+    let bob_offer_id = deliver_mock_offer(&bob, &alice, &contract_input);
+    assert_ne!(alice_offer_id, bob_offer_id);
+    let (alice_contract_id, _, alice_accept) = bob
+        .manager
+        .accept_contract_offer(&alice_offer_id)
+        .expect("Error accepting offer");
+    let (bob_contract_id, _, bob_accept) = alice
+        .manager
+        .accept_contract_offer(&bob_offer_id)
+        .expect("Error accepting offer");
+
+    let alice_sign = alice
+        .manager
+        .on_dlc_message(&Message::Accept(alice_accept), bob.node_id)
+        .expect("Error processing accept")
+        .expect("Expected sign message");
+    let bob_sign = bob
+        .manager
+        .on_dlc_message(&Message::Accept(bob_accept), alice.node_id)
+        .expect("Error processing accept")
+        .expect("Expected sign message");
+    bob.manager
+        .on_dlc_message(&alice_sign, alice.node_id)
+        .expect("Error processing sign");
+    alice
+        .manager
+        .on_dlc_message(&bob_sign, bob.node_id)
+        .expect("Error processing sign");
+
+    assert_ne!(alice_contract_id, bob_contract_id);
+    for party in &[&alice, &bob] {
+        assert_contract_state!(party.manager, alice_contract_id, Signed);
+        assert_contract_state!(party.manager, bob_contract_id, Signed);
+    }
+    assert!(
+        get_signed_contract(&alice, &alice_contract_id)
+            .accepted_contract
+            .offered_contract
+            .is_offer_party
+    );
+    assert!(
+        !get_signed_contract(&alice, &bob_contract_id)
+            .accepted_contract
+            .offered_contract
+            .is_offer_party
+    );
+}
+
+#[test]
+fn messages_for_contracts_in_other_direction_are_rejected_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let alice = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let bob = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let other = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    alice.wallet.add_utxo(3 * COLLATERAL);
+    bob.wallet.add_utxo(3 * COLLATERAL);
+
+    let alice_offer = alice
+        .manager
+        .send_offer(&contract_input, bob.node_id)
+        .expect("Error sending offer");
+    let alice_offer_id = alice_offer.get_temporary_contract_id().unwrap();
+    // An offer with the temporary id of an offer made by the party itself is
+    // not stored as received.
+    assert!(matches!(
+        alice
+            .manager
+            .on_dlc_message(&Message::Offer(alice_offer.clone()), bob.node_id),
+        Err(Error::InvalidParameters(_))
+    ));
+    assert_contract_state_by_temporary_id!(alice.manager, alice_offer_id, Offered);
+    bob.manager
+        .on_dlc_message(&Message::Offer(alice_offer), alice.node_id)
+        .expect("Error processing offer");
+    let bob_offer_id = deliver_mock_offer(&bob, &alice, &contract_input);
+
+    let (_, _, accept) = bob
+        .manager
+        .accept_contract_offer(&alice_offer_id)
+        .expect("Error accepting offer");
+    // The offer received by alice cannot be accepted by its sender.
+    let mut mirrored_accept = accept.clone();
+    mirrored_accept.temporary_contract_id = bob_offer_id;
+    assert!(matches!(
+        alice
+            .manager
+            .on_dlc_message(&Message::Accept(mirrored_accept), bob.node_id),
+        Err(Error::InvalidParameters(_))
+    ));
+    assert_contract_state_by_temporary_id!(alice.manager, bob_offer_id, Offered);
+    // Nor can an offer be accepted by another peer.
+    assert!(matches!(
+        alice
+            .manager
+            .on_dlc_message(&Message::Accept(accept.clone()), other.node_id),
+        Err(Error::InvalidParameters(_))
+    ));
+
+    let sign = alice
+        .manager
+        .on_dlc_message(&Message::Accept(accept), bob.node_id)
+        .expect("Error processing accept")
+        .expect("Expected sign message");
+    // The sign message is only accepted from the offer party.
+    assert!(matches!(
+        bob.manager.on_dlc_message(&sign, other.node_id),
+        Err(Error::InvalidParameters(_))
+    ));
+    bob.manager
+        .on_dlc_message(&sign, alice.node_id)
+        .expect("Error processing sign");
+}
+
+#[test]
+fn contract_tool_signs_and_verifies_adaptor_signatures_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    let secp = Secp256k1::new();
+
+    let accept_contract = get_signed_contract(&accept_party, &contract_id);
+    let accepted = &accept_contract.accepted_contract;
+    let offered = &accepted.offered_contract;
+    let accept_fund_sk = accept_party
+        .wallet
+        .get_secret_key_for_pubkey(&accepted.accept_params.fund_pubkey)
+        .unwrap();
+    let offer_fund_sk = offer_party
+        .wallet
+        .get_secret_key_for_pubkey(&offered.offer_params.fund_pubkey)
+        .unwrap();
+
+    let (adaptor_infos, accept_sigs, cets) = contract_tool::sign_cets(
+        &secp,
+        offered,
+        &accepted.accept_params,
+        &accepted.dlc_transactions,
+        &accept_fund_sk,
+    )
+    .unwrap();
+    assert_eq!(accepted.dlc_transactions.cets, cets);
+    assert_eq!(
+        accepted.adaptor_signatures.as_ref().unwrap().len(),
+        accept_sigs.len()
+    );
+
+    // The offering party verifies the signatures of the accepting party.
+    let (verified_infos, verified_cets) = contract_tool::verify_cets(
+        &secp,
+        offered,
+        &accepted.accept_params,
+        &accepted.dlc_transactions,
+        &accept_sigs,
+    )
+    .unwrap();
+    assert_eq!(adaptor_infos.len(), verified_infos.len());
+    assert_eq!(cets, verified_cets);
+
+    let offer_sigs = contract_tool::sign_cets_with_adaptor_infos(
+        &secp,
+        &offered.contract_info,
+        &verified_infos,
+        &accepted.dlc_transactions,
+        &offer_fund_sk,
+    )
+    .unwrap();
+    assert_eq!(accept_sigs.len(), offer_sigs.len());
+
+    // The accepting party verifies the signatures of the offering party.
+    contract_tool::verify_cets_with_adaptor_infos(
+        &secp,
+        &offered.contract_info,
+        &adaptor_infos,
+        &accepted.dlc_transactions,
+        &offered.offer_params.fund_pubkey,
+        &offer_sigs,
+    )
+    .unwrap();
+
+    // Signatures are rejected when checked against the key of the other party.
+    assert!(contract_tool::verify_cets(
+        &secp,
+        offered,
+        &accepted.accept_params,
+        &accepted.dlc_transactions,
+        &offer_sigs,
+    )
+    .is_err());
+    assert!(contract_tool::verify_cets_with_adaptor_infos(
+        &secp,
+        &offered.contract_info,
+        &adaptor_infos,
+        &accepted.dlc_transactions,
+        &accepted.accept_params.fund_pubkey,
+        &offer_sigs,
+    )
+    .is_err());
+
+    // The signatures produced by the manager are valid ones.
+    contract_tool::verify_cets_with_adaptor_infos(
+        &secp,
+        &offered.contract_info,
+        &accepted.adaptor_infos,
+        &accepted.dlc_transactions,
+        &offered.offer_params.fund_pubkey,
+        accept_contract.adaptor_signatures.as_ref().unwrap(),
+    )
+    .unwrap();
+}
+
+#[test]
+fn adaptor_info_is_loaded_from_storage_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party_with_storage(
+        &blockchain,
+        &oracles,
+        3 * COLLATERAL,
+        MemoryStorage::new_with_separate_adaptor_infos(),
+    );
+    let accept_party = new_mock_party_with_storage(
+        &blockchain,
+        &oracles,
+        3 * COLLATERAL,
+        MemoryStorage::new_with_separate_adaptor_infos(),
+    );
+
+    // The accept party verifies the signatures of the sign message against the
+    // adaptor information of its accepted contract, stored without it.
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+
+    for party in &[&offer_party, &accept_party] {
+        let signed_contract = get_signed_contract(party, &contract_id);
+        assert!(signed_contract.accepted_contract.adaptor_infos.is_empty());
+        let adaptor_infos = party
+            .manager
+            .get_store()
+            .get_adaptor_info(&contract_id)
+            .expect("Error retrieving adaptor info")
+            .expect("Missing adaptor info");
+        assert_eq!(1, adaptor_infos.len());
+    }
+
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+    set_time(contract_input.maturity_time as u64 + 1);
+    periodic_check!(offer_party.manager, contract_id, Closed);
+}
+
+#[test]
+fn funding_input_policy_limits_number_of_inputs_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let mut offer_party = new_mock_party(&blockchain, &oracles, COLLATERAL);
+    offer_party.wallet.add_utxo(COLLATERAL);
+    let mut accept_party = new_mock_party(&blockchain, &oracles, COLLATERAL);
+    accept_party.wallet.add_utxo(COLLATERAL);
+    let one_input_policy = FundingInputPolicy {
+        max_inputs: Some(1),
+        ..Default::default()
+    };
+
+    let offer = offer_party
+        .manager
+        .send_offer(&contract_input, accept_party.node_id)
+        .expect("Error sending offer");
+    assert_eq!(2, offer.funding_inputs.len());
+    accept_party
+        .manager
+        .set_funding_input_policy(one_input_policy.clone());
+    assert!(matches!(
+        accept_party
+            .manager
+            .on_dlc_message(&Message::Offer(offer.clone()), offer_party.node_id),
+        Err(Error::InvalidParameters(_))
+    ));
+
+    accept_party
+        .manager
+        .set_funding_input_policy(FundingInputPolicy {
+            max_inputs: Some(2),
+            ..Default::default()
+        });
+    accept_party
+        .manager
+        .on_dlc_message(&Message::Offer(offer.clone()), offer_party.node_id)
+        .expect("Error processing offer");
+
+    // The inputs of the accept message are checked by the offering party.
+    let (_, _, accept) = accept_party
+        .manager
+        .accept_contract_offer(&offer.get_temporary_contract_id().unwrap())
+        .expect("Error accepting offer");
+    assert_eq!(2, accept.funding_inputs.len());
+    offer_party
+        .manager
+        .set_funding_input_policy(one_input_policy);
+    assert!(matches!(
+        offer_party
+            .manager
+            .on_dlc_message(&Message::Accept(accept), accept_party.node_id),
+        Err(Error::InvalidParameters(_))
+    ));
+}
+
+#[test]
+fn funding_input_policy_requires_confirmations_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let mut accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    accept_party
+        .manager
+        .set_funding_input_policy(FundingInputPolicy {
+            min_confirmations: Some(2),
+            ..Default::default()
+        });
+    let offer = offer_party
+        .manager
+        .send_offer(&contract_input, accept_party.node_id)
+        .expect("Error sending offer");
+    let prev_tx: Transaction =
+        bitcoin::consensus::deserialize(&offer.funding_inputs[0].prev_tx).unwrap();
+
+    // The transaction spent by the funding input is not in the chain.
+    assert!(matches!(
+        accept_party
+            .manager
+            .on_dlc_message(&Message::Offer(offer.clone()), offer_party.node_id),
+        Err(Error::InvalidParameters(_))
+    ));
+
+    blockchain.send_transaction(&prev_tx).unwrap();
+    blockchain.mine_blocks(1);
+    assert!(matches!(
+        accept_party
+            .manager
+            .on_dlc_message(&Message::Offer(offer.clone()), offer_party.node_id),
+        Err(Error::InvalidParameters(_))
+    ));
+
+    blockchain.mine_blocks(1);
+    accept_party
+        .manager
+        .on_dlc_message(&Message::Offer(offer.clone()), offer_party.node_id)
+        .expect("Error processing offer");
+    assert_contract_state_by_temporary_id!(
+        accept_party.manager,
+        offer.get_temporary_contract_id().unwrap(),
+        Offered
+    );
+}
+
+#[test]
+fn funding_input_policy_rejects_non_segwit_inputs_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let mut accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    accept_party
+        .manager
+        .set_funding_input_policy(FundingInputPolicy {
+            standard_only: true,
+            ..Default::default()
+        });
+    let offer = offer_party
+        .manager
+        .send_offer(&contract_input, accept_party.node_id)
+        .expect("Error sending offer");
+
+    let mut legacy_offer = offer.clone();
+    let mut prev_tx: Transaction =
+        bitcoin::consensus::deserialize(&legacy_offer.funding_inputs[0].prev_tx).unwrap();
+    let vout = legacy_offer.funding_inputs[0].prev_tx_vout as usize;
+    let key = bitcoin::PublicKey {
+        compressed: true,
+        key: offer.funding_pubkey,
+    };
+    prev_tx.output[vout].script_pubkey = Address::p2pkh(&key, Network::Regtest).script_pubkey();
+    legacy_offer.funding_inputs[0].prev_tx = bitcoin::consensus::serialize(&prev_tx);
+    assert!(matches!(
+        accept_party
+            .manager
+            .on_dlc_message(&Message::Offer(legacy_offer), offer_party.node_id),
+        Err(Error::InvalidParameters(_))
+    ));
+
+    // The P2WPKH inputs of the mock wallet are accepted.
+    accept_party
+        .manager
+        .on_dlc_message(&Message::Offer(offer), offer_party.node_id)
+        .expect("Error processing offer");
+}
+
+#[test]
+fn zero_conf_funding_requires_peer_policy_test() {
+    let (blockchain, oracles, mut contract_input) =
+        mock_test_setup(get_enum_test_params(1, 1, None));
+    contract_input.nb_confirmations = Some(0);
+    let mut offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let mut accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let zero_conf_policy = PeerPolicy {
+        allow_zero_conf: true,
+        ..Default::default()
+    };
+
+    assert!(matches!(
+        offer_party
+            .manager
+            .send_offer(&contract_input, accept_party.node_id),
+        Err(Error::InvalidParameters(_))
+    ));
+
+    offer_party
+        .manager
+        .set_peer_policy(accept_party.node_id, zero_conf_policy.clone());
+    let offer = offer_party
+        .manager
+        .send_offer(&contract_input, accept_party.node_id)
+        .expect("Error sending offer");
+    assert!(matches!(
+        accept_party
+            .manager
+            .on_dlc_message(&Message::Offer(offer.clone()), offer_party.node_id),
+        Err(Error::InvalidParameters(_))
+    ));
+
+    accept_party
+        .manager
+        .set_peer_policy(offer_party.node_id, zero_conf_policy);
+    accept_party
+        .manager
+        .on_dlc_message(&Message::Offer(offer), offer_party.node_id)
+        .expect("Error processing offer");
+}
+
+/// Establishes a contract using zero-conf funding, returning the parties and
+/// the contract id.
+fn establish_zero_conf_contract(
+    blockchain: &Arc<MockBlockchain>,
+    oracles: &[Arc<MockOracle>],
+    mut contract_input: ContractInput,
+) -> (MockParty, MockParty, ContractId) {
+    contract_input.nb_confirmations = Some(0);
+    let mut offer_party = new_mock_party(blockchain, oracles, 3 * COLLATERAL);
+    let mut accept_party = new_mock_party(blockchain, oracles, 3 * COLLATERAL);
+    let zero_conf_policy = PeerPolicy {
+        allow_zero_conf: true,
+        ..Default::default()
+    };
+    offer_party
+        .manager
+        .set_default_peer_policy(zero_conf_policy.clone());
+    accept_party
+        .manager
+        .set_default_peer_policy(zero_conf_policy);
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    (offer_party, accept_party, contract_id)
+}
+
+#[test]
+fn zero_conf_contract_is_confirmed_once_broadcast_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let (offer_party, accept_party, contract_id) =
+        establish_zero_conf_contract(&blockchain, &oracles, contract_input);
+
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+    periodic_check!(accept_party.manager, contract_id, Confirmed);
+    let contract = offer_party
+        .manager
+        .get_store()
+        .get_contract(&contract_id)
+        .unwrap()
+        .unwrap();
+    assert!(contract.is_zero_conf());
+}
+
+#[test]
+fn zero_conf_funding_reverted_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let (mut offer_party, _accept_party, contract_id) =
+        establish_zero_conf_contract(&blockchain, &oracles, contract_input);
+    let event_recorder = EventRecorder::default();
+    offer_party
+        .manager
+        .set_event_handler(Box::new(event_recorder.clone()));
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+    let fund_txid = get_signed_contract(&offer_party, &contract_id)
+        .accepted_contract
+        .dlc_transactions
+        .fund
+        .txid();
+
+    assert!(blockchain.evict(&fund_txid));
+    periodic_check!(offer_party.manager, contract_id, Signed);
+
+    let events = event_recorder.events.lock().unwrap();
+    assert_eq!(1, events.len());
+    assert!(matches!(
+        &events[0],
+        Event::ZeroConfFundingReverted { contract_id: id } if *id == contract_id
+    ));
+}
+
+#[test]
+fn upcoming_maturities_are_ordered_test() {
+    let (blockchain, oracles, mut contract_input) =
+        mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    offer_party.wallet.add_utxo(3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    accept_party.wallet.add_utxo(3 * COLLATERAL);
+    let maturity = contract_input.maturity_time;
+    contract_input.maturity_time = maturity + 100;
+    let late_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    contract_input.maturity_time = maturity;
+    let early_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+
+    set_time(maturity as u64 - 100);
+    let upcoming = offer_party.manager.upcoming_maturities(50).unwrap();
+    assert!(upcoming.is_empty());
+    let upcoming = offer_party.manager.upcoming_maturities(100).unwrap();
+    assert_eq!(vec![(early_id, maturity)], upcoming);
+    let upcoming = accept_party.manager.upcoming_maturities(200).unwrap();
+    assert_eq!(
+        vec![(early_id, maturity), (late_id, maturity + 100)],
+        upcoming
+    );
+
+    // Contracts whose maturity is passed are not upcoming anymore.
+    set_time(maturity as u64);
+    let upcoming = offer_party.manager.upcoming_maturities(200).unwrap();
+    assert_eq!(vec![(late_id, maturity + 100)], upcoming);
+}
+
+#[test]
+fn maturity_approaching_is_notified_once_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let mut offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let event_recorder = EventRecorder::default();
+    offer_party
+        .manager
+        .set_event_handler(Box::new(event_recorder.clone()));
+    offer_party.manager.set_maturity_notification_window(100);
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    let maturity = contract_input.maturity_time;
+
+    set_time(maturity as u64 - 200);
+    periodic_check!(offer_party.manager, contract_id, Signed);
+    assert!(event_recorder.events.lock().unwrap().is_empty());
+
+    set_time(maturity as u64 - 100);
+    periodic_check!(offer_party.manager, contract_id, Signed);
+    set_time(maturity as u64 - 50);
+    periodic_check!(offer_party.manager, contract_id, Signed);
+
+    let events = event_recorder.events.lock().unwrap();
+    assert_eq!(1, events.len());
+    assert!(matches!(
+        &events[0],
+        Event::MaturityApproaching {
+            contract_id: id,
+            maturity_time,
+        } if *id == contract_id && *maturity_time == maturity
+    ));
+}
+
+fn get_closed_contract(party: &MockParty, contract_id: &ContractId) -> ClosedContract {
+    match party
+        .manager
+        .get_store()
+        .get_contract(contract_id)
+        .expect("Could not retrieve contract")
+    {
+        Some(Contract::Closed(c)) => c,
+        res => panic!("Unexpected contract state {:?}", res),
+    }
+}
+
+#[test]
+fn evicted_cet_is_rebroadcast_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+    set_time(contract_input.maturity_time as u64 + 1);
+    periodic_check!(offer_party.manager, contract_id, Closed);
+    let cet = blockchain
+        .get_broadcast_transactions()
+        .pop()
+        .expect("Expected the CET to be broadcast");
+    assert_eq!(
+        Some(&cet),
+        get_closed_contract(&offer_party, &contract_id)
+            .signed_cet
+            .as_ref()
+    );
+
+    assert!(blockchain.evict(&cet.txid()));
+    periodic_check!(offer_party.manager, contract_id, Closed);
+    assert!(blockchain
+        .is_in_mempool(&cet.txid())
+        .expect("Error querying the mempool"));
+}
+
+/// Establishes an enum contract whose oracles attested to outcomes paying
+/// each party, both parties closing it with the CET maximizing their payout.
+/// Returns the blockchain, the offer and accept parties and the contract id,
+/// the time being set after the maturity of the contract.
+fn establish_contract_with_conflicting_cets(
+) -> (Arc<MockBlockchain>, MockParty, MockParty, ContractId) {
+    let oracles = ["b", "a", "b"]
+        .iter()
+        .map(|outcome| {
+            let mut oracle = get_enum_oracle();
+            oracle.add_attestation(EVENT_ID, &[outcome.to_string()]);
+            oracle
+        })
+        .collect();
+    let (blockchain, oracles, contract_input) =
+        mock_test_setup(get_enum_test_params(3, 1, Some(oracles)));
+    let mut offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let mut accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    offer_party
+        .manager
+        .set_close_policy(ClosePolicy::MaximizeOwnPayout);
+    accept_party
+        .manager
+        .set_close_policy(ClosePolicy::MaximizeOwnPayout);
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+    periodic_check!(accept_party.manager, contract_id, Confirmed);
+    set_time(contract_input.maturity_time as u64 + 1);
+    (blockchain, offer_party, accept_party, contract_id)
+}
+
+#[test]
+fn cet_conflicting_with_counter_party_cet_test() {
+    let (blockchain, offer_party, accept_party, contract_id) =
+        establish_contract_with_conflicting_cets();
+    periodic_check!(offer_party.manager, contract_id, Closed);
+    let offer_cet = blockchain
+        .get_broadcast_transactions()
+        .pop()
+        .expect("Expected the CET to be broadcast");
+
+    // The accept party finds the fund output spent by the CET of the offer
+    // party and records it instead of broadcasting its own.
+    periodic_check!(accept_party.manager, contract_id, Closed);
+    let closed_contract = get_closed_contract(&accept_party, &contract_id);
+    let cets = &closed_contract
+        .signed_contract
+        .accepted_contract
+        .dlc_transactions
+        .cets;
+    assert_eq!(offer_cet.txid(), cets[closed_contract.cet_index].txid());
+    assert!(closed_contract.own_payout < COLLATERAL);
+    assert!(closed_contract.signed_cet.is_none());
+    assert_eq!(
+        offer_cet.txid(),
+        blockchain
+            .get_broadcast_transactions()
+            .pop()
+            .unwrap()
+            .txid()
+    );
+}
+
+#[test]
+fn evicted_cet_superseded_by_counter_party_cet_test() {
+    let (blockchain, offer_party, accept_party, contract_id) =
+        establish_contract_with_conflicting_cets();
+    periodic_check!(offer_party.manager, contract_id, Closed);
+    let offer_cet = blockchain
+        .get_broadcast_transactions()
+        .pop()
+        .expect("Expected the CET to be broadcast");
+    assert!(get_closed_contract(&offer_party, &contract_id).own_payout > COLLATERAL);
+
+    assert!(blockchain.evict(&offer_cet.txid()));
+    periodic_check!(accept_party.manager, contract_id, Closed);
+    let accept_cet = blockchain.get_broadcast_transactions().pop().unwrap();
+    assert_ne!(offer_cet.txid(), accept_cet.txid());
+    blockchain.mine_blocks(1);
+
+    // The offer party does not broadcast its CET again but records the one
+    // of the accept party.
+    periodic_check!(offer_party.manager, contract_id, Closed);
+    let closed_contract = get_closed_contract(&offer_party, &contract_id);
+    assert!(closed_contract.own_payout < COLLATERAL);
+    assert!(closed_contract.signed_cet.is_none());
+    assert!(!blockchain
+        .is_in_mempool(&offer_cet.txid())
+        .expect("Error querying the mempool"));
+}
+
+#[test]
+fn range_payout_cets_cover_outcomes_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_numerical_test_params(
+        1,
+        1,
+        false,
+        get_numerical_contract_descriptor(None),
+    ));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    let signed_contract = get_signed_contract(&offer_party, &contract_id);
+    let cets = &signed_contract.accepted_contract.dlc_transactions.cets;
+    let nb_adaptor_sigs = signed_contract.adaptor_signatures.as_ref().unwrap().len();
+
+    let range_payout_cets = offer_party
+        .manager
+        .get_range_payout_cets(&contract_id)
+        .expect("Error getting range payout CETs");
+    assert_eq!(1, range_payout_cets.len());
+    let range_payout_cets = &range_payout_cets[0];
+    assert_eq!(cets.len(), range_payout_cets.len());
+
+    let mut next_outcome = 0;
+    let mut adaptor_indexes = Vec::new();
+    for (i, range_payout_cet) in range_payout_cets.iter().enumerate() {
+        let range_payout = &range_payout_cet.range_payout;
+        assert_eq!(None, range_payout_cet.branch_index);
+        assert_eq!(i, range_payout_cet.cet_index);
+        assert_eq!(cets[i].txid(), range_payout_cet.cet_txid);
+        assert_eq!(next_outcome, range_payout.start);
+        assert_eq!(
+            2 * COLLATERAL,
+            range_payout.payout.offer + range_payout.payout.accept
+        );
+        assert!(!range_payout_cet.adaptor_indexes.is_empty());
+        next_outcome += range_payout.count;
+        adaptor_indexes.extend(range_payout_cet.adaptor_indexes.iter().cloned());
+    }
+    assert_eq!(max_value() as usize + 1, next_outcome);
+    adaptor_indexes.sort_unstable();
+    assert_eq!((0..nb_adaptor_sigs).collect::<Vec<_>>(), adaptor_indexes);
+
+    // Both parties get the same mapping.
+    let accept_range_payout_cets = accept_party
+        .manager
+        .get_range_payout_cets(&contract_id)
+        .expect("Error getting range payout CETs");
+    let cet_txids: Vec<_> = accept_range_payout_cets[0]
+        .iter()
+        .map(|x| x.cet_txid)
+        .collect();
+    assert_eq!(
+        range_payout_cets
+            .iter()
+            .map(|x| x.cet_txid)
+            .collect::<Vec<_>>(),
+        cet_txids
+    );
+}
+
+#[test]
+fn range_payout_cets_of_enum_contract_are_rejected_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+
+    assert!(matches!(
+        offer_party.manager.get_range_payout_cets(&contract_id),
+        Err(Error::InvalidParameters(_))
+    ));
+    assert!(matches!(
+        offer_party
+            .manager
+            .get_range_payout_cets(&ContractId([0u8; 32])),
+        Err(Error::InvalidParameters(_))
+    ));
+}
+
+#[test]
+fn closing_is_deferred_by_attestation_delay_test() {
+    const DELAY: u64 = 3600;
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let mut offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    offer_party
+        .manager
+        .set_attestation_delay(oracles[0].get_public_key(), DELAY);
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    let announcement = get_signed_contract(&offer_party, &contract_id)
+        .accepted_contract
+        .offered_contract
+        .contract_info[0]
+        .oracle_announcements[0]
+        .clone();
+    let event_maturity = announcement.oracle_event.event_maturity_epoch as u64;
+    assert_eq!(
+        event_maturity + DELAY,
+        offer_party
+            .manager
+            .get_expected_attestation_time(&announcement)
+    );
+    assert_eq!(
+        event_maturity,
+        accept_party
+            .manager
+            .get_expected_attestation_time(&announcement)
+    );
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+
+    set_time(std::cmp::max(event_maturity, contract_input.maturity_time as u64) + 1);
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+
+    set_time(event_maturity + DELAY);
+    periodic_check!(offer_party.manager, contract_id, Closed);
+}
+
+#[test]
+fn closing_with_oracles_of_different_attestation_delays_test() {
+    const DELAY: u64 = 3600;
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(3, 2, None));
+    let mut offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    offer_party
+        .manager
+        .set_attestation_delay(oracles[0].get_public_key(), 10 * DELAY);
+    offer_party
+        .manager
+        .set_attestation_delay(oracles[1].get_public_key(), DELAY);
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    let event_maturity = get_signed_contract(&offer_party, &contract_id)
+        .accepted_contract
+        .offered_contract
+        .contract_info[0]
+        .oracle_announcements[0]
+        .oracle_event
+        .event_maturity_epoch as u64;
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+
+    // Only the attestation of the last oracle is expected.
+    set_time(std::cmp::max(event_maturity, contract_input.maturity_time as u64) + 1);
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+
+    // The attestations of the last two oracles reach the threshold, and must
+    // be matched with the announcements of these oracles.
+    set_time(event_maturity + DELAY);
+    periodic_check!(offer_party.manager, contract_id, Closed);
+}
+
+#[test]
+fn offer_for_other_network_is_rejected_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let offer = offer_party
+        .manager
+        .send_offer(&contract_input, accept_party.node_id)
+        .expect("Error sending offer");
+    assert_eq!(
+        genesis_block(Network::Regtest).block_hash().into_inner(),
+        offer.chain_hash
+    );
+
+    let mut mainnet_offer = offer.clone();
+    mainnet_offer.chain_hash = genesis_block(Network::Bitcoin).block_hash().into_inner();
+    assert!(matches!(
+        accept_party
+            .manager
+            .on_dlc_message(&Message::Offer(mainnet_offer), offer_party.node_id),
+        Err(Error::NetworkMismatch {
+            expected: Network::Regtest,
+            actual: Network::Bitcoin,
+        })
+    ));
+
+    let mut unknown_chain_offer = offer.clone();
+    unknown_chain_offer.chain_hash = [1u8; 32];
+    assert!(matches!(
+        accept_party
+            .manager
+            .on_dlc_message(&Message::Offer(unknown_chain_offer), offer_party.node_id),
+        Err(Error::InvalidParameters(_))
+    ));
+
+    accept_party
+        .manager
+        .on_dlc_message(&Message::Offer(offer), offer_party.node_id)
+        .expect("Error processing offer");
+}
+
+#[test]
+fn wallet_for_other_network_is_rejected_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let wallet = Arc::new(MockWallet::new(Network::Bitcoin));
+    wallet.add_utxo(3 * COLLATERAL);
+    let manager: MockManager = Manager::new(
+        Arc::clone(&wallet),
+        Arc::clone(&blockchain),
+        Box::new(MemoryStorage::new()),
+        oracles
+            .iter()
+            .map(|x| (x.get_public_key(), Arc::clone(x)))
+            .collect(),
+        Arc::new(MockTime {}),
+    );
+
+    assert!(matches!(
+        manager.send_offer(&contract_input, accept_party.node_id),
+        Err(Error::NetworkMismatch {
+            expected: Network::Regtest,
+            actual: Network::Bitcoin,
+        })
+    ));
+}
+
+/// Creates a mock party whose manager uses the given secp256k1 context.
+fn new_mock_party_with_secp(
+    blockchain: &Arc<MockBlockchain>,
+    oracles: &[Arc<MockOracle>],
+    secp: Cow<'static, Secp256k1<All>>,
+) -> MockParty {
+    let wallet = Arc::new(MockWallet::new(Network::Regtest));
+    wallet.add_utxo(3 * COLLATERAL);
+    let node_id = PublicKey::from_secret_key(&secp, &SecretKey::new(&mut thread_rng()));
+    MockParty {
+        manager: Manager::new_with_secp(
+            Arc::clone(&wallet),
+            Arc::clone(blockchain),
+            Box::new(MemoryStorage::new()),
+            oracles
+                .iter()
+                .map(|x| (x.get_public_key(), Arc::clone(x)))
+                .collect(),
+            Arc::new(MockTime {}),
+            secp,
+        ),
+        wallet,
+        node_id,
+    }
+}
+
+#[test]
+fn manager_with_provided_secp_context_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_numerical_test_params(
+        1,
+        1,
+        false,
+        get_numerical_contract_descriptor(None),
+    ));
+    let offer_party = new_mock_party_with_secp(&blockchain, &oracles, Cow::Borrowed(SECP256K1));
+    let accept_party =
+        new_mock_party_with_secp(&blockchain, &oracles, Cow::Owned(Secp256k1::new()));
+    assert!(std::ptr::eq(offer_party.manager.get_secp(), &**SECP256K1));
+
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+    set_time(contract_input.maturity_time as u64 + 1);
+    periodic_check!(offer_party.manager, contract_id, Closed);
+}
+
+#[test]
+fn fund_transaction_broadcast_externally_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let mut accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    accept_party.manager.set_broadcast_fund_tx(false);
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    assert!(blockchain.get_broadcast_transactions().is_empty());
+
+    // The fund transaction is not rebroadcast either.
+    periodic_check!(accept_party.manager, contract_id, Signed);
+    assert!(blockchain.get_broadcast_transactions().is_empty());
+
+    assert!(matches!(
+        offer_party.manager.get_signed_funding(&contract_id),
+        Err(Error::InvalidParameters(_))
+    ));
+    let signed_funding = accept_party
+        .manager
+        .get_signed_funding(&contract_id)
+        .expect("Error getting signed funding");
+    let dlc_transactions = get_signed_contract(&accept_party, &contract_id)
+        .accepted_contract
+        .dlc_transactions;
+    assert_eq!(dlc_transactions.fund.txid(), signed_funding.fund.txid());
+    assert_eq!(dlc_transactions.refund.txid(), signed_funding.refund.txid());
+    assert!(signed_funding
+        .fund
+        .input
+        .iter()
+        .all(|x| !x.witness.is_empty()));
+    assert!(!signed_funding.refund.input[0].witness.is_empty());
+    assert_eq!(
+        bitcoin::consensus::encode::serialize_hex(&signed_funding.fund),
+        signed_funding.get_fund_hex()
+    );
+    assert_eq!(
+        bitcoin::consensus::encode::serialize_hex(&signed_funding.refund),
+        signed_funding.get_refund_hex()
+    );
+    assert_eq!(
+        signed_funding.fund,
+        signed_funding.get_fund_psbt().extract_tx()
+    );
+    assert_eq!(
+        signed_funding.refund,
+        signed_funding.get_refund_psbt().extract_tx()
+    );
+
+    blockchain
+        .send_transaction(&signed_funding.fund)
+        .expect("Error broadcasting fund transaction");
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+    periodic_check!(accept_party.manager, contract_id, Confirmed);
+}
+
+/// Establishes an enum contract with an oracle that does not publish its
+/// attestation, returning the parties, the contract id and an attestation of
+/// the event signed by the oracle.
+fn establish_contract_without_attestation() -> (
+    Arc<MockBlockchain>,
+    MockParty,
+    MockParty,
+    ContractId,
+    OracleAttestation,
+) {
+    let oracle = get_enum_oracle();
+    let mut attesting_oracle = oracle.clone();
+    attesting_oracle.add_attestation(EVENT_ID, &[enum_outcomes()[0].clone()]);
+    let attestation = attesting_oracle.get_attestation(EVENT_ID).unwrap();
+    let (blockchain, oracles, contract_input) =
+        mock_test_setup(get_enum_test_params(1, 1, Some(vec![oracle])));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    set_time(contract_input.maturity_time as u64 + 1);
+    (
+        blockchain,
+        offer_party,
+        accept_party,
+        contract_id,
+        attestation,
+    )
+}
+
+/// Has the offer party refund the given confirmed contract, and mines the
+/// refund transaction.
+fn refund_by_offer_party(
+    blockchain: &MockBlockchain,
+    offer_party: &MockParty,
+    contract_id: ContractId,
+) {
+    let refund_locktime = get_signed_contract(offer_party, &contract_id)
+        .accepted_contract
+        .dlc_transactions
+        .refund
+        .lock_time as u64;
+    set_time(refund_locktime + 1);
+    periodic_check!(offer_party.manager, contract_id, RefundedDueToNoAttestation);
+    blockchain.mine_blocks(1);
+}
+
+#[test]
+fn evicted_refund_is_rebroadcast_test() {
+    let (blockchain, offer_party, _accept_party, contract_id, _) =
+        establish_contract_without_attestation();
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+    let refund = get_signed_contract(&offer_party, &contract_id)
+        .accepted_contract
+        .dlc_transactions
+        .refund;
+    set_time(refund.lock_time as u64 + 1);
+    periodic_check!(offer_party.manager, contract_id, RefundedDueToNoAttestation);
+    assert!(blockchain
+        .is_in_mempool(&refund.txid())
+        .expect("Error querying the mempool"));
+
+    assert!(blockchain.evict(&refund.txid()));
+    assert!(!blockchain
+        .is_in_mempool(&refund.txid())
+        .expect("Error querying the mempool"));
+    periodic_check!(offer_party.manager, contract_id, RefundedDueToNoAttestation);
+    assert!(blockchain
+        .is_in_mempool(&refund.txid())
+        .expect("Error querying the mempool"));
+}
+
+#[test]
+fn counter_party_refund_due_to_no_attestation_test() {
+    let (blockchain, offer_party, mut accept_party, contract_id, _) =
+        establish_contract_without_attestation();
+    let event_recorder = EventRecorder::default();
+    accept_party
+        .manager
+        .set_event_handler(Box::new(event_recorder.clone()));
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+    periodic_check!(accept_party.manager, contract_id, Confirmed);
+
+    refund_by_offer_party(&blockchain, &offer_party, contract_id);
+
+    periodic_check!(
+        accept_party.manager,
+        contract_id,
+        RefundedDueToNoAttestation
+    );
+    assert_eq!(
+        vec![Event::ContractRefundedDueToNoAttestation { contract_id }],
+        *event_recorder.events.lock().unwrap()
+    );
+}
+
+#[test]
+fn counter_party_refund_with_attestation_test() {
+    let (blockchain, offer_party, mut accept_party, contract_id, attestation) =
+        establish_contract_without_attestation();
+    let event_recorder = EventRecorder::default();
+    accept_party
+        .manager
+        .set_event_handler(Box::new(event_recorder.clone()));
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+    periodic_check!(accept_party.manager, contract_id, Confirmed);
+
+    refund_by_offer_party(&blockchain, &offer_party, contract_id);
+
+    accept_party
+        .manager
+        .provide_attestation(&contract_id, attestation)
+        .expect("Error providing attestation");
+    assert_contract_state!(accept_party.manager, contract_id, Refunded);
+    assert!(event_recorder.events.lock().unwrap().is_empty());
+}
+
+#[test]
+fn provided_attestation_closes_confirmed_contract_test() {
+    let (blockchain, offer_party, _accept_party, contract_id, attestation) =
+        establish_contract_without_attestation();
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+
+    let mut other_oracle = get_enum_oracle();
+    other_oracle.add_attestation(EVENT_ID, &[enum_outcomes()[0].clone()]);
+    assert!(matches!(
+        offer_party.manager.provide_attestation(
+            &contract_id,
+            other_oracle.get_attestation(EVENT_ID).unwrap()
+        ),
+        Err(Error::InvalidParameters(_))
+    ));
+    assert!(matches!(
+        offer_party
+            .manager
+            .provide_attestation(&ContractId([0u8; 32]), attestation.clone()),
+        Err(Error::InvalidParameters(_))
+    ));
+    assert_contract_state!(offer_party.manager, contract_id, Confirmed);
+
+    offer_party
+        .manager
+        .provide_attestation(&contract_id, attestation)
+        .expect("Error providing attestation");
+    assert_contract_state!(offer_party.manager, contract_id, Closed);
+    let closed_contract = get_closed_contract(&offer_party, &contract_id);
+    assert_eq!(
+        vec![enum_outcomes()[0].clone()],
+        closed_contract.attestations[0].outcomes
+    );
+}
+
+#[test]
+fn provided_attestation_is_used_once_confirmed_test() {
+    let (blockchain, offer_party, _accept_party, contract_id, attestation) =
+        establish_contract_without_attestation();
+
+    offer_party
+        .manager
+        .provide_attestation(&contract_id, attestation)
+        .expect("Error providing attestation");
+    assert_contract_state!(offer_party.manager, contract_id, Signed);
+
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    offer_party
+        .manager
+        .periodic_check()
+        .expect("Periodic check error");
+    periodic_check!(offer_party.manager, contract_id, Closed);
+}
+
+#[test]
+fn premium_contract_with_zero_accept_collateral_test() {
+    const PREMIUM: u64 = COLLATERAL / 10;
+    let (blockchain, oracles, mut contract_input) =
+        mock_test_setup(get_enum_test_params(1, 1, None));
+    contract_input.offer_collateral = 2 * COLLATERAL;
+    contract_input.accept_collateral = 0;
+    contract_input.premium = Some(PremiumInput {
+        amount: PREMIUM,
+        paid_by_offer: false,
+    });
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    // The accepting party can only fund the premium and its fees.
+    let accept_party = new_mock_party(&blockchain, &oracles, COLLATERAL);
+
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+
+    let accepted_contract = get_signed_contract(&offer_party, &contract_id).accepted_contract;
+    assert_eq!(0, accepted_contract.accept_params.collateral);
+    assert_eq!(
+        2 * COLLATERAL,
+        accepted_contract.offered_contract.offer_params.collateral
+    );
+    let fund = &accepted_contract.dlc_transactions.fund;
+    assert!(fund.output.iter().any(|x| x.value == PREMIUM
+        && x.script_pubkey
+            == accepted_contract
+                .offered_contract
+                .offer_params
+                .payout_script_pubkey));
+}
+
+#[test]
+fn accept_with_unexpected_collateral_is_rejected_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let temporary_id = deliver_mock_offer(&offer_party, &accept_party, &contract_input);
+    let (_, _, mut accept) = accept_party
+        .manager
+        .accept_contract_offer(&temporary_id)
+        .expect("Error accepting offer");
+    accept.accept_collateral += 1;
+
+    assert!(matches!(
+        offer_party
+            .manager
+            .on_dlc_message(&Message::Accept(accept), accept_party.node_id),
+        Err(Error::InvalidParameters(_))
+    ));
+    assert_contract_state_by_temporary_id!(offer_party.manager, temporary_id, FailedAccept);
+}
+
+#[test]
+#[ignore]
+fn bitcoin_core_spending_transaction_test() {
+    let (alice_rpc, bob_rpc, sink_rpc) = init_clients();
+    let alice = BitcoinCoreProvider::from_client(alice_rpc);
+    let bob = BitcoinCoreProvider::from_client(bob_rpc);
+    let utxo = alice
+        .get_utxos_for_amount(100_000, None, false)
+        .expect("Error getting UTXOs")
+        .remove(0);
+    assert!(alice
+        .get_spending_transaction(&utxo.outpoint)
+        .expect("Error getting spending transaction")
+        .is_none());
+
+    let tx = Transaction {
+        version: 2,
+        lock_time: 0,
+        input: vec![TxIn {
+            previous_output: utxo.outpoint,
+            script_sig: Script::new(),
+            sequence: 0xffffffff,
+            witness: Vec::new(),
+        }],
+        output: vec![TxOut {
+            value: utxo.tx_out.value - 10_000,
+            script_pubkey: bob.get_new_address().unwrap().script_pubkey(),
+        }],
+    };
+    let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+    psbt.inputs[0].witness_utxo = Some(utxo.tx_out.clone());
+    alice
+        .sign_psbt_input(&mut psbt, 0)
+        .expect("Error signing input");
+    let tx = psbt.extract_tx();
+    alice
+        .send_transaction(&tx)
+        .expect("Error broadcasting transaction");
+
+    // The spending transaction is found in the mempool, and then in the
+    // blockchain once mined.
+    let get_spending_txid = || {
+        alice
+            .get_spending_transaction(&utxo.outpoint)
+            .expect("Error getting spending transaction")
+            .map(|x| x.txid())
+    };
+    assert_eq!(Some(tx.txid()), get_spending_txid());
+    let sink_address = sink_rpc.get_new_address(None, None).unwrap();
+    sink_rpc.generate_to_address(1, &sink_address).unwrap();
+    assert_eq!(Some(tx.txid()), get_spending_txid());
+    assert_eq!(Some(tx.txid()), get_spending_txid());
+}
+
+#[test]
+fn failed_fund_rebroadcast_does_not_fail_check_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    let fund = get_signed_contract(&accept_party, &contract_id)
+        .accepted_contract
+        .dlc_transactions
+        .fund;
+    assert!(blockchain.evict(&fund.txid()));
+    // A transaction double spending the fund transaction makes its
+    // rebroadcast fail.
+    let conflicting = Transaction {
+        version: 2,
+        lock_time: 0,
+        input: vec![TxIn {
+            previous_output: fund.input[0].previous_output,
+            script_sig: Script::new(),
+            sequence: 0xffffffff,
+            witness: Vec::new(),
+        }],
+        output: vec![TxOut {
+            value: 1000,
+            script_pubkey: Script::new(),
+        }],
+    };
+    blockchain
+        .send_transaction(&conflicting)
+        .expect("Error sending conflicting transaction");
+    let nb_broadcast = blockchain.get_broadcast_transactions().len();
+
+    periodic_check!(accept_party.manager, contract_id, Signed);
+    assert_eq!(nb_broadcast, blockchain.get_broadcast_transactions().len());
+}
+
+#[test]
+fn settled_closing_transaction_is_not_rebroadcast_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+
+    set_time(contract_input.maturity_time as u64 + 1);
+    periodic_check!(offer_party.manager, contract_id, Closed);
+    let nb_broadcast = blockchain.get_broadcast_transactions().len();
+
+    // The CET is rebroadcast if evicted before reaching NB_CONFIRMATIONS.
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64 - 1);
+    blockchain.reorg(NB_CONFIRMATIONS as usize - 1, true);
+    periodic_check!(offer_party.manager, contract_id, Closed);
+    assert_eq!(
+        nb_broadcast + 1,
+        blockchain.get_broadcast_transactions().len()
+    );
+
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    periodic_check!(offer_party.manager, contract_id, Closed);
+    // Once it did, the CET is not watched anymore.
+    blockchain.reorg(NB_CONFIRMATIONS as usize, true);
+    periodic_check!(offer_party.manager, contract_id, Closed);
+    assert_eq!(
+        nb_broadcast + 1,
+        blockchain.get_broadcast_transactions().len()
+    );
+}
+
+#[test]
+fn fund_confirmation_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    let fund = get_signed_contract(&accept_party, &contract_id)
+        .accepted_contract
+        .dlc_transactions
+        .fund;
+    assert!(is_broadcast(&blockchain, &fund));
+
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64 - 1);
+    periodic_check!(offer_party.manager, contract_id, Signed);
+    periodic_check!(accept_party.manager, contract_id, Signed);
+
+    blockchain.mine_blocks(1);
+    assert_eq!(
+        NB_CONFIRMATIONS as u64,
+        blockchain.get_confirmations(&fund.txid())
+    );
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+    periodic_check!(accept_party.manager, contract_id, Confirmed);
+    assert_eq!(1, blockchain.get_broadcast_transactions().len());
+}
+
+#[test]
+fn reorged_fund_transaction_is_confirmed_again_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    let fund = get_signed_contract(&accept_party, &contract_id)
+        .accepted_contract
+        .dlc_transactions
+        .fund;
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64 - 1);
+
+    // The reorged fund transaction goes back to the mempool, so it is not
+    // broadcast again.
+    blockchain.reorg(NB_CONFIRMATIONS as usize - 1, false);
+    assert_eq!(0, blockchain.get_confirmations(&fund.txid()));
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64 - 1);
+    periodic_check!(offer_party.manager, contract_id, Signed);
+    periodic_check!(accept_party.manager, contract_id, Signed);
+    assert_eq!(1, blockchain.get_broadcast_transactions().len());
+
+    blockchain.mine_blocks(1);
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+    periodic_check!(accept_party.manager, contract_id, Confirmed);
+}
+
+#[test]
+fn evicted_fund_transaction_is_rebroadcast_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let contract_id = establish_mock_contract(&offer_party, &accept_party, &contract_input);
+    let fund = get_signed_contract(&accept_party, &contract_id)
+        .accepted_contract
+        .dlc_transactions
+        .fund;
+
+    assert!(blockchain.evict(&fund.txid()));
+    periodic_check!(accept_party.manager, contract_id, Signed);
+    assert_eq!(2, blockchain.get_broadcast_transactions().len());
+    assert!(blockchain
+        .is_in_mempool(&fund.txid())
+        .expect("Error querying the mempool"));
+
+    // The same goes for a fund transaction evicted by a reorg.
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64 - 1);
+    blockchain.reorg(NB_CONFIRMATIONS as usize - 1, true);
+    periodic_check!(accept_party.manager, contract_id, Signed);
+    assert_eq!(3, blockchain.get_broadcast_transactions().len());
+
+    blockchain.mine_blocks(NB_CONFIRMATIONS as u64);
+    periodic_check!(offer_party.manager, contract_id, Confirmed);
+    periodic_check!(accept_party.manager, contract_id, Confirmed);
+    assert_eq!(3, blockchain.get_broadcast_transactions().len());
+}
+
+#[test]
+fn fund_transaction_is_signed_by_mock_wallets_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    establish_mock_contract(&offer_party, &accept_party, &contract_input);
+
+    let fund = blockchain.get_broadcast_transactions().remove(0);
+    assert_eq!(2, fund.input.len());
+    for input in &fund.input {
+        assert!(!input.witness.is_empty());
+    }
+    // Each party funds the contract with its own UTXO.
+    let is_from_wallet = |wallet: &MockWallet| {
+        fund.input
+            .iter()
+            .filter(|x| wallet.get_transaction(&x.previous_output.txid).is_ok())
+            .count()
+    };
+    assert_eq!(1, is_from_wallet(&offer_party.wallet));
+    assert_eq!(1, is_from_wallet(&accept_party.wallet));
+    assert_eq!(0, offer_party.wallet.get_balance());
+    assert_eq!(0, accept_party.wallet.get_balance());
+}
+
+#[test]
+fn insufficient_wallet_funds_are_rejected_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, COLLATERAL / 2);
+    let accept_party = new_mock_party(&blockchain, &oracles, COLLATERAL / 2);
+    assert!(matches!(
+        offer_party
+            .manager
+            .send_offer(&contract_input, accept_party.node_id),
+        Err(Error::WalletError(_))
+    ));
+    assert_eq!(COLLATERAL / 2, offer_party.wallet.get_balance());
+
+    offer_party.wallet.add_utxo(3 * COLLATERAL);
+    let temporary_id = deliver_mock_offer(&offer_party, &accept_party, &contract_input);
+    assert!(matches!(
+        accept_party.manager.accept_contract_offer(&temporary_id),
+        Err(Error::WalletError(_))
+    ));
+    assert_contract_state_by_temporary_id!(accept_party.manager, temporary_id, Offered);
+    assert_eq!(COLLATERAL / 2, accept_party.wallet.get_balance());
+}
+
+/// Records the metrics reported by a manager.
+#[derive(Clone, Default)]
+struct MetricsRecorder {
+    durations: Arc<std::sync::Mutex<Vec<Operation>>>,
+    message_sizes: Arc<std::sync::Mutex<Vec<(u16, MessageDirection, usize)>>>,
+    contract_counts: Arc<std::sync::Mutex<Vec<(String, usize)>>>,
+}
+
+impl Metrics for MetricsRecorder {
+    fn record_duration(&self, operation: Operation, _duration: Duration) {
+        self.durations.lock().unwrap().push(operation);
+    }
+
+    fn record_message_size(&self, message_type: u16, direction: MessageDirection, size: usize) {
+        self.message_sizes
+            .lock()
+            .unwrap()
+            .push((message_type, direction, size));
+    }
+
+    fn record_contract_count(&self, state: &str, count: usize) {
+        self.contract_counts
+            .lock()
+            .unwrap()
+            .push((state.to_string(), count));
+    }
+}
+
+#[test]
+fn metrics_are_reported_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let mut offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let mut accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let offer_metrics = MetricsRecorder::default();
+    let accept_metrics = MetricsRecorder::default();
+    offer_party
+        .manager
+        .set_metrics(Box::new(offer_metrics.clone()));
+    accept_party
+        .manager
+        .set_metrics(Box::new(accept_metrics.clone()));
+
+    let offer = offer_party
+        .manager
+        .send_offer(&contract_input, accept_party.node_id)
+        .expect("Error sending offer");
+    let offer_size = offer.encode().len();
+    let temporary_id = offer.get_temporary_contract_id().unwrap();
+    accept_party
+        .manager
+        .on_dlc_message(&Message::Offer(offer.clone()), offer_party.node_id)
+        .expect("Error processing offer");
+    let (contract_id, _, accept) = accept_party
+        .manager
+        .accept_contract_offer(&temporary_id)
+        .expect("Error accepting offer");
+    let accept_size = accept.encode().len();
+    let sign = offer_party
+        .manager
+        .on_dlc_message(&Message::Accept(accept.clone()), accept_party.node_id)
+        .expect("Error processing accept")
+        .expect("Expected sign message");
+    let sign_size = match &sign {
+        Message::Sign(s) => s.encode().len(),
+        _ => panic!("Expected sign message"),
+    };
+    accept_party
+        .manager
+        .on_dlc_message(&sign, offer_party.node_id)
+        .expect("Error processing sign");
+
+    assert_eq!(
+        vec![
+            (offer.type_id(), MessageDirection::Outgoing, offer_size),
+            (accept.type_id(), MessageDirection::Incoming, accept_size),
+            (sign.type_id(), MessageDirection::Outgoing, sign_size),
+        ],
+        *offer_metrics.message_sizes.lock().unwrap()
+    );
+    assert_eq!(
+        vec![
+            (offer.type_id(), MessageDirection::Incoming, offer_size),
+            (accept.type_id(), MessageDirection::Outgoing, accept_size),
+            (sign.type_id(), MessageDirection::Incoming, sign_size),
+        ],
+        *accept_metrics.message_sizes.lock().unwrap()
+    );
+
+    // Each party creates its own adaptor signatures and verifies the ones of
+    // the counter party.
+    for metrics in &[&offer_metrics, &accept_metrics] {
+        let durations = metrics.durations.lock().unwrap();
+        for operation in &[
+            Operation::AdaptorSignatureCreation,
+            Operation::AdaptorSignatureVerification,
+            Operation::RefundSignatureVerification,
+        ] {
+            assert!(
+                durations.contains(operation),
+                "{:?} not recorded",
+                operation
+            );
+        }
+    }
+
+    // Only the states holding contracts are reported.
+    assert!(offer_metrics.contract_counts.lock().unwrap().is_empty());
+    periodic_check!(offer_party.manager, contract_id, Signed);
+    assert_eq!(
+        vec![("signed".to_string(), 1)],
+        *offer_metrics.contract_counts.lock().unwrap()
+    );
+}
+
+#[test]
+fn oversized_message_is_rejected_before_parsing_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let mut accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let offer = offer_party
+        .manager
+        .send_offer(&contract_input, accept_party.node_id)
+        .expect("Error sending offer");
+    let mut buf = offer.type_id().to_be_bytes().to_vec();
+    buf.extend(offer.encode());
+    let size = buf.len() - 2;
+    accept_party.manager.set_peer_policy(
+        offer_party.node_id,
+        PeerPolicy {
+            max_message_size: Some(size),
+            ..Default::default()
+        },
+    );
+
+    assert!(matches!(
+        accept_party
+            .manager
+            .decode_dlc_message(&buf, &offer_party.node_id),
+        Ok(Message::Offer(_))
+    ));
+    // A larger message is rejected on its size, without being parsed.
+    let garbage = vec![0xFF; buf.len() + 1];
+    match accept_party
+        .manager
+        .decode_dlc_message(&garbage, &offer_party.node_id)
+    {
+        Err(Error::InvalidParameters(e)) => assert!(e.contains("exceeds")),
+        _ => panic!("Expected the message to be rejected"),
+    }
+    // Other counter parties are not subject to the limit.
+    assert!(matches!(
+        accept_party
+            .manager
+            .decode_dlc_message(&garbage, &accept_party.node_id),
+        Err(Error::InvalidParameters(e)) if e.starts_with("Invalid message")
+    ));
+}
+
+#[test]
+fn concurrent_offers_do_not_exceed_peer_policy_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let mut offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    for _ in 0..3 {
+        offer_party.wallet.add_utxo(3 * COLLATERAL);
+    }
+    offer_party.manager.set_peer_policy(
+        accept_party.node_id,
+        PeerPolicy {
+            max_open_contracts: Some(1),
+            ..Default::default()
+        },
+    );
+    let maturity_time = contract_input.maturity_time as u64;
+    let offer_party = Arc::new(offer_party);
+    let contract_input = Arc::new(contract_input);
+
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let offer_party = Arc::clone(&offer_party);
+            let contract_input = Arc::clone(&contract_input);
+            let counter_party = accept_party.node_id;
+            thread::spawn(move || {
+                set_time(maturity_time - 1);
+                offer_party
+                    .manager
+                    .send_offer(&contract_input, counter_party)
+                    .is_ok()
+            })
+        })
+        .collect();
+    let nb_offers = handles
+        .into_iter()
+        .map(|x| x.join().unwrap())
+        .filter(|x| *x)
+        .count();
+
+    assert_eq!(1, nb_offers);
+}
+
+#[test]
+fn pending_accept_counts_towards_peer_policy_test() {
+    let (blockchain, oracles, contract_input) = mock_test_setup(get_enum_test_params(1, 1, None));
+    let offer_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    let mut accept_party = new_mock_party(&blockchain, &oracles, 3 * COLLATERAL);
+    offer_party.wallet.add_utxo(3 * COLLATERAL);
+    accept_party.wallet.add_utxo(3 * COLLATERAL);
+    accept_party.manager.set_peer_policy(
+        offer_party.node_id,
+        PeerPolicy {
+            max_open_contracts: Some(1),
+            ..Default::default()
+        },
+    );
+    let first_id = deliver_mock_offer(&offer_party, &accept_party, &contract_input);
+    let second_id = deliver_mock_offer(&offer_party, &accept_party, &contract_input);
+
+    accept_party
+        .manager
+        .prepare_accept_contract_offer(&first_id)
+        .expect("Error preparing accept");
+    match accept_party
+        .manager
+        .prepare_accept_contract_offer(&second_id)
+    {
+        Err(Error::InvalidParameters(e)) => assert!(e.contains("Maximum number")),
+        _ => panic!("Expected the peer policy to be enforced"),
+    }
+    assert!(accept_party
+        .manager
+        .accept_contract_offer(&second_id)
+        .is_err());
+
+    accept_party
+        .manager
+        .abort_accept(&first_id)
+        .expect("Error aborting accept");
+    accept_party
+        .manager
+        .accept_contract_offer(&second_id)
+        .expect("Error accepting offer");
+}
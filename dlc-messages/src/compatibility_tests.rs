@@ -581,7 +581,7 @@ fn test_single(case: TestCase, secp: &secp256k1::Secp256k1<secp256k1::All>) {
         funding_inputs: offer_inputs,
     };
 
-    let temporary_contract_id = offer.get_hash().unwrap();
+    let temporary_contract_id = offer.get_temporary_contract_id().unwrap();
 
     let accept = AcceptDlc {
         funding_inputs: accept_inputs,
@@ -0,0 +1,111 @@
+//! Strongly typed identifiers for DLCs, preventing the temporary id of a
+//! contract to be used where its final id is expected and vice versa.
+
+use lightning::ln::msgs::DecodeError;
+use lightning::util::ser::{Readable, Writeable, Writer};
+use secp256k1_zkp::bitcoin_hashes::hex::{Error as HexError, FromHex};
+use std::fmt;
+use std::str::FromStr;
+
+macro_rules! impl_contract_id {
+    ($st: ident) => {
+        impl From<[u8; 32]> for $st {
+            fn from(id: [u8; 32]) -> $st {
+                $st(id)
+            }
+        }
+
+        impl AsRef<[u8]> for $st {
+            fn as_ref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $st {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                for byte in &self.0 {
+                    write!(f, "{:02x}", byte)?;
+                }
+                Ok(())
+            }
+        }
+
+        impl FromStr for $st {
+            type Err = HexError;
+
+            fn from_str(s: &str) -> Result<$st, HexError> {
+                Ok($st(FromHex::from_hex(s)?))
+            }
+        }
+
+        impl Writeable for $st {
+            fn write<W: Writer>(&self, writer: &mut W) -> Result<(), ::std::io::Error> {
+                self.0.write(writer)
+            }
+        }
+
+        impl Readable for $st {
+            fn read<R: ::std::io::Read>(reader: &mut R) -> Result<$st, DecodeError> {
+                Ok($st(Readable::read(reader)?))
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for $st {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                crate::serde_utils::serialize_hex(&self.0, serializer)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for $st {
+            fn deserialize<D>(deserializer: D) -> Result<$st, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                crate::serde_utils::deserialize_hex_array(deserializer).map($st)
+            }
+        }
+    };
+}
+
+/// The identifier of a contract, computed from the fund transaction id and the
+/// temporary contract id once the offer was accepted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ContractId(pub [u8; 32]);
+
+impl_contract_id!(ContractId);
+
+/// The identifier of a contract before it was accepted, computed as the hash of
+/// the offer message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TemporaryContractId(pub [u8; 32]);
+
+impl_contract_id!(TemporaryContractId);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contract_id_string_roundtrip() {
+        let id = ContractId([0xab; 32]);
+        let s = id.to_string();
+        assert_eq!(64, s.len());
+        assert_eq!(id, ContractId::from_str(&s).unwrap());
+        assert!(TemporaryContractId::from_str("abcd").is_err());
+    }
+
+    #[test]
+    fn contract_id_ser_roundtrip() {
+        let id = TemporaryContractId([3; 32]);
+        let mut buf = Vec::new();
+        id.write(&mut buf).unwrap();
+        assert_eq!(&id.0[..], &buf[..]);
+        let read: TemporaryContractId = Readable::read(&mut ::std::io::Cursor::new(&buf)).unwrap();
+        assert_eq!(id, read);
+    }
+}
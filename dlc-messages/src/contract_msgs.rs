@@ -1,6 +1,7 @@
 use lightning::ln::msgs::DecodeError;
 use lightning::util::ser::{Readable, Writeable, Writer};
-use oracle_msgs::OracleInfo;
+use oracle_msgs::{OracleAnnouncement, OracleInfo};
+use ser_impls::{read_as_tlv, write_as_tlv};
 
 /// Represents a single outcome of a DLC contract and the associated offer party
 /// payout.
@@ -90,10 +91,18 @@ impl_dlc_writeable!(ContractInfoInner, { (contract_descriptor, writeable), (orac
 pub enum ContractDescriptor {
     EnumeratedContractDescriptor(EnumeratedContractDescriptor),
     NumericOutcomeContractDescriptor(NumericOutcomeContractDescriptor),
+    SummedNumericOutcomeContractDescriptor(SummedNumericOutcomeContractDescriptor),
+    HybridOutcomeContractDescriptor(HybridOutcomeContractDescriptor),
+    BoundedNumericOutcomeContractDescriptor(BoundedNumericOutcomeContractDescriptor),
 }
 
 impl_dlc_writeable_enum!(
-    ContractDescriptor, (0, EnumeratedContractDescriptor), (1, NumericOutcomeContractDescriptor);;
+    ContractDescriptor,
+    (0, EnumeratedContractDescriptor),
+    (1, NumericOutcomeContractDescriptor),
+    (2, SummedNumericOutcomeContractDescriptor),
+    (3, HybridOutcomeContractDescriptor),
+    (4, BoundedNumericOutcomeContractDescriptor);;
 );
 
 #[derive(Clone, Debug, PartialEq)]
@@ -122,6 +131,77 @@ pub struct NumericOutcomeContractDescriptor {
 
 impl_dlc_writeable!(NumericOutcomeContractDescriptor, { (num_digits, writeable), (payout_function, writeable), (rounding_intervals, writeable) });
 
+/// Descriptor for a numerical outcome contract whose payouts are given by the
+/// sum of multiple payout functions, computed before rounding.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct SummedNumericOutcomeContractDescriptor {
+    pub num_digits: u16,
+    pub payout_functions: Vec<PayoutFunction>,
+    pub rounding_intervals: RoundingIntervals,
+}
+
+impl_dlc_writeable!(SummedNumericOutcomeContractDescriptor, { (num_digits, writeable), (payout_functions, vec), (rounding_intervals, writeable) });
+
+/// Descriptor for a numerical outcome contract whose oracles cannot attest
+/// outcomes above `max_value`, which is lower than the largest outcome that
+/// can be represented with `num_digits` digits. The payouts are given by the
+/// sum of the payout functions, and outcomes above `max_value` are not
+/// covered by any CET.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct BoundedNumericOutcomeContractDescriptor {
+    pub num_digits: u16,
+    pub max_value: u64,
+    pub payout_functions: Vec<PayoutFunction>,
+    pub rounding_intervals: RoundingIntervals,
+}
+
+impl_dlc_writeable!(BoundedNumericOutcomeContractDescriptor, { (num_digits, writeable), (max_value, writeable), (payout_functions, vec), (rounding_intervals, writeable) });
+
+/// Descriptor for a contract whose payouts are given by one of several payout
+/// functions of the outcome of the numerical event of the contract oracle
+/// information, the function being selected by the outcome of an enumerated
+/// event attested by the same oracles.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct HybridOutcomeContractDescriptor {
+    pub selector_announcements: Vec<OracleAnnouncement>,
+    pub branches: Vec<HybridOutcomeBranch>,
+}
+
+impl_dlc_writeable!(HybridOutcomeContractDescriptor, {
+    (selector_announcements, {vec_cb, write_as_tlv, read_as_tlv}),
+    (branches, vec)
+});
+
+/// The payouts of a hybrid contract when the selector event has the given
+/// outcome.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct HybridOutcomeBranch {
+    pub outcome: String,
+    pub descriptor: NumericOutcomeContractDescriptor,
+}
+
+impl_dlc_writeable!(HybridOutcomeBranch, { (outcome, string), (descriptor, writeable) });
+
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(
     feature = "serde",
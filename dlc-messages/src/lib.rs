@@ -16,22 +16,26 @@ extern crate serde;
 #[cfg(test)]
 extern crate serde_json;
 
+pub mod contract_id;
 pub mod contract_msgs;
+pub mod marketplace;
+pub mod offer_builder;
 pub mod oracle_msgs;
 
 #[cfg(any(test, feature = "serde"))]
 pub mod serde_utils;
 
 use bitcoin::{consensus::Decodable, hash_types::Txid, OutPoint, Script, Transaction};
+pub use contract_id::{ContractId, TemporaryContractId};
 use contract_msgs::ContractInfo;
-use dlc::TxInputInfo;
+use dlc::{PremiumInfo, TxInputInfo};
 use lightning::ln::msgs::DecodeError;
 use lightning::ln::wire::Type;
 use lightning::util::ser::{Readable, Writeable, Writer};
 use secp256k1_zkp::bitcoin_hashes::*;
 use secp256k1_zkp::EcdsaAdaptorSignature;
 use secp256k1_zkp::{PublicKey, Signature};
-use ser_impls::{read_ecdsa_adaptor_signature, write_ecdsa_adaptor_signature};
+use ser_impls::{read_ecdsa_adaptor_signature, write_ecdsa_adaptor_signature, TlvRecord};
 
 pub const OFFER_TYPE: u16 = 42778;
 
@@ -39,6 +43,29 @@ pub const ACCEPT_TYPE: u16 = 42780;
 
 pub const SIGN_TYPE: u16 = 42782;
 
+pub const RENEW_OFFER_TYPE: u16 = 42784;
+
+pub const REESTABLISH_TYPE: u16 = 42786;
+
+/// Bit of [`OfferDlc::contract_flags`] indicating that the payouts of the
+/// numerical outcomes of the contract must be computed using integer
+/// arithmetic rather than floating point, so that both parties derive the
+/// same payouts.
+pub const EXACT_PAYOUTS_FLAG: u8 = 1;
+
+/// Type of the TLV record containing the [`OfferDlc::premium`].
+pub const PREMIUM_TLV_TYPE: u64 = 1;
+
+/// Type of the TLV record containing the [`OfferDlc::offer_fee_share`].
+pub const OFFER_FEE_SHARE_TLV_TYPE: u64 = 3;
+
+/// Type of the TLV record containing the [`OfferDlc::nb_confirmations`].
+pub const NB_CONFIRMATIONS_TLV_TYPE: u64 = 5;
+
+/// Type of the TLV record containing the funding input proofs of offer and
+/// accept messages.
+pub const FUNDING_INPUT_PROOFS_TLV_TYPE: u64 = 7;
+
 /// Contains information about a specific input to be used in a funding transaction,
 /// as well as its corresponding on-chain UTXO.
 #[derive(Clone, Debug, PartialEq)]
@@ -154,6 +181,40 @@ pub struct FundingSignatures {
 
 impl_dlc_writeable!(FundingSignatures, { (funding_signatures, vec) });
 
+/// Contains the witness proving that the party providing the funding input
+/// with the given serial id controls the output it spends (see
+/// [`dlc::ownership_proof`]).
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct FundingInputProof {
+    pub input_serial_id: u64,
+    pub witness_elements: Vec<WitnessElement>,
+}
+
+impl_dlc_writeable!(FundingInputProof, {
+    (input_serial_id, writeable),
+    (witness_elements, vec)
+});
+
+/// Contains the ownership proofs of the funding inputs of a party. Inputs
+/// that the party cannot sign alone, such as the fund output of a renewed
+/// contract, have no proof.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct FundingInputProofs {
+    pub funding_input_proofs: Vec<FundingInputProof>,
+}
+
+impl_dlc_writeable!(FundingInputProofs, { (funding_input_proofs, vec) });
+
 /// Contains serialized data representing a single witness stack element.
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(
@@ -214,6 +275,46 @@ pub struct DisjointNegotiationFields {
 
 impl_dlc_writeable!(DisjointNegotiationFields, { (negotiation_fields, vec) });
 
+/// Contains information about a premium paid by one party to the other through
+/// an additional output of the fund transaction.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct Premium {
+    pub amount: u64,
+    pub paid_by_offer: bool,
+    pub serial_id: u64,
+}
+
+impl_dlc_writeable!(Premium, {
+    (amount, writeable),
+    (paid_by_offer, writeable),
+    (serial_id, writeable)
+});
+
+impl From<&Premium> for PremiumInfo {
+    fn from(premium: &Premium) -> PremiumInfo {
+        PremiumInfo {
+            amount: premium.amount,
+            paid_by_offer: premium.paid_by_offer,
+            serial_id: premium.serial_id,
+        }
+    }
+}
+
+impl From<&PremiumInfo> for Premium {
+    fn from(premium_info: &PremiumInfo) -> Premium {
+        Premium {
+            amount: premium_info.amount,
+            paid_by_offer: premium_info.paid_by_offer,
+            serial_id: premium_info.serial_id,
+        }
+    }
+}
+
 /// Contains information about a party wishing to enter into a DLC with
 /// another party. The contained information is sufficient for any other party
 /// to create a set of transactions representing the contract and its terms.
@@ -246,6 +347,29 @@ pub struct OfferDlc {
     pub fee_rate_per_vb: u64,
     pub contract_maturity_bound: u32,
     pub contract_timeout: u32,
+    /// An optional premium paid within the fund transaction, serialized as a
+    /// record of the TLV stream ending the message when present.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub premium: Option<Premium>,
+    /// The share of the fees paid by the offer party in basis points (see
+    /// [`dlc::FeeSplit`]), each party paying for its own inputs and outputs if
+    /// not specified. Serialized as a TLV record when present.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub offer_fee_share: Option<u16>,
+    /// The number of confirmations of the fund transaction required before
+    /// considering the contract as confirmed. Serialized as a TLV record when
+    /// present.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub nb_confirmations: Option<u32>,
+    /// The proofs that the offering party controls the outputs spent by its
+    /// funding inputs. Serialized as a TLV record when present.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub funding_input_proofs: Option<FundingInputProofs>,
+    /// Records of odd types unknown to this implementation found at the end
+    /// of the message, kept so that the message serializes back to the bytes
+    /// that were received and hashes to the same temporary contract id.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub extra_tlvs: Vec<TlvRecord>,
 }
 
 impl Type for OfferDlc {
@@ -255,13 +379,22 @@ impl Type for OfferDlc {
 }
 
 impl OfferDlc {
-    /// Returns the hash of the serialized OfferDlc message.
+    /// Returns the hash of the serialized OfferDlc message. Records unknown
+    /// to this implementation being serialized back as received, the hash is
+    /// the same for all parties, making it usable as temporary contract id and
+    /// for signing offers (see [`marketplace::SignedOffer`]).
     pub fn get_hash(&self) -> Result<[u8; 32], ::std::io::Error> {
         let mut buff = Vec::new();
         self.write(&mut buff)?;
         Ok(sha256::Hash::hash(&buff).into_inner())
     }
 
+    /// Returns the temporary id of the contract offered by the message, which
+    /// is the hash of the serialized message.
+    pub fn get_temporary_contract_id(&self) -> Result<TemporaryContractId, ::std::io::Error> {
+        Ok(TemporaryContractId(self.get_hash()?))
+    }
+
     pub fn get_total_collateral(&self) -> u64 {
         match &self.contract_info {
             ContractInfo::SingleContractInfo(single) => single.total_collateral,
@@ -286,6 +419,11 @@ impl_dlc_writeable!(OfferDlc, {
         (fee_rate_per_vb, writeable),
         (contract_maturity_bound, writeable),
         (contract_timeout, writeable)
+    }, tlv_stream extra_tlvs {
+        (premium, PREMIUM_TLV_TYPE),
+        (offer_fee_share, OFFER_FEE_SHARE_TLV_TYPE),
+        (nb_confirmations, NB_CONFIRMATIONS_TLV_TYPE),
+        (funding_input_proofs, FUNDING_INPUT_PROOFS_TLV_TYPE)
 });
 
 /// Contains information about a party wishing to accept a DLC offer. The contained
@@ -299,14 +437,7 @@ impl_dlc_writeable!(OfferDlc, {
     serde(rename_all = "camelCase")
 )]
 pub struct AcceptDlc {
-    #[cfg_attr(
-        feature = "serde",
-        serde(
-            serialize_with = "crate::serde_utils::serialize_hex",
-            deserialize_with = "crate::serde_utils::deserialize_hex_array"
-        )
-    )]
-    pub temporary_contract_id: [u8; 32],
+    pub temporary_contract_id: TemporaryContractId,
     pub accept_collateral: u64,
     pub funding_pubkey: PublicKey,
     pub payout_spk: Script,
@@ -317,6 +448,14 @@ pub struct AcceptDlc {
     pub cet_adaptor_signatures: CetAdaptorSignatures,
     pub refund_signature: Signature,
     pub negotiation_fields: Option<NegotiationFields>,
+    /// The proofs that the accepting party controls the outputs spent by its
+    /// funding inputs. Serialized as a TLV record when present.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub funding_input_proofs: Option<FundingInputProofs>,
+    /// Records of odd types unknown to this implementation found at the end
+    /// of the message.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub extra_tlvs: Vec<TlvRecord>,
 }
 
 impl_dlc_writeable!(AcceptDlc, {
@@ -331,6 +470,8 @@ impl_dlc_writeable!(AcceptDlc, {
     (cet_adaptor_signatures, writeable),
     (refund_signature, writeable),
     (negotiation_fields, option)
+}, tlv_stream extra_tlvs {
+    (funding_input_proofs, FUNDING_INPUT_PROOFS_TLV_TYPE)
 });
 
 impl Type for AcceptDlc {
@@ -348,17 +489,14 @@ impl Type for AcceptDlc {
     serde(rename_all = "camelCase")
 )]
 pub struct SignDlc {
-    #[cfg_attr(
-        feature = "serde",
-        serde(
-            serialize_with = "crate::serde_utils::serialize_hex",
-            deserialize_with = "crate::serde_utils::deserialize_hex_array"
-        )
-    )]
-    pub contract_id: [u8; 32],
+    pub contract_id: ContractId,
     pub cet_adaptor_signatures: CetAdaptorSignatures,
     pub refund_signature: Signature,
     pub funding_signatures: FundingSignatures,
+    /// Records of odd types unknown to this implementation found at the end
+    /// of the message.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub extra_tlvs: Vec<TlvRecord>,
 }
 
 impl_dlc_writeable!(SignDlc, {
@@ -366,7 +504,7 @@ impl_dlc_writeable!(SignDlc, {
     (cet_adaptor_signatures, writeable),
     (refund_signature, writeable),
     (funding_signatures, writeable)
-});
+}, tlv_stream extra_tlvs);
 
 impl Type for SignDlc {
     fn type_id(&self) -> u16 {
@@ -374,12 +512,100 @@ impl Type for SignDlc {
     }
 }
 
+/// Contains information about a party wishing to renew an existing DLC by
+/// spending its fund output directly into the fund transaction of a new
+/// contract. The settlement of the existing contract is given by the payout of
+/// the counter party (the remaining value of the fund output belonging to the
+/// offering party), which funds its part of the new contract.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct RenewOffer {
+    pub contract_id: ContractId,
+    pub counter_payout: u64,
+    pub offer_dlc: OfferDlc,
+}
+
+impl_dlc_writeable!(RenewOffer, {
+    (contract_id, writeable),
+    (counter_payout, writeable),
+    (offer_dlc, writeable)
+});
+
+impl Type for RenewOffer {
+    fn type_id(&self) -> u16 {
+        RENEW_OFFER_TYPE
+    }
+}
+
+/// The progress of the setup of a contract as seen by a party, the setup
+/// being completed once the sign message was sent or received.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub enum ReestablishState {
+    /// The contract is not known.
+    Unknown,
+    /// The offer message was sent or received.
+    Offered,
+    /// The accept message was sent or received.
+    Accepted,
+    /// The sign message was sent or received.
+    Signed,
+}
+
+impl_dlc_writeable_enum!(
+    ReestablishState,;;
+    (0, Unknown),
+    (1, Offered),
+    (2, Accepted),
+    (3, Signed)
+);
+
+/// Sent by a party after reconnecting to its counter party for each contract
+/// whose setup might not have completed, and in response to such a message
+/// when the state of the contract is behind the one of the sender. Mirroring
+/// the `channel_reestablish` message of the Lightning Network, it lets the
+/// parties determine which message was lost during the disconnection and
+/// needs to be sent again.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct ReestablishDlc {
+    pub temporary_contract_id: TemporaryContractId,
+    pub contract_id: Option<ContractId>,
+    pub state: ReestablishState,
+}
+
+impl_dlc_writeable!(ReestablishDlc, {
+    (temporary_contract_id, writeable),
+    (contract_id, option),
+    (state, writeable)
+});
+
+impl Type for ReestablishDlc {
+    fn type_id(&self) -> u16 {
+        REESTABLISH_TYPE
+    }
+}
+
 #[allow(missing_docs)]
 #[derive(Debug)]
 pub enum Message {
     Offer(OfferDlc),
     Accept(AcceptDlc),
     Sign(SignDlc),
+    RenewOffer(RenewOffer),
+    Reestablish(ReestablishDlc),
 }
 
 impl Type for Message {
@@ -388,6 +614,8 @@ impl Type for Message {
             Message::Offer(o) => o.type_id(),
             Message::Accept(a) => a.type_id(),
             Message::Sign(s) => s.type_id(),
+            Message::RenewOffer(r) => r.type_id(),
+            Message::Reestablish(r) => r.type_id(),
         }
     }
 }
@@ -398,23 +626,45 @@ impl Writeable for Message {
             Message::Offer(o) => o.write(writer),
             Message::Accept(a) => a.write(writer),
             Message::Sign(s) => s.write(writer),
+            Message::RenewOffer(r) => r.write(writer),
+            Message::Reestablish(r) => r.write(writer),
         }
     }
 }
 
-/// Compute the ID of a DLC based on the fund transaction ID and temporary contract ID.
+/// Decodes a message from its wire encoding prefixed by its big endian type,
+/// as exchanged between peers.
+pub fn read_message<R: ::std::io::Read>(reader: &mut R) -> Result<Message, DecodeError> {
+    let msg_type: u16 = Readable::read(reader)?;
+    let msg = match msg_type {
+        OFFER_TYPE => Message::Offer(Readable::read(reader)?),
+        ACCEPT_TYPE => Message::Accept(Readable::read(reader)?),
+        SIGN_TYPE => Message::Sign(Readable::read(reader)?),
+        RENEW_OFFER_TYPE => Message::RenewOffer(Readable::read(reader)?),
+        REESTABLISH_TYPE => Message::Reestablish(Readable::read(reader)?),
+        _ => return Err(DecodeError::InvalidValue),
+    };
+    Ok(msg)
+}
+
+/// Compute the ID of a DLC based on the fund transaction ID, the index of the
+/// fund output and the temporary contract ID. The bytes of the fund
+/// transaction ID, in their display (reversed) order, are XORed with the
+/// temporary contract ID, and the big endian fund output index with the last
+/// two bytes of the result. This is the ID referenced by the [`SignDlc`] message and
+/// under which contracts are identified once accepted.
 pub fn compute_contract_id(
     fund_tx_id: Txid,
-    fund_ouput_index: u16,
-    temporary_contract_id: [u8; 32],
-) -> [u8; 32] {
+    fund_output_index: u16,
+    temporary_contract_id: TemporaryContractId,
+) -> ContractId {
     let mut res = [0; 32];
     for i in 0..32 {
-        res[i] = fund_tx_id[31 - i] ^ temporary_contract_id[i];
+        res[i] = fund_tx_id[31 - i] ^ temporary_contract_id.0[i];
     }
-    res[0] ^= ((fund_ouput_index >> 8) & 0xff) as u8;
-    res[1] ^= (fund_ouput_index & 0xff) as u8;
-    res
+    res[30] ^= ((fund_output_index >> 8) & 0xff) as u8;
+    res[31] ^= (fund_output_index & 0xff) as u8;
+    ContractId(res)
 }
 
 #[cfg(test)]
@@ -442,6 +692,172 @@ mod tests {
         roundtrip_test!(OfferDlc, input);
     }
 
+    #[test]
+    fn offer_msg_with_premium_roundtrip() {
+        let input = include_str!("./test_inputs/offer_msg.json");
+        let mut msg: OfferDlc = serde_json::from_str(&input).unwrap();
+        let without_premium = msg.encode();
+        msg.premium = Some(Premium {
+            amount: 100000,
+            paid_by_offer: false,
+            serial_id: 3,
+        });
+        assert_eq!(without_premium[..], msg.encode()[..without_premium.len()]);
+        test_roundtrip(msg);
+    }
+
+    #[test]
+    fn offer_msg_with_fee_share_without_premium_roundtrip() {
+        let input = include_str!("./test_inputs/offer_msg.json");
+        let mut msg: OfferDlc = serde_json::from_str(&input).unwrap();
+        msg.offer_fee_share = Some(10000);
+        test_roundtrip(msg);
+    }
+
+    #[test]
+    fn offer_msg_with_nb_confirmations_roundtrip() {
+        let input = include_str!("./test_inputs/offer_msg.json");
+        let mut msg: OfferDlc = serde_json::from_str(&input).unwrap();
+        msg.nb_confirmations = Some(3);
+        test_roundtrip(msg);
+    }
+
+    #[test]
+    fn read_message_test() {
+        let input = include_str!("./test_inputs/offer_msg.json");
+        let msg: OfferDlc = serde_json::from_str(&input).unwrap();
+        let mut buf = msg.type_id().to_be_bytes().to_vec();
+        buf.extend(msg.encode());
+        match read_message(&mut std::io::Cursor::new(&buf)).expect("Error reading message") {
+            Message::Offer(o) => assert_eq!(msg, o),
+            _ => panic!("Expected offer message"),
+        }
+
+        buf[..2].copy_from_slice(&0xFFFFu16.to_be_bytes());
+        assert!(matches!(
+            read_message(&mut std::io::Cursor::new(&buf)),
+            Err(DecodeError::InvalidValue)
+        ));
+    }
+
+    fn get_funding_input_proofs() -> FundingInputProofs {
+        FundingInputProofs {
+            funding_input_proofs: vec![FundingInputProof {
+                input_serial_id: 1,
+                witness_elements: vec![
+                    WitnessElement {
+                        witness: vec![1u8; 72],
+                    },
+                    WitnessElement {
+                        witness: vec![2u8; 33],
+                    },
+                ],
+            }],
+        }
+    }
+
+    #[test]
+    fn offer_msg_with_funding_input_proofs_roundtrip() {
+        let input = include_str!("./test_inputs/offer_msg.json");
+        let mut msg: OfferDlc = serde_json::from_str(&input).unwrap();
+        msg.funding_input_proofs = Some(get_funding_input_proofs());
+        test_roundtrip(msg);
+    }
+
+    #[test]
+    fn accept_msg_with_funding_input_proofs_roundtrip() {
+        let input = include_str!("./test_inputs/accept_msg.json");
+        let mut msg: AcceptDlc = serde_json::from_str(&input).unwrap();
+        let without_proofs = msg.encode();
+        msg.funding_input_proofs = Some(get_funding_input_proofs());
+        msg.extra_tlvs = vec![TlvRecord {
+            type_id: 0xFDE9,
+            value: vec![1, 2, 3],
+        }];
+        assert_eq!(without_proofs[..], msg.encode()[..without_proofs.len()]);
+        test_roundtrip(msg);
+    }
+
+    #[test]
+    fn offer_msg_with_extra_tlvs_roundtrip() {
+        let input = include_str!("./test_inputs/offer_msg.json");
+        let mut msg: OfferDlc = serde_json::from_str(&input).unwrap();
+        msg.extra_tlvs = vec![TlvRecord {
+            type_id: 0xFDE9,
+            value: vec![1, 2, 3],
+        }];
+        let temporary_contract_id = msg.get_temporary_contract_id().unwrap();
+        let deser: OfferDlc = Readable::read(&mut std::io::Cursor::new(msg.encode())).unwrap();
+        assert_eq!(msg, deser);
+        assert_eq!(
+            temporary_contract_id,
+            deser.get_temporary_contract_id().unwrap()
+        );
+    }
+
+    #[test]
+    fn offer_msg_optional_fields_are_tlv_records() {
+        let input = include_str!("./test_inputs/offer_msg.json");
+        let mut msg: OfferDlc = serde_json::from_str(&input).unwrap();
+        let without_fields = msg.encode();
+        msg.nb_confirmations = Some(3);
+        msg.extra_tlvs = vec![TlvRecord {
+            type_id: 0xFDE9,
+            value: vec![1, 2, 3],
+        }];
+        let encoded = msg.encode();
+        let records =
+            ser_impls::read_tlv_stream(&mut std::io::Cursor::new(&encoded[without_fields.len()..]))
+                .unwrap();
+        assert_eq!(
+            vec![
+                TlvRecord::new(NB_CONFIRMATIONS_TLV_TYPE, &3u32),
+                msg.extra_tlvs[0].clone()
+            ],
+            records
+        );
+        let deser: OfferDlc = Readable::read(&mut std::io::Cursor::new(&encoded)).unwrap();
+        assert_eq!(msg, deser);
+        assert_eq!(encoded, deser.encode());
+    }
+
+    #[test]
+    fn accept_msg_with_unknown_even_tlv_fails() {
+        let input = include_str!("./test_inputs/accept_msg.json");
+        let mut msg: AcceptDlc = serde_json::from_str(&input).unwrap();
+        msg.extra_tlvs = vec![TlvRecord {
+            type_id: 0xFDE8,
+            value: vec![1, 2, 3],
+        }];
+        let res: Result<AcceptDlc, _> = Readable::read(&mut std::io::Cursor::new(msg.encode()));
+        assert_eq!(Err(DecodeError::UnknownRequiredFeature), res);
+    }
+
+    #[test]
+    fn renew_offer_msg_roundtrip() {
+        let input = include_str!("./test_inputs/offer_msg.json");
+        let offer_dlc: OfferDlc = serde_json::from_str(&input).unwrap();
+        test_roundtrip(RenewOffer {
+            contract_id: ContractId([1u8; 32]),
+            counter_payout: 100000000,
+            offer_dlc,
+        });
+    }
+
+    #[test]
+    fn reestablish_msg_roundtrip() {
+        test_roundtrip(ReestablishDlc {
+            temporary_contract_id: TemporaryContractId([2u8; 32]),
+            contract_id: Some(ContractId([1u8; 32])),
+            state: ReestablishState::Accepted,
+        });
+        test_roundtrip(ReestablishDlc {
+            temporary_contract_id: TemporaryContractId([2u8; 32]),
+            contract_id: None,
+            state: ReestablishState::Unknown,
+        });
+    }
+
     #[test]
     fn accept_msg_roundtrip() {
         let input = include_str!("./test_inputs/accept_msg.json");
@@ -453,4 +869,208 @@ mod tests {
         let input = include_str!("./test_inputs/sign_msg.json");
         roundtrip_test!(SignDlc, input);
     }
+
+    #[test]
+    fn compute_contract_id_test() {
+        use bitcoin::hashes::hex::FromHex;
+        let fund_tx_id =
+            Txid::from_hex("ff00000000000000000000000000000000000000000000000000000000000001")
+                .unwrap();
+        let mut temporary_contract_id = [0u8; 32];
+        temporary_contract_id[0] = 0x10;
+
+        let contract_id = compute_contract_id(
+            fund_tx_id,
+            0x0102,
+            TemporaryContractId(temporary_contract_id),
+        );
+
+        let mut expected = [0u8; 32];
+        expected[0] = 0xef;
+        expected[30] = 0x01;
+        expected[31] = 0x03;
+        assert_eq!(ContractId(expected), contract_id);
+    }
+
+    #[test]
+    fn compute_contract_id_matches_test_vectors() {
+        use bitcoin::consensus::deserialize;
+        use bitcoin::hashes::hex::FromHex;
+        use bitcoin::hashes::{sha256, Hash};
+
+        let input = include_str!("./test_inputs/dlc_test.json");
+        let test_cases: Vec<serde_json::Value> = serde_json::from_str(&input).unwrap();
+
+        for case in test_cases {
+            let offer = Vec::<u8>::from_hex(case["offer"].as_str().unwrap()).unwrap();
+            let sign = Vec::<u8>::from_hex(case["sign"].as_str().unwrap()).unwrap();
+            let fund_tx: Transaction = deserialize(
+                &Vec::<u8>::from_hex(case["unsignedTxs"]["fundingTx"].as_str().unwrap()).unwrap(),
+            )
+            .unwrap();
+            let temporary_contract_id =
+                TemporaryContractId(sha256::Hash::hash(&offer).into_inner());
+            let mut expected = [0u8; 32];
+            expected.copy_from_slice(&sign[2..34]);
+
+            assert_eq!(
+                ContractId(expected),
+                compute_contract_id(fund_tx.txid(), 0, temporary_contract_id)
+            );
+        }
+    }
+
+    #[test]
+    fn oracle_messages_v2_test() {
+        use oracle_msgs::{
+            DigitDecompositionEventDescriptor, EventDescriptor, OracleAnnouncement,
+            OracleAttestation, OracleEvent,
+        };
+        use secp256k1_zkp::rand::{thread_rng, RngCore};
+        use secp256k1_zkp::schnorrsig::{KeyPair, PublicKey as SchnorrPublicKey};
+        use secp256k1_zkp::{Message, Secp256k1};
+
+        let secp = Secp256k1::new();
+        let key_pair = KeyPair::new(&secp, &mut thread_rng());
+        let oracle_public_key = SchnorrPublicKey::from_keypair(&secp, &key_pair);
+        let mut nonce_sk = [0u8; 32];
+        thread_rng().fill_bytes(&mut nonce_sk);
+        let nonce_pair = KeyPair::from_seckey_slice(&secp, &nonce_sk).unwrap();
+        let oracle_event = OracleEvent {
+            oracle_nonces: vec![SchnorrPublicKey::from_keypair(&secp, &nonce_pair)],
+            event_maturity_epoch: 1623133104,
+            event_descriptor: EventDescriptor::DigitDecompositionEvent(
+                DigitDecompositionEventDescriptor {
+                    base: 2,
+                    is_signed: false,
+                    unit: "sats".to_string(),
+                    precision: 0,
+                    nb_digits: 1,
+                },
+            ),
+            event_id: "event".to_string(),
+        };
+        // Oracles following the updated specifications sign the updated
+        // serialization of the event.
+        let event_msg = Message::from_hashed_data::<sha256::Hash>(&oracle_event.encode_v2());
+        let announcement = OracleAnnouncement {
+            announcement_signature: secp.schnorrsig_sign(&event_msg, &key_pair),
+            oracle_public_key,
+            oracle_event,
+        };
+        announcement
+            .validate(&secp)
+            .expect("Announcement to be valid");
+
+        let encoded = announcement.encode_v2();
+        assert_ne!(announcement.encode(), encoded);
+        for encoded in &[encoded, announcement.encode()] {
+            let decoded: OracleAnnouncement =
+                Readable::read(&mut std::io::Cursor::new(encoded)).unwrap();
+            assert_eq!(announcement, decoded);
+        }
+
+        let attestation = OracleAttestation {
+            oracle_public_key,
+            signatures: vec![dlc::secp_utils::schnorrsig_sign_with_nonce(
+                &secp,
+                &dlc::get_outcome_message("1"),
+                &key_pair,
+                &nonce_sk,
+            )],
+            outcomes: vec!["1".to_string()],
+        };
+        let (decoded, event_id) = OracleAttestation::decode(&attestation.encode()).unwrap();
+        assert_eq!(None, event_id);
+        assert_eq!(attestation.encode(), decoded.encode());
+        let (decoded, event_id) =
+            OracleAttestation::decode(&attestation.encode_v2("event")).unwrap();
+        assert_eq!(Some("event".to_string()), event_id);
+        assert_eq!(attestation.encode(), decoded.encode());
+        decoded
+            .validate(&secp, &announcement)
+            .expect("Attestation to be valid");
+    }
+
+    #[test]
+    fn event_id_bounds_test() {
+        use oracle_msgs::{
+            check_event_id, parse_event_id, read_event_id, EventIdError, MAX_EVENT_ID_LENGTH,
+        };
+        use ser_impls::write_string;
+
+        let read = |event_id: &[u8]| {
+            let mut buf = Vec::new();
+            ser_impls::BigSize(event_id.len() as u64)
+                .write(&mut buf)
+                .unwrap();
+            buf.extend_from_slice(event_id);
+            read_event_id(&mut std::io::Cursor::new(buf))
+        };
+
+        let max_len = "a".repeat(MAX_EVENT_ID_LENGTH);
+        assert_eq!(Ok(max_len.clone()), read(max_len.as_bytes()));
+        assert_eq!(Ok(()), check_event_id(&max_len));
+
+        let too_long = "a".repeat(MAX_EVENT_ID_LENGTH + 1);
+        assert_eq!(Err(DecodeError::InvalidValue), read(too_long.as_bytes()));
+        assert_eq!(
+            Err(EventIdError::TooLong(MAX_EVENT_ID_LENGTH + 1)),
+            check_event_id(&too_long)
+        );
+
+        assert_eq!(Err(DecodeError::InvalidValue), read(&[0x66, 0xff, 0x6f]));
+        assert_eq!(
+            Err(EventIdError::InvalidUtf8),
+            parse_event_id(&[0x66, 0xff, 0x6f])
+        );
+
+        let mut truncated = Vec::new();
+        write_string("event", &mut truncated).unwrap();
+        truncated.pop();
+        assert_eq!(
+            Err(DecodeError::ShortRead),
+            read_event_id(&mut std::io::Cursor::new(truncated))
+        );
+    }
+
+    #[test]
+    fn oracle_equivocation_proof_test() {
+        use oracle_msgs::OracleAttestation;
+        use secp256k1_zkp::rand::{thread_rng, RngCore};
+        use secp256k1_zkp::schnorrsig::{KeyPair, PublicKey as SchnorrPublicKey};
+        use secp256k1_zkp::Secp256k1;
+
+        let secp = Secp256k1::new();
+        let key_pair = KeyPair::new(&secp, &mut thread_rng());
+        let oracle_public_key = SchnorrPublicKey::from_keypair(&secp, &key_pair);
+        let mut nonce_sk = [0u8; 32];
+        thread_rng().fill_bytes(&mut nonce_sk);
+        let attest = |outcome: &str| OracleAttestation {
+            oracle_public_key,
+            signatures: vec![dlc::secp_utils::schnorrsig_sign_with_nonce(
+                &secp,
+                &dlc::get_outcome_message(outcome),
+                &key_pair,
+                &nonce_sk,
+            )],
+            outcomes: vec![outcome.to_string()],
+        };
+        let attestation = attest("a");
+
+        assert!(attestation.get_equivocation_proof(&attest("a")).is_none());
+
+        let proof = attestation
+            .get_equivocation_proof(&attest("b"))
+            .expect("Equivocation to be detected");
+        let secret_key = proof.extract_secret_key(&secp).unwrap();
+        assert_eq!(
+            oracle_public_key,
+            SchnorrPublicKey::from_keypair(
+                &secp,
+                &KeyPair::from_seckey_slice(&secp, &secret_key[..]).unwrap()
+            )
+        );
+        test_roundtrip(proof);
+    }
 }
@@ -0,0 +1,374 @@
+//! Messages enabling nodes to advertise the contracts they are willing to
+//! enter into and to request quotes for them, so that marketplaces can be built
+//! on top of the DLC messages. A quote is expected to be followed by an
+//! [`crate::OfferDlc`] message using the quoted contract information, which
+//! can be signed by the quoting node when relayed by a third party.
+
+use crate::contract_msgs::ContractInfo;
+use crate::oracle_msgs::{read_event_id, OracleAnnouncement};
+use crate::ser_impls::{
+    read_as_tlv, read_length_prefixed, read_schnorr_pubkey, write_as_tlv, write_length_prefixed,
+    write_schnorr_pubkey, write_string,
+};
+use crate::OfferDlc;
+use lightning::ln::msgs::DecodeError;
+use lightning::ln::wire::Type;
+use lightning::util::ser::{Readable, Writeable, Writer};
+use secp256k1_zkp::bitcoin_hashes::sha256;
+use secp256k1_zkp::schnorrsig::PublicKey as SchnorrPublicKey;
+use secp256k1_zkp::{
+    Error as SecpError, Message, PublicKey, Secp256k1, SecretKey, Signature, Signing, Verification,
+};
+
+pub const CONTRACT_ADVERTISEMENT_TYPE: u16 = 42788;
+
+pub const QUOTE_REQUEST_TYPE: u16 = 42790;
+
+pub const QUOTE_TYPE: u16 = 42792;
+
+pub const SIGNED_OFFER_TYPE: u16 = 42794;
+
+/// Prefix of the data signed by [`SignedOffer`] messages, preventing the
+/// signature from being valid for other messages hashing to the same value.
+const OFFER_SIGNATURE_TAG: &[u8] = b"dlc/offer/signature";
+
+/// Advertises that a node is willing to enter into contracts on the outcome of
+/// the announced oracle event, for a collateral of the counter party within
+/// the given bounds.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct ContractAdvertisement {
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    pub advertisement_id: [u8; 32],
+    pub node_id: PublicKey,
+    pub oracle_announcement: OracleAnnouncement,
+    pub min_collateral: u64,
+    pub max_collateral: u64,
+    pub fee_rate_per_vb: u64,
+    /// The time after which the advertisement is not valid anymore.
+    pub expiry: u32,
+}
+
+impl_dlc_writeable!(ContractAdvertisement, {
+    (advertisement_id, writeable),
+    (node_id, writeable),
+    (oracle_announcement, {cb_writeable, write_as_tlv, read_as_tlv}),
+    (min_collateral, writeable),
+    (max_collateral, writeable),
+    (fee_rate_per_vb, writeable),
+    (expiry, writeable)
+});
+
+impl Type for ContractAdvertisement {
+    fn type_id(&self) -> u16 {
+        CONTRACT_ADVERTISEMENT_TYPE
+    }
+}
+
+/// Requests a quote for a contract on the outcome of the given oracle event,
+/// in which the requesting node puts up the given collateral.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct QuoteRequest {
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    pub request_id: [u8; 32],
+    pub node_id: PublicKey,
+    pub oracle_public_key: SchnorrPublicKey,
+    pub event_id: String,
+    pub collateral: u64,
+    pub max_fee_rate_per_vb: u64,
+}
+
+impl_dlc_writeable!(QuoteRequest, {
+    (request_id, writeable),
+    (node_id, writeable),
+    (oracle_public_key, {cb_writeable, write_schnorr_pubkey, read_schnorr_pubkey}),
+    (event_id, {cb_writeable, write_string, read_event_id}),
+    (collateral, writeable),
+    (max_fee_rate_per_vb, writeable)
+});
+
+impl Type for QuoteRequest {
+    fn type_id(&self) -> u16 {
+        QUOTE_REQUEST_TYPE
+    }
+}
+
+/// Answers a quote request with the terms of the contract that the quoting
+/// node is willing to offer.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct Quote {
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    pub request_id: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    pub advertisement_id: [u8; 32],
+    pub contract_info: ContractInfo,
+    pub offer_collateral: u64,
+    pub fee_rate_per_vb: u64,
+    /// The time after which the quote is not valid anymore.
+    pub expiry: u32,
+}
+
+impl_dlc_writeable!(Quote, {
+    (request_id, writeable),
+    (advertisement_id, writeable),
+    (contract_info, writeable),
+    (offer_collateral, writeable),
+    (fee_rate_per_vb, writeable),
+    (expiry, writeable)
+});
+
+impl Type for Quote {
+    fn type_id(&self) -> u16 {
+        QUOTE_TYPE
+    }
+}
+
+/// An offer signed with the node key of the offering party, enabling a taker
+/// receiving it through a marketplace to check that it was made by the node
+/// that advertised the contract before engaging with it.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct SignedOffer {
+    pub node_id: PublicKey,
+    /// The signed offer, serialized with a length prefix as offers are read
+    /// until the end of their input.
+    pub offer: OfferDlc,
+    /// Signature of the tagged hash of the offer by the node key.
+    pub signature: Signature,
+}
+
+impl_dlc_writeable!(SignedOffer, {
+    (node_id, writeable),
+    (offer, {cb_writeable, write_length_prefixed, read_length_prefixed}),
+    (signature, writeable)
+});
+
+impl Type for SignedOffer {
+    fn type_id(&self) -> u16 {
+        SIGNED_OFFER_TYPE
+    }
+}
+
+impl SignedOffer {
+    /// Signs the given offer with the given node secret key.
+    pub fn new<C: Signing>(
+        secp: &Secp256k1<C>,
+        offer: OfferDlc,
+        node_secret_key: &SecretKey,
+    ) -> Result<SignedOffer, ::std::io::Error> {
+        let msg = get_offer_signature_message(&offer)?;
+        Ok(SignedOffer {
+            node_id: PublicKey::from_secret_key(secp, node_secret_key),
+            signature: secp.sign(&msg, node_secret_key),
+            offer,
+        })
+    }
+
+    /// Checks that the offer was signed by the given node, usually the one of
+    /// the advertisement that the offer answers.
+    pub fn verify<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        node_id: &PublicKey,
+    ) -> Result<(), SecpError> {
+        if self.node_id != *node_id {
+            return Err(SecpError::IncorrectSignature);
+        }
+        let msg =
+            get_offer_signature_message(&self.offer).map_err(|_| SecpError::InvalidMessage)?;
+        secp.verify(&msg, &self.signature, node_id)
+    }
+}
+
+fn get_offer_signature_message(offer: &OfferDlc) -> Result<Message, ::std::io::Error> {
+    let mut data = OFFER_SIGNATURE_TAG.to_vec();
+    data.extend_from_slice(&offer.get_hash()?);
+    Ok(Message::from_hashed_data::<sha256::Hash>(&data))
+}
+
+impl ContractAdvertisement {
+    /// Returns whether the advertisement can answer the given request at the
+    /// given time.
+    pub fn matches(&self, request: &QuoteRequest, time: u32) -> bool {
+        self.expiry > time
+            && self.node_id != request.node_id
+            && self.oracle_announcement.oracle_public_key == request.oracle_public_key
+            && self.oracle_announcement.get_event_id() == request.event_id
+            && self.min_collateral <= request.collateral
+            && request.collateral <= self.max_collateral
+            && self.fee_rate_per_vb <= request.max_fee_rate_per_vb
+    }
+}
+
+/// Returns the advertisements matching the given request at the given time,
+/// ordered by increasing fee rate.
+pub fn get_matching_advertisements<'a>(
+    request: &QuoteRequest,
+    advertisements: &'a [ContractAdvertisement],
+    time: u32,
+) -> Vec<&'a ContractAdvertisement> {
+    let mut matching: Vec<_> = advertisements
+        .iter()
+        .filter(|x| x.matches(request, time))
+        .collect();
+    matching.sort_by_key(|x| x.fee_rate_per_vb);
+    matching
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oracle_msgs::OracleInfo;
+    use crate::OfferDlc;
+    use secp256k1_zkp::rand::thread_rng;
+    use secp256k1_zkp::{Secp256k1, SecretKey};
+
+    fn get_advertisement(offer: &OfferDlc, fee_rate_per_vb: u64) -> ContractAdvertisement {
+        let oracle_announcement = match &offer.contract_info {
+            ContractInfo::SingleContractInfo(s) => match &s.contract_info.oracle_info {
+                OracleInfo::Single(o) => o.oracle_announcement.clone(),
+                OracleInfo::Multi(m) => m.oracle_announcements[0].clone(),
+            },
+            ContractInfo::DisjointContractInfo(_) => unreachable!(),
+        };
+        ContractAdvertisement {
+            advertisement_id: [fee_rate_per_vb as u8; 32],
+            node_id: offer.funding_pubkey,
+            oracle_announcement,
+            min_collateral: 1000,
+            max_collateral: 100000,
+            fee_rate_per_vb,
+            expiry: 100,
+        }
+    }
+
+    fn get_request(advertisement: &ContractAdvertisement) -> QuoteRequest {
+        let secp = Secp256k1::new();
+        QuoteRequest {
+            request_id: [1u8; 32],
+            node_id: PublicKey::from_secret_key(&secp, &SecretKey::new(&mut thread_rng())),
+            oracle_public_key: advertisement.oracle_announcement.oracle_public_key,
+            event_id: advertisement.oracle_announcement.get_event_id().to_string(),
+            collateral: 5000,
+            max_fee_rate_per_vb: 10,
+        }
+    }
+
+    fn test_roundtrip<T: Writeable + Readable + PartialEq + std::fmt::Debug>(msg: T) {
+        let mut buf = Vec::new();
+        msg.write(&mut buf).expect("Error writing message");
+        let mut cursor = std::io::Cursor::new(&buf);
+        let deser = Readable::read(&mut cursor).expect("Error reading message");
+        assert_eq!(msg, deser);
+    }
+
+    #[test]
+    fn marketplace_msgs_roundtrip() {
+        let input = include_str!("./test_inputs/offer_msg.json");
+        let offer: OfferDlc = serde_json::from_str(&input).unwrap();
+        let advertisement = get_advertisement(&offer, 2);
+        let request = get_request(&advertisement);
+        let quote = Quote {
+            request_id: request.request_id,
+            advertisement_id: advertisement.advertisement_id,
+            contract_info: offer.contract_info.clone(),
+            offer_collateral: offer.offer_collateral,
+            fee_rate_per_vb: advertisement.fee_rate_per_vb,
+            expiry: 100,
+        };
+
+        test_roundtrip(advertisement);
+        test_roundtrip(request);
+        test_roundtrip(quote);
+        test_roundtrip(
+            SignedOffer::new(&Secp256k1::new(), offer, &SecretKey::new(&mut thread_rng())).unwrap(),
+        );
+    }
+
+    #[test]
+    fn signed_offer_verify_test() {
+        let input = include_str!("./test_inputs/offer_msg.json");
+        let offer: OfferDlc = serde_json::from_str(&input).unwrap();
+        let secp = Secp256k1::new();
+        let node_secret_key = SecretKey::new(&mut thread_rng());
+        let node_id = PublicKey::from_secret_key(&secp, &node_secret_key);
+        let other_node_id = PublicKey::from_secret_key(&secp, &SecretKey::new(&mut thread_rng()));
+
+        let signed_offer = SignedOffer::new(&secp, offer, &node_secret_key).unwrap();
+        signed_offer
+            .verify(&secp, &node_id)
+            .expect("to have a valid signature");
+        signed_offer
+            .verify(&secp, &other_node_id)
+            .expect_err("not to be signed by the other node");
+
+        let mut altered = signed_offer;
+        altered.offer.offer_collateral += 1;
+        altered
+            .verify(&secp, &node_id)
+            .expect_err("not to be valid for an altered offer");
+    }
+
+    #[test]
+    fn get_matching_advertisements_test() {
+        let input = include_str!("./test_inputs/offer_msg.json");
+        let offer: OfferDlc = serde_json::from_str(&input).unwrap();
+        let cheap = get_advertisement(&offer, 2);
+        let expensive = get_advertisement(&offer, 8);
+        let too_expensive = get_advertisement(&offer, 20);
+        let mut expired = get_advertisement(&offer, 1);
+        expired.expiry = 10;
+        let advertisements = vec![expensive, too_expensive, expired, cheap];
+        let request = get_request(&advertisements[0]);
+
+        let matching = get_matching_advertisements(&request, &advertisements, 50);
+
+        assert_eq!(vec![&advertisements[3], &advertisements[0]], matching);
+
+        let mut large_request = request;
+        large_request.collateral = 200000;
+        assert!(get_matching_advertisements(&large_request, &advertisements, 50).is_empty());
+    }
+}
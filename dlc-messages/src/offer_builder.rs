@@ -0,0 +1,433 @@
+//! Builder for [`OfferDlc`] messages, for services generating offers without
+//! going through the manager. Serial ids that are not provided are generated
+//! randomly, the total collateral is computed from the collateral of both
+//! parties, and the resulting message is checked for structural consistency.
+
+use crate::contract_msgs::{
+    ContractDescriptor, ContractInfo, ContractInfoInner, DisjointContractInfo, SingleContractInfo,
+};
+use crate::oracle_msgs::{OracleAnnouncement, OracleInfo};
+use crate::{FundingInput, OfferDlc, Premium};
+use bitcoin::Script;
+use secp256k1_zkp::rand::{thread_rng, RngCore};
+use secp256k1_zkp::PublicKey;
+use std::collections::HashSet;
+use std::fmt;
+
+/// The protocol version set by default on built offers.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// The chain hash of the bitcoin main chain, set by default on built offers.
+pub const BITCOIN_CHAINHASH: [u8; 32] = [
+    0x06, 0x22, 0x6e, 0x46, 0x11, 0x1a, 0x0b, 0x59, 0xca, 0xaf, 0x12, 0x60, 0x43, 0xeb, 0x5b, 0xbf,
+    0x28, 0xc3, 0x4f, 0x3a, 0x5e, 0x33, 0x2a, 0x1f, 0xc7, 0xb2, 0xb7, 0x3c, 0xf1, 0x88, 0x91, 0x0f,
+];
+
+/// An error returned when building an offer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// A required field was not set.
+    MissingField(&'static str),
+    /// The same serial id is used for several inputs or outputs of the fund
+    /// transaction.
+    DuplicateSerialId(u64),
+    /// The given parameters are inconsistent.
+    InvalidParameters(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::MissingField(field) => write!(f, "Missing field {}", field),
+            Error::DuplicateSerialId(id) => write!(f, "Duplicate serial id {}", id),
+            Error::InvalidParameters(s) => write!(f, "Invalid parameters: {}", s),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Builder for [`OfferDlc`] messages.
+#[derive(Clone, Debug, Default)]
+pub struct OfferDlcBuilder {
+    chain_hash: Option<[u8; 32]>,
+    contract_infos: Vec<ContractInfoInner>,
+    funding_pubkey: Option<PublicKey>,
+    payout_spk: Option<Script>,
+    payout_serial_id: Option<u64>,
+    offer_collateral: Option<u64>,
+    accept_collateral: Option<u64>,
+    funding_inputs: Vec<FundingInput>,
+    change_spk: Option<Script>,
+    change_serial_id: Option<u64>,
+    fund_output_serial_id: Option<u64>,
+    fee_rate_per_vb: Option<u64>,
+    contract_maturity_bound: Option<u32>,
+    contract_timeout: Option<u32>,
+    premium: Option<(u64, bool, Option<u64>)>,
+    offer_fee_share: Option<u16>,
+    nb_confirmations: Option<u32>,
+}
+
+impl OfferDlcBuilder {
+    /// Creates a new builder without any field set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the chain hash, defaulting to the one of the bitcoin main chain.
+    pub fn chain_hash(mut self, chain_hash: [u8; 32]) -> Self {
+        self.chain_hash = Some(chain_hash);
+        self
+    }
+
+    /// Adds a contract information. Offers with several contract informations
+    /// use a disjoint contract information.
+    pub fn contract_info(mut self, contract_info: ContractInfoInner) -> Self {
+        self.contract_infos.push(contract_info);
+        self
+    }
+
+    /// Sets the funding public key of the offer party.
+    pub fn funding_pubkey(mut self, funding_pubkey: PublicKey) -> Self {
+        self.funding_pubkey = Some(funding_pubkey);
+        self
+    }
+
+    /// Sets the payout script pubkey of the offer party.
+    pub fn payout_spk(mut self, payout_spk: Script) -> Self {
+        self.payout_spk = Some(payout_spk);
+        self
+    }
+
+    /// Sets the serial id of the payout output, generated if not set.
+    pub fn payout_serial_id(mut self, payout_serial_id: u64) -> Self {
+        self.payout_serial_id = Some(payout_serial_id);
+        self
+    }
+
+    /// Sets the collateral of the offer party.
+    pub fn offer_collateral(mut self, offer_collateral: u64) -> Self {
+        self.offer_collateral = Some(offer_collateral);
+        self
+    }
+
+    /// Sets the collateral expected from the accept party, used to compute
+    /// the total collateral of the contract.
+    pub fn accept_collateral(mut self, accept_collateral: u64) -> Self {
+        self.accept_collateral = Some(accept_collateral);
+        self
+    }
+
+    /// Adds a funding input of the offer party.
+    pub fn funding_input(mut self, funding_input: FundingInput) -> Self {
+        self.funding_inputs.push(funding_input);
+        self
+    }
+
+    /// Sets the change script pubkey of the offer party.
+    pub fn change_spk(mut self, change_spk: Script) -> Self {
+        self.change_spk = Some(change_spk);
+        self
+    }
+
+    /// Sets the serial id of the change output, generated if not set.
+    pub fn change_serial_id(mut self, change_serial_id: u64) -> Self {
+        self.change_serial_id = Some(change_serial_id);
+        self
+    }
+
+    /// Sets the serial id of the fund output, generated if not set.
+    pub fn fund_output_serial_id(mut self, fund_output_serial_id: u64) -> Self {
+        self.fund_output_serial_id = Some(fund_output_serial_id);
+        self
+    }
+
+    /// Sets the fee rate of the transactions in satoshi per virtual byte.
+    pub fn fee_rate_per_vb(mut self, fee_rate_per_vb: u64) -> Self {
+        self.fee_rate_per_vb = Some(fee_rate_per_vb);
+        self
+    }
+
+    /// Sets the time after which the contract can be closed.
+    pub fn contract_maturity_bound(mut self, contract_maturity_bound: u32) -> Self {
+        self.contract_maturity_bound = Some(contract_maturity_bound);
+        self
+    }
+
+    /// Sets the time after which the contract can be refunded.
+    pub fn contract_timeout(mut self, contract_timeout: u32) -> Self {
+        self.contract_timeout = Some(contract_timeout);
+        self
+    }
+
+    /// Sets a premium paid within the fund transaction. The serial id of its
+    /// output is generated if not provided.
+    pub fn premium(mut self, amount: u64, paid_by_offer: bool, serial_id: Option<u64>) -> Self {
+        self.premium = Some((amount, paid_by_offer, serial_id));
+        self
+    }
+
+    /// Sets the share of the fees paid by the offer party in basis points.
+    pub fn offer_fee_share(mut self, offer_fee_share: u16) -> Self {
+        self.offer_fee_share = Some(offer_fee_share);
+        self
+    }
+
+    /// Sets the number of confirmations required for the fund transaction.
+    pub fn nb_confirmations(mut self, nb_confirmations: u32) -> Self {
+        self.nb_confirmations = Some(nb_confirmations);
+        self
+    }
+
+    /// Builds the offer, generating the missing serial ids and checking the
+    /// consistency of the message.
+    pub fn build(self) -> Result<OfferDlc, Error> {
+        let offer_collateral = self
+            .offer_collateral
+            .ok_or(Error::MissingField("offer_collateral"))?;
+        let accept_collateral = self
+            .accept_collateral
+            .ok_or(Error::MissingField("accept_collateral"))?;
+        let total_collateral = offer_collateral
+            .checked_add(accept_collateral)
+            .ok_or_else(|| invalid("total collateral overflows"))?;
+        let contract_maturity_bound = self
+            .contract_maturity_bound
+            .ok_or(Error::MissingField("contract_maturity_bound"))?;
+        let contract_timeout = self
+            .contract_timeout
+            .ok_or(Error::MissingField("contract_timeout"))?;
+
+        if contract_timeout <= contract_maturity_bound {
+            return Err(invalid("contract timeout must be after the maturity bound"));
+        }
+
+        if self.contract_infos.is_empty() {
+            return Err(Error::MissingField("contract_info"));
+        }
+
+        for contract_info in &self.contract_infos {
+            check_contract_info(contract_info, total_collateral, contract_maturity_bound)?;
+        }
+
+        if let Some(offer_fee_share) = self.offer_fee_share {
+            dlc::FeeSplit::from_offer_share(offer_fee_share)
+                .map_err(|_| invalid("offer fee share must be at most 10000 basis points"))?;
+        }
+
+        let mut serial_ids = HashSet::new();
+        for input in &self.funding_inputs {
+            check_serial_id(&mut serial_ids, input.input_serial_id)?;
+        }
+        let mut provided = vec![
+            self.payout_serial_id,
+            self.change_serial_id,
+            self.fund_output_serial_id,
+        ];
+        if let Some((_, _, serial_id)) = &self.premium {
+            provided.push(*serial_id);
+        }
+        for serial_id in provided.iter().flatten() {
+            check_serial_id(&mut serial_ids, *serial_id)?;
+        }
+
+        let payout_serial_id = get_serial_id(&mut serial_ids, self.payout_serial_id);
+        let change_serial_id = get_serial_id(&mut serial_ids, self.change_serial_id);
+        let fund_output_serial_id = get_serial_id(&mut serial_ids, self.fund_output_serial_id);
+        let premium = self
+            .premium
+            .map(|(amount, paid_by_offer, serial_id)| Premium {
+                amount,
+                paid_by_offer,
+                serial_id: get_serial_id(&mut serial_ids, serial_id),
+            });
+
+        let contract_info = if self.contract_infos.len() == 1 {
+            ContractInfo::SingleContractInfo(SingleContractInfo {
+                total_collateral,
+                contract_info: self.contract_infos.into_iter().next().unwrap(),
+            })
+        } else {
+            ContractInfo::DisjointContractInfo(DisjointContractInfo {
+                total_collateral,
+                contract_infos: self.contract_infos,
+            })
+        };
+
+        Ok(OfferDlc {
+            protocol_version: PROTOCOL_VERSION,
+            contract_flags: 0,
+            chain_hash: self.chain_hash.unwrap_or(BITCOIN_CHAINHASH),
+            contract_info,
+            funding_pubkey: self
+                .funding_pubkey
+                .ok_or(Error::MissingField("funding_pubkey"))?,
+            payout_spk: self.payout_spk.ok_or(Error::MissingField("payout_spk"))?,
+            payout_serial_id,
+            offer_collateral,
+            funding_inputs: self.funding_inputs,
+            change_spk: self.change_spk.ok_or(Error::MissingField("change_spk"))?,
+            change_serial_id,
+            fund_output_serial_id,
+            fee_rate_per_vb: self
+                .fee_rate_per_vb
+                .ok_or(Error::MissingField("fee_rate_per_vb"))?,
+            contract_maturity_bound,
+            contract_timeout,
+            premium,
+            offer_fee_share: self.offer_fee_share,
+            nb_confirmations: self.nb_confirmations,
+            funding_input_proofs: None,
+            extra_tlvs: Vec::new(),
+        })
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::InvalidParameters(message.to_string())
+}
+
+fn check_serial_id(serial_ids: &mut HashSet<u64>, serial_id: u64) -> Result<(), Error> {
+    if !serial_ids.insert(serial_id) {
+        return Err(Error::DuplicateSerialId(serial_id));
+    }
+    Ok(())
+}
+
+/// Returns the given serial id, or a new one not already in use.
+fn get_serial_id(serial_ids: &mut HashSet<u64>, serial_id: Option<u64>) -> u64 {
+    if let Some(serial_id) = serial_id {
+        return serial_id;
+    }
+    loop {
+        let serial_id = thread_rng().next_u64();
+        if serial_ids.insert(serial_id) {
+            return serial_id;
+        }
+    }
+}
+
+fn check_contract_info(
+    contract_info: &ContractInfoInner,
+    total_collateral: u64,
+    contract_maturity_bound: u32,
+) -> Result<(), Error> {
+    let announcements: Vec<&OracleAnnouncement> = match &contract_info.oracle_info {
+        OracleInfo::Single(single) => vec![&single.oracle_announcement],
+        OracleInfo::Multi(multi) => {
+            if multi.threshold == 0 || multi.threshold as usize > multi.oracle_announcements.len() {
+                return Err(invalid(
+                    "oracle threshold must be between one and the number of oracles",
+                ));
+            }
+            multi.oracle_announcements.iter().collect()
+        }
+    };
+
+    if announcements
+        .iter()
+        .any(|x| x.oracle_event.event_maturity_epoch > contract_maturity_bound)
+    {
+        return Err(invalid(
+            "contract maturity bound must not be before the oracle events maturity",
+        ));
+    }
+
+    if let ContractDescriptor::EnumeratedContractDescriptor(e) = &contract_info.contract_descriptor
+    {
+        if e.payouts.iter().any(|x| x.local_payout > total_collateral) {
+            return Err(invalid("payouts must not exceed the total collateral"));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_offer() -> OfferDlc {
+        let input = include_str!("./test_inputs/offer_msg.json");
+        serde_json::from_str(input).unwrap()
+    }
+
+    fn get_builder(offer: &OfferDlc) -> OfferDlcBuilder {
+        let contract_info = match &offer.contract_info {
+            ContractInfo::SingleContractInfo(single) => single.contract_info.clone(),
+            ContractInfo::DisjointContractInfo(_) => unreachable!(),
+        };
+        let mut builder = OfferDlcBuilder::new()
+            .contract_info(contract_info)
+            .funding_pubkey(offer.funding_pubkey)
+            .payout_spk(offer.payout_spk.clone())
+            .offer_collateral(offer.offer_collateral)
+            .accept_collateral(offer.get_total_collateral() - offer.offer_collateral)
+            .change_spk(offer.change_spk.clone())
+            .fee_rate_per_vb(offer.fee_rate_per_vb)
+            .contract_maturity_bound(offer.contract_maturity_bound)
+            .contract_timeout(offer.contract_timeout);
+        for input in &offer.funding_inputs {
+            builder = builder.funding_input(input.clone());
+        }
+        builder
+    }
+
+    #[test]
+    fn build_offer_with_serial_ids_is_same_test() {
+        let offer = get_offer();
+        let built = get_builder(&offer)
+            .payout_serial_id(offer.payout_serial_id)
+            .change_serial_id(offer.change_serial_id)
+            .fund_output_serial_id(offer.fund_output_serial_id)
+            .build()
+            .expect("to be able to build the offer");
+
+        assert_eq!(offer, built);
+    }
+
+    #[test]
+    fn build_offer_generates_unique_serial_ids_test() {
+        let offer = get_offer();
+        let built = get_builder(&offer)
+            .premium(1000, true, None)
+            .build()
+            .expect("to be able to build the offer");
+
+        let mut serial_ids: Vec<u64> = built
+            .funding_inputs
+            .iter()
+            .map(|x| x.input_serial_id)
+            .collect();
+        serial_ids.push(built.payout_serial_id);
+        serial_ids.push(built.change_serial_id);
+        serial_ids.push(built.fund_output_serial_id);
+        serial_ids.push(built.premium.unwrap().serial_id);
+        let nb_serial_ids = serial_ids.len();
+        serial_ids.sort_unstable();
+        serial_ids.dedup();
+        assert_eq!(nb_serial_ids, serial_ids.len());
+    }
+
+    #[test]
+    fn build_offer_with_duplicate_serial_ids_fails_test() {
+        let offer = get_offer();
+        let res = get_builder(&offer)
+            .payout_serial_id(1)
+            .change_serial_id(1)
+            .build();
+
+        assert_eq!(Err(Error::DuplicateSerialId(1)), res);
+    }
+
+    #[test]
+    fn build_offer_with_timeout_before_maturity_fails_test() {
+        let offer = get_offer();
+        let res = get_builder(&offer)
+            .contract_timeout(offer.contract_maturity_bound)
+            .build();
+
+        assert!(matches!(res, Err(Error::InvalidParameters(_))));
+    }
+}
@@ -1,17 +1,87 @@
 use crate::ser_impls::{
-    read_as_tlv, read_i32, read_schnorr_pubkey, read_schnorrsig, read_strings_u16, write_as_tlv,
-    write_i32, write_schnorr_pubkey, write_schnorrsig, write_strings_u16,
+    read_as_tlv, read_as_tlv_or_subtype, read_i32, read_schnorr_pubkey, read_schnorrsig,
+    read_strings_u16, read_tlv_or_subtype_type, write_as_subtype, write_as_tlv, write_i32,
+    write_schnorr_pubkey, write_schnorrsig, write_string, write_strings_u16, BigSize,
 };
 use dlc::OracleInfo as DlcOracleInfo;
 use lightning::ln::msgs::DecodeError;
 use lightning::ln::wire::Type;
 use lightning::util::ser::{Readable, Writeable, Writer};
+use secp256k1_zkp::bitcoin_hashes::sha256;
 use secp256k1_zkp::schnorrsig::{PublicKey as SchnorrPublicKey, Signature as SchnorrSignature};
+use secp256k1_zkp::{Error as SecpError, Message, Secp256k1, SecretKey, Verification};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
 pub const ANNOUNCEMENT_TYPE: u16 = 55332;
 pub const ATTESTATION_TYPE: u16 = 55400;
+const ORACLE_EVENT_TYPE: u16 = 55330;
+const ENUM_EVENT_DESCRIPTOR_TYPE: u16 = 55302;
+const DIGIT_DECOMPOSITION_EVENT_DESCRIPTOR_TYPE: u16 = 55306;
+/// The maximum length in bytes of the id of an oracle event.
+pub const MAX_EVENT_ID_LENGTH: usize = 256;
+
+/// An error returned for an invalid event id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventIdError {
+    /// The event id is longer than [`MAX_EVENT_ID_LENGTH`] bytes, the length
+    /// of the event id being given.
+    TooLong(usize),
+    /// The event id is not valid UTF-8.
+    InvalidUtf8,
+}
+
+impl fmt::Display for EventIdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EventIdError::TooLong(len) => write!(
+                f,
+                "Event id of {} bytes is longer than the maximum of {} bytes",
+                len, MAX_EVENT_ID_LENGTH
+            ),
+            EventIdError::InvalidUtf8 => write!(f, "Event id is not valid UTF-8"),
+        }
+    }
+}
+
+impl std::error::Error for EventIdError {}
+
+impl From<EventIdError> for DecodeError {
+    fn from(_: EventIdError) -> DecodeError {
+        DecodeError::InvalidValue
+    }
+}
+
+/// Checks that the given event id is not longer than [`MAX_EVENT_ID_LENGTH`].
+pub fn check_event_id(event_id: &str) -> Result<(), EventIdError> {
+    if event_id.len() > MAX_EVENT_ID_LENGTH {
+        return Err(EventIdError::TooLong(event_id.len()));
+    }
+    Ok(())
+}
+
+/// Parses an event id from its UTF-8 bytes, checking its length.
+pub fn parse_event_id(bytes: &[u8]) -> Result<String, EventIdError> {
+    if bytes.len() > MAX_EVENT_ID_LENGTH {
+        return Err(EventIdError::TooLong(bytes.len()));
+    }
+    String::from_utf8(bytes.to_vec()).map_err(|_| EventIdError::InvalidUtf8)
+}
+
+/// Reads an event id written with [`write_string`], failing without reading
+/// its content if it is longer than [`MAX_EVENT_ID_LENGTH`].
+pub fn read_event_id<R: ::std::io::Read>(reader: &mut R) -> Result<String, DecodeError> {
+    let len: BigSize = Readable::read(reader)?;
+    if len.0 > MAX_EVENT_ID_LENGTH as u64 {
+        return Err(DecodeError::InvalidValue);
+    }
+    let mut buf = vec![0u8; len.0 as usize];
+    reader
+        .read_exact(&mut buf)
+        .map_err(|_| DecodeError::ShortRead)?;
+    Ok(parse_event_id(&buf)?)
+}
 
 #[derive(Clone, Eq, PartialEq, Debug)]
 #[cfg_attr(
@@ -103,6 +173,48 @@ pub struct OracleAnnouncement {
     pub oracle_event: OracleEvent,
 }
 
+impl OracleAnnouncement {
+    /// Checks that the announcement was signed by the oracle, over either the
+    /// original or the updated serialization of its event (see
+    /// [`OracleEvent::encode_v2`]).
+    pub fn validate<C: Verification>(&self, secp: &Secp256k1<C>) -> Result<(), SecpError> {
+        let mut event_ser = Vec::new();
+        self.oracle_event
+            .write(&mut event_ser)
+            .expect("Writing to a vec to succeed");
+        let msg = Message::from_hashed_data::<sha256::Hash>(&event_ser);
+        secp.schnorrsig_verify(&self.announcement_signature, &msg, &self.oracle_public_key)
+            .or_else(|_| {
+                let msg = Message::from_hashed_data::<sha256::Hash>(&self.oracle_event.encode_v2());
+                secp.schnorrsig_verify(&self.announcement_signature, &msg, &self.oracle_public_key)
+            })
+    }
+
+    /// Returns the serialization of the announcement of the updated DLC
+    /// specifications, where its event is prefixed with its type as a u16
+    /// instead of being a TLV record. Announcements are read from either
+    /// serialization.
+    pub fn encode_v2(&self) -> Vec<u8> {
+        let mut res = Vec::new();
+        write_schnorrsig(&self.announcement_signature, &mut res)
+            .and_then(|_| write_schnorr_pubkey(&self.oracle_public_key, &mut res))
+            .and_then(|_| ORACLE_EVENT_TYPE.write(&mut res))
+            .expect("Writing to a vec to succeed");
+        res.extend(self.oracle_event.encode_v2());
+        res
+    }
+
+    /// Returns the id of the announced event.
+    pub fn get_event_id(&self) -> &str {
+        &self.oracle_event.event_id
+    }
+
+    /// Returns the unix time at which the announced event matures.
+    pub fn get_event_maturity_epoch(&self) -> u32 {
+        self.oracle_event.event_maturity_epoch
+    }
+}
+
 impl Type for OracleAnnouncement {
     fn type_id(&self) -> u16 {
         ANNOUNCEMENT_TYPE
@@ -112,7 +224,7 @@ impl Type for OracleAnnouncement {
 impl_dlc_writeable!(OracleAnnouncement, {
     (announcement_signature, {cb_writeable, write_schnorrsig, read_schnorrsig}),
     (oracle_public_key, {cb_writeable, write_schnorr_pubkey, read_schnorr_pubkey}),
-    (oracle_event, {cb_writeable, write_as_tlv, read_as_tlv})
+    (oracle_event, {cb_writeable, write_as_tlv, read_as_tlv_or_subtype})
 });
 
 impl From<&OracleAnnouncement> for DlcOracleInfo {
@@ -137,17 +249,38 @@ pub struct OracleEvent {
     pub event_id: String,
 }
 
+impl OracleEvent {
+    /// Returns the serialization of the event of the updated DLC
+    /// specifications, where its event descriptor is prefixed with its type
+    /// as a u16 instead of being a TLV record.
+    pub fn encode_v2(&self) -> Vec<u8> {
+        let mut res = Vec::new();
+        (self.oracle_nonces.len() as u16)
+            .write(&mut res)
+            .expect("Writing to a vec to succeed");
+        for nonce in &self.oracle_nonces {
+            write_schnorr_pubkey(nonce, &mut res).expect("Writing to a vec to succeed");
+        }
+        self.event_maturity_epoch
+            .write(&mut res)
+            .and_then(|_| write_as_subtype(&self.event_descriptor, &mut res))
+            .and_then(|_| write_string(&self.event_id, &mut res))
+            .expect("Writing to a vec to succeed");
+        res
+    }
+}
+
 impl Type for OracleEvent {
     fn type_id(&self) -> u16 {
-        55330
+        ORACLE_EVENT_TYPE
     }
 }
 
 impl_dlc_writeable!(OracleEvent, {
     (oracle_nonces, {vec_u16_cb, write_schnorr_pubkey, read_schnorr_pubkey}),
     (event_maturity_epoch, writeable),
-    (event_descriptor, writeable),
-    (event_id, string)
+    (event_descriptor, {cb_writeable, write_event_descriptor, read_event_descriptor}),
+    (event_id, {cb_writeable, write_string, read_event_id})
 });
 
 #[derive(Clone, PartialEq, Eq, Debug)]
@@ -161,7 +294,50 @@ pub enum EventDescriptor {
     DigitDecompositionEvent(DigitDecompositionEventDescriptor),
 }
 
-impl_dlc_writeable_enum_as_tlv!(EventDescriptor, (55302, EnumEvent), (55306, DigitDecompositionEvent););
+impl Type for EventDescriptor {
+    fn type_id(&self) -> u16 {
+        match self {
+            EventDescriptor::EnumEvent(_) => ENUM_EVENT_DESCRIPTOR_TYPE,
+            EventDescriptor::DigitDecompositionEvent(_) => {
+                DIGIT_DECOMPOSITION_EVENT_DESCRIPTOR_TYPE
+            }
+        }
+    }
+}
+
+// Descriptors are written as TLV records as in the original specifications,
+// and read from either TLV records or the u16 type prefixed values of the
+// updated ones. `Writeable::write` only writes the value of the record, so
+// that `write_as_subtype` can be used for the updated serialization.
+impl Writeable for EventDescriptor {
+    fn write<W: Writer>(&self, w: &mut W) -> Result<(), ::std::io::Error> {
+        match self {
+            EventDescriptor::EnumEvent(e) => e.write(w),
+            EventDescriptor::DigitDecompositionEvent(d) => d.write(w),
+        }
+    }
+}
+
+fn write_event_descriptor<W: Writer>(
+    descriptor: &EventDescriptor,
+    w: &mut W,
+) -> Result<(), ::std::io::Error> {
+    BigSize(descriptor.type_id() as u64).write(w)?;
+    BigSize(descriptor.serialized_length() as u64).write(w)?;
+    descriptor.write(w)
+}
+
+fn read_event_descriptor<R: ::std::io::Read>(
+    reader: &mut R,
+) -> Result<EventDescriptor, DecodeError> {
+    match read_tlv_or_subtype_type(reader)? {
+        ENUM_EVENT_DESCRIPTOR_TYPE => Ok(EventDescriptor::EnumEvent(Readable::read(reader)?)),
+        DIGIT_DECOMPOSITION_EVENT_DESCRIPTOR_TYPE => Ok(EventDescriptor::DigitDecompositionEvent(
+            Readable::read(reader)?,
+        )),
+        _ => Err(DecodeError::UnknownRequiredFeature),
+    }
+}
 
 #[derive(Clone, PartialEq, Eq, Debug)]
 #[cfg_attr(
@@ -206,6 +382,151 @@ pub struct OracleAttestation {
     pub outcomes: Vec<String>,
 }
 
+impl OracleAttestation {
+    /// Checks that the attestation was signed by the oracle of the given
+    /// announcement, using the nonces it committed to.
+    pub fn validate<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        announcement: &OracleAnnouncement,
+    ) -> Result<(), SecpError> {
+        let nonces = &announcement.oracle_event.oracle_nonces;
+        if self.oracle_public_key != announcement.oracle_public_key
+            || self.signatures.len() != self.outcomes.len()
+            || self.signatures.len() > nonces.len()
+        {
+            return Err(SecpError::IncorrectSignature);
+        }
+
+        for ((signature, outcome), nonce) in self
+            .signatures
+            .iter()
+            .zip(self.outcomes.iter())
+            .zip(nonces.iter())
+        {
+            if signature[..32] != nonce.serialize()[..] {
+                return Err(SecpError::IncorrectSignature);
+            }
+            let msg = dlc::get_outcome_message(outcome);
+            secp.schnorrsig_verify(signature, &msg, &self.oracle_public_key)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the serialization of the attestation of the updated DLC
+    /// specifications, which starts with the id of the attested event.
+    pub fn encode_v2(&self, event_id: &str) -> Vec<u8> {
+        let mut res = Vec::new();
+        write_string(event_id, &mut res)
+            .and_then(|_| self.write(&mut res))
+            .expect("Writing to a vec to succeed");
+        res
+    }
+
+    /// Reads an attestation serialized by an oracle using either the original
+    /// serialization or the updated one of the DLC specifications (see
+    /// [`OracleAttestation::encode_v2`]), returning the id of the attested
+    /// event as well in the latter case. `data` must contain exactly one
+    /// attestation, which is used to tell the serializations apart.
+    pub fn decode(data: &[u8]) -> Result<(OracleAttestation, Option<String>), DecodeError> {
+        let read_all = |with_event_id: bool| {
+            let mut cursor = ::std::io::Cursor::new(data);
+            let event_id = if with_event_id {
+                Some(read_event_id(&mut cursor)?)
+            } else {
+                None
+            };
+            let attestation: OracleAttestation = Readable::read(&mut cursor)?;
+            if cursor.position() != data.len() as u64 {
+                return Err(DecodeError::InvalidValue);
+            }
+            Ok((attestation, event_id))
+        };
+        read_all(false).or_else(|_| read_all(true))
+    }
+
+    /// Returns a proof that the oracle equivocated if the given attestation
+    /// from the same oracle contains a signature using the same nonce as one
+    /// of the signatures of this attestation for a different outcome.
+    pub fn get_equivocation_proof(
+        &self,
+        other: &OracleAttestation,
+    ) -> Option<OracleEquivocationProof> {
+        if self.oracle_public_key != other.oracle_public_key {
+            return None;
+        }
+
+        for (signature, outcome) in self.signatures.iter().zip(self.outcomes.iter()) {
+            for (other_signature, other_outcome) in
+                other.signatures.iter().zip(other.outcomes.iter())
+            {
+                if signature[..32] == other_signature[..32]
+                    && dlc::get_outcome_message(outcome) != dlc::get_outcome_message(other_outcome)
+                {
+                    return Some(OracleEquivocationProof {
+                        oracle_public_key: self.oracle_public_key,
+                        first_signature: *signature,
+                        first_outcome: outcome.clone(),
+                        second_signature: *other_signature,
+                        second_outcome: other_outcome.clone(),
+                    });
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Evidence that an oracle signed two different outcomes using the same
+/// nonce, which reveals its private key. Such a proof can be published for
+/// others to stop relying on the oracle.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OracleEquivocationProof {
+    pub oracle_public_key: SchnorrPublicKey,
+    pub first_signature: SchnorrSignature,
+    pub first_outcome: String,
+    pub second_signature: SchnorrSignature,
+    pub second_outcome: String,
+}
+
+impl OracleEquivocationProof {
+    /// Checks that both signatures are valid signatures of the oracle over
+    /// different outcomes using the same nonce.
+    pub fn validate<C: Verification>(&self, secp: &Secp256k1<C>) -> Result<(), dlc::Error> {
+        self.extract_secret_key(secp).map(|_| ())
+    }
+
+    /// Extracts the private key of the oracle from the proof, failing if the
+    /// proof is not valid.
+    pub fn extract_secret_key<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+    ) -> Result<SecretKey, dlc::Error> {
+        dlc::secp_utils::schnorrsig_recover_secret_key(
+            secp,
+            &self.oracle_public_key,
+            (
+                &self.first_signature,
+                &dlc::get_outcome_message(&self.first_outcome),
+            ),
+            (
+                &self.second_signature,
+                &dlc::get_outcome_message(&self.second_outcome),
+            ),
+        )
+    }
+}
+
+impl_dlc_writeable!(OracleEquivocationProof, {
+    (oracle_public_key, {cb_writeable, write_schnorr_pubkey, read_schnorr_pubkey}),
+    (first_signature, {cb_writeable, write_schnorrsig, read_schnorrsig}),
+    (first_outcome, string),
+    (second_signature, {cb_writeable, write_schnorrsig, read_schnorrsig}),
+    (second_outcome, string)
+});
+
 impl Type for OracleAttestation {
     fn type_id(&self) -> u16 {
         ATTESTATION_TYPE
@@ -217,3 +538,57 @@ impl_dlc_writeable!(OracleAttestation, {
     (signatures, {vec_u16_cb, write_schnorrsig, read_schnorrsig}),
     (outcomes, {cb_writeable, write_strings_u16, read_strings_u16})
 });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dlc::secp_utils::schnorrsig_sign_with_nonce;
+    use secp256k1_zkp::rand::{thread_rng, RngCore};
+    use secp256k1_zkp::schnorrsig::KeyPair;
+
+    #[test]
+    fn oracle_announcement_and_attestation_validation() {
+        let secp = Secp256k1::new();
+        let key_pair = KeyPair::new(&secp, &mut thread_rng());
+        let oracle_public_key = SchnorrPublicKey::from_keypair(&secp, &key_pair);
+        let mut nonce_sk = [0u8; 32];
+        thread_rng().fill_bytes(&mut nonce_sk);
+        let nonce_pair = KeyPair::from_seckey_slice(&secp, &nonce_sk).unwrap();
+        let oracle_event = OracleEvent {
+            oracle_nonces: vec![SchnorrPublicKey::from_keypair(&secp, &nonce_pair)],
+            event_maturity_epoch: 1623133104,
+            event_descriptor: EventDescriptor::EnumEvent(EnumEventDescriptor {
+                outcomes: vec!["a".to_string(), "b".to_string()],
+            }),
+            event_id: "event".to_string(),
+        };
+        let event_msg = Message::from_hashed_data::<sha256::Hash>(&oracle_event.encode());
+        let announcement = OracleAnnouncement {
+            announcement_signature: secp.schnorrsig_sign(&event_msg, &key_pair),
+            oracle_public_key,
+            oracle_event,
+        };
+        announcement
+            .validate(&secp)
+            .expect("Announcement to be valid");
+
+        let outcome_msg = Message::from_hashed_data::<sha256::Hash>(b"a");
+        let attestation = OracleAttestation {
+            oracle_public_key,
+            signatures: vec![schnorrsig_sign_with_nonce(
+                &secp,
+                &outcome_msg,
+                &key_pair,
+                &nonce_sk,
+            )],
+            outcomes: vec!["a".to_string()],
+        };
+        attestation
+            .validate(&secp, &announcement)
+            .expect("Attestation to be valid");
+
+        let mut invalid_attestation = attestation;
+        invalid_attestation.outcomes = vec!["b".to_string()];
+        assert!(invalid_attestation.validate(&secp, &announcement).is_err());
+    }
+}
@@ -1,6 +1,17 @@
+//! Functions to read and write the types used in DLC messages following the
+//! serialization of the DLC specifications, usable as callbacks with the
+//! serialization macros of [`crate::ser_macros`]. Variable length values are
+//! prefixed with their length as a [`BigSize`] unless noted otherwise.
+//!
+//! Crates defining their own messages or extension TLVs can use
+//! [`write_as_tlv`] and [`read_as_tlv`] to embed a message as a TLV record,
+//! and [`write_tlv_stream`] and [`read_tlv_stream`] to handle streams of
+//! records, e.g. to add optional fields to a message in a way that remains
+//! readable by nodes that do not know them.
+
 use bitcoin::network::constants::Network;
 use bitcoin::Address;
-use dlc::{EnumerationPayout, PartyParams, Payout, TxInputInfo};
+use dlc::{EnumerationPayout, PartyParams, Payout, PremiumInfo, TxInputInfo};
 use lightning::ln::msgs::DecodeError;
 use lightning::ln::wire::Type;
 use lightning::util::ser::{Readable, Writeable, Writer};
@@ -74,6 +85,7 @@ impl Readable for BigSize {
     }
 }
 
+/// Writes a string as its BigSize length prefixed UTF-8 bytes.
 pub fn write_string<W: Writer>(input: &str, writer: &mut W) -> Result<(), ::std::io::Error> {
     let len = BigSize(input.len() as u64);
     len.write(writer)?;
@@ -86,6 +98,7 @@ pub fn write_string<W: Writer>(input: &str, writer: &mut W) -> Result<(), ::std:
     Ok(())
 }
 
+/// Reads a string written with [`write_string`].
 pub fn read_string<R: ::std::io::Read>(reader: &mut R) -> Result<String, DecodeError> {
     let len: BigSize = Readable::read(reader)?;
 
@@ -108,6 +121,7 @@ pub fn read_string<R: ::std::io::Read>(reader: &mut R) -> Result<String, DecodeE
     Ok(res)
 }
 
+/// Writes a BigSize length prefixed list of strings.
 pub fn write_strings<W: Writer>(inputs: &[String], writer: &mut W) -> Result<(), ::std::io::Error> {
     BigSize(inputs.len() as u64).write(writer)?;
     for s in inputs {
@@ -117,6 +131,7 @@ pub fn write_strings<W: Writer>(inputs: &[String], writer: &mut W) -> Result<(),
     Ok(())
 }
 
+/// Reads a list of strings written with [`write_strings`].
 pub fn read_strings<R: ::std::io::Read>(
     reader: &mut R,
 ) -> Result<Vec<String>, lightning::ln::msgs::DecodeError> {
@@ -132,6 +147,7 @@ pub fn read_strings<R: ::std::io::Read>(
     Ok(res)
 }
 
+/// Writes a u16 length prefixed list of strings.
 pub fn write_strings_u16<W: Writer>(
     inputs: &[String],
     writer: &mut W,
@@ -144,6 +160,7 @@ pub fn write_strings_u16<W: Writer>(
     Ok(())
 }
 
+/// Reads a list of strings written with [`write_strings_u16`].
 pub fn read_strings_u16<R: ::std::io::Read>(
     reader: &mut R,
 ) -> Result<Vec<String>, lightning::ln::msgs::DecodeError> {
@@ -156,6 +173,8 @@ pub fn read_strings_u16<R: ::std::io::Read>(
     Ok(res)
 }
 
+/// Writes a float as its sign, its BigSize integer part and 16 bits of
+/// fractional part.
 pub fn write_f64<W: lightning::util::ser::Writer>(
     input: f64,
     writer: &mut W,
@@ -169,6 +188,7 @@ pub fn write_f64<W: lightning::util::ser::Writer>(
     extra_precision.write(writer)
 }
 
+/// Reads a float written with [`write_f64`].
 pub fn read_f64<R: ::std::io::Read>(
     reader: &mut R,
 ) -> Result<f64, lightning::ln::msgs::DecodeError> {
@@ -181,6 +201,7 @@ pub fn read_f64<R: ::std::io::Read>(
     Ok(((no_precision) + ((extra_precision as f64) / ((1 << 16) as f64))) * mul_sign)
 }
 
+/// Writes a 64 bytes schnorr signature.
 pub fn write_schnorrsig<W: lightning::util::ser::Writer>(
     signature: &secp256k1_zkp::schnorrsig::Signature,
     writer: &mut W,
@@ -188,6 +209,7 @@ pub fn write_schnorrsig<W: lightning::util::ser::Writer>(
     signature.as_ref().write(writer)
 }
 
+/// Reads a schnorr signature written with [`write_schnorrsig`].
 pub fn read_schnorrsig<R: ::std::io::Read>(
     reader: &mut R,
 ) -> Result<secp256k1_zkp::schnorrsig::Signature, lightning::ln::msgs::DecodeError> {
@@ -198,6 +220,7 @@ pub fn read_schnorrsig<R: ::std::io::Read>(
     }
 }
 
+/// Writes a u16 length prefixed list of schnorr signatures.
 pub fn write_schnorr_signatures<W: lightning::util::ser::Writer>(
     signatures: &[secp256k1_zkp::schnorrsig::Signature],
     writer: &mut W,
@@ -209,6 +232,8 @@ pub fn write_schnorr_signatures<W: lightning::util::ser::Writer>(
     Ok(())
 }
 
+/// Reads a list of schnorr signatures written with
+/// [`write_schnorr_signatures`].
 pub fn read_schnorr_signatures<R: ::std::io::Read>(
     reader: &mut R,
 ) -> Result<Vec<secp256k1_zkp::schnorrsig::Signature>, lightning::ln::msgs::DecodeError> {
@@ -226,6 +251,7 @@ pub fn read_schnorr_signatures<R: ::std::io::Read>(
     Ok(ret)
 }
 
+/// Writes a 32 bytes x-only public key.
 pub fn write_schnorr_pubkey<W: lightning::util::ser::Writer>(
     pubkey: &secp256k1_zkp::schnorrsig::PublicKey,
     writer: &mut W,
@@ -233,6 +259,7 @@ pub fn write_schnorr_pubkey<W: lightning::util::ser::Writer>(
     pubkey.serialize().write(writer)
 }
 
+/// Reads an x-only public key written with [`write_schnorr_pubkey`].
 pub fn read_schnorr_pubkey<R: ::std::io::Read>(
     reader: &mut R,
 ) -> Result<secp256k1_zkp::schnorrsig::PublicKey, lightning::ln::msgs::DecodeError> {
@@ -243,6 +270,7 @@ pub fn read_schnorr_pubkey<R: ::std::io::Read>(
     }
 }
 
+/// Writes a u16 length prefixed list of x-only public keys.
 pub fn write_schnorr_pubkeys<W: Writer>(
     pubkeys: &[secp256k1_zkp::schnorrsig::PublicKey],
     writer: &mut W,
@@ -254,6 +282,8 @@ pub fn write_schnorr_pubkeys<W: Writer>(
     Ok(())
 }
 
+/// Reads a list of x-only public keys written with
+/// [`write_schnorr_pubkeys`].
 pub fn read_schnorr_pubkeys<R: ::std::io::Read>(
     reader: &mut R,
 ) -> Result<Vec<secp256k1_zkp::schnorrsig::PublicKey>, DecodeError> {
@@ -271,6 +301,7 @@ pub fn read_schnorr_pubkeys<R: ::std::io::Read>(
     Ok(ret)
 }
 
+/// Writes a BigSize length prefixed list of values.
 pub fn write_vec<W: Writer, T>(input: &[T], writer: &mut W) -> Result<(), ::std::io::Error>
 where
     T: Writeable,
@@ -278,6 +309,7 @@ where
     write_vec_cb(input, writer, &<T as Writeable>::write)
 }
 
+/// Reads a list of values written with [`write_vec`].
 pub fn read_vec<R: ::std::io::Read, T>(reader: &mut R) -> Result<Vec<T>, DecodeError>
 where
     T: Readable,
@@ -285,6 +317,8 @@ where
     read_vec_cb(reader, &Readable::read)
 }
 
+/// Writes a BigSize length prefixed list of values, each of them written
+/// with the given callback.
 pub fn write_vec_cb<W: Writer, T, F>(
     input: &[T],
     writer: &mut W,
@@ -300,6 +334,8 @@ where
     Ok(())
 }
 
+/// Reads a list of values written with [`write_vec_cb`], each of them read
+/// with the given callback.
 pub fn read_vec_cb<R: ::std::io::Read, T, F>(reader: &mut R, cb: &F) -> Result<Vec<T>, DecodeError>
 where
     F: Fn(&mut R) -> Result<T, DecodeError>,
@@ -316,6 +352,7 @@ where
     Ok(res)
 }
 
+/// Writes a u16 length prefixed list of values.
 pub fn write_vec_u16<W: Writer, T>(input: &[T], writer: &mut W) -> Result<(), ::std::io::Error>
 where
     T: Writeable,
@@ -323,6 +360,7 @@ where
     write_vec_u16_cb(input, writer, &<T as Writeable>::write)
 }
 
+/// Reads a list of values written with [`write_vec_u16`].
 pub fn read_vec_u16<R: ::std::io::Read, T>(reader: &mut R) -> Result<Vec<T>, DecodeError>
 where
     T: Readable,
@@ -330,6 +368,8 @@ where
     read_vec_u16_cb(reader, &Readable::read)
 }
 
+/// Writes a u16 length prefixed list of values, each of them written with
+/// the given callback.
 pub fn write_vec_u16_cb<W: Writer, T, F>(
     input: &[T],
     writer: &mut W,
@@ -345,6 +385,8 @@ where
     Ok(())
 }
 
+/// Reads a list of values written with [`write_vec_u16_cb`], each of them
+/// read with the given callback.
 pub fn read_vec_u16_cb<R: ::std::io::Read, T, F>(
     reader: &mut R,
     cb: &F,
@@ -361,15 +403,19 @@ where
     Ok(res)
 }
 
+/// Writes a usize as a u64.
 pub fn write_usize<W: Writer>(i: &usize, writer: &mut W) -> Result<(), ::std::io::Error> {
     <u64 as Writeable>::write(&(*i as u64), writer)
 }
 
+/// Reads a usize written with [`write_usize`].
 pub fn read_usize<R: ::std::io::Read>(reader: &mut R) -> Result<usize, DecodeError> {
     let i: u64 = Readable::read(reader)?;
     Ok(i as usize)
 }
 
+/// Writes an optional value prefixed by a byte set to 1 if the value is
+/// present and to 0 otherwise.
 pub fn write_option<W: Writer, T>(t: &Option<T>, writer: &mut W) -> Result<(), ::std::io::Error>
 where
     T: Writeable,
@@ -377,6 +423,7 @@ where
     write_option_cb(t, writer, &<T as Writeable>::write)
 }
 
+/// Reads an optional value written with [`write_option`].
 pub fn read_option<R: ::std::io::Read, T>(reader: &mut R) -> Result<Option<T>, DecodeError>
 where
     T: Readable,
@@ -384,6 +431,8 @@ where
     read_option_cb(reader, &<T as Readable>::read)
 }
 
+/// Writes an optional value as [`write_option`], using the given callback
+/// to write the value.
 pub fn write_option_cb<W: Writer, T, F>(
     t: &Option<T>,
     writer: &mut W,
@@ -401,6 +450,8 @@ where
     }
 }
 
+/// Reads an optional value written with [`write_option_cb`], using the
+/// given callback to read the value.
 pub fn read_option_cb<R: ::std::io::Read, T, F>(
     reader: &mut R,
     cb: &F,
@@ -417,6 +468,8 @@ where
     Ok(res)
 }
 
+/// Writes an address as its script pubkey followed by a byte identifying
+/// its network.
 pub fn write_address<W: Writer>(address: &Address, writer: &mut W) -> Result<(), ::std::io::Error> {
     address.script_pubkey().write(writer)?;
     let net: u8 = match address.network {
@@ -429,6 +482,7 @@ pub fn write_address<W: Writer>(address: &Address, writer: &mut W) -> Result<(),
     net.write(writer)
 }
 
+/// Reads an address written with [`write_address`].
 pub fn read_address<R: Read>(reader: &mut R) -> Result<Address, DecodeError> {
     let script: bitcoin::Script = Readable::read(reader)?;
     let net: u8 = Readable::read(reader)?;
@@ -442,6 +496,7 @@ pub fn read_address<R: Read>(reader: &mut R) -> Result<Address, DecodeError> {
     Ok(bitcoin::Address::from_script(&script, network).unwrap())
 }
 
+/// Writes an ECDSA adaptor signature.
 pub fn write_ecdsa_adaptor_signature<W: Writer>(
     sig: &EcdsaAdaptorSignature,
     writer: &mut W,
@@ -452,6 +507,8 @@ pub fn write_ecdsa_adaptor_signature<W: Writer>(
     Ok(())
 }
 
+/// Reads an ECDSA adaptor signature written with
+/// [`write_ecdsa_adaptor_signature`].
 pub fn read_ecdsa_adaptor_signature<R: ::std::io::Read>(
     reader: &mut R,
 ) -> Result<EcdsaAdaptorSignature, DecodeError> {
@@ -463,6 +520,7 @@ pub fn read_ecdsa_adaptor_signature<R: ::std::io::Read>(
     EcdsaAdaptorSignature::from_slice(&buf).map_err(|_| DecodeError::InvalidValue)
 }
 
+/// Writes a BigSize length prefixed list of ECDSA adaptor signatures.
 #[allow(clippy::ptr_arg)] // Need to have Vec to work with callbacks.
 pub fn write_ecdsa_adaptor_signatures<W: Writer>(
     sig: &Vec<EcdsaAdaptorSignature>,
@@ -471,16 +529,20 @@ pub fn write_ecdsa_adaptor_signatures<W: Writer>(
     write_vec_cb(sig, writer, &write_ecdsa_adaptor_signature)
 }
 
+/// Reads a list of ECDSA adaptor signatures written with
+/// [`write_ecdsa_adaptor_signatures`].
 pub fn read_ecdsa_adaptor_signatures<R: ::std::io::Read>(
     reader: &mut R,
 ) -> Result<Vec<EcdsaAdaptorSignature>, DecodeError> {
     read_vec_cb(reader, &read_ecdsa_adaptor_signature)
 }
 
+/// Writes an i32 as a length prefixed vector of its big endian bytes.
 pub fn write_i32<W: Writer>(i: &i32, writer: &mut W) -> Result<(), ::std::io::Error> {
     write_vec(&i.to_be_bytes().to_vec(), writer)
 }
 
+/// Reads an i32 written with [`write_i32`].
 pub fn read_i32<R: ::std::io::Read>(reader: &mut R) -> Result<i32, DecodeError> {
     let v = read_vec(reader)?;
     Ok(i32::from_be_bytes(
@@ -488,6 +550,8 @@ pub fn read_i32<R: ::std::io::Read>(reader: &mut R) -> Result<i32, DecodeError>
     ))
 }
 
+/// Writes a value as a TLV record, its type being given by its [`Type`]
+/// implementation and its length computed from its serialization.
 pub fn write_as_tlv<T: Type + Writeable, W: Writer>(
     e: &T,
     writer: &mut W,
@@ -497,6 +561,8 @@ pub fn write_as_tlv<T: Type + Writeable, W: Writer>(
     e.write(writer)
 }
 
+/// Reads a value written with [`write_as_tlv`]. The type and length of the
+/// record are currently not checked.
 pub fn read_as_tlv<T: Type + Readable, R: ::std::io::Read>(
     reader: &mut R,
 ) -> Result<T, DecodeError> {
@@ -508,9 +574,238 @@ pub fn read_as_tlv<T: Type + Readable, R: ::std::io::Read>(
     Readable::read(reader)
 }
 
+/// Writes a value prefixed with its type as a u16 and without length, as done
+/// for the oracle types nested in other types by the updated DLC
+/// specifications.
+pub fn write_as_subtype<T: Type + Writeable, W: Writer>(
+    e: &T,
+    writer: &mut W,
+) -> Result<(), ::std::io::Error> {
+    e.type_id().write(writer)?;
+    e.write(writer)
+}
+
+/// Reads the type of a value written with either [`write_as_tlv`] or
+/// [`write_as_subtype`], skipping the length of the former. The two encodings
+/// are told apart by the first byte of the BigSize encoding of types in
+/// `[0xfd, 0xffff]`, so this only supports types whose first byte as a u16 is
+/// not `0xfd`, such as those of oracle messages.
+pub fn read_tlv_or_subtype_type<R: ::std::io::Read>(reader: &mut R) -> Result<u16, DecodeError> {
+    let first: u8 = Readable::read(reader)?;
+    if first != 0xfd {
+        let second: u8 = Readable::read(reader)?;
+        return Ok(u16::from_be_bytes([first, second]));
+    }
+    let type_id: u16 = Readable::read(reader)?;
+    let _: BigSize = Readable::read(reader)?;
+    Ok(type_id)
+}
+
+/// Reads a value written with either [`write_as_tlv`] or [`write_as_subtype`]
+/// (see [`read_tlv_or_subtype_type`]). As with [`read_as_tlv`], the type of the
+/// value is not checked.
+pub fn read_as_tlv_or_subtype<T: Type + Readable, R: ::std::io::Read>(
+    reader: &mut R,
+) -> Result<T, DecodeError> {
+    read_tlv_or_subtype_type(reader)?;
+    Readable::read(reader)
+}
+
+/// A record of a TLV stream, as specified in
+/// [BOLT 1](https://github.com/lightning/bolts/blob/master/01-messaging.md#type-length-value-format).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct TlvRecord {
+    /// The type of the record.
+    pub type_id: u64,
+    /// The serialized value of the record.
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_string"
+        )
+    )]
+    pub value: Vec<u8>,
+}
+
+impl TlvRecord {
+    /// Creates a record containing the serialization of the given value.
+    pub fn new<T: Writeable>(type_id: u64, value: &T) -> TlvRecord {
+        TlvRecord {
+            type_id,
+            value: value.encode(),
+        }
+    }
+
+    /// Returns whether a reader must understand the record to process the
+    /// stream containing it, which is the case for records of even types.
+    pub fn is_required(&self) -> bool {
+        self.type_id % 2 == 0
+    }
+
+    /// Deserializes the value of the record, failing if it is not entirely
+    /// consumed.
+    pub fn read_value<T: Readable>(&self) -> Result<T, DecodeError> {
+        let mut cursor = ::std::io::Cursor::new(&self.value);
+        let value = Readable::read(&mut cursor)?;
+        if cursor.position() != self.value.len() as u64 {
+            return Err(DecodeError::InvalidValue);
+        }
+        Ok(value)
+    }
+}
+
+/// Writes the given records as a TLV stream, without length prefix. The
+/// records must be ordered by strictly increasing type.
+pub fn write_tlv_stream<W: Writer>(
+    records: &[TlvRecord],
+    writer: &mut W,
+) -> Result<(), ::std::io::Error> {
+    if records.windows(2).any(|x| x[0].type_id >= x[1].type_id) {
+        return Err(::std::io::Error::new(
+            ::std::io::ErrorKind::InvalidInput,
+            "TLV records must be ordered by strictly increasing type",
+        ));
+    }
+    for record in records {
+        BigSize(record.type_id).write(writer)?;
+        BigSize(record.value.len() as u64).write(writer)?;
+        writer.write_all(&record.value)?;
+    }
+    Ok(())
+}
+
+/// Reads a TLV stream until the end of the reader, checking that records are
+/// ordered by strictly increasing type. Unknown records are returned as well,
+/// callers being expected to fail on the ones that are required (see
+/// [`TlvRecord::is_required`]).
+pub fn read_tlv_stream<R: ::std::io::Read>(reader: &mut R) -> Result<Vec<TlvRecord>, DecodeError> {
+    let mut records: Vec<TlvRecord> = Vec::new();
+    loop {
+        let mut prefix = [0u8; 1];
+        if reader.read(&mut prefix)? == 0 {
+            return Ok(records);
+        }
+        let type_id: BigSize = Readable::read(&mut (&prefix[..]).chain(&mut *reader))?;
+        if records.last().map_or(false, |x| x.type_id >= type_id.0) {
+            return Err(DecodeError::InvalidValue);
+        }
+        let len: BigSize = Readable::read(reader)?;
+        if len.0 > MAX_VEC_SIZE {
+            return Err(DecodeError::BadLengthDescriptor);
+        }
+        let mut value = vec![0u8; len.0 as usize];
+        reader
+            .read_exact(&mut value)
+            .map_err(|_| DecodeError::ShortRead)?;
+        records.push(TlvRecord {
+            type_id: type_id.0,
+            value,
+        });
+    }
+}
+
+/// Reads a TLV stream of records unknown to the reader, as found at the end of
+/// messages, failing if any of them is required.
+pub fn read_extra_tlv_stream<R: ::std::io::Read>(
+    reader: &mut R,
+) -> Result<Vec<TlvRecord>, DecodeError> {
+    let records = read_tlv_stream(reader)?;
+    if records.iter().any(|x| x.is_required()) {
+        return Err(DecodeError::UnknownRequiredFeature);
+    }
+    Ok(records)
+}
+
+/// Returns the value of the record of the given type if present in the
+/// stream, enabling optional TLV fields to be read.
+pub fn get_tlv_value<T: Readable>(
+    records: &[TlvRecord],
+    type_id: u64,
+) -> Result<Option<T>, DecodeError> {
+    records
+        .iter()
+        .find(|x| x.type_id == type_id)
+        .map(|x| x.read_value())
+        .transpose()
+}
+
+/// Writes a value prefixed with the BigSize length of its serialization,
+/// enabling values that are read until the end of their input, such as
+/// messages ending with a TLV stream, to be embedded in other values.
+pub fn write_length_prefixed<W: Writer, T: Writeable>(
+    input: &T,
+    writer: &mut W,
+) -> Result<(), ::std::io::Error> {
+    BigSize(input.serialized_length() as u64).write(writer)?;
+    input.write(writer)
+}
+
+/// Reads a value written with [`write_length_prefixed`], failing if it does
+/// not consume its entire length.
+pub fn read_length_prefixed<R: ::std::io::Read, T: Readable>(
+    reader: &mut R,
+) -> Result<T, DecodeError> {
+    let len: BigSize = Readable::read(reader)?;
+    if len.0 > MAX_VEC_SIZE {
+        return Err(DecodeError::BadLengthDescriptor);
+    }
+    let mut value_reader = reader.take(len.0);
+    let value = Readable::read(&mut value_reader)?;
+    if value_reader.limit() != 0 {
+        return Err(DecodeError::InvalidValue);
+    }
+    Ok(value)
+}
+
+/// Writes a BigSize length prefixed list of values, each of them as a TLV
+/// record.
+pub fn write_tlv_vec<W: Writer, T: Type + Writeable>(
+    input: &[T],
+    writer: &mut W,
+) -> Result<(), ::std::io::Error> {
+    write_vec_cb(input, writer, &write_as_tlv)
+}
+
+/// Reads a list of values written with [`write_tlv_vec`].
+pub fn read_tlv_vec<R: ::std::io::Read, T: Type + Readable>(
+    reader: &mut R,
+) -> Result<Vec<T>, DecodeError> {
+    read_vec_cb(reader, &read_as_tlv)
+}
+
 impl_dlc_writeable_external!(Payout, payout, { (offer, writeable), (accept, writeable) });
 impl_dlc_writeable_external!(EnumerationPayout, enum_payout, { (outcome, string), (payout, { cb_writeable, payout::write, payout::read} )});
 impl_dlc_writeable_external!(TxInputInfo, tx_input_info, { (outpoint, writeable), (max_witness_len, usize), (redeem_script, writeable), (serial_id, writeable)});
+impl_dlc_writeable_external!(PremiumInfo, premium_info, { (amount, writeable), (paid_by_offer, writeable), (serial_id, writeable) });
+
+/// Module containing write and read functions for FeeSplit
+pub mod fee_split {
+    use super::*;
+    use dlc::FeeSplit;
+
+    /// Function to write FeeSplit
+    pub fn write<W: Writer>(fee_split: &FeeSplit, w: &mut W) -> Result<(), ::std::io::Error> {
+        fee_split
+            .get_offer_share()
+            .map_err(|_| {
+                ::std::io::Error::new(::std::io::ErrorKind::InvalidData, "Invalid fee split")
+            })?
+            .write(w)
+    }
+
+    /// Function to read FeeSplit
+    pub fn read<R: ::std::io::Read>(r: &mut R) -> Result<FeeSplit, DecodeError> {
+        let offer_share: u16 = Readable::read(r)?;
+        FeeSplit::from_offer_share(offer_share).map_err(|_| DecodeError::InvalidValue)
+    }
+}
+
 impl_dlc_writeable_external!(PartyParams, party_params, {
     (fund_pubkey, writeable),
     (change_script_pubkey, writeable),
@@ -521,3 +816,37 @@ impl_dlc_writeable_external!(PartyParams, party_params, {
     (input_amount, writeable),
     (collateral, writeable)
 });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tlv_stream_round_trip_test() {
+        let records = vec![
+            TlvRecord::new(1, &42u64),
+            TlvRecord::new(0xFFFF, &true),
+            TlvRecord::new(0x1000000, &Vec::<u8>::new()),
+        ];
+        let mut buf = Vec::new();
+        write_tlv_stream(&records, &mut buf).unwrap();
+
+        let read = read_tlv_stream(&mut ::std::io::Cursor::new(&buf)).unwrap();
+
+        assert_eq!(records, read);
+        assert_eq!(Some(42u64), get_tlv_value(&read, 1).unwrap());
+        assert_eq!(None, get_tlv_value::<u64>(&read, 3).unwrap());
+        assert!(!read[0].is_required());
+    }
+
+    #[test]
+    fn tlv_stream_with_unordered_records_fails_test() {
+        let records = vec![TlvRecord::new(3, &1u8), TlvRecord::new(1, &1u8)];
+        assert!(write_tlv_stream(&records, &mut Vec::new()).is_err());
+
+        let mut buf = Vec::new();
+        write_tlv_stream(&records[..1], &mut buf).unwrap();
+        write_tlv_stream(&records[1..], &mut buf).unwrap();
+        assert!(read_tlv_stream(&mut ::std::io::Cursor::new(&buf)).is_err());
+    }
+}
@@ -88,6 +88,68 @@ macro_rules! impl_dlc_writeable {
             }
         }
     };
+    ($st:ident, {$(($field: ident, $fieldty: tt)), *}, tlv_stream $tlv_field: ident ) => {
+        impl Writeable for $st {
+			fn write<W: Writer>(&self, w: &mut W) -> Result<(), ::std::io::Error> {
+				$(
+                    field_write!(w, self.$field, $fieldty);
+                )*
+                $crate::ser_impls::write_tlv_stream(&self.$tlv_field, w)?;
+				Ok(())
+            }
+        }
+
+        impl Readable for $st {
+			fn read<R: std::io::Read>(r: &mut R) -> Result<Self, DecodeError> {
+                Ok(Self {
+                    $(
+                        $field: field_read!(r, $fieldty),
+                    )*
+                    $tlv_field: $crate::ser_impls::read_extra_tlv_stream(r)?,
+                })
+            }
+        }
+    };
+    ($st:ident, {$(($field: ident, $fieldty: tt)), *}, tlv_stream $tlv_field: ident {$(($tlv_opt_field: ident, $tlv_type: expr)), *} ) => {
+        impl Writeable for $st {
+			fn write<W: Writer>(&self, w: &mut W) -> Result<(), ::std::io::Error> {
+				$(
+                    field_write!(w, self.$field, $fieldty);
+                )*
+                // Optional fields are written as records of the TLV stream,
+                // ordered by type together with the unknown records.
+                let mut records = self.$tlv_field.clone();
+                $(
+                    if let Some(value) = &self.$tlv_opt_field {
+                        records.push($crate::ser_impls::TlvRecord::new($tlv_type, value));
+                    }
+                )*
+                records.sort_by_key(|x| x.type_id);
+                $crate::ser_impls::write_tlv_stream(&records, w)?;
+				Ok(())
+            }
+        }
+
+        impl Readable for $st {
+			fn read<R: std::io::Read>(r: &mut R) -> Result<Self, DecodeError> {
+                let mut res = Self {
+                    $(
+                        $field: field_read!(r, $fieldty),
+                    )*
+                    $(
+                        $tlv_opt_field: None,
+                    )*
+                    $tlv_field: $crate::ser_impls::read_extra_tlv_stream(r)?,
+                };
+                $(
+                    res.$tlv_opt_field = $crate::ser_impls::get_tlv_value(&res.$tlv_field, $tlv_type)?;
+                )*
+                let known_types: &[u64] = &[$($tlv_type),*];
+                res.$tlv_field.retain(|x| !known_types.contains(&x.type_id));
+                Ok(res)
+            }
+        }
+    };
 }
 
 #[macro_export]
@@ -0,0 +1,124 @@
+//! Nostr events as specified in
+//! [NIP-01](https://github.com/nostr-protocol/nips/blob/master/01.md).
+
+use crate::Error;
+use secp256k1_zkp::bitcoin_hashes::hex::{FromHex, ToHex};
+use secp256k1_zkp::bitcoin_hashes::{sha256, Hash};
+use secp256k1_zkp::schnorrsig::{KeyPair, PublicKey as SchnorrPublicKey, Signature};
+use secp256k1_zkp::{Message, Secp256k1, Signing, Verification};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// A signed Nostr event.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Event {
+    /// The hex encoded hash of the serialized event.
+    pub id: String,
+    /// The hex encoded x-only public key of the author of the event.
+    pub pubkey: String,
+    /// The unix timestamp at which the event was created.
+    pub created_at: u64,
+    /// The kind of the event.
+    pub kind: u64,
+    /// The tags of the event.
+    pub tags: Vec<Vec<String>>,
+    /// The content of the event.
+    pub content: String,
+    /// The hex encoded schnorr signature of the id of the event.
+    pub sig: String,
+}
+
+impl Event {
+    /// Creates an event signed with the given key pair.
+    pub fn new<C: Signing>(
+        secp: &Secp256k1<C>,
+        key_pair: &KeyPair,
+        created_at: u64,
+        kind: u64,
+        tags: Vec<Vec<String>>,
+        content: String,
+    ) -> Event {
+        let pubkey = SchnorrPublicKey::from_keypair(secp, key_pair)
+            .serialize()
+            .to_hex();
+        let id = get_event_id(&pubkey, created_at, kind, &tags, &content);
+        let msg = Message::from_slice(&id).expect("a sha256 hash to be a valid message");
+        let sig = secp.schnorrsig_sign(&msg, key_pair);
+        Event {
+            id: id[..].to_hex(),
+            pubkey,
+            created_at,
+            kind,
+            tags,
+            content,
+            sig: sig.as_ref()[..].to_hex(),
+        }
+    }
+
+    /// Returns the public key of the author of the event.
+    pub fn get_public_key(&self) -> Result<SchnorrPublicKey, Error> {
+        let pubkey = Vec::<u8>::from_hex(&self.pubkey)
+            .map_err(|_| Error::InvalidEvent("Invalid public key hex.".to_string()))?;
+        SchnorrPublicKey::from_slice(&pubkey)
+            .map_err(|_| Error::InvalidEvent("Invalid public key.".to_string()))
+    }
+
+    /// Returns the values of the tags with the given name.
+    pub fn get_tag_values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.tags
+            .iter()
+            .filter(move |x| x.len() > 1 && x[0] == name)
+            .map(|x| x[1].as_str())
+    }
+
+    /// Checks that the id of the event matches its content and that it was
+    /// signed by its author.
+    pub fn verify<C: Verification>(&self, secp: &Secp256k1<C>) -> Result<(), Error> {
+        let id = get_event_id(
+            &self.pubkey,
+            self.created_at,
+            self.kind,
+            &self.tags,
+            &self.content,
+        );
+        if id[..].to_hex() != self.id {
+            return Err(Error::InvalidEvent("Invalid event id.".to_string()));
+        }
+        let sig = Vec::<u8>::from_hex(&self.sig)
+            .ok()
+            .and_then(|x| Signature::from_slice(&x).ok())
+            .ok_or_else(|| Error::InvalidEvent("Invalid signature encoding.".to_string()))?;
+        let msg = Message::from_slice(&id).expect("a sha256 hash to be a valid message");
+        secp.schnorrsig_verify(&sig, &msg, &self.get_public_key()?)
+            .map_err(|_| Error::InvalidEvent("Invalid signature.".to_string()))
+    }
+}
+
+fn get_event_id(
+    pubkey: &str,
+    created_at: u64,
+    kind: u64,
+    tags: &[Vec<String>],
+    content: &str,
+) -> [u8; 32] {
+    let serialized = json!([0, pubkey, created_at, kind, tags, content]).to_string();
+    sha256::Hash::hash(serialized.as_bytes()).into_inner()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1_zkp::rand::thread_rng;
+
+    #[test]
+    fn verify_event_test() {
+        let secp = Secp256k1::new();
+        let key_pair = KeyPair::new(&secp, &mut thread_rng());
+        let mut event = Event::new(&secp, &key_pair, 1, 4, Vec::new(), "content".to_string());
+
+        event.verify(&secp).expect("a valid event");
+
+        event.content = "modified".to_string();
+        assert!(event.verify(&secp).is_err());
+    }
+}
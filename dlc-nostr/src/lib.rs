@@ -0,0 +1,261 @@
+//! # dlc-nostr
+//! Transport of DLC messages over Nostr encrypted direct messages
+//! ([NIP-04](https://github.com/nostr-protocol/nips/blob/master/04.md)).
+//! Messages are encoded as hex strings of their wire encoding prefixed by
+//! their two bytes type, encrypted for the peer and published as kind 4
+//! events tagged with the public key of the peer. NIP-44 encryption is not
+//! supported (see [`nip04`]).
+//!
+//! The crate does not manage relay connections. The application subscribes to
+//! the events addressed to its key using [`get_subscription_request`], passes
+//! the events received from relays to [`NostrMessageHandler::handle_event`]
+//! and publishes the returned events using [`get_publish_request`].
+//!
+//! Nostr public keys being x-only keys, the public key of a peer used as
+//! counter party by the [`Manager`] is the one with an even y coordinate
+//! having the same x coordinate, as returned by [`get_peer_public_key`].
+
+#![crate_name = "dlc_nostr"]
+// Coding conventions
+#![forbid(unsafe_code)]
+#![deny(non_upper_case_globals)]
+#![deny(non_camel_case_types)]
+#![deny(non_snake_case)]
+#![deny(unused_mut)]
+#![deny(dead_code)]
+#![deny(unused_imports)]
+#![deny(missing_docs)]
+
+extern crate aes;
+extern crate base64;
+extern crate block_modes;
+extern crate dlc_manager;
+extern crate dlc_messages;
+extern crate lightning;
+extern crate secp256k1_zkp;
+extern crate serde;
+extern crate serde_json;
+
+pub mod event;
+pub mod nip04;
+
+use dlc_manager::manager::Manager;
+use dlc_manager::{Blockchain, Oracle, Storage, Time, Wallet};
+use dlc_messages::Message;
+use event::Event;
+use lightning::ln::msgs::DecodeError;
+use lightning::ln::wire::Type;
+use lightning::util::ser::Writeable;
+use secp256k1_zkp::bitcoin_hashes::hex::{FromHex, ToHex};
+use secp256k1_zkp::schnorrsig::{KeyPair, PublicKey as SchnorrPublicKey};
+use secp256k1_zkp::{All, PublicKey, Secp256k1, SecretKey};
+use serde_json::json;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The kind of encrypted direct message events.
+pub const ENCRYPTED_DIRECT_MESSAGE_KIND: u64 = 4;
+
+/// An error code.
+#[derive(Debug)]
+pub enum Error {
+    /// An event was malformed or incorrectly signed.
+    InvalidEvent(String),
+    /// The content of an event could not be encrypted or decrypted.
+    Encryption(String),
+    /// The content of an event was not a valid DLC message.
+    InvalidMessage(DecodeError),
+    /// The manager failed to process a message.
+    Manager(dlc_manager::error::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::InvalidEvent(ref s) => write!(f, "Invalid event: {}", s),
+            Error::Encryption(ref s) => write!(f, "Encryption error: {}", s),
+            Error::InvalidMessage(ref e) => write!(f, "Invalid message: {:?}", e),
+            Error::Manager(ref e) => write!(f, "Manager error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<dlc_manager::error::Error> for Error {
+    fn from(e: dlc_manager::error::Error) -> Error {
+        Error::Manager(e)
+    }
+}
+
+/// Returns the public key identifying the peer with the given Nostr public
+/// key, to be used as counter party with the [`Manager`].
+pub fn get_peer_public_key(nostr_public_key: &SchnorrPublicKey) -> PublicKey {
+    let mut buf = [2u8; 33];
+    buf[1..].copy_from_slice(&nostr_public_key.serialize());
+    PublicKey::from_slice(&buf).expect("an x-only key to be a valid compressed key")
+}
+
+/// Returns the Nostr public key of the peer with the given public key.
+pub fn get_nostr_public_key(public_key: &PublicKey) -> SchnorrPublicKey {
+    SchnorrPublicKey::from_slice(&public_key.serialize()[1..])
+        .expect("the x coordinate of a public key to be a valid x-only key")
+}
+
+/// Returns the relay request subscribing to the encrypted direct messages
+/// addressed to the given public key, created after `since`.
+pub fn get_subscription_request(
+    subscription_id: &str,
+    public_key: &SchnorrPublicKey,
+    since: u64,
+) -> String {
+    json!([
+        "REQ",
+        subscription_id,
+        {
+            "kinds": [ENCRYPTED_DIRECT_MESSAGE_KIND],
+            "#p": [public_key.serialize().to_hex()],
+            "since": since,
+        }
+    ])
+    .to_string()
+}
+
+/// Returns the relay request publishing the given event.
+pub fn get_publish_request(event: &Event) -> String {
+    json!(["EVENT", event]).to_string()
+}
+
+/// Returns the event contained in a message received from a relay, if any.
+pub fn parse_relay_message(message: &str) -> Option<Event> {
+    let (message_type, _, event): (String, String, Event) = serde_json::from_str(message).ok()?;
+    if message_type != "EVENT" {
+        return None;
+    }
+    Some(event)
+}
+
+/// Exchanges DLC messages with peers through Nostr events, processing the
+/// received ones with a [`Manager`].
+pub struct NostrMessageHandler<W: Deref, B: Deref, S: DerefMut, O: Deref, T: Deref>
+where
+    W::Target: Wallet,
+    B::Target: Blockchain,
+    S::Target: Storage,
+    O::Target: Oracle,
+    T::Target: Time,
+{
+    manager: Arc<Manager<W, B, S, O, T>>,
+    secret_key: SecretKey,
+    key_pair: KeyPair,
+    secp: Secp256k1<All>,
+}
+
+impl<W: Deref, B: Deref, S: DerefMut, O: Deref, T: Deref> NostrMessageHandler<W, B, S, O, T>
+where
+    W::Target: Wallet,
+    B::Target: Blockchain,
+    S::Target: Storage,
+    O::Target: Oracle,
+    T::Target: Time,
+{
+    /// Creates a new handler signing and decrypting events with the given
+    /// secret key.
+    pub fn new(manager: Arc<Manager<W, B, S, O, T>>, secret_key: SecretKey) -> Self {
+        let secp = Secp256k1::new();
+        let key_pair = KeyPair::from_seckey_slice(&secp, &secret_key[..])
+            .expect("a secret key to be a valid key pair secret");
+        NostrMessageHandler {
+            manager,
+            secret_key,
+            key_pair,
+            secp,
+        }
+    }
+
+    /// Returns the Nostr public key of the handler, to which peers send
+    /// messages.
+    pub fn get_public_key(&self) -> SchnorrPublicKey {
+        SchnorrPublicKey::from_keypair(&self.secp, &self.key_pair)
+    }
+
+    /// Returns an event containing the given message encrypted for the given
+    /// peer.
+    pub fn create_message_event(
+        &self,
+        peer: &SchnorrPublicKey,
+        message: &Message,
+    ) -> Result<Event, Error> {
+        let mut buf = message.type_id().to_be_bytes().to_vec();
+        buf.extend(message.encode());
+        let content = nip04::encrypt(&self.secret_key, peer, &buf.to_hex())?;
+        Ok(Event::new(
+            &self.secp,
+            &self.key_pair,
+            get_unix_time_now(),
+            ENCRYPTED_DIRECT_MESSAGE_KIND,
+            vec![vec!["p".to_string(), peer.serialize().to_hex()]],
+            content,
+        ))
+    }
+
+    /// Processes the DLC message contained in the given event with the
+    /// manager, returning the event containing the reply to publish, if any.
+    /// Events that are not addressed to the handler are ignored.
+    pub fn handle_event(&self, event: &Event) -> Result<Option<Event>, Error> {
+        let own_key = self.get_public_key().serialize().to_hex();
+        if event.kind != ENCRYPTED_DIRECT_MESSAGE_KIND
+            || !event.get_tag_values("p").any(|x| x == own_key)
+        {
+            return Ok(None);
+        }
+        let (peer, content) = nip04::decrypt_event(&self.secp, &self.secret_key, event)?;
+        let buf = Vec::<u8>::from_hex(&content)
+            .map_err(|_| Error::InvalidMessage(DecodeError::InvalidValue))?;
+        let counter_party = get_peer_public_key(&peer);
+        let message = self.manager.decode_dlc_message(&buf, &counter_party)?;
+
+        match self.manager.on_dlc_message(&message, counter_party)? {
+            Some(reply) => Ok(Some(self.create_message_event(&peer, &reply)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+fn get_unix_time_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("the current time to be after the unix epoch")
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1_zkp::rand::thread_rng;
+
+    #[test]
+    fn peer_public_key_round_trip_test() {
+        let secp = Secp256k1::new();
+        let key_pair = KeyPair::new(&secp, &mut thread_rng());
+        let nostr_public_key = SchnorrPublicKey::from_keypair(&secp, &key_pair);
+
+        assert_eq!(
+            nostr_public_key,
+            get_nostr_public_key(&get_peer_public_key(&nostr_public_key))
+        );
+    }
+
+    #[test]
+    fn parse_relay_message_test() {
+        let secp = Secp256k1::new();
+        let key_pair = KeyPair::new(&secp, &mut thread_rng());
+        let event = Event::new(&secp, &key_pair, 1, 4, Vec::new(), "content".to_string());
+        let message = json!(["EVENT", "sub", event]).to_string();
+
+        assert_eq!(Some(event), parse_relay_message(&message));
+        assert_eq!(None, parse_relay_message("[\"EOSE\", \"sub\"]"));
+    }
+}
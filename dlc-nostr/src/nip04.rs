@@ -0,0 +1,179 @@
+//! Encryption of direct message contents as specified in
+//! [NIP-04](https://github.com/nostr-protocol/nips/blob/master/04.md): the
+//! content is encrypted with AES-256-CBC using the x coordinate of the ECDH
+//! point of the parties as key, and encoded as `<ciphertext>?iv=<iv>` using
+//! base64.
+//!
+//! AES-256-CBC does not authenticate the content, which could be altered
+//! without it being detected on decryption. Received content is therefore
+//! only decrypted through [`decrypt_event`], after checking the signature of
+//! the event committing to it. The authenticated encryption specified in
+//! [NIP-44](https://github.com/nostr-protocol/nips/blob/master/44.md) is not
+//! supported, and content encrypted with it is rejected.
+
+use crate::event::Event;
+use crate::{Error, ENCRYPTED_DIRECT_MESSAGE_KIND};
+use aes::Aes256;
+use block_modes::block_padding::Pkcs7;
+use block_modes::{BlockMode, Cbc};
+use secp256k1_zkp::ecdh::SharedSecret;
+use secp256k1_zkp::rand::{thread_rng, RngCore};
+use secp256k1_zkp::schnorrsig::PublicKey as SchnorrPublicKey;
+use secp256k1_zkp::{Secp256k1, SecretKey, Verification};
+
+type Aes256Cbc = Cbc<Aes256, Pkcs7>;
+
+const IV_SEPARATOR: &str = "?iv=";
+
+/// Encrypts the given content for the given peer.
+pub fn encrypt(
+    secret_key: &SecretKey,
+    peer: &SchnorrPublicKey,
+    content: &str,
+) -> Result<String, Error> {
+    let mut iv = [0u8; 16];
+    thread_rng().fill_bytes(&mut iv);
+    let cipher = Aes256Cbc::new_from_slices(&get_shared_key(secret_key, peer)?, &iv)
+        .map_err(|e| Error::Encryption(e.to_string()))?;
+    let ciphertext = cipher.encrypt_vec(content.as_bytes());
+    Ok(format!(
+        "{}{}{}",
+        base64::encode(&ciphertext),
+        IV_SEPARATOR,
+        base64::encode(&iv)
+    ))
+}
+
+/// Decrypts the content of the given encrypted direct message event, after
+/// verifying that it was signed by its author, returning the public key of the
+/// author along with the decrypted content.
+pub fn decrypt_event<C: Verification>(
+    secp: &Secp256k1<C>,
+    secret_key: &SecretKey,
+    event: &Event,
+) -> Result<(SchnorrPublicKey, String), Error> {
+    if event.kind != ENCRYPTED_DIRECT_MESSAGE_KIND {
+        return Err(Error::InvalidEvent(format!(
+            "Expected an encrypted direct message, got kind {}.",
+            event.kind
+        )));
+    }
+    event.verify(secp)?;
+    let peer = event.get_public_key()?;
+    let content = decrypt(secret_key, &peer, &event.content)?;
+    Ok((peer, content))
+}
+
+/// Decrypts the given content received from the given peer, which must have
+/// been authenticated beforehand.
+fn decrypt(
+    secret_key: &SecretKey,
+    peer: &SchnorrPublicKey,
+    content: &str,
+) -> Result<String, Error> {
+    let mut parts = content.split(IV_SEPARATOR);
+    let (ciphertext, iv) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(ciphertext), Some(iv), None) => (ciphertext, iv),
+        _ => {
+            return Err(Error::Encryption(
+                "Invalid content format, only NIP-04 encryption is supported.".to_string(),
+            ))
+        }
+    };
+    let ciphertext = base64::decode(ciphertext).map_err(|e| Error::Encryption(e.to_string()))?;
+    let iv = base64::decode(iv).map_err(|e| Error::Encryption(e.to_string()))?;
+    let cipher = Aes256Cbc::new_from_slices(&get_shared_key(secret_key, peer)?, &iv)
+        .map_err(|e| Error::Encryption(e.to_string()))?;
+    let plaintext = cipher
+        .decrypt_vec(&ciphertext)
+        .map_err(|e| Error::Encryption(e.to_string()))?;
+    String::from_utf8(plaintext).map_err(|e| Error::Encryption(e.to_string()))
+}
+
+/// Returns the x coordinate of the ECDH point of the secret key and the peer
+/// key, the latter being taken with an even y coordinate as all Nostr keys.
+fn get_shared_key(secret_key: &SecretKey, peer: &SchnorrPublicKey) -> Result<[u8; 32], Error> {
+    let peer = crate::get_peer_public_key(peer);
+    let shared_secret = SharedSecret::new_with_hash(&peer, secret_key, |x, _| x.into())
+        .map_err(|e| Error::Encryption(e.to_string()))?;
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&shared_secret[..]);
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1_zkp::schnorrsig::KeyPair;
+    use secp256k1_zkp::Secp256k1;
+
+    #[test]
+    fn encrypt_decrypt_test() {
+        let secp = Secp256k1::new();
+        let mut rng = thread_rng();
+        let alice = SecretKey::new(&mut rng);
+        let bob = SecretKey::new(&mut rng);
+        let alice_pk = SchnorrPublicKey::from_keypair(
+            &secp,
+            &KeyPair::from_seckey_slice(&secp, &alice[..]).unwrap(),
+        );
+        let bob_pk = SchnorrPublicKey::from_keypair(
+            &secp,
+            &KeyPair::from_seckey_slice(&secp, &bob[..]).unwrap(),
+        );
+
+        let encrypted = encrypt(&alice, &bob_pk, "a8a9").unwrap();
+
+        assert_eq!("a8a9", decrypt(&bob, &alice_pk, &encrypted).unwrap());
+    }
+
+    #[test]
+    fn decrypt_event_test() {
+        let secp = Secp256k1::new();
+        let mut rng = thread_rng();
+        let alice_sk = SecretKey::new(&mut rng);
+        let bob = SecretKey::new(&mut rng);
+        let alice = KeyPair::from_seckey_slice(&secp, &alice_sk[..]).unwrap();
+        let alice_pk = SchnorrPublicKey::from_keypair(&secp, &alice);
+        let bob_pk = SchnorrPublicKey::from_keypair(
+            &secp,
+            &KeyPair::from_seckey_slice(&secp, &bob[..]).unwrap(),
+        );
+        let content = encrypt(&alice_sk, &bob_pk, "a8a9").unwrap();
+        let event = Event::new(
+            &secp,
+            &alice,
+            1,
+            ENCRYPTED_DIRECT_MESSAGE_KIND,
+            Vec::new(),
+            content,
+        );
+
+        assert_eq!(
+            (alice_pk, "a8a9".to_string()),
+            decrypt_event(&secp, &bob, &event).unwrap()
+        );
+
+        // Content altered after being signed is rejected.
+        let mut altered = event.clone();
+        altered.content = encrypt(&alice_sk, &bob_pk, "b8b9").unwrap();
+        assert!(matches!(
+            decrypt_event(&secp, &bob, &altered),
+            Err(Error::InvalidEvent(_))
+        ));
+
+        // NIP-44 payloads are not supported.
+        let nip44_event = Event::new(
+            &secp,
+            &alice,
+            1,
+            ENCRYPTED_DIRECT_MESSAGE_KIND,
+            Vec::new(),
+            base64::encode(&[2u8; 99]),
+        );
+        assert!(matches!(
+            decrypt_event(&secp, &bob, &nip44_event),
+            Err(Error::Encryption(_))
+        ));
+    }
+}
@@ -0,0 +1,113 @@
+//! # dlc-py
+//! Python bindings to the payout curves and message serialization of rust-dlc,
+//! so that payout structures can be backtested from Python and their
+//! serialization checked against the specification. Payout functions,
+//! rounding intervals and offers are passed as JSON strings using the serde
+//! representation of the rust-dlc types.
+
+#![crate_name = "dlc_py"]
+// Coding conventions, except for naming and documentation lints triggered by
+// the code generated by pyo3.
+#![deny(unused_mut)]
+#![deny(dead_code)]
+#![deny(unused_imports)]
+
+extern crate dlc_manager;
+extern crate dlc_messages;
+extern crate lightning;
+extern crate pyo3;
+extern crate serde;
+extern crate serde_json;
+
+use dlc_manager::payout_curve::{PayoutFunction, RoundingIntervals};
+use dlc_messages::contract_msgs::PayoutFunction as SerPayoutFunction;
+use dlc_messages::OfferDlc;
+use lightning::util::ser::{Readable, Writeable};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+/// Evaluates the given payout function at each of the given outcomes,
+/// returning the unrounded payouts of the offer party.
+#[pyfunction]
+fn evaluate_payout_function(payout_function: &str, outcomes: Vec<u64>) -> PyResult<Vec<f64>> {
+    let payout_function: PayoutFunction = from_json(payout_function)?;
+    Ok(outcomes
+        .iter()
+        .map(|x| payout_function.evaluate(*x))
+        .collect())
+}
+
+/// Returns the range payouts of the given payout function for the given
+/// rounding intervals and total collateral, as a list of
+/// `(start, count, offer_payout, accept_payout)` tuples.
+#[pyfunction]
+fn get_range_payouts(
+    payout_function: &str,
+    rounding_intervals: &str,
+    total_collateral: u64,
+) -> PyResult<Vec<(usize, usize, u64, u64)>> {
+    let payout_function: PayoutFunction = from_json(payout_function)?;
+    let rounding_intervals: RoundingIntervals = from_json(rounding_intervals)?;
+    Ok(payout_function
+        .to_range_payouts(total_collateral, &rounding_intervals)
+        .iter()
+        .map(|x| (x.start, x.count, x.payout.offer, x.payout.accept))
+        .collect())
+}
+
+/// Serializes the given payout function as specified for offer messages.
+#[pyfunction]
+fn serialize_payout_function<'p>(py: Python<'p>, payout_function: &str) -> PyResult<&'p PyBytes> {
+    let payout_function: PayoutFunction = from_json(payout_function)?;
+    Ok(PyBytes::new(
+        py,
+        &SerPayoutFunction::from(&payout_function).encode(),
+    ))
+}
+
+/// Deserializes a payout function serialized as specified for offer messages.
+#[pyfunction]
+fn deserialize_payout_function(data: &[u8]) -> PyResult<String> {
+    let payout_function: SerPayoutFunction = read(data)?;
+    to_json(&PayoutFunction::from(&payout_function))
+}
+
+/// Serializes the given offer message, without its type prefix.
+#[pyfunction]
+fn serialize_offer<'p>(py: Python<'p>, offer: &str) -> PyResult<&'p PyBytes> {
+    let offer: OfferDlc = from_json(offer)?;
+    Ok(PyBytes::new(py, &offer.encode()))
+}
+
+/// Deserializes an offer message serialized without its type prefix.
+#[pyfunction]
+fn deserialize_offer(data: &[u8]) -> PyResult<String> {
+    let offer: OfferDlc = read(data)?;
+    to_json(&offer)
+}
+
+/// Python module exposing the bindings.
+#[pymodule]
+fn dlc_py(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(evaluate_payout_function, m)?)?;
+    m.add_function(wrap_pyfunction!(get_range_payouts, m)?)?;
+    m.add_function(wrap_pyfunction!(serialize_payout_function, m)?)?;
+    m.add_function(wrap_pyfunction!(deserialize_payout_function, m)?)?;
+    m.add_function(wrap_pyfunction!(serialize_offer, m)?)?;
+    m.add_function(wrap_pyfunction!(deserialize_offer, m)?)?;
+    Ok(())
+}
+
+fn read<T: Readable>(data: &[u8]) -> PyResult<T> {
+    Readable::read(&mut std::io::Cursor::new(data))
+        .map_err(|e| PyValueError::new_err(format!("Invalid data: {:?}", e)))
+}
+
+fn from_json<'a, T: serde::Deserialize<'a>>(json: &'a str) -> PyResult<T> {
+    serde_json::from_str(json).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> PyResult<String> {
+    serde_json::to_string(value).map_err(|e| PyValueError::new_err(e.to_string()))
+}
@@ -0,0 +1,4 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/dlc.proto").expect("Error compiling protobuf definitions");
+}
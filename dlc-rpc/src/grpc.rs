@@ -0,0 +1,242 @@
+//! gRPC service, defined in `proto/dlc.proto`, exposing the same methods as
+//! the JSON-RPC server. Requests must carry the token given to the service in
+//! an `authorization` metadata entry of the form `Bearer <token>`. As the
+//! operations of the manager are blocking, the service must be run on a
+//! multi-threaded tokio runtime.
+
+use crate::{constant_time_eq, encode_message, RpcError, INVALID_PARAMS};
+use bitcoin::secp256k1::PublicKey;
+use dlc_manager::contract::contract_input::ContractInput;
+use dlc_manager::error::Error;
+use dlc_manager::manager::Manager;
+use dlc_manager::{Blockchain, Oracle, Storage, TemporaryContractId, Time, Wallet};
+use dlc_messages::Message;
+use log::warn;
+use std::net::SocketAddr;
+use std::ops::{Deref, DerefMut};
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::task::block_in_place;
+use tonic::service::interceptor::InterceptedService;
+use tonic::service::Interceptor;
+use tonic::{Request, Response, Status};
+
+/// The types generated from the protobuf definitions.
+#[allow(missing_docs)]
+pub mod proto {
+    tonic::include_proto!("dlcrpc");
+}
+
+use proto::dlc_server::{Dlc, DlcServer};
+use proto::{
+    AcceptRequest, AcceptResponse, CloseRequest, ContractSummary, ListContractsRequest,
+    ListContractsResponse, OfferRequest, OfferResponse, ProcessMessageRequest,
+    ProcessMessageResponse,
+};
+
+/// The address on which the service is served if none is given.
+pub const DEFAULT_GRPC_ADDRESS: &str = "127.0.0.1:9346";
+
+impl From<RpcError> for Status {
+    fn from(e: RpcError) -> Self {
+        match e.code {
+            INVALID_PARAMS => Status::invalid_argument(e.message),
+            _ => Status::internal(e.message),
+        }
+    }
+}
+
+fn to_status(e: Error) -> Status {
+    match e {
+        Error::InvalidParameters(_) => Status::invalid_argument(e.to_string()),
+        Error::InvalidState => Status::failed_precondition(e.to_string()),
+        _ => Status::internal(e.to_string()),
+    }
+}
+
+fn parse_public_key(public_key: &str) -> Result<PublicKey, Status> {
+    PublicKey::from_str(public_key)
+        .map_err(|_| Status::invalid_argument("Invalid counter party public key."))
+}
+
+/// gRPC service driving a [`Manager`].
+pub struct GrpcService<W: Deref, B: Deref, S: DerefMut, O: Deref, T: Deref>
+where
+    W::Target: Wallet,
+    B::Target: Blockchain,
+    S::Target: Storage,
+    O::Target: Oracle,
+    T::Target: Time,
+{
+    manager: Arc<Manager<W, B, S, O, T>>,
+}
+
+impl<W: Deref, B: Deref, S: DerefMut, O: Deref, T: Deref> GrpcService<W, B, S, O, T>
+where
+    W::Target: Wallet,
+    B::Target: Blockchain,
+    S::Target: Storage,
+    O::Target: Oracle,
+    T::Target: Time,
+{
+    /// Creates a new service driving the given manager.
+    pub fn new(manager: Arc<Manager<W, B, S, O, T>>) -> Self {
+        GrpcService { manager }
+    }
+
+    /// Returns the service, rejecting the requests that do not carry the
+    /// given token, to be added to a [`tonic::transport::Server`] (e.g. one
+    /// configured with TLS). Panics if the token is empty.
+    pub fn with_auth_token(
+        self,
+        auth_token: String,
+    ) -> InterceptedService<DlcServer<Self>, impl Interceptor>
+    where
+        Self: Dlc,
+    {
+        assert!(!auth_token.is_empty(), "Authentication token must be set");
+        let expected = format!("Bearer {}", auth_token);
+        DlcServer::with_interceptor(self, move |request: Request<()>| {
+            match request.metadata().get("authorization") {
+                Some(value) if constant_time_eq(value.as_bytes(), expected.as_bytes()) => {
+                    Ok(request)
+                }
+                _ => Err(Status::unauthenticated("Invalid token.")),
+            }
+        })
+    }
+
+    /// Serves the service, without TLS, on the given address or on
+    /// [`DEFAULT_GRPC_ADDRESS`] if none is given, rejecting the requests that
+    /// do not carry the given token.
+    pub async fn serve(
+        self,
+        auth_token: String,
+        address: Option<SocketAddr>,
+    ) -> Result<(), tonic::transport::Error>
+    where
+        Self: Dlc,
+    {
+        let address = address.unwrap_or_else(|| {
+            DEFAULT_GRPC_ADDRESS
+                .parse()
+                .expect("Default address to be valid")
+        });
+        if !address.ip().is_loopback() {
+            warn!(
+                "gRPC service listening on {}, connections are not encrypted",
+                address
+            );
+        }
+        tonic::transport::Server::builder()
+            .add_service(self.with_auth_token(auth_token))
+            .serve(address)
+            .await
+    }
+
+    fn get_contract_summaries(&self) -> Result<ListContractsResponse, Status> {
+        let contracts = self
+            .manager
+            .get_store()
+            .get_contracts()
+            .map_err(to_status)?;
+        Ok(ListContractsResponse {
+            contracts: contracts
+                .iter()
+                .map(|c| ContractSummary {
+                    temporary_contract_id: c.get_temporary_id().to_string(),
+                    contract_id: c.get_id().map(|x| x.to_string()).unwrap_or_default(),
+                    counter_party: c.get_offered_contract().counter_party.to_string(),
+                    state: c.get_state_name().to_string(),
+                    zero_conf: c.is_zero_conf(),
+                })
+                .collect(),
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl<W: Deref, B: Deref, S: DerefMut, O: Deref, T: Deref> Dlc for GrpcService<W, B, S, O, T>
+where
+    W: 'static,
+    B: 'static,
+    S: 'static,
+    O: 'static,
+    T: 'static,
+    W::Target: Wallet,
+    B::Target: Blockchain,
+    S::Target: Storage,
+    O::Target: Oracle,
+    T::Target: Time,
+    Manager<W, B, S, O, T>: Send + Sync,
+{
+    async fn offer(
+        &self,
+        request: Request<OfferRequest>,
+    ) -> Result<Response<OfferResponse>, Status> {
+        let request = request.into_inner();
+        let counter_party = parse_public_key(&request.counter_party)?;
+        let contract_input: ContractInput = serde_json::from_str(&request.contract_input)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let offer = block_in_place(|| self.manager.send_offer(&contract_input, counter_party))
+            .map_err(to_status)?;
+        let temporary_id = offer
+            .get_temporary_contract_id()
+            .map_err(|e| Status::internal(e.to_string()))?;
+        Ok(Response::new(OfferResponse {
+            temporary_contract_id: temporary_id.to_string(),
+            message: encode_message(&Message::Offer(offer)),
+        }))
+    }
+
+    async fn accept(
+        &self,
+        request: Request<AcceptRequest>,
+    ) -> Result<Response<AcceptResponse>, Status> {
+        let temporary_id = TemporaryContractId::from_str(&request.get_ref().temporary_contract_id)
+            .map_err(|_| Status::invalid_argument("Invalid contract id."))?;
+        let (contract_id, counter_party, accept) =
+            block_in_place(|| self.manager.accept_contract_offer(&temporary_id))
+                .map_err(to_status)?;
+        Ok(Response::new(AcceptResponse {
+            contract_id: contract_id.to_string(),
+            counter_party: counter_party.to_string(),
+            message: encode_message(&Message::Accept(accept)),
+        }))
+    }
+
+    async fn process_message(
+        &self,
+        request: Request<ProcessMessageRequest>,
+    ) -> Result<Response<ProcessMessageResponse>, Status> {
+        let request = request.into_inner();
+        let counter_party = parse_public_key(&request.counter_party)?;
+        let message = self
+            .manager
+            .decode_dlc_message(&request.message, &counter_party)
+            .map_err(to_status)?;
+        let reply = block_in_place(|| self.manager.on_dlc_message(&message, counter_party))
+            .map_err(to_status)?;
+        Ok(Response::new(ProcessMessageResponse {
+            message: reply.map(|x| encode_message(&x)).unwrap_or_default(),
+        }))
+    }
+
+    async fn list_contracts(
+        &self,
+        _: Request<ListContractsRequest>,
+    ) -> Result<Response<ListContractsResponse>, Status> {
+        block_in_place(|| self.get_contract_summaries()).map(Response::new)
+    }
+
+    async fn close(
+        &self,
+        _: Request<CloseRequest>,
+    ) -> Result<Response<ListContractsResponse>, Status> {
+        block_in_place(|| {
+            self.manager.periodic_check().map_err(to_status)?;
+            self.get_contract_summaries()
+        })
+        .map(Response::new)
+    }
+}
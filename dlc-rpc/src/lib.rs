@@ -0,0 +1,460 @@
+//! # dlc-rpc
+//! JSON-RPC 2.0 server exposing the API of the dlc-manager, so that
+//! applications written in other languages can drive a DLC node. Requests and
+//! responses are exchanged over TCP, one JSON object per line, and requests
+//! longer than the maximum request size close the connection. The server
+//! listens on localhost unless given another address, and does not encrypt
+//! the connections, which should be tunneled (e.g. over SSH or a TLS proxy)
+//! when accessed remotely. With the `grpc` feature, the same methods are also
+//! available as a gRPC service (see the `grpc` module).
+//!
+//! Each connection must first be authenticated by calling `authenticate`
+//! with `{"token"}` set to the token given to the server, other methods
+//! failing until it is. A connection on which authentication fails is closed.
+//!
+//! DLC messages are passed as hex strings containing their wire encoding
+//! prefixed by their two bytes type, and must be relayed to the counter party
+//! by the application. The following methods are available:
+//!
+//! * `offer`: `{"counterParty", "contractInput"}` creates an offer and returns
+//! `{"temporaryContractId", "message"}`.
+//! * `accept`: `{"temporaryContractId"}` accepts a received offer and returns
+//! `{"contractId", "counterParty", "message"}`.
+//! * `processMessage`: `{"counterParty", "message"}` processes a message
+//! received from the counter party and returns `{"message"}`, set to the reply
+//! to send back if any.
+//! * `listContracts`: returns the list of contracts with their state.
+//! * `close`: checks the contracts, closing the ones for which an attestation
+//! is available, and returns the list of contracts.
+
+#![crate_name = "dlc_rpc"]
+// Coding conventions
+#![deny(non_upper_case_globals)]
+#![deny(non_camel_case_types)]
+#![deny(non_snake_case)]
+#![deny(unused_mut)]
+#![deny(dead_code)]
+#![deny(unused_imports)]
+#![deny(missing_docs)]
+
+extern crate bitcoin;
+extern crate dlc_manager;
+extern crate dlc_messages;
+extern crate lightning;
+extern crate log;
+#[cfg(test)]
+extern crate mocks;
+#[cfg(feature = "grpc")]
+extern crate prost;
+extern crate serde_json;
+#[cfg(feature = "grpc")]
+extern crate tokio;
+#[cfg(feature = "grpc")]
+extern crate tonic;
+
+use bitcoin::hashes::hex::{FromHex, ToHex};
+use bitcoin::secp256k1::PublicKey;
+use dlc_manager::contract::contract_input::ContractInput;
+use dlc_manager::manager::Manager;
+use dlc_manager::{Blockchain, Oracle, Storage, TemporaryContractId, Time, Wallet};
+use dlc_messages::Message;
+use lightning::ln::wire::Type;
+use lightning::util::ser::Writeable;
+use log::{error, info, warn};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::ops::{Deref, DerefMut};
+use std::str::FromStr;
+use std::sync::Arc;
+
+#[cfg(feature = "grpc")]
+pub mod grpc;
+
+/// The address on which the server listens if none is given.
+pub const DEFAULT_RPC_ADDRESS: &str = "127.0.0.1:9345";
+/// The default maximum size in bytes of a request.
+pub const DEFAULT_MAX_REQUEST_SIZE: usize = 8 * 1024 * 1024;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const MANAGER_ERROR: i64 = -32000;
+const UNAUTHORIZED: i64 = -32001;
+
+/// An error returned to the client, with its JSON-RPC error code.
+#[derive(Debug)]
+pub struct RpcError {
+    /// The JSON-RPC error code.
+    pub code: i64,
+    /// A description of the error.
+    pub message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: &str) -> Self {
+        RpcError {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<dlc_manager::error::Error> for RpcError {
+    fn from(e: dlc_manager::error::Error) -> Self {
+        RpcError {
+            code: MANAGER_ERROR,
+            message: e.to_string(),
+        }
+    }
+}
+
+/// Server handling JSON-RPC requests using a [`Manager`].
+pub struct RpcServer<W: Deref, B: Deref, S: DerefMut, O: Deref, T: Deref>
+where
+    W::Target: Wallet,
+    B::Target: Blockchain,
+    S::Target: Storage,
+    O::Target: Oracle,
+    T::Target: Time,
+{
+    manager: Arc<Manager<W, B, S, O, T>>,
+    auth_token: String,
+    max_request_size: usize,
+}
+
+impl<W: Deref, B: Deref, S: DerefMut, O: Deref, T: Deref> RpcServer<W, B, S, O, T>
+where
+    W::Target: Wallet,
+    B::Target: Blockchain,
+    S::Target: Storage,
+    O::Target: Oracle,
+    T::Target: Time,
+{
+    /// Creates a new server driving the given manager, whose clients must
+    /// authenticate using the given token. Panics if the token is empty.
+    pub fn new(manager: Arc<Manager<W, B, S, O, T>>, auth_token: String) -> Self {
+        assert!(!auth_token.is_empty(), "Authentication token must be set");
+        RpcServer {
+            manager,
+            auth_token,
+            max_request_size: DEFAULT_MAX_REQUEST_SIZE,
+        }
+    }
+
+    /// Sets the maximum size in bytes of a request, defaulting to
+    /// [`DEFAULT_MAX_REQUEST_SIZE`].
+    pub fn set_max_request_size(&mut self, max_request_size: usize) {
+        self.max_request_size = max_request_size;
+    }
+
+    /// Listens on the given address, or on [`DEFAULT_RPC_ADDRESS`] if none is
+    /// given, and serves the accepted connections (see [`RpcServer::serve`]).
+    pub fn listen(self: Arc<Self>, address: Option<&str>) -> std::io::Result<()>
+    where
+        Self: Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind(address.unwrap_or(DEFAULT_RPC_ADDRESS))?;
+        self.serve(listener)
+    }
+
+    /// Accepts connections on the given listener, handling each of them on
+    /// its own thread. Only returns if accepting a connection fails.
+    pub fn serve(self: Arc<Self>, listener: TcpListener) -> std::io::Result<()>
+    where
+        Self: Send + Sync + 'static,
+    {
+        let local_addr = listener.local_addr()?;
+        if !local_addr.ip().is_loopback() {
+            warn!(
+                "RPC server listening on {}, connections are not encrypted",
+                local_addr
+            );
+        }
+        loop {
+            let (stream, addr) = listener.accept()?;
+            info!("Accepted RPC connection from {}", addr);
+            let server = self.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = server.handle_connection(stream) {
+                    error!("Error handling RPC connection: {}", e);
+                }
+            });
+        }
+    }
+
+    fn handle_connection(&self, stream: TcpStream) -> std::io::Result<()> {
+        let mut writer = stream.try_clone()?;
+        let mut reader = BufReader::new(stream);
+        let mut authenticated = false;
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            // At most one byte more than the maximum request size is read, so
+            // that longer requests are detected without being buffered.
+            let read = (&mut reader)
+                .take(self.max_request_size as u64 + 1)
+                .read_until(b'\n', &mut line)?;
+            if read == 0 {
+                return Ok(());
+            }
+            if line.last() == Some(&b'\n') {
+                line.pop();
+            } else if line.len() > self.max_request_size {
+                let response = get_response(
+                    Value::Null,
+                    Err(RpcError::new(INVALID_REQUEST, "Request too large.")),
+                );
+                writer.write_all(response.as_bytes())?;
+                writer.write_all(b"\n")?;
+                return Ok(());
+            }
+            let request = String::from_utf8_lossy(&line);
+            if request.trim().is_empty() {
+                continue;
+            }
+            let (response, close) = self.handle_request(&request, &mut authenticated);
+            writer.write_all(response.as_bytes())?;
+            writer.write_all(b"\n")?;
+            if close {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Handles a single JSON-RPC request received on a connection, returning
+    /// the serialized response and whether the connection should be closed.
+    fn handle_request(&self, request: &str, authenticated: &mut bool) -> (String, bool) {
+        let request: Value = match serde_json::from_str(request) {
+            Ok(request) => request,
+            Err(e) => {
+                let response =
+                    get_response(Value::Null, Err(RpcError::new(PARSE_ERROR, &e.to_string())));
+                return (response, false);
+            }
+        };
+
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+        let (result, close) = match request.get("method").and_then(|x| x.as_str()) {
+            Some("authenticate") => {
+                *authenticated = get_str_param(&params, "token")
+                    .map(|token| constant_time_eq(token.as_bytes(), self.auth_token.as_bytes()))
+                    .unwrap_or(false);
+                if *authenticated {
+                    (Ok(Value::Bool(true)), false)
+                } else {
+                    (Err(RpcError::new(UNAUTHORIZED, "Invalid token.")), true)
+                }
+            }
+            Some(_) if !*authenticated => (
+                Err(RpcError::new(UNAUTHORIZED, "Not authenticated.")),
+                false,
+            ),
+            Some(method) => (self.dispatch(method, &params), false),
+            None => (
+                Err(RpcError::new(INVALID_REQUEST, "Missing method.")),
+                false,
+            ),
+        };
+
+        (get_response(id, result), close)
+    }
+
+    fn dispatch(&self, method: &str, params: &Value) -> Result<Value, RpcError> {
+        match method {
+            "offer" => {
+                let counter_party = get_public_key_param(params, "counterParty")?;
+                let contract_input: ContractInput = serde_json::from_value(
+                    params.get("contractInput").cloned().unwrap_or(Value::Null),
+                )
+                .map_err(|e| RpcError::new(INVALID_PARAMS, &e.to_string()))?;
+                let offer = self.manager.send_offer(&contract_input, counter_party)?;
+                let temporary_id = offer
+                    .get_temporary_contract_id()
+                    .map_err(|e| RpcError::new(MANAGER_ERROR, &e.to_string()))?;
+                Ok(json!({
+                    "temporaryContractId": temporary_id.to_string(),
+                    "message": encode_message(&Message::Offer(offer)).to_hex(),
+                }))
+            }
+            "accept" => {
+                let temporary_id =
+                    TemporaryContractId::from_str(get_str_param(params, "temporaryContractId")?)
+                        .map_err(|_| RpcError::new(INVALID_PARAMS, "Invalid contract id."))?;
+                let (contract_id, counter_party, accept) =
+                    self.manager.accept_contract_offer(&temporary_id)?;
+                Ok(json!({
+                    "contractId": contract_id.to_string(),
+                    "counterParty": counter_party.to_string(),
+                    "message": encode_message(&Message::Accept(accept)).to_hex(),
+                }))
+            }
+            "processMessage" => {
+                let counter_party = get_public_key_param(params, "counterParty")?;
+                let buf = Vec::<u8>::from_hex(get_str_param(params, "message")?)
+                    .map_err(|_| RpcError::new(INVALID_PARAMS, "Invalid message hex."))?;
+                let message = self.manager.decode_dlc_message(&buf, &counter_party)?;
+                let reply = self.manager.on_dlc_message(&message, counter_party)?;
+                Ok(json!({ "message": reply.map(|x| encode_message(&x).to_hex()) }))
+            }
+            "listContracts" => self.list_contracts(),
+            "close" => {
+                self.manager.periodic_check()?;
+                self.list_contracts()
+            }
+            _ => Err(RpcError::new(METHOD_NOT_FOUND, "Unknown method.")),
+        }
+    }
+
+    fn list_contracts(&self) -> Result<Value, RpcError> {
+        let contracts = self.manager.get_store().get_contracts()?;
+        Ok(Value::Array(
+            contracts
+                .iter()
+                .map(|c| {
+                    json!({
+                        "temporaryContractId": c.get_temporary_id().to_string(),
+                        "contractId": c.get_id().map(|x| x.to_string()),
+                        "counterParty": c.get_offered_contract().counter_party.to_string(),
+                        "state": c.get_state_name(),
+                        "zeroConf": c.is_zero_conf(),
+                    })
+                })
+                .collect(),
+        ))
+    }
+}
+
+fn get_response(id: Value, result: Result<Value, RpcError>) -> String {
+    let response = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": e.code, "message": e.message },
+        }),
+    };
+    response.to_string()
+}
+
+/// Compares the given byte strings in a time independent of their content.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn get_str_param<'a>(params: &'a Value, name: &str) -> Result<&'a str, RpcError> {
+    params
+        .get(name)
+        .and_then(|x| x.as_str())
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, &format!("Missing parameter {}.", name)))
+}
+
+fn get_public_key_param(params: &Value, name: &str) -> Result<PublicKey, RpcError> {
+    PublicKey::from_str(get_str_param(params, name)?)
+        .map_err(|_| RpcError::new(INVALID_PARAMS, &format!("Invalid public key {}.", name)))
+}
+
+/// Returns the wire encoding of the given message prefixed by its type.
+fn encode_message(msg: &Message) -> Vec<u8> {
+    let mut buf = msg.type_id().to_be_bytes().to_vec();
+    buf.extend(msg.encode());
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::network::constants::Network;
+    use mocks::memory_storage_provider::MemoryStorage;
+    use mocks::mock_blockchain::MockBlockchain;
+    use mocks::mock_oracle_provider::MockOracle;
+    use mocks::mock_time::MockTime;
+    use mocks::mock_wallet::MockWallet;
+    use std::collections::HashMap;
+
+    type TestServer = RpcServer<
+        Arc<MockWallet>,
+        Arc<MockBlockchain>,
+        Box<MemoryStorage>,
+        Arc<MockOracle>,
+        Arc<MockTime>,
+    >;
+
+    fn get_server() -> TestServer {
+        let manager = Manager::new(
+            Arc::new(MockWallet::new(Network::Regtest)),
+            Arc::new(MockBlockchain::new(Network::Regtest, 600)),
+            Box::new(MemoryStorage::new()),
+            HashMap::new(),
+            Arc::new(MockTime {}),
+        );
+        RpcServer::new(Arc::new(manager), "token".to_string())
+    }
+
+    fn get_result(response: &str) -> Result<Value, i64> {
+        let response: Value = serde_json::from_str(response).unwrap();
+        match response.get("error") {
+            Some(error) => Err(error["code"].as_i64().unwrap()),
+            None => Ok(response["result"].clone()),
+        }
+    }
+
+    #[test]
+    fn requests_require_authentication_test() {
+        let server = get_server();
+        let mut authenticated = false;
+        let list_contracts = r#"{"jsonrpc": "2.0", "id": 1, "method": "listContracts"}"#;
+
+        let (response, close) = server.handle_request(list_contracts, &mut authenticated);
+        assert_eq!(Err(UNAUTHORIZED), get_result(&response));
+        assert!(!close);
+
+        let (response, close) = server.handle_request(
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "authenticate", "params": {"token": "token"}}"#,
+            &mut authenticated,
+        );
+        assert_eq!(Ok(Value::Bool(true)), get_result(&response));
+        assert!(!close);
+
+        let (response, _) = server.handle_request(list_contracts, &mut authenticated);
+        assert_eq!(Ok(Value::Array(Vec::new())), get_result(&response));
+    }
+
+    #[test]
+    fn invalid_token_closes_connection_test() {
+        let server = get_server();
+        let mut authenticated = false;
+
+        let (response, close) = server.handle_request(
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "authenticate", "params": {"token": "tokem"}}"#,
+            &mut authenticated,
+        );
+
+        assert_eq!(Err(UNAUTHORIZED), get_result(&response));
+        assert!(close);
+        assert!(!authenticated);
+    }
+
+    #[test]
+    fn too_large_request_closes_connection_test() {
+        let mut server = get_server();
+        server.set_max_request_size(64);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = Arc::new(server);
+        std::thread::spawn(move || server.serve(listener));
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        // One byte more than the maximum, all of which is read by the server
+        // before it closes the connection.
+        stream.write_all(&[b'a'; 65]).unwrap();
+        let mut response = String::new();
+        BufReader::new(stream)
+            .read_to_string(&mut response)
+            .unwrap();
+
+        assert_eq!(Err(INVALID_REQUEST), get_result(response.trim()));
+    }
+}
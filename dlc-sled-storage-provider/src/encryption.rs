@@ -0,0 +1,104 @@
+//! Encryption of the values stored in the database, so that the details of
+//! the contracts (including adaptor signatures and counter party information)
+//! are not readable by someone getting access to the database files.
+
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use dlc_manager::error::Error;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+
+const NONCE_SIZE: usize = 12;
+const PBKDF2_ROUNDS: u32 = 100_000;
+const SEED_DERIVATION_TAG: &[u8] = b"dlc-sled-storage-provider/encryption";
+
+/// Key used to encrypt the values stored in the database using
+/// ChaCha20-Poly1305.
+#[derive(Clone)]
+pub struct StorageEncryptionKey {
+    cipher: ChaCha20Poly1305,
+}
+
+impl StorageEncryptionKey {
+    /// Derives an encryption key from the given seed, which must be kept
+    /// secret and have enough entropy (e.g. the seed of the wallet).
+    pub fn from_seed(seed: &[u8]) -> Self {
+        let mut mac = Hmac::<Sha256>::new_from_slice(SEED_DERIVATION_TAG)
+            .expect("HMAC to accept keys of any size");
+        mac.update(seed);
+        let key = mac.finalize().into_bytes();
+        Self::from_key_bytes(&key)
+    }
+
+    /// Derives an encryption key from the given passphrase using
+    /// PBKDF2-HMAC-SHA256. The same salt must be provided every time the
+    /// database is opened.
+    pub fn from_passphrase(passphrase: &str, salt: &[u8]) -> Self {
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2::<Hmac<Sha256>>(passphrase.as_bytes(), salt, PBKDF2_ROUNDS, &mut key);
+        Self::from_key_bytes(&key)
+    }
+
+    fn from_key_bytes(key: &[u8]) -> Self {
+        StorageEncryptionKey {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(key)),
+        }
+    }
+
+    /// Encrypts the given value with a random nonce, authenticating `aad` with
+    /// it. The returned buffer contains the nonce followed by the cipher text.
+    /// Other storages can use it to encrypt their records with the same key.
+    pub fn encrypt(&self, aad: &[u8], value: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut nonce = [0u8; NONCE_SIZE];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let cipher_text = self
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: value, aad })
+            .map_err(|_| Error::StorageError("Could not encrypt value".to_string()))?;
+        let mut res = Vec::with_capacity(NONCE_SIZE + cipher_text.len());
+        res.extend_from_slice(&nonce);
+        res.extend_from_slice(&cipher_text);
+        Ok(res)
+    }
+
+    /// Decrypts a value produced by [`StorageEncryptionKey::encrypt`], failing
+    /// if the key or the associated data do not match.
+    pub fn decrypt(&self, aad: &[u8], value: &[u8]) -> Result<Vec<u8>, Error> {
+        if value.len() < NONCE_SIZE {
+            return Err(Error::StorageError(
+                "Encrypted value is too short".to_string(),
+            ));
+        }
+        let (nonce, cipher_text) = value.split_at(NONCE_SIZE);
+        self.cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: cipher_text,
+                    aad,
+                },
+            )
+            .map_err(|_| Error::StorageError("Could not decrypt value".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decrypt_with_other_key_or_aad_fails() {
+        let key = StorageEncryptionKey::from_seed(&[1u8; 32]);
+        let encrypted = key.encrypt(b"id", b"contract").unwrap();
+
+        assert_eq!(
+            b"contract".to_vec(),
+            key.decrypt(b"id", &encrypted).unwrap()
+        );
+        assert!(key.decrypt(b"other_id", &encrypted).is_err());
+        assert!(StorageEncryptionKey::from_seed(&[2u8; 32])
+            .decrypt(b"id", &encrypted)
+            .is_err());
+    }
+}
@@ -11,22 +11,58 @@
 #![deny(unused_imports)]
 #![deny(missing_docs)]
 
+extern crate chacha20poly1305;
 extern crate dlc_manager;
+extern crate hmac;
+extern crate pbkdf2;
+extern crate rand;
+extern crate sha2;
 extern crate sled;
 
+mod encryption;
+
+pub use encryption::StorageEncryptionKey;
+
+use dlc_manager::broadcast::{BroadcastKind, PendingBroadcast};
 use dlc_manager::contract::accepted_contract::AcceptedContract;
 use dlc_manager::contract::offered_contract::OfferedContract;
-use dlc_manager::contract::ser::Serializable;
+use dlc_manager::contract::ser::{
+    deserialize_adaptor_infos, serialize_adaptor_infos, Serializable, VersionedSerializable,
+    CONTRACT_VERSION,
+};
 use dlc_manager::contract::signed_contract::SignedContract;
-use dlc_manager::contract::{ClosedContract, Contract, FailedAcceptContract, FailedSignContract};
-use dlc_manager::{error::Error, ContractId, Storage};
-use sled::Db;
+use dlc_manager::contract::{
+    AdaptorInfo, ArchivedContract, ClosedContract, Contract, ContractHistoryEntry, ContractState,
+    FailedAcceptContract, FailedSignContract,
+};
+use dlc_manager::{error::Error, ContractId, Storage, TemporaryContractId};
+use sled::{Db, Transactional, Tree};
+use std::borrow::Cow;
 use std::convert::TryInto;
 use std::io::{Cursor, Read};
 
+const HISTORY_TREE: &str = "contract_history";
+const ARCHIVE_TREE: &str = "archived_contracts";
+const ADAPTOR_INFO_TREE: &str = "adaptor_infos";
+const PENDING_BROADCAST_TREE: &str = "pending_broadcasts";
+const METADATA_TREE: &str = "metadata";
+const VERSION_KEY: &[u8] = b"version";
+/// Key of the metadata tree under which encrypted databases store
+/// [`KEY_CHECK_VALUE`] encrypted with their key, so that opening them with a
+/// wrong key, or without key, is detected before reading any contract.
+const KEY_CHECK_KEY: &[u8] = b"key_check";
+const KEY_CHECK_VALUE: &[u8] = b"dlc-sled-storage-provider";
+/// The current version of the layout of the database, kept in the metadata
+/// tree. Databases without version were created before versions were
+/// introduced, and store contracts serialized with version 0 of their
+/// serialization under contract ids computed from the fund transaction id in
+/// serialization order.
+const STORAGE_VERSION: u8 = 1;
+
 /// Implementation of Storage interface using the sled DB backend.
 pub struct SledStorageProvider {
     db: Db,
+    encryption_key: Option<StorageEncryptionKey>,
 }
 
 macro_rules! convertible_enum {
@@ -67,19 +103,27 @@ convertible_enum!(
         FailedAccept,
         FailedSign,
         Refunded,
+        Renewed,
+        RefundedDueToNoAttestation,
     }
 );
 
 fn get_prefix(contract: &Contract) -> u8 {
-    let prefix = match contract {
-        Contract::Offered(_) => ContractPrefix::Offered,
-        Contract::Accepted(_) => ContractPrefix::Accepted,
-        Contract::Signed(_) => ContractPrefix::Signed,
-        Contract::Confirmed(_) => ContractPrefix::Confirmed,
-        Contract::Closed(_) => ContractPrefix::Closed,
-        Contract::FailedAccept(_) => ContractPrefix::FailedAccept,
-        Contract::FailedSign(_) => ContractPrefix::FailedSign,
-        Contract::Refunded(_) => ContractPrefix::Refunded,
+    get_state_prefix(contract.get_state())
+}
+
+fn get_state_prefix(state: ContractState) -> u8 {
+    let prefix = match state {
+        ContractState::Offered => ContractPrefix::Offered,
+        ContractState::Accepted => ContractPrefix::Accepted,
+        ContractState::Signed => ContractPrefix::Signed,
+        ContractState::Confirmed => ContractPrefix::Confirmed,
+        ContractState::Closed => ContractPrefix::Closed,
+        ContractState::FailedAccept => ContractPrefix::FailedAccept,
+        ContractState::FailedSign => ContractPrefix::FailedSign,
+        ContractState::Refunded => ContractPrefix::Refunded,
+        ContractState::Renewed => ContractPrefix::Renewed,
+        ContractState::RefundedDueToNoAttestation => ContractPrefix::RefundedDueToNoAttestation,
     };
     prefix.into()
 }
@@ -92,35 +136,245 @@ where
 }
 
 impl SledStorageProvider {
-    /// Creates a new instance of a SledStorageProvider.
-    pub fn new(path: &str) -> Result<Self, sled::Error> {
-        Ok(SledStorageProvider {
-            db: sled::open(path)?,
-        })
+    /// Creates a new instance of a SledStorageProvider, upgrading the
+    /// database if it was created by a previous version.
+    pub fn new(path: &str) -> Result<Self, Error> {
+        let storage = SledStorageProvider {
+            db: sled::open(path).map_err(to_storage_error)?,
+            encryption_key: None,
+        };
+        storage.check_encryption_key()?;
+        storage.migrate()?;
+        Ok(storage)
     }
 
-    fn get_contracts_with_prefix<T: Serializable>(&self, prefix: u8) -> Result<Vec<T>, Error> {
-        let iter = self.db.iter();
-        iter.values()
-            .filter_map(|res| {
-                let value = res.unwrap();
-                let mut cursor = Cursor::new(&value);
-                let mut pref = [0u8; 1];
-                cursor.read_exact(&mut pref).expect("Error reading prefix");
-                if pref[0] == prefix {
-                    Some(Ok(T::deserialize(&mut cursor).ok()?))
-                } else {
-                    None
+    /// Creates a new instance of a SledStorageProvider encrypting the stored
+    /// contracts and history entries with the given key. Only the state of
+    /// the contracts is left in clear so that they can be filtered without
+    /// being decrypted. The database must always be opened with the same key,
+    /// opening it with another key or opening an unencrypted database that
+    /// already contains contracts returns an error.
+    pub fn new_encrypted(path: &str, encryption_key: StorageEncryptionKey) -> Result<Self, Error> {
+        let storage = SledStorageProvider {
+            db: sled::open(path).map_err(to_storage_error)?,
+            encryption_key: Some(encryption_key),
+        };
+        storage.check_encryption_key()?;
+        storage.migrate()?;
+        Ok(storage)
+    }
+
+    /// Checks that the database is opened with the key it was encrypted with,
+    /// or without key if it is not encrypted. The check value is stored when
+    /// an empty database is first opened with a key.
+    fn check_encryption_key(&self) -> Result<(), Error> {
+        let metadata = self.metadata_tree()?;
+        let key_check = metadata.get(KEY_CHECK_KEY).map_err(to_storage_error)?;
+        match (&self.encryption_key, key_check) {
+            (None, None) => Ok(()),
+            (None, Some(_)) => Err(Error::StorageError("The database is encrypted".to_string())),
+            (Some(encryption_key), Some(key_check)) => {
+                match encryption_key.decrypt(KEY_CHECK_KEY, &key_check) {
+                    Ok(value) if value == KEY_CHECK_VALUE => Ok(()),
+                    _ => Err(Error::StorageError("Invalid encryption key".to_string())),
+                }
+            }
+            (Some(encryption_key), None) if self.db.is_empty() => {
+                metadata
+                    .insert(
+                        KEY_CHECK_KEY,
+                        encryption_key.encrypt(KEY_CHECK_KEY, KEY_CHECK_VALUE)?,
+                    )
+                    .map_err(to_storage_error)?;
+                Ok(())
+            }
+            (Some(_), None) => Err(Error::StorageError(
+                "The database is not encrypted".to_string(),
+            )),
+        }
+    }
+
+    /// Upgrades the records of a database created by a previous version to
+    /// the current version, and records the current version in the database.
+    fn migrate(&self) -> Result<(), Error> {
+        let metadata = self.metadata_tree()?;
+        let version = match metadata.get(VERSION_KEY).map_err(to_storage_error)? {
+            Some(version) if version.len() == 1 => version[0],
+            Some(_) => return Err(Error::StorageError("Invalid storage version".to_string())),
+            None if self.db.is_empty() => STORAGE_VERSION,
+            None => 0,
+        };
+        if version > STORAGE_VERSION {
+            return Err(Error::StorageError(format!(
+                "Unsupported storage version {}",
+                version
+            )));
+        }
+        let upgraded = if version == 0 {
+            self.upgrade_contracts(0)?
+        } else {
+            Vec::new()
+        };
+        // The upgraded contracts are written together with the version so
+        // that an interrupted migration is restarted from scratch.
+        let db: &Tree = &self.db;
+        (db, &metadata)
+            .transaction::<_, _, ::std::io::Error>(|(db, metadata)| {
+                for (key, _, _) in &upgraded {
+                    db.remove(&key[..])?;
                 }
+                for (_, new_key, value) in &upgraded {
+                    db.insert(&new_key[..], &value[..])?;
+                }
+                metadata.insert(VERSION_KEY, &[STORAGE_VERSION][..])?;
+                Ok(())
             })
-            .collect()
+            .map_err(to_storage_error)?;
+        Ok(())
+    }
+
+    /// Returns the contracts serialized with the given version, serialized
+    /// using the current one, together with the key under which they are
+    /// stored and the one under which they should be. Contracts that were
+    /// accepted are stored under their contract id, which is recomputed.
+    fn upgrade_contracts(&self, version: u8) -> Result<Vec<(Vec<u8>, Vec<u8>, Vec<u8>)>, Error> {
+        let records = self
+            .db
+            .iter()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(to_storage_error)?;
+        let mut upgraded = Vec::with_capacity(records.len());
+        for (key, value) in records {
+            let contract =
+                deserialize_contract_version(&self.decode_value(&key, &value)?, version)?;
+            let new_key = match contract.get_id() {
+                Some(id) => id.0,
+                None => contract.get_temporary_id().0,
+            };
+            let contract = self.split_adaptor_infos(&contract)?;
+            let value = self.encode_contract(&new_key, &contract)?;
+            upgraded.push((key.to_vec(), new_key.to_vec(), value));
+        }
+        Ok(upgraded)
+    }
+
+    /// Serializes the given contract, prefixed with its state, encrypting it
+    /// if an encryption key was provided. The key under which the value is
+    /// stored and the state prefix are authenticated together with it.
+    fn encode_contract(&self, key: &[u8], contract: &Contract) -> Result<Vec<u8>, Error> {
+        let serialized = serialize_contract(contract)?;
+        match &self.encryption_key {
+            Some(encryption_key) => {
+                let (prefix, value) = serialized.split_at(1);
+                let mut res = prefix.to_vec();
+                res.append(&mut encryption_key.encrypt(&get_aad(key, prefix), value)?);
+                Ok(res)
+            }
+            None => Ok(serialized),
+        }
+    }
+
+    /// Returns the state prefixed serialization of the contract stored under
+    /// the given key, decrypting it if an encryption key was provided.
+    fn decode_value<'a>(&self, key: &[u8], value: &'a [u8]) -> Result<Cow<'a, [u8]>, Error> {
+        match &self.encryption_key {
+            Some(encryption_key) => {
+                if value.is_empty() {
+                    return Err(Error::StorageError("Empty value".to_string()));
+                }
+                let (prefix, value) = value.split_at(1);
+                let mut res = prefix.to_vec();
+                res.append(&mut encryption_key.decrypt(&get_aad(key, prefix), value)?);
+                Ok(Cow::Owned(res))
+            }
+            None => Ok(Cow::Borrowed(value)),
+        }
+    }
+
+    fn decode_contract(&self, key: &[u8], value: &[u8]) -> Result<Contract, Error> {
+        deserialize_contract(&self.decode_value(key, value)?)
+    }
+
+    fn history_tree(&self) -> Result<Tree, Error> {
+        self.db.open_tree(HISTORY_TREE).map_err(to_storage_error)
+    }
+
+    fn archive_tree(&self) -> Result<Tree, Error> {
+        self.db.open_tree(ARCHIVE_TREE).map_err(to_storage_error)
+    }
+
+    fn adaptor_info_tree(&self) -> Result<Tree, Error> {
+        self.db
+            .open_tree(ADAPTOR_INFO_TREE)
+            .map_err(to_storage_error)
+    }
+
+    fn metadata_tree(&self) -> Result<Tree, Error> {
+        self.db.open_tree(METADATA_TREE).map_err(to_storage_error)
+    }
+
+    fn pending_broadcast_tree(&self) -> Result<Tree, Error> {
+        self.db
+            .open_tree(PENDING_BROADCAST_TREE)
+            .map_err(to_storage_error)
+    }
+
+    /// Stores the adaptor information of the given contract under its id if
+    /// it was not already, and returns the contract without it so that it is
+    /// not deserialized each time the contract is loaded. Adaptor information
+    /// does not change once a contract is accepted.
+    fn split_adaptor_infos(&self, contract: &Contract) -> Result<Contract, Error> {
+        let mut contract = contract.clone();
+        let (id, adaptor_infos) = match contract.get_accepted_contract_mut() {
+            Some(a) if !a.adaptor_infos.is_empty() => {
+                (a.get_contract_id(), std::mem::take(&mut a.adaptor_infos))
+            }
+            _ => return Ok(contract),
+        };
+        let tree = self.adaptor_info_tree()?;
+        if !tree.contains_key(&id).map_err(to_storage_error)? {
+            let serialized = serialize_adaptor_infos(&adaptor_infos)?;
+            let serialized = match &self.encryption_key {
+                Some(encryption_key) => encryption_key.encrypt(id.as_ref(), &serialized)?,
+                None => serialized,
+            };
+            tree.insert(&id, serialized).map_err(to_storage_error)?;
+        }
+        Ok(contract)
+    }
+
+    fn get_contracts_with_prefix<T: Serializable>(&self, prefix: u8) -> Result<Vec<T>, Error> {
+        let iter = self.db.iter();
+        iter.filter_map(|res| {
+            let (key, value) = res.unwrap();
+            if value.first() != Some(&prefix) {
+                return None;
+            }
+            let decoded = match self.decode_value(&key, &value) {
+                Ok(decoded) => decoded,
+                Err(e) => return Some(Err(e)),
+            };
+            let mut cursor = Cursor::new(&decoded[1..]);
+            Some(Ok(T::deserialize(&mut cursor).ok()?))
+        })
+        .collect()
     }
 }
 
 impl Storage for SledStorageProvider {
     fn get_contract(&self, contract_id: &ContractId) -> Result<Option<Contract>, Error> {
         match self.db.get(contract_id).map_err(to_storage_error)? {
-            Some(res) => Ok(Some(deserialize_contract(&res)?)),
+            Some(res) => Ok(Some(self.decode_contract(contract_id.as_ref(), &res)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn get_contract_by_temporary_id(
+        &self,
+        temporary_id: &TemporaryContractId,
+    ) -> Result<Option<Contract>, Error> {
+        match self.db.get(temporary_id).map_err(to_storage_error)? {
+            Some(res) => Ok(Some(self.decode_contract(temporary_id.as_ref(), &res)?)),
             None => Ok(None),
         }
     }
@@ -128,13 +382,51 @@ impl Storage for SledStorageProvider {
     fn get_contracts(&self) -> Result<Vec<Contract>, Error> {
         self.db
             .iter()
-            .values()
-            .map(|x| deserialize_contract(&x.unwrap()))
+            .map(|x| {
+                let (key, value) = x.unwrap();
+                self.decode_contract(&key, &value)
+            })
             .collect::<Result<Vec<Contract>, Error>>()
     }
 
+    fn get_contracts_by_state(
+        &self,
+        state: ContractState,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<Contract>, Error> {
+        let prefix = get_state_prefix(state);
+        // Only the prefix of the values is read to filter out contracts in
+        // other states, avoiding their deserialization.
+        self.db
+            .iter()
+            .filter(|res| match res {
+                Ok((_, value)) => value.first() == Some(&prefix),
+                Err(_) => true,
+            })
+            .skip(offset)
+            .take(limit)
+            .map(|res| {
+                let (key, value) = res.map_err(to_storage_error)?;
+                self.decode_contract(&key, &value)
+            })
+            .collect()
+    }
+
+    fn count_contracts_by_state(&self, state: ContractState) -> Result<usize, Error> {
+        let prefix = get_state_prefix(state);
+        let mut count = 0;
+        for res in self.db.iter().values() {
+            if res.map_err(to_storage_error)?.first() == Some(&prefix) {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
     fn create_contract(&mut self, contract: &OfferedContract) -> Result<(), Error> {
-        let serialized = serialize_contract(&Contract::Offered(contract.clone()))?;
+        let serialized =
+            self.encode_contract(contract.id.as_ref(), &Contract::Offered(contract.clone()))?;
         self.db
             .insert(&contract.id, serialized)
             .map_err(to_storage_error)?;
@@ -143,16 +435,31 @@ impl Storage for SledStorageProvider {
 
     fn delete_contract(&mut self, contract_id: &ContractId) -> Result<(), Error> {
         self.db.remove(&contract_id).map_err(to_storage_error)?;
+        self.adaptor_info_tree()?
+            .remove(&contract_id)
+            .map_err(to_storage_error)?;
+        Ok(())
+    }
+
+    fn delete_contract_by_temporary_id(
+        &mut self,
+        temporary_id: &TemporaryContractId,
+    ) -> Result<(), Error> {
+        self.db.remove(&temporary_id).map_err(to_storage_error)?;
         Ok(())
     }
 
     fn update_contract(&mut self, contract: &Contract) -> Result<(), Error> {
+        let key = match contract.get_id() {
+            Some(id) => id.0,
+            None => contract.get_temporary_id().0,
+        };
+        // The adaptor information is stored first so that it is available
+        // whenever the contract is.
+        let contract = &self.split_adaptor_infos(contract)?;
+        let serialized = self.encode_contract(&key, contract)?;
         self.db
-            .transaction(|db| {
-                let serialized = match serialize_contract(contract) {
-                    Ok(b) => b,
-                    Err(e) => sled::transaction::abort(e)?,
-                };
+            .transaction::<_, _, ::std::io::Error>(|db| {
                 match contract {
                     a @ Contract::Accepted(_) | a @ Contract::Signed(_) => {
                         db.remove(&a.get_temporary_id())?;
@@ -160,7 +467,7 @@ impl Storage for SledStorageProvider {
                     _ => {}
                 };
 
-                db.insert(&contract.get_id(), serialized)?;
+                db.insert(&key, &serialized[..])?;
                 Ok(())
             })
             .map_err(to_storage_error)?;
@@ -178,13 +485,172 @@ impl Storage for SledStorageProvider {
     fn get_contract_offers(&self) -> Result<Vec<OfferedContract>, Error> {
         self.get_contracts_with_prefix(ContractPrefix::Offered.into())
     }
+
+    fn add_contract_history_entry(
+        &mut self,
+        temporary_id: &TemporaryContractId,
+        entry: &ContractHistoryEntry,
+    ) -> Result<(), Error> {
+        let tree = self.history_tree()?;
+        // Entries are keyed by temporary id followed by a monotonic counter so
+        // that iterating over a prefix returns them in insertion order.
+        let counter = self.db.generate_id().map_err(to_storage_error)?;
+        let mut key = Vec::with_capacity(temporary_id.0.len() + 8);
+        key.extend_from_slice(&temporary_id.0);
+        key.extend_from_slice(&counter.to_be_bytes());
+        let serialized = match &self.encryption_key {
+            Some(encryption_key) => encryption_key.encrypt(&key, &entry.serialize()?)?,
+            None => entry.serialize()?,
+        };
+        tree.insert(key, serialized).map_err(to_storage_error)?;
+        Ok(())
+    }
+
+    fn get_contract_history(
+        &self,
+        temporary_id: &TemporaryContractId,
+    ) -> Result<Vec<ContractHistoryEntry>, Error> {
+        self.history_tree()?
+            .scan_prefix(temporary_id)
+            .map(|x| {
+                let (key, value) = x.map_err(to_storage_error)?;
+                let value = match &self.encryption_key {
+                    Some(encryption_key) => Cow::Owned(encryption_key.decrypt(&key, &value)?),
+                    None => Cow::Borrowed(&value[..]),
+                };
+                let mut cursor = Cursor::new(&value);
+                ContractHistoryEntry::deserialize(&mut cursor).map_err(to_storage_error)
+            })
+            .collect()
+    }
+
+    fn archive_contract(&mut self, contract_id: &ContractId) -> Result<(), Error> {
+        let archived = self
+            .get_contract(contract_id)?
+            .as_ref()
+            .and_then(ArchivedContract::from_contract)
+            .ok_or_else(|| {
+                Error::StorageError("No closed or refunded contract to archive".to_string())
+            })?;
+        let serialized = match &self.encryption_key {
+            Some(encryption_key) => {
+                encryption_key.encrypt(contract_id.as_ref(), &archived.serialize()?)?
+            }
+            None => archived.serialize()?,
+        };
+        // The record is inserted before the contract is removed so that an
+        // interrupted archival can simply be performed again.
+        self.archive_tree()?
+            .insert(contract_id, serialized)
+            .map_err(to_storage_error)?;
+        self.db.remove(contract_id).map_err(to_storage_error)?;
+        self.adaptor_info_tree()?
+            .remove(contract_id)
+            .map_err(to_storage_error)?;
+        Ok(())
+    }
+
+    fn get_adaptor_info(
+        &self,
+        contract_id: &ContractId,
+    ) -> Result<Option<Vec<AdaptorInfo>>, Error> {
+        match self
+            .adaptor_info_tree()?
+            .get(contract_id)
+            .map_err(to_storage_error)?
+        {
+            Some(value) => {
+                let value = match &self.encryption_key {
+                    Some(encryption_key) => {
+                        Cow::Owned(encryption_key.decrypt(contract_id.as_ref(), &value)?)
+                    }
+                    None => Cow::Borrowed(&value[..]),
+                };
+                let mut cursor = Cursor::new(&value);
+                Ok(Some(
+                    deserialize_adaptor_infos(&mut cursor).map_err(to_storage_error)?,
+                ))
+            }
+            // Contracts stored before adaptor information was kept separately
+            // still include it.
+            None => Ok(self
+                .get_contract(contract_id)?
+                .and_then(|c| c.get_accepted_contract().map(|a| a.adaptor_infos.clone()))),
+        }
+    }
+
+    fn get_archived_contracts(&self) -> Result<Vec<ArchivedContract>, Error> {
+        self.archive_tree()?
+            .iter()
+            .map(|x| {
+                let (key, value) = x.map_err(to_storage_error)?;
+                let value = match &self.encryption_key {
+                    Some(encryption_key) => Cow::Owned(encryption_key.decrypt(&key, &value)?),
+                    None => Cow::Borrowed(&value[..]),
+                };
+                let mut cursor = Cursor::new(&value);
+                ArchivedContract::deserialize(&mut cursor).map_err(to_storage_error)
+            })
+            .collect()
+    }
+
+    fn save_pending_broadcast(&mut self, pending: &PendingBroadcast) -> Result<(), Error> {
+        let key = get_pending_broadcast_key(&pending.contract_id, pending.kind)?;
+        let serialized = match &self.encryption_key {
+            Some(encryption_key) => encryption_key.encrypt(&key, &pending.serialize()?)?,
+            None => pending.serialize()?,
+        };
+        self.pending_broadcast_tree()?
+            .insert(key, serialized)
+            .map_err(to_storage_error)?;
+        Ok(())
+    }
+
+    fn delete_pending_broadcast(
+        &mut self,
+        contract_id: &ContractId,
+        kind: BroadcastKind,
+    ) -> Result<(), Error> {
+        self.pending_broadcast_tree()?
+            .remove(get_pending_broadcast_key(contract_id, kind)?)
+            .map_err(to_storage_error)?;
+        Ok(())
+    }
+
+    fn get_pending_broadcasts(&self) -> Result<Vec<PendingBroadcast>, Error> {
+        self.pending_broadcast_tree()?
+            .iter()
+            .map(|x| {
+                let (key, value) = x.map_err(to_storage_error)?;
+                let value = match &self.encryption_key {
+                    Some(encryption_key) => Cow::Owned(encryption_key.decrypt(&key, &value)?),
+                    None => Cow::Borrowed(&value[..]),
+                };
+                let mut cursor = Cursor::new(&value);
+                PendingBroadcast::deserialize(&mut cursor).map_err(to_storage_error)
+            })
+            .collect()
+    }
+}
+
+fn get_pending_broadcast_key(
+    contract_id: &ContractId,
+    kind: BroadcastKind,
+) -> Result<Vec<u8>, Error> {
+    let mut key = contract_id.0.to_vec();
+    key.extend_from_slice(&kind.serialize()?);
+    Ok(key)
 }
 
 fn serialize_contract(contract: &Contract) -> Result<Vec<u8>, ::std::io::Error> {
     let serialized = match contract {
         Contract::Offered(o) => o.serialize(),
         Contract::Accepted(o) => o.serialize(),
-        Contract::Signed(o) | Contract::Confirmed(o) | Contract::Refunded(o) => o.serialize(),
+        Contract::Signed(o)
+        | Contract::Confirmed(o)
+        | Contract::Refunded(o)
+        | Contract::RefundedDueToNoAttestation(o)
+        | Contract::Renewed(o) => o.serialize(),
         Contract::FailedAccept(c) => c.serialize(),
         Contract::FailedSign(c) => c.serialize(),
         Contract::Closed(c) => c.serialize(),
@@ -196,36 +662,56 @@ fn serialize_contract(contract: &Contract) -> Result<Vec<u8>, ::std::io::Error>
     Ok(res)
 }
 
-fn deserialize_contract(buff: &sled::IVec) -> Result<Contract, Error> {
+fn get_aad(key: &[u8], prefix: &[u8]) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(key.len() + prefix.len());
+    aad.extend_from_slice(key);
+    aad.extend_from_slice(prefix);
+    aad
+}
+
+fn deserialize_contract(buff: &[u8]) -> Result<Contract, Error> {
+    deserialize_contract_version(buff, CONTRACT_VERSION)
+}
+
+fn deserialize_contract_version(buff: &[u8], version: u8) -> Result<Contract, Error> {
     let mut cursor = ::std::io::Cursor::new(buff);
     let mut prefix = [0u8; 1];
     cursor.read_exact(&mut prefix)?;
     let contract_prefix: ContractPrefix = prefix[0].try_into()?;
     let contract = match contract_prefix {
-        ContractPrefix::Offered => {
-            Contract::Offered(OfferedContract::deserialize(&mut cursor).map_err(to_storage_error)?)
-        }
+        ContractPrefix::Offered => Contract::Offered(
+            OfferedContract::deserialize_version(&mut cursor, version).map_err(to_storage_error)?,
+        ),
         ContractPrefix::Accepted => Contract::Accepted(
-            AcceptedContract::deserialize(&mut cursor).map_err(to_storage_error)?,
+            AcceptedContract::deserialize_version(&mut cursor, version)
+                .map_err(to_storage_error)?,
+        ),
+        ContractPrefix::Signed => Contract::Signed(
+            SignedContract::deserialize_version(&mut cursor, version).map_err(to_storage_error)?,
+        ),
+        ContractPrefix::Confirmed => Contract::Confirmed(
+            SignedContract::deserialize_version(&mut cursor, version).map_err(to_storage_error)?,
+        ),
+        ContractPrefix::Closed => Contract::Closed(
+            ClosedContract::deserialize_version(&mut cursor, version).map_err(to_storage_error)?,
         ),
-        ContractPrefix::Signed => {
-            Contract::Signed(SignedContract::deserialize(&mut cursor).map_err(to_storage_error)?)
-        }
-        ContractPrefix::Confirmed => {
-            Contract::Confirmed(SignedContract::deserialize(&mut cursor).map_err(to_storage_error)?)
-        }
-        ContractPrefix::Closed => {
-            Contract::Closed(ClosedContract::deserialize(&mut cursor).map_err(to_storage_error)?)
-        }
         ContractPrefix::FailedAccept => Contract::FailedAccept(
-            FailedAcceptContract::deserialize(&mut cursor).map_err(to_storage_error)?,
+            FailedAcceptContract::deserialize_version(&mut cursor, version)
+                .map_err(to_storage_error)?,
         ),
         ContractPrefix::FailedSign => Contract::FailedSign(
-            FailedSignContract::deserialize(&mut cursor).map_err(to_storage_error)?,
+            FailedSignContract::deserialize_version(&mut cursor, version)
+                .map_err(to_storage_error)?,
+        ),
+        ContractPrefix::Refunded => Contract::Refunded(
+            SignedContract::deserialize_version(&mut cursor, version).map_err(to_storage_error)?,
+        ),
+        ContractPrefix::Renewed => Contract::Renewed(
+            SignedContract::deserialize_version(&mut cursor, version).map_err(to_storage_error)?,
+        ),
+        ContractPrefix::RefundedDueToNoAttestation => Contract::RefundedDueToNoAttestation(
+            SignedContract::deserialize_version(&mut cursor, version).map_err(to_storage_error)?,
         ),
-        ContractPrefix::Refunded => {
-            Contract::Refunded(SignedContract::deserialize(&mut cursor).map_err(to_storage_error)?)
-        }
     };
     Ok(contract)
 }
@@ -267,7 +753,7 @@ mod tests {
                 .expect("Error creating contract");
 
             let retrieved = storage
-                .get_contract(&contract.id)
+                .get_contract_by_temporary_id(&contract.id)
                 .expect("Error retrieving contract.");
 
             if let Some(Contract::Offered(retrieved_offer)) = retrieved {
@@ -295,7 +781,7 @@ mod tests {
                 .update_contract(&accepted_contract)
                 .expect("Error updating contract.");
             let retrieved = storage
-                .get_contract(&accepted_contract.get_id())
+                .get_contract(&accepted_contract.get_id().unwrap())
                 .expect("Error retrieving contract.");
 
             if let Some(Contract::Accepted(_)) = retrieved {
@@ -305,6 +791,56 @@ mod tests {
         }
     );
 
+    sled_test!(
+        adaptor_info_is_stored_separately,
+        |mut storage: SledStorageProvider| {
+            let serialized = include_bytes!("../test_files/Accepted");
+            let accepted_contract: AcceptedContract = deserialize_contract(serialized);
+            let contract_id = accepted_contract.get_contract_id();
+            let nb_adaptor_infos = accepted_contract.adaptor_infos.len();
+            assert!(nb_adaptor_infos > 0);
+
+            storage
+                .update_contract(&Contract::Accepted(accepted_contract))
+                .expect("Error updating contract.");
+
+            if let Some(Contract::Accepted(retrieved)) = storage
+                .get_contract(&contract_id)
+                .expect("Error retrieving contract.")
+            {
+                assert!(retrieved.adaptor_infos.is_empty());
+            } else {
+                unreachable!();
+            }
+            let adaptor_infos = storage
+                .get_adaptor_info(&contract_id)
+                .expect("Error retrieving adaptor information.")
+                .expect("Missing adaptor information.");
+            assert_eq!(nb_adaptor_infos, adaptor_infos.len());
+        }
+    );
+
+    sled_test!(
+        deleted_contract_adaptor_info_is_deleted,
+        |mut storage: SledStorageProvider| {
+            let serialized = include_bytes!("../test_files/Accepted");
+            let accepted_contract: AcceptedContract = deserialize_contract(serialized);
+            let contract_id = accepted_contract.get_contract_id();
+            storage
+                .update_contract(&Contract::Accepted(accepted_contract))
+                .expect("Error updating contract.");
+
+            storage
+                .delete_contract(&contract_id)
+                .expect("Error deleting contract");
+
+            assert!(storage
+                .get_adaptor_info(&contract_id)
+                .expect("Error retrieving adaptor information.")
+                .is_none());
+        }
+    );
+
     sled_test!(
         delete_contract_is_deleted,
         |mut storage: SledStorageProvider| {
@@ -315,11 +851,11 @@ mod tests {
                 .expect("Error creating contract");
 
             storage
-                .delete_contract(&contract.id)
+                .delete_contract_by_temporary_id(&contract.id)
                 .expect("Error deleting contract");
 
             assert!(storage
-                .get_contract(&contract.id)
+                .get_contract_by_temporary_id(&contract.id)
                 .expect("Error querying contract")
                 .is_none());
         }
@@ -393,4 +929,436 @@ mod tests {
             assert_eq!(1, offered_contracts.len());
         }
     );
+
+    sled_test!(
+        get_contracts_by_state_is_paginated,
+        |mut storage: SledStorageProvider| {
+            insert_offered_signed_and_confirmed(&mut storage);
+
+            let signed_contracts = storage
+                .get_contracts_by_state(ContractState::Signed, 0, 10)
+                .expect("Error retrieving contracts");
+            assert_eq!(2, signed_contracts.len());
+            assert!(signed_contracts
+                .iter()
+                .all(|x| x.get_state() == ContractState::Signed));
+
+            let pages: Vec<_> = (0..3)
+                .map(|offset| {
+                    storage
+                        .get_contracts_by_state(ContractState::Signed, offset, 1)
+                        .expect("Error retrieving contracts")
+                })
+                .collect();
+            assert_eq!(1, pages[0].len());
+            assert_eq!(1, pages[1].len());
+            assert!(pages[2].is_empty());
+            assert_ne!(pages[0][0].get_id(), pages[1][0].get_id());
+            let mut ids: Vec<_> = signed_contracts.iter().map(|x| x.get_id()).collect();
+            let mut page_ids: Vec<_> = pages.iter().flatten().map(|x| x.get_id()).collect();
+            ids.sort();
+            page_ids.sort();
+            assert_eq!(ids, page_ids);
+
+            assert!(storage
+                .get_contracts_by_state(ContractState::Closed, 0, 10)
+                .expect("Error retrieving contracts")
+                .is_empty());
+        }
+    );
+
+    sled_test!(
+        count_contracts_by_state_only_counts_state,
+        |mut storage: SledStorageProvider| {
+            insert_offered_signed_and_confirmed(&mut storage);
+            storage
+                .add_contract_history_entry(
+                    &TemporaryContractId([1u8; 32]),
+                    &ContractHistoryEntry {
+                        timestamp: 0,
+                        state: "signed".to_string(),
+                        txid: None,
+                        message_hash: None,
+                    },
+                )
+                .expect("Error adding history entry");
+
+            let count = |state| {
+                storage
+                    .count_contracts_by_state(state)
+                    .expect("Error counting contracts")
+            };
+            assert_eq!(1, count(ContractState::Offered));
+            assert_eq!(2, count(ContractState::Signed));
+            assert_eq!(2, count(ContractState::Confirmed));
+            assert_eq!(0, count(ContractState::Closed));
+        }
+    );
+
+    sled_test!(
+        contract_history_is_ordered_and_separated,
+        |mut storage: SledStorageProvider| {
+            insert_offered_signed_and_confirmed(&mut storage);
+            let nb_contracts = storage
+                .get_contracts()
+                .expect("Error retrieving contracts")
+                .len();
+            let id = TemporaryContractId([1u8; 32]);
+            let other_id = TemporaryContractId([2u8; 32]);
+            let entries: Vec<_> = ["offered", "accepted", "signed"]
+                .iter()
+                .enumerate()
+                .map(|(i, state)| ContractHistoryEntry {
+                    timestamp: i as u64,
+                    state: state.to_string(),
+                    txid: None,
+                    message_hash: Some([i as u8; 32]),
+                })
+                .collect();
+
+            for entry in &entries {
+                storage
+                    .add_contract_history_entry(&id, entry)
+                    .expect("Error adding history entry");
+            }
+            storage
+                .add_contract_history_entry(&other_id, &entries[0])
+                .expect("Error adding history entry");
+
+            assert_eq!(
+                entries,
+                storage
+                    .get_contract_history(&id)
+                    .expect("Error retrieving history")
+            );
+            assert_eq!(
+                1,
+                storage
+                    .get_contract_history(&other_id)
+                    .expect("Error retrieving history")
+                    .len()
+            );
+            // History entries should not be mistaken for contracts.
+            assert_eq!(
+                nb_contracts,
+                storage
+                    .get_contracts()
+                    .expect("Error retrieving contracts")
+                    .len()
+            );
+        }
+    );
+
+    sled_test!(
+        archived_contract_replaces_contract,
+        |mut storage: SledStorageProvider| {
+            insert_offered_signed_and_confirmed(&mut storage);
+            let serialized = include_bytes!("../test_files/Confirmed");
+            let refunded_contract = Contract::Refunded(deserialize_contract(serialized));
+            let contract_id = refunded_contract.get_id().unwrap();
+            let serialized = include_bytes!("../test_files/Confirmed1");
+            let confirmed_contract = Contract::Confirmed(deserialize_contract(serialized));
+
+            storage
+                .update_contract(&refunded_contract)
+                .expect("Error updating contract");
+            storage
+                .archive_contract(&confirmed_contract.get_id().unwrap())
+                .expect_err("Should not archive a confirmed contract");
+            storage
+                .archive_contract(&contract_id)
+                .expect("Error archiving contract");
+
+            assert!(storage
+                .get_contract(&contract_id)
+                .expect("Error querying contract")
+                .is_none());
+            let archived = storage
+                .get_archived_contracts()
+                .expect("Error retrieving archived contracts");
+            assert_eq!(1, archived.len());
+            assert_eq!(contract_id, archived[0].id);
+            assert_eq!(ContractState::Refunded, archived[0].state);
+        }
+    );
+
+    sled_test!(
+        pending_broadcasts_are_keyed_by_contract_and_kind,
+        |mut storage: SledStorageProvider| {
+            let serialized = include_bytes!("../test_files/Confirmed");
+            let contract: SignedContract = deserialize_contract(serialized);
+            let mut pending = PendingBroadcast {
+                contract_id: contract.accepted_contract.get_contract_id(),
+                kind: BroadcastKind::Refund,
+                transaction: contract.accepted_contract.dlc_transactions.refund.clone(),
+                not_before: 10,
+                nb_attempts: 0,
+            };
+
+            storage
+                .save_pending_broadcast(&pending)
+                .expect("Error saving pending broadcast");
+            pending.nb_attempts = 1;
+            storage
+                .save_pending_broadcast(&pending)
+                .expect("Error saving pending broadcast");
+            let mut fund = pending.clone();
+            fund.kind = BroadcastKind::Fund;
+            fund.transaction = contract.accepted_contract.dlc_transactions.fund.clone();
+            storage
+                .save_pending_broadcast(&fund)
+                .expect("Error saving pending broadcast");
+
+            let mut retrieved = storage
+                .get_pending_broadcasts()
+                .expect("Error retrieving pending broadcasts");
+            retrieved.sort_by_key(|x| x.kind != BroadcastKind::Fund);
+            assert_eq!(vec![fund, pending.clone()], retrieved);
+
+            storage
+                .delete_pending_broadcast(&pending.contract_id, BroadcastKind::Fund)
+                .expect("Error deleting pending broadcast");
+            assert_eq!(
+                vec![pending],
+                storage
+                    .get_pending_broadcasts()
+                    .expect("Error retrieving pending broadcasts")
+            );
+        }
+    );
+
+    fn assert_upgraded<T: VersionedSerializable>(v0: &[u8], current: &[u8]) {
+        let mut cursor = std::io::Cursor::new(&v0);
+        let contract = T::deserialize_version(&mut cursor, 0).unwrap();
+        assert_eq!(v0.len() as u64, cursor.position());
+        assert_eq!(current[..], contract.serialize().unwrap()[..]);
+    }
+
+    #[test]
+    fn v0_contracts_are_upgraded() {
+        // The v0 test files were written before serialization versions were
+        // introduced, and contain the same contracts as the current ones.
+        assert_upgraded::<OfferedContract>(
+            include_bytes!("../test_files/v0/Offered"),
+            include_bytes!("../test_files/Offered"),
+        );
+        assert_upgraded::<AcceptedContract>(
+            include_bytes!("../test_files/v0/Accepted"),
+            include_bytes!("../test_files/Accepted"),
+        );
+        assert_upgraded::<SignedContract>(
+            include_bytes!("../test_files/v0/Signed"),
+            include_bytes!("../test_files/Signed"),
+        );
+        assert_upgraded::<SignedContract>(
+            include_bytes!("../test_files/v0/Confirmed"),
+            include_bytes!("../test_files/Confirmed"),
+        );
+        assert_upgraded::<ClosedContract>(
+            include_bytes!("../test_files/v0/Closed"),
+            include_bytes!("../test_files/Closed"),
+        );
+    }
+
+    fn deserialize_v0<T: VersionedSerializable>(serialized: &[u8]) -> T {
+        T::deserialize_version(&mut std::io::Cursor::new(serialized), 0).unwrap()
+    }
+
+    #[test]
+    fn v0_offered_contract_uses_wall_clock() {
+        let contract: OfferedContract = deserialize_v0(include_bytes!("../test_files/v0/Offered"));
+        assert_eq!(dlc_manager::TimeSource::WallClock, contract.time_source);
+    }
+
+    #[test]
+    fn v0_accepted_contract_has_no_funding_input_proofs() {
+        let contract: AcceptedContract =
+            deserialize_v0(include_bytes!("../test_files/v0/Accepted"));
+        assert!(contract.funding_input_proofs.is_none());
+        assert!(contract.offered_contract.funding_input_proofs.is_none());
+        assert!(contract
+            .funding_inputs
+            .iter()
+            .chain(contract.offered_contract.funding_inputs_info.iter())
+            .all(|x| x.witness_script.is_none()));
+    }
+
+    #[test]
+    fn v0_signed_contract_pays_out_to_wallet() {
+        let contract: SignedContract = deserialize_v0(include_bytes!("../test_files/v0/Signed"));
+        let offered_contract = &contract.accepted_contract.offered_contract;
+        assert!(!offered_contract.is_payout_external);
+        assert!(offered_contract.premium.is_none());
+        assert!(offered_contract.fee_split.is_none());
+        assert!(offered_contract.renewal.is_none());
+        assert_eq!(
+            dlc_manager::manager::NB_CONFIRMATIONS,
+            offered_contract.nb_confirmations
+        );
+        assert!(contract.signed_fund_tx.is_none());
+    }
+
+    #[test]
+    fn v0_database_is_migrated() {
+        let path = "test_files/sleddb/v0_database_is_migrated";
+        let offered: OfferedContract =
+            deserialize_contract(include_bytes!("../test_files/Offered"));
+        let signed: SignedContract = deserialize_contract(include_bytes!("../test_files/Signed"));
+        let signed_id = signed.accepted_contract.get_contract_id();
+        // Version 0 databases store contracts under an id computed from the
+        // fund transaction id in serialization order.
+        let dlc_transactions = &signed.accepted_contract.dlc_transactions;
+        let mut v0_id = signed.accepted_contract.offered_contract.id.0;
+        for (i, b) in dlc_transactions.fund.txid().as_ref().iter().enumerate() {
+            v0_id[i] ^= b;
+        }
+        let fund_output_index = dlc_transactions.get_fund_output_index() as u16;
+        v0_id[30] ^= (fund_output_index >> 8) as u8;
+        v0_id[31] ^= (fund_output_index & 0xff) as u8;
+        assert_ne!(signed_id.0, v0_id);
+        {
+            let db = sled::open(path).expect("Error opening sled DB");
+            let mut value: Vec<u8> = vec![ContractPrefix::Offered.into()];
+            value.extend_from_slice(include_bytes!("../test_files/v0/Offered"));
+            db.insert(&offered.id, value).unwrap();
+            let mut value: Vec<u8> = vec![ContractPrefix::Signed.into()];
+            value.extend_from_slice(include_bytes!("../test_files/v0/Signed"));
+            db.insert(&v0_id, value).unwrap();
+            db.flush().unwrap();
+        }
+        for _ in 0..2 {
+            let storage = SledStorageProvider::new(path).expect("Error opening sled DB");
+            if let Some(Contract::Offered(retrieved)) = storage
+                .get_contract_by_temporary_id(&offered.id)
+                .expect("Error retrieving contract.")
+            {
+                assert_eq!(offered.serialize().unwrap(), retrieved.serialize().unwrap());
+            } else {
+                unreachable!();
+            }
+            assert!(!storage.db.contains_key(&v0_id).unwrap());
+            assert!(matches!(
+                storage
+                    .get_contract(&signed_id)
+                    .expect("Error retrieving contract."),
+                Some(Contract::Signed(_))
+            ));
+            let signed_contracts = storage
+                .get_signed_contracts()
+                .expect("Error retrieving signed contracts");
+            assert_eq!(1, signed_contracts.len());
+            assert_eq!(
+                signed_id,
+                signed_contracts[0].accepted_contract.get_contract_id()
+            );
+            assert!(signed_contracts[0]
+                .accepted_contract
+                .adaptor_infos
+                .is_empty());
+            assert_eq!(
+                signed.accepted_contract.adaptor_infos.len(),
+                storage
+                    .get_adaptor_info(&signed_id)
+                    .expect("Error retrieving adaptor information.")
+                    .expect("Missing adaptor information.")
+                    .len()
+            );
+        }
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn newer_storage_version_is_rejected() {
+        let path = "test_files/sleddb/newer_storage_version_is_rejected";
+        {
+            let db = sled::open(path).expect("Error opening sled DB");
+            db.open_tree(METADATA_TREE)
+                .unwrap()
+                .insert(VERSION_KEY, &[STORAGE_VERSION + 1])
+                .unwrap();
+            db.flush().unwrap();
+        }
+        assert!(SledStorageProvider::new(path).is_err());
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn encrypted_contracts_require_key() {
+        let path = "test_files/sleddb/encrypted_contracts_require_key";
+        {
+            let encryption_key = StorageEncryptionKey::from_seed(&[1u8; 32]);
+            let mut storage = SledStorageProvider::new_encrypted(path, encryption_key)
+                .expect("Error opening sled DB");
+            insert_offered_signed_and_confirmed(&mut storage);
+
+            assert_eq!(
+                2,
+                storage
+                    .get_confirmed_contracts()
+                    .expect("Error retrieving confirmed contracts")
+                    .len()
+            );
+            assert_eq!(
+                2,
+                storage
+                    .count_contracts_by_state(ContractState::Signed)
+                    .expect("Error counting contracts")
+            );
+        }
+        {
+            let encryption_key = StorageEncryptionKey::from_seed(&[2u8; 32]);
+            assert!(SledStorageProvider::new_encrypted(path, encryption_key).is_err());
+            assert!(SledStorageProvider::new(path).is_err());
+        }
+        {
+            let encryption_key = StorageEncryptionKey::from_seed(&[1u8; 32]);
+            let storage = SledStorageProvider::new_encrypted(path, encryption_key)
+                .expect("Error opening sled DB");
+            assert_eq!(
+                2,
+                storage
+                    .get_confirmed_contracts()
+                    .expect("Error retrieving confirmed contracts")
+                    .len()
+            );
+        }
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn encrypted_adaptor_info_is_retrieved() {
+        let path = "test_files/sleddb/encrypted_adaptor_info_is_retrieved";
+        {
+            let encryption_key = StorageEncryptionKey::from_seed(&[1u8; 32]);
+            let mut storage = SledStorageProvider::new_encrypted(path, encryption_key)
+                .expect("Error opening sled DB");
+            let serialized = include_bytes!("../test_files/Accepted");
+            let accepted_contract: AcceptedContract = deserialize_contract(serialized);
+            let contract_id = accepted_contract.get_contract_id();
+            let nb_adaptor_infos = accepted_contract.adaptor_infos.len();
+            storage
+                .update_contract(&Contract::Accepted(accepted_contract))
+                .expect("Error updating contract.");
+
+            let adaptor_infos = storage
+                .get_adaptor_info(&contract_id)
+                .expect("Error retrieving adaptor information.")
+                .expect("Missing adaptor information.");
+            assert_eq!(nb_adaptor_infos, adaptor_infos.len());
+        }
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn unencrypted_contracts_are_not_opened_with_key() {
+        let path = "test_files/sleddb/unencrypted_contracts_are_not_opened_with_key";
+        {
+            let mut storage = SledStorageProvider::new(path).expect("Error opening sled DB");
+            insert_offered_signed_and_confirmed(&mut storage);
+        }
+        let encryption_key = StorageEncryptionKey::from_seed(&[1u8; 32]);
+        assert!(SledStorageProvider::new_encrypted(path, encryption_key).is_err());
+        assert!(SledStorageProvider::new(path).is_ok());
+        std::fs::remove_dir_all(path).unwrap();
+    }
 }
@@ -0,0 +1,147 @@
+#[macro_use]
+extern crate criterion;
+extern crate dlc;
+extern crate dlc_trie;
+
+use criterion::{black_box, BenchmarkId, Criterion};
+use dlc::{Payout, RangePayout};
+use dlc_trie::multi_oracle::CoverageStrategy;
+use dlc_trie::multi_oracle_trie::MultiOracleTrie;
+use dlc_trie::multi_oracle_trie_with_diff::MultiOracleTrieWithDiff;
+use dlc_trie::DlcTrie;
+
+/// The base in which the outcome values are decomposed.
+const BASE: usize = 2;
+/// The number of outcomes grouped in a single payout between the floor and
+/// the cap of the payout curve.
+const ROUNDING_MOD: usize = 100;
+/// The minimum difference between oracle supported for the contract (as a power of 2).
+const MIN_SUPPORT_EXP: usize = 7;
+/// The maximum difference between oracle supported for the contract (as a power of 2).
+const MAX_ERROR_EXP: usize = 8;
+/// The total collateral value locked in the contract.
+const TOTAL_COLLATERAL: u64 = 200000000;
+
+/// The oracle setup of a benchmarked trie.
+struct TrieSize {
+    /// The name of the setup used as benchmark parameter.
+    name: &'static str,
+    /// The number of digits used to represent outcome values.
+    nb_digits: usize,
+    /// The number of oracles used for the contract.
+    nb_oracles: usize,
+    /// The number of oracles required to be in agreement to close the contract.
+    threshold: usize,
+}
+
+const TRIE_SIZES: [TrieSize; 3] = [
+    TrieSize {
+        name: "1-of-1 20 digits",
+        nb_digits: 20,
+        nb_oracles: 1,
+        threshold: 1,
+    },
+    TrieSize {
+        name: "2-of-3 17 digits",
+        nb_digits: 17,
+        nb_oracles: 3,
+        threshold: 2,
+    },
+    TrieSize {
+        name: "3-of-5 17 digits",
+        nb_digits: 17,
+        nb_oracles: 5,
+        threshold: 3,
+    },
+];
+
+/// Returns range payouts of a collar shaped payout curve, paying everything
+/// to the accept party below 45% of the outcome domain and everything to the
+/// offer party above 55%, with a linear payout in between.
+fn create_range_payouts(nb_digits: usize) -> Vec<RangePayout> {
+    let nb_outcomes = BASE.pow(nb_digits as u32);
+    let floor = nb_outcomes * 9 / 20;
+    let cap = nb_outcomes * 11 / 20;
+    let mut range_payouts = vec![RangePayout {
+        start: 0,
+        count: floor,
+        payout: Payout {
+            offer: 0,
+            accept: TOTAL_COLLATERAL,
+        },
+    }];
+
+    let mut start = floor;
+    while start < cap {
+        let count = std::cmp::min(ROUNDING_MOD, cap - start);
+        let offer = TOTAL_COLLATERAL * (start - floor) as u64 / (cap - floor) as u64;
+        range_payouts.push(RangePayout {
+            start,
+            count,
+            payout: Payout {
+                offer,
+                accept: TOTAL_COLLATERAL - offer,
+            },
+        });
+        start += count;
+    }
+
+    range_payouts.push(RangePayout {
+        start: cap,
+        count: nb_outcomes - cap,
+        payout: Payout {
+            offer: TOTAL_COLLATERAL,
+            accept: 0,
+        },
+    });
+    range_payouts
+}
+
+/// Benchmark to measure the generation time of tries requiring oracles to
+/// attest to the same outcome.
+pub fn multi_oracle_trie_bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("multi_oracle_trie");
+    for size in &TRIE_SIZES {
+        let range_payouts = create_range_payouts(size.nb_digits);
+        group.bench_function(BenchmarkId::from_parameter(size.name), |b| {
+            b.iter(|| {
+                let mut trie =
+                    MultiOracleTrie::new(BASE, size.nb_oracles, size.threshold, size.nb_digits);
+                black_box(trie.generate(0, &range_payouts).unwrap());
+            });
+        });
+    }
+    group.finish();
+}
+
+/// Benchmark to measure the generation time of tries allowing differences
+/// between the outcomes attested by the oracles.
+pub fn multi_oracle_trie_with_diff_bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("multi_oracle_trie_with_diff");
+    // A single oracle cannot disagree with itself.
+    for size in TRIE_SIZES.iter().filter(|x| x.nb_oracles > 1) {
+        let range_payouts = create_range_payouts(size.nb_digits);
+        group.bench_function(BenchmarkId::from_parameter(size.name), |b| {
+            b.iter(|| {
+                let mut trie = MultiOracleTrieWithDiff::new(
+                    BASE,
+                    size.nb_oracles,
+                    size.threshold,
+                    size.nb_digits,
+                    MIN_SUPPORT_EXP,
+                    MAX_ERROR_EXP,
+                    CoverageStrategy::MaximizeSupport,
+                );
+                black_box(trie.generate(0, &range_payouts).unwrap());
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = trie_bench;
+    config = Criterion::default().sample_size(10);
+    targets = multi_oracle_trie_bench, multi_oracle_trie_with_diff_bench
+}
+criterion_main!(trie_bench);
@@ -38,6 +38,57 @@ impl CombinationIterator {
 
         None
     }
+
+    /// Returns the combination at the given index in the set of combinations
+    /// produced by the iterator, None if the index is out of range. Computed
+    /// without iterating through the previous combinations, this is the
+    /// inverse of [`CombinationIterator::get_index_for_combination`].
+    pub fn get_combination_for_index(&self, index: usize) -> Option<Vec<usize>> {
+        if index >= self.len() {
+            return None;
+        }
+
+        let mut index = index;
+        let mut combination = Vec::with_capacity(self.nb_selected);
+        let mut element = 0;
+        for i in 0..self.nb_selected {
+            let nb_remaining = self.nb_selected - i - 1;
+            // Skip the combinations having a smaller element at this position.
+            loop {
+                let nb_combinations = binomial(self.nb_elements - element - 1, nb_remaining);
+                if index < nb_combinations {
+                    break;
+                }
+                index -= nb_combinations;
+                element += 1;
+            }
+            combination.push(element);
+            element += 1;
+        }
+
+        Some(combination)
+    }
+
+    /// Returns the total number of combinations produced by the iterator,
+    /// independently of the ones already returned.
+    pub fn len(&self) -> usize {
+        binomial(self.nb_elements, self.nb_selected)
+    }
+
+    /// Returns whether the iterator produces no combination, which is never
+    /// the case as choosing zero elements gives a single empty combination.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// Returns the number of ways to choose `k` elements out of `n`.
+fn binomial(n: usize, k: usize) -> usize {
+    if k > n {
+        return 0;
+    }
+
+    (0..k.min(n - k)).fold(1, |acc, i| acc * (n - i) / (i + 1))
 }
 
 impl Iterator for CombinationIterator {
@@ -97,4 +148,25 @@ mod tests {
                 .expect("Could not find combination")
         );
     }
+
+    #[test]
+    fn get_combination_for_index_test() {
+        for (nb_elements, nb_selected) in &[(4, 3), (5, 2), (6, 6), (7, 1), (10, 4)] {
+            let combination_iterator = CombinationIterator::new(*nb_elements, *nb_selected);
+            let combinations =
+                CombinationIterator::new(*nb_elements, *nb_selected).collect::<Vec<_>>();
+
+            assert_eq!(combinations.len(), combination_iterator.len());
+            for (i, combination) in combinations.iter().enumerate() {
+                assert_eq!(
+                    Some(combination),
+                    combination_iterator.get_combination_for_index(i).as_ref()
+                );
+            }
+            assert_eq!(
+                None,
+                combination_iterator.get_combination_for_index(combinations.len())
+            );
+        }
+    }
 }
@@ -0,0 +1,250 @@
+//! # CompactMultiOracleTrie
+//! Data structure and functions used to store adaptor signature information
+//! for numerical outcome DLC with t of n oracles where at least t oracles
+//! need to sign the exact same outcome for the contract to be able to close.
+//! Contrary to the [`crate::multi_oracle_trie::MultiOracleTrie`], a single
+//! entry is stored for each path of the trie, the adaptor signatures of the
+//! oracle combinations being laid out consecutively so that their index can
+//! be computed from the index of the combination.
+
+use crate::combination_iterator::CombinationIterator;
+use crate::digit_decomposition::group_by_ignoring_digits;
+use crate::digit_trie::{DigitTrie, DigitTrieDump, DigitTrieIter};
+use crate::{DlcTrie, LookupResult, RangeInfo, TrieIterInfo};
+use dlc::{Error, RangePayout};
+
+/// Data structure used to store adaptor signature information for numerical
+/// outcome DLC with t of n oracles where at least t oracles need to sign the
+/// exact same outcome for the contract to be able to close. The value stored
+/// for each path is the range information of the first combination of oracles.
+#[derive(Clone)]
+pub struct CompactMultiOracleTrie {
+    /// The underlying trie data structure.
+    pub digit_trie: DigitTrie<RangeInfo>,
+    nb_oracles: usize,
+    threshold: usize,
+    nb_digits: usize,
+}
+
+/// Container for a dump of a CompactMultiOracleTrie used for serialization
+/// purpose.
+pub struct CompactMultiOracleTrieDump {
+    /// A dump of the underlying digit trie.
+    pub digit_trie_dump: DigitTrieDump<RangeInfo>,
+    /// The total number of oracles for this trie.
+    pub nb_oracles: usize,
+    /// The required number of oracles for this trie.
+    pub threshold: usize,
+    /// The maximum number of digits for a path in the trie.
+    pub nb_digits: usize,
+}
+
+impl CompactMultiOracleTrie {
+    /// Creates a new CompactMultiOracleTrie
+    pub fn new(base: usize, nb_oracles: usize, threshold: usize, nb_digits: usize) -> Self {
+        CompactMultiOracleTrie {
+            digit_trie: DigitTrie::new(base),
+            nb_oracles,
+            threshold,
+            nb_digits,
+        }
+    }
+
+    /// Dump the trie information.
+    pub fn dump(&self) -> CompactMultiOracleTrieDump {
+        CompactMultiOracleTrieDump {
+            digit_trie_dump: self.digit_trie.dump(),
+            nb_oracles: self.nb_oracles,
+            threshold: self.threshold,
+            nb_digits: self.nb_digits,
+        }
+    }
+
+    /// Recover a CompactMultiOracleTrie from a dump.
+    pub fn from_dump(dump: CompactMultiOracleTrieDump) -> CompactMultiOracleTrie {
+        let CompactMultiOracleTrieDump {
+            digit_trie_dump,
+            nb_oracles,
+            threshold,
+            nb_digits,
+        } = dump;
+        CompactMultiOracleTrie {
+            digit_trie: DigitTrie::from_dump(digit_trie_dump),
+            nb_oracles,
+            threshold,
+            nb_digits,
+        }
+    }
+
+    /// Looks up the given outcome digits attested by the given combination of
+    /// oracles, which must contain `threshold` oracle indexes in increasing
+    /// order. Returns the length of the matching path and the range
+    /// information of the combination.
+    pub fn look_up(&self, digits: &[usize], combination: &[usize]) -> Option<(usize, RangeInfo)> {
+        let res = self.digit_trie.look_up(digits)?;
+        let combination_index = CombinationIterator::new(self.nb_oracles, self.threshold)
+            .get_index_for_combination(combination)?;
+        let first = &res[0];
+        Some((
+            first.path.len(),
+            RangeInfo {
+                cet_index: first.value.cet_index,
+                adaptor_index: first.value.adaptor_index + combination_index,
+            },
+        ))
+    }
+}
+
+impl<'a> DlcTrie<'a, CompactMultiOracleTrieIter<'a>> for CompactMultiOracleTrie {
+    fn generate(
+        &mut self,
+        adaptor_index_start: usize,
+        outcomes: &[RangePayout],
+    ) -> Result<Vec<TrieIterInfo>, Error> {
+        let threshold = self.threshold;
+        let nb_oracles = self.nb_oracles;
+        let mut adaptor_index = adaptor_index_start;
+        let mut trie_infos = Vec::new();
+        for (cet_index, outcome) in outcomes.iter().enumerate() {
+            let groups = group_by_ignoring_digits(
+                outcome.start,
+                outcome.start + outcome.count - 1,
+                self.digit_trie.base,
+                self.nb_digits,
+            );
+            for group in groups {
+                let mut get_value = |_: Option<RangeInfo>| -> Result<RangeInfo, Error> {
+                    let first = RangeInfo {
+                        cet_index,
+                        adaptor_index,
+                    };
+                    for selector in CombinationIterator::new(nb_oracles, threshold) {
+                        trie_infos.push(TrieIterInfo {
+                            indexes: selector,
+                            paths: std::iter::repeat(group.clone()).take(threshold).collect(),
+                            value: RangeInfo {
+                                cet_index,
+                                adaptor_index,
+                            },
+                        });
+                        adaptor_index += 1;
+                    }
+                    Ok(first)
+                };
+                self.digit_trie.insert(&group, &mut get_value)?;
+            }
+        }
+        Ok(trie_infos)
+    }
+
+    fn iter(&'a self) -> CompactMultiOracleTrieIter {
+        CompactMultiOracleTrieIter {
+            digit_trie_iterator: DigitTrieIter::new(&self.digit_trie),
+            cur_res: None,
+            cur_index: 0,
+            combination_iter: CombinationIterator::new(self.nb_oracles, self.threshold),
+        }
+    }
+}
+
+/// Iterator for a CompactMultiOracleTrie.
+pub struct CompactMultiOracleTrieIter<'a> {
+    digit_trie_iterator: DigitTrieIter<'a, RangeInfo>,
+    cur_res: Option<LookupResult<'a, RangeInfo, usize>>,
+    cur_index: usize,
+    combination_iter: CombinationIterator,
+}
+
+impl<'a> Iterator for CompactMultiOracleTrieIter<'a> {
+    type Item = TrieIterInfo;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.cur_res.is_none() {
+            self.cur_res = self.digit_trie_iterator.next();
+        }
+        let res = match &self.cur_res {
+            None => return None,
+            Some(res) => res,
+        };
+
+        let indexes = match self.combination_iter.next() {
+            Some(selector) => selector,
+            None => {
+                self.cur_res = None;
+                self.cur_index = 0;
+                self.combination_iter = CombinationIterator::new(
+                    self.combination_iter.nb_elements,
+                    self.combination_iter.nb_selected,
+                );
+                return self.next();
+            }
+        };
+        let paths = std::iter::repeat(res.path.clone())
+            .take(self.combination_iter.nb_selected)
+            .collect();
+        let value = RangeInfo {
+            cet_index: res.value.cet_index,
+            adaptor_index: res.value.adaptor_index + self.cur_index,
+        };
+        self.cur_index += 1;
+        Some(TrieIterInfo {
+            indexes,
+            paths,
+            value,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multi_oracle_trie::MultiOracleTrie;
+    use dlc::Payout;
+
+    fn get_range_payouts() -> Vec<RangePayout> {
+        vec![
+            RangePayout {
+                start: 0,
+                count: 5,
+                payout: Payout {
+                    offer: 10,
+                    accept: 0,
+                },
+            },
+            RangePayout {
+                start: 5,
+                count: 11,
+                payout: Payout {
+                    offer: 0,
+                    accept: 10,
+                },
+            },
+        ]
+    }
+
+    #[test]
+    fn compact_trie_matches_multi_oracle_trie() {
+        let mut trie = MultiOracleTrie::new(2, 3, 2, 4);
+        let mut compact_trie = CompactMultiOracleTrie::new(2, 3, 2, 4);
+
+        let infos = trie.generate(2, &get_range_payouts()).unwrap();
+        let compact_infos = compact_trie.generate(2, &get_range_payouts()).unwrap();
+
+        let to_tuples = |infos: Vec<TrieIterInfo>| {
+            infos
+                .into_iter()
+                .map(|x| (x.indexes, x.paths, x.value))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(to_tuples(infos), to_tuples(compact_infos));
+        assert_eq!(
+            to_tuples(trie.iter().collect()),
+            to_tuples(compact_trie.iter().collect())
+        );
+
+        let (path_len, range_info) = compact_trie.look_up(&[1, 0, 1, 1], &[1, 2]).unwrap();
+        let expected = trie.digit_trie.look_up(&[1, 0, 1, 1]).unwrap();
+        assert_eq!(expected[0].path.len(), path_len);
+        assert_eq!(expected[0].value[2], range_info);
+    }
+}
@@ -51,6 +51,103 @@ where
         let store = node_data.into_iter().map(|x| Node::from_data(x)).collect();
         DigitTrie { store, root, base }
     }
+
+    /// Dump the content of the trie in chunks of at most `chunk_size` nodes, so
+    /// that large tries can be serialized without holding a complete dump in
+    /// memory. The returned dump contains no node data, which is yielded by
+    /// the iterator in order.
+    pub fn dump_chunks<'a>(
+        &'a self,
+        chunk_size: usize,
+    ) -> (
+        DigitTrieDump<T>,
+        impl Iterator<Item = Vec<DigitNodeData<T>>> + 'a,
+    ) {
+        let dump = DigitTrieDump {
+            root: self.root,
+            base: self.base,
+            node_data: Vec::new(),
+        };
+        let chunks = self
+            .store
+            .chunks(chunk_size.max(1))
+            .map(|chunk| chunk.iter().map(|x| x.get_data()).collect());
+        (dump, chunks)
+    }
+
+    /// Restore a trie from a dump obtained with [`DigitTrie::dump_chunks`] and
+    /// its chunks of node data, in order.
+    pub fn from_chunks<I>(dump: DigitTrieDump<T>, chunks: I) -> DigitTrie<T>
+    where
+        I: IntoIterator<Item = Vec<DigitNodeData<T>>>,
+    {
+        let DigitTrieDump {
+            root,
+            base,
+            node_data,
+        } = dump;
+        let store = node_data
+            .into_iter()
+            .chain(chunks.into_iter().flatten())
+            .map(|x| Node::from_data(x))
+            .collect();
+        DigitTrie { store, root, base }
+    }
+
+    /// Removes the values for which `f` returns false. Nodes left without a
+    /// value nor descendants are removed while the paths to the remaining
+    /// values are kept.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let old_store = std::mem::replace(&mut self.store, Vec::new());
+        self.root = match self.root {
+            Some(root) => self.retain_node(&old_store, root, &mut f),
+            None => None,
+        };
+    }
+
+    // Copies the node at the given index of the old store, and its retained
+    // descendants, to the store, returning its new index if it was kept.
+    fn retain_node<F>(
+        &mut self,
+        old_store: &[Node<DigitLeaf<T>, DigitNode<T>>],
+        index: usize,
+        f: &mut F,
+    ) -> Option<usize>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let node = match &old_store[index] {
+            Node::Leaf(l) if f(&l.data) => Node::Leaf(l.clone()),
+            Node::Leaf(_) | Node::None => return None,
+            Node::Node(n) => {
+                let mut children = Vec::with_capacity(n.children.len());
+                for child in &n.children {
+                    children.push(child.and_then(|x| self.retain_node(old_store, x, f)));
+                }
+                let data = match &n.data {
+                    Some(data) if f(data) => Some(data.clone()),
+                    _ => None,
+                };
+                if children.iter().any(|x| x.is_some()) {
+                    Node::Node(DigitNode {
+                        children,
+                        prefix: n.prefix.clone(),
+                        data,
+                    })
+                } else {
+                    Node::Leaf(DigitLeaf {
+                        data: data?,
+                        prefix: n.prefix.clone(),
+                    })
+                }
+            }
+        };
+        self.store.push(node);
+        Some(self.store.len() - 1)
+    }
 }
 
 /// External representation of a node used for serialization purpose.
@@ -463,6 +560,35 @@ impl<T> DigitTrie<T> {
             },
         }
     }
+
+    /// Returns a trie with the same structure in which each value is replaced
+    /// by the result of applying `f` to it.
+    pub fn map_values<U, F>(&self, mut f: F) -> DigitTrie<U>
+    where
+        F: FnMut(&T) -> U,
+    {
+        let store = self
+            .store
+            .iter()
+            .map(|node| match node {
+                Node::Leaf(l) => Node::Leaf(DigitLeaf {
+                    data: f(&l.data),
+                    prefix: l.prefix.clone(),
+                }),
+                Node::Node(n) => Node::Node(DigitNode {
+                    children: n.children.clone(),
+                    prefix: n.prefix.clone(),
+                    data: n.data.as_ref().map(|x| f(x)),
+                }),
+                Node::None => Node::None,
+            })
+            .collect();
+        DigitTrie {
+            store,
+            root: self.root,
+            base: self.base,
+        }
+    }
 }
 
 fn extend_lookup_res_paths<'a, T>(
@@ -757,4 +883,53 @@ mod tests {
             assert_eq!(*i, prev_index + 1);
         }
     }
+
+    #[test]
+    fn digit_trie_retain_removes_filtered_values() {
+        for test_case in digit_trie_test_cases() {
+            let mut digit_trie = DigitTrie::<usize>::new(16);
+            for (i, path) in test_case.iter().enumerate() {
+                digit_trie.insert(path, &mut |_| Ok(i)).unwrap();
+            }
+
+            digit_trie.retain(|x| x % 2 == 0);
+            let digit_trie = digit_trie.map_values(|x| x * 10);
+
+            for (i, path) in test_case.iter().enumerate() {
+                match digit_trie.look_up(path) {
+                    Some(l_res) => {
+                        assert_eq!(0, i % 2);
+                        assert_eq!(1, l_res.len());
+                        assert_eq!(i * 10, *l_res[0].value);
+                    }
+                    None => assert_eq!(1, i % 2),
+                }
+            }
+            assert_eq!(
+                (test_case.len() + 1) / 2,
+                DigitTrieIter::new(&digit_trie).count()
+            );
+        }
+    }
+
+    #[test]
+    fn digit_trie_restored_from_chunks_returns_inserted_elements() {
+        for test_case in digit_trie_test_cases() {
+            let mut digit_trie = DigitTrie::<usize>::new(16);
+            for (i, path) in test_case.iter().enumerate() {
+                digit_trie.insert(path, &mut |_| Ok(i)).unwrap();
+            }
+
+            let (dump, chunks) = digit_trie.dump_chunks(3);
+            let chunks = chunks.collect::<Vec<_>>();
+            assert!(chunks.iter().all(|x| x.len() <= 3));
+            let restored = DigitTrie::from_chunks(dump, chunks);
+
+            for (i, path) in test_case.iter().enumerate() {
+                let l_res = restored.look_up(path).unwrap();
+                assert_eq!(1, l_res.len());
+                assert_eq!(i, *l_res[0].value);
+            }
+        }
+    }
 }
@@ -17,14 +17,20 @@ extern crate dlc;
 #[cfg(feature = "parallel")]
 extern crate rayon;
 extern crate secp256k1_zkp;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "tracing")]
+extern crate tracing;
 
 use bitcoin::{Script, Transaction};
 use dlc::{Error, RangePayout};
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 use secp256k1_zkp::{All, EcdsaAdaptorSignature, PublicKey, Secp256k1, SecretKey};
+use std::borrow::Cow;
 
 pub mod combination_iterator;
+pub mod compact_multi_oracle_trie;
 pub mod digit_decomposition;
 pub mod digit_trie;
 pub mod multi_oracle;
@@ -66,6 +72,19 @@ pub struct RangeInfo {
     pub adaptor_index: usize,
 }
 
+/// Function returning the CET at the given index, enabling CETs to be
+/// generated on demand instead of being all kept in memory while signing or
+/// verifying adaptor signatures.
+pub type CetProvider<'a> = dyn Fn(usize) -> Result<Cow<'a, Transaction>, Error> + Sync + 'a;
+
+/// Returns a reference to the CET at the given index of the slice, to be used
+/// as a [`CetProvider`] when all the CETs are already available.
+pub fn get_cet_from_slice(cets: &[Transaction], index: usize) -> Result<Cow<Transaction>, Error> {
+    cets.get(index)
+        .map(Cow::Borrowed)
+        .ok_or(Error::InvalidArgument)
+}
+
 /// A common trait for trie data structures that store DLC adaptor signature
 /// information.
 pub trait DlcTrie<'a, TrieIterator: Iterator<Item = TrieIterInfo>> {
@@ -89,7 +108,7 @@ pub trait DlcTrie<'a, TrieIterator: Iterator<Item = TrieIterInfo>> {
         funding_script_pubkey: &Script,
         fund_output_value: u64,
         outcomes: &[RangePayout],
-        cets: &[Transaction],
+        cets: &CetProvider,
         precomputed_points: &[Vec<Vec<PublicKey>>],
         adaptor_sigs: &[EcdsaAdaptorSignature],
         adaptor_index_start: usize,
@@ -115,7 +134,7 @@ pub trait DlcTrie<'a, TrieIterator: Iterator<Item = TrieIterInfo>> {
         funding_script_pubkey: &Script,
         fund_output_value: u64,
         outcomes: &[RangePayout],
-        cets: &[Transaction],
+        cets: &CetProvider,
         precomputed_points: &[Vec<Vec<PublicKey>>],
         adaptor_index_start: usize,
     ) -> Result<Vec<EcdsaAdaptorSignature>, Error> {
@@ -140,7 +159,7 @@ pub trait DlcTrie<'a, TrieIterator: Iterator<Item = TrieIterInfo>> {
         funding_script_pubkey: &Script,
         fund_output_value: u64,
         adaptor_sigs: &[EcdsaAdaptorSignature],
-        cets: &[Transaction],
+        cets: &CetProvider,
         precomputed_points: &[Vec<Vec<PublicKey>>],
     ) -> Result<usize, Error> {
         verify_helper(
@@ -162,7 +181,7 @@ pub trait DlcTrie<'a, TrieIterator: Iterator<Item = TrieIterInfo>> {
         fund_privkey: &SecretKey,
         funding_script_pubkey: &Script,
         fund_output_value: u64,
-        cets: &[Transaction],
+        cets: &CetProvider,
         precomputed_points: &[Vec<Vec<PublicKey>>],
     ) -> Result<Vec<EcdsaAdaptorSignature>, Error> {
         let trie_info = self.iter();
@@ -176,6 +195,43 @@ pub trait DlcTrie<'a, TrieIterator: Iterator<Item = TrieIterInfo>> {
             trie_info,
         )
     }
+
+    /// Returns the adaptor index and adaptor point of each adaptor signature
+    /// related to the CET at the given index.
+    fn get_adaptor_points_for_cet(
+        &'a self,
+        cet_index: usize,
+        precomputed_points: &[Vec<Vec<PublicKey>>],
+    ) -> Result<Vec<(usize, PublicKey)>, Error> {
+        self.iter()
+            .filter(|x| x.value.cet_index == cet_index)
+            .map(|x| {
+                let adaptor_point = utils::get_adaptor_point_for_indexed_paths(
+                    &x.indexes,
+                    &x.paths,
+                    precomputed_points,
+                )?;
+                Ok((x.value.adaptor_index, adaptor_point))
+            })
+            .collect()
+    }
+
+    /// Returns the oracle indexes and the digit paths used to compute the
+    /// adaptor point of the adaptor signature at the given index, if any.
+    fn get_oracle_paths_for_adaptor_index(
+        &'a self,
+        adaptor_index: usize,
+    ) -> Option<Vec<(usize, Vec<usize>)>> {
+        self.iter()
+            .find(|x| x.value.adaptor_index == adaptor_index)
+            .map(|x| x.indexes.into_iter().zip(x.paths.into_iter()).collect())
+    }
+
+    /// Returns the CET and adaptor signature indexes of all the paths of the
+    /// trie, in iteration order.
+    fn get_range_infos(&'a self) -> Vec<RangeInfo> {
+        self.iter().map(|x| x.value).collect()
+    }
 }
 
 #[derive(Debug)]
@@ -187,25 +243,28 @@ pub struct TrieIterInfo {
 }
 
 #[cfg(not(feature = "parallel"))]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
 fn sign_helper<T: Iterator<Item = TrieIterInfo>>(
     secp: &Secp256k1<All>,
-    cets: &[Transaction],
+    cets: &CetProvider,
     fund_privkey: &SecretKey,
     funding_script_pubkey: &Script,
     fund_output_value: u64,
     precomputed_points: &[Vec<Vec<PublicKey>>],
     trie_info: T,
 ) -> Result<Vec<EcdsaAdaptorSignature>, Error> {
+    let mut keys = Vec::new();
     let mut unsorted = trie_info
         .map(|x| {
-            let adaptor_point = utils::get_adaptor_point_for_indexed_paths(
+            let adaptor_point = utils::get_adaptor_point_for_indexed_paths_with_scratch(
                 &x.indexes,
                 &x.paths,
                 precomputed_points,
+                &mut keys,
             )?;
             let adaptor_sig = dlc::create_cet_adaptor_sig_from_point(
                 secp,
-                &cets[x.value.cet_index],
+                &cets(x.value.cet_index)?,
                 &adaptor_point,
                 fund_privkey,
                 funding_script_pubkey,
@@ -219,9 +278,10 @@ fn sign_helper<T: Iterator<Item = TrieIterInfo>>(
 }
 
 #[cfg(feature = "parallel")]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
 fn sign_helper<T: Iterator<Item = TrieIterInfo>>(
     secp: &Secp256k1<All>,
-    cets: &[Transaction],
+    cets: &CetProvider,
     fund_privkey: &SecretKey,
     funding_script_pubkey: &Script,
     fund_output_value: u64,
@@ -231,15 +291,16 @@ fn sign_helper<T: Iterator<Item = TrieIterInfo>>(
     let trie_info: Vec<TrieIterInfo> = trie_info.collect();
     let mut unsorted = trie_info
         .par_iter()
-        .map(|x| {
-            let adaptor_point = utils::get_adaptor_point_for_indexed_paths(
+        .map_init(Vec::new, |keys, x| {
+            let adaptor_point = utils::get_adaptor_point_for_indexed_paths_with_scratch(
                 &x.indexes,
                 &x.paths,
                 precomputed_points,
+                keys,
             )?;
             let adaptor_sig = dlc::create_cet_adaptor_sig_from_point(
                 secp,
-                &cets[x.value.cet_index],
+                &cets(x.value.cet_index)?,
                 &adaptor_point,
                 fund_privkey,
                 funding_script_pubkey,
@@ -253,9 +314,10 @@ fn sign_helper<T: Iterator<Item = TrieIterInfo>>(
 }
 
 #[cfg(not(feature = "parallel"))]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
 fn verify_helper<T: Iterator<Item = TrieIterInfo>>(
     secp: &Secp256k1<All>,
-    cets: &[Transaction],
+    cets: &CetProvider,
     adaptor_sigs: &[EcdsaAdaptorSignature],
     fund_pubkey: &PublicKey,
     funding_script_pubkey: &Script,
@@ -264,31 +326,45 @@ fn verify_helper<T: Iterator<Item = TrieIterInfo>>(
     trie_info: T,
 ) -> Result<usize, Error> {
     let mut max_adaptor_index = 0;
-    for x in trie_info {
-        let adaptor_point =
-            utils::get_adaptor_point_for_indexed_paths(&x.indexes, &x.paths, precomputed_points)?;
-        let adaptor_sig = adaptor_sigs[x.value.adaptor_index];
-        let cet = &cets[x.value.cet_index];
-        if x.value.adaptor_index > max_adaptor_index {
-            max_adaptor_index = x.value.adaptor_index;
-        }
-        dlc::verify_cet_adaptor_sig_from_point(
-            secp,
-            &adaptor_sig,
-            cet,
-            &adaptor_point,
-            fund_pubkey,
-            funding_script_pubkey,
-            fund_output_value,
-        )?;
-    }
+    let mut keys = Vec::new();
+    let batch = trie_info
+        .map(|x| {
+            let adaptor_point = utils::get_adaptor_point_for_indexed_paths_with_scratch(
+                &x.indexes,
+                &x.paths,
+                precomputed_points,
+                &mut keys,
+            )?;
+            if x.value.adaptor_index > max_adaptor_index {
+                max_adaptor_index = x.value.adaptor_index;
+            }
+            Ok((
+                x.value.cet_index,
+                adaptor_sigs[x.value.adaptor_index],
+                adaptor_point,
+            ))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    dlc::verify_batch(
+        secp,
+        cets,
+        &batch,
+        fund_pubkey,
+        funding_script_pubkey,
+        fund_output_value,
+    )?;
     Ok(max_adaptor_index + 1)
 }
 
+/// The number of adaptor signatures verified in a single batch by each thread.
+#[cfg(feature = "parallel")]
+const VERIFY_BATCH_SIZE: usize = 256;
+
 #[cfg(feature = "parallel")]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
 fn verify_helper<T: Iterator<Item = TrieIterInfo>>(
     secp: &Secp256k1<All>,
-    cets: &[Transaction],
+    cets: &CetProvider,
     adaptor_sigs: &[EcdsaAdaptorSignature],
     fund_pubkey: &PublicKey,
     funding_script_pubkey: &Script,
@@ -301,21 +377,37 @@ fn verify_helper<T: Iterator<Item = TrieIterInfo>>(
         .iter()
         .max_by(|x, y| x.value.adaptor_index.cmp(&y.value.adaptor_index))
         .unwrap();
-    trie_info.par_iter().try_for_each(|x| {
-        let adaptor_point =
-            utils::get_adaptor_point_for_indexed_paths(&x.indexes, &x.paths, precomputed_points)?;
-        let adaptor_sig = adaptor_sigs[x.value.adaptor_index];
-        let cet = &cets[x.value.cet_index];
-        dlc::verify_cet_adaptor_sig_from_point(
-            secp,
-            &adaptor_sig,
-            cet,
-            &adaptor_point,
-            fund_pubkey,
-            funding_script_pubkey,
-            fund_output_value,
-        )
-    })?;
+    // Chunks are verified as batches, as consecutive entries mostly relate to
+    // the same CETs.
+    trie_info
+        .par_chunks(VERIFY_BATCH_SIZE)
+        .try_for_each(|chunk| {
+            let mut keys = Vec::new();
+            let batch = chunk
+                .iter()
+                .map(|x| {
+                    let adaptor_point = utils::get_adaptor_point_for_indexed_paths_with_scratch(
+                        &x.indexes,
+                        &x.paths,
+                        precomputed_points,
+                        &mut keys,
+                    )?;
+                    Ok((
+                        x.value.cet_index,
+                        adaptor_sigs[x.value.adaptor_index],
+                        adaptor_point,
+                    ))
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            dlc::verify_batch(
+                secp,
+                cets,
+                &batch,
+                fund_pubkey,
+                funding_script_pubkey,
+                fund_output_value,
+            )
+        })?;
 
     Ok(max_adaptor_index.value.adaptor_index + 1)
 }
@@ -2,6 +2,44 @@
 //! multi oracle DLC.
 
 use digit_decomposition::{compose_value, decompose_value};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Strategy used to choose the outcomes of the secondary oracles covered
+/// together with an outcome of the main oracle, between the ones within the
+/// min support that are always covered and the ones beyond the max error that
+/// never are.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub enum CoverageStrategy {
+    /// Covers as many outcomes within the max error as possible, increasing
+    /// the probability of the contract being closeable.
+    MaximizeSupport,
+    /// Only covers the outcomes required to guarantee the min support.
+    MinimizeCetCount,
+    /// Guarantees a support of `2^support_exp`, which must be greater than or
+    /// equal to the min support and smaller than the max error.
+    Custom {
+        /// The guaranteed support as a power of 2.
+        support_exp: usize,
+    },
+}
+
+impl CoverageStrategy {
+    /// Returns whether the strategy can be used with the given exponents.
+    pub fn is_valid_for(&self, max_error_exp: usize, min_support_exp: usize) -> bool {
+        match self {
+            CoverageStrategy::Custom { support_exp } => {
+                min_support_exp <= *support_exp && *support_exp < max_error_exp
+            }
+            _ => min_support_exp < max_error_exp,
+        }
+    }
+}
 
 /// Returns the interval represented by the given prefix in the given base with
 /// the given number of digits.
@@ -92,221 +130,281 @@ fn compute_right_covering_prefix(
         .collect()
 }
 
-fn single_covering_prefix_combinations(
-    main_outcome_prefix: &[usize],
-    secondary_outcomes_prefix: &[usize],
-    nb_oracles: usize,
-) -> Vec<Vec<usize>> {
-    let mut secondary = (0..nb_oracles - 1)
-        .map(|_| secondary_outcomes_prefix.to_vec())
-        .collect::<Vec<Vec<_>>>();
-    let mut res = vec![main_outcome_prefix.to_vec()];
-    res.append(&mut secondary);
-    res
-}
-
-/// All combinations of main outcome and other except for the one that contains
-/// only main outcome.
-fn double_covering_restricted_prefix_combinations(
-    main_outcome_prefix: &[usize],
-    other_interval_prefix: &[usize],
-    nb_oracles: usize,
-) -> Vec<Vec<Vec<usize>>> {
-    let mut combinations = double_covering_prefix_combinations(
-        main_outcome_prefix,
-        main_outcome_prefix,
-        other_interval_prefix,
-        nb_oracles,
-    );
-
-    if main_outcome_prefix > other_interval_prefix {
-        combinations.remove(combinations.len() - 1);
-        combinations
+/// Returns the given prefixes ordered lexicographically.
+fn ordered_prefixes(first: Vec<usize>, second: Vec<usize>) -> Vec<Vec<usize>> {
+    if first <= second {
+        vec![first, second]
     } else {
-        combinations.into_iter().skip(1).collect::<Vec<_>>()
+        vec![second, first]
     }
 }
 
-/// Generates all the combination of prefixes starting with `main_outcome_prefix`
-/// with `left_interval_prefix` and `right_interval_prefix` in lexicographic
-/// order.
-fn double_covering_prefix_combinations(
+/// Generates all the combinations of `main_outcome_prefix` with one of the
+/// prefixes of each secondary oracle in lexicographic order.
+fn prefix_combinations(
     main_outcome_prefix: &[usize],
-    left_interval_prefix: &[usize],
-    right_interval_prefix: &[usize],
-    nb_oracles: usize,
+    secondary_prefixes: &[Vec<Vec<usize>>],
 ) -> Vec<Vec<Vec<usize>>> {
-    let mut res = Vec::with_capacity(nb_oracles);
-    let (first, second) = if left_interval_prefix <= right_interval_prefix {
-        (left_interval_prefix, right_interval_prefix)
-    } else {
-        (right_interval_prefix, left_interval_prefix)
-    };
-
-    for i in 0..(1 << (nb_oracles - 1)) {
-        let mut mid_res = Vec::with_capacity(nb_oracles);
-        for j in 0..(nb_oracles - 1) {
-            let val: Vec<usize> = match i & (1 << j) {
-                0 => first.to_vec(),
-                _ => second.to_vec(),
-            };
-            mid_res.push(val);
-        }
-        mid_res.push(main_outcome_prefix.to_vec());
-        mid_res.reverse();
-        res.push(mid_res);
+    let mut res = vec![vec![main_outcome_prefix.to_vec()]];
+    for prefixes in secondary_prefixes {
+        res = res
+            .into_iter()
+            .flat_map(|combination| {
+                prefixes.iter().map(move |prefix| {
+                    let mut combination = combination.clone();
+                    combination.push(prefix.clone());
+                    combination
+                })
+            })
+            .collect();
     }
-
     res
 }
 
-/// Compute the outcome combinations required to cover intervals that will
-/// satisfy the specified min support and max error parameters.
-pub fn compute_outcome_combinations(
+/// The prefixes that a secondary oracle can attest to given the interval of
+/// the main outcome prefix.
+enum SecondaryPrefixes {
+    /// The interval is strictly smaller than the max error, so the given
+    /// prefixes can be paired with any outcome of the interval.
+    Covering(Vec<Vec<usize>>),
+    /// Otherwise, the number of ignored digits of the prefixes to use around
+    /// the bounds of the interval.
+    Bounds(usize),
+}
+
+/// Returns the prefixes of each secondary oracle, using `bound_prefixes` to
+/// get the ones around an interval bound from their number of ignored digits.
+fn get_secondary_prefixes<F>(
+    secondaries: &[SecondaryPrefixes],
+    bound_prefixes: F,
+) -> Vec<Vec<Vec<usize>>>
+where
+    F: Fn(usize) -> Vec<Vec<usize>>,
+{
+    secondaries
+        .iter()
+        .map(|secondary| match secondary {
+            SecondaryPrefixes::Covering(prefixes) => prefixes.clone(),
+            SecondaryPrefixes::Bounds(ignored) => bound_prefixes(*ignored),
+        })
+        .collect()
+}
+
+/// Compute the prefixes covering an interval strictly smaller than the max
+/// error, ordered lexicographically.
+fn compute_covering_prefixes(
+    start: usize,
+    end: usize,
     nb_digits: usize,
-    main_outcome_prefix: &[usize],
     max_error_exp: usize,
     min_support_exp: usize,
     maximize_coverage: bool,
-    nb_oracles: usize,
-) -> Vec<Vec<Vec<usize>>> {
-    assert!(nb_oracles > 1 && max_error_exp > min_support_exp);
-
+) -> Vec<Vec<usize>> {
     let max_num: usize = (1 << nb_digits) - 1;
     let max_error: usize = 1 << max_error_exp;
     let half_max_error: usize = max_error >> 1;
     let min_support: usize = 1 << min_support_exp;
-    let suffix_len = nb_digits - main_outcome_prefix.len();
 
-    let (start, end) = compute_interval_from_prefix(main_outcome_prefix, nb_digits, 2);
+    let start_max_error_suffix = start & ((1 << max_error_exp) - 1);
+    let left_bound = (start >> max_error_exp) << max_error_exp;
+    let right_bound = left_bound | (max_error - 1);
+    let error_interval_prefix = num_to_vec(left_bound, nb_digits, max_error_exp, 2);
 
-    // interval length is strictly smaller than max_error
-    if suffix_len < max_error_exp {
-        let start_max_error_suffix = start & ((1 << max_error_exp) - 1);
-        let left_bound = (start >> max_error_exp) << max_error_exp;
-        let right_bound = left_bound | (max_error - 1);
-        let error_interval_prefix = num_to_vec(left_bound, nb_digits, max_error_exp, 2);
-
-        // interval length is less than or equal to min_support
-        if start_max_error_suffix >= min_support && end <= right_bound - min_support {
-            let support_interval_prefix = if maximize_coverage {
-                error_interval_prefix
-            } else {
-                compute_min_support_covering_prefix(start, end, min_support, nb_digits)
-            };
+    // interval length is less than or equal to min_support
+    if start_max_error_suffix >= min_support && end <= right_bound - min_support {
+        let support_interval_prefix = if maximize_coverage {
+            error_interval_prefix
+        } else {
+            compute_min_support_covering_prefix(start, end, min_support, nb_digits)
+        };
 
-            return vec![single_covering_prefix_combinations(
-                main_outcome_prefix,
-                &support_interval_prefix,
-                nb_oracles,
-            )];
-        } else if start_max_error_suffix < min_support {
-            let right_interval_prefix = if maximize_coverage {
-                error_interval_prefix
-            } else {
-                compute_right_covering_prefix(end, max_error_exp, min_support, nb_digits)
-            };
+        vec![support_interval_prefix]
+    } else if start_max_error_suffix < min_support {
+        let right_interval_prefix = if maximize_coverage {
+            error_interval_prefix
+        } else {
+            compute_right_covering_prefix(end, max_error_exp, min_support, nb_digits)
+        };
 
-            return if left_bound == 0 {
-                vec![single_covering_prefix_combinations(
-                    main_outcome_prefix,
-                    &right_interval_prefix,
-                    nb_oracles,
-                )]
-            } else {
-                let left_interval_prefix = if maximize_coverage {
-                    num_to_vec(left_bound - half_max_error, nb_digits, max_error_exp - 1, 2)
-                } else {
-                    compute_left_covering_prefix(start, max_error_exp, min_support, nb_digits)
-                };
-                double_covering_prefix_combinations(
-                    main_outcome_prefix,
-                    &right_interval_prefix,
-                    &left_interval_prefix,
-                    nb_oracles,
-                )
-            };
-        } else if end > right_bound - min_support {
+        if left_bound == 0 {
+            vec![right_interval_prefix]
+        } else {
             let left_interval_prefix = if maximize_coverage {
-                error_interval_prefix
+                num_to_vec(left_bound - half_max_error, nb_digits, max_error_exp - 1, 2)
             } else {
                 compute_left_covering_prefix(start, max_error_exp, min_support, nb_digits)
             };
+            ordered_prefixes(right_interval_prefix, left_interval_prefix)
+        }
+    } else if end > right_bound - min_support {
+        let left_interval_prefix = if maximize_coverage {
+            error_interval_prefix
+        } else {
+            compute_left_covering_prefix(start, max_error_exp, min_support, nb_digits)
+        };
 
-            return if right_bound == max_num {
-                vec![single_covering_prefix_combinations(
-                    main_outcome_prefix,
-                    &left_interval_prefix,
-                    nb_oracles,
-                )]
+        if right_bound == max_num {
+            vec![left_interval_prefix]
+        } else {
+            let right_interval_prefix = if maximize_coverage {
+                num_to_vec(right_bound + 1, nb_digits, max_error_exp - 1, 2)
             } else {
-                let right_interval_prefix = if maximize_coverage {
-                    num_to_vec(right_bound + 1, nb_digits, max_error_exp - 1, 2)
-                } else {
-                    compute_right_covering_prefix(end, max_error_exp, min_support, nb_digits)
-                };
-
-                double_covering_prefix_combinations(
-                    main_outcome_prefix,
-                    &left_interval_prefix,
-                    &right_interval_prefix,
-                    nb_oracles,
-                )
+                compute_right_covering_prefix(end, max_error_exp, min_support, nb_digits)
             };
-        } else {
-            unreachable!();
+            ordered_prefixes(left_interval_prefix, right_interval_prefix)
         }
+    } else {
+        unreachable!();
     }
+}
 
-    let mut res = Vec::new();
+/// Generates the combinations of `main_outcome_prefix` with the prefixes of
+/// the secondary oracles around one of the interval bounds, except the ones
+/// where all the secondary oracles using bound prefixes attest to the prefix
+/// at `inner_index`, as they are included in the interval.
+fn bound_prefix_combinations(
+    main_outcome_prefix: &[usize],
+    secondaries: &[SecondaryPrefixes],
+    secondary_prefixes: &[Vec<Vec<usize>>],
+    inner_index: usize,
+) -> Vec<Vec<Vec<usize>>> {
+    prefix_combinations(main_outcome_prefix, secondary_prefixes)
+        .into_iter()
+        .filter(|combination| {
+            secondaries
+                .iter()
+                .enumerate()
+                .any(|(i, secondary)| match secondary {
+                    SecondaryPrefixes::Bounds(_) => {
+                        combination[i + 1] != secondary_prefixes[i][inner_index]
+                    }
+                    SecondaryPrefixes::Covering(_) => false,
+                })
+        })
+        .collect()
+}
 
-    if start != 0 {
-        let right_interval_prefix = if maximize_coverage {
-            num_to_vec(start, nb_digits, max_error_exp - 1, 2)
-        } else {
-            num_to_vec(start, nb_digits, min_support_exp, 2)
-        };
+/// Compute the outcome combinations required to cover intervals that will
+/// satisfy the specified min support and max error parameters.
+pub fn compute_outcome_combinations(
+    nb_digits: usize,
+    main_outcome_prefix: &[usize],
+    max_error_exp: usize,
+    min_support_exp: usize,
+    coverage_strategy: CoverageStrategy,
+    nb_oracles: usize,
+) -> Vec<Vec<Vec<usize>>> {
+    assert!(nb_oracles > 1);
+    compute_outcome_combinations_with_pair_exps(
+        nb_digits,
+        main_outcome_prefix,
+        &vec![(max_error_exp, min_support_exp); nb_oracles - 1],
+        coverage_strategy,
+    )
+}
 
-        let left_interval_prefix = if maximize_coverage {
-            num_to_vec(start - half_max_error, nb_digits, max_error_exp - 1, 2)
-        } else {
-            num_to_vec(start - min_support, nb_digits, min_support_exp, 2)
-        };
+/// Compute the outcome combinations required to cover intervals that will
+/// satisfy the min support and max error parameters of each pair formed by
+/// the main oracle and a secondary one. `pair_exps` contains the max error and
+/// min support exponents of each secondary oracle. When the parameters differ,
+/// the outcome prefixes of the main oracle around the interval bounds are the
+/// ones required by the pair with the lowest tolerance, so the coverage of
+/// the other pairs can be reduced close to the bounds.
+pub fn compute_outcome_combinations_with_pair_exps(
+    nb_digits: usize,
+    main_outcome_prefix: &[usize],
+    pair_exps: &[(usize, usize)],
+    coverage_strategy: CoverageStrategy,
+) -> Vec<Vec<Vec<usize>>> {
+    assert!(
+        !pair_exps.is_empty()
+            && pair_exps
+                .iter()
+                .all(|(max_error_exp, min_support_exp)| coverage_strategy
+                    .is_valid_for(*max_error_exp, *min_support_exp))
+    );
 
-        let mut combination = double_covering_restricted_prefix_combinations(
-            &right_interval_prefix,
-            &left_interval_prefix,
-            nb_oracles,
-        );
+    let max_num: usize = (1 << nb_digits) - 1;
+    let suffix_len = nb_digits - main_outcome_prefix.len();
 
-        res.append(&mut combination);
-    }
+    let (start, end) = compute_interval_from_prefix(main_outcome_prefix, nb_digits, 2);
 
-    res.push(single_covering_prefix_combinations(
-        main_outcome_prefix,
+    let secondaries = pair_exps
+        .iter()
+        .map(|(max_error_exp, min_support_exp)| {
+            let (maximize_coverage, support_exp) = match coverage_strategy {
+                CoverageStrategy::MaximizeSupport => (true, *min_support_exp),
+                CoverageStrategy::MinimizeCetCount => (false, *min_support_exp),
+                CoverageStrategy::Custom { support_exp } => (false, support_exp),
+            };
+            // interval length is strictly smaller than max_error
+            if suffix_len < *max_error_exp {
+                SecondaryPrefixes::Covering(compute_covering_prefixes(
+                    start,
+                    end,
+                    nb_digits,
+                    *max_error_exp,
+                    support_exp,
+                    maximize_coverage,
+                ))
+            } else if maximize_coverage {
+                SecondaryPrefixes::Bounds(max_error_exp - 1)
+            } else {
+                SecondaryPrefixes::Bounds(support_exp)
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let main_ignored = secondaries
+        .iter()
+        .filter_map(|secondary| match secondary {
+            SecondaryPrefixes::Bounds(ignored) => Some(*ignored),
+            SecondaryPrefixes::Covering(_) => None,
+        })
+        .min();
+
+    let main_combinations = prefix_combinations(
         main_outcome_prefix,
-        nb_oracles,
-    ));
+        &get_secondary_prefixes(&secondaries, |_| vec![main_outcome_prefix.to_vec()]),
+    );
 
-    if end != max_num {
-        let right_interval_prefix = if maximize_coverage {
-            num_to_vec(end - half_max_error + 1, nb_digits, max_error_exp - 1, 2)
-        } else {
-            num_to_vec(end - min_support + 1, nb_digits, min_support_exp, 2)
-        };
+    let main_ignored = match main_ignored {
+        Some(main_ignored) => main_ignored,
+        None => return main_combinations,
+    };
 
-        let left_interval_prefix = if maximize_coverage {
-            num_to_vec(end + 1, nb_digits, max_error_exp - 1, 2)
-        } else {
-            num_to_vec(end + 1, nb_digits, min_support_exp, 2)
-        };
+    let mut res = Vec::new();
 
-        let mut combination = double_covering_restricted_prefix_combinations(
-            &right_interval_prefix,
-            &left_interval_prefix,
-            nb_oracles,
-        );
-        res.append(&mut combination);
+    if start != 0 {
+        let secondary_prefixes = get_secondary_prefixes(&secondaries, |ignored| {
+            vec![
+                num_to_vec(start - (1 << ignored), nb_digits, ignored, 2),
+                num_to_vec(start, nb_digits, ignored, 2),
+            ]
+        });
+        res.append(&mut bound_prefix_combinations(
+            &num_to_vec(start, nb_digits, main_ignored, 2),
+            &secondaries,
+            &secondary_prefixes,
+            1,
+        ));
+    }
+
+    res.extend(main_combinations);
+
+    if end != max_num {
+        let secondary_prefixes = get_secondary_prefixes(&secondaries, |ignored| {
+            vec![
+                num_to_vec(end - (1 << ignored) + 1, nb_digits, ignored, 2),
+                num_to_vec(end + 1, nb_digits, ignored, 2),
+            ]
+        });
+        res.append(&mut bound_prefix_combinations(
+            &num_to_vec(end - (1 << main_ignored) + 1, nb_digits, main_ignored, 2),
+            &secondaries,
+            &secondary_prefixes,
+            0,
+        ));
     }
 
     res
@@ -328,7 +426,7 @@ mod tests {
             main_outcome_prefix,
             max_error_exp,
             min_support_exp,
-            true,
+            CoverageStrategy::MaximizeSupport,
             2,
         );
         let covering_min = compute_outcome_combinations(
@@ -336,7 +434,7 @@ mod tests {
             main_outcome_prefix,
             max_error_exp,
             min_support_exp,
-            false,
+            CoverageStrategy::MinimizeCetCount,
             2,
         );
 
@@ -496,7 +594,8 @@ mod tests {
     fn compute_outcome_three_oracles() {
         let prefix = vec![0, 1, 0];
 
-        let res = compute_outcome_combinations(3, &prefix, 2, 1, true, 3);
+        let res =
+            compute_outcome_combinations(3, &prefix, 2, 1, CoverageStrategy::MaximizeSupport, 3);
 
         let expected = vec![
             vec![vec![0, 1, 0], vec![0], vec![0]],
@@ -508,6 +607,43 @@ mod tests {
         assert_eq!(res, expected);
     }
 
+    #[test]
+    fn custom_coverage_guarantees_given_support() {
+        let nb_digits = 6;
+        for prefix in outcome_prefixes().iter().filter(|x| x.len() <= nb_digits) {
+            let custom = compute_outcome_combinations(
+                nb_digits,
+                prefix,
+                4,
+                1,
+                CoverageStrategy::Custom { support_exp: 2 },
+                3,
+            );
+            let expected = compute_outcome_combinations(
+                nb_digits,
+                prefix,
+                4,
+                2,
+                CoverageStrategy::MinimizeCetCount,
+                3,
+            );
+            assert_eq!(expected, custom);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn custom_coverage_above_max_error_panics() {
+        compute_outcome_combinations(
+            6,
+            &[0, 1],
+            4,
+            1,
+            CoverageStrategy::Custom { support_exp: 4 },
+            2,
+        );
+    }
+
     #[test]
     fn multiple_interval_within_bounds() {
         let mut rng = thread_rng();
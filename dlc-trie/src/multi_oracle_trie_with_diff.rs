@@ -4,7 +4,8 @@
 //! between the outcomes of each oracle can be supported.
 
 use crate::digit_decomposition::group_by_ignoring_digits;
-use crate::multi_trie::{MultiTrie, MultiTrieDump, MultiTrieIterator};
+use crate::multi_oracle::CoverageStrategy;
+use crate::multi_trie::{MultiTrie, MultiTrieDump, MultiTrieIterator, OraclePairExps};
 
 use crate::RangeInfo;
 use crate::{DlcTrie, TrieIterInfo};
@@ -30,15 +31,41 @@ impl MultiOracleTrieWithDiff {
         nb_digits: usize,
         min_support_exp: usize,
         max_error_exp: usize,
+        coverage_strategy: CoverageStrategy,
     ) -> Self {
-        let multi_trie = MultiTrie::new(
+        Self::new_with_pair_exps(
+            base,
+            nb_oracles,
+            threshold,
+            nb_digits,
+            min_support_exp,
+            max_error_exp,
+            coverage_strategy,
+            Vec::new(),
+        )
+    }
+
+    /// Create a new MultiOracleTrieWithDiff where the pairs of oracles found
+    /// in `pair_exps` support a specific difference between their outcomes.
+    pub fn new_with_pair_exps(
+        base: usize,
+        nb_oracles: usize,
+        threshold: usize,
+        nb_digits: usize,
+        min_support_exp: usize,
+        max_error_exp: usize,
+        coverage_strategy: CoverageStrategy,
+        pair_exps: Vec<OraclePairExps>,
+    ) -> Self {
+        let multi_trie = MultiTrie::new_with_pair_exps(
             nb_oracles,
             threshold,
             base,
             min_support_exp,
             max_error_exp,
             nb_digits,
-            true,
+            coverage_strategy,
+            pair_exps,
         );
         MultiOracleTrieWithDiff {
             multi_trie,
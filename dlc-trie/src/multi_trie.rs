@@ -5,7 +5,7 @@ use crate::{LookupResult, Node};
 use combination_iterator::CombinationIterator;
 use digit_trie::{DigitTrie, DigitTrieDump, DigitTrieIter};
 use dlc::Error;
-use multi_oracle::compute_outcome_combinations;
+use multi_oracle::{compute_outcome_combinations_with_pair_exps, CoverageStrategy};
 
 #[derive(Clone, Debug)]
 /// Information stored in a node.
@@ -160,6 +160,20 @@ impl<'a, T> Iterator for MultiTrieIterator<'a, T> {
     }
 }
 
+/// Max error and min support exponents to use for a pair of oracles instead
+/// of the default ones of a [`MultiTrie`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OraclePairExps {
+    /// The index of the first oracle of the pair.
+    pub first_index: usize,
+    /// The index of the second oracle of the pair.
+    pub second_index: usize,
+    /// The guaranteed support as a power of 2.
+    pub min_support_exp: usize,
+    /// The maximum support as a power of 2.
+    pub max_error_exp: usize,
+}
+
 /// Struct used to store DLC outcome information for multi oracle cases.  
 #[derive(Clone)]
 pub struct MultiTrie<T> {
@@ -170,7 +184,8 @@ pub struct MultiTrie<T> {
     min_support_exp: usize,
     max_error_exp: usize,
     nb_digits: usize,
-    maximize_coverage: bool,
+    coverage_strategy: CoverageStrategy,
+    pair_exps: Vec<OraclePairExps>,
 }
 
 impl<T> MultiTrie<T> {
@@ -183,9 +198,38 @@ impl<T> MultiTrie<T> {
         min_support_exp: usize,
         max_error_exp: usize,
         nb_digits: usize,
-        maximize_coverage: bool,
+        coverage_strategy: CoverageStrategy,
+    ) -> MultiTrie<T> {
+        MultiTrie::new_with_pair_exps(
+            nb_tries,
+            nb_required,
+            base,
+            min_support_exp,
+            max_error_exp,
+            nb_digits,
+            coverage_strategy,
+            Vec::new(),
+        )
+    }
+
+    /// Create a new MultiTrie where the outcome combinations of the pairs of
+    /// oracles found in `pair_exps` use their specific max error and min
+    /// support exponents. Panics under the same conditions as
+    /// [`MultiTrie::new`], or if a pair refers to an out of range oracle.
+    pub fn new_with_pair_exps(
+        nb_tries: usize,
+        nb_required: usize,
+        base: usize,
+        min_support_exp: usize,
+        max_error_exp: usize,
+        nb_digits: usize,
+        coverage_strategy: CoverageStrategy,
+        pair_exps: Vec<OraclePairExps>,
     ) -> MultiTrie<T> {
         assert!(nb_required > 0 && nb_tries >= nb_required);
+        assert!(pair_exps
+            .iter()
+            .all(|x| x.first_index < nb_tries && x.second_index < nb_tries));
         let nb_roots = nb_tries - nb_required + 1;
         let mut store = Vec::new();
 
@@ -203,10 +247,24 @@ impl<T> MultiTrie<T> {
             min_support_exp,
             max_error_exp,
             nb_digits,
-            maximize_coverage,
+            coverage_strategy,
+            pair_exps,
         }
     }
 
+    /// Returns the max error and min support exponents to use for the given
+    /// pair of oracles.
+    fn get_pair_exps(&self, first_index: usize, second_index: usize) -> (usize, usize) {
+        self.pair_exps
+            .iter()
+            .find(|x| {
+                (x.first_index == first_index && x.second_index == second_index)
+                    || (x.first_index == second_index && x.second_index == first_index)
+            })
+            .map(|x| (x.max_error_exp, x.min_support_exp))
+            .unwrap_or((self.max_error_exp, self.min_support_exp))
+    }
+
     fn swap_remove(&mut self, index: usize) -> MultiTrieNode<T> {
         self.store.push(MultiTrieNode::None);
         self.store.swap_remove(index)
@@ -217,24 +275,41 @@ impl<T> MultiTrie<T> {
     where
         F: FnMut(&[Vec<usize>], &[usize]) -> Result<T, Error>,
     {
-        let combinations = if self.nb_required > 1 {
-            compute_outcome_combinations(
-                self.nb_digits,
-                path,
-                self.max_error_exp,
-                self.min_support_exp,
-                self.maximize_coverage,
-                self.nb_required,
-            )
-        } else {
-            vec![vec![path.to_vec()]]
-        };
-
-        for combination in combinations {
-            let combination_iter = CombinationIterator::new(self.nb_tries, self.nb_required);
-
-            for selector in combination_iter {
-                self.insert_internal(selector[0], &combination, 0, &selector, get_value)?;
+        // The combinations depend on the oracles of each selector when some
+        // pairs have specific parameters. They are inserted by rank and then
+        // by selector, so that the insertion order is the same whether or not
+        // pair parameters are used.
+        let selectors = CombinationIterator::new(self.nb_tries, self.nb_required)
+            .map(|selector| {
+                let combinations = if self.nb_required > 1 {
+                    let pair_exps = selector[1..]
+                        .iter()
+                        .map(|x| self.get_pair_exps(selector[0], *x))
+                        .collect::<Vec<_>>();
+                    compute_outcome_combinations_with_pair_exps(
+                        self.nb_digits,
+                        path,
+                        &pair_exps,
+                        self.coverage_strategy,
+                    )
+                } else {
+                    vec![vec![path.to_vec()]]
+                };
+                (selector, combinations)
+            })
+            .collect::<Vec<_>>();
+
+        let nb_combinations = selectors
+            .iter()
+            .map(|(_, combinations)| combinations.len())
+            .max()
+            .unwrap_or(0);
+
+        for i in 0..nb_combinations {
+            for (selector, combinations) in &selectors {
+                if let Some(combination) = combinations.get(i) {
+                    self.insert_internal(selector[0], combination, 0, selector, get_value)?;
+                }
             }
         }
 
@@ -390,6 +465,36 @@ impl<T> MultiTrie<T> {
             }
         }
     }
+
+    /// Returns a trie with the same structure in which each value is replaced
+    /// by the result of applying `f` to it, e.g. to drop information that is
+    /// no longer needed once a contract is closed.
+    pub fn map_values<U, F>(&self, mut f: F) -> MultiTrie<U>
+    where
+        F: FnMut(&T) -> U,
+    {
+        let store = self
+            .store
+            .iter()
+            .map(|node| match node {
+                Node::Leaf(d_trie) => Node::Leaf(d_trie.map_values(&mut f)),
+                Node::Node(d_trie) => Node::Node(d_trie.clone()),
+                Node::None => Node::None,
+            })
+            .collect();
+
+        MultiTrie {
+            store,
+            base: self.base,
+            nb_tries: self.nb_tries,
+            nb_required: self.nb_required,
+            min_support_exp: self.min_support_exp,
+            max_error_exp: self.max_error_exp,
+            nb_digits: self.nb_digits,
+            coverage_strategy: self.coverage_strategy,
+            pair_exps: self.pair_exps.clone(),
+        }
+    }
 }
 
 fn find_store_index(children: &[TrieNodeInfo], trie_index: usize) -> Option<usize> {
@@ -421,8 +526,10 @@ where
     pub max_error_exp: usize,
     /// The maximum number of digits for a single trie path.
     pub nb_digits: usize,
-    /// Whether this trie maximizes outcome coverage.
-    pub maximize_coverage: bool,
+    /// The strategy used to choose the outcomes covered by this trie.
+    pub coverage_strategy: CoverageStrategy,
+    /// The parameters specific to some pairs of oracles.
+    pub pair_exps: Vec<OraclePairExps>,
 }
 
 impl<T> MultiTrie<T>
@@ -440,7 +547,8 @@ where
             min_support_exp: self.min_support_exp,
             max_error_exp: self.max_error_exp,
             nb_digits: self.nb_digits,
-            maximize_coverage: self.maximize_coverage,
+            coverage_strategy: self.coverage_strategy,
+            pair_exps: self.pair_exps.clone(),
         }
     }
 
@@ -454,11 +562,93 @@ where
             min_support_exp,
             max_error_exp,
             nb_digits,
-            maximize_coverage,
+            coverage_strategy,
+            pair_exps,
+        } = dump;
+
+        let store = node_data
+            .into_iter()
+            .map(|x| MultiTrieNode::from_data(x))
+            .collect();
+
+        MultiTrie {
+            store,
+            base,
+            nb_tries,
+            nb_required,
+            min_support_exp,
+            max_error_exp,
+            nb_digits,
+            coverage_strategy,
+            pair_exps,
+        }
+    }
+
+    /// Dump the content of the trie in chunks of at most `chunk_size` nodes, so
+    /// that large tries can be written to storage as they are serialized. The
+    /// returned dump holds the parameters of the trie but no node data, which
+    /// is yielded by the iterator in order.
+    pub fn dump_chunks<'a>(
+        &'a self,
+        chunk_size: usize,
+    ) -> (
+        MultiTrieDump<T>,
+        impl Iterator<Item = Vec<MultiTrieNodeData<T>>> + 'a,
+    ) {
+        let dump = MultiTrieDump {
+            node_data: Vec::new(),
+            base: self.base,
+            nb_tries: self.nb_tries,
+            nb_required: self.nb_required,
+            min_support_exp: self.min_support_exp,
+            max_error_exp: self.max_error_exp,
+            nb_digits: self.nb_digits,
+            coverage_strategy: self.coverage_strategy,
+            pair_exps: self.pair_exps.clone(),
+        };
+        let chunks = self
+            .store
+            .chunks(chunk_size.max(1))
+            .map(|chunk| chunk.iter().map(|x| x.get_data()).collect());
+        (dump, chunks)
+    }
+
+    /// Removes the values for which `f` returns false, pruning the paths of
+    /// the leaf tries that no longer lead to a value. The paths of the tries of
+    /// the first oracles are kept, lookups through them failing once the
+    /// values they lead to are removed.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        for node in self.store.iter_mut() {
+            if let Node::Leaf(d_trie) = node {
+                d_trie.retain(&mut f);
+            }
+        }
+    }
+
+    /// Restore a trie from a dump obtained with [`MultiTrie::dump_chunks`] and
+    /// its chunks of node data, in order.
+    pub fn from_chunks<I>(dump: MultiTrieDump<T>, chunks: I) -> MultiTrie<T>
+    where
+        I: IntoIterator<Item = Vec<MultiTrieNodeData<T>>>,
+    {
+        let MultiTrieDump {
+            node_data,
+            base,
+            nb_tries,
+            nb_required,
+            min_support_exp,
+            max_error_exp,
+            nb_digits,
+            coverage_strategy,
+            pair_exps,
         } = dump;
 
         let store = node_data
             .into_iter()
+            .chain(chunks.into_iter().flatten())
             .map(|x| MultiTrieNode::from_data(x))
             .collect();
 
@@ -470,7 +660,8 @@ where
             min_support_exp,
             max_error_exp,
             nb_digits,
-            maximize_coverage,
+            coverage_strategy,
+            pair_exps,
         }
     }
 }
@@ -540,7 +731,7 @@ mod tests {
 
     #[test]
     fn multi_trie_1_of_1_test() {
-        let m_trie = MultiTrie::<usize>::new(1, 1, 2, 2, 3, 5, true);
+        let m_trie = MultiTrie::<usize>::new(1, 1, 2, 2, 3, 5, CoverageStrategy::MaximizeSupport);
 
         let path = vec![0, 1, 1, 1];
 
@@ -562,7 +753,7 @@ mod tests {
 
     #[test]
     fn multi_trie_1_of_2_test() {
-        let m_trie = MultiTrie::<usize>::new(2, 1, 2, 2, 3, 5, true);
+        let m_trie = MultiTrie::<usize>::new(2, 1, 2, 2, 3, 5, CoverageStrategy::MaximizeSupport);
 
         let path = vec![0, 1, 1, 1];
 
@@ -587,7 +778,7 @@ mod tests {
 
     #[test]
     fn multi_trie_2_of_2_test() {
-        let m_trie = MultiTrie::<usize>::new(2, 2, 2, 2, 3, 5, true);
+        let m_trie = MultiTrie::<usize>::new(2, 2, 2, 2, 3, 5, CoverageStrategy::MaximizeSupport);
 
         let path = vec![0, 1, 1, 1];
 
@@ -611,9 +802,91 @@ mod tests {
         tests_common(m_trie, path, good_paths, bad_paths, Some(expected_iter));
     }
 
+    #[test]
+    fn multi_trie_pair_exps_test() {
+        let pair_exps = vec![OraclePairExps {
+            first_index: 0,
+            second_index: 2,
+            min_support_exp: 1,
+            max_error_exp: 2,
+        }];
+        let m_trie = MultiTrie::<usize>::new_with_pair_exps(
+            3,
+            2,
+            2,
+            2,
+            3,
+            5,
+            CoverageStrategy::MaximizeSupport,
+            pair_exps,
+        );
+
+        let path = vec![0, 1, 1, 1];
+
+        let good_paths = vec![
+            vec![(0, vec![0, 1, 1, 1, 1]), (1, vec![1, 0, 0, 1, 1])],
+            vec![(0, vec![0, 1, 1, 1, 1]), (2, vec![0, 1, 1, 0, 0])],
+            vec![(0, vec![0, 1, 1, 1, 1]), (2, vec![1, 0, 0, 0, 1])],
+        ];
+
+        let bad_paths = vec![
+            vec![(0, vec![0, 1, 1, 1, 1]), (2, vec![1, 0, 0, 1, 1])],
+            vec![(0, vec![0, 1, 1, 1, 1]), (2, vec![0, 1, 0, 1, 1])],
+        ];
+
+        tests_common(m_trie, path, good_paths, bad_paths, None);
+    }
+
+    #[test]
+    fn multi_trie_restored_from_chunks_test() {
+        let mut m_trie =
+            MultiTrie::<usize>::new(3, 2, 2, 2, 3, 5, CoverageStrategy::MaximizeSupport);
+        let mut get_value = |_: &[Vec<usize>], _: &[usize]| -> Result<usize, Error> { Ok(2) };
+        m_trie.insert(&[0, 1, 1, 1], &mut get_value).unwrap();
+
+        let (dump, chunks) = m_trie.dump_chunks(2);
+        let restored = MultiTrie::from_chunks(dump, chunks.collect::<Vec<_>>());
+
+        let expected = MultiTrieIterator::new(&m_trie)
+            .map(|x| x.path)
+            .collect::<Vec<_>>();
+        let actual = MultiTrieIterator::new(&restored)
+            .map(|x| x.path)
+            .collect::<Vec<_>>();
+        assert!(!expected.is_empty());
+        assert_eq!(expected, actual);
+        assert!(restored
+            .look_up(&[(0, vec![0, 1, 1, 1, 1]), (1, vec![0, 1, 1, 1, 1])])
+            .is_some());
+    }
+
+    #[test]
+    fn multi_trie_map_values_and_retain_test() {
+        let mut m_trie =
+            MultiTrie::<usize>::new(3, 2, 2, 2, 3, 5, CoverageStrategy::MaximizeSupport);
+        let mut counter = 0;
+        let mut get_value = |_: &[Vec<usize>], _: &[usize]| -> Result<usize, Error> {
+            counter += 1;
+            Ok(counter)
+        };
+        m_trie.insert(&[0, 1, 1, 1], &mut get_value).unwrap();
+        let nb_values = MultiTrieIterator::new(&m_trie).count();
+
+        let mapped = m_trie.map_values(|x| x * 2);
+        assert!(MultiTrieIterator::new(&mapped).all(|x| *x.value % 2 == 0));
+        assert_eq!(nb_values, MultiTrieIterator::new(&mapped).count());
+
+        m_trie.retain(|x| *x != 1);
+        assert!(MultiTrieIterator::new(&m_trie).all(|x| *x.value != 1));
+        assert_eq!(nb_values - 1, MultiTrieIterator::new(&m_trie).count());
+
+        m_trie.retain(|_| false);
+        assert_eq!(0, MultiTrieIterator::new(&m_trie).count());
+    }
+
     #[test]
     fn multi_trie_2_of_3_test() {
-        let m_trie = MultiTrie::<usize>::new(3, 2, 2, 2, 3, 5, true);
+        let m_trie = MultiTrie::<usize>::new(3, 2, 2, 2, 3, 5, CoverageStrategy::MaximizeSupport);
 
         let path = vec![0, 1, 1, 1];
 
@@ -637,7 +910,7 @@ mod tests {
 
     #[test]
     fn multi_trie_5_of_5_test() {
-        let m_trie = MultiTrie::<usize>::new(5, 5, 2, 1, 2, 3, true);
+        let m_trie = MultiTrie::<usize>::new(5, 5, 2, 1, 2, 3, CoverageStrategy::MaximizeSupport);
 
         let path = vec![0, 0, 0];
 
@@ -654,7 +927,8 @@ mod tests {
 
     #[test]
     fn multi_3_of_3_test_lexicographic_order() {
-        let mut m_trie = MultiTrie::<usize>::new(3, 3, 2, 1, 2, 3, true);
+        let mut m_trie =
+            MultiTrie::<usize>::new(3, 3, 2, 1, 2, 3, CoverageStrategy::MaximizeSupport);
 
         let inputs = vec![
             vec![0, 0],
@@ -719,13 +993,13 @@ mod tests {
 
     #[test]
     fn multi_3_of_5_test_enumerate_equal_lookup() {
-        let m_trie = MultiTrie::<usize>::new(5, 3, 2, 1, 2, 3, true);
+        let m_trie = MultiTrie::<usize>::new(5, 3, 2, 1, 2, 3, CoverageStrategy::MaximizeSupport);
         multi_enumerate_equal_lookup_common(m_trie);
     }
 
     #[test]
     fn multi_5_of_5_test_enumerate_equal_lookup() {
-        let m_trie = MultiTrie::<usize>::new(5, 5, 2, 1, 2, 3, true);
+        let m_trie = MultiTrie::<usize>::new(5, 5, 2, 1, 2, 3, CoverageStrategy::MaximizeSupport);
         multi_enumerate_equal_lookup_common(m_trie);
     }
 }
@@ -9,6 +9,23 @@ pub(crate) fn get_adaptor_point_for_indexed_paths(
     indexes: &[usize],
     paths: &[Vec<usize>],
     precomputed_points: &[Vec<Vec<PublicKey>>],
+) -> Result<PublicKey, super::Error> {
+    get_adaptor_point_for_indexed_paths_with_scratch(
+        indexes,
+        paths,
+        precomputed_points,
+        &mut Vec::new(),
+    )
+}
+
+/// Same as [`get_adaptor_point_for_indexed_paths`], collecting the points to
+/// combine in the given vector so that its allocation can be reused when
+/// computing many adaptor points.
+pub(crate) fn get_adaptor_point_for_indexed_paths_with_scratch<'a>(
+    indexes: &[usize],
+    paths: &[Vec<usize>],
+    precomputed_points: &'a [Vec<Vec<PublicKey>>],
+    keys: &mut Vec<&'a PublicKey>,
 ) -> Result<PublicKey, super::Error> {
     debug_assert!(indexes.len() == paths.len());
     debug_assert!(precomputed_points.len() >= indexes.len());
@@ -16,17 +33,17 @@ pub(crate) fn get_adaptor_point_for_indexed_paths(
         return Err(super::Error::InvalidArgument);
     }
 
-    let mut keys = Vec::new();
+    keys.clear();
 
     for (i, j) in indexes.iter().enumerate() {
         let path = &paths[i];
-        let k: Vec<&PublicKey> = precomputed_points[*j]
-            .iter()
-            .zip(path.iter())
-            .map(|(y, p)| &y[*p])
-            .collect();
-        keys.extend(k);
+        keys.extend(
+            precomputed_points[*j]
+                .iter()
+                .zip(path.iter())
+                .map(|(y, p)| &y[*p]),
+        );
     }
 
-    Ok(PublicKey::combine_keys(&keys)?)
+    Ok(PublicKey::combine_keys(keys)?)
 }
@@ -0,0 +1,135 @@
+//! # dlc-wasm
+//! WebAssembly bindings enabling web frontends to render payout curves and to
+//! verify offers and oracle signatures client side, before sending them to a
+//! node. Structures are exchanged as JSON strings using the serde
+//! representation of the rust-dlc types, and messages as hex strings of their
+//! wire encoding.
+
+#![crate_name = "dlc_wasm"]
+// Coding conventions
+#![deny(non_upper_case_globals)]
+#![deny(non_camel_case_types)]
+#![deny(non_snake_case)]
+#![deny(unused_mut)]
+#![deny(dead_code)]
+#![deny(unused_imports)]
+#![deny(missing_docs)]
+
+extern crate dlc_manager;
+extern crate dlc_messages;
+extern crate lightning;
+extern crate secp256k1_zkp;
+extern crate serde;
+extern crate serde_json;
+extern crate wasm_bindgen;
+
+use dlc_manager::contract::offered_contract::OfferedContract;
+use dlc_manager::payout_curve::{PayoutFunction, RoundingIntervals};
+use dlc_messages::oracle_msgs::{OracleAnnouncement, OracleAttestation};
+use dlc_messages::OfferDlc;
+use lightning::util::ser::Readable;
+use secp256k1_zkp::bitcoin_hashes::hex::FromHex;
+use secp256k1_zkp::{PublicKey, Secp256k1};
+use serde_json::{json, Value};
+use std::str::FromStr;
+use wasm_bindgen::prelude::*;
+
+/// Returns the range payouts of the given payout function (as JSON) for the
+/// given rounding intervals (as JSON) and total collateral, as a JSON array of
+/// `{start, count, offer, accept}` objects.
+#[wasm_bindgen(js_name = getRangePayouts)]
+pub fn get_range_payouts(
+    payout_function: &str,
+    rounding_intervals: &str,
+    total_collateral: u64,
+) -> Result<String, JsValue> {
+    let payout_function: PayoutFunction = from_json(payout_function)?;
+    let rounding_intervals: RoundingIntervals = from_json(rounding_intervals)?;
+    let range_payouts: Vec<Value> = payout_function
+        .to_range_payouts(total_collateral, &rounding_intervals)
+        .iter()
+        .map(|x| {
+            json!({
+                "start": x.start,
+                "count": x.count,
+                "offer": x.payout.offer,
+                "accept": x.payout.accept,
+            })
+        })
+        .collect();
+    Ok(Value::Array(range_payouts).to_string())
+}
+
+/// Samples the given payout function (as JSON) at `nb_points` evenly
+/// distributed outcomes, returning a JSON array of `[outcome, payout]` pairs
+/// that can be used to plot it.
+#[wasm_bindgen(js_name = samplePayoutFunction)]
+pub fn sample_payout_function(payout_function: &str, nb_points: usize) -> Result<String, JsValue> {
+    let payout_function: PayoutFunction = from_json(payout_function)?;
+    to_json(&payout_function.sample(nb_points))
+}
+
+/// Parses the given offer message (as hex, without type prefix) and checks
+/// that its contract information and oracle announcements are valid,
+/// returning the offer as JSON. `counter_party` is the hex encoded public key
+/// of the node that sent the offer.
+#[wasm_bindgen(js_name = parseOffer)]
+pub fn parse_offer(offer: &str, counter_party: &str) -> Result<String, JsValue> {
+    let offer: OfferDlc = from_hex(offer)?;
+    let counter_party = PublicKey::from_str(counter_party).map_err(to_js_error)?;
+    let offered_contract =
+        OfferedContract::try_from_offer_dlc(&offer, counter_party).map_err(to_js_error)?;
+
+    let secp = Secp256k1::verification_only();
+    for contract_info in &offered_contract.contract_info {
+        contract_info
+            .validate(offered_contract.total_collateral)
+            .map_err(to_js_error)?;
+        for announcement in &contract_info.oracle_announcements {
+            announcement.validate(&secp).map_err(to_js_error)?;
+        }
+    }
+
+    to_json(&offer)
+}
+
+/// Checks the signature of the given oracle announcement (as hex), returning
+/// the announcement as JSON.
+#[wasm_bindgen(js_name = verifyAnnouncement)]
+pub fn verify_announcement(announcement: &str) -> Result<String, JsValue> {
+    let announcement: OracleAnnouncement = from_hex(announcement)?;
+    announcement
+        .validate(&Secp256k1::verification_only())
+        .map_err(to_js_error)?;
+    to_json(&announcement)
+}
+
+/// Checks that the given attestation (as hex) was signed by the oracle of the
+/// given announcement (as hex), returning the attested outcomes as JSON.
+#[wasm_bindgen(js_name = verifyAttestation)]
+pub fn verify_attestation(attestation: &str, announcement: &str) -> Result<String, JsValue> {
+    let attestation: OracleAttestation = from_hex(attestation)?;
+    let announcement: OracleAnnouncement = from_hex(announcement)?;
+    attestation
+        .validate(&Secp256k1::verification_only(), &announcement)
+        .map_err(to_js_error)?;
+    to_json(&attestation.outcomes)
+}
+
+fn from_hex<T: Readable>(hex: &str) -> Result<T, JsValue> {
+    let buf = Vec::<u8>::from_hex(hex).map_err(to_js_error)?;
+    Readable::read(&mut std::io::Cursor::new(buf))
+        .map_err(|e| JsValue::from_str(&format!("Invalid message: {:?}", e)))
+}
+
+fn from_json<'a, T: serde::Deserialize<'a>>(json: &'a str) -> Result<T, JsValue> {
+    serde_json::from_str(json).map_err(to_js_error)
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<String, JsValue> {
+    serde_json::to_string(value).map_err(to_js_error)
+}
+
+fn to_js_error<E: std::fmt::Display>(e: E) -> JsValue {
+    JsValue::from_str(&e.to_string())
+}
@@ -18,26 +18,32 @@ extern crate secp256k1_sys;
 extern crate secp256k1_zkp;
 #[cfg(feature = "serde")]
 extern crate serde;
+extern crate unicode_normalization;
 
 use bitcoin::blockdata::{
     opcodes,
     script::{Builder, Script},
     transaction::{OutPoint, Transaction, TxIn, TxOut},
 };
+use bitcoin::util::psbt::PartiallySignedTransaction;
 use secp256k1_zkp::schnorrsig::{PublicKey as SchnorrPublicKey, Signature as SchnorrSignature};
 use secp256k1_zkp::EcdsaAdaptorSignature;
 use secp256k1_zkp::{Message, PublicKey, Secp256k1, SecretKey, Signature, Verification};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt;
+use unicode_normalization::UnicodeNormalization;
 
+pub mod ownership_proof;
 pub mod secp_utils;
 pub mod util;
 
 /// Minimum value that can be included in a transaction output. Under this value,
 /// outputs are discarded
 /// See: https://github.com/discreetlogcontracts/dlcspecs/blob/master/Transactions.md#change-outputs
-const DUST_LIMIT: u64 = 1000;
+pub const DUST_LIMIT: u64 = 1000;
 
 /// The transaction version
 /// See: https://github.com/discreetlogcontracts/dlcspecs/blob/master/Transactions.md#funding-transaction
@@ -55,6 +61,10 @@ const CET_BASE_WEIGHT: usize = 500;
 /// See: https://github.com/discreetlogcontracts/dlcspecs/blob/master/Transactions.md#fees
 const TX_INPUT_BASE_WEIGHT: usize = 164;
 
+/// The value of the anchor outputs that can optionally be added to CETs and
+/// refund transactions. Corresponds to the dust limit of a P2WSH output.
+pub const ANCHOR_OUTPUT_VALUE: u64 = 330;
+
 /// The witness size of a P2WPKH input
 /// See: https://github.com/discreetlogcontracts/dlcspecs/blob/master/Transactions.md#fees
 pub const P2WPKH_WITNESS_SIZE: usize = 107;
@@ -66,6 +76,19 @@ const DISABLE_LOCKTIME: u32 = 0xffffffff;
 // RBF but enables nLockTime usage.
 const ENABLE_LOCKTIME: u32 = 0xfffffffe;
 
+/// The nSequence value signaling opt-in replace-by-fee (BIP125) while enabling
+/// nLockTime usage, that can be set for the inputs of the transactions of a
+/// contract through [`TxSequences`].
+pub const RBF_SEQUENCE: u32 = 0xfffffffd;
+
+// When set in the nSequence of an input of a version 2 transaction, this flag
+// disables its interpretation as a relative lock time (BIP68).
+const SEQUENCE_LOCKTIME_DISABLE_FLAG: u32 = 1 << 31;
+
+// Lock time values below this threshold are block heights, values above are
+// unix timestamps.
+const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+
 /// Represents the payouts for a unique contract outcome. Offer party represents
 /// the initiator of the contract while accept party represents the party
 /// accepting the contract.
@@ -78,7 +101,7 @@ pub struct Payout {
     pub accept: u64,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 /// Representation of a set of contiguous outcomes that share a single payout.
 pub struct RangePayout {
     /// The start of the range
@@ -131,6 +154,294 @@ impl DlcTransactions {
             .unwrap()
             .0
     }
+
+    /// Get the CETs as PSBTs including the fund output and the funding script
+    /// in their input.
+    pub fn get_cet_psbts(&self) -> Result<Vec<PartiallySignedTransaction>, Error> {
+        let fund_output = self.get_fund_output();
+        self.cets
+            .iter()
+            .map(|cet| create_fund_spending_psbt(cet, fund_output, &self.funding_script_pubkey))
+            .collect()
+    }
+
+    /// Get the refund transaction as a PSBT including the fund output and the
+    /// funding script in its input.
+    pub fn get_refund_psbt(&self) -> Result<PartiallySignedTransaction, Error> {
+        create_fund_spending_psbt(
+            &self.refund,
+            self.get_fund_output(),
+            &self.funding_script_pubkey,
+        )
+    }
+}
+
+/// Contains information about a premium paid by one party to the other through
+/// an additional output of the fund transaction, e.g. for option style
+/// contracts where one party contributes no collateral.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct PremiumInfo {
+    /// The value of the premium output.
+    pub amount: u64,
+    /// Whether the premium is paid by the offer party to the accept party
+    /// (otherwise it is paid by the accept party to the offer party).
+    pub paid_by_offer: bool,
+    /// Id used to order the premium output in the fund transaction.
+    pub serial_id: u64,
+}
+
+/// The number of basis points representing the totality of the fees when
+/// splitting them between the parties.
+pub const FEE_SPLIT_TOTAL_SHARE: u16 = 10000;
+
+/// Defines how the fees of the fund transaction and of the CETs and refund
+/// transaction are split between the parties. When not specified, each party
+/// pays for its own inputs and outputs and for half of the common parts of the
+/// transactions.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub enum FeeSplit {
+    /// All the fees are paid by the offer party.
+    OfferPaysAll,
+    /// All the fees are paid by the accept party.
+    AcceptPaysAll,
+    /// The offer party pays the given share of the fees, expressed in basis
+    /// points (up to [`FEE_SPLIT_TOTAL_SHARE`]), the accept party paying the rest.
+    OfferShare(u16),
+}
+
+impl FeeSplit {
+    /// Creates a fee split from the share of the fees paid by the offer party
+    /// expressed in basis points, returning an error if it is greater than
+    /// [`FEE_SPLIT_TOTAL_SHARE`].
+    pub fn from_offer_share(offer_share: u16) -> Result<FeeSplit, Error> {
+        match offer_share {
+            0 => Ok(FeeSplit::AcceptPaysAll),
+            FEE_SPLIT_TOTAL_SHARE => Ok(FeeSplit::OfferPaysAll),
+            s if s < FEE_SPLIT_TOTAL_SHARE => Ok(FeeSplit::OfferShare(s)),
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+
+    /// Returns the share of the fees paid by the offer party expressed in basis
+    /// points, or an error if it is greater than [`FEE_SPLIT_TOTAL_SHARE`].
+    pub fn get_offer_share(&self) -> Result<u16, Error> {
+        match self {
+            FeeSplit::OfferPaysAll => Ok(FEE_SPLIT_TOTAL_SHARE),
+            FeeSplit::AcceptPaysAll => Ok(0),
+            FeeSplit::OfferShare(s) if *s <= FEE_SPLIT_TOTAL_SHARE => Ok(*s),
+            FeeSplit::OfferShare(_) => Err(Error::InvalidArgument),
+        }
+    }
+
+    /// Returns the fees paid by the offer and accept party respectively out
+    /// of the given total fee.
+    pub fn split(&self, total_fee: u64) -> Result<(u64, u64), Error> {
+        let offer_fee = total_fee * self.get_offer_share()? as u64 / FEE_SPLIT_TOTAL_SHARE as u64;
+        Ok((offer_fee, total_fee - offer_fee))
+    }
+}
+
+/// The nSequence values to use for the inputs of the transactions of a
+/// contract instead of the ones derived from their lock times, e.g. to signal
+/// opt-in replace-by-fee with [`RBF_SEQUENCE`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct TxSequences {
+    /// The nSequence of the inputs of the fund transaction.
+    pub fund_sequence: u32,
+    /// The nSequence of the input spending the fund output, shared by the CETs
+    /// and the refund transaction.
+    pub cet_sequence: u32,
+}
+
+impl TxSequences {
+    /// Checks that the sequences can be used with the given lock times: they
+    /// must not encode a relative lock time, and must enable the lock time of
+    /// the transactions having one, which always includes the refund
+    /// transaction.
+    pub fn validate(
+        &self,
+        fund_lock_time: u32,
+        cet_lock_time: u32,
+        refund_lock_time: u32,
+    ) -> Result<(), Error> {
+        let is_valid = |sequence: u32, lock_time: u32| {
+            sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0
+                && (lock_time == 0 || sequence != DISABLE_LOCKTIME)
+        };
+
+        if !is_valid(self.fund_sequence, fund_lock_time)
+            || !is_valid(self.cet_sequence, cet_lock_time)
+            || !is_valid(self.cet_sequence, refund_lock_time)
+        {
+            return Err(Error::InvalidArgument);
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks that the CETs of a contract maturing at `maturity_time` can be
+/// broadcast once it is reached and before its refund transaction: the CET
+/// lock time must not be after the maturity nor after the refund lock time.
+/// Lock times are only compared with values of the same kind (block height or
+/// unix timestamp), the maturity being a unix timestamp.
+pub fn validate_cet_lock_time(
+    cet_lock_time: u32,
+    refund_lock_time: u32,
+    maturity_time: u32,
+) -> Result<(), Error> {
+    let is_timestamp = |lock_time: u32| lock_time >= LOCKTIME_THRESHOLD;
+
+    if is_timestamp(cet_lock_time) && cet_lock_time > maturity_time {
+        return Err(Error::InvalidArgument);
+    }
+
+    if is_timestamp(cet_lock_time) == is_timestamp(refund_lock_time)
+        && cet_lock_time >= refund_lock_time
+    {
+        return Err(Error::InvalidArgument);
+    }
+
+    Ok(())
+}
+
+/// The estimated weights of the transactions of a contract and the total fee
+/// paid by the parties for them, see [`estimate_weights`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct WeightEstimates {
+    /// The weight of the fund transaction.
+    pub fund_weight: usize,
+    /// The weight of a CET.
+    pub cet_weight: usize,
+    /// The weight of the refund transaction.
+    pub refund_weight: usize,
+    /// The fee paid by both parties for the fund transaction and for the CET
+    /// or refund transaction.
+    pub total_fee: u64,
+}
+
+impl WeightEstimates {
+    /// Returns the virtual sizes of the fund transaction, of a CET and of the
+    /// refund transaction.
+    pub fn get_vsizes(&self) -> (usize, usize, usize) {
+        let to_vsize = |weight: usize| (weight + 3) / 4;
+        (
+            to_vsize(self.fund_weight),
+            to_vsize(self.cet_weight),
+            to_vsize(self.refund_weight),
+        )
+    }
+}
+
+/// The parameters of a party from which the weights of the transactions of a
+/// contract are estimated, see [`estimate_weights`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct WeightEstimateParams {
+    /// The number of funding inputs of the party, assumed to spend P2WPKH
+    /// outputs.
+    pub nb_inputs: usize,
+    /// The length of the payout script pubkey of the party, which can be of
+    /// any type (e.g. 34 for P2TR).
+    pub payout_script_pubkey_len: usize,
+    /// The length of the change script pubkey of the party, zero if it does
+    /// not have a change output.
+    pub change_script_pubkey_len: usize,
+}
+
+impl WeightEstimateParams {
+    /// Returns the parameters of a party funding a contract with the given
+    /// number of inputs, and whose payout and change outputs are P2WPKH.
+    pub fn p2wpkh(nb_inputs: usize) -> Self {
+        const P2WPKH_SCRIPT_PUBKEY_LEN: usize = 22;
+        WeightEstimateParams {
+            nb_inputs,
+            payout_script_pubkey_len: P2WPKH_SCRIPT_PUBKEY_LEN,
+            change_script_pubkey_len: P2WPKH_SCRIPT_PUBKEY_LEN,
+        }
+    }
+}
+
+/// Estimates the weights of the transactions of a contract funded by parties
+/// with the given parameters, and the fee paid for them at the given fee rate,
+/// so that costs can be known before offering a contract. When `with_anchors`
+/// is set, the CETs and the refund transaction include the anchor outputs
+/// added by [`create_dlc_transactions_with_anchors`], whose fee is included in
+/// the total fee but not their value. The estimates follow the fee computation
+/// of [`PartyParams::get_fees`], in which the weight of a CET does not depend
+/// on the contract outcomes, so that no contract descriptor is needed.
+pub fn estimate_weights(
+    offer_params: &WeightEstimateParams,
+    accept_params: &WeightEstimateParams,
+    with_anchors: bool,
+    fee_rate_per_vb: u64,
+) -> WeightEstimates {
+    // Value (8) + script length var_int (1), scaled by 4 from vBytes to weight
+    // units.
+    const OUTPUT_BASE_WEIGHT: usize = 36;
+
+    let get_fund_weight = |params: &WeightEstimateParams| {
+        FUND_TX_BASE_WEIGHT / 2
+            + params.nb_inputs * (TX_INPUT_BASE_WEIGHT + P2WPKH_WITNESS_SIZE)
+            + OUTPUT_BASE_WEIGHT
+            + params.change_script_pubkey_len * 4
+    };
+    let get_cet_weight =
+        |params: &WeightEstimateParams| CET_BASE_WEIGHT / 2 + params.payout_script_pubkey_len * 4;
+    let get_anchor_weight = |params: &WeightEstimateParams| {
+        if with_anchors {
+            OUTPUT_BASE_WEIGHT + params.payout_script_pubkey_len * 4
+        } else {
+            0
+        }
+    };
+
+    let mut fund_weight = 0;
+    let mut cet_weight = 0;
+    let mut total_fee = 0;
+    for params in [offer_params, accept_params].iter().copied() {
+        let party_fund_weight = get_fund_weight(params);
+        let party_cet_weight = get_cet_weight(params);
+        let party_anchor_weight = get_anchor_weight(params);
+        fund_weight += party_fund_weight;
+        cet_weight += party_cet_weight + party_anchor_weight;
+        // Fees are rounded per party and per anchor output as when creating
+        // the transactions.
+        total_fee += util::weight_to_fee(party_fund_weight, fee_rate_per_vb)
+            + util::weight_to_fee(party_cet_weight, fee_rate_per_vb)
+            + util::weight_to_fee(party_anchor_weight, fee_rate_per_vb);
+    }
+
+    WeightEstimates {
+        fund_weight,
+        cet_weight,
+        refund_weight: cet_weight,
+        total_fee,
+    }
 }
 
 /// Contains info about a utxo used for funding a DLC contract
@@ -203,7 +514,8 @@ impl fmt::Display for Error {
 pub struct PartyParams {
     /// The public key for the fund multisig script
     pub fund_pubkey: PublicKey,
-    /// An address to receive change
+    /// An address to receive change. If empty, no change output is created
+    /// for the party and any remaining input value is added to the fees.
     pub change_script_pubkey: Script,
     /// Id used to order fund outputs
     pub change_serial_id: u64,
@@ -229,6 +541,16 @@ impl PartyParams {
         &self,
         fee_rate_per_vb: u64,
     ) -> Result<(TxOut, u64, u64), Error> {
+        let (fund_fee, cet_or_refund_fee) = self.get_fees(fee_rate_per_vb);
+        let change_output = self.get_change_output(fund_fee + cet_or_refund_fee)?;
+
+        Ok((change_output, fund_fee, cet_or_refund_fee))
+    }
+
+    /// Returns the fees that the party is required to pay for the fund
+    /// transaction and the cet or refund transaction when each party pays for
+    /// its own inputs and outputs.
+    pub fn get_fees(&self, fee_rate_per_vb: u64) -> (u64, u64) {
         let inputs_weight: usize = self
             .inputs
             .iter()
@@ -260,17 +582,20 @@ impl PartyParams {
         let output_spk_weight = self.payout_script_pubkey.len() * 4;
         let total_cet_weight = this_party_cet_base_weight + output_spk_weight;
         let cet_or_refund_fee = util::weight_to_fee(total_cet_weight, fee_rate_per_vb);
-        let required_input_funds = self.collateral + fund_fee + cet_or_refund_fee;
+
+        (fund_fee, cet_or_refund_fee)
+    }
+
+    fn get_change_output(&self, fee: u64) -> Result<TxOut, Error> {
+        let required_input_funds = self.collateral + fee;
         if self.input_amount < required_input_funds {
             return Err(Error::InvalidArgument);
         }
 
-        let change_output = TxOut {
+        Ok(TxOut {
             value: self.input_amount - required_input_funds,
             script_pubkey: self.change_script_pubkey.clone(),
-        };
-
-        Ok((change_output, fund_fee, cet_or_refund_fee))
+        })
     }
 
     fn get_unsigned_tx_inputs_and_serial_ids(&self, sequence: u32) -> (Vec<TxIn>, Vec<u64>) {
@@ -303,6 +628,171 @@ pub fn create_dlc_transactions(
     cet_lock_time: u32,
     fund_output_serial_id: u64,
 ) -> Result<DlcTransactions, Error> {
+    create_dlc_transactions_internal(
+        offer_params,
+        accept_params,
+        payouts,
+        refund_lock_time,
+        fee_rate_per_vb,
+        fund_lock_time,
+        cet_lock_time,
+        fund_output_serial_id,
+        false,
+        None,
+        None,
+        None,
+    )
+}
+
+/// Create the transactions for a DLC contract based on the provided parameters,
+/// adding an anchor output of [`ANCHOR_OUTPUT_VALUE`] for each party to the
+/// CETs and the refund transaction. The anchor outputs pay to the payout script
+/// pubkey of each party and are appended after the payout outputs (offer party
+/// first), so that either party can bump the fee of the closing transaction
+/// through CPFP. The value of each anchor, as well as the fee for adding it to
+/// the CETs and refund transaction, is deducted from the change of the
+/// corresponding party and added to the fund output.
+pub fn create_dlc_transactions_with_anchors(
+    offer_params: &PartyParams,
+    accept_params: &PartyParams,
+    payouts: &[Payout],
+    refund_lock_time: u32,
+    fee_rate_per_vb: u64,
+    fund_lock_time: u32,
+    cet_lock_time: u32,
+    fund_output_serial_id: u64,
+) -> Result<DlcTransactions, Error> {
+    create_dlc_transactions_internal(
+        offer_params,
+        accept_params,
+        payouts,
+        refund_lock_time,
+        fee_rate_per_vb,
+        fund_lock_time,
+        cet_lock_time,
+        fund_output_serial_id,
+        true,
+        None,
+        None,
+        None,
+    )
+}
+
+/// Create the transactions for a DLC contract based on the provided parameters,
+/// including an output in the fund transaction paying the given premium to the
+/// payout script pubkey of the receiving party. The premium, as well as the fee
+/// for the additional output, are deducted from the change of the paying party.
+/// If `premium` is `None`, this is equivalent to [`create_dlc_transactions`].
+pub fn create_dlc_transactions_with_premium(
+    offer_params: &PartyParams,
+    accept_params: &PartyParams,
+    payouts: &[Payout],
+    refund_lock_time: u32,
+    fee_rate_per_vb: u64,
+    fund_lock_time: u32,
+    cet_lock_time: u32,
+    fund_output_serial_id: u64,
+    premium: Option<&PremiumInfo>,
+) -> Result<DlcTransactions, Error> {
+    create_dlc_transactions_internal(
+        offer_params,
+        accept_params,
+        payouts,
+        refund_lock_time,
+        fee_rate_per_vb,
+        fund_lock_time,
+        cet_lock_time,
+        fund_output_serial_id,
+        false,
+        premium,
+        None,
+        None,
+    )
+}
+
+/// Create the transactions for a DLC contract based on the provided parameters,
+/// with the fees of the transactions split between the parties as defined by
+/// `fee_split` (see [`create_dlc_transactions_with_premium`] for `premium`).
+/// If `fee_split` is `None`, each party pays for its own inputs and outputs and
+/// for half of the common parts of the transactions.
+pub fn create_dlc_transactions_with_fee_split(
+    offer_params: &PartyParams,
+    accept_params: &PartyParams,
+    payouts: &[Payout],
+    refund_lock_time: u32,
+    fee_rate_per_vb: u64,
+    fund_lock_time: u32,
+    cet_lock_time: u32,
+    fund_output_serial_id: u64,
+    premium: Option<&PremiumInfo>,
+    fee_split: Option<&FeeSplit>,
+) -> Result<DlcTransactions, Error> {
+    create_dlc_transactions_internal(
+        offer_params,
+        accept_params,
+        payouts,
+        refund_lock_time,
+        fee_rate_per_vb,
+        fund_lock_time,
+        cet_lock_time,
+        fund_output_serial_id,
+        false,
+        premium,
+        fee_split,
+        None,
+    )
+}
+
+/// Create the transactions for a DLC contract based on the provided parameters,
+/// using the given nSequence values for their inputs instead of the ones
+/// derived from the lock times. Returns an error if they are not valid for the
+/// lock times (see [`TxSequences::validate`]). The CET lock time can be checked
+/// against the maturity of the contract with [`validate_cet_lock_time`].
+pub fn create_dlc_transactions_with_sequences(
+    offer_params: &PartyParams,
+    accept_params: &PartyParams,
+    payouts: &[Payout],
+    refund_lock_time: u32,
+    fee_rate_per_vb: u64,
+    fund_lock_time: u32,
+    cet_lock_time: u32,
+    fund_output_serial_id: u64,
+    sequences: &TxSequences,
+) -> Result<DlcTransactions, Error> {
+    create_dlc_transactions_internal(
+        offer_params,
+        accept_params,
+        payouts,
+        refund_lock_time,
+        fee_rate_per_vb,
+        fund_lock_time,
+        cet_lock_time,
+        fund_output_serial_id,
+        false,
+        None,
+        None,
+        Some(sequences),
+    )
+}
+
+fn create_dlc_transactions_internal(
+    offer_params: &PartyParams,
+    accept_params: &PartyParams,
+    payouts: &[Payout],
+    refund_lock_time: u32,
+    fee_rate_per_vb: u64,
+    fund_lock_time: u32,
+    cet_lock_time: u32,
+    fund_output_serial_id: u64,
+    with_anchors: bool,
+    premium: Option<&PremiumInfo>,
+    fee_split: Option<&FeeSplit>,
+    sequences: Option<&TxSequences>,
+) -> Result<DlcTransactions, Error> {
+    if let Some(sequences) = sequences {
+        sequences.validate(fund_lock_time, cet_lock_time, refund_lock_time)?;
+    }
+
     let total_collateral = offer_params.collateral + accept_params.collateral;
 
     let has_proper_outcomes = payouts
@@ -313,19 +803,87 @@ pub fn create_dlc_transactions(
         return Err(Error::InvalidArgument);
     }
 
-    let (offer_change_output, offer_fund_fee, offer_cet_fee) =
-        offer_params.get_change_output_and_fees(fee_rate_per_vb)?;
-    let (accept_change_output, accept_fund_fee, accept_cet_fee) =
-        accept_params.get_change_output_and_fees(fee_rate_per_vb)?;
+    let (offer_fund_fee, offer_cet_fee) = offer_params.get_fees(fee_rate_per_vb);
+    let (accept_fund_fee, accept_cet_fee) = accept_params.get_fees(fee_rate_per_vb);
+
+    let (offer_fee, accept_fee) = match fee_split {
+        Some(fee_split) => {
+            fee_split.split(offer_fund_fee + offer_cet_fee + accept_fund_fee + accept_cet_fee)?
+        }
+        None => (
+            offer_fund_fee + offer_cet_fee,
+            accept_fund_fee + accept_cet_fee,
+        ),
+    };
+
+    let mut offer_change_output = offer_params.get_change_output(offer_fee)?;
+    let mut accept_change_output = accept_params.get_change_output(accept_fee)?;
+
+    let anchor_value = if with_anchors { ANCHOR_OUTPUT_VALUE } else { 0 };
+    // Value (8) + script length var_int (1) + script pubkey, scaled by 4 from
+    // vBytes to weight units.
+    let get_anchor_fee = |params: &PartyParams| {
+        if with_anchors {
+            let weight = (9 + params.payout_script_pubkey.len()) * 4;
+            util::weight_to_fee(weight, fee_rate_per_vb)
+        } else {
+            0
+        }
+    };
+    let offer_anchor_fee = get_anchor_fee(offer_params);
+    let accept_anchor_fee = get_anchor_fee(accept_params);
+
+    if offer_change_output.value < anchor_value + offer_anchor_fee
+        || accept_change_output.value < anchor_value + accept_anchor_fee
+    {
+        return Err(Error::InvalidArgument);
+    }
+
+    offer_change_output.value -= anchor_value + offer_anchor_fee;
+    accept_change_output.value -= anchor_value + accept_anchor_fee;
+
+    let (premium_output, premium_value, premium_fee) = match premium {
+        Some(premium) => {
+            if premium.amount < DUST_LIMIT {
+                return Err(Error::InvalidArgument);
+            }
+            let (payer_change_output, receiver_params) = if premium.paid_by_offer {
+                (&mut offer_change_output, accept_params)
+            } else {
+                (&mut accept_change_output, offer_params)
+            };
+            let output = TxOut {
+                value: premium.amount,
+                script_pubkey: receiver_params.payout_script_pubkey.clone(),
+            };
+            // Value (8) + script length var_int (1) + script pubkey, scaled by 4
+            // from vBytes to weight units.
+            let output_weight = (9 + output.script_pubkey.len()) * 4;
+            let fee = util::weight_to_fee(output_weight, fee_rate_per_vb);
+            if payer_change_output.value < premium.amount + fee {
+                return Err(Error::InvalidArgument);
+            }
+            payer_change_output.value -= premium.amount + fee;
+            (Some((output, premium.serial_id)), premium.amount, fee)
+        }
+        None => (None, 0, 0),
+    };
 
     let fund_output_value = offer_params.input_amount + accept_params.input_amount
         - offer_change_output.value
         - accept_change_output.value
         - offer_fund_fee
-        - accept_fund_fee;
+        - accept_fund_fee
+        - premium_value
+        - premium_fee;
 
     assert_eq!(
-        total_collateral + offer_cet_fee + accept_cet_fee,
+        total_collateral
+            + offer_cet_fee
+            + accept_cet_fee
+            + 2 * anchor_value
+            + offer_anchor_fee
+            + accept_anchor_fee,
         fund_output_value
     );
 
@@ -336,6 +894,8 @@ pub fn create_dlc_transactions(
             + accept_change_output.value
             + offer_fund_fee
             + accept_fund_fee
+            + premium_value
+            + premium_fee
     );
 
     fn get_sequence(lock_time: u32) -> u32 {
@@ -346,7 +906,7 @@ pub fn create_dlc_transactions(
         }
     }
 
-    let fund_sequence = get_sequence(fund_lock_time);
+    let fund_sequence = sequences.map_or(get_sequence(fund_lock_time), |x| x.fund_sequence);
     let (offer_tx_ins, offer_inputs_serial_ids) =
         offer_params.get_unsigned_tx_inputs_and_serial_ids(fund_sequence);
     let (accept_tx_ins, accept_inputs_serial_ids) =
@@ -355,7 +915,7 @@ pub fn create_dlc_transactions(
     let funding_script_pubkey =
         make_funding_redeemscript(&offer_params.fund_pubkey, &accept_params.fund_pubkey);
 
-    let fund_tx = create_funding_transaction(
+    let fund_tx = create_funding_transaction_internal(
         &funding_script_pubkey,
         fund_output_value,
         &offer_tx_ins,
@@ -368,6 +928,7 @@ pub fn create_dlc_transactions(
         accept_params.change_serial_id,
         fund_output_serial_id,
         fund_lock_time,
+        premium_output,
     );
 
     let (fund_vout, _) =
@@ -382,10 +943,10 @@ pub fn create_dlc_transactions(
         previous_output: fund_outpoint,
         witness: Vec::new(),
         script_sig: Script::new(),
-        sequence: get_sequence(cet_lock_time),
+        sequence: sequences.map_or(get_sequence(cet_lock_time), |x| x.cet_sequence),
     };
 
-    let cets = create_cets(
+    let mut cets = create_cets(
         &fund_tx_in,
         &offer_params.payout_script_pubkey,
         offer_params.payout_serial_id,
@@ -405,13 +966,23 @@ pub fn create_dlc_transactions(
         script_pubkey: accept_params.payout_script_pubkey.clone(),
     };
 
-    let refund_tx = create_refund_transaction(
+    let mut refund_tx = create_refund_transaction(
         offer_refund_output,
         accept_refund_ouput,
         fund_tx_in,
         refund_lock_time,
     );
 
+    if with_anchors {
+        for tx in cets.iter_mut().chain(std::iter::once(&mut refund_tx)) {
+            add_anchor_outputs(
+                tx,
+                &offer_params.payout_script_pubkey,
+                &accept_params.payout_script_pubkey,
+            );
+        }
+    }
+
     Ok(DlcTransactions {
         fund: fund_tx,
         cets,
@@ -420,6 +991,23 @@ pub fn create_dlc_transactions(
     })
 }
 
+/// Append an anchor output for each party to the given transaction, offer party
+/// first, that can be used to bump its fee through CPFP.
+pub fn add_anchor_outputs(
+    tx: &mut Transaction,
+    offer_anchor_script_pubkey: &Script,
+    accept_anchor_script_pubkey: &Script,
+) {
+    tx.output.push(TxOut {
+        value: ANCHOR_OUTPUT_VALUE,
+        script_pubkey: offer_anchor_script_pubkey.clone(),
+    });
+    tx.output.push(TxOut {
+        value: ANCHOR_OUTPUT_VALUE,
+        script_pubkey: accept_anchor_script_pubkey.clone(),
+    });
+}
+
 /// Create a contract execution transaction
 pub fn create_cet(
     offer_output: TxOut,
@@ -480,6 +1068,76 @@ pub fn create_cets(
     txs
 }
 
+/// Create a set of contract execution transactions for each provided outcome
+/// from a caller supplied template. The template must contain a single input
+/// spending the fund output, and its version and lock time are used for all
+/// the CETs. Outputs of the template, if any, are appended to each CET after
+/// the payout outputs.
+pub fn create_cets_from_template(
+    template: &Transaction,
+    offer_payout_script_pubkey: &Script,
+    offer_payout_serial_id: u64,
+    accept_payout_script_pubkey: &Script,
+    accept_payout_serial_id: u64,
+    payouts: &[Payout],
+) -> Result<Vec<Transaction>, Error> {
+    if template.input.len() != 1 {
+        return Err(Error::InvalidArgument);
+    }
+
+    let mut cets = create_cets(
+        &template.input[0],
+        offer_payout_script_pubkey,
+        offer_payout_serial_id,
+        accept_payout_script_pubkey,
+        accept_payout_serial_id,
+        payouts,
+        template.lock_time,
+    );
+
+    for cet in cets.iter_mut() {
+        cet.version = template.version;
+        cet.output.extend(template.output.iter().cloned());
+    }
+
+    Ok(cets)
+}
+
+/// Create a PSBT for the given transaction spending the fund output, with the
+/// fund output and funding script set on its single input so that it can be
+/// signed without access to the fund transaction.
+pub fn create_fund_spending_psbt(
+    tx: &Transaction,
+    fund_output: &TxOut,
+    funding_script_pubkey: &Script,
+) -> Result<PartiallySignedTransaction, Error> {
+    if tx.input.len() != 1 {
+        return Err(Error::InvalidArgument);
+    }
+
+    let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx.clone())
+        .map_err(|_| Error::InvalidArgument)?;
+    psbt.inputs[0].witness_utxo = Some(fund_output.clone());
+    psbt.inputs[0].witness_script = Some(funding_script_pubkey.clone());
+
+    Ok(psbt)
+}
+
+/// Returns the funding script and fund output value set on the input of a PSBT
+/// spending the fund output.
+fn get_fund_info_from_psbt(psbt: &PartiallySignedTransaction) -> Result<(&Script, u64), Error> {
+    let input = match psbt.inputs.as_slice() {
+        [input] => input,
+        _ => return Err(Error::InvalidArgument),
+    };
+    match (&input.witness_script, &input.witness_utxo) {
+        (Some(funding_script_pubkey), Some(fund_output)) => {
+            Ok((funding_script_pubkey, fund_output.value))
+        }
+        _ => Err(Error::InvalidArgument),
+    }
+}
+
 /// Create a funding transaction
 pub fn create_funding_transaction(
     funding_script_pubkey: &Script,
@@ -495,24 +1153,58 @@ pub fn create_funding_transaction(
     fund_output_serial_id: u64,
     lock_time: u32,
 ) -> Transaction {
-    let fund_tx_out = TxOut {
-        value: output_amount,
-        script_pubkey: funding_script_pubkey.to_v0_p2wsh(),
-    };
-
-    let output: Vec<TxOut> = {
-        let serial_ids = vec![
-            fund_output_serial_id,
+    create_funding_transaction_internal(
+        funding_script_pubkey,
+        output_amount,
+        offer_inputs,
+        offer_inputs_serial_ids,
+        accept_inputs,
+        accept_inputs_serial_ids,
+        offer_change_output,
+        offer_change_serial_id,
+        accept_change_output,
+        accept_change_serial_id,
+        fund_output_serial_id,
+        lock_time,
+        None,
+    )
+}
+
+fn create_funding_transaction_internal(
+    funding_script_pubkey: &Script,
+    output_amount: u64,
+    offer_inputs: &[TxIn],
+    offer_inputs_serial_ids: &[u64],
+    accept_inputs: &[TxIn],
+    accept_inputs_serial_ids: &[u64],
+    offer_change_output: TxOut,
+    offer_change_serial_id: u64,
+    accept_change_output: TxOut,
+    accept_change_serial_id: u64,
+    fund_output_serial_id: u64,
+    lock_time: u32,
+    premium_output: Option<(TxOut, u64)>,
+) -> Transaction {
+    let fund_tx_out = TxOut {
+        value: output_amount,
+        script_pubkey: funding_script_pubkey.to_v0_p2wsh(),
+    };
+
+    let output: Vec<TxOut> = {
+        let mut serial_ids = vec![
+            fund_output_serial_id,
             offer_change_serial_id,
             accept_change_serial_id,
         ];
-        util::discard_dust(
-            util::order_by_serial_ids(
-                vec![fund_tx_out, offer_change_output, accept_change_output],
-                &serial_ids,
-            ),
-            DUST_LIMIT,
-        )
+        let mut outputs = vec![fund_tx_out, offer_change_output, accept_change_output];
+        if let Some((premium_output, premium_serial_id)) = premium_output {
+            outputs.push(premium_output);
+            serial_ids.push(premium_serial_id);
+        }
+        util::discard_dust(util::order_by_serial_ids(outputs, &serial_ids), DUST_LIMIT)
+            .into_iter()
+            .filter(|x| !x.script_pubkey.is_empty())
+            .collect()
     };
 
     let input = util::order_by_serial_ids(
@@ -556,6 +1248,48 @@ pub fn make_funding_redeemscript(a: &PublicKey, b: &PublicKey) -> Script {
         .into_script()
 }
 
+/// Returns the output descriptor, with its checksum, of the funding output
+/// locked by the multisig redeem script of the given fund public keys, which
+/// external wallets can import to watch the output.
+pub fn get_funding_descriptor(a: &PublicKey, b: &PublicKey) -> String {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    let descriptor = format!("wsh(multi(2,{},{}))", first, second);
+    let checksum =
+        util::get_descriptor_checksum(&descriptor).expect("to only contain valid characters");
+    format!("{}#{}", descriptor, checksum)
+}
+
+/// Returns the Unicode Normalization Form C (NFC) of the given outcome, as
+/// required by the DLC specification for strings. Outcomes are normalized
+/// before being hashed or compared, so that outcomes using different encodings
+/// of the same characters (e.g. precomposed or combining accents) match.
+pub fn normalize_outcome(outcome: &str) -> Cow<'_, str> {
+    if unicode_normalization::is_nfc(outcome) {
+        Cow::Borrowed(outcome)
+    } else {
+        Cow::Owned(outcome.nfc().collect())
+    }
+}
+
+/// Returns the message signed by an oracle attesting to the given outcome,
+/// which is the SHA256 hash of the UTF-8 encoding of the NFC normalized
+/// outcome. Digits of numerical outcomes are attested as their decimal string
+/// representation.
+pub fn get_outcome_message(outcome: &str) -> Message {
+    Message::from_hashed_data::<secp256k1_zkp::bitcoin_hashes::sha256::Hash>(
+        normalize_outcome(outcome).as_bytes(),
+    )
+}
+
+/// Returns the messages attesting to each of the given outcomes, so that they
+/// can be computed once when generating adaptor points for many CETs.
+pub fn get_outcome_messages<S: AsRef<str>>(outcomes: &[S]) -> Vec<Message> {
+    outcomes
+        .iter()
+        .map(|x| get_outcome_message(x.as_ref()))
+        .collect()
+}
+
 fn get_oracle_sig_point<C: secp256k1_zkp::Verification>(
     secp: &Secp256k1<C>,
     oracle_info: &OracleInfo,
@@ -637,6 +1371,26 @@ pub fn create_cet_adaptor_sig_from_oracle_info(
     )
 }
 
+/// Create an adaptor signature for the CET contained in the given PSBT using
+/// the provided adaptor point. The PSBT input must include the fund output and
+/// the funding script.
+pub fn create_cet_adaptor_sig_from_psbt<C: secp256k1_zkp::Signing>(
+    secp: &secp256k1_zkp::Secp256k1<C>,
+    cet_psbt: &PartiallySignedTransaction,
+    adaptor_point: &PublicKey,
+    funding_sk: &SecretKey,
+) -> Result<EcdsaAdaptorSignature, Error> {
+    let (funding_script_pubkey, fund_output_value) = get_fund_info_from_psbt(cet_psbt)?;
+    create_cet_adaptor_sig_from_point(
+        secp,
+        &cet_psbt.global.unsigned_tx,
+        adaptor_point,
+        funding_sk,
+        funding_script_pubkey,
+        fund_output_value,
+    )
+}
+
 /// Crerate a set of adaptor signatures for the given cet/message pairs.
 pub fn create_cet_adaptor_sigs_from_points<C: secp256k1_zkp::Signing>(
     secp: &secp256k1_zkp::Secp256k1<C>,
@@ -753,6 +1507,59 @@ pub fn verify_cet_adaptor_sig_from_point(
     Ok(())
 }
 
+/// Verify that a given adaptor signature for the CET contained in the given
+/// PSBT is valid with respect to an adaptor point. The PSBT input must include
+/// the fund output and the funding script.
+pub fn verify_cet_adaptor_sig_from_psbt(
+    secp: &Secp256k1<secp256k1_zkp::All>,
+    adaptor_sig: &EcdsaAdaptorSignature,
+    cet_psbt: &PartiallySignedTransaction,
+    adaptor_point: &PublicKey,
+    pubkey: &PublicKey,
+) -> Result<(), Error> {
+    let (funding_script_pubkey, fund_output_value) = get_fund_info_from_psbt(cet_psbt)?;
+    verify_cet_adaptor_sig_from_point(
+        secp,
+        adaptor_sig,
+        &cet_psbt.global.unsigned_tx,
+        adaptor_point,
+        pubkey,
+        funding_script_pubkey,
+        fund_output_value,
+    )
+}
+
+/// Verify a batch of adaptor signatures, each given together with the index
+/// of its CET and its adaptor point. CETs are obtained through `get_cet`, and
+/// signatures for a same CET share the computation of its signature hash,
+/// which makes verifying the adaptor signatures of a numerical contract (where
+/// many of them relate to each CET) significantly cheaper than verifying them
+/// one by one.
+pub fn verify_batch<'a>(
+    secp: &Secp256k1<secp256k1_zkp::All>,
+    get_cet: &dyn Fn(usize) -> Result<Cow<'a, Transaction>, Error>,
+    adaptor_sigs: &[(usize, EcdsaAdaptorSignature, PublicKey)],
+    pubkey: &PublicKey,
+    funding_script_pubkey: &Script,
+    total_collateral: u64,
+) -> Result<(), Error> {
+    let mut sig_hashes: HashMap<usize, Message> = HashMap::new();
+    for (cet_index, adaptor_sig, adaptor_point) in adaptor_sigs {
+        let sig_hash = match sig_hashes.get(cet_index) {
+            Some(sig_hash) => *sig_hash,
+            None => {
+                let cet = get_cet(*cet_index)?;
+                let sig_hash =
+                    util::get_sig_hash_msg(&cet, 0, funding_script_pubkey, total_collateral);
+                sig_hashes.insert(*cet_index, sig_hash);
+                sig_hash
+            }
+        };
+        adaptor_sig.verify(secp, &sig_hash, pubkey, adaptor_point)?;
+    }
+    Ok(())
+}
+
 /// Verify that a given adaptor signature for a given cet is valid with respect
 /// to an oracle public key, nonce and a given message.
 pub fn verify_cet_adaptor_sig_from_oracle_info(
@@ -832,6 +1639,28 @@ mod tests {
         (pk, pk1)
     }
 
+    #[test]
+    fn get_descriptor_checksum_test() {
+        assert_eq!(
+            Some("89f8spxm".to_string()),
+            util::get_descriptor_checksum("raw(deadbeef)")
+        );
+        assert_eq!(None, util::get_descriptor_checksum("raw(deadbeef)\u{e9}"));
+    }
+
+    #[test]
+    fn get_funding_descriptor_test() {
+        let secp = Secp256k1::new();
+        let get_pubkey =
+            |sk: &str| PublicKey::from_secret_key(&secp, &SecretKey::from_str(sk).unwrap());
+        let pk = get_pubkey("0000000000000000000000000000000000000000000000000000000000000001");
+        let pk1 = get_pubkey("0000000000000000000000000000000000000000000000000000000000000002");
+        let expected = "wsh(multi(2,0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798,02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5))#e7d75zev";
+
+        assert_eq!(expected, get_funding_descriptor(&pk1, &pk));
+        assert_eq!(expected, get_funding_descriptor(&pk, &pk1));
+    }
+
     fn create_test_tx_io() -> (TxOut, TxOut, TxIn) {
         let offer = TxOut {
             value: 1,
@@ -1172,6 +2001,709 @@ mod tests {
         assert!(dlc_txs.cets.iter().all(|x| x.lock_time == 10));
     }
 
+    #[test]
+    fn create_dlc_transactions_with_anchors_test() {
+        // Arrange
+        let (offer_party_params, _) = get_party_params(1000000000, 100000000, None);
+        let (accept_party_params, _) = get_party_params(1000000000, 100000000, None);
+
+        // Act
+        let dlc_txs = create_dlc_transactions(
+            &offer_party_params,
+            &accept_party_params,
+            &payouts(),
+            100,
+            4,
+            10,
+            10,
+            0,
+        )
+        .unwrap();
+        let anchored_dlc_txs = create_dlc_transactions_with_anchors(
+            &offer_party_params,
+            &accept_party_params,
+            &payouts(),
+            100,
+            4,
+            10,
+            10,
+            0,
+        )
+        .unwrap();
+
+        // Assert
+        let anchor_fee =
+            |script_pubkey: &Script| util::weight_to_fee((9 + script_pubkey.len()) * 4, 4);
+        let anchors_fee = anchor_fee(&offer_party_params.payout_script_pubkey)
+            + anchor_fee(&accept_party_params.payout_script_pubkey);
+        assert_eq!(
+            dlc_txs.get_fund_output().value + 2 * ANCHOR_OUTPUT_VALUE + anchors_fee,
+            anchored_dlc_txs.get_fund_output().value
+        );
+        let fund_output_value = dlc_txs.get_fund_output().value;
+        let anchored_fund_output_value = anchored_dlc_txs.get_fund_output().value;
+        for (tx, anchored_tx) in dlc_txs
+            .cets
+            .iter()
+            .chain(std::iter::once(&dlc_txs.refund))
+            .zip(
+                anchored_dlc_txs
+                    .cets
+                    .iter()
+                    .chain(std::iter::once(&anchored_dlc_txs.refund)),
+            )
+        {
+            let nb_outputs = tx.output.len();
+            assert_eq!(nb_outputs + 2, anchored_tx.output.len());
+            assert_eq!(tx.output[..], anchored_tx.output[..nb_outputs]);
+            assert_eq!(
+                offer_party_params.payout_script_pubkey,
+                anchored_tx.output[nb_outputs].script_pubkey
+            );
+            assert_eq!(
+                accept_party_params.payout_script_pubkey,
+                anchored_tx.output[nb_outputs + 1].script_pubkey
+            );
+            assert!(anchored_tx.output[nb_outputs..]
+                .iter()
+                .all(|x| x.value == ANCHOR_OUTPUT_VALUE));
+            // The anchor outputs do not lower the fee rate of the transaction.
+            let fee = fund_output_value - tx.output.iter().map(|x| x.value).sum::<u64>();
+            let anchored_fee = anchored_fund_output_value
+                - anchored_tx.output.iter().map(|x| x.value).sum::<u64>();
+            assert_eq!(fee + anchors_fee, anchored_fee);
+            let added_weight = anchored_tx.get_weight() - tx.get_weight();
+            assert!(anchored_fee - fee >= util::weight_to_fee(added_weight, 4));
+        }
+    }
+
+    #[test]
+    fn create_dlc_transactions_with_premium_test() {
+        // Arrange
+        let (offer_party_params, _) = get_party_params(1000000000, 200000000, None);
+        let (accept_party_params, _) = get_party_params(1000000000, 0, None);
+        let premium = PremiumInfo {
+            amount: 1000000,
+            paid_by_offer: false,
+            serial_id: 2,
+        };
+
+        // Act
+        let dlc_txs = create_dlc_transactions(
+            &offer_party_params,
+            &accept_party_params,
+            &payouts(),
+            100,
+            4,
+            10,
+            10,
+            0,
+        )
+        .unwrap();
+        let premium_dlc_txs = create_dlc_transactions_with_premium(
+            &offer_party_params,
+            &accept_party_params,
+            &payouts(),
+            100,
+            4,
+            10,
+            10,
+            0,
+            Some(&premium),
+        )
+        .unwrap();
+
+        // Assert
+        assert_eq!(
+            dlc_txs.get_fund_output().value,
+            premium_dlc_txs.get_fund_output().value
+        );
+        assert_eq!(
+            dlc_txs.fund.output.len() + 1,
+            premium_dlc_txs.fund.output.len()
+        );
+        assert!(premium_dlc_txs
+            .fund
+            .output
+            .iter()
+            .any(|x| x.value == premium.amount
+                && x.script_pubkey == offer_party_params.payout_script_pubkey));
+        let accept_change = |txs: &DlcTransactions| {
+            txs.fund
+                .output
+                .iter()
+                .find(|x| x.script_pubkey == accept_party_params.change_script_pubkey)
+                .unwrap()
+                .value
+        };
+        assert!(accept_change(&dlc_txs) > accept_change(&premium_dlc_txs) + premium.amount);
+    }
+
+    #[test]
+    fn create_dlc_transactions_with_dust_premium_fails() {
+        // Arrange
+        let (offer_party_params, _) = get_party_params(1000000000, 200000000, None);
+        let (accept_party_params, _) = get_party_params(1000000000, 0, None);
+        let premium = PremiumInfo {
+            amount: 500,
+            paid_by_offer: false,
+            serial_id: 2,
+        };
+
+        // Act
+        let res = create_dlc_transactions_with_premium(
+            &offer_party_params,
+            &accept_party_params,
+            &payouts(),
+            100,
+            4,
+            10,
+            10,
+            0,
+            Some(&premium),
+        );
+
+        // Assert
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn create_dlc_transactions_with_fee_split_test() {
+        // Arrange
+        let (offer_party_params, _) = get_party_params(1000000000, 100000000, None);
+        let (accept_party_params, _) = get_party_params(1000000000, 100000000, None);
+        let get_dlc_txs = |fee_split: Option<&FeeSplit>| {
+            create_dlc_transactions_with_fee_split(
+                &offer_party_params,
+                &accept_party_params,
+                &payouts(),
+                100,
+                4,
+                10,
+                10,
+                0,
+                None,
+                fee_split,
+            )
+        };
+        let get_change = |txs: &DlcTransactions, params: &PartyParams| {
+            txs.fund
+                .output
+                .iter()
+                .find(|x| x.script_pubkey == params.change_script_pubkey)
+                .unwrap()
+                .value
+        };
+
+        // Act
+        let dlc_txs = get_dlc_txs(None).unwrap();
+        let offer_pays_all_txs = get_dlc_txs(Some(&FeeSplit::OfferPaysAll)).unwrap();
+        let invalid_res = get_dlc_txs(Some(&FeeSplit::OfferShare(FEE_SPLIT_TOTAL_SHARE + 1)));
+
+        // Assert
+        assert_eq!(
+            dlc_txs.get_fund_output().value,
+            offer_pays_all_txs.get_fund_output().value
+        );
+        assert_eq!(
+            accept_party_params.input_amount - accept_party_params.collateral,
+            get_change(&offer_pays_all_txs, &accept_party_params)
+        );
+        assert_eq!(
+            get_change(&dlc_txs, &offer_party_params) + get_change(&dlc_txs, &accept_party_params),
+            get_change(&offer_pays_all_txs, &offer_party_params)
+                + get_change(&offer_pays_all_txs, &accept_party_params)
+        );
+        assert!(invalid_res.is_err());
+    }
+
+    #[test]
+    fn create_dlc_transactions_with_sequences_test() {
+        // Arrange
+        let (offer_party_params, _) = get_party_params(1000000000, 100000000, None);
+        let (accept_party_params, _) = get_party_params(1000000000, 100000000, None);
+        let get_dlc_txs = |fund_lock_time: u32, sequences: &TxSequences| {
+            create_dlc_transactions_with_sequences(
+                &offer_party_params,
+                &accept_party_params,
+                &payouts(),
+                100,
+                4,
+                fund_lock_time,
+                10,
+                0,
+                sequences,
+            )
+        };
+        let rbf_sequences = TxSequences {
+            fund_sequence: RBF_SEQUENCE,
+            cet_sequence: RBF_SEQUENCE,
+        };
+
+        // Act
+        let dlc_txs = get_dlc_txs(10, &rbf_sequences).unwrap();
+        let no_locktime_res = get_dlc_txs(
+            0,
+            &TxSequences {
+                fund_sequence: DISABLE_LOCKTIME,
+                cet_sequence: RBF_SEQUENCE,
+            },
+        );
+        let disabled_locktime_res = get_dlc_txs(
+            10,
+            &TxSequences {
+                fund_sequence: DISABLE_LOCKTIME,
+                cet_sequence: RBF_SEQUENCE,
+            },
+        );
+        let relative_locktime_res = get_dlc_txs(
+            10,
+            &TxSequences {
+                fund_sequence: RBF_SEQUENCE,
+                cet_sequence: 144,
+            },
+        );
+
+        // Assert
+        assert!(dlc_txs
+            .fund
+            .input
+            .iter()
+            .chain(dlc_txs.cets.iter().flat_map(|x| x.input.iter()))
+            .chain(dlc_txs.refund.input.iter())
+            .all(|x| x.sequence == RBF_SEQUENCE));
+        assert!(no_locktime_res.is_ok());
+        assert!(disabled_locktime_res.is_err());
+        assert!(relative_locktime_res.is_err());
+    }
+
+    #[test]
+    fn validate_cet_lock_time_test() {
+        let maturity = 1_700_000_000;
+        assert!(validate_cet_lock_time(maturity, maturity + 100, maturity).is_ok());
+        assert!(validate_cet_lock_time(100, 200, maturity).is_ok());
+        assert!(validate_cet_lock_time(100, maturity + 100, maturity).is_ok());
+        assert!(validate_cet_lock_time(maturity + 1, maturity + 100, maturity).is_err());
+        assert!(validate_cet_lock_time(200, 100, maturity).is_err());
+    }
+
+    #[test]
+    fn estimate_weights_test() {
+        // Arrange
+        let (mut offer_party_params, _) = get_party_params(1000000000, 100000000, None);
+        let (mut accept_party_params, _) = get_party_params(1000000000, 100000000, None);
+        let mut second_input = accept_party_params.inputs[0].clone();
+        second_input.serial_id += 1;
+        accept_party_params.inputs.push(second_input);
+        for input in offer_party_params
+            .inputs
+            .iter_mut()
+            .chain(accept_party_params.inputs.iter_mut())
+        {
+            input.max_witness_len = P2WPKH_WITNESS_SIZE;
+        }
+        let (offer_fund_fee, offer_cet_fee) = offer_party_params.get_fees(4);
+        let (accept_fund_fee, accept_cet_fee) = accept_party_params.get_fees(4);
+
+        // Act
+        let estimates = estimate_weights(
+            &WeightEstimateParams::p2wpkh(1),
+            &WeightEstimateParams::p2wpkh(2),
+            false,
+            4,
+        );
+
+        // Assert
+        assert_eq!(
+            offer_fund_fee + offer_cet_fee + accept_fund_fee + accept_cet_fee,
+            estimates.total_fee
+        );
+        assert_eq!(estimates.cet_weight, estimates.refund_weight);
+        assert!(
+            estimates.fund_weight
+                > estimate_weights(
+                    &WeightEstimateParams::p2wpkh(1),
+                    &WeightEstimateParams::p2wpkh(1),
+                    false,
+                    4
+                )
+                .fund_weight
+        );
+    }
+
+    #[test]
+    fn estimate_weights_with_script_types_and_anchors_test() {
+        // Arrange
+        let (mut offer_party_params, _) = get_party_params(1000000000, 100000000, None);
+        let (mut accept_party_params, _) = get_party_params(1000000000, 100000000, None);
+        // P2TR payout script and no change output.
+        accept_party_params.payout_script_pubkey = Script::from(vec![0x51; 34]);
+        accept_party_params.change_script_pubkey = Script::new();
+        for input in offer_party_params
+            .inputs
+            .iter_mut()
+            .chain(accept_party_params.inputs.iter_mut())
+        {
+            input.max_witness_len = P2WPKH_WITNESS_SIZE;
+        }
+        let offer_estimate_params = WeightEstimateParams::p2wpkh(1);
+        let accept_estimate_params = WeightEstimateParams {
+            nb_inputs: 1,
+            payout_script_pubkey_len: 34,
+            change_script_pubkey_len: 0,
+        };
+        let dlc_txs = create_dlc_transactions(
+            &offer_party_params,
+            &accept_party_params,
+            &payouts(),
+            100,
+            4,
+            10,
+            10,
+            0,
+        )
+        .unwrap();
+        let anchored_dlc_txs = create_dlc_transactions_with_anchors(
+            &offer_party_params,
+            &accept_party_params,
+            &payouts(),
+            100,
+            4,
+            10,
+            10,
+            0,
+        )
+        .unwrap();
+        let (offer_fund_fee, offer_cet_fee) = offer_party_params.get_fees(4);
+        let (accept_fund_fee, accept_cet_fee) = accept_party_params.get_fees(4);
+
+        // Act
+        let estimates = estimate_weights(&offer_estimate_params, &accept_estimate_params, false, 4);
+        let anchored_estimates =
+            estimate_weights(&offer_estimate_params, &accept_estimate_params, true, 4);
+
+        // Assert
+        assert_eq!(
+            offer_fund_fee + offer_cet_fee + accept_fund_fee + accept_cet_fee,
+            estimates.total_fee
+        );
+        // The anchor outputs are funded through the fund output, by their
+        // value and the fee for adding them.
+        assert_eq!(
+            anchored_dlc_txs.get_fund_output().value - dlc_txs.get_fund_output().value,
+            anchored_estimates.total_fee - estimates.total_fee + 2 * ANCHOR_OUTPUT_VALUE
+        );
+        assert_eq!(
+            anchored_dlc_txs.cets[0].get_weight() - dlc_txs.cets[0].get_weight(),
+            anchored_estimates.cet_weight - estimates.cet_weight
+        );
+    }
+
+    #[test]
+    fn create_dlc_transactions_without_change_test() {
+        // Arrange
+        let (offer_party_params, _) = get_party_params(1000000000, 100000000, None);
+        let (accept_party_params, _) = get_party_params(1000000000, 100000000, None);
+        let mut no_change_params = accept_party_params.clone();
+        no_change_params.change_script_pubkey = Script::new();
+        let get_dlc_txs = |accept_params: &PartyParams| {
+            create_dlc_transactions(
+                &offer_party_params,
+                accept_params,
+                &payouts(),
+                100,
+                4,
+                10,
+                10,
+                0,
+            )
+            .unwrap()
+        };
+
+        // Act
+        let dlc_txs = get_dlc_txs(&accept_party_params);
+        let no_change_txs = get_dlc_txs(&no_change_params);
+
+        // Assert
+        assert_eq!(3, dlc_txs.fund.output.len());
+        assert_eq!(2, no_change_txs.fund.output.len());
+        assert!(no_change_txs
+            .fund
+            .output
+            .iter()
+            .all(|x| !x.script_pubkey.is_empty()));
+        assert_eq!(
+            dlc_txs.get_fund_output().value,
+            no_change_txs.get_fund_output().value
+        );
+    }
+
+    #[test]
+    fn create_cets_from_template_test() {
+        // Arrange
+        let (offer_party_params, _) = get_party_params(1000000000, 100000000, None);
+        let (accept_party_params, _) = get_party_params(1000000000, 100000000, Some(2));
+        let dlc_txs = create_dlc_transactions(
+            &offer_party_params,
+            &accept_party_params,
+            &payouts(),
+            100,
+            4,
+            10,
+            10,
+            0,
+        )
+        .unwrap();
+        let extra_output = TxOut {
+            value: 1000,
+            script_pubkey: offer_party_params.change_script_pubkey.clone(),
+        };
+        let template = Transaction {
+            version: 3,
+            lock_time: 10,
+            input: dlc_txs.cets[0].input.clone(),
+            output: vec![extra_output.clone()],
+        };
+
+        // Act
+        let cets = create_cets_from_template(
+            &template,
+            &offer_party_params.payout_script_pubkey,
+            offer_party_params.payout_serial_id,
+            &accept_party_params.payout_script_pubkey,
+            accept_party_params.payout_serial_id,
+            &payouts(),
+        )
+        .unwrap();
+
+        // Assert
+        for (cet, expected) in cets.iter().zip(dlc_txs.cets.iter()) {
+            assert_eq!(3, cet.version);
+            assert_eq!(expected.input, cet.input);
+            assert_eq!(expected.lock_time, cet.lock_time);
+            assert_eq!(expected.output.len() + 1, cet.output.len());
+            assert_eq!(&expected.output[..], &cet.output[..expected.output.len()]);
+            assert_eq!(&extra_output, cet.output.last().unwrap());
+        }
+        let mut no_input_template = template;
+        no_input_template.input.clear();
+        assert!(create_cets_from_template(
+            &no_input_template,
+            &offer_party_params.payout_script_pubkey,
+            offer_party_params.payout_serial_id,
+            &accept_party_params.payout_script_pubkey,
+            accept_party_params.payout_serial_id,
+            &payouts(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn create_cet_adaptor_sig_from_psbt_is_valid() {
+        // Arrange
+        let secp = Secp256k1::new();
+        let mut rng = secp256k1_zkp::rand::thread_rng();
+        let (offer_party_params, offer_fund_sk) = get_party_params(1000000000, 100000000, None);
+        let (accept_party_params, _) = get_party_params(1000000000, 100000000, None);
+        let dlc_txs = create_dlc_transactions(
+            &offer_party_params,
+            &accept_party_params,
+            &payouts(),
+            100,
+            4,
+            10,
+            10,
+            0,
+        )
+        .unwrap();
+        let adaptor_point = PublicKey::from_secret_key(&secp, &SecretKey::new(&mut rng));
+
+        // Act
+        let cet_psbts = dlc_txs.get_cet_psbts().unwrap();
+        let adaptor_sig =
+            create_cet_adaptor_sig_from_psbt(&secp, &cet_psbts[0], &adaptor_point, &offer_fund_sk)
+                .unwrap();
+
+        // Assert
+        assert_eq!(dlc_txs.cets[0], cet_psbts[0].global.unsigned_tx);
+        assert_eq!(
+            Some(dlc_txs.get_fund_output().clone()),
+            cet_psbts[0].inputs[0].witness_utxo
+        );
+        verify_cet_adaptor_sig_from_point(
+            &secp,
+            &adaptor_sig,
+            &dlc_txs.cets[0],
+            &adaptor_point,
+            &offer_party_params.fund_pubkey,
+            &dlc_txs.funding_script_pubkey,
+            dlc_txs.get_fund_output().value,
+        )
+        .expect("Invalid adaptor signature");
+        verify_cet_adaptor_sig_from_psbt(
+            &secp,
+            &adaptor_sig,
+            &cet_psbts[0],
+            &adaptor_point,
+            &offer_party_params.fund_pubkey,
+        )
+        .expect("Invalid adaptor signature");
+        assert_eq!(
+            dlc_txs.refund,
+            dlc_txs.get_refund_psbt().unwrap().global.unsigned_tx
+        );
+    }
+
+    #[test]
+    fn aggregated_oracle_sig_point_test() {
+        // Arrange
+        let secp = Secp256k1::new();
+        let mut rng = secp256k1_zkp::rand::thread_rng();
+        let key_shares: Vec<SecretKey> = (0..3).map(|_| SecretKey::new(&mut rng)).collect();
+        let nonce_shares: Vec<SecretKey> = (0..3).map(|_| SecretKey::new(&mut rng)).collect();
+        let aggregate = |shares: &[SecretKey]| {
+            let mut res = shares[0];
+            for share in &shares[1..] {
+                res.add_assign(&share[..]).unwrap();
+            }
+            res
+        };
+        let to_x_only = |shares: &[SecretKey]| {
+            let points: Vec<PublicKey> = shares
+                .iter()
+                .map(|x| PublicKey::from_secret_key(&secp, x))
+                .collect();
+            let mut res = points[0];
+            for point in &points[1..] {
+                res = res.combine(point).unwrap();
+            }
+            SchnorrPublicKey::from_slice(&res.serialize()[1..]).unwrap()
+        };
+        let keypair = KeyPair::from_seckey_slice(&secp, &aggregate(&key_shares)[..]).unwrap();
+        let msg = Message::from_hashed_data::<secp256k1_zkp::bitcoin_hashes::sha256::Hash>(
+            "1".as_bytes(),
+        );
+
+        let mut nonce = [0u8; 32];
+        nonce.copy_from_slice(&aggregate(&nonce_shares)[..]);
+
+        // Act
+        let signature = secp_utils::schnorrsig_sign_with_nonce(&secp, &msg, &keypair, &nonce);
+        let sig_point = secp_utils::schnorrsig_compute_sig_point(
+            &secp,
+            &to_x_only(&key_shares),
+            &to_x_only(&nonce_shares),
+            &msg,
+        )
+        .unwrap();
+
+        // Assert
+        let (_, s) = secp_utils::schnorrsig_decompose(&signature).unwrap();
+        secp.schnorrsig_verify(&signature, &msg, &to_x_only(&key_shares))
+            .expect("Invalid aggregated signature");
+        assert_eq!(
+            PublicKey::from_secret_key(&secp, &SecretKey::from_slice(s).unwrap()),
+            sig_point
+        );
+    }
+
+    #[test]
+    fn schnorrsig_recover_secret_key_test() {
+        let secp = Secp256k1::new();
+        let mut rng = secp256k1_zkp::rand::thread_rng();
+        let keypair = KeyPair::new(&secp, &mut rng);
+        let pubkey = SchnorrPublicKey::from_keypair(&secp, &keypair);
+        let mut nonce = [0u8; 32];
+        nonce.copy_from_slice(&SecretKey::new(&mut rng)[..]);
+        let msg1 = get_outcome_message("1");
+        let msg2 = get_outcome_message("2");
+        let sig1 = secp_utils::schnorrsig_sign_with_nonce(&secp, &msg1, &keypair, &nonce);
+        let sig2 = secp_utils::schnorrsig_sign_with_nonce(&secp, &msg2, &keypair, &nonce);
+
+        let secret_key = secp_utils::schnorrsig_recover_secret_key(
+            &secp,
+            &pubkey,
+            (&sig1, &msg1),
+            (&sig2, &msg2),
+        )
+        .unwrap();
+
+        let recovered_keypair = KeyPair::from_seckey_slice(&secp, &secret_key[..]).unwrap();
+        assert_eq!(
+            pubkey,
+            SchnorrPublicKey::from_keypair(&secp, &recovered_keypair)
+        );
+        assert_eq!(
+            Err(Error::InvalidArgument),
+            secp_utils::schnorrsig_recover_secret_key(
+                &secp,
+                &pubkey,
+                (&sig1, &msg1),
+                (&sig1, &msg1)
+            )
+        );
+    }
+
+    #[test]
+    fn verify_batch_test() {
+        // Arrange
+        let secp = Secp256k1::new();
+        let mut rng = secp256k1_zkp::rand::thread_rng();
+        let (offer_party_params, offer_fund_sk) = get_party_params(1000000000, 100000000, None);
+        let (accept_party_params, _) = get_party_params(1000000000, 100000000, None);
+        let dlc_txs = create_dlc_transactions(
+            &offer_party_params,
+            &accept_party_params,
+            &payouts(),
+            100,
+            4,
+            10,
+            10,
+            0,
+        )
+        .unwrap();
+        let fund_output_value = dlc_txs.get_fund_output().value;
+        let mut adaptor_sigs = (0..4)
+            .map(|i| {
+                let cet_index = i % dlc_txs.cets.len();
+                let adaptor_point = PublicKey::from_secret_key(&secp, &SecretKey::new(&mut rng));
+                let adaptor_sig = create_cet_adaptor_sig_from_point(
+                    &secp,
+                    &dlc_txs.cets[cet_index],
+                    &adaptor_point,
+                    &offer_fund_sk,
+                    &dlc_txs.funding_script_pubkey,
+                    fund_output_value,
+                )
+                .unwrap();
+                (cet_index, adaptor_sig, adaptor_point)
+            })
+            .collect::<Vec<_>>();
+
+        // Act/Assert
+        let get_cet = |i: usize| Ok::<_, Error>(Cow::Borrowed(&dlc_txs.cets[i]));
+        verify_batch(
+            &secp,
+            &get_cet,
+            &adaptor_sigs,
+            &offer_party_params.fund_pubkey,
+            &dlc_txs.funding_script_pubkey,
+            fund_output_value,
+        )
+        .expect("Invalid adaptor signature");
+
+        adaptor_sigs[3].2 = adaptor_sigs[0].2;
+        assert!(verify_batch(
+            &secp,
+            &get_cet,
+            &adaptor_sigs,
+            &offer_party_params.fund_pubkey,
+            &dlc_txs.funding_script_pubkey,
+            fund_output_value,
+        )
+        .is_err());
+    }
+
     #[test]
     fn create_cet_adaptor_sig_is_valid() {
         // Arrange
@@ -1404,4 +2936,46 @@ mod tests {
             .expect("Could not find fund output");
         }
     }
+
+    #[test]
+    fn get_dust_limit_test() {
+        let test_cases = vec![
+            (
+                "76a914000000000000000000000000000000000000000088ac",
+                Some(546),
+            ),
+            ("a914000000000000000000000000000000000000000087", Some(540)),
+            ("00140000000000000000000000000000000000000000", Some(294)),
+            (
+                "00200000000000000000000000000000000000000000000000000000000000000000",
+                Some(330),
+            ),
+            (
+                "51200000000000000000000000000000000000000000000000000000000000000000",
+                Some(330),
+            ),
+            ("6a0401020304", None),
+        ];
+
+        for (script_hex, expected) in test_cases {
+            let script = Script::from(Vec::<u8>::from_hex(script_hex).unwrap());
+            assert_eq!(expected, util::get_dust_limit(&script));
+        }
+    }
+
+    #[test]
+    fn outcome_message_normalization_test() {
+        let precomposed = "caf\u{e9}";
+        let combining = "cafe\u{301}";
+        assert_ne!(precomposed.as_bytes(), combining.as_bytes());
+        assert_eq!(precomposed, normalize_outcome(combining));
+        assert_eq!(
+            get_outcome_message(precomposed),
+            get_outcome_message(combining)
+        );
+        assert_eq!(
+            Message::from_hashed_data::<secp256k1_zkp::bitcoin_hashes::sha256::Hash>(b"1"),
+            get_outcome_messages(&["1"])[0]
+        );
+    }
 }
@@ -0,0 +1,201 @@
+//! # Ownership proof
+//! Proofs that a party controls the outputs spent by the funding inputs it
+//! provides, so that a counter party cannot commit UTXOs belonging to someone
+//! else to a contract. Following BIP322, a proof is the witness of an input
+//! spending a virtual transaction locked by the script of the spent output,
+//! the virtual transaction committing to the outpoint and to the fund public
+//! key of the party for the contract. Signing the proof thus only requires a
+//! wallet able to sign regular inputs, while the proof cannot be used to spend
+//! any actual output.
+
+use crate::Error;
+use bitcoin::blockdata::{
+    opcodes,
+    script::{Builder, Script},
+    transaction::{OutPoint, Transaction, TxIn, TxOut},
+};
+use bitcoin::consensus::encode::serialize;
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::{SigHashType, Txid};
+use secp256k1_zkp::{PublicKey, Secp256k1, Signature, Verification};
+
+const OWNERSHIP_PROOF_TAG: &[u8] = b"DLC/funding/ownership";
+
+/// Returns the transaction whose first input must be signed as if it was
+/// spending an output locked by `script_pubkey` to prove the ownership of the
+/// output at `outpoint` for the contract using `fund_pubkey`. The spent output
+/// has a value of zero.
+pub fn get_ownership_proof_transaction(
+    fund_pubkey: &PublicKey,
+    outpoint: &OutPoint,
+    script_pubkey: &Script,
+) -> Transaction {
+    let message_hash = get_ownership_proof_message_hash(fund_pubkey, outpoint);
+    let to_spend = Transaction {
+        version: 0,
+        lock_time: 0,
+        input: vec![TxIn {
+            previous_output: OutPoint {
+                txid: Txid::default(),
+                vout: 0xFFFFFFFF,
+            },
+            script_sig: Builder::new()
+                .push_int(0)
+                .push_slice(&message_hash[..])
+                .into_script(),
+            sequence: 0,
+            witness: Vec::new(),
+        }],
+        output: vec![TxOut {
+            value: 0,
+            script_pubkey: script_pubkey.clone(),
+        }],
+    };
+
+    Transaction {
+        version: 0,
+        lock_time: 0,
+        input: vec![TxIn {
+            previous_output: OutPoint {
+                txid: to_spend.txid(),
+                vout: 0,
+            },
+            script_sig: Script::new(),
+            sequence: 0,
+            witness: Vec::new(),
+        }],
+        output: vec![TxOut {
+            value: 0,
+            script_pubkey: Builder::new()
+                .push_opcode(opcodes::all::OP_RETURN)
+                .into_script(),
+        }],
+    }
+}
+
+/// Verifies that `witness` proves the ownership of the output at `outpoint`
+/// locked by `script_pubkey` for the contract using `fund_pubkey`, the
+/// `redeem_script` being empty unless the output is P2SH wrapped. Only P2WPKH
+/// outputs, native or P2SH wrapped, are supported.
+pub fn verify_ownership_proof<C: Verification>(
+    secp: &Secp256k1<C>,
+    fund_pubkey: &PublicKey,
+    outpoint: &OutPoint,
+    script_pubkey: &Script,
+    redeem_script: &Script,
+    witness: &[Vec<u8>],
+) -> Result<(), Error> {
+    if witness.len() != 2 {
+        return Err(Error::InvalidArgument);
+    }
+
+    let pubkey = bitcoin::PublicKey::from_slice(&witness[1]).map_err(|_| Error::InvalidArgument)?;
+    let wpkh_script = Script::new_v0_wpkh(&pubkey.wpubkey_hash().ok_or(Error::InvalidArgument)?);
+    let is_owned = if redeem_script.is_empty() {
+        script_pubkey == &wpkh_script
+    } else {
+        redeem_script == &wpkh_script
+            && script_pubkey == &Script::new_p2sh(&redeem_script.script_hash())
+    };
+    if !is_owned {
+        return Err(Error::InvalidArgument);
+    }
+
+    let (sig_hash_type, signature) = witness[0].split_last().ok_or(Error::InvalidArgument)?;
+    if *sig_hash_type != SigHashType::All.as_u32() as u8 {
+        return Err(Error::InvalidArgument);
+    }
+    let signature = Signature::from_der(signature)?;
+
+    let proof_tx = get_ownership_proof_transaction(fund_pubkey, outpoint, script_pubkey);
+    let script_code = Script::new_p2pkh(&pubkey.pubkey_hash());
+    crate::verify_tx_input_sig(secp, &signature, &proof_tx, 0, &script_code, 0, &pubkey.key)
+}
+
+fn get_ownership_proof_message_hash(fund_pubkey: &PublicKey, outpoint: &OutPoint) -> sha256::Hash {
+    let tag_hash = sha256::Hash::hash(OWNERSHIP_PROOF_TAG);
+    let mut engine = sha256::Hash::engine();
+    engine.input(&tag_hash);
+    engine.input(&tag_hash);
+    engine.input(&fund_pubkey.serialize());
+    engine.input(&serialize(outpoint));
+    sha256::Hash::from_engine(engine)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1_zkp::{rand::thread_rng, SecretKey};
+    use util::get_witness_for_p2wpkh_input;
+
+    fn get_p2wpkh_script_pubkey(secp: &Secp256k1<secp256k1_zkp::All>, sk: &SecretKey) -> Script {
+        let pubkey = bitcoin::PublicKey {
+            compressed: true,
+            key: PublicKey::from_secret_key(secp, sk),
+        };
+        Script::new_v0_wpkh(&pubkey.wpubkey_hash().unwrap())
+    }
+
+    #[test]
+    fn ownership_proof_verify_test() {
+        let secp = Secp256k1::new();
+        let sk = SecretKey::new(&mut thread_rng());
+        let fund_pubkey = PublicKey::from_secret_key(&secp, &SecretKey::new(&mut thread_rng()));
+        let script_pubkey = get_p2wpkh_script_pubkey(&secp, &sk);
+        let outpoint = OutPoint {
+            txid: Txid::hash(&[1u8; 32]),
+            vout: 1,
+        };
+
+        let proof_tx = get_ownership_proof_transaction(&fund_pubkey, &outpoint, &script_pubkey);
+        let witness = get_witness_for_p2wpkh_input(&secp, &sk, &proof_tx, 0, SigHashType::All, 0);
+
+        verify_ownership_proof(
+            &secp,
+            &fund_pubkey,
+            &outpoint,
+            &script_pubkey,
+            &Script::new(),
+            &witness,
+        )
+        .expect("a valid proof");
+
+        let other_outpoint = OutPoint {
+            txid: outpoint.txid,
+            vout: 0,
+        };
+        verify_ownership_proof(
+            &secp,
+            &fund_pubkey,
+            &other_outpoint,
+            &script_pubkey,
+            &Script::new(),
+            &witness,
+        )
+        .expect_err("a proof for another outpoint");
+
+        let other_fund_pubkey =
+            PublicKey::from_secret_key(&secp, &SecretKey::new(&mut thread_rng()));
+        verify_ownership_proof(
+            &secp,
+            &other_fund_pubkey,
+            &outpoint,
+            &script_pubkey,
+            &Script::new(),
+            &witness,
+        )
+        .expect_err("a proof for another fund public key");
+
+        let other_script_pubkey =
+            get_p2wpkh_script_pubkey(&secp, &SecretKey::new(&mut thread_rng()));
+        verify_ownership_proof(
+            &secp,
+            &fund_pubkey,
+            &outpoint,
+            &other_script_pubkey,
+            &Script::new(),
+            &witness,
+        )
+        .expect_err("a proof for a script of another key");
+    }
+}
@@ -11,7 +11,7 @@ use secp256k1_zkp::bitcoin_hashes::Hash;
 use secp256k1_zkp::bitcoin_hashes::*;
 use secp256k1_zkp::{
     schnorrsig::{KeyPair, PublicKey as SchnorrPublicKey, Signature as SchnorrSignature},
-    Message, PublicKey, Secp256k1, Signing, Verification,
+    Message, PublicKey, Secp256k1, SecretKey, Signing, Verification,
 };
 
 const BIP340_MIDSTATE: [u8; 32] = [
@@ -19,6 +19,12 @@ const BIP340_MIDSTATE: [u8; 32] = [
     0x97, 0xc8, 0x75, 0x50, 0x00, 0x3c, 0xc7, 0x65, 0x90, 0xf6, 0x11, 0x64, 0x33, 0xe9, 0xb6, 0x6a,
 ];
 
+/// The order of the secp256k1 group minus two, used to compute inverses.
+const CURVE_ORDER_MINUS_TWO: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe,
+    0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36, 0x41, 0x3f,
+];
+
 sha256t_hash_newtype!(
     BIP340Hash,
     BIP340HashTag,
@@ -54,6 +60,9 @@ pub fn schnorrsig_sign_with_nonce<S: Signing>(
 }
 
 /// Compute a signature point for the given public key, nonce and message.
+/// The public key and nonce can be aggregates of the keys and nonces of
+/// multiple signers (e.g. a federated oracle using FROST), as long as the
+/// resulting signature is a regular BIP340 signature.
 pub fn schnorrsig_compute_sig_point<C: Verification>(
     secp: &Secp256k1<C>,
     pubkey: &SchnorrPublicKey,
@@ -75,6 +84,57 @@ pub fn schnorrsig_decompose(
     Ok((SchnorrPublicKey::from_slice(&bytes[0..32])?, &bytes[32..64]))
 }
 
+/// Recover the secret key of the signer of two signatures made with the same
+/// nonce over different messages, as happens when an oracle attests to two
+/// different outcomes using a nonce it committed to. Both signatures are
+/// verified against the given public key, and the returned key is the one
+/// whose public key has an even Y coordinate as used in bip340.
+pub fn schnorrsig_recover_secret_key<C: Verification>(
+    secp: &Secp256k1<C>,
+    pubkey: &SchnorrPublicKey,
+    first: (&SchnorrSignature, &Message),
+    second: (&SchnorrSignature, &Message),
+) -> Result<SecretKey, Error> {
+    let (nonce, first_s) = schnorrsig_decompose(first.0)?;
+    let (second_nonce, second_s) = schnorrsig_decompose(second.0)?;
+    if nonce != second_nonce || first.1 == second.1 {
+        return Err(Error::InvalidArgument);
+    }
+    secp.schnorrsig_verify(first.0, first.1, pubkey)?;
+    secp.schnorrsig_verify(second.0, second.1, pubkey)?;
+
+    // As s = k + e * x for both signatures, x = (s1 - s2) / (e1 - e2).
+    let mut s_diff = SecretKey::from_slice(first_s)?;
+    let mut neg_s = SecretKey::from_slice(second_s)?;
+    neg_s.negate_assign();
+    s_diff.add_assign(&neg_s[..])?;
+
+    let mut e_diff = SecretKey::from_slice(&create_schnorr_hash(first.1, &nonce, pubkey))?;
+    let mut neg_e = SecretKey::from_slice(&create_schnorr_hash(second.1, &nonce, pubkey))?;
+    neg_e.negate_assign();
+    e_diff.add_assign(&neg_e[..])?;
+
+    s_diff.mul_assign(&invert_scalar(&e_diff)?[..])?;
+    Ok(s_diff)
+}
+
+// Computes the inverse of a scalar as x^(n - 2) using square and multiply.
+fn invert_scalar(x: &SecretKey) -> Result<SecretKey, Error> {
+    let mut one = [0u8; 32];
+    one[31] = 1;
+    let mut res = SecretKey::from_slice(&one)?;
+    for byte in CURVE_ORDER_MINUS_TWO.iter() {
+        for i in (0..8).rev() {
+            let square = res;
+            res.mul_assign(&square[..])?;
+            if (byte >> i) & 1 == 1 {
+                res.mul_assign(&x[..])?;
+            }
+        }
+    }
+    Ok(res)
+}
+
 extern "C" fn constant_nonce_fn(
     nonce32: *mut c_uchar,
     _msg32: *const c_uchar,
@@ -2,11 +2,17 @@
 
 use bitcoin::util::bip143::SigHashCache;
 use bitcoin::{
-    blockdata::script::Builder, hash_types::PubkeyHash, util::address::Payload, Script,
-    SigHashType, Transaction, TxOut,
+    blockdata::{opcodes, script::Builder},
+    hash_types::PubkeyHash,
+    util::address::Payload,
+    Script, SigHashType, Transaction, TxOut,
 };
 use secp256k1_zkp::{Message, PublicKey, Secp256k1, SecretKey, Signature, Signing};
 
+const DESCRIPTOR_INPUT_CHARSET: &str =
+    "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+const DESCRIPTOR_CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
 /// Get a BIP143 (https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki)
 /// signature hash with sighash all flag for a segwit transaction input as
 /// a Message instance
@@ -204,3 +210,81 @@ pub fn get_output_for_script_pubkey<'a>(
 pub(crate) fn discard_dust(txs: Vec<TxOut>, dust_limit: u64) -> Vec<TxOut> {
     txs.into_iter().filter(|x| x.value >= dust_limit).collect()
 }
+
+/// Returns the minimum value that an output with the given script pubkey must
+/// have to be relayed by Bitcoin Core nodes with default settings, or `None` if
+/// the script pubkey is not of a standard type (P2PKH, P2SH, P2WPKH, P2WSH or
+/// P2TR).
+pub fn get_dust_limit(script_pubkey: &Script) -> Option<u64> {
+    if script_pubkey.is_p2pkh() {
+        Some(546)
+    } else if script_pubkey.is_p2sh() {
+        Some(540)
+    } else if script_pubkey.is_v0_p2wpkh() {
+        Some(294)
+    } else if script_pubkey.is_v0_p2wsh() || is_v1_p2tr(script_pubkey) {
+        Some(330)
+    } else {
+        None
+    }
+}
+
+fn is_v1_p2tr(script_pubkey: &Script) -> bool {
+    let bytes = script_pubkey.as_bytes();
+    bytes.len() == 34
+        && bytes[0] == opcodes::all::OP_PUSHNUM_1.into_u8()
+        && bytes[1] == opcodes::all::OP_PUSHBYTES_32.into_u8()
+}
+
+/// Returns the checksum of the given output descriptor as defined in BIP380
+/// (https://github.com/bitcoin/bips/blob/master/bip-0380.mediawiki), or `None`
+/// if the descriptor contains characters that are not allowed.
+pub fn get_descriptor_checksum(descriptor: &str) -> Option<String> {
+    let mut c = 1;
+    let mut class = 0;
+    let mut class_count = 0;
+    for ch in descriptor.chars() {
+        let pos = DESCRIPTOR_INPUT_CHARSET.find(ch)? as u64;
+        c = descriptor_polymod(c, pos & 31);
+        class = class * 3 + (pos >> 5);
+        class_count += 1;
+        if class_count == 3 {
+            c = descriptor_polymod(c, class);
+            class = 0;
+            class_count = 0;
+        }
+    }
+    if class_count > 0 {
+        c = descriptor_polymod(c, class);
+    }
+    for _ in 0..8 {
+        c = descriptor_polymod(c, 0);
+    }
+    c ^= 1;
+    Some(
+        (0..8)
+            .map(|j| DESCRIPTOR_CHECKSUM_CHARSET[((c >> (5 * (7 - j))) & 31) as usize] as char)
+            .collect(),
+    )
+}
+
+fn descriptor_polymod(c: u64, val: u64) -> u64 {
+    let c0 = c >> 35;
+    let mut c = ((c & 0x7ffffffff) << 5) ^ val;
+    if c0 & 1 != 0 {
+        c ^= 0xf5dee51989;
+    }
+    if c0 & 2 != 0 {
+        c ^= 0xa9fdca3312;
+    }
+    if c0 & 4 != 0 {
+        c ^= 0x1bab10e32d;
+    }
+    if c0 & 8 != 0 {
+        c ^= 0x3706b1677a;
+    }
+    if c0 & 16 != 0 {
+        c ^= 0x644d626ffd;
+    }
+    c
+}
@@ -12,8 +12,9 @@ use bitcoincore_rpc::{Client, RpcApi};
 use bitcoincore_rpc_json::AddressType;
 use dlc::{DlcTransactions, OracleInfo, PartyParams, Payout, RangePayout, TxInputInfo};
 use dlc_trie::digit_decomposition::{decompose_value, pad_range_payouts};
+use dlc_trie::multi_oracle::CoverageStrategy;
 use dlc_trie::multi_oracle_trie_with_diff::MultiOracleTrieWithDiff;
-use dlc_trie::DlcTrie;
+use dlc_trie::{get_cet_from_slice, DlcTrie};
 use secp256k1_zkp::bitcoin_hashes::*;
 use secp256k1_zkp::{
     rand::{seq::SliceRandom, thread_rng, Rng, RngCore},
@@ -333,6 +334,8 @@ fn integration_tests_decomposed_common(
     .1
     .value;
 
+    let get_cet = |i| get_cet_from_slice(&dlc_txs.cets, i);
+
     let mut trie = MultiOracleTrieWithDiff::new(
         base,
         nb_oracles,
@@ -340,6 +343,7 @@ fn integration_tests_decomposed_common(
         nb_digits,
         min_support_exp,
         max_error_exp,
+        CoverageStrategy::MaximizeSupport,
     );
 
     let adaptor_pairs_offer = trie
@@ -349,7 +353,7 @@ fn integration_tests_decomposed_common(
             &funding_script_pubkey,
             fund_output_value,
             &outcomes,
-            &dlc_txs.cets,
+            &get_cet,
             &precomputed_points,
             0,
         )
@@ -361,7 +365,7 @@ fn integration_tests_decomposed_common(
             &accept_params.fund_priv_key,
             &funding_script_pubkey,
             fund_output_value,
-            &dlc_txs.cets,
+            &get_cet,
             &precomputed_points,
         )
         .expect("Error creating accept adaptor signatures.");
@@ -372,7 +376,7 @@ fn integration_tests_decomposed_common(
         &funding_script_pubkey,
         fund_output_value,
         &adaptor_pairs_offer,
-        &dlc_txs.cets,
+        &get_cet,
         &precomputed_points,
     )
     .expect("Invalid offer adaptor signatures");
@@ -383,7 +387,7 @@ fn integration_tests_decomposed_common(
         &funding_script_pubkey,
         fund_output_value,
         &adaptor_pairs_accept,
-        &dlc_txs.cets,
+        &get_cet,
         &precomputed_points,
     )
     .expect("Invalid accept adaptor signatures");
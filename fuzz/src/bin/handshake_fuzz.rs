@@ -0,0 +1,239 @@
+//! Drives two in-process managers through the offer, accept and sign
+//! handshake, the fuzzing input choosing the order in which the messages are
+//! delivered, which ones are mutated or replayed, and when time passes for the
+//! oracle to attest and the contract to close.
+
+use bitcoin::network::constants::Network;
+use dlc::{EnumerationPayout, Payout};
+use dlc_manager::contract::{
+    contract_input::{ContractInput, ContractInputInfo, OracleInput},
+    enum_descriptor::EnumDescriptor,
+    Contract, ContractDescriptor,
+};
+use dlc_manager::manager::Manager;
+use dlc_manager::{Oracle, Storage, Time, TimeSource};
+use dlc_messages::oracle_msgs::{EnumEventDescriptor, EventDescriptor};
+use dlc_messages::{AcceptDlc, Message, OfferDlc, ReestablishDlc, RenewOffer, SignDlc};
+use honggfuzz::fuzz;
+use lightning::util::ser::{Readable, Writeable};
+use mocks::memory_storage_provider::MemoryStorage;
+use mocks::mock_blockchain::MockBlockchain;
+use mocks::mock_oracle_provider::MockOracle;
+use mocks::mock_time::{set_time, MockTime};
+use mocks::mock_wallet::MockWallet;
+use secp256k1_zkp::{PublicKey, Secp256k1, SecretKey};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+const COLLATERAL: u64 = 100_000;
+const EVENT_ID: &str = "event";
+const EVENT_MATURITY: u32 = 1_623_133_104;
+const BLOCK_INTERVAL: u64 = 600;
+
+type TestManager = Manager<
+    Arc<MockWallet>,
+    Arc<MockBlockchain>,
+    Box<MemoryStorage>,
+    Arc<MockOracle>,
+    Arc<MockTime>,
+>;
+
+struct Party {
+    manager: TestManager,
+    node_id: PublicKey,
+    inbox: VecDeque<Message>,
+}
+
+fn get_outcomes() -> Vec<String> {
+    vec!["a".to_string(), "b".to_string()]
+}
+
+fn get_contract_input(oracle: &MockOracle) -> ContractInput {
+    let outcome_payouts = get_outcomes()
+        .into_iter()
+        .zip([(2 * COLLATERAL, 0), (0, 2 * COLLATERAL)].iter())
+        .map(|(outcome, (offer, accept))| EnumerationPayout {
+            outcome,
+            payout: Payout {
+                offer: *offer,
+                accept: *accept,
+            },
+        })
+        .collect();
+    ContractInput {
+        offer_collateral: COLLATERAL,
+        accept_collateral: COLLATERAL,
+        maturity_time: EVENT_MATURITY,
+        fee_rate: 2,
+        contract_infos: vec![ContractInputInfo {
+            contract_descriptor: ContractDescriptor::Enum(EnumDescriptor { outcome_payouts }),
+            oracles: OracleInput {
+                public_keys: vec![oracle.get_public_key()],
+                event_id: EVENT_ID.to_string(),
+                threshold: 1,
+            },
+        }],
+        payout_script_pubkey: None,
+        is_payout_external: false,
+        premium: None,
+        fee_split: None,
+        change_policy: None,
+        nb_confirmations: None,
+        time_source: TimeSource::WallClock,
+        refund_locktime: None,
+        exact_payouts: false,
+    }
+}
+
+fn new_party(blockchain: &Arc<MockBlockchain>, oracle: &Arc<MockOracle>) -> Party {
+    let wallet = Arc::new(MockWallet::new(Network::Regtest));
+    wallet.add_utxo(3 * COLLATERAL);
+    let mut oracles = HashMap::new();
+    oracles.insert(oracle.get_public_key(), Arc::clone(oracle));
+    let node_id = PublicKey::from_secret_key(
+        &Secp256k1::new(),
+        &SecretKey::new(&mut secp256k1_zkp::rand::thread_rng()),
+    );
+    Party {
+        manager: Manager::new(
+            wallet,
+            Arc::clone(blockchain),
+            Box::new(MemoryStorage::new()),
+            oracles,
+            Arc::new(MockTime {}),
+        ),
+        node_id,
+        inbox: VecDeque::new(),
+    }
+}
+
+/// Re-encodes the given message, xoring the byte at the given position with
+/// the given mask. Returns `None` if the result cannot be decoded.
+fn copy_message(msg: &Message, mutation: Option<(usize, u8)>) -> Option<Message> {
+    let mut buf = Vec::new();
+    msg.write(&mut buf).unwrap();
+    if let Some((index, mask)) = mutation {
+        let len = buf.len();
+        buf[index % len] ^= mask;
+    }
+    let mut cursor = std::io::Cursor::new(buf);
+    let res = match msg {
+        Message::Offer(_) => Message::Offer(<OfferDlc as Readable>::read(&mut cursor).ok()?),
+        Message::Accept(_) => Message::Accept(<AcceptDlc as Readable>::read(&mut cursor).ok()?),
+        Message::Sign(_) => Message::Sign(<SignDlc as Readable>::read(&mut cursor).ok()?),
+        Message::RenewOffer(_) => {
+            Message::RenewOffer(<RenewOffer as Readable>::read(&mut cursor).ok()?)
+        }
+        Message::Reestablish(_) => {
+            Message::Reestablish(<ReestablishDlc as Readable>::read(&mut cursor).ok()?)
+        }
+    };
+    Some(res)
+}
+
+/// Delivers the given message to the party at `to`, queuing its answer in the
+/// inbox of the other party. Offers are accepted right away.
+fn deliver(parties: &mut [Party; 2], to: usize, msg: &Message) {
+    let from_id = parties[1 - to].node_id;
+    let answer = match parties[to].manager.on_dlc_message(msg, from_id) {
+        Ok(answer) => answer,
+        Err(_) => return,
+    };
+    if let Some(answer) = answer {
+        parties[1 - to].inbox.push_back(answer);
+    }
+    if let Message::Offer(offer) = msg {
+        if let Ok(temporary_id) = offer.get_temporary_contract_id() {
+            if let Ok((_, _, accept)) = parties[to].manager.accept_contract_offer(&temporary_id) {
+                parties[1 - to].inbox.push_back(Message::Accept(accept));
+            }
+        }
+    }
+}
+
+/// Checks that every contract signed by the offering party is known to the
+/// accepting one, which must have accepted it first.
+fn check_consistency(parties: &[Party; 2]) {
+    let contracts = parties[0].manager.get_store().get_contracts().unwrap();
+    for contract in contracts {
+        if let Contract::Signed(c) | Contract::Confirmed(c) = &contract {
+            let contract_id = c.accepted_contract.get_contract_id();
+            assert!(parties[1]
+                .manager
+                .get_store()
+                .get_contract(&contract_id)
+                .unwrap()
+                .is_some());
+        }
+    }
+}
+
+fn do_test(data: &[u8]) {
+    set_time(EVENT_MATURITY as u64 - 10 * BLOCK_INTERVAL);
+    let blockchain = Arc::new(MockBlockchain::new(Network::Regtest, BLOCK_INTERVAL));
+    let mut oracle = MockOracle::new();
+    oracle.add_event(
+        EVENT_ID,
+        &EventDescriptor::EnumEvent(EnumEventDescriptor {
+            outcomes: get_outcomes(),
+        }),
+        EVENT_MATURITY,
+    );
+    let outcome = get_outcomes()[data.first().copied().unwrap_or(0) as usize % 2].clone();
+    oracle.add_attestation(EVENT_ID, &[outcome]);
+    let oracle = Arc::new(oracle);
+
+    let mut parties = [
+        new_party(&blockchain, &oracle),
+        new_party(&blockchain, &oracle),
+    ];
+    let offer = parties[0]
+        .manager
+        .send_offer(&get_contract_input(&oracle), parties[1].node_id)
+        .expect("Offer to be created");
+    parties[1].inbox.push_back(Message::Offer(offer));
+
+    let mut last_delivered: Option<(usize, Message)> = None;
+    let mut bytes = data.iter().skip(1).copied();
+    while let Some(action) = bytes.next() {
+        let to = (action & 1) as usize;
+        match (action >> 1) % 4 {
+            0 => {
+                if let Some(msg) = parties[to].inbox.pop_front() {
+                    deliver(&mut parties, to, &msg);
+                    last_delivered = Some((to, msg));
+                }
+            }
+            1 => {
+                let index = bytes.next().unwrap_or(0) as usize;
+                let mask = bytes.next().unwrap_or(1).max(1);
+                if let Some(msg) = parties[to].inbox.pop_front() {
+                    if let Some(mutated) = copy_message(&msg, Some((index, mask))) {
+                        deliver(&mut parties, to, &mutated);
+                    }
+                }
+            }
+            2 => {
+                if let Some((to, msg)) = &last_delivered {
+                    if let Some(msg) = copy_message(msg, None) {
+                        deliver(&mut parties, *to, &msg);
+                    }
+                }
+            }
+            _ => {
+                let elapsed = bytes.next().unwrap_or(0) as u64 * BLOCK_INTERVAL;
+                set_time(MockTime {}.unix_time_now() + elapsed);
+                for party in parties.iter() {
+                    let _ = party.manager.periodic_check();
+                }
+            }
+        }
+        check_consistency(&parties);
+    }
+}
+
+fn main() {
+    fuzz!(|data| {
+        do_test(data);
+    });
+}
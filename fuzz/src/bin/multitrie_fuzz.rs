@@ -0,0 +1,92 @@
+//! Fuzzes the parameters of `MultiTrie` as well as the paths inserted in it
+//! and looked up from it.
+
+use dlc::Error;
+use dlc_trie::digit_decomposition::{decompose_value, group_by_ignoring_digits};
+use dlc_trie::multi_oracle::CoverageStrategy;
+use dlc_trie::multi_trie::MultiTrie;
+use honggfuzz::fuzz;
+
+const BASE: usize = 2;
+
+fn do_test(data: &[u8]) {
+    if data.len() < 6 {
+        return;
+    }
+    let nb_tries = 1 + data[0] as usize % 5;
+    let nb_required = 1 + data[1] as usize % nb_tries;
+    let nb_digits = 2 + data[2] as usize % 8;
+    let max_error_exp = 1 + data[3] as usize % (nb_digits - 1);
+    let min_support_exp = data[4] as usize % max_error_exp;
+    let coverage_strategy = match data[5] % 3 {
+        0 => CoverageStrategy::MaximizeSupport,
+        1 => CoverageStrategy::MinimizeCetCount,
+        _ => CoverageStrategy::Custom {
+            support_exp: min_support_exp,
+        },
+    };
+    let mut trie = MultiTrie::<usize>::new(
+        nb_tries,
+        nb_required,
+        BASE,
+        min_support_exp,
+        max_error_exp,
+        nb_digits,
+        coverage_strategy,
+    );
+
+    // The outcomes are split in consecutive ranges whose lengths are given
+    // by the input, as the ranges of a payout curve.
+    let max_outcome = BASE.pow(nb_digits as u32) - 1;
+    let mut bytes = data[6..].iter().copied();
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    while start <= max_outcome {
+        let end = match bytes.next() {
+            Some(b) => (start + b as usize).min(max_outcome),
+            None => max_outcome,
+        };
+        ranges.push((start, end));
+        start = end + 1;
+    }
+
+    for (index, (start, end)) in ranges.iter().enumerate() {
+        for path in group_by_ignoring_digits(*start, *end, BASE, nb_digits) {
+            let mut get_value =
+                |_: &[Vec<usize>], _: &[usize]| -> Result<usize, Error> { Ok(index) };
+            trie.insert(&path, &mut get_value)
+                .expect("Disjoint paths to be inserted");
+        }
+    }
+
+    // Outcomes attested by all the oracles must be found, with the value of
+    // their range.
+    let nb_oracles =
+        nb_required + bytes.next().unwrap_or(0) as usize % (nb_tries - nb_required + 1);
+    for (index, (start, end)) in ranges.iter().enumerate() {
+        for outcome in &[*start, *end] {
+            let digits = decompose_value(*outcome, BASE, nb_digits);
+            let paths: Vec<_> = (0..nb_oracles).map(|i| (i, digits.clone())).collect();
+            let res = trie.look_up(&paths).expect("Attested outcome to be found");
+            assert_eq!(index, *res.value);
+        }
+    }
+
+    // Arbitrary outcomes must not make the look up panic.
+    while let Some(b) = bytes.next() {
+        let paths: Vec<_> = (0..nb_oracles)
+            .map(|i| {
+                let outcome =
+                    (b as usize * (i + 1) + bytes.next().unwrap_or(0) as usize) % (max_outcome + 1);
+                (i, decompose_value(outcome, BASE, nb_digits))
+            })
+            .collect();
+        let _ = trie.look_up(&paths);
+    }
+}
+
+fn main() {
+    fuzz!(|data| {
+        do_test(data);
+    });
+}
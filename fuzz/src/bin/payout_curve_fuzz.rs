@@ -0,0 +1,245 @@
+//! Compares the range payouts computed by `PayoutFunction::to_range_payouts`
+//! for random polynomial payout curves and rounding intervals with a slow
+//! reference evaluating the payout of each outcome using exact rational
+//! arithmetic, to detect rounding drift and off-by-one errors on the bounds of
+//! the ranges.
+
+use dlc_manager::payout_curve::{
+    PayoutFunction, PayoutFunctionPiece, PayoutPoint, PolynomialPayoutCurvePiece, RoundingInterval,
+    RoundingIntervals,
+};
+use honggfuzz::fuzz;
+
+const MAX_OUTCOME: u64 = 1023;
+const EXTRA_PRECISION_DENOMINATOR: i128 = 1 << 16;
+// Results within this distance of the middle of two multiples of the rounding
+// modulus can be rounded either way due to floating point errors.
+const ROUNDING_TOLERANCE: f64 = 1e-6;
+
+struct Input<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Input<'a> {
+    fn u8(&mut self) -> u8 {
+        let res = self.data.get(self.pos).copied().unwrap_or(0);
+        self.pos += 1;
+        res
+    }
+
+    fn u16(&mut self) -> u16 {
+        ((self.u8() as u16) << 8) | self.u8() as u16
+    }
+}
+
+/// A fraction with a positive denominator.
+#[derive(Clone, Copy)]
+struct Rational {
+    num: i128,
+    den: i128,
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+impl Rational {
+    fn new(num: i128, den: i128) -> Rational {
+        let sign = if den < 0 { -1 } else { 1 };
+        let g = gcd(num, den).max(1);
+        Rational {
+            num: sign * num / g,
+            den: sign * den / g,
+        }
+    }
+
+    fn add(self, other: Rational) -> Option<Rational> {
+        let num = self
+            .num
+            .checked_mul(other.den)?
+            .checked_add(other.num.checked_mul(self.den)?)?;
+        Some(Rational::new(num, self.den.checked_mul(other.den)?))
+    }
+
+    fn mul(self, other: Rational) -> Option<Rational> {
+        Some(Rational::new(
+            self.num.checked_mul(other.num)?,
+            self.den.checked_mul(other.den)?,
+        ))
+    }
+}
+
+/// Evaluates the polynomial interpolating the given points at the given
+/// outcome, using Lagrange interpolation as the payout curve does.
+fn evaluate_exact(points: &[PayoutPoint], outcome: u64) -> Option<Rational> {
+    let mut res = Rational::new(0, 1);
+    for (i, point) in points.iter().enumerate() {
+        let payout = point.outcome_payout as i128 * EXTRA_PRECISION_DENOMINATOR
+            + point.extra_precision as i128;
+        let mut term = Rational::new(payout, EXTRA_PRECISION_DENOMINATOR);
+        for (j, other) in points.iter().enumerate() {
+            if i != j {
+                term = term.mul(Rational::new(
+                    outcome as i128 - other.event_outcome as i128,
+                    point.event_outcome as i128 - other.event_outcome as i128,
+                ))?;
+            }
+        }
+        res = res.add(term)?;
+    }
+    Some(res)
+}
+
+/// Rounds the given payout to the closest multiple of the rounding modulus,
+/// halves being rounded up, and returns whether the payout is close to a half.
+fn round_exact(payout: Rational, rounding_mod: u64) -> Option<(u64, bool)> {
+    let rounding_mod = rounding_mod as i128;
+    let step = payout.den.checked_mul(rounding_mod)?;
+    let quotient = payout.num.div_euclid(step);
+    let remainder = payout.num.rem_euclid(step);
+    let rounded = if 2 * remainder >= step {
+        (quotient + 1) * rounding_mod
+    } else {
+        quotient * rounding_mod
+    };
+    let distance_to_half = ((2 * remainder - step) as f64).abs() / (2.0 * payout.den as f64);
+    Some((rounded.max(0) as u64, distance_to_half < ROUNDING_TOLERANCE))
+}
+
+fn get_rounding_mod(rounding_intervals: &RoundingIntervals, outcome: u64) -> u64 {
+    rounding_intervals
+        .intervals
+        .iter()
+        .rev()
+        .find(|x| x.begin_interval <= outcome)
+        .unwrap()
+        .rounding_mod
+}
+
+fn do_test(data: &[u8]) {
+    let mut input = Input { data, pos: 0 };
+    let total_collateral = 1 + input.u16() as u64 * 16;
+
+    let mut pieces_points: Vec<Vec<PayoutPoint>> = Vec::new();
+    let mut last_point = PayoutPoint {
+        event_outcome: input.u8() as u64 % 16,
+        outcome_payout: input.u16() as u64 * total_collateral / u16::MAX as u64,
+        extra_precision: input.u16(),
+    };
+    let nb_pieces = 1 + input.u8() % 3;
+    for _ in 0..nb_pieces {
+        let nb_points = 2 + input.u8() % 3;
+        let mut points = vec![last_point.clone()];
+        for _ in 1..nb_points {
+            let event_outcome = last_point.event_outcome + 1 + input.u8() as u64 % 64;
+            if event_outcome > MAX_OUTCOME {
+                return;
+            }
+            last_point = PayoutPoint {
+                event_outcome,
+                outcome_payout: input.u16() as u64 * total_collateral / u16::MAX as u64,
+                extra_precision: input.u16(),
+            };
+            points.push(last_point.clone());
+        }
+        pieces_points.push(points);
+    }
+
+    let mut intervals = vec![RoundingInterval {
+        begin_interval: 0,
+        rounding_mod: 1 + input.u16() as u64 % 1000,
+    }];
+    for _ in 0..input.u8() % 3 {
+        let begin_interval = intervals.last().unwrap().begin_interval + 1 + input.u8() as u64 * 4;
+        intervals.push(RoundingInterval {
+            begin_interval,
+            rounding_mod: 1 + input.u16() as u64 % 1000,
+        });
+    }
+    let rounding_intervals = RoundingIntervals { intervals };
+
+    // Computes the expected payout of each outcome, a shared point of two
+    // consecutive pieces being evaluated with the first one.
+    let first_outcome = pieces_points[0][0].event_outcome;
+    let mut expected = Vec::new();
+    for (i, points) in pieces_points.iter().enumerate() {
+        let start = if i == 0 {
+            points[0].event_outcome
+        } else {
+            points[0].event_outcome + 1
+        };
+        for outcome in start..(points.last().unwrap().event_outcome + 1) {
+            let rounding_mod = get_rounding_mod(&rounding_intervals, outcome);
+            let payout = match evaluate_exact(points, outcome) {
+                Some(payout) => payout,
+                None => return,
+            };
+            let (rounded, is_ambiguous) = match round_exact(payout, rounding_mod) {
+                Some(res) => res,
+                None => return,
+            };
+            // The payout function requires payouts within the collateral.
+            if rounded + rounding_mod > total_collateral {
+                return;
+            }
+            expected.push((rounded, rounding_mod, is_ambiguous));
+        }
+    }
+
+    let pieces = pieces_points
+        .into_iter()
+        .map(|points| {
+            PolynomialPayoutCurvePiece::new(points)
+                .map(PayoutFunctionPiece::PolynomialPayoutCurvePiece)
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .expect("Points to be ascending");
+    let payout_function = PayoutFunction::new(pieces).expect("Pieces to be continuous");
+    let range_payouts = payout_function.to_range_payouts(total_collateral, &rounding_intervals);
+
+    let mut next_outcome = first_outcome as usize;
+    for (i, range_payout) in range_payouts.iter().enumerate() {
+        assert_eq!(
+            next_outcome, range_payout.start,
+            "Ranges are not contiguous"
+        );
+        assert!(range_payout.count > 0, "Empty range");
+        assert_eq!(
+            total_collateral,
+            range_payout.payout.offer + range_payout.payout.accept
+        );
+        if i > 0 {
+            assert_ne!(
+                range_payouts[i - 1].payout,
+                range_payout.payout,
+                "Consecutive ranges have the same payout"
+            );
+        }
+        for outcome in range_payout.start..(range_payout.start + range_payout.count) {
+            let (payout, rounding_mod, is_ambiguous) = expected[outcome - first_outcome as usize];
+            let actual = range_payout.payout.offer;
+            assert!(
+                actual == payout
+                    || (is_ambiguous
+                        && (actual as i128 - payout as i128).abs() == rounding_mod as i128),
+                "Payout {} instead of {} for outcome {}",
+                actual,
+                payout,
+                outcome
+            );
+        }
+        next_outcome = range_payout.start + range_payout.count;
+    }
+    assert_eq!(first_outcome as usize + expected.len(), next_outcome);
+}
+
+fn main() {
+    fuzz!(|data| {
+        do_test(data);
+    });
+}
@@ -1,21 +1,45 @@
 extern crate dlc_manager;
 
+use dlc_manager::broadcast::{BroadcastKind, PendingBroadcast};
 use dlc_manager::contract::{
-    offered_contract::OfferedContract, signed_contract::SignedContract, Contract,
+    offered_contract::OfferedContract, signed_contract::SignedContract, AdaptorInfo,
+    ArchivedContract, Contract, ContractHistoryEntry,
 };
 use dlc_manager::Storage;
-use dlc_manager::{error::Error as DaemonError, ContractId};
+use dlc_manager::{error::Error as DaemonError, ContractId, TemporaryContractId};
 use std::collections::HashMap;
 use std::sync::RwLock;
 
 pub struct MemoryStorage {
-    contracts: RwLock<HashMap<ContractId, Contract>>,
+    // Contracts are keyed by temporary id until they are accepted, and by
+    // contract id afterwards.
+    contracts: RwLock<HashMap<[u8; 32], Contract>>,
+    history: RwLock<HashMap<TemporaryContractId, Vec<ContractHistoryEntry>>>,
+    archived: RwLock<HashMap<ContractId, ArchivedContract>>,
+    pending_broadcasts: RwLock<HashMap<(ContractId, BroadcastKind), PendingBroadcast>>,
+    // Set if the adaptor information of the contracts is kept separately, as
+    // done by the sled storage, in which case it is not returned with them.
+    adaptor_infos: Option<RwLock<HashMap<ContractId, Vec<AdaptorInfo>>>>,
 }
 
 impl MemoryStorage {
     pub fn new() -> Self {
         MemoryStorage {
             contracts: RwLock::new(HashMap::new()),
+            history: RwLock::new(HashMap::new()),
+            archived: RwLock::new(HashMap::new()),
+            pending_broadcasts: RwLock::new(HashMap::new()),
+            adaptor_infos: None,
+        }
+    }
+
+    /// Creates a storage keeping the adaptor information of the contracts
+    /// separately, so that it has to be loaded through
+    /// [`Storage::get_adaptor_info`].
+    pub fn new_with_separate_adaptor_infos() -> Self {
+        MemoryStorage {
+            adaptor_infos: Some(RwLock::new(HashMap::new())),
+            ..Self::new()
         }
     }
 }
@@ -29,7 +53,15 @@ impl Default for MemoryStorage {
 impl Storage for MemoryStorage {
     fn get_contract(&self, id: &ContractId) -> Result<Option<Contract>, DaemonError> {
         let map = self.contracts.read().expect("Could not get read lock");
-        Ok(map.get(id).cloned())
+        Ok(map.get(&id.0).cloned())
+    }
+
+    fn get_contract_by_temporary_id(
+        &self,
+        temporary_id: &TemporaryContractId,
+    ) -> Result<Option<Contract>, DaemonError> {
+        let map = self.contracts.read().expect("Could not get read lock");
+        Ok(map.get(&temporary_id.0).cloned())
     }
 
     fn get_contracts(&self) -> Result<Vec<Contract>, DaemonError> {
@@ -44,7 +76,7 @@ impl Storage for MemoryStorage {
 
     fn create_contract(&mut self, contract: &OfferedContract) -> Result<(), DaemonError> {
         let mut map = self.contracts.write().expect("Could not get write lock");
-        let res = map.insert(contract.id, Contract::Offered(contract.clone()));
+        let res = map.insert(contract.id.0, Contract::Offered(contract.clone()));
         match res {
             None => Ok(()),
             Some(_) => Err(DaemonError::StorageError(
@@ -55,7 +87,22 @@ impl Storage for MemoryStorage {
 
     fn delete_contract(&mut self, id: &ContractId) -> Result<(), DaemonError> {
         let mut map = self.contracts.write().expect("Could not get write lock");
-        map.remove(id);
+        map.remove(&id.0);
+        if let Some(adaptor_infos) = &self.adaptor_infos {
+            adaptor_infos
+                .write()
+                .expect("Could not get write lock")
+                .remove(id);
+        }
+        Ok(())
+    }
+
+    fn delete_contract_by_temporary_id(
+        &mut self,
+        temporary_id: &TemporaryContractId,
+    ) -> Result<(), DaemonError> {
+        let mut map = self.contracts.write().expect("Could not get write lock");
+        map.remove(&temporary_id.0);
         Ok(())
     }
 
@@ -63,14 +110,45 @@ impl Storage for MemoryStorage {
         let mut map = self.contracts.write().expect("Could not get write lock");
         match contract {
             a @ Contract::Accepted(_) | a @ Contract::Signed(_) => {
-                map.remove(&a.get_temporary_id());
+                map.remove(&a.get_temporary_id().0);
             }
             _ => {}
         };
-        map.insert(contract.get_id(), contract.clone());
+        let key = match contract.get_id() {
+            Some(id) => id.0,
+            None => contract.get_temporary_id().0,
+        };
+        let mut contract = contract.clone();
+        if let Some(adaptor_infos) = &self.adaptor_infos {
+            if let Some(a) = contract.get_accepted_contract_mut() {
+                if !a.adaptor_infos.is_empty() {
+                    adaptor_infos
+                        .write()
+                        .expect("Could not get write lock")
+                        .insert(a.get_contract_id(), std::mem::take(&mut a.adaptor_infos));
+                }
+            }
+        }
+        map.insert(key, contract);
         Ok(())
     }
 
+    fn get_adaptor_info(&self, id: &ContractId) -> Result<Option<Vec<AdaptorInfo>>, DaemonError> {
+        if let Some(adaptor_infos) = &self.adaptor_infos {
+            if let Some(infos) = adaptor_infos
+                .read()
+                .expect("Could not get read lock")
+                .get(id)
+            {
+                return Ok(Some(infos.clone()));
+            }
+        }
+        let map = self.contracts.read().expect("Could not get read lock");
+        Ok(map
+            .get(&id.0)
+            .and_then(|c| c.get_accepted_contract().map(|a| a.adaptor_infos.clone())))
+    }
+
     fn get_signed_contracts(&self) -> Result<Vec<SignedContract>, DaemonError> {
         let map = self.contracts.read().expect("Could not get read lock");
 
@@ -112,4 +190,80 @@ impl Storage for MemoryStorage {
 
         Ok(res)
     }
+
+    fn add_contract_history_entry(
+        &mut self,
+        temporary_id: &TemporaryContractId,
+        entry: &ContractHistoryEntry,
+    ) -> Result<(), DaemonError> {
+        let mut map = self.history.write().expect("Could not get write lock");
+        map.entry(*temporary_id)
+            .or_insert_with(Vec::new)
+            .push(entry.clone());
+        Ok(())
+    }
+
+    fn get_contract_history(
+        &self,
+        temporary_id: &TemporaryContractId,
+    ) -> Result<Vec<ContractHistoryEntry>, DaemonError> {
+        let map = self.history.read().expect("Could not get read lock");
+        Ok(map.get(temporary_id).cloned().unwrap_or_default())
+    }
+
+    fn archive_contract(&mut self, id: &ContractId) -> Result<(), DaemonError> {
+        let mut map = self.contracts.write().expect("Could not get write lock");
+        let archived = map
+            .get(&id.0)
+            .and_then(ArchivedContract::from_contract)
+            .ok_or_else(|| {
+                DaemonError::StorageError("No closed or refunded contract to archive".to_string())
+            })?;
+        map.remove(&id.0);
+        self.archived
+            .write()
+            .expect("Could not get write lock")
+            .insert(*id, archived);
+        Ok(())
+    }
+
+    fn get_archived_contracts(&self) -> Result<Vec<ArchivedContract>, DaemonError> {
+        Ok(self
+            .archived
+            .read()
+            .expect("Could not get read lock")
+            .values()
+            .cloned()
+            .collect())
+    }
+
+    fn save_pending_broadcast(&mut self, pending: &PendingBroadcast) -> Result<(), DaemonError> {
+        self.pending_broadcasts
+            .write()
+            .expect("Could not get write lock")
+            .insert((pending.contract_id, pending.kind), pending.clone());
+        Ok(())
+    }
+
+    fn delete_pending_broadcast(
+        &mut self,
+        contract_id: &ContractId,
+        kind: BroadcastKind,
+    ) -> Result<(), DaemonError> {
+        self.pending_broadcasts
+            .write()
+            .expect("Could not get write lock")
+            .remove(&(*contract_id, kind));
+        Ok(())
+    }
+
+    fn get_pending_broadcasts(&self) -> Result<Vec<PendingBroadcast>, DaemonError> {
+        Ok(self
+            .pending_broadcasts
+            .read()
+            .expect("Could not get read lock")
+            .values()
+            .cloned()
+            .collect())
+    }
 }
@@ -0,0 +1,319 @@
+extern crate dlc_manager;
+
+use crate::mock_time::MockTime;
+use bitcoin::network::constants::Network;
+use bitcoin::{OutPoint, Transaction, Txid};
+use dlc_manager::error::Error as DaemonError;
+use dlc_manager::{Blockchain, Time};
+use std::sync::{Mutex, MutexGuard};
+
+// Number of blocks used to compute the median time past, as in bitcoin.
+const MEDIAN_TIME_SPAN: usize = 11;
+
+struct Block {
+    time: u64,
+    transactions: Vec<Transaction>,
+}
+
+struct ChainState {
+    blocks: Vec<Block>,
+    mempool: Vec<Transaction>,
+    broadcast: Vec<Transaction>,
+    // Number of blocks mined as a result of the mock time advancing, which
+    // does not decrease on reorgs so that reorged blocks are not mined again.
+    nb_timed_blocks: u64,
+}
+
+/// A blockchain kept in memory, in which a block including the transactions
+/// of the mempool is mined every `block_interval` seconds of mock time (see
+/// [`crate::mock_time::set_time`]). Blocks can also be mined, reorged and
+/// transactions evicted from the mempool explicitly to test the monitoring of
+/// the chain by the manager. As the mock time is thread local, the blockchain
+/// should be queried from the thread setting it.
+pub struct MockBlockchain {
+    network: Network,
+    block_interval: u64,
+    start_time: u64,
+    state: Mutex<ChainState>,
+}
+
+impl MockBlockchain {
+    pub fn new(network: Network, block_interval: u64) -> Self {
+        assert!(block_interval > 0, "Block interval must be positive");
+        MockBlockchain {
+            network,
+            block_interval,
+            start_time: MockTime {}.unix_time_now(),
+            state: Mutex::new(ChainState {
+                blocks: Vec::new(),
+                mempool: Vec::new(),
+                broadcast: Vec::new(),
+                nb_timed_blocks: 0,
+            }),
+        }
+    }
+
+    /// Mines the given number of blocks, the first one including the
+    /// transactions of the mempool.
+    pub fn mine_blocks(&self, nb_blocks: u64) {
+        let mut state = self.sync();
+        let time = MockTime {}.unix_time_now();
+        for _ in 0..nb_blocks {
+            mine_block(&mut state, time);
+        }
+    }
+
+    /// Removes the last `depth` blocks from the chain, their transactions
+    /// going back to the mempool unless `evict` is set.
+    pub fn reorg(&self, depth: usize, evict: bool) {
+        let mut state = self.sync();
+        let new_len = state.blocks.len().saturating_sub(depth);
+        let removed: Vec<Block> = state.blocks.drain(new_len..).collect();
+        if !evict {
+            for block in removed {
+                state.mempool.extend(block.transactions);
+            }
+        }
+    }
+
+    /// Removes the transaction with the given id from the mempool, returning
+    /// whether it was in it.
+    pub fn evict(&self, txid: &Txid) -> bool {
+        let mut state = self.sync();
+        let len = state.mempool.len();
+        state.mempool.retain(|x| x.txid() != *txid);
+        state.mempool.len() != len
+    }
+
+    /// Returns all the transactions broadcast so far, in the order in which
+    /// they were broadcast.
+    pub fn get_broadcast_transactions(&self) -> Vec<Transaction> {
+        self.state.lock().unwrap().broadcast.clone()
+    }
+
+    /// Returns the number of confirmations of the transaction with the given
+    /// id, zero if it is not included in the chain.
+    pub fn get_confirmations(&self, txid: &Txid) -> u64 {
+        let state = self.sync();
+        match get_block_index(&state, txid) {
+            Some(index) => (state.blocks.len() - index) as u64,
+            None => 0,
+        }
+    }
+
+    // Mines the blocks that are due given the current mock time.
+    fn sync(&self) -> MutexGuard<'_, ChainState> {
+        let mut state = self.state.lock().unwrap();
+        let now = MockTime {}.unix_time_now();
+        let nb_due = now.saturating_sub(self.start_time) / self.block_interval;
+        while state.nb_timed_blocks < nb_due {
+            state.nb_timed_blocks += 1;
+            let time = self.start_time + state.nb_timed_blocks * self.block_interval;
+            mine_block(&mut state, time);
+        }
+        state
+    }
+}
+
+fn mine_block(state: &mut ChainState, time: u64) {
+    let transactions = std::mem::take(&mut state.mempool);
+    state.blocks.push(Block { time, transactions });
+}
+
+fn get_block_index(state: &ChainState, txid: &Txid) -> Option<usize> {
+    state
+        .blocks
+        .iter()
+        .position(|x| x.transactions.iter().any(|tx| tx.txid() == *txid))
+}
+
+fn get_spending_transaction<'a>(
+    state: &'a ChainState,
+    outpoint: &OutPoint,
+) -> Option<&'a Transaction> {
+    state
+        .blocks
+        .iter()
+        .flat_map(|x| x.transactions.iter())
+        .chain(state.mempool.iter())
+        .find(|tx| tx.input.iter().any(|x| x.previous_output == *outpoint))
+}
+
+impl Blockchain for MockBlockchain {
+    fn send_transaction(&self, transaction: &Transaction) -> Result<(), DaemonError> {
+        let mut state = self.sync();
+        let txid = transaction.txid();
+        if get_block_index(&state, &txid).is_some()
+            || state.mempool.iter().any(|x| x.txid() == txid)
+        {
+            return Ok(());
+        }
+        let is_double_spend = transaction
+            .input
+            .iter()
+            .any(|x| get_spending_transaction(&state, &x.previous_output).is_some());
+        if is_double_spend {
+            return Err(DaemonError::BlockchainError);
+        }
+        state.mempool.push(transaction.clone());
+        state.broadcast.push(transaction.clone());
+        Ok(())
+    }
+
+    fn get_network(&self) -> Result<Network, DaemonError> {
+        Ok(self.network)
+    }
+
+    fn get_block_height(&self) -> Result<u64, DaemonError> {
+        Ok(self.sync().blocks.len() as u64)
+    }
+
+    fn get_median_time_past(&self) -> Result<u64, DaemonError> {
+        let state = self.sync();
+        let start = state.blocks.len().saturating_sub(MEDIAN_TIME_SPAN);
+        let mut times: Vec<u64> = state.blocks[start..].iter().map(|x| x.time).collect();
+        if times.is_empty() {
+            return Ok(self.start_time);
+        }
+        times.sort_unstable();
+        Ok(times[times.len() / 2])
+    }
+
+    fn is_in_mempool(&self, txid: &Txid) -> Result<bool, DaemonError> {
+        Ok(self.sync().mempool.iter().any(|x| x.txid() == *txid))
+    }
+
+    fn get_tx_block_height(&self, txid: &Txid) -> Result<Option<u64>, DaemonError> {
+        Ok(get_block_index(&self.sync(), txid).map(|x| x as u64 + 1))
+    }
+
+    fn get_spending_transaction(
+        &self,
+        outpoint: &OutPoint,
+    ) -> Result<Option<Transaction>, DaemonError> {
+        Ok(get_spending_transaction(&self.sync(), outpoint).cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_time::set_time;
+    use bitcoin::{Script, TxIn, TxOut};
+
+    const START_TIME: u64 = 1_000_000;
+    const BLOCK_INTERVAL: u64 = 600;
+
+    fn get_transaction(previous_output: OutPoint, lock_time: u32) -> Transaction {
+        Transaction {
+            version: 2,
+            lock_time,
+            input: vec![TxIn {
+                previous_output,
+                script_sig: Script::new(),
+                sequence: 0xffffffff,
+                witness: Vec::new(),
+            }],
+            output: vec![TxOut {
+                value: 1000,
+                script_pubkey: Script::new(),
+            }],
+        }
+    }
+
+    fn get_blockchain() -> MockBlockchain {
+        set_time(START_TIME);
+        MockBlockchain::new(Network::Regtest, BLOCK_INTERVAL)
+    }
+
+    #[test]
+    fn mined_transaction_is_confirmed_test() {
+        let blockchain = get_blockchain();
+        let tx = get_transaction(OutPoint::null(), 0);
+        blockchain.send_transaction(&tx).unwrap();
+        assert!(blockchain.is_in_mempool(&tx.txid()).unwrap());
+        assert_eq!(None, blockchain.get_tx_block_height(&tx.txid()).unwrap());
+
+        blockchain.mine_blocks(3);
+        assert!(!blockchain.is_in_mempool(&tx.txid()).unwrap());
+        assert_eq!(Some(1), blockchain.get_tx_block_height(&tx.txid()).unwrap());
+        assert_eq!(3, blockchain.get_block_height().unwrap());
+        assert_eq!(3, blockchain.get_confirmations(&tx.txid()));
+    }
+
+    #[test]
+    fn blocks_are_mined_as_time_advances_test() {
+        let blockchain = get_blockchain();
+        assert_eq!(0, blockchain.get_block_height().unwrap());
+        assert_eq!(START_TIME, blockchain.get_median_time_past().unwrap());
+
+        set_time(START_TIME + 3 * BLOCK_INTERVAL + 1);
+        assert_eq!(3, blockchain.get_block_height().unwrap());
+        assert_eq!(
+            START_TIME + 2 * BLOCK_INTERVAL,
+            blockchain.get_median_time_past().unwrap()
+        );
+
+        // Reorged blocks are not mined again by the time.
+        blockchain.reorg(2, false);
+        assert_eq!(1, blockchain.get_block_height().unwrap());
+    }
+
+    #[test]
+    fn reorg_test() {
+        let blockchain = get_blockchain();
+        let tx = get_transaction(OutPoint::null(), 0);
+        let other_tx = get_transaction(OutPoint::null(), 1);
+        blockchain.send_transaction(&tx).unwrap();
+        blockchain.mine_blocks(2);
+
+        blockchain.reorg(2, false);
+        assert!(blockchain.is_in_mempool(&tx.txid()).unwrap());
+        // The transaction still spends its input.
+        assert!(blockchain.send_transaction(&other_tx).is_err());
+
+        blockchain.mine_blocks(1);
+        blockchain.reorg(1, true);
+        assert!(!blockchain.is_in_mempool(&tx.txid()).unwrap());
+        assert_eq!(0, blockchain.get_confirmations(&tx.txid()));
+        blockchain.send_transaction(&other_tx).unwrap();
+    }
+
+    #[test]
+    fn evict_test() {
+        let blockchain = get_blockchain();
+        let tx = get_transaction(OutPoint::null(), 0);
+        blockchain.send_transaction(&tx).unwrap();
+        let spent = tx.input[0].previous_output;
+        assert_eq!(
+            Some(tx.txid()),
+            blockchain
+                .get_spending_transaction(&spent)
+                .unwrap()
+                .map(|x| x.txid())
+        );
+
+        assert!(blockchain.evict(&tx.txid()));
+        assert!(!blockchain.evict(&tx.txid()));
+        assert!(blockchain
+            .get_spending_transaction(&spent)
+            .unwrap()
+            .is_none());
+        // Sending an evicted transaction again broadcasts it again.
+        blockchain.send_transaction(&tx).unwrap();
+        assert_eq!(2, blockchain.get_broadcast_transactions().len());
+    }
+
+    #[test]
+    fn double_spend_is_rejected_test() {
+        let blockchain = get_blockchain();
+        let tx = get_transaction(OutPoint::null(), 0);
+        blockchain.send_transaction(&tx).unwrap();
+        // Sending the same transaction again is a no op.
+        blockchain.send_transaction(&tx).unwrap();
+        assert!(blockchain
+            .send_transaction(&get_transaction(OutPoint::null(), 1))
+            .is_err());
+        assert_eq!(1, blockchain.get_broadcast_transactions().len());
+    }
+}
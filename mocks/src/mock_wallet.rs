@@ -0,0 +1,330 @@
+extern crate dlc_manager;
+
+use bitcoin::blockdata::transaction::SigHashType;
+use bitcoin::network::constants::Network;
+use bitcoin::util::psbt::PartiallySignedTransaction;
+use bitcoin::{Address, OutPoint, Script, Transaction, TxIn, TxOut, Txid};
+use dlc_manager::error::Error as DaemonError;
+use dlc_manager::{Utxo, Wallet};
+use secp256k1_zkp::rand::thread_rng;
+use secp256k1_zkp::{All, PublicKey, Secp256k1, SecretKey};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+#[derive(Default)]
+struct WalletState {
+    keys: HashMap<PublicKey, SecretKey>,
+    scripts: HashMap<Script, SecretKey>,
+    utxos: Vec<Utxo>,
+    locked: HashSet<OutPoint>,
+    transactions: HashMap<Txid, Transaction>,
+}
+
+/// A wallet kept in memory, generating a new P2WPKH key for each address or
+/// secret key requested and signing the inputs spending them. Funds are added
+/// with [`MockWallet::add_utxo`], the transactions creating them being
+/// considered confirmed.
+pub struct MockWallet {
+    secp: Secp256k1<All>,
+    network: Network,
+    state: Mutex<WalletState>,
+}
+
+impl MockWallet {
+    pub fn new(network: Network) -> Self {
+        MockWallet {
+            secp: Secp256k1::new(),
+            network,
+            state: Mutex::new(WalletState::default()),
+        }
+    }
+
+    /// Adds a UTXO of the given value paying to a new address of the wallet,
+    /// created by a transaction that can be retrieved with
+    /// [`Wallet::get_transaction`].
+    pub fn add_utxo(&self, value: u64) -> Utxo {
+        let (_, address) = self.new_key();
+        let mut state = self.state.lock().unwrap();
+        let tx = Transaction {
+            version: 2,
+            // Makes the ids of the transactions distinct.
+            lock_time: state.transactions.len() as u32,
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                script_sig: Script::new(),
+                sequence: 0xffffffff,
+                witness: Vec::new(),
+            }],
+            output: vec![TxOut {
+                value,
+                script_pubkey: address.script_pubkey(),
+            }],
+        };
+        let utxo = Utxo {
+            tx_out: tx.output[0].clone(),
+            outpoint: OutPoint {
+                txid: tx.txid(),
+                vout: 0,
+            },
+            address,
+            redeem_script: Script::new(),
+            witness_script: None,
+            max_witness_len: None,
+        };
+        state.transactions.insert(tx.txid(), tx);
+        state.utxos.push(utxo.clone());
+        utxo
+    }
+
+    /// Returns the total value of the UTXOs of the wallet that are not locked.
+    pub fn get_balance(&self) -> u64 {
+        let state = self.state.lock().unwrap();
+        state
+            .utxos
+            .iter()
+            .filter(|x| !state.locked.contains(&x.outpoint))
+            .map(|x| x.tx_out.value)
+            .sum()
+    }
+
+    fn new_key(&self) -> (SecretKey, Address) {
+        let sk = SecretKey::new(&mut thread_rng());
+        let pk = PublicKey::from_secret_key(&self.secp, &sk);
+        let address = Address::p2wpkh(
+            &bitcoin::PublicKey {
+                compressed: true,
+                key: pk,
+            },
+            self.network,
+        )
+        .expect("Compressed keys to be valid");
+        let mut state = self.state.lock().unwrap();
+        state.keys.insert(pk, sk);
+        state.scripts.insert(address.script_pubkey(), sk);
+        (sk, address)
+    }
+}
+
+impl Wallet for MockWallet {
+    fn get_new_address(&self) -> Result<Address, DaemonError> {
+        Ok(self.new_key().1)
+    }
+
+    fn get_new_secret_key(&self) -> Result<SecretKey, DaemonError> {
+        Ok(self.new_key().0)
+    }
+
+    fn get_secret_key_for_pubkey(&self, pubkey: &PublicKey) -> Result<SecretKey, DaemonError> {
+        self.state
+            .lock()
+            .unwrap()
+            .keys
+            .get(pubkey)
+            .cloned()
+            .ok_or_else(|| DaemonError::InvalidParameters("Unknown public key".to_string()))
+    }
+
+    fn sign_psbt_input(
+        &self,
+        psbt: &mut PartiallySignedTransaction,
+        input_index: usize,
+    ) -> Result<(), DaemonError> {
+        let tx = &psbt.global.unsigned_tx;
+        let vout = tx.input[input_index].previous_output.vout as usize;
+        let psbt_input = &psbt.inputs[input_index];
+        let tx_out = match (&psbt_input.witness_utxo, &psbt_input.non_witness_utxo) {
+            (Some(tx_out), _) => tx_out.clone(),
+            (None, Some(prev_tx)) => prev_tx
+                .output
+                .get(vout)
+                .ok_or(DaemonError::InvalidState)?
+                .clone(),
+            (None, None) => return Err(DaemonError::InvalidState),
+        };
+        let sk = *self
+            .state
+            .lock()
+            .unwrap()
+            .scripts
+            .get(&tx_out.script_pubkey)
+            .ok_or_else(|| {
+                DaemonError::InvalidParameters("Input does not belong to the wallet".to_string())
+            })?;
+
+        let witness = dlc::util::get_witness_for_p2wpkh_input(
+            &self.secp,
+            &sk,
+            tx,
+            input_index,
+            SigHashType::All,
+            tx_out.value,
+        );
+        psbt.inputs[input_index].final_script_witness = Some(witness);
+
+        Ok(())
+    }
+
+    fn get_utxos_for_amount(
+        &self,
+        amount: u64,
+        _fee_rate: Option<u64>,
+        lock_utxos: bool,
+    ) -> Result<Vec<Utxo>, DaemonError> {
+        let mut state = self.state.lock().unwrap();
+        let mut available: Vec<&Utxo> = state
+            .utxos
+            .iter()
+            .filter(|x| !state.locked.contains(&x.outpoint))
+            .collect();
+        available.sort_by(|a, b| b.tx_out.value.cmp(&a.tx_out.value));
+
+        let mut selected = Vec::new();
+        let mut total = 0;
+        for utxo in available {
+            if total >= amount {
+                break;
+            }
+            total += utxo.tx_out.value;
+            selected.push(utxo.clone());
+        }
+        if total < amount {
+            return Err(DaemonError::WalletError(
+                format!("Not enough funds: {} available for {}", total, amount).into(),
+            ));
+        }
+
+        if lock_utxos {
+            state.locked.extend(selected.iter().map(|x| x.outpoint));
+        }
+        Ok(selected)
+    }
+
+    fn import_address(&self, _address: &Address) -> Result<(), DaemonError> {
+        Ok(())
+    }
+
+    fn get_transaction(&self, tx_id: &Txid) -> Result<Transaction, DaemonError> {
+        self.state
+            .lock()
+            .unwrap()
+            .transactions
+            .get(tx_id)
+            .cloned()
+            .ok_or_else(|| DaemonError::InvalidParameters("Unknown transaction".to_string()))
+    }
+
+    fn get_transaction_confirmations(&self, tx_id: &Txid) -> Result<u32, DaemonError> {
+        self.get_transaction(tx_id).map(|_| 1)
+    }
+
+    fn unlock_utxos(&self, outpoints: &[OutPoint]) -> Result<(), DaemonError> {
+        let mut state = self.state.lock().unwrap();
+        for outpoint in outpoints {
+            state.locked.remove(outpoint);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_spending_psbt(utxo: &Utxo) -> PartiallySignedTransaction {
+        let tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: utxo.outpoint,
+                script_sig: Script::new(),
+                sequence: 0xffffffff,
+                witness: Vec::new(),
+            }],
+            output: vec![TxOut {
+                value: utxo.tx_out.value - 1000,
+                script_pubkey: Script::new(),
+            }],
+        };
+        PartiallySignedTransaction::from_unsigned_tx(tx).unwrap()
+    }
+
+    #[test]
+    fn utxo_selection_test() {
+        let wallet = MockWallet::new(Network::Regtest);
+        let small = wallet.add_utxo(1000);
+        let large = wallet.add_utxo(5000);
+
+        let utxos = wallet.get_utxos_for_amount(4000, None, false).unwrap();
+        assert_eq!(vec![large.outpoint], get_outpoints(&utxos));
+        let utxos = wallet.get_utxos_for_amount(5500, None, true).unwrap();
+        assert_eq!(vec![large.outpoint, small.outpoint], get_outpoints(&utxos));
+        assert_eq!(0, wallet.get_balance());
+        assert!(matches!(
+            wallet.get_utxos_for_amount(1, None, false),
+            Err(DaemonError::WalletError(_))
+        ));
+
+        wallet.unlock_utxos(&[small.outpoint]).unwrap();
+        assert_eq!(1000, wallet.get_balance());
+        let utxos = wallet.get_utxos_for_amount(1000, None, false).unwrap();
+        assert_eq!(vec![small.outpoint], get_outpoints(&utxos));
+    }
+
+    fn get_outpoints(utxos: &[Utxo]) -> Vec<OutPoint> {
+        utxos.iter().map(|x| x.outpoint).collect()
+    }
+
+    #[test]
+    fn sign_psbt_input_test() {
+        let wallet = MockWallet::new(Network::Regtest);
+        let utxo = wallet.add_utxo(10_000);
+        let mut psbt = get_spending_psbt(&utxo);
+        psbt.inputs[0].non_witness_utxo =
+            Some(wallet.get_transaction(&utxo.outpoint.txid).unwrap());
+        wallet.sign_psbt_input(&mut psbt, 0).unwrap();
+
+        let witness = psbt.inputs[0].final_script_witness.clone().unwrap();
+        assert_eq!(2, witness.len());
+        let pk = PublicKey::from_slice(&witness[1]).unwrap();
+        let sk = wallet.get_secret_key_for_pubkey(&pk).unwrap();
+        assert_eq!(
+            dlc::util::get_witness_for_p2wpkh_input(
+                &wallet.secp,
+                &sk,
+                &psbt.global.unsigned_tx,
+                0,
+                SigHashType::All,
+                utxo.tx_out.value,
+            ),
+            witness
+        );
+
+        // Providing the witness UTXO instead gives the same signature.
+        let mut witness_utxo_psbt = get_spending_psbt(&utxo);
+        witness_utxo_psbt.inputs[0].witness_utxo = Some(utxo.tx_out.clone());
+        wallet.sign_psbt_input(&mut witness_utxo_psbt, 0).unwrap();
+        assert_eq!(
+            Some(witness),
+            witness_utxo_psbt.inputs[0].final_script_witness
+        );
+    }
+
+    #[test]
+    fn sign_psbt_input_rejects_unknown_inputs_test() {
+        let wallet = MockWallet::new(Network::Regtest);
+        let other_wallet = MockWallet::new(Network::Regtest);
+        let utxo = other_wallet.add_utxo(10_000);
+        let mut psbt = get_spending_psbt(&utxo);
+        assert!(matches!(
+            wallet.sign_psbt_input(&mut psbt, 0),
+            Err(DaemonError::InvalidState)
+        ));
+
+        psbt.inputs[0].witness_utxo = Some(utxo.tx_out.clone());
+        assert!(matches!(
+            wallet.sign_psbt_input(&mut psbt, 0),
+            Err(DaemonError::InvalidParameters(_))
+        ));
+        assert!(psbt.inputs[0].final_script_witness.is_none());
+    }
+}
@@ -7,9 +7,8 @@ use bitcoin::network::constants::Network;
 use bitcoin::secp256k1::key::PublicKey;
 use dlc_manager::contract::contract_input::ContractInput;
 use dlc_manager::contract::{ClosedContract, Contract};
-use dlc_manager::Storage;
+use dlc_manager::{Storage, TemporaryContractId};
 use dlc_messages::Message as DlcMessage;
-use hex_utils::{hex_str, to_slice};
 use lightning::ln::msgs::NetAddress;
 use serde::Deserialize;
 use serde_json::Value;
@@ -19,7 +18,7 @@ use std::io;
 use std::io::{BufRead, Write};
 use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
 use std::str::FromStr;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use std::time::Duration;
 
 #[derive(Debug, Deserialize)]
@@ -104,7 +103,7 @@ pub(crate) fn parse_config(config_path: &str) -> Result<Configuration, String> {
 pub(crate) async fn poll_for_user_input(
     peer_manager: Arc<PeerManager>,
     dlc_message_handler: Arc<DlcMessageHandler>,
-    dlc_manager: Arc<Mutex<DlcManager>>,
+    dlc_manager: Arc<DlcManager>,
     offers_path: &str,
 ) {
     println!("DLC node startup successful. To view available commands: \"help\".");
@@ -180,8 +179,6 @@ pub(crate) async fn poll_for_user_input(
                     let manager_clone = dlc_manager.clone();
                     let offer = tokio::task::spawn_blocking(move || {
                         manager_clone
-                            .lock()
-                            .unwrap()
                             .send_offer(&contract_input, pubkey)
                             .expect("Error sending offer")
                     })
@@ -193,15 +190,14 @@ pub(crate) async fn poll_for_user_input(
                 "listoffers" => {
                     process_incoming_messages(&peer_manager, &dlc_manager, &dlc_message_handler);
 
-                    let locked_manager = dlc_manager.lock().unwrap();
-                    for offer in locked_manager
+                    for offer in dlc_manager
                         .get_store()
                         .get_contract_offers()
                         .unwrap()
                         .iter()
                         .filter(|x| !x.is_offer_party)
                     {
-                        let offer_id = hex_str(&offer.id);
+                        let offer_id = offer.id.to_string();
                         let offer_json_path = format!("{}/{}.json", offers_path, offer_id);
                         if fs::metadata(&offer_json_path).is_err() {
                             let offer_str = serde_json::to_string_pretty(&offer)
@@ -218,21 +214,16 @@ pub(crate) async fn poll_for_user_input(
                             println!("ERROR: acceptoffer expects the contract id as parameter.");
                             continue;
                         }
-                        Some(s) => {
-                            let mut res = [0u8; 32];
-                            match to_slice(s, &mut res) {
-                                Err(_) => {
-                                    println!("ERROR: invalid contract id.");
-                                    continue;
-                                }
-                                Ok(_) => res,
+                        Some(s) => match TemporaryContractId::from_str(s) {
+                            Err(_) => {
+                                println!("ERROR: invalid contract id.");
+                                continue;
                             }
-                        }
+                            Ok(id) => id,
+                        },
                     };
 
                     let (_, node_id, msg) = dlc_manager
-                        .lock()
-                        .unwrap()
                         .accept_contract_offer(&contract_id)
                         .expect("Error accepting contract.");
                     dlc_message_handler.send_message(node_id, DlcMessage::Accept(msg));
@@ -244,18 +235,17 @@ pub(crate) async fn poll_for_user_input(
                     // Because the oracle client is currently blocking we need to use `spawn_blocking` here.
                     tokio::task::spawn_blocking(move || {
                         manager_clone
-                            .lock()
-                            .unwrap()
                             .periodic_check()
                             .expect("Error doing periodic check.");
                         let contracts = manager_clone
-                            .lock()
-                            .unwrap()
                             .get_store()
                             .get_contracts()
                             .expect("Error retrieving contract list.");
                         for contract in contracts {
-                            let id = hex_str(&contract.get_id());
+                            let id = match contract.get_id() {
+                                Some(id) => id.to_string(),
+                                None => contract.get_temporary_id().to_string(),
+                            };
                             match contract {
                                 Contract::Offered(_) => {
                                     println!("Offered contract: {}", id);
@@ -284,6 +274,12 @@ pub(crate) async fn poll_for_user_input(
                                 Contract::Refunded(_) => {
                                     println!("Refunded contract: {}", id);
                                 }
+                                Contract::RefundedDueToNoAttestation(_) => {
+                                    println!("Refunded contract (no attestation): {}", id);
+                                }
+                                Contract::Renewed(_) => {
+                                    println!("Renewed contract: {}", id);
+                                }
                                 _ => {
                                     println!("Rejected contract: {}", id);
                                 }
@@ -421,7 +417,7 @@ fn compute_pnl(contract: &ClosedContract) -> i64 {
 
 fn process_incoming_messages(
     peer_manager: &Arc<PeerManager>,
-    dlc_manager: &Arc<Mutex<DlcManager>>,
+    dlc_manager: &Arc<DlcManager>,
     dlc_message_handler: &Arc<DlcMessageHandler>,
 ) {
     let messages = dlc_message_handler.get_and_clear_received_messages();
@@ -429,8 +425,6 @@ fn process_incoming_messages(
     for (node_id, message) in messages {
         println!("Processing message from {}", node_id);
         let resp = dlc_manager
-            .lock()
-            .unwrap()
             .on_dlc_message(&message, node_id)
             .expect("Error processing message");
         if let Some(msg) = resp {
@@ -83,6 +83,8 @@ impl CustomMessageReader for DlcMessageHandler {
             dlc_messages::OFFER_TYPE => DlcMessage::Offer(Readable::read(&mut buffer)?),
             dlc_messages::ACCEPT_TYPE => DlcMessage::Accept(Readable::read(&mut buffer)?),
             dlc_messages::SIGN_TYPE => DlcMessage::Sign(Readable::read(&mut buffer)?),
+            dlc_messages::RENEW_OFFER_TYPE => DlcMessage::RenewOffer(Readable::read(&mut buffer)?),
+            dlc_messages::REESTABLISH_TYPE => DlcMessage::Reestablish(Readable::read(&mut buffer)?),
             _ => return Ok(None),
         };
 
@@ -146,7 +148,7 @@ async fn main() {
     oracles.insert(oracle.get_public_key(), Box::new(oracle));
 
     // Instantiate a DlcManager.
-    let dlc_manager = Arc::new(Mutex::new(dlc_manager::manager::Manager::new(
+    let dlc_manager = Arc::new(dlc_manager::manager::Manager::new(
         bitcoind_provider.clone(),
         bitcoind_provider.clone(),
         Box::new(
@@ -155,7 +157,7 @@ async fn main() {
         ),
         oracles,
         Arc::new(dlc_manager::SystemTimeProvider {}),
-    )));
+    ));
 
     let dlc_data_dir = format!("{}/.dlc", config.storage_dir_path);
     let logger = Arc::new(FilesystemLogger::new(dlc_data_dir.clone()));